@@ -0,0 +1,178 @@
+//! ALSA card information
+//!
+//! Collects ALSA card/device numbers and driver information from the
+//! properties of PipeWire devices and nodes, and reads the active hardware
+//! parameters from /proc/asound. Helps correlating PipeWire objects with
+//! `aplay -l` output.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::pwcli::PwObject;
+
+/// ALSA information of a PipeWire device
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AlsaCardInfo {
+    pub device_id: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub card: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub card_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_card_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mixer_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub components: Option<String>,
+    pub pcms: Vec<AlsaPcmInfo>,
+}
+
+/// ALSA PCM used by a PipeWire node
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AlsaPcmInfo {
+    pub node_id: u32,
+    pub node_name: String,
+    /// ALSA device string, e.g. "hw:0,0"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub card: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<u32>,
+    /// "playback" or "capture"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<String>,
+    /// Active hardware parameters, None if the PCM is closed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hw_params: Option<BTreeMap<String, String>>,
+}
+
+/// Check if a device is provided by the ALSA plugin
+pub fn is_alsa_device(obj: &PwObject) -> bool {
+    obj.get("device.api") == Some("alsa")
+}
+
+fn prop_u32(obj: &PwObject, keys: &[&str]) -> Option<u32> {
+    keys.iter().find_map(|k| obj.get(k).and_then(|v| v.parse().ok()))
+}
+
+fn prop_string(obj: &PwObject, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|k| obj.get(k)).map(|s| s.to_string())
+}
+
+/// Parse /proc/asound/cardN/pcmMx/sub0/hw_params
+///
+/// Returns None if the PCM is closed.
+pub fn parse_hw_params(content: &str) -> Option<BTreeMap<String, String>> {
+    if content.trim() == "closed" {
+        return None;
+    }
+    let params: BTreeMap<String, String> = content
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            Some((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect();
+    if params.is_empty() {
+        None
+    } else {
+        Some(params)
+    }
+}
+
+/// Read the active hardware parameters of a PCM
+fn read_hw_params(card: u32, device: u32, stream: &str) -> Option<BTreeMap<String, String>> {
+    let suffix = if stream == "capture" { 'c' } else { 'p' };
+    let path = format!("/proc/asound/card{}/pcm{}{}/sub0/hw_params", card, device, suffix);
+    std::fs::read_to_string(path).ok().and_then(|c| parse_hw_params(&c))
+}
+
+/// Build PCM info for a node
+pub fn pcm_info(node: &PwObject) -> AlsaPcmInfo {
+    let card = prop_u32(node, &["api.alsa.pcm.card", "alsa.card"]);
+    let device = prop_u32(node, &["api.alsa.pcm.device", "alsa.device"]);
+    let stream = prop_string(node, &["api.alsa.pcm.stream"]).or_else(|| {
+        node.media_class().map(|c| {
+            if c.contains("Source") { "capture".to_string() } else { "playback".to_string() }
+        })
+    });
+
+    let hw_params = match (card, device, stream.as_deref()) {
+        (Some(c), Some(d), Some(s)) => read_hw_params(c, d, s),
+        _ => None,
+    };
+
+    AlsaPcmInfo {
+        node_id: node.id,
+        node_name: node.display_name(),
+        path: prop_string(node, &["api.alsa.path"]),
+        card,
+        device,
+        stream,
+        hw_params,
+    }
+}
+
+/// Build ALSA info for a device and its nodes
+pub fn card_info(device: &PwObject, nodes: &[PwObject]) -> AlsaCardInfo {
+    let id_str = device.id.to_string();
+    AlsaCardInfo {
+        device_id: device.id,
+        card: prop_u32(device, &["alsa.card", "api.alsa.card"]),
+        card_name: prop_string(device, &["alsa.card_name", "api.alsa.card.name"]),
+        long_card_name: prop_string(device, &["alsa.long_card_name", "api.alsa.card.longname"]),
+        driver: prop_string(device, &["alsa.driver_name"]),
+        mixer_name: prop_string(device, &["alsa.mixer_name"]),
+        components: prop_string(device, &["alsa.components"]),
+        pcms: nodes
+            .iter()
+            .filter(|n| n.get("device.id") == Some(id_str.as_str()))
+            .map(pcm_info)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hw_params() {
+        let content = "access: RW_INTERLEAVED\nformat: S32_LE\nsubformat: STD\nchannels: 2\nrate: 48000 (48000/1)\nperiod_size: 1024\nbuffer_size: 8192\n";
+        let params = parse_hw_params(content).unwrap();
+        assert_eq!(params["format"], "S32_LE");
+        assert_eq!(params["rate"], "48000 (48000/1)");
+        assert_eq!(parse_hw_params("closed\n"), None);
+    }
+
+    #[test]
+    fn test_card_info() {
+        let device = PwObject::new(56, "Device", &[
+            ("device.api", "alsa"),
+            ("alsa.card", "0"),
+            ("alsa.card_name", "snd_rpi_hifiberry_dacplus"),
+            ("alsa.driver_name", "snd_soc_pcm512x"),
+        ]);
+        let nodes = vec![
+            PwObject::new(81, "Node", &[
+                ("device.id", "56"),
+                ("node.name", "alsa_output.platform-soc_sound.stereo-fallback"),
+                ("api.alsa.path", "front:0"),
+                ("api.alsa.pcm.card", "0"),
+                ("api.alsa.pcm.device", "0"),
+                ("api.alsa.pcm.stream", "playback-does-not-exist"),
+            ]),
+            PwObject::new(90, "Node", &[("device.id", "57"), ("node.name", "other")]),
+        ];
+        let info = card_info(&device, &nodes);
+        assert!(is_alsa_device(&device));
+        assert_eq!(info.card, Some(0));
+        assert_eq!(info.driver.as_deref(), Some("snd_soc_pcm512x"));
+        assert_eq!(info.pcms.len(), 1);
+        assert_eq!(info.pcms[0].path.as_deref(), Some("front:0"));
+        assert_eq!(info.pcms[0].device, Some(0));
+    }
+}
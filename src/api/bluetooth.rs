@@ -0,0 +1,101 @@
+//! Bluetooth handlers
+//!
+//! Battery level, codec and profile switching for Bluetooth devices.
+
+use axum::{
+    extract::Path,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api_server::ApiError;
+use crate::bluetooth::{self, BluetoothInfo, BluetoothProfile};
+use crate::pwcli;
+
+/// Response for GET /api/v1/bluetooth
+#[derive(Debug, Serialize)]
+pub struct BluetoothListResponse {
+    pub devices: Vec<BluetoothInfo>,
+}
+
+/// Response for GET /api/v1/bluetooth/:id
+#[derive(Debug, Serialize)]
+pub struct BluetoothDeviceResponse {
+    #[serde(flatten)]
+    pub info: BluetoothInfo,
+    pub profiles: Vec<BluetoothProfile>,
+}
+
+/// Request body for PUT /api/v1/bluetooth/:id/profile
+#[derive(Debug, Deserialize)]
+pub struct SetProfileRequest {
+    /// Profile name (e.g. "a2dp-sink", "headset-head-unit") or index
+    pub profile: String,
+}
+
+/// Load all Bluetooth devices with their info
+fn load_devices() -> Result<Vec<BluetoothInfo>, String> {
+    let devices = pwcli::list_devices()?;
+    let nodes = pwcli::list_nodes()?;
+    Ok(devices
+        .iter()
+        .filter(|d| bluetooth::is_bluetooth(d))
+        .map(|d| bluetooth::device_info(d, &nodes))
+        .collect())
+}
+
+async fn load_device(id: u32) -> Result<BluetoothInfo, ApiError> {
+    crate::worker::run(load_devices)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list Bluetooth devices: {}", e)))?
+        .into_iter()
+        .find(|d| d.id == id)
+        .ok_or_else(|| ApiError::NotFound(format!("Bluetooth device {} not found", id)))
+}
+
+/// List Bluetooth devices
+/// GET /api/v1/bluetooth
+pub async fn list_devices() -> Result<Json<BluetoothListResponse>, ApiError> {
+    let devices = crate::worker::run(load_devices)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list Bluetooth devices: {}", e)))?;
+
+    Ok(Json(BluetoothListResponse { devices }))
+}
+
+/// Get a Bluetooth device with its available profiles
+/// GET /api/v1/bluetooth/:id
+pub async fn get_device(Path(id): Path<u32>) -> Result<Json<BluetoothDeviceResponse>, ApiError> {
+    let info = load_device(id).await?;
+
+    let profiles = crate::worker::run(move || bluetooth::list_profiles(id))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list profiles: {}", e)))?;
+
+    Ok(Json(BluetoothDeviceResponse { info, profiles }))
+}
+
+/// Switch the profile of a Bluetooth device (e.g. A2DP vs. HFP)
+/// PUT /api/v1/bluetooth/:id/profile
+pub async fn set_profile(
+    Path(id): Path<u32>,
+    Json(request): Json<SetProfileRequest>,
+) -> Result<Json<BluetoothProfile>, ApiError> {
+    load_device(id).await?;
+
+    let profile = crate::worker::run(move || bluetooth::set_profile(id, &request.profile))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| {
+            if e.starts_with("Unknown profile") {
+                ApiError::BadRequest(e)
+            } else {
+                ApiError::Internal(format!("Failed to set profile: {}", e))
+            }
+        })?;
+
+    Ok(Json(profile))
+}
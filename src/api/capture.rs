@@ -0,0 +1,72 @@
+//! Capture streaming handler
+//!
+//! Streams the audio of a node as WAV or FLAC over chunked HTTP, see
+//! [`crate::capture`].
+
+use axum::{
+    body::Body,
+    extract::{Path, Query},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::convert::Infallible;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+
+use crate::api_server::ApiError;
+use crate::capture::{self, CaptureFormat, CaptureSpec};
+use crate::pwcli;
+
+/// Longest capture with a duration
+pub const MAX_STREAM_SECS: u64 = 24 * 3600;
+
+/// Query parameters for the capture stream
+#[derive(Debug, Default, Deserialize)]
+pub struct CaptureQuery {
+    /// wav (default) or flac
+    #[serde(default)]
+    pub format: CaptureFormat,
+    pub rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// Stop after this many seconds (default: until the client disconnects)
+    pub duration: Option<u64>,
+}
+
+/// Find the node and build the capture of it
+pub async fn capture_spec(node_id: u32, query: &CaptureQuery, max_secs: u64) -> Result<CaptureSpec, ApiError> {
+    let rate = query.rate.unwrap_or(capture::DEFAULT_RATE);
+    let channels = query.channels.unwrap_or(capture::DEFAULT_CHANNELS);
+    capture::validate(rate, channels, query.duration, max_secs).map_err(ApiError::BadRequest)?;
+
+    let node = crate::worker::run(move || pwcli::query_object(node_id))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to get node: {}", e)))?
+        .filter(|o| o.is_type("Node"))
+        .ok_or_else(|| ApiError::NotFound(format!("Node {} not found", node_id)))?;
+    let (target, monitor) = capture::capture_target(&node).map_err(ApiError::BadRequest)?;
+
+    Ok(CaptureSpec {
+        target,
+        monitor,
+        format: query.format,
+        rate,
+        channels,
+        duration_secs: query.duration,
+    })
+}
+
+/// Stream the audio of a node (the monitor of a sink)
+/// GET /api/v1/capture/:node_id/stream?format=wav|flac&rate=&channels=&duration=
+pub async fn stream_capture(
+    Path(node_id): Path<u32>,
+    Query(query): Query<CaptureQuery>,
+) -> Result<Response, ApiError> {
+    let spec = capture_spec(node_id, &query, MAX_STREAM_SECS).await?;
+    let content_type = spec.format.content_type();
+    // The capture ends when the client disconnects and the body is dropped
+    let capture = capture::start_capture(spec).map_err(ApiError::Internal)?;
+    let body = Body::from_stream(ReceiverStream::new(capture.audio).map(Ok::<_, Infallible>));
+
+    Ok(([(header::CONTENT_TYPE, content_type), (header::CACHE_CONTROL, "no-store")], body).into_response())
+}
@@ -0,0 +1,137 @@
+//! Client handlers
+//!
+//! List connected PipeWire clients, inspect them together with the nodes
+//! they own, and disconnect misbehaving clients.
+
+use axum::{
+    extract::Path,
+    Json,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::api_server::ApiError;
+use crate::pwcli;
+
+/// Summary of a connected client
+#[derive(Debug, Serialize)]
+pub struct ClientInfo {
+    pub id: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access: Option<String>,
+}
+
+/// Node owned by a client
+#[derive(Debug, Serialize)]
+pub struct ClientNode {
+    pub id: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_class: Option<String>,
+}
+
+/// Client with properties and owned nodes
+#[derive(Debug, Serialize)]
+pub struct ClientDetail {
+    pub id: u32,
+    pub name: String,
+    pub properties: HashMap<String, String>,
+    pub nodes: Vec<ClientNode>,
+}
+
+/// Response for GET /api/v1/clients
+#[derive(Debug, Serialize)]
+pub struct ClientListResponse {
+    pub clients: Vec<ClientInfo>,
+}
+
+fn client_name(obj: &pwcli::PwObject) -> String {
+    obj.get("application.name")
+        .or_else(|| obj.get("client.name"))
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| obj.display_name())
+}
+
+fn to_client_info(obj: &pwcli::PwObject) -> ClientInfo {
+    ClientInfo {
+        id: obj.id,
+        name: client_name(obj),
+        pid: obj.get("application.process.id").and_then(|p| p.parse().ok()),
+        access: obj.get("pipewire.access").map(|s| s.to_string()),
+    }
+}
+
+/// Load a client object, returning NotFound if the id is not a client
+async fn load_client(id: u32) -> Result<pwcli::PwObject, ApiError> {
+    let clients = crate::worker::run(pwcli::list_clients)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list clients: {}", e)))?;
+
+    clients
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or_else(|| ApiError::NotFound(format!("Client {} not found", id)))
+}
+
+/// List all connected clients
+/// GET /api/v1/clients
+pub async fn list_clients() -> Result<Json<ClientListResponse>, ApiError> {
+    let clients = crate::worker::run(pwcli::list_clients)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list clients: {}", e)))?;
+
+    Ok(Json(ClientListResponse {
+        clients: clients.iter().map(to_client_info).collect(),
+    }))
+}
+
+/// Get a client with its properties and the nodes it owns
+/// GET /api/v1/clients/:id
+pub async fn get_client(Path(id): Path<u32>) -> Result<Json<ClientDetail>, ApiError> {
+    let client = load_client(id).await?;
+
+    let nodes = crate::worker::run(pwcli::list_nodes)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list nodes: {}", e)))?;
+
+    let id_str = id.to_string();
+    let nodes = nodes
+        .iter()
+        .filter(|n| n.get("client.id") == Some(id_str.as_str()))
+        .map(|n| ClientNode {
+            id: n.id,
+            name: n.display_name(),
+            media_class: n.media_class().map(|s| s.to_string()),
+        })
+        .collect();
+
+    Ok(Json(ClientDetail {
+        id: client.id,
+        name: client_name(&client),
+        properties: client.properties,
+        nodes,
+    }))
+}
+
+/// Disconnect a client
+/// DELETE /api/v1/clients/:id
+pub async fn disconnect_client(Path(id): Path<u32>) -> Result<Json<serde_json::Value>, ApiError> {
+    let client = load_client(id).await?;
+    let name = client_name(&client);
+
+    crate::worker::run(move || pwcli::destroy_object(id))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to disconnect client: {}", e)))?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": format!("Disconnected client {} ({})", id, name)
+    })))
+}
@@ -0,0 +1,173 @@
+//! Clock settings handlers
+//!
+//! Reads and writes the quantum and sample rate overrides in the PipeWire
+//! `settings` metadata, to tune latency vs. stability at runtime.
+
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api_server::ApiError;
+use crate::pwmetadata::{self, MetadataEntry};
+
+/// Name of the PipeWire settings metadata object
+pub(crate) const SETTINGS_METADATA: &str = "settings";
+
+/// Valid range for clock.force-quantum (0 disables the override)
+const QUANTUM_RANGE: std::ops::RangeInclusive<u32> = 16..=8192;
+/// Valid range for clock.force-rate (0 disables the override)
+pub(crate) const RATE_RANGE: std::ops::RangeInclusive<u32> = 8000..=768000;
+
+/// Current clock settings
+#[derive(Debug, Serialize, Default, PartialEq)]
+pub struct ClockSettings {
+    pub rate: Option<u32>,
+    pub allowed_rates: Vec<u32>,
+    pub quantum: Option<u32>,
+    pub min_quantum: Option<u32>,
+    pub max_quantum: Option<u32>,
+    /// Forced quantum, 0 if not forced
+    pub force_quantum: u32,
+    /// Forced sample rate, 0 if not forced
+    pub force_rate: u32,
+}
+
+/// Request body for PUT /api/v1/settings/clock
+#[derive(Debug, Deserialize)]
+pub struct SetClockRequest {
+    /// Quantum to force, 0 to reset
+    pub force_quantum: Option<u32>,
+    /// Sample rate to force, 0 to reset
+    pub force_rate: Option<u32>,
+}
+
+fn entry_u32(entries: &[MetadataEntry], key: &str) -> Option<u32> {
+    entries
+        .iter()
+        .find(|e| e.subject == 0 && e.key == key)
+        .and_then(|e| match &e.value {
+            Value::String(s) => s.trim().parse().ok(),
+            Value::Number(n) => n.as_u64().map(|n| n as u32),
+            _ => None,
+        })
+}
+
+/// Parse clock settings from the entries of the settings metadata
+pub fn clock_settings_from_entries(entries: &[MetadataEntry]) -> ClockSettings {
+    let allowed_rates = entries
+        .iter()
+        .find(|e| e.subject == 0 && e.key == "clock.allowed-rates")
+        .map(|e| match &e.value {
+            // "[ 44100 48000 ]" or "[ 44100, 48000 ]"
+            Value::String(s) => s
+                .split(|c: char| !c.is_ascii_digit())
+                .filter_map(|p| p.parse().ok())
+                .collect(),
+            Value::Array(a) => a.iter().filter_map(|v| v.as_u64().map(|n| n as u32)).collect(),
+            _ => Vec::new(),
+        })
+        .unwrap_or_default();
+
+    ClockSettings {
+        rate: entry_u32(entries, "clock.rate"),
+        allowed_rates,
+        quantum: entry_u32(entries, "clock.quantum"),
+        min_quantum: entry_u32(entries, "clock.min-quantum"),
+        max_quantum: entry_u32(entries, "clock.max-quantum"),
+        force_quantum: entry_u32(entries, "clock.force-quantum").unwrap_or(0),
+        force_rate: entry_u32(entries, "clock.force-rate").unwrap_or(0),
+    }
+}
+
+async fn read_clock_settings() -> Result<ClockSettings, ApiError> {
+    let entries = crate::worker::run(|| pwmetadata::get_metadata(SETTINGS_METADATA))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to read settings metadata: {}", e)))?;
+
+    Ok(clock_settings_from_entries(&entries))
+}
+
+/// Get clock settings
+/// GET /api/v1/settings/clock
+pub async fn get_clock() -> Result<Json<ClockSettings>, ApiError> {
+    Ok(Json(read_clock_settings().await?))
+}
+
+/// Set forced quantum and/or sample rate
+/// PUT /api/v1/settings/clock
+pub async fn set_clock(Json(request): Json<SetClockRequest>) -> Result<Json<ClockSettings>, ApiError> {
+    if request.force_quantum.is_none() && request.force_rate.is_none() {
+        return Err(ApiError::BadRequest("Specify force_quantum and/or force_rate".to_string()));
+    }
+    if let Some(q) = request.force_quantum {
+        if q != 0 && !QUANTUM_RANGE.contains(&q) {
+            return Err(ApiError::BadRequest(format!(
+                "force_quantum must be 0 or between {} and {}",
+                QUANTUM_RANGE.start(), QUANTUM_RANGE.end()
+            )));
+        }
+    }
+    if let Some(r) = request.force_rate {
+        if r != 0 && !RATE_RANGE.contains(&r) {
+            return Err(ApiError::BadRequest(format!(
+                "force_rate must be 0 or between {} and {}",
+                RATE_RANGE.start(), RATE_RANGE.end()
+            )));
+        }
+    }
+
+    crate::worker::run(move || -> Result<(), String> {
+        if let Some(q) = request.force_quantum {
+            pwmetadata::set_metadata(SETTINGS_METADATA, 0, "clock.force-quantum", &Value::from(q))?;
+        }
+        if let Some(r) = request.force_rate {
+            pwmetadata::set_metadata(SETTINGS_METADATA, 0, "clock.force-rate", &Value::from(r))?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Failed to set clock settings: {}", e)))?;
+
+    Ok(Json(read_clock_settings().await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &str, value: &str) -> MetadataEntry {
+        MetadataEntry {
+            subject: 0,
+            key: key.to_string(),
+            value: Value::String(value.to_string()),
+            value_type: None,
+        }
+    }
+
+    #[test]
+    fn test_clock_settings_from_entries() {
+        let entries = vec![
+            entry("clock.rate", "48000"),
+            entry("clock.allowed-rates", "[ 44100 48000 ]"),
+            entry("clock.quantum", "1024"),
+            entry("clock.min-quantum", "32"),
+            entry("clock.max-quantum", "2048"),
+            entry("clock.force-quantum", "256"),
+            entry("clock.force-rate", "0"),
+        ];
+        let settings = clock_settings_from_entries(&entries);
+        assert_eq!(settings.rate, Some(48000));
+        assert_eq!(settings.allowed_rates, vec![44100, 48000]);
+        assert_eq!(settings.quantum, Some(1024));
+        assert_eq!(settings.force_quantum, 256);
+        assert_eq!(settings.force_rate, 0);
+    }
+
+    #[test]
+    fn test_clock_settings_missing_entries() {
+        let settings = clock_settings_from_entries(&[]);
+        assert_eq!(settings, ClockSettings::default());
+    }
+}
@@ -0,0 +1,46 @@
+//! Combined sink handler
+//!
+//! Creates a sink that plays to several sinks, see [`crate::combine`].
+
+use axum::Json;
+use serde::Deserialize;
+
+use crate::api_server::ApiError;
+use crate::combine::{self, CombinedSink};
+
+fn default_name() -> String {
+    "combined_sink".to_string()
+}
+
+/// Request body for POST /api/v1/combine-sinks
+#[derive(Debug, Deserialize)]
+pub struct CombineSinksRequest {
+    /// node.name of the new sink
+    #[serde(default = "default_name")]
+    pub name: String,
+    /// node.name of the sinks to play to
+    pub sinks: Vec<String>,
+}
+
+/// Create a combined sink
+/// POST /api/v1/combine-sinks
+pub async fn combine_sinks(Json(request): Json<CombineSinksRequest>) -> Result<Json<CombinedSink>, ApiError> {
+    if request.sinks.is_empty() {
+        return Err(ApiError::BadRequest("At least one sink is required".to_string()));
+    }
+    if request.name.is_empty() || request.name.contains(['"', ' ', '}']) {
+        return Err(ApiError::BadRequest(format!("Invalid sink name '{}'", request.name)));
+    }
+
+    crate::worker::run(move || combine::create_combined_sink(&request.name, &request.sinks))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map(Json)
+        .map_err(|e| {
+            if e.contains("not found") || e.contains("already exists") {
+                ApiError::BadRequest(e)
+            } else {
+                ApiError::Internal(format!("Failed to create combined sink: {}", e))
+            }
+        })
+}
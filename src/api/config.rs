@@ -0,0 +1,52 @@
+//! Configuration handlers
+
+use axum::{extract::State, Json};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::config_validation::{self, ConfigKind, FileReport, ValidationReport};
+use crate::server_config::ServerConfig;
+
+/// Get the effective server configuration
+/// GET /api/v1/config
+pub async fn get_config(State(state): State<Arc<AppState>>) -> Json<ServerConfig> {
+    Json(state.get_config())
+}
+
+/// Optional request body for POST /api/v1/config/validate
+///
+/// Validates the given content instead of the files on disk.
+#[derive(Debug, Deserialize)]
+pub struct ValidateContentRequest {
+    pub kind: ConfigKind,
+    pub content: String,
+}
+
+/// Validate configuration files
+/// POST /api/v1/config/validate
+pub async fn validate_config(
+    body: Option<Json<ValidateContentRequest>>,
+) -> Result<Json<ValidationReport>, ApiError> {
+    let report = match body {
+        Some(Json(request)) => {
+            let (rules, issues) = config_validation::validate_content(request.kind, &request.content);
+            let file = FileReport {
+                kind: request.kind,
+                path: "(request)".to_string(),
+                exists: true,
+                rules,
+                issues,
+            };
+            ValidationReport {
+                valid: file.is_valid(),
+                files: vec![file],
+            }
+        }
+        None => crate::worker::run(config_validation::validate_all)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?,
+    };
+
+    Ok(Json(report))
+}
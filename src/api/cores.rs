@@ -0,0 +1,49 @@
+//! Cores handler
+//!
+//! Lists the PipeWire instances served under `/api/v1/cores/<name>/...`,
+//! see [`crate::remote`].
+
+use axum::Json;
+use serde::Serialize;
+
+use crate::api_server::ApiError;
+use crate::remote;
+
+/// A PipeWire instance
+#[derive(Debug, Serialize)]
+pub struct CoreInfo {
+    pub name: String,
+    /// PIPEWIRE_REMOTE of the core, None for the default instance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    /// Whether the instance answers
+    pub connected: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// Response for GET /api/v1/cores
+#[derive(Debug, Serialize)]
+pub struct CoresResponse {
+    pub cores: Vec<CoreInfo>,
+    /// Endpoints available under /api/v1/cores/<name>
+    pub routes: Vec<&'static str>,
+}
+
+/// List the default and the configured PipeWire instances
+/// GET /api/v1/cores
+pub async fn list_cores() -> Result<Json<CoresResponse>, ApiError> {
+    let cores = crate::worker::run(|| {
+        std::iter::once((remote::DEFAULT_CORE.to_string(), None))
+            .chain(remote::cores().into_iter().map(|(name, remote)| (name, Some(remote))))
+            .map(|(name, remote)| {
+                let version = remote::run_with(remote.clone(), crate::status::pipewire_version);
+                CoreInfo { name, remote, connected: version.is_some(), version }
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?;
+
+    Ok(Json(CoresResponse { cores, routes: remote::CORE_ROUTES.to_vec() }))
+}
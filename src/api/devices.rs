@@ -0,0 +1,94 @@
+//! Device handlers (ALSA card info, volume of device routes)
+
+use axum::{
+    extract::Path,
+    Json,
+};
+
+use crate::alsa::{self, AlsaCardInfo};
+use crate::api_server::ApiError;
+use crate::pwcli;
+use crate::route_volume::{RouteVolume, SetRouteVolumeRequest};
+use crate::volume_ops;
+
+/// Get ALSA card information for a device
+/// GET /api/v1/devices/:id/alsa
+///
+/// If `id` refers to a node, the device the node belongs to is used.
+pub async fn get_alsa_info(Path(id): Path<u32>) -> Result<Json<AlsaCardInfo>, ApiError> {
+    let result = crate::worker::run(move || -> Result<Result<AlsaCardInfo, ApiError>, String> {
+        let devices = pwcli::list_devices()?;
+        let nodes = pwcli::list_nodes()?;
+
+        let device_id = match nodes.iter().find(|n| n.id == id) {
+            Some(node) => match node.get("device.id").and_then(|d| d.parse::<u32>().ok()) {
+                Some(device_id) => device_id,
+                None => return Ok(Err(ApiError::BadRequest(format!("Node {} has no device", id)))),
+            },
+            None => id,
+        };
+
+        let Some(device) = devices.iter().find(|d| d.id == device_id) else {
+            return Ok(Err(ApiError::NotFound(format!("Device {} not found", device_id))));
+        };
+        if !alsa::is_alsa_device(device) {
+            return Ok(Err(ApiError::BadRequest(format!("Device {} is not an ALSA device", device_id))));
+        }
+
+        Ok(Ok(alsa::card_info(device, &nodes)))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Failed to list devices: {}", e)))?;
+
+    result.map(Json)
+}
+
+fn route_not_found(id: u32, index: u32) -> ApiError {
+    ApiError::NotFound(format!("Device {} has no route {}", id, index))
+}
+
+/// Get the volume of one route of a device
+/// GET /api/v1/devices/:id/routes/:index/volume
+pub async fn get_route_volume(Path((id, index)): Path<(u32, u32)>) -> Result<Json<RouteVolume>, ApiError> {
+    let route = crate::worker::run(move || volume_ops::read_device_volume(id, index))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to read routes: {}", e)))?;
+
+    route.map(Json).ok_or_else(|| route_not_found(id, index))
+}
+
+/// Set the volume (and optionally mute) of an active route of a device
+/// PUT /api/v1/devices/:id/routes/:index/volume
+pub async fn set_route_volume(
+    Path((id, index)): Path<(u32, u32)>,
+    Json(request): Json<SetRouteVolumeRequest>,
+) -> Result<Json<RouteVolume>, ApiError> {
+    let result = crate::worker::run(move || -> Result<Result<RouteVolume, ApiError>, String> {
+        let volumes = request.volumes();
+        if volumes.is_empty() && request.mute.is_none() {
+            return Ok(Err(ApiError::BadRequest("Give volume, channels or mute".to_string())));
+        }
+        let Some(route) = volume_ops::read_device_volume(id, index)? else {
+            return Ok(Err(route_not_found(id, index)));
+        };
+        if !route.active {
+            return Ok(Err(ApiError::Conflict(format!(
+                "Route {} ({}) is not active, only active routes have a volume",
+                index, route.name
+            ))));
+        }
+        match volume_ops::write_device_volume(id, &route, &volumes, request.mute) {
+            Err(e) if e.contains("channel volumes") => Ok(Err(ApiError::BadRequest(e))),
+            result => result.map(Ok),
+        }
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Failed to set route volume: {}", e)))?;
+
+    let route = result?;
+    tracing::info!("Route {} of device {} set to volume {:?}", index, id, route.volume);
+    Ok(Json(route))
+}
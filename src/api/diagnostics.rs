@@ -0,0 +1,25 @@
+//! Diagnostics handler
+//!
+//! Runs the self-test in [`crate::diagnostics`].
+
+use axum::{extract::State, Json};
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::diagnostics::{self, DiagnosticsReport};
+
+/// Run the self-test and report each check
+/// POST /api/v1/diagnostics/run
+pub async fn run_diagnostics(State(state): State<Arc<AppState>>) -> Result<Json<DiagnosticsReport>, ApiError> {
+    let modules = state.get_modules();
+    let report = crate::worker::run(move || diagnostics::run(&modules))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?;
+
+    if !report.passed {
+        let failed: Vec<&str> = report.checks.iter().filter(|c| !c.passed).map(|c| c.name.as_str()).collect();
+        tracing::warn!("Diagnostics failed: {}", failed.join(", "));
+    }
+
+    Ok(Json(report))
+}
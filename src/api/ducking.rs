@@ -0,0 +1,16 @@
+//! Ducking handler
+//!
+//! Shows the ducking rules and the nodes whose volume they lowered, see
+//! [`crate::ducking`].
+
+use axum::{extract::State, Json};
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::ducking::DuckingStatus;
+
+/// Get the ducking rules and the ducked nodes
+/// GET /api/v1/ducking
+pub async fn get_ducking(State(state): State<Arc<AppState>>) -> Result<Json<DuckingStatus>, ApiError> {
+    Ok(Json(state.ducking.status()))
+}
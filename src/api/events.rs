@@ -0,0 +1,45 @@
+//! Event history handlers
+//!
+//! Devices and nodes that appeared or disappeared, see [`crate::events`].
+
+use axum::{extract::Query, Json};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+use crate::api_server::ApiError;
+use crate::events::{self, HistoryEntry};
+
+/// Default number of entries returned by GET /api/v1/events/history
+const DEFAULT_LIMIT: usize = 200;
+
+/// Query parameters for GET /api/v1/events/history
+#[derive(Debug, Deserialize)]
+pub struct EventHistoryQuery {
+    /// RFC 3339 time or a duration like "12h" (events of the last 12 hours)
+    pub since: Option<String>,
+    /// Maximum number of entries, the newest ones are returned
+    pub limit: Option<usize>,
+}
+
+/// Response for GET /api/v1/events/history
+#[derive(Debug, Serialize)]
+pub struct EventHistoryResponse {
+    pub events: Vec<HistoryEntry>,
+}
+
+/// Get the devices and nodes that appeared or disappeared, oldest first
+/// GET /api/v1/events/history?since=&limit=
+pub async fn get_event_history(Query(query): Query<EventHistoryQuery>) -> Result<Json<EventHistoryResponse>, ApiError> {
+    let since = match &query.since {
+        Some(since) => Some(super::logs::parse_since(since, SystemTime::now()).map_err(ApiError::BadRequest)?),
+        None => None,
+    };
+
+    let mut events = events::history(since);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if events.len() > limit {
+        events.drain(..events.len() - limit);
+    }
+
+    Ok(Json(EventHistoryResponse { events }))
+}
@@ -0,0 +1,71 @@
+//! History handlers
+//!
+//! Volume history and the audit log of state changes, see [`crate::store`].
+//! Only available with the sqlite state backend.
+
+use axum::{
+    extract::{Path, Query},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api_server::ApiError;
+use crate::store::{AuditEvent, VolumeRecord};
+
+/// Default number of history entries
+const DEFAULT_LIMIT: usize = 100;
+
+/// Query parameters for the history endpoints
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub limit: Option<usize>,
+}
+
+/// Response for GET /api/v1/history/volume/:name
+#[derive(Debug, Serialize)]
+pub struct VolumeHistoryResponse {
+    pub name: String,
+    pub history: Vec<VolumeRecord>,
+}
+
+/// Response for GET /api/v1/history/events
+#[derive(Debug, Serialize)]
+pub struct EventsResponse {
+    pub events: Vec<AuditEvent>,
+}
+
+fn history_error(e: String) -> ApiError {
+    if e.contains("not available") {
+        ApiError::BadRequest(e)
+    } else {
+        ApiError::Internal(format!("Failed to read history: {}", e))
+    }
+}
+
+/// Get the saved volumes of a node, newest first
+/// GET /api/v1/history/volume/:name
+pub async fn get_volume_history(
+    Path(name): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<VolumeHistoryResponse>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let lookup = name.clone();
+    let history = crate::worker::run(move || crate::store::get().volume_history(&lookup, limit))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(history_error)?;
+
+    Ok(Json(VolumeHistoryResponse { name, history }))
+}
+
+/// Get the audit log of state changes, newest first
+/// GET /api/v1/history/events
+pub async fn get_events(Query(query): Query<HistoryQuery>) -> Result<Json<EventsResponse>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let events = crate::worker::run(move || crate::store::get().events(limit))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(history_error)?;
+
+    Ok(Json(EventsResponse { events }))
+}
@@ -3,13 +3,15 @@
 //! This module provides REST API endpoints for managing PipeWire audio links.
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
 };
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
 use crate::api_server::{ApiError, AppState};
+use crate::link_factory;
+use crate::pwcli::{self, PwObject};
 use crate::pwlink;
 
 /// Request to create a link
@@ -19,6 +21,9 @@ pub struct CreateLinkRequest {
     pub output: String,
     /// Input port name (format: "node_name:port_name") or port ID
     pub input: String,
+    /// `passive` and `linger` flags of the link
+    #[serde(flatten)]
+    pub options: pwlink::LinkOptions,
 }
 
 /// Response for link operations
@@ -28,15 +33,28 @@ pub struct LinkResponse {
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub link_id: Option<u32>,
+    /// Set if the session manager reverted the change (see [`crate::reverts`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
 }
 
 /// A port in the list response
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, PartialEq)]
 pub struct PortInfo {
     pub id: u32,
     pub name: String,
     pub node_name: String,
     pub port_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<u32>,
+    /// Channel position (`audio.channel`), e.g. "FL"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// audio, midi or video (from `format.dsp`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media: Option<String>,
+    /// Monitor port of a sink (`port.monitor`)
+    pub monitor: bool,
 }
 
 /// Response for list ports
@@ -45,52 +63,159 @@ pub struct ListPortsResponse {
     pub ports: Vec<PortInfo>,
 }
 
-/// List output ports
-/// GET /api/v1/links/ports/output
-pub async fn list_output_ports(
-    State(_state): State<Arc<AppState>>,
-) -> Result<Json<ListPortsResponse>, ApiError> {
-    let ports = tokio::task::spawn_blocking(|| {
-        pwlink::list_output_ports()
+/// Ports of one node
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct NodePorts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<u32>,
+    pub node_name: String,
+    pub ports: Vec<PortInfo>,
+}
+
+/// Response for list ports with `?group_by=node`
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupedPortsResponse {
+    pub nodes: Vec<NodePorts>,
+}
+
+/// Response for the port listings, flat or grouped by node
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum PortsResponse {
+    List(ListPortsResponse),
+    Grouped(GroupedPortsResponse),
+}
+
+/// Query parameters for the port listings
+#[derive(Debug, Default, Deserialize)]
+pub struct PortsQuery {
+    /// Regex on the node name
+    pub node: Option<String>,
+    /// Channel position, e.g. FL (case-insensitive)
+    pub channel: Option<String>,
+    /// audio, midi or video
+    pub media: Option<String>,
+    /// `node` nests the ports under their node
+    pub group_by: Option<String>,
+    /// Also list the monitor ports of sinks
+    #[serde(default)]
+    pub include_monitors: bool,
+}
+
+impl PortsQuery {
+    /// Check the values of the query
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(ref media) = self.media {
+            if !matches!(media.to_lowercase().as_str(), "audio" | "midi" | "video") {
+                return Err(format!("Invalid media '{}' (use audio, midi or video)", media));
+            }
+        }
+        if let Some(ref group_by) = self.group_by {
+            if group_by != "node" {
+                return Err(format!("Invalid group_by '{}' (use node)", group_by));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether a port passes the filters
+    pub fn matches(&self, port: &PortInfo) -> bool {
+        let same = |value: &Option<String>, wanted: &str| value.as_deref().is_some_and(|v| v.eq_ignore_ascii_case(wanted));
+        (self.include_monitors || !port.monitor)
+            && self.node.as_deref().is_none_or(|n| crate::util::regex_match(n, &port.node_name))
+            && self.channel.as_deref().is_none_or(|c| same(&port.channel, c))
+            && self.media.as_deref().is_none_or(|m| same(&port.media, m))
+    }
+}
+
+/// Build a port entry, with node ID, channel, media and monitor flag from the pw-cli port object
+pub fn port_info(port: &pwlink::PwPort, object: Option<&PwObject>) -> PortInfo {
+    let media = object
+        .map(|o| pwcli::classify_port_format(o.get("format.dsp")))
+        .and_then(|kind| match kind {
+            pwcli::NodeTypeClassification::Audio => Some("audio"),
+            pwcli::NodeTypeClassification::Midi => Some("midi"),
+            pwcli::NodeTypeClassification::Video => Some("video"),
+            _ => None,
+        });
+    PortInfo {
+        id: port.id,
+        name: port.name.clone(),
+        node_name: port.node_name.clone(),
+        port_name: port.port_name.clone(),
+        node_id: object.and_then(|o| o.get("node.id")).and_then(|id| id.parse().ok()),
+        channel: object.and_then(|o| o.get("audio.channel")).map(|c| c.to_string()),
+        media: media.map(|m| m.to_string()),
+        monitor: object.and_then(|o| o.get("port.monitor")) == Some("true"),
+    }
+}
+
+/// Nest ports under their node, in the order the nodes first appear
+pub fn group_by_node(ports: Vec<PortInfo>) -> Vec<NodePorts> {
+    let mut nodes: Vec<NodePorts> = Vec::new();
+    for port in ports {
+        match nodes.iter_mut().find(|n| n.node_name == port.node_name) {
+            Some(node) => node.ports.push(port),
+            None => nodes.push(NodePorts {
+                node_id: port.node_id,
+                node_name: port.node_name.clone(),
+                ports: vec![port],
+            }),
+        }
+    }
+    nodes
+}
+
+async fn list_ports(direction: pwlink::PortDirection, query: PortsQuery) -> Result<Json<PortsResponse>, ApiError> {
+    query.validate().map_err(ApiError::BadRequest)?;
+    let (ports, objects) = crate::worker::run(move || {
+        let ports = pwlink::list_ports(direction)?;
+        // Without the port objects the ports are listed without node ID, channel and media,
+        // and monitor ports cannot be told apart
+        let objects = pwcli::list_ports().unwrap_or_default();
+        Ok::<_, String>((ports, objects))
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
-    .map_err(|e| ApiError::Internal(format!("Failed to list output ports: {}", e)))?;
-
-    let port_infos: Vec<PortInfo> = ports.iter()
-        .map(|p| PortInfo {
-            id: p.id,
-            name: p.name.clone(),
-            node_name: p.node_name.clone(),
-            port_name: p.port_name.clone(),
-        })
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Failed to list ports: {}", e)))?;
+
+    let ports: Vec<PortInfo> = ports
+        .iter()
+        .map(|p| port_info(p, objects.iter().find(|o| o.id == p.id)))
+        .filter(|p| query.matches(p))
         .collect();
 
-    Ok(Json(ListPortsResponse { ports: port_infos }))
+    Ok(Json(match query.group_by {
+        Some(_) => PortsResponse::Grouped(GroupedPortsResponse { nodes: group_by_node(ports) }),
+        None => PortsResponse::List(ListPortsResponse { ports }),
+    }))
+}
+
+/// List output ports
+/// GET /api/v1/links/ports/output?node=&channel=&media=&group_by=node&include_monitors=true
+pub async fn list_output_ports(
+    State(_state): State<Arc<AppState>>,
+    Query(query): Query<PortsQuery>,
+) -> Result<Json<PortsResponse>, ApiError> {
+    list_ports(pwlink::PortDirection::Output, query).await
 }
 
 /// List input ports
-/// GET /api/v1/links/ports/input
+/// GET /api/v1/links/ports/input?node=&channel=&media=&group_by=node&include_monitors=true
 pub async fn list_input_ports(
     State(_state): State<Arc<AppState>>,
-) -> Result<Json<ListPortsResponse>, ApiError> {
-    let ports = tokio::task::spawn_blocking(|| {
-        pwlink::list_input_ports()
-    })
-    .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
-    .map_err(|e| ApiError::Internal(format!("Failed to list input ports: {}", e)))?;
-
-    let port_infos: Vec<PortInfo> = ports.iter()
-        .map(|p| PortInfo {
-            id: p.id,
-            name: p.name.clone(),
-            node_name: p.node_name.clone(),
-            port_name: p.port_name.clone(),
-        })
-        .collect();
+    Query(query): Query<PortsQuery>,
+) -> Result<Json<PortsResponse>, ApiError> {
+    list_ports(pwlink::PortDirection::Input, query).await
+}
 
-    Ok(Json(ListPortsResponse { ports: port_infos }))
+/// Map a failed link operation to 409 (exists), 404 (no such port or link) or 500
+fn link_error(message: String) -> ApiError {
+    match pwlink::error_kind(&message) {
+        pwlink::LinkErrorKind::AlreadyExists => ApiError::Conflict(message),
+        pwlink::LinkErrorKind::NotFound => ApiError::NotFound(message),
+        pwlink::LinkErrorKind::Other => ApiError::Internal(message),
+    }
 }
 
 /// Create a link between two ports
@@ -99,44 +224,28 @@ pub async fn create_link(
     State(_state): State<Arc<AppState>>,
     Json(request): Json<CreateLinkRequest>,
 ) -> Result<Json<LinkResponse>, ApiError> {
-    // Check if it looks like a port ID (all digits)
-    let is_output_id = request.output.chars().all(|c| c.is_ascii_digit());
-    let is_input_id = request.input.chars().all(|c| c.is_ascii_digit());
-
     let output = request.output.clone();
     let input = request.input.clone();
+    let options = request.options;
+    if !options.linger && !link_factory::is_enabled() {
+        return Err(ApiError::BadRequest(
+            "Links that do not linger need native link creation (native_links = true)".to_string(),
+        ));
+    }
 
-    let result = tokio::task::spawn_blocking(move || {
-        let create_result = if is_output_id && is_input_id {
-            // Both are IDs
-            let output_id: u32 = output.parse().map_err(|_| "Invalid output port ID".to_string())?;
-            let input_id: u32 = input.parse().map_err(|_| "Invalid input port ID".to_string())?;
-            pwlink::create_link_by_id(output_id, input_id)
-        } else {
-            // Use names
-            pwlink::create_link(&output, &input)
-        };
-
-        if let Err(e) = create_result {
-            return Err(format!("Failed to create link: {}", e));
-        }
-
-        // Try to find the link ID
-        let link_id = pwlink::find_link(&output, &input)
-            .ok()
-            .flatten()
-            .map(|l| l.id);
-
-        Ok(link_id)
+    let result = crate::worker::run(move || {
+        link_factory::create_link(&output, &input, &options)
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
-    .map_err(|e| ApiError::Internal(e))?;
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(|e| link_error(format!("Failed to create link: {}", e)))?;
 
+    let warning = crate::reverts::check_link(request.output.clone(), request.input.clone(), false).await;
     Ok(Json(LinkResponse {
         status: "ok".to_string(),
         message: format!("Link created: {} -> {}", request.output, request.input),
         link_id: result,
+        warning,
     }))
 }
 
@@ -146,17 +255,27 @@ pub async fn remove_link_by_id(
     State(_state): State<Arc<AppState>>,
     Path(id): Path<u32>,
 ) -> Result<Json<LinkResponse>, ApiError> {
-    tokio::task::spawn_blocking(move || {
-        pwlink::remove_link(id)
+    // The ports of the link, to check whether it comes back
+    let ports = crate::worker::run(move || {
+        let ports = pwlink::list_links()
+            .ok()
+            .and_then(|links| links.into_iter().find(|l| l.id == id))
+            .map(|l| (l.output_port_name, l.input_port_name));
+        pwlink::remove_link(id).map(|_| ports)
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
-    .map_err(|e| ApiError::Internal(format!("Failed to remove link: {}", e)))?;
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(|e| link_error(format!("Failed to remove link: {}", e)))?;
 
+    let warning = match ports {
+        Some((output, input)) => crate::reverts::check_link(output, input, true).await,
+        None => None,
+    };
     Ok(Json(LinkResponse {
         status: "ok".to_string(),
         message: format!("Link {} removed", id),
         link_id: Some(id),
+        warning,
     }))
 }
 
@@ -174,20 +293,100 @@ pub async fn remove_link_by_name(
 ) -> Result<Json<LinkResponse>, ApiError> {
     let output = request.output.clone();
     let input = request.input.clone();
-    tokio::task::spawn_blocking(move || {
+    crate::worker::run(move || {
         pwlink::remove_link_by_name(&output, &input)
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
-    .map_err(|e| ApiError::Internal(format!("Failed to remove link: {}", e)))?;
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(|e| link_error(format!("Failed to remove link: {}", e)))?;
 
+    let warning = crate::reverts::check_link(request.output.clone(), request.input.clone(), true).await;
     Ok(Json(LinkResponse {
         status: "ok".to_string(),
         message: format!("Link removed: {} -> {}", request.output, request.input),
         link_id: None,
+        warning,
     }))
 }
 
+/// Which links of a node to remove
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeLinkDirection {
+    /// Links into the input ports of the node
+    Input,
+    /// Links from the output ports of the node
+    Output,
+    #[default]
+    Both,
+}
+
+/// Query parameters for DELETE /api/v1/links/by-node/:id
+#[derive(Debug, Default, Deserialize)]
+pub struct RemoveNodeLinksQuery {
+    #[serde(default)]
+    pub direction: NodeLinkDirection,
+}
+
+/// Response for DELETE /api/v1/links/by-node/:id
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoveNodeLinksResponse {
+    pub node_id: u32,
+    /// IDs of the removed links
+    pub removed: Vec<u32>,
+    /// Links that could not be removed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+/// Get the IDs of the links touching the ports of a node
+pub fn node_links(links: &[PwObject], node_id: u32, direction: NodeLinkDirection) -> Vec<u32> {
+    let is_node = |link: &PwObject, key: &str| link.get(key).and_then(|v| v.parse::<u32>().ok()) == Some(node_id);
+    links
+        .iter()
+        .filter(|link| {
+            let output = is_node(link, "link.output.node");
+            let input = is_node(link, "link.input.node");
+            match direction {
+                NodeLinkDirection::Input => input,
+                NodeLinkDirection::Output => output,
+                NodeLinkDirection::Both => input || output,
+            }
+        })
+        .map(|link| link.id)
+        .collect()
+}
+
+/// Remove all links of a node, e.g. before rewiring a chain
+/// DELETE /api/v1/links/by-node/:id?direction=input|output|both
+pub async fn remove_links_by_node(
+    State(_state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+    Query(query): Query<RemoveNodeLinksQuery>,
+) -> Result<Json<RemoveNodeLinksResponse>, ApiError> {
+    let result = crate::worker::run(move || -> Result<Result<RemoveNodeLinksResponse, ApiError>, String> {
+        match pwcli::query_object(id)? {
+            Some(obj) if obj.is_type("Node") => {}
+            _ => return Ok(Err(ApiError::NotFound(format!("Node {} not found", id)))),
+        }
+        let mut response = RemoveNodeLinksResponse { node_id: id, removed: Vec::new(), errors: Vec::new() };
+        for link_id in node_links(&pwcli::list_links()?, id, query.direction) {
+            match pwlink::remove_link(link_id) {
+                Ok(()) => response.removed.push(link_id),
+                Err(e) => response.errors.push(format!("Link {}: {}", link_id, e)),
+            }
+        }
+        Ok(Ok(response))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Failed to list links: {}", e)))?;
+
+    let response = result?;
+    tracing::info!("Removed {} link(s) of node {}", response.removed.len(), id);
+    Ok(Json(response))
+}
+
 /// Check if a link exists between two ports
 /// GET /api/v1/links/exists?output=...&input=...
 #[derive(Debug, Clone, Deserialize)]
@@ -209,11 +408,11 @@ pub async fn check_link_exists(
 ) -> Result<Json<LinkExistsResponse>, ApiError> {
     let output = query.output.clone();
     let input = query.input.clone();
-    let link = tokio::task::spawn_blocking(move || {
+    let link = crate::worker::run(move || {
         pwlink::find_link(&output, &input)
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     .map_err(|e| ApiError::Internal(format!("Failed to check link: {}", e)))?;
 
     Ok(Json(LinkExistsResponse {
@@ -221,3 +420,79 @@ pub async fn check_link_exists(
         link_id: link.map(|l| l.id),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(id: u32, output: u32, input: u32) -> PwObject {
+        let (output, input) = (output.to_string(), input.to_string());
+        PwObject::new(id, "PipeWire:Interface:Link", &[("link.output.node", &output), ("link.input.node", &input)])
+    }
+
+    fn port(id: u32, name: &str, channel: &str, format: &str) -> PortInfo {
+        let (node, port_name) = name.split_once(':').unwrap();
+        let monitor = port_name.starts_with("monitor_").to_string();
+        let object = PwObject::new(id, "Port", &[
+            ("node.id", "45"),
+            ("audio.channel", channel),
+            ("format.dsp", format),
+            ("port.monitor", &monitor),
+        ]);
+        let port = pwlink::PwPort { id, name: name.to_string(), node_name: node.to_string(), port_name: port_name.to_string() };
+        port_info(&port, Some(&object))
+    }
+
+    #[test]
+    fn test_port_filters_and_grouping() {
+        let ports = vec![
+            port(67, "speakereq2x2:output_FL", "FL", "32 bit float mono audio"),
+            port(68, "speakereq2x2:output_FR", "FR", "32 bit float mono audio"),
+            port(70, "midi-bridge:capture_0", "", "8 bit raw midi"),
+        ];
+        assert_eq!(ports[0].node_id, Some(45));
+        assert_eq!(ports[2].media.as_deref(), Some("midi"));
+
+        let query = PortsQuery { node: Some("^speakereq".to_string()), channel: Some("fl".to_string()), ..Default::default() };
+        let selected: Vec<u32> = ports.iter().filter(|p| query.matches(p)).map(|p| p.id).collect();
+        assert_eq!(selected, vec![67]);
+        let query = PortsQuery { media: Some("audio".to_string()), ..Default::default() };
+        assert_eq!(ports.iter().filter(|p| query.matches(p)).count(), 2);
+        assert!(PortsQuery { group_by: Some("client".to_string()), ..Default::default() }.validate().is_err());
+
+        let nodes = group_by_node(ports);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].node_name, "speakereq2x2");
+        assert_eq!(nodes[0].ports.len(), 2);
+    }
+
+    #[test]
+    fn test_monitor_ports() {
+        let monitor = port(80, "alsa_output.usb:monitor_FL", "FL", "32 bit float mono audio");
+        let output = port(67, "speakereq2x2:output_FL", "FL", "32 bit float mono audio");
+        assert!(monitor.monitor);
+        assert!(!output.monitor);
+
+        let query = PortsQuery { channel: Some("FL".to_string()), ..Default::default() };
+        assert!(!query.matches(&monitor));
+        assert!(query.matches(&output));
+        let query = PortsQuery { include_monitors: true, ..query };
+        assert!(query.matches(&monitor));
+    }
+
+    #[test]
+    fn test_link_error() {
+        assert!(matches!(link_error("failed to link ports: File exists".to_string()), ApiError::Conflict(_)));
+        assert!(matches!(link_error("Port a:b not found".to_string()), ApiError::NotFound(_)));
+        assert!(matches!(link_error("pw-link timed out".to_string()), ApiError::Internal(_)));
+    }
+
+    #[test]
+    fn test_node_links() {
+        let links = vec![link(90, 41, 45), link(91, 41, 45), link(92, 45, 52), link(93, 60, 52)];
+        assert_eq!(node_links(&links, 45, NodeLinkDirection::Input), vec![90, 91]);
+        assert_eq!(node_links(&links, 45, NodeLinkDirection::Output), vec![92]);
+        assert_eq!(node_links(&links, 45, NodeLinkDirection::Both), vec![90, 91, 92]);
+        assert!(node_links(&links, 99, NodeLinkDirection::Both).is_empty());
+    }
+}
@@ -1,17 +1,146 @@
 //! Listing handlers for PipeWire objects
 //!
 //! Uses pw-cli for simple and reliable object listing.
+//! Listings can be filtered and paginated with query parameters, see [`ListFilter`].
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
 };
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::api_server::{ApiError, AppState};
 use crate::pwcli;
 use super::types::*;
 
+/// Filter and pagination options for listing endpoints
+///
+/// Built from the query string:
+/// - `type=node` - simplified object type (node, port, link, device, ...)
+/// - `media_class=Audio/Sink` - value of the media.class property
+/// - `kind=video` - audio, midi or video: nodes and devices classified by
+///   media.class, ports by their format
+/// - `prop.<key>=<value>` - any property value, e.g. `prop.device.api=alsa`
+/// - `limit=N`, `offset=N` - pagination, applied after filtering
+///
+/// Text comparisons are case-insensitive. Other query parameters are ignored.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ListFilter {
+    pub object_type: Option<String>,
+    pub media_class: Option<String>,
+    pub kind: Option<pwcli::NodeTypeClassification>,
+    pub props: Vec<(String, String)>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl ListFilter {
+    /// Parse filter options from query parameters
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, String> {
+        let parse_number = |name: &str| -> Result<Option<usize>, String> {
+            query
+                .get(name)
+                .map(|v| v.parse::<usize>().map_err(|_| format!("Invalid {} '{}'", name, v)))
+                .transpose()
+        };
+
+        let mut props: Vec<(String, String)> = query
+            .iter()
+            .filter_map(|(k, v)| k.strip_prefix("prop.").map(|key| (key.to_string(), v.clone())))
+            .collect();
+        props.sort();
+
+        let kind = query
+            .get("kind")
+            .map(|v| match v.to_lowercase().as_str() {
+                "audio" => Ok(pwcli::NodeTypeClassification::Audio),
+                "midi" => Ok(pwcli::NodeTypeClassification::Midi),
+                "video" => Ok(pwcli::NodeTypeClassification::Video),
+                _ => Err(format!("Invalid kind '{}' (use audio, midi or video)", v)),
+            })
+            .transpose()?;
+
+        Ok(Self {
+            object_type: query.get("type").cloned(),
+            media_class: query.get("media_class").cloned(),
+            kind,
+            props,
+            limit: parse_number("limit")?,
+            offset: parse_number("offset")?.unwrap_or(0),
+        })
+    }
+
+    /// Check whether an object passes the filters (ignoring pagination)
+    pub fn matches(&self, obj: &pwcli::PwObject) -> bool {
+        if let Some(ref object_type) = self.object_type {
+            let simplified = pwcli::simplify_type(&obj.object_type);
+            if !simplified.eq_ignore_ascii_case(object_type) && !obj.object_type.eq_ignore_ascii_case(object_type) {
+                return false;
+            }
+        }
+
+        if let Some(ref media_class) = self.media_class {
+            if !obj.media_class().map(|m| m.eq_ignore_ascii_case(media_class)).unwrap_or(false) {
+                return false;
+            }
+        }
+
+        if let Some(kind) = self.kind {
+            let classification = if pwcli::simplify_type(&obj.object_type) == "port" {
+                pwcli::classify_port_format(obj.get("format.dsp"))
+            } else {
+                pwcli::classify_media_class(obj.media_class())
+            };
+            if classification != kind {
+                return false;
+            }
+        }
+
+        self.props
+            .iter()
+            .all(|(key, value)| obj.get(key).map(|v| v.eq_ignore_ascii_case(value)).unwrap_or(false))
+    }
+
+    /// Filter and paginate objects, returning the number of matches before pagination
+    pub fn apply(&self, objects: Vec<pwcli::PwObject>) -> (usize, Vec<pwcli::PwObject>) {
+        let matching: Vec<pwcli::PwObject> = objects.into_iter().filter(|o| self.matches(o)).collect();
+        let total = matching.len();
+        let page = matching
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        (total, page)
+    }
+}
+
+/// Groups of nodes for device pickers, selected by media.class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeGroup {
+    /// `Audio/Sink` and `Audio/Duplex` nodes
+    Sinks,
+    /// `Audio/Source`, `Audio/Source/Virtual` and `Audio/Duplex` nodes
+    Sources,
+    /// Application streams (`Stream/*`)
+    Streams,
+}
+
+impl NodeGroup {
+    /// Check whether an object is a node of this group
+    pub fn matches(self, obj: &pwcli::PwObject) -> bool {
+        if obj.object_type != "Node" {
+            return false;
+        }
+        let media_class = obj.media_class().unwrap_or_default().to_lowercase();
+        match self {
+            NodeGroup::Sinks => media_class.starts_with("audio/sink") || media_class == "audio/duplex",
+            NodeGroup::Sources => media_class.starts_with("audio/source") || media_class == "audio/duplex",
+            NodeGroup::Streams => media_class.starts_with("stream/"),
+        }
+    }
+}
+
 /// Convert NodeTypeClassification to string for API response
 fn classification_to_string(classification: pwcli::NodeTypeClassification) -> String {
     match classification {
@@ -59,22 +188,71 @@ fn to_api_object(obj: &pwcli::PwObject) -> PipeWireObject {
 }
 
 /// List all PipeWire objects
-pub async fn list_all(State(_state): State<Arc<AppState>>) -> Result<Json<ListResponse>, ApiError> {
-    let objects = tokio::task::spawn_blocking(|| {
+/// GET /api/v1/ls?type=&media_class=&prop.<key>=&limit=&offset=
+pub async fn list_all(
+    State(_state): State<Arc<AppState>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<ListResponse>, ApiError> {
+    let filter = ListFilter::from_query(&query).map_err(ApiError::BadRequest)?;
+
+    let objects = crate::worker::run(|| {
         pwcli::list_all()
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     .map_err(|e| ApiError::Internal(format!("Failed to list objects: {}", e)))?;
 
+    let (total, objects) = filter.apply(objects);
     let api_objects: Vec<PipeWireObject> = objects.iter()
         .map(to_api_object)
         .collect();
 
-    Ok(Json(ListResponse { objects: api_objects }))
+    Ok(Json(ListResponse { objects: api_objects, total }))
+}
+
+
+/// List the nodes of a group, with the same filters as `list_all`
+async fn list_group(group: NodeGroup, query: HashMap<String, String>) -> Result<Json<ListResponse>, ApiError> {
+    let filter = ListFilter::from_query(&query).map_err(ApiError::BadRequest)?;
+
+    let objects = crate::worker::run(pwcli::list_nodes)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list nodes: {}", e)))?;
+
+    let nodes = objects.into_iter().filter(|o| group.matches(o)).collect();
+    let (total, nodes) = filter.apply(nodes);
+    let api_objects = nodes.iter().map(to_api_object).collect();
+
+    Ok(Json(ListResponse { objects: api_objects, total }))
 }
 
+/// List sink nodes
+/// GET /api/v1/ls/sinks
+pub async fn list_sinks(
+    State(_state): State<Arc<AppState>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<ListResponse>, ApiError> {
+    list_group(NodeGroup::Sinks, query).await
+}
 
+/// List source nodes
+/// GET /api/v1/ls/sources
+pub async fn list_sources(
+    State(_state): State<Arc<AppState>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<ListResponse>, ApiError> {
+    list_group(NodeGroup::Sources, query).await
+}
+
+/// List application streams
+/// GET /api/v1/ls/streams
+pub async fn list_streams(
+    State(_state): State<Arc<AppState>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<ListResponse>, ApiError> {
+    list_group(NodeGroup::Streams, query).await
+}
 
 /// Get a single object by ID
 pub async fn get_object_by_id(
@@ -87,11 +265,11 @@ pub async fn get_object_by_id(
     }
 
     // If not in cache, try to get it directly from pw-cli
-    let result = tokio::task::spawn_blocking(move || {
+    let result = crate::worker::run(move || {
         pwcli::get_object(id)
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?;
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?;
 
     match result {
         Ok(Some(obj)) => Ok(Json(to_api_object(&obj))),
@@ -112,3 +290,78 @@ pub async fn refresh_cache(State(state): State<Arc<AppState>>) -> Result<Json<se
         "object_count": count
     })))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pwcli::PwObject;
+
+    fn objects() -> Vec<pwcli::PwObject> {
+        vec![
+            PwObject::new(30, "Node", &[("media.class", "Audio/Sink"), ("device.api", "alsa")]),
+            PwObject::new(31, "Node", &[("media.class", "Audio/Source"), ("device.api", "alsa")]),
+            PwObject::new(40, "Node", &[("media.class", "Audio/Sink"), ("device.api", "bluez5")]),
+            PwObject::new(50, "Port", &[("port.direction", "in")]),
+            PwObject::new(60, "Node", &[("media.class", "Video/Source"), ("device.api", "v4l2")]),
+            PwObject::new(61, "Port", &[("port.direction", "out"), ("format.dsp", "32 bit float RGBA video")]),
+        ]
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_filter_by_type_and_media_class() {
+        let filter = ListFilter::from_query(&query(&[("type", "node"), ("media_class", "audio/sink")])).unwrap();
+        let (total, page) = filter.apply(objects());
+        assert_eq!(total, 2);
+        assert_eq!(page.iter().map(|o| o.id).collect::<Vec<_>>(), vec![30, 40]);
+    }
+
+    #[test]
+    fn test_filter_by_property() {
+        let filter = ListFilter::from_query(&query(&[("prop.device.api", "alsa")])).unwrap();
+        let (total, page) = filter.apply(objects());
+        assert_eq!(total, 2);
+        assert_eq!(page.iter().map(|o| o.id).collect::<Vec<_>>(), vec![30, 31]);
+    }
+
+    #[test]
+    fn test_pagination() {
+        let filter = ListFilter::from_query(&query(&[("limit", "2"), ("offset", "1")])).unwrap();
+        let (total, page) = filter.apply(objects());
+        assert_eq!(total, 6);
+        assert_eq!(page.iter().map(|o| o.id).collect::<Vec<_>>(), vec![31, 40]);
+    }
+
+    #[test]
+    fn test_filter_by_kind() {
+        let filter = ListFilter::from_query(&query(&[("kind", "video")])).unwrap();
+        let (total, page) = filter.apply(objects());
+        assert_eq!(total, 2);
+        assert_eq!(page.iter().map(|o| o.id).collect::<Vec<_>>(), vec![60, 61]);
+        assert!(ListFilter::from_query(&query(&[("kind", "pictures")])).is_err());
+    }
+
+    #[test]
+    fn test_node_groups() {
+        let objects = [
+            PwObject::new(30, "Node", &[("media.class", "Audio/Sink")]),
+            PwObject::new(31, "Node", &[("media.class", "Audio/Source/Virtual")]),
+            PwObject::new(32, "Node", &[("media.class", "Audio/Duplex")]),
+            PwObject::new(33, "Node", &[("media.class", "Stream/Output/Audio")]),
+            PwObject::new(34, "Node", &[("media.class", "Video/Source")]),
+            PwObject::new(35, "Device", &[("media.class", "Audio/Device")]),
+        ];
+        let ids = |group: NodeGroup| objects.iter().filter(|o| group.matches(o)).map(|o| o.id).collect::<Vec<_>>();
+        assert_eq!(ids(NodeGroup::Sinks), vec![30, 32]);
+        assert_eq!(ids(NodeGroup::Sources), vec![31, 32]);
+        assert_eq!(ids(NodeGroup::Streams), vec![33]);
+    }
+
+    #[test]
+    fn test_invalid_limit() {
+        assert!(ListFilter::from_query(&query(&[("limit", "many")])).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! Log access handlers
+//!
+//! Recent log events from the in-memory buffer and runtime log level
+//! changes, see [`crate::logging`].
+
+use axum::{extract::Query, Json};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::Level;
+
+use crate::api_server::ApiError;
+use crate::logging::{self, LogEntry};
+
+/// Default number of entries returned by GET /api/v1/logs
+const DEFAULT_LIMIT: usize = 200;
+
+/// Query parameters for GET /api/v1/logs
+#[derive(Debug, Deserialize)]
+pub struct LogsQuery {
+    /// Minimum level (error, warn, info, debug, trace)
+    pub level: Option<String>,
+    /// RFC 3339 time or a duration like "5m" (events of the last 5 minutes)
+    pub since: Option<String>,
+    /// Maximum number of entries, the newest ones are returned
+    pub limit: Option<usize>,
+}
+
+/// Response for GET /api/v1/logs
+#[derive(Debug, Serialize)]
+pub struct LogsResponse {
+    /// Active log filter
+    pub filter: String,
+    pub entries: Vec<LogEntry>,
+}
+
+/// Request body for PUT /api/v1/logs/level
+#[derive(Debug, Deserialize)]
+pub struct SetLevelRequest {
+    /// Level (`debug`) or env-filter directives (`warn,pw_api::speakereq=debug`)
+    pub level: String,
+}
+
+/// Response for PUT /api/v1/logs/level
+#[derive(Debug, Serialize)]
+pub struct SetLevelResponse {
+    pub filter: String,
+}
+
+/// Parse the `since` parameter into milliseconds since the Unix epoch
+pub(crate) fn parse_since(since: &str, now: SystemTime) -> Result<u64, String> {
+    let time = match humantime::parse_rfc3339_weak(since) {
+        Ok(time) => time,
+        Err(_) => {
+            let ago = humantime::parse_duration(since)
+                .map_err(|_| format!("Invalid since '{}', use an RFC 3339 time or a duration like 5m", since))?;
+            now.checked_sub(ago).unwrap_or(UNIX_EPOCH)
+        }
+    };
+    Ok(time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_millis() as u64)
+}
+
+/// Get recent log events
+/// GET /api/v1/logs?level=&since=&limit=
+pub async fn get_logs(Query(query): Query<LogsQuery>) -> Result<Json<LogsResponse>, ApiError> {
+    let level = match &query.level {
+        Some(level) => Some(
+            level
+                .parse::<Level>()
+                .map_err(|_| ApiError::BadRequest(format!("Invalid log level '{}'", level)))?,
+        ),
+        None => None,
+    };
+    let since = match &query.since {
+        Some(since) => Some(parse_since(since, SystemTime::now()).map_err(ApiError::BadRequest)?),
+        None => None,
+    };
+
+    let mut entries = logging::buffer().query(level, since);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if entries.len() > limit {
+        entries.drain(..entries.len() - limit);
+    }
+
+    Ok(Json(LogsResponse {
+        filter: logging::current_filter(),
+        entries,
+    }))
+}
+
+/// Change the log level or per-module filter at runtime
+/// PUT /api/v1/logs/level
+pub async fn set_log_level(Json(request): Json<SetLevelRequest>) -> Result<Json<SetLevelResponse>, ApiError> {
+    logging::set_filter(&request.level).map_err(|e| {
+        if e.contains("Invalid") {
+            ApiError::BadRequest(e)
+        } else {
+            ApiError::Internal(e)
+        }
+    })?;
+    tracing::info!("Log filter changed to '{}'", logging::current_filter());
+
+    Ok(Json(SetLevelResponse {
+        filter: logging::current_filter(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        let now = UNIX_EPOCH + Duration::from_secs(3600);
+        assert_eq!(parse_since("5m", now).unwrap(), 3300 * 1000);
+        assert_eq!(parse_since("1970-01-01T00:00:10Z", now).unwrap(), 10_000);
+        assert!(parse_since("yesterday", now).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Metadata handlers
+//!
+//! Read and write PipeWire metadata objects (default sink/source,
+//! target.object, clock settings, ...) via pw-metadata.
+
+use axum::{
+    extract::{Path, Query},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api_server::ApiError;
+use crate::pwmetadata::{self, MetadataEntry, MetadataObject};
+
+/// Response for GET /api/v1/metadata
+#[derive(Debug, Serialize)]
+pub struct MetadataListResponse {
+    pub metadata: Vec<MetadataObject>,
+}
+
+/// Response for GET /api/v1/metadata/:name
+#[derive(Debug, Serialize)]
+pub struct MetadataEntriesResponse {
+    pub name: String,
+    pub entries: Vec<MetadataEntry>,
+}
+
+/// Query parameters selecting the subject of a metadata key
+#[derive(Debug, Deserialize)]
+pub struct SubjectQuery {
+    #[serde(default)]
+    pub subject: u32,
+}
+
+/// Request body for setting a metadata key
+#[derive(Debug, Deserialize)]
+pub struct SetMetadataRequest {
+    pub value: Value,
+    #[serde(default)]
+    pub subject: u32,
+}
+
+/// Make sure a metadata object with this name exists
+async fn ensure_metadata_exists(name: &str) -> Result<(), ApiError> {
+    let objects = crate::worker::run(pwmetadata::list_metadata_objects)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list metadata: {}", e)))?;
+
+    if objects.iter().any(|o| o.name == name) {
+        Ok(())
+    } else {
+        Err(ApiError::NotFound(format!("Metadata '{}' not found", name)))
+    }
+}
+
+/// List all metadata objects
+/// GET /api/v1/metadata
+pub async fn list_metadata() -> Result<Json<MetadataListResponse>, ApiError> {
+    let metadata = crate::worker::run(pwmetadata::list_metadata_objects)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list metadata: {}", e)))?;
+
+    Ok(Json(MetadataListResponse { metadata }))
+}
+
+/// Get all entries of a metadata object
+/// GET /api/v1/metadata/:name
+pub async fn get_metadata(
+    Path(name): Path<String>,
+) -> Result<Json<MetadataEntriesResponse>, ApiError> {
+    ensure_metadata_exists(&name).await?;
+
+    let query_name = name.clone();
+    let entries = crate::worker::run(move || pwmetadata::get_metadata(&query_name))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to read metadata: {}", e)))?;
+
+    Ok(Json(MetadataEntriesResponse { name, entries }))
+}
+
+/// Get a single metadata key
+/// GET /api/v1/metadata/:name/:key?subject=0
+pub async fn get_metadata_key(
+    Path((name, key)): Path<(String, String)>,
+    Query(query): Query<SubjectQuery>,
+) -> Result<Json<MetadataEntry>, ApiError> {
+    ensure_metadata_exists(&name).await?;
+
+    let subject = query.subject;
+    let lookup_key = key.clone();
+    let entry = crate::worker::run(move || pwmetadata::get_metadata_key(&name, subject, &lookup_key))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to read metadata: {}", e)))?;
+
+    entry
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Key '{}' not set for subject {}", key, subject)))
+}
+
+/// Set a metadata key
+/// PUT /api/v1/metadata/:name/:key
+pub async fn set_metadata_key(
+    Path((name, key)): Path<(String, String)>,
+    Json(request): Json<SetMetadataRequest>,
+) -> Result<Json<MetadataEntry>, ApiError> {
+    ensure_metadata_exists(&name).await?;
+
+    let subject = request.subject;
+    let value = request.value.clone();
+    let set_key = key.clone();
+    crate::worker::run(move || pwmetadata::set_metadata(&name, subject, &set_key, &value))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to set metadata: {}", e)))?;
+
+    let (_, value_type) = pwmetadata::value_to_metadata(&request.value);
+    Ok(Json(MetadataEntry {
+        subject,
+        key,
+        value: request.value,
+        value_type: value_type.map(|t| t.to_string()),
+    }))
+}
+
+/// Delete a metadata key
+/// DELETE /api/v1/metadata/:name/:key?subject=0
+pub async fn delete_metadata_key(
+    Path((name, key)): Path<(String, String)>,
+    Query(query): Query<SubjectQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    ensure_metadata_exists(&name).await?;
+
+    let subject = query.subject;
+    let delete_key = key.clone();
+    crate::worker::run(move || pwmetadata::delete_metadata(&name, subject, &delete_key))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to delete metadata: {}", e)))?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": format!("Deleted '{}' for subject {}", key, subject)
+    })))
+}
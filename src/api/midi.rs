@@ -0,0 +1,25 @@
+//! MIDI handlers
+//!
+//! List MIDI nodes and their ports, see [`crate::midi`].
+
+use axum::Json;
+use serde::Serialize;
+
+use crate::api_server::ApiError;
+use crate::midi::{self, MidiNode};
+
+/// Response for GET /api/v1/midi
+#[derive(Debug, Serialize)]
+pub struct MidiResponse {
+    pub nodes: Vec<MidiNode>,
+}
+
+/// List MIDI nodes with their ports
+/// GET /api/v1/midi
+pub async fn list_midi() -> Result<Json<MidiResponse>, ApiError> {
+    crate::worker::run(midi::list)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map(|nodes| Json(MidiResponse { nodes }))
+        .map_err(ApiError::Internal)
+}
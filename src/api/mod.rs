@@ -4,16 +4,89 @@
 //! - `types`: Common data structures
 //! - `listing`: List PipeWire objects
 //! - `properties`: Object properties
+//! - `search`: Search objects by name and property values
+//! - `watch`: Stream changes of a single object (SSE)
 //! - `volume`: Unified volume control (via wpctl)
 //! - `links`: Link management (via pw-link)
+//! - `params`: Raw parameter access, batch control updates and control
+//!   discovery for any object (via pw-cli)
+//! - `metadata`: Metadata objects (via pw-metadata)
+//! - `clients`: Client listing and disconnect (via pw-cli)
+//! - `clock`: Quantum and sample rate settings (via pw-metadata)
+//! - `policy`: Session manager decisions and sample-rate policy (follow the
+//!   streams or pin a rate)
+//! - `streams`: Stream targets (target.object) honored by the session manager
+//! - `bluetooth`: Bluetooth codec, battery and profiles
+//! - `devices`: Device details (ALSA card info) and route volumes
+//! - `topology`: Topology export and declarative apply
+//! - `config`: Configuration file validation
+//! - `modules`: DSP module availability
+//! - `status`: Status summary for control panels
+//! - `startup`: State of the rules applied at startup
+//! - `logs`: Recent log events and runtime log level
+//! - `events`: History of devices and nodes appearing and disappearing
+//! - `diagnostics`: Self-test of tools, PipeWire connection, modules and storage
+//! - `history`: Volume history and audit log (sqlite state backend)
+//! - `scenes`: Scenes combining settings presets, links and volumes
+//! - `transactions`: Several changes applied together with rollback
+//! - `undo`: Undo/redo of DSP and volume changes
+//! - `pairing`: PIN pairing of clients that issues tokens
+//! - `midi`: MIDI nodes and ports
+//! - `power`: Suspending of idle sinks
+//! - `zones`: Multi-room zones
+//! - `combine`: Combined sinks (one stream to several sinks)
+//! - `stream_output`: Null sink feeding an external streaming server
+//! - `capture`: Audio of a node streamed as WAV or FLAC
+//! - `record`: Recording the audio of a node into a file
+//! - `play`: Playback of WAV files through a sink
+//! - `notify`: Notification sounds played with ducking
+//! - `ducking`: State of the ducking rules
+//! - `stats`: Statistics of stream activity, xruns, quanta and DSP load, metrics history
+//! - `cores`: PipeWire instances served under `/api/v1/cores/<name>`
 
 pub mod types;
 pub mod listing;
 pub mod properties;
+pub mod search;
+pub mod watch;
 pub mod volume;
 pub mod links;
+pub mod params;
+pub mod metadata;
+pub mod clients;
+pub mod clock;
+pub mod policy;
+pub mod streams;
+pub mod bluetooth;
+pub mod devices;
+pub mod topology;
+pub mod config;
+pub mod modules;
+pub mod status;
+pub mod startup;
+pub mod logs;
+pub mod events;
+pub mod diagnostics;
+pub mod history;
+pub mod scenes;
+pub mod transactions;
+pub mod undo;
+pub mod pairing;
+pub mod midi;
+pub mod power;
+pub mod zones;
+pub mod combine;
+pub mod stream_output;
+pub mod capture;
+pub mod record;
+pub mod play;
+pub mod notify;
+pub mod ducking;
+pub mod stats;
+pub mod cores;
 
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post, put, delete},
     Json, Router,
 };
@@ -69,16 +142,171 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
                 methods: vec!["GET"],
                 description: "Get API and package version information",
             },
+            EndpointInfo {
+                path: "/api/v1/config",
+                methods: vec!["GET"],
+                description: "Get the effective server configuration",
+            },
+            EndpointInfo {
+                path: "/api/v1/config/validate",
+                methods: vec!["POST"],
+                description: "Validate link-rules.conf, param-rules.conf and volume.conf",
+            },
+            EndpointInfo {
+                path: "/api/v1/modules",
+                methods: vec!["GET"],
+                description: "List DSP modules and whether they are present",
+            },
+            EndpointInfo {
+                path: "/api/v1/status",
+                methods: vec!["GET"],
+                description: "Status summary: defaults, volumes, streams, modules, PipeWire version",
+            },
+            EndpointInfo {
+                path: "/api/v1/startup/status",
+                methods: vec!["GET"],
+                description: "Get the state of the rules applied at startup",
+            },
+            EndpointInfo {
+                path: "/api/v1/logs",
+                methods: vec!["GET"],
+                description: "Get recent log events (?level=, ?since=, ?limit=)",
+            },
+            EndpointInfo {
+                path: "/api/v1/logs/level",
+                methods: vec!["PUT"],
+                description: "Change the log level at runtime, globally or per module",
+            },
+            EndpointInfo {
+                path: "/api/v1/power",
+                methods: vec!["GET"],
+                description: "Get the sinks suspended when idle and their state",
+            },
+            EndpointInfo {
+                path: "/api/v1/power/:name",
+                methods: vec!["PUT"],
+                description: "Keep a sink awake or let it be suspended when idle",
+            },
+            EndpointInfo {
+                path: "/api/v1/power/:name/suspend",
+                methods: vec!["POST"],
+                description: "Suspend a sink now",
+            },
+            EndpointInfo {
+                path: "/api/v1/stream-output",
+                methods: vec!["GET", "PUT"],
+                description: "Get or switch the stream output to an external process (e.g. snapserver)",
+            },
+            EndpointInfo {
+                path: "/api/v1/capture/:node_id/stream",
+                methods: vec!["GET"],
+                description: "Stream the audio of a node (monitor of a sink) as WAV or FLAC (?format=, ?duration=)",
+            },
+            EndpointInfo {
+                path: "/api/v1/record",
+                methods: vec!["GET", "POST"],
+                description: "List recordings or record the audio of a node into a WAV/FLAC file",
+            },
+            EndpointInfo {
+                path: "/api/v1/record/:id",
+                methods: vec!["GET"],
+                description: "Get the status of a recording",
+            },
+            EndpointInfo {
+                path: "/api/v1/record/:id/stop",
+                methods: vec!["POST"],
+                description: "Stop a recording",
+            },
+            EndpointInfo {
+                path: "/api/v1/record/:id/download",
+                methods: vec!["GET"],
+                description: "Download the file of a recording",
+            },
+            EndpointInfo {
+                path: "/api/v1/play",
+                methods: vec!["POST"],
+                description: "Play an uploaded WAV file or one from the sounds directory through a sink",
+            },
+            EndpointInfo {
+                path: "/api/v1/notify",
+                methods: vec!["GET"],
+                description: "List the notification sounds",
+            },
+            EndpointInfo {
+                path: "/api/v1/notify/:name",
+                methods: vec!["POST", "PUT", "DELETE"],
+                description: "Play a notification sound with ducking (?sink=&gain_db=), store (WAV body) or delete it",
+            },
+            EndpointInfo {
+                path: "/api/v1/ducking",
+                methods: vec!["GET"],
+                description: "Get the ducking rules and the nodes whose volume they lowered",
+            },
+            EndpointInfo {
+                path: "/api/v1/stats/streams",
+                methods: vec!["GET"],
+                description: "Get the playtime and number of streams per application (?window=24h)",
+            },
+            EndpointInfo {
+                path: "/api/v1/stats/xruns",
+                methods: vec!["GET"],
+                description: "Get the xruns per node and the quantum and rate history of the drivers",
+            },
+            EndpointInfo {
+                path: "/api/v1/stats/load",
+                methods: vec!["GET"],
+                description: "Get the DSP load of the drivers and the processing time of their nodes",
+            },
+            EndpointInfo {
+                path: "/api/v1/stats/history",
+                methods: vec!["GET"],
+                description: "Get the history of a metric (?metric=volume|xruns|playtime&range=24h)",
+            },
+            EndpointInfo {
+                path: "/api/v1/cores",
+                methods: vec!["GET"],
+                description: "List the PipeWire instances; /api/v1/cores/:core/... serves an endpoint for one of them",
+            },
+            EndpointInfo {
+                path: "/api/v1/events/history",
+                methods: vec!["GET"],
+                description: "Get devices and nodes that appeared or disappeared (?since=, ?limit=)",
+            },
+            EndpointInfo {
+                path: "/api/v1/diagnostics/run",
+                methods: vec!["POST"],
+                description: "Run a self-test and report pass/fail per check",
+            },
             EndpointInfo {
                 path: "/api/v1/ls",
                 methods: vec!["GET"],
                 description: "List all PipeWire objects",
             },
+            EndpointInfo {
+                path: "/api/v1/ls/sinks",
+                methods: vec!["GET"],
+                description: "List sink nodes (media.class Audio/Sink)",
+            },
+            EndpointInfo {
+                path: "/api/v1/ls/sources",
+                methods: vec!["GET"],
+                description: "List source nodes (media.class Audio/Source)",
+            },
+            EndpointInfo {
+                path: "/api/v1/ls/streams",
+                methods: vec!["GET"],
+                description: "List application streams (media.class Stream/*)",
+            },
             EndpointInfo {
                 path: "/api/v1/objects/:id",
                 methods: vec!["GET"],
                 description: "Get object by ID",
             },
+            EndpointInfo {
+                path: "/api/v1/objects/:id/watch",
+                methods: vec!["GET"],
+                description: "Stream property and parameter changes of an object (SSE)",
+            },
             EndpointInfo {
                 path: "/api/v1/cache/refresh",
                 methods: vec!["POST"],
@@ -94,6 +322,36 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
                 methods: vec!["GET"],
                 description: "Get properties for object by ID",
             },
+            EndpointInfo {
+                path: "/api/v1/properties/query",
+                methods: vec!["POST"],
+                description: "Get properties of several objects by ID or name in one call",
+            },
+            EndpointInfo {
+                path: "/api/v1/search",
+                methods: vec!["GET"],
+                description: "Search objects by name and property values (?q=)",
+            },
+            EndpointInfo {
+                path: "/api/v1/nodes/:id/params/:type",
+                methods: vec!["GET", "PUT"],
+                description: "Get/set raw parameters (Props, Route, Format, ...) of any object",
+            },
+            EndpointInfo {
+                path: "/api/v1/nodes/:id/props",
+                methods: vec!["PUT"],
+                description: "Set several control parameters of any node in one call",
+            },
+            EndpointInfo {
+                path: "/api/v1/nodes/:id/props/schema",
+                methods: vec!["GET"],
+                description: "Get name, type, default, range and labels of all controls of a node",
+            },
+            EndpointInfo {
+                path: "/api/v1/nodes/:id/bypass",
+                methods: vec!["GET", "PUT"],
+                description: "Get/set bypass of a DSP node via its Enable/bypass controls",
+            },
             // Volume endpoints
             EndpointInfo {
                 path: "/api/v1/volume",
@@ -115,6 +373,86 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
                 methods: vec!["POST"],
                 description: "Save specific volume to state file",
             },
+            EndpointInfo {
+                path: "/api/v1/history/volume/:name",
+                methods: vec!["GET"],
+                description: "Saved volumes of a node over time (sqlite state backend)",
+            },
+            EndpointInfo {
+                path: "/api/v1/history/events",
+                methods: vec!["GET"],
+                description: "Audit log of saved state (sqlite state backend)",
+            },
+            EndpointInfo {
+                path: "/api/v1/scenes",
+                methods: vec!["GET"],
+                description: "List scenes and the active scene",
+            },
+            EndpointInfo {
+                path: "/api/v1/scenes/:name",
+                methods: vec!["GET"],
+                description: "Get a scene",
+            },
+            EndpointInfo {
+                path: "/api/v1/scenes/:name/activate",
+                methods: vec!["POST"],
+                description: "Activate a scene (settings, links, volumes) with rollback on failure",
+            },
+            EndpointInfo {
+                path: "/api/v1/transactions",
+                methods: vec!["POST"],
+                description: "Validate and apply volume, EQ, crossbar and link changes together with rollback on failure",
+            },
+            EndpointInfo {
+                path: "/api/v1/undo",
+                methods: vec!["GET", "POST"],
+                description: "List recent DSP and volume changes / Revert the last change",
+            },
+            EndpointInfo {
+                path: "/api/v1/redo",
+                methods: vec!["POST"],
+                description: "Apply the last undone change again",
+            },
+            EndpointInfo {
+                path: "/api/v1/pair",
+                methods: vec!["POST"],
+                description: "Request pairing (PIN shown in the log) / Confirm the PIN to receive a token",
+            },
+            EndpointInfo {
+                path: "/api/v1/pair/clients",
+                methods: vec!["GET"],
+                description: "List paired clients",
+            },
+            EndpointInfo {
+                path: "/api/v1/pair/clients/:name",
+                methods: vec!["DELETE"],
+                description: "Revoke the token of a paired client",
+            },
+            EndpointInfo {
+                path: "/api/v1/zones",
+                methods: vec!["GET"],
+                description: "List multi-room zones",
+            },
+            EndpointInfo {
+                path: "/api/v1/zones/:name",
+                methods: vec!["GET"],
+                description: "Get a zone",
+            },
+            EndpointInfo {
+                path: "/api/v1/zones/:name/enabled",
+                methods: vec!["PUT"],
+                description: "Enable or disable a zone",
+            },
+            EndpointInfo {
+                path: "/api/v1/zones/:name/volume",
+                methods: vec!["PUT"],
+                description: "Set zone volume",
+            },
+            EndpointInfo {
+                path: "/api/v1/combine-sinks",
+                methods: vec!["POST"],
+                description: "Create a sink that plays to several sinks",
+            },
             EndpointInfo {
                 path: "/api/v1/defaults/sink",
                 methods: vec!["GET"],
@@ -125,6 +463,59 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
                 methods: vec!["GET"],
                 description: "Get default audio source info",
             },
+            EndpointInfo {
+                path: "/api/v1/devices/:id/alsa",
+                methods: vec!["GET"],
+                description: "Get ALSA card/device numbers, driver and hw params",
+            },
+            EndpointInfo {
+                path: "/api/v1/devices/:id/routes/:index/volume",
+                methods: vec!["GET", "PUT"],
+                description: "Get or set the volume of one route (speaker, headphones) of a device",
+            },
+            // Client endpoints
+            EndpointInfo {
+                path: "/api/v1/clients",
+                methods: vec!["GET"],
+                description: "List connected clients",
+            },
+            EndpointInfo {
+                path: "/api/v1/clients/:id",
+                methods: vec!["GET", "DELETE"],
+                description: "Get client details / Disconnect client",
+            },
+            // Bluetooth endpoints
+            EndpointInfo {
+                path: "/api/v1/bluetooth",
+                methods: vec!["GET"],
+                description: "List Bluetooth devices with codec, profile and battery",
+            },
+            EndpointInfo {
+                path: "/api/v1/bluetooth/:id",
+                methods: vec!["GET"],
+                description: "Get Bluetooth device with available profiles",
+            },
+            EndpointInfo {
+                path: "/api/v1/bluetooth/:id/profile",
+                methods: vec!["PUT"],
+                description: "Switch Bluetooth profile (A2DP/HFP)",
+            },
+            // Metadata endpoints
+            EndpointInfo {
+                path: "/api/v1/metadata",
+                methods: vec!["GET"],
+                description: "List metadata objects",
+            },
+            EndpointInfo {
+                path: "/api/v1/metadata/:name",
+                methods: vec!["GET"],
+                description: "Get all entries of a metadata object",
+            },
+            EndpointInfo {
+                path: "/api/v1/metadata/:name/:key",
+                methods: vec!["GET", "PUT", "DELETE"],
+                description: "Get/set/delete a metadata key",
+            },
             // Link endpoints
             EndpointInfo {
                 path: "/api/v1/links",
@@ -141,6 +532,11 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
                 methods: vec!["DELETE"],
                 description: "Remove link by port names",
             },
+            EndpointInfo {
+                path: "/api/v1/links/by-node/:id",
+                methods: vec!["DELETE"],
+                description: "Remove all links of a node (?direction=input|output|both)",
+            },
             EndpointInfo {
                 path: "/api/v1/links/exists",
                 methods: vec!["GET"],
@@ -156,7 +552,33 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
                 methods: vec!["GET"],
                 description: "List input ports",
             },
+            EndpointInfo {
+                path: "/api/v1/midi",
+                methods: vec!["GET"],
+                description: "List MIDI nodes with their ports (link them with POST /api/v1/links)",
+            },
+            // Topology endpoints
+            EndpointInfo {
+                path: "/api/v1/topology/export",
+                methods: vec!["GET"],
+                description: "Export nodes and links as JSON",
+            },
+            EndpointInfo {
+                path: "/api/v1/topology/apply",
+                methods: vec!["POST"],
+                description: "Recreate a link set, optionally pruning extra links",
+            },
             // SpeakerEQ module endpoints
+            EndpointInfo {
+                path: "/api/module/speakereq/instances",
+                methods: vec!["GET"],
+                description: "List speakereq nodes (instances)",
+            },
+            EndpointInfo {
+                path: "/api/module/speakereq/:instance/*path",
+                methods: vec!["GET", "PUT", "POST"],
+                description: "Any speakereq endpoint for a specific node",
+            },
             EndpointInfo {
                 path: "/api/module/speakereq/structure",
                 methods: vec!["GET"],
@@ -182,15 +604,55 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
                 methods: vec!["GET"],
                 description: "Get supported filter types and parameter ranges",
             },
+            EndpointInfo {
+                path: "/api/module/speakereq/headroom",
+                methods: vec!["GET", "PUT"],
+                description: "Worst-case gain per output with clipping warnings, PUT trims the master gain",
+            },
+            EndpointInfo {
+                path: "/api/module/speakereq/license",
+                methods: vec!["GET", "PUT"],
+                description: "Get license status, expiry and feature flags, submit a license key",
+            },
             EndpointInfo {
                 path: "/api/v1/settings/save",
                 methods: vec!["POST"],
-                description: "Save current settings to ~/.state/pipewire-api/settings.json",
+                description: "Save current settings to ~/.state/pipewire-api/settings.json (?preset= for a named preset)",
+            },
+            EndpointInfo {
+                path: "/api/v1/settings/clock",
+                methods: vec!["GET", "PUT"],
+                description: "Get/set clock.force-quantum and clock.force-rate",
+            },
+            EndpointInfo {
+                path: "/api/v1/policy",
+                methods: vec!["GET"],
+                description: "Get the session manager (WirePlumber), its default-node decisions, stream targets and restored stream state",
+            },
+            EndpointInfo {
+                path: "/api/v1/policy/sample-rate",
+                methods: vec!["GET", "PUT"],
+                description: "Get the graph rate, follow the stream rates (bit-perfect) or pin a rate",
+            },
+            EndpointInfo {
+                path: "/api/v1/streams/:id/target",
+                methods: vec!["GET", "PUT", "DELETE"],
+                description: "Get/set/remove the sink or source of a stream (target.object, kept by the session manager)",
             },
             EndpointInfo {
                 path: "/api/v1/settings/restore",
                 methods: vec!["POST"],
-                description: "Restore settings from ~/.state/pipewire-api/settings.json",
+                description: "Restore settings from ~/.state/pipewire-api/settings.json (?preset= for a named preset, ?smooth_ms= to ramp gains)",
+            },
+            EndpointInfo {
+                path: "/api/v1/settings/backups",
+                methods: vec!["GET"],
+                description: "List backups of settings.json, volume.state and links.state",
+            },
+            EndpointInfo {
+                path: "/api/v1/settings/backups/restore",
+                methods: vec!["POST"],
+                description: "Replace a state file with one of its backups",
             },
             EndpointInfo {
                 path: "/api/module/speakereq/eq/:block/:band",
@@ -205,7 +667,7 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
             EndpointInfo {
                 path: "/api/module/speakereq/eq/:block/clear",
                 methods: vec!["PUT"],
-                description: "Clear all EQ bands in block",
+                description: "Clear all EQ bands in block (?smooth_ms= to fade out)",
             },
             EndpointInfo {
                 path: "/api/module/speakereq/gain/master",
@@ -222,6 +684,11 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
                 methods: vec!["POST"],
                 description: "Refresh parameter cache",
             },
+            EndpointInfo {
+                path: "/api/module/speakereq/events",
+                methods: vec!["GET"],
+                description: "Stream parameter changes (SSE)",
+            },
             EndpointInfo {
                 path: "/api/module/speakereq/default",
                 methods: vec!["POST"],
@@ -273,6 +740,11 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
                 methods: vec!["GET", "PUT"],
                 description: "Get/set notch filter config",
             },
+            EndpointInfo {
+                path: "/api/module/riaa/input",
+                methods: vec!["GET", "PUT"],
+                description: "Get/switch the capture source linked into RIAA",
+            },
             EndpointInfo {
                 path: "/api/module/riaa/set-default",
                 methods: vec!["PUT"],
@@ -282,12 +754,17 @@ pub async fn list_endpoints() -> Json<EndpointListResponse> {
             EndpointInfo {
                 path: "/api/v1/graph",
                 methods: vec!["GET"],
-                description: "Get audio topology graph (DOT format)",
+                description: "Get audio topology graph (DOT format, ?cluster=device|client, ?annotate=volume,format, ?include=midi,video)",
             },
             EndpointInfo {
                 path: "/api/v1/graph/png",
                 methods: vec!["GET"],
-                description: "Get audio topology graph (PNG image)",
+                description: "Get audio topology graph (PNG image, ?cluster=device|client, ?annotate=volume,format, ?include=midi,video)",
+            },
+            EndpointInfo {
+                path: "/api/v1/graph/html",
+                methods: vec!["GET"],
+                description: "Interactive topology page (click a node for its properties)",
             },
         ],
     })
@@ -300,30 +777,139 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/api/v1", get(list_endpoints))
         // Version endpoint
         .route("/api/v1/version", get(get_version))
+        // Server configuration
+        .route("/api/v1/config", get(config::get_config))
+        .route("/api/v1/config/validate", post(config::validate_config))
+        // DSP module availability
+        .route("/api/v1/modules", get(modules::list_modules))
+        // Status summary
+        .route("/api/v1/status", get(status::get_status))
+        // Startup rule status
+        .route("/api/v1/startup/status", get(startup::get_startup_status))
+        // Log access
+        .route("/api/v1/logs", get(logs::get_logs))
+        .route("/api/v1/logs/level", put(logs::set_log_level))
+        .route("/api/v1/events/history", get(events::get_event_history))
+        .route("/api/v1/power", get(power::get_power))
+        .route("/api/v1/power/:name", put(power::set_power_override))
+        .route("/api/v1/power/:name/suspend", post(power::suspend_node))
+        .route("/api/v1/stream-output", get(stream_output::get_stream_output).put(stream_output::set_stream_output))
+        .route("/api/v1/capture/:node_id/stream", get(capture::stream_capture))
+        .route("/api/v1/record", get(record::list_recordings).post(record::start_recording))
+        .route("/api/v1/record/:id", get(record::get_recording))
+        .route("/api/v1/record/:id/stop", post(record::stop_recording))
+        .route("/api/v1/record/:id/download", get(record::download_recording))
+        .route("/api/v1/play", post(play::play).layer(DefaultBodyLimit::max(play::MAX_UPLOAD_BYTES)))
+        .route("/api/v1/notify", get(notify::list_sounds))
+        .route(
+            "/api/v1/notify/:name",
+            post(notify::play_sound)
+                .put(notify::store_sound)
+                .delete(notify::delete_sound)
+                .layer(DefaultBodyLimit::max(notify::MAX_UPLOAD_BYTES)),
+        )
+        .route("/api/v1/ducking", get(ducking::get_ducking))
+        .route("/api/v1/stats/streams", get(stats::get_stream_stats))
+        .route("/api/v1/stats/xruns", get(stats::get_xrun_stats))
+        .route("/api/v1/stats/load", get(stats::get_load))
+        .route("/api/v1/stats/history", get(stats::get_history))
+        .route("/api/v1/cores", get(cores::list_cores))
+        // Self-test
+        .route("/api/v1/diagnostics/run", post(diagnostics::run_diagnostics))
         // Listing endpoints
         .route("/api/v1/ls", get(listing::list_all))
+        .route("/api/v1/ls/sinks", get(listing::list_sinks))
+        .route("/api/v1/ls/sources", get(listing::list_sources))
+        .route("/api/v1/ls/streams", get(listing::list_streams))
         // Object by ID endpoint
         .route("/api/v1/objects/:id", get(listing::get_object_by_id))
+        .route("/api/v1/objects/:id/watch", get(watch::watch_object))
         // Cache refresh endpoint
         .route("/api/v1/cache/refresh", post(listing::refresh_cache))
         // Properties endpoints
         .route("/api/v1/properties", get(properties::list_all_properties))
+        .route("/api/v1/properties/query", post(properties::query_properties))
         .route("/api/v1/properties/:id", get(properties::get_object_properties))
+        // Search endpoint
+        .route("/api/v1/search", get(search::search))
+        // Raw parameter endpoints (via pw-cli)
+        .route("/api/v1/nodes/:id/params/:type", get(params::get_params).put(params::set_params))
+        .route("/api/v1/nodes/:id/props", put(params::set_props))
+        .route("/api/v1/nodes/:id/props/schema", get(params::get_props_schema))
+        .route("/api/v1/nodes/:id/bypass", get(params::get_bypass).put(params::set_bypass))
         // Unified volume endpoints (via wpctl)
         .route("/api/v1/volume", get(volume::list_all_volumes))
         .route("/api/v1/volume/:id", get(volume::get_volume_by_id))
         .route("/api/v1/volume/:id", put(volume::set_volume_by_id))
         .route("/api/v1/volume/save", post(volume::save_all_volumes))
         .route("/api/v1/volume/save/:id", post(volume::save_volume))
+        // History (sqlite state backend)
+        .route("/api/v1/history/volume/:name", get(history::get_volume_history))
+        .route("/api/v1/history/events", get(history::get_events))
+        // Zone endpoints
+        .route("/api/v1/zones", get(zones::list_zones))
+        .route("/api/v1/zones/:name", get(zones::get_zone))
+        .route("/api/v1/zones/:name/enabled", put(zones::set_zone_enabled))
+        .route("/api/v1/zones/:name/volume", put(zones::set_zone_volume))
+        // Scenes
+        .route("/api/v1/scenes", get(scenes::list_scenes))
+        .route("/api/v1/scenes/:name", get(scenes::get_scene))
+        .route("/api/v1/scenes/:name/activate", post(scenes::activate_scene))
+        .route("/api/v1/transactions", post(transactions::run_transaction))
+        .route("/api/v1/undo", get(undo::get_history).post(undo::undo))
+        .route("/api/v1/redo", post(undo::redo))
+        // Pairing
+        .route("/api/v1/pair", post(pairing::pair))
+        .route("/api/v1/pair/clients", get(pairing::list_clients))
+        .route("/api/v1/pair/clients/:name", delete(pairing::revoke_client))
+        // Combined sinks
+        .route("/api/v1/combine-sinks", post(combine::combine_sinks))
         // Defaults endpoints (via wpctl)
         .route("/api/v1/defaults/sink", get(volume::get_default_sink))
         .route("/api/v1/defaults/source", get(volume::get_default_source))
+        // Device endpoints
+        .route("/api/v1/devices/:id/alsa", get(devices::get_alsa_info))
+        .route("/api/v1/devices/:id/routes/:index/volume", get(devices::get_route_volume).put(devices::set_route_volume))
+        // Client endpoints (via pw-cli)
+        .route("/api/v1/clients", get(clients::list_clients))
+        .route("/api/v1/clients/:id", get(clients::get_client).delete(clients::disconnect_client))
+        // Bluetooth endpoints
+        .route("/api/v1/bluetooth", get(bluetooth::list_devices))
+        .route("/api/v1/bluetooth/:id", get(bluetooth::get_device))
+        .route("/api/v1/bluetooth/:id/profile", put(bluetooth::set_profile))
+        // Metadata endpoints (via pw-metadata)
+        .route("/api/v1/metadata", get(metadata::list_metadata))
+        .route("/api/v1/metadata/:name", get(metadata::get_metadata))
+        .route(
+            "/api/v1/metadata/:name/:key",
+            get(metadata::get_metadata_key)
+                .put(metadata::set_metadata_key)
+                .delete(metadata::delete_metadata_key),
+        )
+        // Clock settings (via settings metadata)
+        .route("/api/v1/settings/clock", get(clock::get_clock).put(clock::set_clock))
+        .route("/api/v1/policy", get(policy::get_session_policy))
+        .route(
+            "/api/v1/policy/sample-rate",
+            get(policy::get_sample_rate_policy).put(policy::set_sample_rate_policy),
+        )
+        .route(
+            "/api/v1/streams/:id/target",
+            get(streams::get_stream_target)
+                .put(streams::set_stream_target)
+                .delete(streams::delete_stream_target),
+        )
         // Links endpoints (via pw-link)
         .route("/api/v1/links", post(links::create_link))
         .route("/api/v1/links/:id", delete(links::remove_link_by_id))
         .route("/api/v1/links/by-name", delete(links::remove_link_by_name))
+        .route("/api/v1/links/by-node/:id", delete(links::remove_links_by_node))
         .route("/api/v1/links/exists", get(links::check_link_exists))
         .route("/api/v1/links/ports/output", get(links::list_output_ports))
         .route("/api/v1/links/ports/input", get(links::list_input_ports))
+        .route("/api/v1/midi", get(midi::list_midi))
+        // Topology endpoints
+        .route("/api/v1/topology/export", get(topology::export_topology))
+        .route("/api/v1/topology/apply", post(topology::apply_topology))
         .with_state(state)
 }
@@ -0,0 +1,32 @@
+//! Module status handler
+//!
+//! Reports which DSP modules are available, see [`crate::modules`].
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::modules::ModuleStatus;
+
+/// Response for GET /api/v1/modules
+#[derive(Debug, Serialize)]
+pub struct ModulesResponse {
+    pub modules: Vec<ModuleStatus>,
+}
+
+/// List DSP modules and whether their nodes are loaded
+/// GET /api/v1/modules
+pub async fn list_modules(State(state): State<Arc<AppState>>) -> Result<Json<ModulesResponse>, ApiError> {
+    let modules = state.get_modules();
+    let modules = crate::worker::run(move || {
+        modules
+            .iter()
+            .map(|m| m.status())
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))??;
+
+    Ok(Json(ModulesResponse { modules }))
+}
@@ -0,0 +1,151 @@
+//! Notification sound handlers
+//!
+//! Stores named sounds and plays them on a sink while the other streams are
+//! ducked, see [`crate::notify`].
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::notify::{self, Notification, Sound};
+use crate::{playback, pwcli, wpctl};
+
+/// Largest sound accepted for upload
+pub const MAX_UPLOAD_BYTES: usize = 8 * 1024 * 1024;
+
+/// Response for GET /api/v1/notify
+#[derive(Debug, Serialize)]
+pub struct SoundsResponse {
+    pub directory: String,
+    pub max_sounds: usize,
+    pub sounds: Vec<Sound>,
+}
+
+/// Query parameters for playing a notification
+#[derive(Debug, Default, Deserialize)]
+pub struct NotifyQuery {
+    /// Sink ID or node.name (default: `[notify] sink`, then the default sink)
+    pub sink: Option<String>,
+    /// Gain in dB (default: `[notify] gain_db`)
+    pub gain_db: Option<f32>,
+    /// Respond when the sound ended and the volumes are restored
+    #[serde(default)]
+    pub wait: bool,
+}
+
+fn check_name(name: &str) -> Result<(), ApiError> {
+    if notify::valid_name(name) {
+        Ok(())
+    } else {
+        Err(ApiError::BadRequest(format!(
+            "Invalid sound name '{}' (letters, digits, '-' and '_')",
+            name
+        )))
+    }
+}
+
+fn directory(state: &AppState) -> Result<std::path::PathBuf, ApiError> {
+    notify::notify_dir(state.get_config().notify.directory.as_deref()).map_err(ApiError::Internal)
+}
+
+/// List the stored sounds
+/// GET /api/v1/notify
+pub async fn list_sounds(State(state): State<Arc<AppState>>) -> Result<Json<SoundsResponse>, ApiError> {
+    let directory = directory(&state)?;
+    let sounds = notify::list_sounds(&directory).await.map_err(ApiError::Internal)?;
+    Ok(Json(SoundsResponse {
+        directory: directory.display().to_string(),
+        max_sounds: notify::MAX_SOUNDS,
+        sounds,
+    }))
+}
+
+/// Store a sound (WAV file as body)
+/// PUT /api/v1/notify/:name
+pub async fn store_sound(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    body: Bytes,
+) -> Result<Json<Sound>, ApiError> {
+    check_name(&name)?;
+    let directory = directory(&state)?;
+    notify::store_sound(&directory, &name, &body).await.map(Json).map_err(|e| {
+        if e.starts_with("Failed") {
+            ApiError::Internal(e)
+        } else if e.starts_with("At most") {
+            ApiError::Conflict(e)
+        } else {
+            ApiError::BadRequest(e)
+        }
+    })
+}
+
+/// Delete a sound
+/// DELETE /api/v1/notify/:name
+pub async fn delete_sound(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    check_name(&name)?;
+    let path = notify::sound_path(&directory(&state)?, &name);
+    match tokio::fs::remove_file(&path).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(ApiError::NotFound(format!("Sound {} not found", name)))
+        }
+        Err(e) => Err(ApiError::Internal(format!("Failed to delete {}: {}", path.display(), e))),
+    }
+}
+
+/// Play a sound, ducking the other playback streams
+/// POST /api/v1/notify/:name?sink=&gain_db=&wait=
+pub async fn play_sound(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(query): Query<NotifyQuery>,
+) -> Result<Json<Notification>, ApiError> {
+    check_name(&name)?;
+    let config = state.get_config().notify;
+    let path = notify::sound_path(&directory(&state)?, &name);
+    if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+        return Err(ApiError::NotFound(format!("Sound {} not found", name)));
+    }
+
+    let sink = query.sink.or(config.sink);
+    let node = crate::worker::run(move || {
+        let sink = match sink {
+            Some(sink) => sink,
+            None => wpctl::get_default_sink()
+                .map(|s| s.id.to_string())
+                .map_err(|e| format!("No sink given and no default sink: {}", e))?,
+        };
+        let nodes = pwcli::list_nodes().map_err(|e| format!("Failed to list nodes: {}", e))?;
+        playback::find_sink(&nodes, &sink).cloned()
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(|e| {
+        if e.contains("not found") {
+            ApiError::NotFound(e)
+        } else if e.starts_with("Failed") {
+            ApiError::Internal(e)
+        } else {
+            ApiError::BadRequest(e)
+        }
+    })?;
+
+    let gain_db = query.gain_db.unwrap_or(config.gain_db);
+    let (notification, handle) = notify::notify(node, path, gain_db, config.duck_db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to play {}: {}", name, e)))?;
+    if query.wait {
+        let _ = handle.await;
+    }
+    Ok(Json(notification))
+}
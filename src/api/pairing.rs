@@ -0,0 +1,152 @@
+//! Pairing handlers
+//!
+//! Issue tokens to clients that confirm a PIN, see [`crate::pairing`].
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::process::Command;
+use tracing::warn;
+
+use crate::api_server::{ApiError, AppState};
+use crate::pairing::{PairedClient, PairingError, PIN_VALIDITY};
+
+/// Request body for POST /api/v1/pair
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PairRequest {
+    /// Second step: confirm the PIN
+    Confirm { pairing_id: String, pin: String },
+    /// First step: request pairing
+    Start { name: String, role: String },
+}
+
+/// Response to the first pairing step
+#[derive(Debug, Serialize)]
+pub struct PairingStarted {
+    pub pairing_id: String,
+    pub expires_in_secs: u64,
+}
+
+/// Response to the second pairing step
+#[derive(Debug, Serialize)]
+pub struct PairingCompleted {
+    pub name: String,
+    pub role: String,
+    /// Send as `Authorization: Bearer <token>`
+    pub token: String,
+}
+
+fn run_pairing_command(command: String, name: &str, pin: &str) {
+    let env = [("PW_PAIRING_CLIENT", name.to_string()), ("PW_PAIRING_PIN", pin.to_string())];
+    tokio::spawn(async move {
+        match Command::new("sh").arg("-c").arg(&command).envs(env).output().await {
+            Ok(output) if !output.status.success() => warn!(
+                "Pairing command '{}' failed ({}): {}",
+                command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Ok(_) => {}
+            Err(e) => warn!("Pairing command '{}' could not be started: {}", command, e),
+        }
+    });
+}
+
+fn pairing_error(error: PairingError) -> ApiError {
+    match error {
+        PairingError::NotFound => ApiError::NotFound("Unknown or expired pairing request".to_string()),
+        PairingError::WrongPin(0) => ApiError::BadRequest("Wrong PIN, pairing request discarded".to_string()),
+        PairingError::WrongPin(left) => ApiError::BadRequest(format!("Wrong PIN, {} attempts left", left)),
+        PairingError::Busy => ApiError::BadRequest("Too many open pairing requests, try again later".to_string()),
+        PairingError::LockedOut(secs) => {
+            ApiError::BadRequest(format!("Too many wrong PINs, pairing is locked for {} s", secs))
+        }
+        PairingError::NameTaken(name) => ApiError::Conflict(format!("A client named '{}' is paired already", name)),
+        PairingError::Storage(e) => ApiError::Internal(e),
+    }
+}
+
+fn start(state: &AppState, name: String, role: String) -> Result<Response, ApiError> {
+    if !state.auth.enabled() {
+        return Err(ApiError::BadRequest(
+            "Access control is off, no token is needed".to_string(),
+        ));
+    }
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiError::BadRequest("Client name must not be empty".to_string()));
+    }
+    let config = state.get_config().auth;
+    if !config.pairing_roles.contains(&role) || !state.auth.has_role(&role) {
+        return Err(ApiError::BadRequest(format!(
+            "Role '{}' can't be requested by pairing (allowed: {})",
+            role,
+            config.pairing_roles.join(", ")
+        )));
+    }
+
+    let (pairing_id, pin) = state.pairing.start(&name, &role).map_err(pairing_error)?;
+    // The PIN goes to stdout (the journal) only: log messages can be read
+    // via GET /api/v1/logs
+    warn!("Pairing request from '{}' for role '{}', the PIN is shown on the console", name, role);
+    println!("Pairing request from '{}' for role '{}', PIN: {}", name, role, pin);
+    if let Some(command) = config.pairing_command {
+        run_pairing_command(command, &name, &pin);
+    }
+
+    let started = PairingStarted {
+        pairing_id,
+        expires_in_secs: PIN_VALIDITY.as_secs(),
+    };
+    Ok((StatusCode::ACCEPTED, Json(started)).into_response())
+}
+
+fn confirm(state: &AppState, pairing_id: &str, pin: &str) -> Result<Response, ApiError> {
+    let client = state.pairing.complete(pairing_id, pin).map_err(pairing_error)?;
+    state.auth.set_paired(state.pairing.grants());
+
+    let completed = PairingCompleted {
+        name: client.name,
+        role: client.role,
+        token: client.token,
+    };
+    Ok(Json(completed).into_response())
+}
+
+/// Request pairing or confirm it with the PIN
+/// POST /api/v1/pair
+pub async fn pair(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PairRequest>,
+) -> Result<Response, ApiError> {
+    match request {
+        PairRequest::Start { name, role } => start(&state, name, role),
+        PairRequest::Confirm { pairing_id, pin } => confirm(&state, &pairing_id, &pin),
+    }
+}
+
+/// List paired clients (without tokens)
+/// GET /api/v1/pair/clients
+pub async fn list_clients(State(state): State<Arc<AppState>>) -> Json<Vec<PairedClient>> {
+    Json(state.pairing.clients())
+}
+
+/// Revoke the token of a paired client
+/// DELETE /api/v1/pair/clients/:name
+pub async fn revoke_client(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let removed = state.pairing.revoke(&name).map_err(ApiError::Internal)?;
+    if !removed {
+        return Err(ApiError::NotFound(format!("Paired client '{}' not found", name)));
+    }
+    state.auth.set_paired(state.pairing.grants());
+    Ok(Json(serde_json::json!({ "success": true, "message": format!("Revoked '{}'", name) })))
+}
@@ -0,0 +1,254 @@
+//! Raw parameter handlers for any PipeWire object
+//!
+//! Exposes the full parameter system (Props, Route, Format, ...) via pw-cli,
+//! for advanced use cases not covered by the curated module endpoints.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::bypass::{self, BypassStatus};
+use crate::parameters::{self, ParameterValue};
+use crate::pod_parser::ParamObject;
+use crate::prop_schema::{self, PropSchema};
+use crate::pwcli;
+use crate::pwparams;
+
+/// Response for raw parameter endpoints
+#[derive(Debug, Serialize)]
+pub struct ParamsResponse {
+    pub id: u32,
+    #[serde(rename = "type")]
+    pub param_type: String,
+    pub params: Vec<ParamObject>,
+}
+
+/// Resolve the parameter type and make sure the object exists
+async fn validate_request(state: &AppState, id: u32, param_type: &str) -> Result<&'static str, ApiError> {
+    let param_type = pwparams::normalize_param_type(param_type).ok_or_else(|| {
+        ApiError::BadRequest(format!(
+            "Unknown parameter type '{}'. Valid types: {}",
+            param_type,
+            pwparams::param_types().join(", ")
+        ))
+    })?;
+
+    if state.get_object_by_id(id).is_none() {
+        let exists = crate::worker::run(move || pwcli::get_object(id))
+            .await
+            .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+            .map_err(|e| ApiError::Internal(format!("Failed to get object: {}", e)))?
+            .is_some();
+        if !exists {
+            return Err(ApiError::NotFound(format!("Object {} not found", id)));
+        }
+    }
+
+    Ok(param_type)
+}
+
+async fn read_params(id: u32, param_type: &'static str) -> Result<Vec<ParamObject>, ApiError> {
+    crate::worker::run(move || pwparams::enum_params(id, param_type))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to read parameters: {}", e)))
+}
+
+/// Get all parameters of a type
+/// GET /api/v1/nodes/:id/params/:type
+pub async fn get_params(
+    State(state): State<Arc<AppState>>,
+    Path((id, param_type)): Path<(u32, String)>,
+) -> Result<Json<ParamsResponse>, ApiError> {
+    let param_type = validate_request(&state, id, &param_type).await?;
+    let params = read_params(id, param_type).await?;
+
+    Ok(Json(ParamsResponse {
+        id,
+        param_type: param_type.to_string(),
+        params,
+    }))
+}
+
+/// Set a parameter from a JSON object and return the updated parameters
+/// PUT /api/v1/nodes/:id/params/:type
+pub async fn set_params(
+    State(state): State<Arc<AppState>>,
+    Path((id, param_type)): Path<(u32, String)>,
+    Json(body): Json<Value>,
+) -> Result<Json<ParamsResponse>, ApiError> {
+    let param_type = validate_request(&state, id, &param_type).await?;
+
+    if !pwparams::is_writable(param_type) {
+        return Err(ApiError::BadRequest(format!("Parameter type '{}' is read-only", param_type)));
+    }
+    if !body.is_object() {
+        return Err(ApiError::BadRequest("Request body must be a JSON object".to_string()));
+    }
+
+    crate::worker::run(move || pwparams::set_param(id, param_type, &body))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to set parameter: {}", e)))?;
+
+    let params = read_params(id, param_type).await?;
+
+    Ok(Json(ParamsResponse {
+        id,
+        param_type: param_type.to_string(),
+        params,
+    }))
+}
+
+/// Response for GET /api/v1/nodes/:id/props/schema
+#[derive(Debug, Serialize)]
+pub struct PropsSchemaResponse {
+    pub id: u32,
+    pub controls: Vec<PropSchema>,
+}
+
+/// Get name, type, default, range and labels of all controls of a node
+/// GET /api/v1/nodes/:id/props/schema
+pub async fn get_props_schema(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+) -> Result<Json<PropsSchemaResponse>, ApiError> {
+    validate_request(&state, id, "PropInfo").await?;
+
+    let controls = crate::worker::run(move || prop_schema::read_schema(id))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to read parameters: {}", e)))?;
+
+    Ok(Json(PropsSchemaResponse { id, controls }))
+}
+
+/// Convert a flat map of control names to values into parameter values
+pub fn props_from_json(body: &Map<String, Value>) -> Result<HashMap<String, ParameterValue>, String> {
+    if body.is_empty() {
+        return Err("Request body must contain at least one property".to_string());
+    }
+    body.iter()
+        .map(|(name, value)| {
+            ParameterValue::from_json(value)
+                .map(|v| (name.clone(), v))
+                .map_err(|e| format!("{}: {}", name, e))
+        })
+        .collect()
+}
+
+/// Set several control parameters of any node (e.g. a filter-chain plugin)
+/// in a single pw-cli call and return the updated Props
+/// PUT /api/v1/nodes/:id/props
+pub async fn set_props(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+    Json(body): Json<Value>,
+) -> Result<Json<ParamsResponse>, ApiError> {
+    let param_type = validate_request(&state, id, "Props").await?;
+
+    let body = body
+        .as_object()
+        .ok_or_else(|| ApiError::BadRequest("Request body must be a JSON object".to_string()))?;
+    let props = props_from_json(body).map_err(ApiError::BadRequest)?;
+
+    crate::worker::run(move || parameters::set_params(id, &props))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| {
+            if e.starts_with("Invalid label") || e.starts_with("Control") {
+                ApiError::BadRequest(e)
+            } else {
+                ApiError::Internal(format!("Failed to set properties: {}", e))
+            }
+        })?;
+
+    let params = read_params(id, param_type).await?;
+
+    Ok(Json(ParamsResponse {
+        id,
+        param_type: param_type.to_string(),
+        params,
+    }))
+}
+
+/// Request body for PUT /api/v1/nodes/:id/bypass
+#[derive(Debug, Deserialize)]
+pub struct BypassRequest {
+    pub bypassed: bool,
+}
+
+fn bypass_error(e: String) -> ApiError {
+    if e.contains("has no enable or bypass control") {
+        ApiError::NotFound(e)
+    } else {
+        ApiError::Internal(format!("Failed to access bypass controls: {}", e))
+    }
+}
+
+/// Get whether a DSP node is bypassed, with its enable/bypass controls
+/// GET /api/v1/nodes/:id/bypass
+pub async fn get_bypass(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+) -> Result<Json<BypassStatus>, ApiError> {
+    validate_request(&state, id, "Props").await?;
+
+    let status = crate::worker::run(move || bypass::read_status(id))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(bypass_error)?;
+    Ok(Json(status))
+}
+
+/// Bypass or enable a DSP node by switching all its enable/bypass controls
+/// PUT /api/v1/nodes/:id/bypass
+pub async fn set_bypass(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+    Json(request): Json<BypassRequest>,
+) -> Result<Json<BypassStatus>, ApiError> {
+    validate_request(&state, id, "Props").await?;
+
+    let status = crate::worker::run(move || bypass::set_bypassed(id, request.bypassed))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(bypass_error)?;
+    tracing::info!("Node {} {}", id, if request.bypassed { "bypassed" } else { "enabled" });
+    Ok(Json(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_props_from_json() {
+        let body = json!({"eq:Gain": -3.5, "eq:Type": 2, "eq:Enable": true, "eq:Label": "bass"});
+        let props = props_from_json(body.as_object().unwrap()).unwrap();
+        assert_eq!(props["eq:Gain"], ParameterValue::Float(-3.5));
+        assert_eq!(props["eq:Type"], ParameterValue::Int(2));
+        assert_eq!(props["eq:Enable"], ParameterValue::Bool(true));
+        assert_eq!(props["eq:Label"], ParameterValue::String("bass".to_string()));
+
+        assert_eq!(
+            parameters::props_json(&props),
+            json!({"params": ["eq:Enable", true, "eq:Gain", -3.5, "eq:Label", "bass", "eq:Type", 2]})
+        );
+    }
+
+    #[test]
+    fn test_props_from_json_rejects_invalid() {
+        assert!(props_from_json(&Map::new()).is_err());
+        let body = json!({"eq:Gain": null});
+        let err = props_from_json(body.as_object().unwrap()).unwrap_err();
+        assert!(err.starts_with("eq:Gain"));
+    }
+}
@@ -0,0 +1,99 @@
+//! Playback handler
+//!
+//! Plays an uploaded WAV file or one from the sounds directory through a
+//! sink, see [`crate::playback`].
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::playback::{self, Playback};
+use crate::pwcli;
+
+/// Largest WAV file accepted for upload
+pub const MAX_UPLOAD_BYTES: usize = 32 * 1024 * 1024;
+
+/// Options of a playback, as JSON body or (for uploads) as query parameters
+#[derive(Debug, Default, Deserialize)]
+pub struct PlayRequest {
+    /// Sink ID or node.name
+    pub sink: Option<String>,
+    /// File name in the sounds directory (JSON body only)
+    pub file: Option<String>,
+    /// Stream volume 0.0 - 1.0
+    pub volume: Option<f32>,
+    /// Respond when the playback ended
+    #[serde(default)]
+    pub wait: bool,
+}
+
+/// Play a WAV file through a sink
+/// POST /api/v1/play
+///
+/// With a JSON body, `file` is taken from the sounds directory. Any other
+/// body is an uploaded WAV file, with `?sink=&volume=&wait=` as options.
+pub async fn play(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PlayRequest>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Playback>, ApiError> {
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let request: PlayRequest = if is_json {
+        serde_json::from_slice(&body).map_err(|e| ApiError::BadRequest(format!("Invalid request: {}", e)))?
+    } else {
+        query
+    };
+    let sink = request.sink.clone().ok_or_else(|| ApiError::BadRequest("sink is required".to_string()))?;
+
+    let (path, data, temporary) = match (is_json, request.file.as_deref()) {
+        (true, Some(file)) => {
+            if !crate::recording::valid_file_name(file) {
+                return Err(ApiError::BadRequest(format!("Invalid file name '{}'", file)));
+            }
+            let directory = playback::sounds_dir(state.get_config().playback.directory.as_deref())
+                .map_err(ApiError::Internal)?;
+            let path = directory.join(file);
+            let data = tokio::fs::read(&path)
+                .await
+                .map_err(|e| ApiError::NotFound(format!("Sound {} not found: {}", file, e)))?;
+            (Some(path), data, false)
+        }
+        (true, None) => return Err(ApiError::BadRequest("file is required".to_string())),
+        (false, _) if body.is_empty() => return Err(ApiError::BadRequest("No WAV file uploaded".to_string())),
+        (false, _) => (None, body.to_vec(), true),
+    };
+    let info = playback::wav_info(&data).map_err(ApiError::BadRequest)?;
+
+    let nodes = crate::worker::run(pwcli::list_nodes)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list nodes: {}", e)))?;
+    let node = playback::find_sink(&nodes, &sink).map_err(|e| {
+        if e.contains("not found") {
+            ApiError::NotFound(e)
+        } else {
+            ApiError::BadRequest(e)
+        }
+    })?;
+
+    let path = match path {
+        Some(path) => path,
+        None => playback::write_upload(&data).await.map_err(ApiError::Internal)?,
+    };
+    let (started, handle) =
+        playback::play(node, path, info, request.volume, temporary).map_err(ApiError::Internal)?;
+    if request.wait {
+        let _ = handle.await;
+    }
+    Ok(Json(started))
+}
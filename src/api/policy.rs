@@ -0,0 +1,294 @@
+//! Policy handlers
+//!
+//! Reports the decisions of the session manager (WirePlumber), see
+//! [`crate::session_manager`], and switches the graph between following the
+//! rate of the streams (bit-perfect playback) and a pinned rate, by writing
+//! `clock.allowed-rates` and `clock.force-rate` to the `settings` metadata,
+//! see [`crate::api::clock`].
+
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api::clock::{self, ClockSettings, RATE_RANGE, SETTINGS_METADATA};
+use crate::api_server::ApiError;
+use crate::pod_parser::ParamObject;
+use crate::pwcli::{self, PwObject};
+use crate::pwmetadata;
+use crate::pwparams;
+use crate::session_manager::{self, DefaultDecision, RestoredStream, SessionManager, StreamTarget};
+
+/// Rates the graph can switch to in follow mode, unless given in the request
+pub const DEFAULT_FOLLOW_RATES: [u32; 6] = [44100, 48000, 88200, 96000, 176400, 192000];
+
+/// Rate used for the default mode when `clock.rate` is not in the metadata
+const FALLBACK_RATE: u32 = 48000;
+
+/// How the graph rate is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateMode {
+    /// The graph runs at `clock.rate`
+    Default,
+    /// The graph switches to the rate of the streams (bit-perfect)
+    Follow,
+    /// The graph is forced to one rate
+    Pinned,
+}
+
+/// Rate of a stream with a negotiated format
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StreamRate {
+    pub id: u32,
+    pub name: String,
+    pub rate: u32,
+}
+
+/// Current sample-rate policy
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SampleRatePolicy {
+    pub mode: RateMode,
+    /// Rate the sinks are running at, None if no sink is running
+    pub graph_rate: Option<u32>,
+    /// Default rate (`clock.rate`)
+    pub default_rate: Option<u32>,
+    /// Forced rate, 0 if not forced
+    pub force_rate: u32,
+    pub allowed_rates: Vec<u32>,
+    /// Playback streams and their rates
+    pub streams: Vec<StreamRate>,
+}
+
+/// Request body for PUT /api/v1/policy/sample-rate
+#[derive(Debug, Deserialize)]
+pub struct SetSampleRatePolicyRequest {
+    pub mode: RateMode,
+    /// Rate to pin (pinned mode)
+    pub rate: Option<u32>,
+    /// Rates to follow (follow mode), defaults to [`DEFAULT_FOLLOW_RATES`]
+    pub rates: Option<Vec<u32>>,
+}
+
+/// Decisions of the session manager
+#[derive(Debug, Serialize)]
+pub struct SessionPolicy {
+    /// None if no session manager is running
+    pub session_manager: Option<SessionManager>,
+    pub defaults: Vec<DefaultDecision>,
+    pub stream_targets: Vec<StreamTarget>,
+    /// State file WirePlumber restores stream volumes and targets from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restore_stream_file: Option<String>,
+    pub restore_stream: Vec<RestoredStream>,
+    /// Explanations, e.g. why links get replaced
+    pub notes: Vec<String>,
+}
+
+/// Derive the mode from the clock settings
+pub fn rate_mode(settings: &ClockSettings) -> RateMode {
+    if settings.force_rate != 0 {
+        RateMode::Pinned
+    } else if settings.allowed_rates.len() > 1 {
+        RateMode::Follow
+    } else {
+        RateMode::Default
+    }
+}
+
+/// Get the rate from the Format parameter of a node
+pub fn format_rate(format: &[ParamObject]) -> Option<u32> {
+    format
+        .first()
+        .and_then(|f| f.properties.get("rate"))
+        .and_then(|r| r.as_u64())
+        .map(|r| r as u32)
+}
+
+/// Format a rate list as PipeWire writes it, e.g. "[ 44100 48000 ]"
+pub fn rates_value(rates: &[u32]) -> String {
+    let rates: Vec<String> = rates.iter().map(|r| r.to_string()).collect();
+    format!("[ {} ]", rates.join(" "))
+}
+
+/// Check the request and get the metadata entries to write
+pub fn policy_entries(
+    request: &SetSampleRatePolicyRequest,
+    settings: &ClockSettings,
+) -> Result<Vec<(&'static str, String)>, String> {
+    let check = |rate: u32| {
+        if RATE_RANGE.contains(&rate) {
+            Ok(())
+        } else {
+            Err(format!("Rate {} is not between {} and {}", rate, RATE_RANGE.start(), RATE_RANGE.end()))
+        }
+    };
+    match request.mode {
+        RateMode::Pinned => {
+            let rate = request.rate.ok_or("Pinned mode needs a rate")?;
+            check(rate)?;
+            Ok(vec![("clock.force-rate", rate.to_string())])
+        }
+        RateMode::Follow => {
+            let rates = request.rates.clone().unwrap_or_else(|| DEFAULT_FOLLOW_RATES.to_vec());
+            if rates.is_empty() {
+                return Err("Follow mode needs at least one rate".to_string());
+            }
+            rates.iter().try_for_each(|r| check(*r))?;
+            Ok(vec![("clock.allowed-rates", rates_value(&rates)), ("clock.force-rate", "0".to_string())])
+        }
+        RateMode::Default => {
+            let rate = settings.rate.unwrap_or(FALLBACK_RATE);
+            Ok(vec![("clock.allowed-rates", rates_value(&[rate])), ("clock.force-rate", "0".to_string())])
+        }
+    }
+}
+
+fn is_sink(obj: &PwObject) -> bool {
+    obj.media_class() == Some("Audio/Sink")
+}
+
+fn is_playback_stream(obj: &PwObject) -> bool {
+    obj.media_class() == Some("Stream/Output/Audio")
+}
+
+/// Read the policy from the settings metadata and the formats of the nodes
+fn read_policy() -> Result<SampleRatePolicy, String> {
+    let entries = pwmetadata::get_metadata(SETTINGS_METADATA)
+        .map_err(|e| format!("Failed to read settings metadata: {}", e))?;
+    let settings = clock::clock_settings_from_entries(&entries);
+    let nodes = pwcli::list_nodes()?;
+    let rate_of = |node: &PwObject| format_rate(&pwparams::enum_params(node.id, "Format").unwrap_or_default());
+
+    let graph_rate = nodes.iter().filter(|n| is_sink(n)).find_map(rate_of);
+    let streams = nodes
+        .iter()
+        .filter(|n| is_playback_stream(n))
+        .filter_map(|n| {
+            Some(StreamRate {
+                id: n.id,
+                name: n.display_name(),
+                rate: rate_of(n)?,
+            })
+        })
+        .collect();
+
+    Ok(SampleRatePolicy {
+        mode: rate_mode(&settings),
+        graph_rate,
+        default_rate: settings.rate,
+        force_rate: settings.force_rate,
+        allowed_rates: settings.allowed_rates,
+        streams,
+    })
+}
+
+/// Collect the decisions of the session manager
+fn read_session_policy() -> Result<SessionPolicy, String> {
+    let session_manager = session_manager::detect(&pwcli::list_clients()?);
+    let entries = pwmetadata::get_metadata("default").map_err(|e| format!("Failed to read default metadata: {}", e))?;
+    let defaults = session_manager::default_decisions(&entries);
+    let stream_targets = session_manager::stream_targets(&entries, &pwcli::list_nodes()?);
+
+    let restore_path = session_manager::restore_stream_path().filter(|p| p.exists());
+    let restore_stream = restore_path
+        .as_ref()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .map(|content| session_manager::parse_restore_stream(&content))
+        .unwrap_or_default();
+
+    Ok(SessionPolicy {
+        notes: session_manager::notes(session_manager.as_ref(), &defaults),
+        session_manager,
+        defaults,
+        stream_targets,
+        restore_stream_file: restore_path.map(|p| p.display().to_string()),
+        restore_stream,
+    })
+}
+
+/// Get the session manager, its default-node decisions, stream targets and
+/// the restored stream state
+/// GET /api/v1/policy
+pub async fn get_session_policy() -> Result<Json<SessionPolicy>, ApiError> {
+    let policy = crate::worker::run(read_session_policy)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(ApiError::Internal)?;
+    Ok(Json(policy))
+}
+
+async fn get_policy() -> Result<SampleRatePolicy, ApiError> {
+    crate::worker::run(read_policy)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(ApiError::Internal)
+}
+
+/// Get the sample-rate policy and the current graph rate
+/// GET /api/v1/policy/sample-rate
+pub async fn get_sample_rate_policy() -> Result<Json<SampleRatePolicy>, ApiError> {
+    Ok(Json(get_policy().await?))
+}
+
+/// Follow the stream rates, pin a rate or go back to the default rate
+/// PUT /api/v1/policy/sample-rate
+pub async fn set_sample_rate_policy(
+    Json(request): Json<SetSampleRatePolicyRequest>,
+) -> Result<Json<SampleRatePolicy>, ApiError> {
+    let entries = crate::worker::run(|| pwmetadata::get_metadata(SETTINGS_METADATA))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to read settings metadata: {}", e)))?;
+    let settings = clock::clock_settings_from_entries(&entries);
+    let writes = policy_entries(&request, &settings).map_err(ApiError::BadRequest)?;
+
+    crate::worker::run(move || -> Result<(), String> {
+        for (key, value) in writes {
+            pwmetadata::set_metadata(SETTINGS_METADATA, 0, key, &Value::String(value))?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Failed to set sample-rate policy: {}", e)))?;
+
+    tracing::info!("Sample-rate policy set to {:?}", request.mode);
+    Ok(Json(get_policy().await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(mode: RateMode, rate: Option<u32>, rates: Option<Vec<u32>>) -> SetSampleRatePolicyRequest {
+        SetSampleRatePolicyRequest { mode, rate, rates }
+    }
+
+    #[test]
+    fn test_rate_mode() {
+        let mut settings = ClockSettings { allowed_rates: vec![48000], ..Default::default() };
+        assert_eq!(rate_mode(&settings), RateMode::Default);
+        settings.allowed_rates = vec![44100, 48000];
+        assert_eq!(rate_mode(&settings), RateMode::Follow);
+        settings.force_rate = 96000;
+        assert_eq!(rate_mode(&settings), RateMode::Pinned);
+    }
+
+    #[test]
+    fn test_policy_entries() {
+        let settings = ClockSettings { rate: Some(44100), ..Default::default() };
+        assert_eq!(
+            policy_entries(&request(RateMode::Pinned, Some(96000), None), &settings).unwrap(),
+            vec![("clock.force-rate", "96000".to_string())]
+        );
+        assert!(policy_entries(&request(RateMode::Pinned, None, None), &settings).is_err());
+        assert!(policy_entries(&request(RateMode::Pinned, Some(1000), None), &settings).is_err());
+
+        let follow = policy_entries(&request(RateMode::Follow, None, Some(vec![44100, 48000])), &settings).unwrap();
+        assert_eq!(follow[0], ("clock.allowed-rates", "[ 44100 48000 ]".to_string()));
+        assert_eq!(follow[1], ("clock.force-rate", "0".to_string()));
+
+        let default = policy_entries(&request(RateMode::Default, None, None), &settings).unwrap();
+        assert_eq!(default[0], ("clock.allowed-rates", "[ 44100 ]".to_string()));
+    }
+}
@@ -0,0 +1,77 @@
+//! Power handlers
+//!
+//! Status of the sinks suspended when idle and overrides, see
+//! [`crate::power`].
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api_server::{ApiError, AppState};
+use crate::power::{self, PowerNodeStatus};
+
+/// Response for GET /api/v1/power
+#[derive(Debug, Serialize)]
+pub struct PowerResponse {
+    /// Sinks are configured in the `[power]` section
+    pub enabled: bool,
+    pub idle_minutes: u64,
+    pub nodes: Vec<PowerNodeStatus>,
+}
+
+/// Request body for PUT /api/v1/power/:name
+#[derive(Debug, Deserialize)]
+pub struct PowerOverrideRequest {
+    pub keep_awake: bool,
+}
+
+fn node_status(state: &AppState, name: &str) -> Result<PowerNodeStatus, ApiError> {
+    state
+        .power
+        .status(Instant::now())
+        .into_iter()
+        .find(|n| n.name == name)
+        .ok_or_else(|| ApiError::NotFound(format!("Sink '{}' is not managed", name)))
+}
+
+/// Get the managed sinks
+/// GET /api/v1/power
+pub async fn get_power(State(state): State<Arc<AppState>>) -> Json<PowerResponse> {
+    Json(PowerResponse {
+        enabled: state.power.is_enabled(),
+        idle_minutes: state.power.idle_minutes(),
+        nodes: state.power.status(Instant::now()),
+    })
+}
+
+/// Keep a sink awake or let it be suspended when idle
+/// PUT /api/v1/power/:name
+pub async fn set_power_override(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(request): Json<PowerOverrideRequest>,
+) -> Result<Json<PowerNodeStatus>, ApiError> {
+    node_status(&state, &name)?;
+    state.power.set_keep_awake(&name, request.keep_awake);
+    Ok(Json(node_status(&state, &name)?))
+}
+
+/// Suspend a sink now
+/// POST /api/v1/power/:name/suspend
+pub async fn suspend_node(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<PowerNodeStatus>, ApiError> {
+    node_status(&state, &name)?;
+    let manager = state.power.clone();
+    let node = name.clone();
+    crate::worker::run(move || power::suspend(&manager, &node))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to suspend {}: {}", name, e)))?;
+    Ok(Json(node_status(&state, &name)?))
+}
@@ -1,33 +1,145 @@
 //! Properties handlers for PipeWire objects
 //!
 //! Uses pw-cli for simple and reliable property listing.
+//! `?fields=` limits the returned property keys, see [`FieldSelection`].
+//! `?include_params=true` adds the Props parameters of the listed nodes.
+//! `POST /api/v1/properties/query` returns several objects in one response.
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
 };
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::api_server::{ApiError, AppState};
+use crate::pod_parser::ParamObject;
 use crate::pwcli;
+use crate::pwparams;
+use super::listing::ListFilter;
 use super::types::*;
 
+/// Selection of property keys to return
+///
+/// Parsed from `?fields=node.name,media.class,object.serial`. A field ending
+/// in `.*` selects all keys with that prefix (e.g. `api.alsa.*`). Without
+/// `fields` all properties are returned.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FieldSelection {
+    fields: Option<Vec<String>>,
+}
+
+impl FieldSelection {
+    /// Parse the `fields` query parameter
+    pub fn from_query(query: &HashMap<String, String>) -> Self {
+        let fields = query.get("fields").map(|f| {
+            f.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        });
+        Self { fields }
+    }
+
+    /// Check whether a property key is selected
+    pub fn includes(&self, key: &str) -> bool {
+        match self.fields {
+            None => true,
+            Some(ref fields) => fields.iter().any(|f| match f.strip_suffix('*') {
+                Some(prefix) => key.starts_with(prefix),
+                None => f == key,
+            }),
+        }
+    }
+
+    /// Reduce a property map to the selected keys
+    pub fn select(&self, properties: &HashMap<String, String>) -> HashMap<String, String> {
+        properties
+            .iter()
+            .filter(|(k, _)| self.includes(k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+}
+
 /// Convert a pwcli::PwObject to PipeWireObjectWithProperties
-fn to_object_with_properties(obj: &pwcli::PwObject) -> PipeWireObjectWithProperties {
+fn to_object_with_properties(obj: &pwcli::PwObject, fields: &FieldSelection) -> PipeWireObjectWithProperties {
     PipeWireObjectWithProperties {
         id: obj.id,
         name: obj.display_name(),
         object_type: pwcli::simplify_type(&obj.object_type).to_string(),
-        properties: obj.properties.clone(),
-        dynamic_properties: None,  // pw-cli doesn't provide dynamic properties
+        properties: fields.select(&obj.properties),
+        dynamic_properties: None,  // filled by add_dynamic_props on request
+    }
+}
+
+/// Most parameter enumerations running at the same time for `?include_params=true`
+const PARAM_JOBS: usize = 4;
+
+/// Merge the Props parameter objects of a node into one map
+pub fn dynamic_props(params: &[ParamObject]) -> HashMap<String, JsonValue> {
+    params
+        .iter()
+        .flat_map(|p| p.properties.iter())
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+/// Split node IDs into at most `jobs` groups of similar size
+fn param_jobs(ids: &[u32], jobs: usize) -> Vec<Vec<u32>> {
+    let count = jobs.max(1).min(ids.len());
+    let mut groups = vec![Vec::new(); count];
+    for (i, id) in ids.iter().enumerate() {
+        groups[i % count].push(*id);
+    }
+    groups
+}
+
+/// Read the Props of the nodes in the listing, at most [`PARAM_JOBS`] at a time
+///
+/// Nodes whose parameters cannot be read keep `dynamic_properties` unset.
+async fn add_dynamic_props(objects: &mut [PipeWireObjectWithProperties]) {
+    let ids: Vec<u32> = objects.iter().filter(|o| o.object_type == "node").map(|o| o.id).collect();
+    let mut tasks = tokio::task::JoinSet::new();
+    for group in param_jobs(&ids, PARAM_JOBS) {
+        tasks.spawn(crate::worker::run(move || {
+            group
+                .into_iter()
+                .filter_map(|id| match pwparams::enum_params(id, "Props") {
+                    Ok(params) => Some((id, dynamic_props(&params))),
+                    Err(e) => {
+                        tracing::debug!("Failed to read Props of node {}: {}", id, e);
+                        None
+                    }
+                })
+                .collect::<Vec<_>>()
+        }));
+    }
+
+    let mut props = HashMap::new();
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(Ok(group)) => props.extend(group),
+            Ok(Err(e)) => tracing::warn!("Worker error reading node Props: {}", e),
+            Err(e) => tracing::warn!("Failed to read node Props: {}", e),
+        }
+    }
+    for object in objects.iter_mut() {
+        object.dynamic_properties = props.remove(&object.id);
     }
 }
 
 /// List all PipeWire objects with their properties
-/// GET /api/v1/properties
+/// GET /api/v1/properties?type=&media_class=&prop.<key>=&limit=&offset=&fields=&include_params=
 pub async fn list_all_properties(
     State(state): State<Arc<AppState>>,
+    Query(query): Query<HashMap<String, String>>,
 ) -> Result<Json<PropertiesResponse>, ApiError> {
+    let filter = ListFilter::from_query(&query).map_err(ApiError::BadRequest)?;
+    let fields = FieldSelection::from_query(&query);
+
     // Try to use cached objects first
     let cached = state.get_cached_objects();
 
@@ -35,42 +147,185 @@ pub async fn list_all_properties(
         cached
     } else {
         // Fall back to fresh query
-        tokio::task::spawn_blocking(|| {
+        crate::worker::run(|| {
             pwcli::list_all()
         })
         .await
-        .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
         .map_err(|e| ApiError::Internal(format!("Failed to list objects: {}", e)))?
     };
 
-    let objects_with_props: Vec<PipeWireObjectWithProperties> = objects.iter()
-        .map(to_object_with_properties)
+    let (total, objects) = filter.apply(objects);
+    let mut objects_with_props: Vec<PipeWireObjectWithProperties> = objects.iter()
+        .map(|o| to_object_with_properties(o, &fields))
         .collect();
+    if query.get("include_params").is_some_and(|v| v == "true") {
+        add_dynamic_props(&mut objects_with_props).await;
+    }
 
-    Ok(Json(PropertiesResponse { objects: objects_with_props }))
+    Ok(Json(PropertiesResponse { objects: objects_with_props, total }))
 }
 
 /// Get properties for a specific object by ID
-/// GET /api/v1/properties/:id
+/// GET /api/v1/properties/:id?fields=
 pub async fn get_object_properties(
     State(state): State<Arc<AppState>>,
     Path(id): Path<u32>,
+    Query(query): Query<HashMap<String, String>>,
 ) -> Result<Json<PipeWireObjectWithProperties>, ApiError> {
+    let fields = FieldSelection::from_query(&query);
+
     // Try cache first
     if let Some(obj) = state.get_object_by_id(id) {
-        return Ok(Json(to_object_with_properties(&obj)));
+        return Ok(Json(to_object_with_properties(&obj, &fields)));
     }
 
     // Fall back to fresh query
-    let result = tokio::task::spawn_blocking(move || {
+    let result = crate::worker::run(move || {
         pwcli::get_object(id)
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?;
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?;
 
     match result {
-        Ok(Some(obj)) => Ok(Json(to_object_with_properties(&obj))),
+        Ok(Some(obj)) => Ok(Json(to_object_with_properties(&obj, &fields))),
         Ok(None) => Err(ApiError::NotFound(format!("Object {} not found", id))),
         Err(e) => Err(ApiError::Internal(format!("Failed to get object: {}", e))),
     }
 }
+
+/// Most objects accepted by one query
+const MAX_QUERY_OBJECTS: usize = 500;
+
+/// Request body for POST /api/v1/properties/query
+#[derive(Debug, Default, Deserialize)]
+pub struct PropertiesQuery {
+    /// Object IDs
+    #[serde(default)]
+    pub ids: Vec<u32>,
+    /// Object names (`node.name`, `device.name`, ...)
+    #[serde(default)]
+    pub names: Vec<String>,
+}
+
+/// Response for POST /api/v1/properties/query
+#[derive(Debug, Serialize)]
+pub struct PropertiesQueryResponse {
+    /// Found objects, in the order of the request (IDs first, then names)
+    pub objects: Vec<PipeWireObjectWithProperties>,
+    /// Requested IDs and names that were not found
+    pub missing: Vec<String>,
+}
+
+/// Look up the requested objects, returning them and the missing entries
+pub fn query_objects<'a>(objects: &'a [pwcli::PwObject], query: &PropertiesQuery) -> (Vec<&'a pwcli::PwObject>, Vec<String>) {
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for id in &query.ids {
+        match objects.iter().find(|o| o.id == *id) {
+            Some(obj) => found.push(obj),
+            None => missing.push(id.to_string()),
+        }
+    }
+    for name in &query.names {
+        match objects.iter().find(|o| o.name() == Some(name.as_str())) {
+            Some(obj) => found.push(obj),
+            None => missing.push(name.clone()),
+        }
+    }
+    (found, missing)
+}
+
+/// Get the properties of several objects by ID or name in one call
+/// POST /api/v1/properties/query?fields=
+pub async fn query_properties(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(query): Json<PropertiesQuery>,
+) -> Result<Json<PropertiesQueryResponse>, ApiError> {
+    let count = query.ids.len() + query.names.len();
+    if count == 0 {
+        return Err(ApiError::BadRequest("Specify ids and/or names".to_string()));
+    }
+    if count > MAX_QUERY_OBJECTS {
+        return Err(ApiError::BadRequest(format!("At most {} objects per query", MAX_QUERY_OBJECTS)));
+    }
+    let fields = FieldSelection::from_query(&params);
+
+    // Use the cache if it has all objects, otherwise list them once
+    let mut objects = state.get_cached_objects();
+    if objects.is_empty() || !query_objects(&objects, &query).1.is_empty() {
+        objects = crate::worker::run(pwcli::list_all)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+            .map_err(|e| ApiError::Internal(format!("Failed to list objects: {}", e)))?;
+    }
+
+    let (found, missing) = query_objects(&objects, &query);
+    Ok(Json(PropertiesQueryResponse {
+        objects: found.into_iter().map(|o| to_object_with_properties(o, &fields)).collect(),
+        missing,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(fields: &str) -> HashMap<String, String> {
+        HashMap::from([("fields".to_string(), fields.to_string())])
+    }
+
+    #[test]
+    fn test_field_selection() {
+        let properties: HashMap<String, String> = [
+            ("node.name", "alsa_output.hw0"),
+            ("media.class", "Audio/Sink"),
+            ("api.alsa.card", "0"),
+            ("api.alsa.pcm.device", "0"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let selected = FieldSelection::from_query(&query("node.name, media.class")).select(&properties);
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected.get("media.class").map(|s| s.as_str()), Some("Audio/Sink"));
+
+        let selected = FieldSelection::from_query(&query("api.alsa.*")).select(&properties);
+        assert_eq!(selected.len(), 2);
+
+        let selected = FieldSelection::from_query(&HashMap::new()).select(&properties);
+        assert_eq!(selected, properties);
+    }
+
+    #[test]
+    fn test_query_objects() {
+        let object = |id: u32, name: &str| pwcli::PwObject::new(id, "PipeWire:Interface:Node", &[("node.name", name)]);
+        let objects = vec![object(40, "alsa_output"), object(41, "speakereq2x2")];
+        let query = PropertiesQuery { ids: vec![41, 99], names: vec!["alsa_output".to_string(), "gone".to_string()] };
+
+        let (found, missing) = query_objects(&objects, &query);
+        assert_eq!(found.iter().map(|o| o.id).collect::<Vec<_>>(), vec![41, 40]);
+        assert_eq!(missing, vec!["99", "gone"]);
+    }
+
+    #[test]
+    fn test_dynamic_props() {
+        let param = |json: serde_json::Value| ParamObject {
+            object_type: "Props".to_string(),
+            id: "Props".to_string(),
+            properties: json.as_object().unwrap().clone(),
+        };
+        let props = dynamic_props(&[
+            param(serde_json::json!({"volume": 0.5, "mute": false})),
+            param(serde_json::json!({"params": {"eq:Gain": 1.0}})),
+        ]);
+        assert_eq!(props.len(), 3);
+        assert_eq!(props["volume"], serde_json::json!(0.5));
+
+        let groups = param_jobs(&[1, 2, 3, 4, 5], 2);
+        assert_eq!(groups, vec![vec![1, 3, 5], vec![2, 4]]);
+        assert!(param_jobs(&[], 4).is_empty());
+    }
+}
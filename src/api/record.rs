@@ -0,0 +1,135 @@
+//! Recording handlers
+//!
+//! Records the audio of a node into a file on the device, see
+//! [`crate::recording`].
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+
+use crate::api::capture::{capture_spec, CaptureQuery};
+use crate::api_server::{ApiError, AppState};
+use crate::capture::{self, CaptureFormat};
+use crate::recording::{self, RecordingStatus};
+
+/// Request body for POST /api/v1/record
+#[derive(Debug, Deserialize)]
+pub struct StartRecordingRequest {
+    pub node_id: u32,
+    /// wav (default) or flac
+    #[serde(default)]
+    pub format: CaptureFormat,
+    pub rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// Seconds to record (default: until stopped, at most 4 hours)
+    pub duration: Option<u64>,
+    /// File name in the recordings directory (default: node name and time)
+    pub name: Option<String>,
+}
+
+/// Response for GET /api/v1/record
+#[derive(Debug, Serialize)]
+pub struct RecordingsResponse {
+    pub directory: String,
+    pub recordings: Vec<RecordingStatus>,
+}
+
+fn not_found(id: u32) -> ApiError {
+    ApiError::NotFound(format!("Recording {} not found", id))
+}
+
+/// Start recording a node into a file
+/// POST /api/v1/record
+pub async fn start_recording(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<StartRecordingRequest>,
+) -> Result<Json<RecordingStatus>, ApiError> {
+    if let Some(ref name) = request.name {
+        if !recording::valid_file_name(name) {
+            return Err(ApiError::BadRequest(format!(
+                "Invalid file name '{}' (letters, digits, '-', '_' and '.')",
+                name
+            )));
+        }
+    }
+    let query = CaptureQuery {
+        format: request.format,
+        rate: request.rate,
+        channels: request.channels,
+        duration: request.duration,
+    };
+    let mut spec = capture_spec(request.node_id, &query, recording::MAX_RECORD_SECS).await?;
+    spec.duration_secs = Some(spec.duration_secs.unwrap_or(recording::MAX_RECORD_SECS));
+    let file = recording::file_name(request.name.as_deref(), &spec.target, spec.format, SystemTime::now());
+
+    state.recordings.start(request.node_id, spec, file).await.map(Json).map_err(|e| {
+        if e.contains("already exists") {
+            ApiError::Conflict(e)
+        } else {
+            ApiError::Internal(format!("Failed to start recording: {}", e))
+        }
+    })
+}
+
+/// List the recordings
+/// GET /api/v1/record
+pub async fn list_recordings(State(state): State<Arc<AppState>>) -> Result<Json<RecordingsResponse>, ApiError> {
+    let directory = state.recordings.directory().map_err(ApiError::Internal)?;
+    Ok(Json(RecordingsResponse {
+        directory: directory.display().to_string(),
+        recordings: state.recordings.list(),
+    }))
+}
+
+/// Get the status of a recording
+/// GET /api/v1/record/:id
+pub async fn get_recording(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+) -> Result<Json<RecordingStatus>, ApiError> {
+    state.recordings.status(id).map(Json).ok_or_else(|| not_found(id))
+}
+
+/// Stop a recording
+/// POST /api/v1/record/:id/stop
+pub async fn stop_recording(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+) -> Result<Json<RecordingStatus>, ApiError> {
+    state.recordings.stop(id).map(Json).ok_or_else(|| not_found(id))
+}
+
+/// Download the file of a recording
+/// GET /api/v1/record/:id/download
+pub async fn download_recording(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+) -> Result<Response, ApiError> {
+    let (path, format) = state.recordings.file(id).ok_or_else(|| not_found(id))?;
+    let file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|e| ApiError::NotFound(format!("Failed to open {}: {}", path.display(), e)))?;
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+    let (sender, receiver) = mpsc::channel(8);
+    tokio::spawn(async move { capture::forward(file, &sender).await });
+    let body = Body::from_stream(ReceiverStream::new(receiver).map(Ok::<_, Infallible>));
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name)),
+        ],
+        body,
+    )
+        .into_response())
+}
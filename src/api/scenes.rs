@@ -0,0 +1,66 @@
+//! Scene handlers
+//!
+//! List and activate scenes, see [`crate::scenes`].
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::scenes::{self, ActivationResult, SceneStatus};
+
+/// Response for GET /api/v1/scenes
+#[derive(Debug, Serialize)]
+pub struct ScenesResponse {
+    /// Last successfully activated scene
+    pub active: Option<String>,
+    pub scenes: Vec<SceneStatus>,
+}
+
+/// List all scenes
+/// GET /api/v1/scenes
+pub async fn list_scenes(State(state): State<Arc<AppState>>) -> Json<ScenesResponse> {
+    let scenes = state.get_scenes();
+    Json(ScenesResponse {
+        active: scenes.active(),
+        scenes: scenes.list(),
+    })
+}
+
+/// Get a scene
+/// GET /api/v1/scenes/:name
+pub async fn get_scene(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<SceneStatus>, ApiError> {
+    state
+        .get_scenes()
+        .list()
+        .into_iter()
+        .find(|s| s.config.name == name)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Scene '{}' not found", name)))
+}
+
+/// Activate a scene, rolling back on failure
+/// POST /api/v1/scenes/:name/activate
+pub async fn activate_scene(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<ActivationResult>, ApiError> {
+    let manager = state.get_scenes();
+    let scene = manager
+        .get(&name)
+        .ok_or_else(|| ApiError::NotFound(format!("Scene '{}' not found", name)))?;
+    let speakereq = state
+        .module_state("speakereq")
+        .ok_or_else(|| ApiError::Internal("speakereq module is not registered".to_string()))?;
+    let riaa = state
+        .module_state("riaa")
+        .ok_or_else(|| ApiError::Internal("riaa module is not registered".to_string()))?;
+
+    Ok(Json(scenes::activate(&manager, &scene, speakereq, riaa).await))
+}
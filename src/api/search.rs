@@ -0,0 +1,166 @@
+//! Search handler
+//!
+//! Case-insensitive substring search over names and property values of all
+//! cached PipeWire objects, e.g. to find a device by "hdmi" or "usb".
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::pwcli;
+
+/// Query parameters for GET /api/v1/search
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// Text to search for
+    pub q: String,
+    /// Restrict hits to an object type (node, device, port, ...)
+    #[serde(rename = "type")]
+    pub object_type: Option<String>,
+    /// Maximum number of hits
+    pub limit: Option<usize>,
+}
+
+/// A field of an object that matched the search text
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SearchMatch {
+    /// "name" or the matching property key
+    pub field: String,
+    pub value: String,
+}
+
+/// An object matching the search text
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SearchHit {
+    pub id: u32,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub object_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_class: Option<String>,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Response for GET /api/v1/search
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub query: String,
+    pub hits: Vec<SearchHit>,
+}
+
+/// Match an object against lowercase search text
+fn match_object(obj: &pwcli::PwObject, needle: &str) -> Option<SearchHit> {
+    let name = obj.display_name();
+    let mut matches = Vec::new();
+
+    if name.to_lowercase().contains(needle) {
+        matches.push(SearchMatch {
+            field: "name".to_string(),
+            value: name.clone(),
+        });
+    }
+
+    let mut keys: Vec<&String> = obj.properties.keys().collect();
+    keys.sort();
+    for key in keys {
+        let value = &obj.properties[key];
+        if value.to_lowercase().contains(needle) {
+            matches.push(SearchMatch {
+                field: key.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    if matches.is_empty() {
+        return None;
+    }
+
+    Some(SearchHit {
+        id: obj.id,
+        name,
+        object_type: pwcli::simplify_type(&obj.object_type).to_string(),
+        media_class: obj.media_class().map(|s| s.to_string()),
+        matches,
+    })
+}
+
+/// Search objects, name matches first, then by ID
+pub fn search_objects(objects: &[pwcli::PwObject], text: &str, object_type: Option<&str>) -> Vec<SearchHit> {
+    let needle = text.trim().to_lowercase();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let mut hits: Vec<SearchHit> = objects
+        .iter()
+        .filter(|o| {
+            object_type
+                .map(|t| pwcli::simplify_type(&o.object_type).eq_ignore_ascii_case(t))
+                .unwrap_or(true)
+        })
+        .filter_map(|o| match_object(o, &needle))
+        .collect();
+
+    hits.sort_by_key(|h| (h.matches[0].field != "name", h.id));
+    hits
+}
+
+/// Search all objects by name and property values
+/// GET /api/v1/search?q=hdmi&type=node&limit=10
+pub async fn search(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, ApiError> {
+    if query.q.trim().is_empty() {
+        return Err(ApiError::BadRequest("Search text 'q' must not be empty".to_string()));
+    }
+
+    let cached = state.get_cached_objects();
+    let objects = if !cached.is_empty() {
+        cached
+    } else {
+        crate::worker::run(pwcli::list_all)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+            .map_err(|e| ApiError::Internal(format!("Failed to list objects: {}", e)))?
+    };
+
+    let mut hits = search_objects(&objects, &query.q, query.object_type.as_deref());
+    if let Some(limit) = query.limit {
+        hits.truncate(limit);
+    }
+
+    Ok(Json(SearchResponse { query: query.q, hits }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pwcli::PwObject;
+
+    #[test]
+    fn test_search_objects() {
+        let objects = vec![
+            PwObject::new(40, "Node", &[("node.name", "alsa_output.hdmi-stereo"), ("media.class", "Audio/Sink")]),
+            PwObject::new(30, "Device", &[("device.name", "alsa_card.0"), ("device.description", "Built-in HDMI")]),
+            PwObject::new(50, "Node", &[("node.name", "speakereq2x2")]),
+        ];
+
+        let hits = search_objects(&objects, "HDMI", None);
+        assert_eq!(hits.iter().map(|h| h.id).collect::<Vec<_>>(), vec![40, 30]);
+        assert_eq!(hits[0].object_type, "node");
+        assert_eq!(hits[0].matches[0].field, "name");
+        assert_eq!(hits[1].matches[0].field, "device.description");
+
+        let hits = search_objects(&objects, "hdmi", Some("device"));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, 30);
+
+        assert!(search_objects(&objects, "  ", None).is_empty());
+    }
+}
@@ -0,0 +1,27 @@
+//! Startup status handler
+//!
+//! Reports which startup rules have been applied, see [`crate::startup`].
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::api_server::AppState;
+use crate::startup::StartupEntry;
+
+/// Response for GET /api/v1/startup/status
+#[derive(Debug, Serialize)]
+pub struct StartupStatusResponse {
+    /// All rules have been applied or timed out
+    pub complete: bool,
+    pub rules: Vec<StartupEntry>,
+}
+
+/// Get the state of the startup rules
+/// GET /api/v1/startup/status
+pub async fn get_startup_status(State(state): State<Arc<AppState>>) -> Json<StartupStatusResponse> {
+    Json(StartupStatusResponse {
+        complete: state.startup.is_complete(),
+        rules: state.startup.entries(),
+    })
+}
@@ -0,0 +1,142 @@
+//! Statistics handlers
+//!
+//! Stream activity per application (see [`crate::stream_stats`]), xruns
+//! and quanta of the nodes (see [`crate::xrun_stats`]) and the DSP load of
+//! the graph (see [`crate::dsp_load`]), and the long-term history of
+//! metrics (see [`crate::metrics_history`]).
+
+use axum::{extract::Query, Json};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api_server::ApiError;
+use crate::dsp_load::{self, LoadReport};
+use crate::metrics_history::{self, Point};
+use crate::stream_stats::{self, AppStats};
+use crate::xrun_stats::{self, XrunReport};
+
+/// Window used without `?window=`
+const DEFAULT_WINDOW: Duration = Duration::from_secs(24 * 3600);
+
+/// Query parameters for GET /api/v1/stats/streams
+#[derive(Debug, Default, Deserialize)]
+pub struct StreamStatsQuery {
+    /// Duration like "24h" or "30m" (default 24h, at most 7 days)
+    pub window: Option<String>,
+}
+
+/// Response for GET /api/v1/stats/streams
+#[derive(Debug, Serialize)]
+pub struct StreamStatsResponse {
+    pub window_secs: u64,
+    /// Start of the window (RFC 3339)
+    pub since: String,
+    /// Start of the recording, activity before is unknown (RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracking_since: Option<String>,
+    pub total_playtime_secs: u64,
+    pub applications: Vec<AppStats>,
+}
+
+/// Query parameters for GET /api/v1/stats/history
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryQuery {
+    /// volume, xruns or playtime
+    pub metric: Option<String>,
+    /// Duration like "24h" or "7d" (default 24h)
+    pub range: Option<String>,
+}
+
+/// Response for GET /api/v1/stats/history
+#[derive(Debug, Serialize)]
+pub struct HistoryResponse {
+    pub metric: String,
+    pub range_secs: u64,
+    /// Time span of a point
+    pub bucket_secs: u64,
+    pub points: Vec<Point>,
+}
+
+/// Parse a window like "24h", limited to the sessions kept
+pub fn parse_window(window: Option<&str>) -> Result<Duration, String> {
+    let window = match window {
+        Some(window) => humantime::parse_duration(window)
+            .map_err(|_| format!("Invalid window '{}', use a duration like 24h", window))?,
+        None => DEFAULT_WINDOW,
+    };
+    if window.is_zero() || window > stream_stats::MAX_WINDOW {
+        return Err(format!(
+            "Window must be between 1s and {}",
+            humantime::format_duration(stream_stats::MAX_WINDOW)
+        ));
+    }
+    Ok(window)
+}
+
+/// Get the playtime and sessions per application
+/// GET /api/v1/stats/streams?window=24h
+pub async fn get_stream_stats(Query(query): Query<StreamStatsQuery>) -> Result<Json<StreamStatsResponse>, ApiError> {
+    let window = parse_window(query.window.as_deref()).map_err(ApiError::BadRequest)?;
+    let since = SystemTime::now().checked_sub(window).unwrap_or(UNIX_EPOCH);
+    let since_ms = since.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_millis() as u64;
+
+    let applications = stream_stats::stats(since_ms);
+    Ok(Json(StreamStatsResponse {
+        window_secs: window.as_secs(),
+        since: humantime::format_rfc3339_seconds(since).to_string(),
+        tracking_since: stream_stats::tracking_since(),
+        total_playtime_secs: applications.iter().map(|a| a.playtime_secs).sum(),
+        applications,
+    }))
+}
+
+/// Get the xruns per node and the quantum and rate history of the drivers
+/// GET /api/v1/stats/xruns
+pub async fn get_xrun_stats() -> Result<Json<XrunReport>, ApiError> {
+    Ok(Json(xrun_stats::report()))
+}
+
+/// Get the DSP load of the running drivers from a fresh profiler sample
+/// GET /api/v1/stats/load
+pub async fn get_load() -> Result<Json<LoadReport>, ApiError> {
+    let nodes = crate::worker::run(crate::pwtop::sample)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(ApiError::Internal)?;
+    let sampled = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    Ok(Json(dsp_load::load_report(&nodes, sampled)))
+}
+
+/// Get the values of a metric over a time range
+/// GET /api/v1/stats/history?metric=volume&range=24h
+pub async fn get_history(Query(query): Query<HistoryQuery>) -> Result<Json<HistoryResponse>, ApiError> {
+    let metric = query.metric.ok_or_else(|| {
+        ApiError::BadRequest(format!("Missing metric, use one of: {}", metrics_history::METRICS.join(", ")))
+    })?;
+    let range = match query.range.as_deref() {
+        Some(range) => humantime::parse_duration(range)
+            .map_err(|_| ApiError::BadRequest(format!("Invalid range '{}', use a duration like 24h", range)))?,
+        None => DEFAULT_WINDOW,
+    };
+    let since_ms = crate::store::now_ms().saturating_sub(range.as_millis() as u64);
+    let (bucket_secs, points) = metrics_history::query(&metric, since_ms)
+        .ok_or_else(|| {
+            ApiError::BadRequest("The metrics history is disabled, set history = true in the [stats] section".to_string())
+        })?
+        .map_err(ApiError::BadRequest)?;
+    Ok(Json(HistoryResponse { metric, range_secs: range.as_secs(), bucket_secs, points }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(parse_window(None).unwrap(), Duration::from_secs(86400));
+        assert_eq!(parse_window(Some("30m")).unwrap(), Duration::from_secs(1800));
+        assert!(parse_window(Some("8days")).is_err());
+        assert!(parse_window(Some("0s")).is_err());
+        assert!(parse_window(Some("yesterday")).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+//! Status summary handler
+//!
+//! One call for a control panel home screen, see [`crate::status`].
+
+use axum::{extract::State, Json};
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::status::{self, StatusSummary};
+
+/// Get default sink/source, streams, module states and PipeWire version
+/// GET /api/v1/status
+pub async fn get_status(State(state): State<Arc<AppState>>) -> Result<Json<StatusSummary>, ApiError> {
+    let modules = state.get_modules();
+    crate::worker::run(move || status::collect(&modules))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map(Json)
+        .map_err(|e| ApiError::Internal(format!("Failed to collect status: {}", e)))
+}
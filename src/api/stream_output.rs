@@ -0,0 +1,45 @@
+//! Stream output handlers
+//!
+//! Switches the null sink feeding an external streaming server on and off,
+//! see [`crate::stream_output`].
+
+use axum::{extract::State, Json};
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::stream_output::{SetStreamOutputRequest, StreamOutputStatus};
+
+/// Get the state of the stream output
+/// GET /api/v1/stream-output
+pub async fn get_stream_output(State(state): State<Arc<AppState>>) -> Result<Json<StreamOutputStatus>, ApiError> {
+    let manager = state.stream_output.clone();
+    crate::worker::run(move || manager.status())
+        .await
+        .map(Json)
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))
+}
+
+/// Enable or disable the stream output
+/// PUT /api/v1/stream-output
+pub async fn set_stream_output(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SetStreamOutputRequest>,
+) -> Result<Json<StreamOutputStatus>, ApiError> {
+    if request.enabled && !state.stream_output.is_configured() {
+        return Err(ApiError::BadRequest(
+            "No stream output command configured ([stream_output] command)".to_string(),
+        ));
+    }
+    let manager = state.stream_output.clone();
+    crate::worker::run(move || {
+        if request.enabled {
+            manager.start()
+        } else {
+            Ok(manager.stop())
+        }
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map(Json)
+    .map_err(|e| ApiError::Internal(format!("Failed to start stream output: {}", e)))
+}
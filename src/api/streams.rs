@@ -0,0 +1,217 @@
+//! Stream target handlers
+//!
+//! Assigns a stream to a sink (or a capture stream to a source) by writing
+//! `target.object` for the stream to the `default` metadata instead of
+//! linking its ports. The session manager then moves the stream itself and
+//! keeps the assignment when it re-links the stream; WirePlumber also stores
+//! it in its restore-stream state for the next time the application plays.
+//! Links made by hand are replaced in these cases, see
+//! [`crate::session_manager`] and [`crate::reverts`].
+
+use axum::{extract::Path, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api_server::ApiError;
+use crate::pwcli::{self, PwObject};
+use crate::pwmetadata;
+use crate::session_manager::TARGET_KEYS;
+
+/// Metadata holding the stream targets
+pub const TARGET_METADATA: &str = "default";
+
+/// Request body for PUT /api/v1/streams/:id/target
+#[derive(Debug, Deserialize)]
+pub struct SetStreamTargetRequest {
+    /// Node name or ID of the sink or source
+    pub target: String,
+}
+
+/// Target of a stream
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StreamTargetResponse {
+    pub id: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_class: Option<String>,
+    /// Node name of the target, None if the session manager chooses (default node)
+    pub target: Option<String>,
+    /// ID of the target node, None if it does not exist at the moment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<u32>,
+}
+
+/// Check that a node is a stream
+pub fn check_stream(node: &PwObject) -> Result<(), String> {
+    match node.media_class() {
+        Some(class) if class.starts_with("Stream/") => Ok(()),
+        class => Err(format!("Node {} is not a stream (media.class {})", node.id, class.unwrap_or("none"))),
+    }
+}
+
+/// Check that a node can be the target of a stream: playback streams play
+/// to sinks, capture streams record from sources or the monitor of a sink
+pub fn check_target(stream: &PwObject, target: &PwObject) -> Result<(), String> {
+    let class = target.media_class().unwrap_or_default();
+    let playback = stream.media_class().is_some_and(|c| c.starts_with("Stream/Output/"));
+    let valid = if playback {
+        class.ends_with("/Sink") || class.ends_with("/Duplex")
+    } else {
+        class.ends_with("/Sink") || class.ends_with("/Source") || class.ends_with("/Duplex") || class.contains("/Source/")
+    };
+    if !valid {
+        return Err(format!(
+            "Node '{}' ({}) cannot be the target of a {} stream",
+            target.display_name(),
+            if class.is_empty() { "no media.class" } else { class },
+            if playback { "playback" } else { "capture" },
+        ));
+    }
+    target.get("node.name").map(|_| ()).ok_or_else(|| format!("Node {} has no node.name", target.id))
+}
+
+/// Node name of the target in a metadata value (plain name, serial or ID)
+fn target_name(value: &Value, nodes: &[PwObject]) -> Option<String> {
+    let text = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        _ => return None,
+    };
+    let node = nodes.iter().find(|n| {
+        n.get("node.name") == Some(text.as_str())
+            || n.get("object.serial") == Some(text.as_str())
+            || n.id.to_string() == text
+    });
+    Some(node.and_then(|n| n.get("node.name")).unwrap_or(&text).to_string())
+}
+
+fn response(stream: &PwObject, target: Option<String>, nodes: &[PwObject]) -> StreamTargetResponse {
+    let target_id = target
+        .as_deref()
+        .and_then(|t| nodes.iter().find(|n| n.get("node.name") == Some(t)))
+        .map(|n| n.id);
+    StreamTargetResponse {
+        id: stream.id,
+        name: stream.display_name(),
+        media_class: stream.media_class().map(|s| s.to_string()),
+        target,
+        target_id,
+    }
+}
+
+/// The stream with this ID among the nodes
+fn find_stream(nodes: &[PwObject], id: u32) -> Result<&PwObject, ApiError> {
+    let stream = nodes
+        .iter()
+        .find(|n| n.id == id)
+        .ok_or_else(|| ApiError::NotFound(format!("Stream {} not found", id)))?;
+    check_stream(stream).map_err(ApiError::BadRequest)?;
+    Ok(stream)
+}
+
+fn list_nodes() -> Result<Vec<PwObject>, ApiError> {
+    pwcli::list_nodes().map_err(|e| ApiError::Internal(format!("Failed to list nodes: {}", e)))
+}
+
+/// Get the target of a stream
+/// GET /api/v1/streams/:id/target
+pub async fn get_stream_target(Path(id): Path<u32>) -> Result<Json<StreamTargetResponse>, ApiError> {
+    crate::worker::run(move || {
+        let nodes = list_nodes()?;
+        let stream = find_stream(&nodes, id)?;
+        let entries = pwmetadata::get_metadata(TARGET_METADATA)
+            .map_err(|e| ApiError::Internal(format!("Failed to read metadata: {}", e)))?;
+        // target.object takes precedence over the deprecated target.node
+        let target = TARGET_KEYS
+            .iter()
+            .find_map(|key| entries.iter().find(|e| e.subject == id && e.key == *key))
+            .and_then(|e| target_name(&e.value, &nodes));
+        Ok(Json(response(stream, target, &nodes)))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+}
+
+/// Assign a stream to a sink or source by its target.object
+/// PUT /api/v1/streams/:id/target
+pub async fn set_stream_target(
+    Path(id): Path<u32>,
+    Json(request): Json<SetStreamTargetRequest>,
+) -> Result<Json<StreamTargetResponse>, ApiError> {
+    crate::worker::run(move || {
+        let nodes = list_nodes()?;
+        let stream = find_stream(&nodes, id)?;
+        let target = nodes
+            .iter()
+            .find(|n| n.get("node.name") == Some(request.target.as_str()) || n.id.to_string() == request.target)
+            .ok_or_else(|| ApiError::NotFound(format!("Node '{}' not found", request.target)))?;
+        check_target(stream, target).map_err(ApiError::BadRequest)?;
+        let name = target.get("node.name").unwrap_or_default().to_string();
+
+        pwmetadata::set_metadata(TARGET_METADATA, id, "target.object", &Value::String(name.clone()))
+            .map_err(|e| ApiError::Internal(format!("Failed to set target.object: {}", e)))?;
+        // A left-over target.node would conflict with the new target
+        let _ = pwmetadata::delete_metadata(TARGET_METADATA, id, "target.node");
+        Ok(Json(response(stream, Some(name), &nodes)))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+}
+
+/// Remove the target of a stream, the session manager moves it to the default node
+/// DELETE /api/v1/streams/:id/target
+pub async fn delete_stream_target(Path(id): Path<u32>) -> Result<Json<StreamTargetResponse>, ApiError> {
+    crate::worker::run(move || {
+        let nodes = list_nodes()?;
+        let stream = find_stream(&nodes, id)?;
+        for key in TARGET_KEYS {
+            let _ = pwmetadata::delete_metadata(TARGET_METADATA, id, key);
+        }
+        Ok(Json(response(stream, None, &nodes)))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: u32, name: &str, class: &str) -> PwObject {
+        let serial = (id + 1000).to_string();
+        let properties = [("node.name", name), ("media.class", class), ("object.serial", serial.as_str())];
+        PwObject::new(id, "PipeWire:Interface:Node", &properties)
+    }
+
+    #[test]
+    fn test_check_target() {
+        let playback = node(71, "spotify", "Stream/Output/Audio");
+        let capture = node(72, "recorder", "Stream/Input/Audio");
+        let sink = node(48, "alsa_output.usb", "Audio/Sink");
+        let source = node(49, "alsa_input.usb", "Audio/Source");
+
+        assert!(check_stream(&playback).is_ok());
+        assert!(check_stream(&sink).is_err());
+        assert!(check_target(&playback, &sink).is_ok());
+        assert!(check_target(&playback, &source).is_err());
+        assert!(check_target(&capture, &source).is_ok());
+        // Recording the monitor of a sink
+        assert!(check_target(&capture, &sink).is_ok());
+        assert!(check_target(&playback, &capture).is_err());
+    }
+
+    #[test]
+    fn test_target_name() {
+        let nodes = vec![node(48, "alsa_output.usb", "Audio/Sink")];
+        assert_eq!(target_name(&Value::String("alsa_output.usb".into()), &nodes).as_deref(), Some("alsa_output.usb"));
+        // Serial and ID are resolved to the name
+        assert_eq!(target_name(&Value::String("1048".into()), &nodes).as_deref(), Some("alsa_output.usb"));
+        assert_eq!(target_name(&serde_json::json!(48), &nodes).as_deref(), Some("alsa_output.usb"));
+        // Targets that are gone are kept
+        assert_eq!(target_name(&Value::String("alsa_output.hdmi".into()), &nodes).as_deref(), Some("alsa_output.hdmi"));
+
+        let stream = node(71, "spotify", "Stream/Output/Audio");
+        let response = response(&stream, Some("alsa_output.usb".to_string()), &nodes);
+        assert_eq!(response.target_id, Some(48));
+    }
+}
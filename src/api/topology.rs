@@ -0,0 +1,43 @@
+//! Topology handlers
+//!
+//! Export the current routing as JSON and apply a routing declaratively.
+
+use axum::Json;
+use serde::Deserialize;
+
+use crate::api_server::ApiError;
+use crate::topology::{self, ApplyResult, Topology};
+
+/// Request body for POST /api/v1/topology/apply
+#[derive(Debug, Deserialize)]
+pub struct ApplyTopologyRequest {
+    #[serde(flatten)]
+    pub topology: Topology,
+    /// Remove links of the referenced nodes that are not in the topology
+    #[serde(default)]
+    pub prune: bool,
+}
+
+/// Export the current topology
+/// GET /api/v1/topology/export
+pub async fn export_topology() -> Result<Json<Topology>, ApiError> {
+    let topology = crate::worker::run(topology::export)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to export topology: {}", e)))?;
+
+    Ok(Json(topology))
+}
+
+/// Apply a topology
+/// POST /api/v1/topology/apply
+pub async fn apply_topology(
+    Json(request): Json<ApplyTopologyRequest>,
+) -> Result<Json<ApplyResult>, ApiError> {
+    let result = crate::worker::run(move || topology::apply(&request.topology, request.prune))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to apply topology: {}", e)))?;
+
+    Ok(Json(result))
+}
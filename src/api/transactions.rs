@@ -0,0 +1,40 @@
+//! Transaction handlers
+//!
+//! Apply several changes together, see [`crate::transactions`].
+
+use axum::{extract::State, Json};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::transactions::{self, Operation, TransactionResult};
+
+/// Request for POST /api/v1/transactions
+#[derive(Debug, Deserialize)]
+pub struct TransactionRequest {
+    pub operations: Vec<Operation>,
+}
+
+/// Validate and apply a list of operations, rolling back on failure
+/// POST /api/v1/transactions
+pub async fn run_transaction(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<TransactionRequest>,
+) -> Result<Json<TransactionResult>, ApiError> {
+    let speakereq = state
+        .module_state("speakereq")
+        .ok_or_else(|| ApiError::Internal("speakereq module is not registered".to_string()))?;
+
+    // Serialize with other SpeakerEQ writes (see crate::revision)
+    let _guard = if request.operations.iter().any(Operation::uses_speakereq) {
+        Some(speakereq.write_lock.lock().await)
+    } else {
+        None
+    };
+
+    let node = speakereq.clone();
+    let result = crate::worker::run(move || transactions::execute(&request.operations, &node))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))??;
+    Ok(Json(result))
+}
@@ -29,6 +29,9 @@ pub struct PipeWireObject {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListResponse {
     pub objects: Vec<PipeWireObject>,
+    /// Number of objects matching the filters, before limit/offset
+    #[serde(default)]
+    pub total: usize,
 }
 
 /// PipeWire object with full properties
@@ -47,6 +50,9 @@ pub struct PipeWireObjectWithProperties {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PropertiesResponse {
     pub objects: Vec<PipeWireObjectWithProperties>,
+    /// Number of objects matching the filters, before limit/offset
+    #[serde(default)]
+    pub total: usize,
 }
 
 /// Device information with optional volume
@@ -89,4 +95,7 @@ pub struct SetVolumeRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VolumeResponse {
     pub volume: Option<f32>,
+    /// Set if the session manager reverted the volume (see [`crate::reverts`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
 }
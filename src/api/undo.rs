@@ -0,0 +1,61 @@
+//! Undo/redo handlers
+//!
+//! Revert and reapply recent DSP and volume changes, see [`crate::undo`].
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState, NodeState};
+use crate::undo::{self, HistoryStatus, UndoEntry};
+
+/// Response for POST /api/v1/undo and /api/v1/redo
+#[derive(Debug, Serialize)]
+pub struct UndoResponse {
+    /// The change that was undone or redone
+    pub change: UndoEntry,
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
+fn module_nodes(state: &AppState) -> Vec<Arc<NodeState>> {
+    state.get_modules().iter().map(|m| m.state.clone()).collect()
+}
+
+async fn run(state: &AppState, f: fn(&[Arc<NodeState>]) -> Result<UndoEntry, String>) -> Result<Json<UndoResponse>, ApiError> {
+    let nodes = module_nodes(state);
+    let change = crate::worker::run(move || f(&nodes))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| {
+            if e.starts_with("Nothing to") {
+                ApiError::NotFound(e)
+            } else {
+                ApiError::Internal(e)
+            }
+        })?;
+    let status = undo::status();
+    Ok(Json(UndoResponse {
+        change,
+        can_undo: !status.undo.is_empty(),
+        can_redo: !status.redo.is_empty(),
+    }))
+}
+
+/// List the changes that can be undone and redone
+/// GET /api/v1/undo
+pub async fn get_history() -> Json<HistoryStatus> {
+    Json(undo::status())
+}
+
+/// Revert the last change
+/// POST /api/v1/undo
+pub async fn undo(State(state): State<Arc<AppState>>) -> Result<Json<UndoResponse>, ApiError> {
+    run(&state, undo::undo).await
+}
+
+/// Apply the last undone change again
+/// POST /api/v1/redo
+pub async fn redo(State(state): State<Arc<AppState>>) -> Result<Json<UndoResponse>, ApiError> {
+    run(&state, undo::redo).await
+}
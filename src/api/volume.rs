@@ -16,11 +16,11 @@ use super::types::*;
 pub async fn list_all_volumes(
     State(_state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<VolumeInfo>>, ApiError> {
-    let volumes = tokio::task::spawn_blocking(|| {
+    let volumes = crate::worker::run(|| {
         crate::wpctl::list_volumes()
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     .map_err(|e| ApiError::Internal(format!("Failed to list volumes: {}", e)))?;
 
     // Convert wpctl::VolumeInfo to api::VolumeInfo
@@ -39,11 +39,11 @@ pub async fn get_volume_by_id(
     State(_state): State<Arc<AppState>>,
     Path(id): Path<u32>,
 ) -> Result<Json<VolumeInfo>, ApiError> {
-    let volume = tokio::task::spawn_blocking(move || {
+    let volume = crate::worker::run(move || {
         crate::wpctl::get_volume(id)
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     .map_err(|e| {
         if e.contains("not found") {
             ApiError::NotFound(format!("Object {} not found", id))
@@ -67,11 +67,20 @@ pub async fn set_volume_by_id(
     Json(request): Json<SetVolumeRequest>,
 ) -> Result<Json<VolumeResponse>, ApiError> {
     let req_volume = request.volume;
-    let volume = tokio::task::spawn_blocking(move || {
-        crate::wpctl::set_volume(id, req_volume)
+    let volume = crate::worker::run(move || {
+        let previous = crate::wpctl::get_volume(id).map(|v| v.volume);
+        let volume = crate::wpctl::set_volume(id, req_volume)?;
+        if let Ok(previous) = previous {
+            crate::undo::record(
+                format!("Set volume of {} to {:.2}", id, volume),
+                vec![crate::transactions::Change::Volume { id, volume }],
+                vec![crate::transactions::Change::Volume { id, volume: previous }],
+            );
+        }
+        Ok::<f32, String>(volume)
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     .map_err(|e| {
         if e.contains("not found") {
             ApiError::NotFound(format!("Object {} not found", id))
@@ -80,36 +89,18 @@ pub async fn set_volume_by_id(
         }
     })?;
 
-    Ok(Json(VolumeResponse { volume: Some(volume) }))
+    let warning = crate::reverts::check_volume(id, volume).await;
+    Ok(Json(VolumeResponse { volume: Some(volume), warning }))
 }
 
 /// Save all current volumes to state file
 pub async fn save_all_volumes(
     State(_state): State<Arc<AppState>>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    tokio::task::spawn_blocking(|| {
-        // Get all current volumes
-        let volumes = crate::wpctl::list_volumes()
-            .map_err(|e| format!("Failed to list volumes: {}", e))?;
-
-        // Convert to state format
-        let states: Vec<crate::config::VolumeState> = volumes
-            .into_iter()
-            .map(|v| crate::config::VolumeState {
-                name: v.name,
-                volume: v.volume,
-            })
-            .collect();
-
-        // Save to state file
-        crate::config::save_volume_state(states)
-            .map_err(|e| format!("Failed to save volume state: {}", e))?;
-
-        Ok::<_, String>(())
-    })
+    crate::worker::run(crate::volume::save_all_volumes)
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
-    .map_err(|e| ApiError::Internal(e))?;
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    .map_err(ApiError::Internal)?;
 
     Ok(Json(serde_json::json!({
         "success": true,
@@ -122,7 +113,7 @@ pub async fn save_volume(
     State(_state): State<Arc<AppState>>,
     Path(id): Path<u32>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    let volume = tokio::task::spawn_blocking(move || {
+    let volume = crate::worker::run(move || {
         // Get current volume for this ID
         let volume = crate::wpctl::get_volume(id)
             .map_err(|e| {
@@ -133,14 +124,21 @@ pub async fn save_volume(
                 }
             })?;
 
-        // Save to state file using name
-        crate::config::save_single_volume_state(volume.name.clone(), volume.volume)
+        // Save to the state store using name
+        let store = crate::store::get();
+        store
+            .save_volume(&volume.name, volume.volume)
             .map_err(|e| format!("Failed to save volume state: {}", e))?;
+        let _ = store.record_event(&crate::store::AuditEvent::new(
+            "volume.save",
+            &volume.name,
+            format!("{:.3}", volume.volume),
+        ));
 
         Ok::<_, String>(volume)
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     .map_err(|e| {
         if e.starts_with("not found:") {
             ApiError::NotFound(e.strip_prefix("not found: ").unwrap_or(&e).to_string())
@@ -162,11 +160,11 @@ pub async fn save_volume(
 pub async fn get_default_sink(
     State(_state): State<Arc<AppState>>,
 ) -> Result<Json<DefaultNodeInfo>, ApiError> {
-    let info = tokio::task::spawn_blocking(|| {
+    let info = crate::worker::run(|| {
         crate::wpctl::get_default_sink()
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     .map_err(|e| ApiError::Internal(format!("Failed to get default sink: {}", e)))?;
 
     Ok(Json(DefaultNodeInfo {
@@ -181,11 +179,11 @@ pub async fn get_default_sink(
 pub async fn get_default_source(
     State(_state): State<Arc<AppState>>,
 ) -> Result<Json<DefaultNodeInfo>, ApiError> {
-    let info = tokio::task::spawn_blocking(|| {
+    let info = crate::worker::run(|| {
         crate::wpctl::get_default_source()
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     .map_err(|e| ApiError::Internal(format!("Failed to get default source: {}", e)))?;
 
     Ok(Json(DefaultNodeInfo {
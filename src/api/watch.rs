@@ -0,0 +1,47 @@
+//! Object watch handler
+//!
+//! Streams changes of a single object as Server-Sent Events.
+
+use axum::{
+    extract::{Path, Query},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+
+use crate::api_server::ApiError;
+use crate::watch::{self, WatchEvent};
+
+/// Query parameters for the watch endpoint
+#[derive(Debug, Deserialize)]
+pub struct WatchQuery {
+    /// Polling interval in milliseconds
+    pub interval_ms: Option<u64>,
+}
+
+fn to_sse(event: WatchEvent) -> Result<Event, Infallible> {
+    Ok(Event::default()
+        .event(event.name())
+        .data(watch::event_data(&event).to_string()))
+}
+
+/// Watch an object for property and parameter changes
+/// GET /api/v1/objects/:id/watch?interval_ms=1000
+pub async fn watch_object(
+    Path(id): Path<u32>,
+    Query(query): Query<WatchQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let interval = Duration::from_millis(query.interval_ms.unwrap_or(watch::DEFAULT_INTERVAL_MS));
+    let receiver = watch::watch_object(id, interval).await.map_err(|e| {
+        if e.contains("not found") {
+            ApiError::NotFound(e)
+        } else {
+            ApiError::Internal(format!("Failed to watch object: {}", e))
+        }
+    })?;
+
+    let stream = ReceiverStream::new(receiver).map(to_sse);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,98 @@
+//! Zone handlers
+//!
+//! Enable zones and set zone volumes, see [`crate::zones`].
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, AppState};
+use crate::zones::ZoneStatus;
+
+/// Response for GET /api/v1/zones
+#[derive(Debug, Serialize)]
+pub struct ZonesResponse {
+    pub zones: Vec<ZoneStatus>,
+}
+
+/// Request body for PUT /api/v1/zones/:name/enabled
+#[derive(Debug, Deserialize)]
+pub struct EnabledRequest {
+    pub enabled: bool,
+}
+
+/// Request body for PUT /api/v1/zones/:name/volume
+#[derive(Debug, Deserialize)]
+pub struct ZoneVolumeRequest {
+    pub volume: f32,
+}
+
+/// List all zones
+/// GET /api/v1/zones
+pub async fn list_zones(State(state): State<Arc<AppState>>) -> Result<Json<ZonesResponse>, ApiError> {
+    let zones = state.get_zones();
+    let zones = crate::worker::run(move || zones.list())
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?;
+
+    Ok(Json(ZonesResponse { zones }))
+}
+
+/// Get a zone
+/// GET /api/v1/zones/:name
+pub async fn get_zone(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<ZoneStatus>, ApiError> {
+    let zones = state.get_zones();
+    let lookup = name.clone();
+    crate::worker::run(move || zones.get(&lookup))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Zone '{}' not found", name)))
+}
+
+/// Enable or disable a zone
+/// PUT /api/v1/zones/:name/enabled
+pub async fn set_zone_enabled(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(request): Json<EnabledRequest>,
+) -> Result<Json<ZoneStatus>, ApiError> {
+    let zones = state.get_zones();
+    let lookup = name.clone();
+    crate::worker::run(move || zones.set_enabled(&lookup, request.enabled))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to update zone: {}", e)))?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Zone '{}' not found", name)))
+}
+
+/// Set the volume of a zone
+/// PUT /api/v1/zones/:name/volume
+pub async fn set_zone_volume(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(request): Json<ZoneVolumeRequest>,
+) -> Result<Json<ZoneStatus>, ApiError> {
+    if !(0.0..=1.5).contains(&request.volume) {
+        return Err(ApiError::BadRequest(format!(
+            "Volume {} out of range (0.0 - 1.5)",
+            request.volume
+        )));
+    }
+
+    let zones = state.get_zones();
+    let lookup = name.clone();
+    crate::worker::run(move || zones.set_volume(&lookup, request.volume))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to set zone volume: {}", e)))?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Zone '{}' not found", name)))
+}
@@ -3,16 +3,33 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::collections::HashMap;
+use crate::modules::Module;
+use crate::param_monitor::{ChangeSource, ParamChangeEvent, PARAM_EVENT_CAPACITY};
 use crate::parameters::ParameterValue;
 use crate::linker::LinkRule;
 use crate::pwcli::PwObject;
+use crate::auth::Authorizer;
+use crate::pairing::PairingManager;
+use crate::power::PowerManager;
+use crate::stream_output::StreamOutputManager;
+use crate::ducking::DuckingManager;
+use crate::recording::RecordingManager;
+use crate::rate_limit::RateLimiter;
+use crate::response_cache::ResponseCache;
+use crate::server_config::ServerConfig;
+use crate::startup::StartupTracker;
+use crate::zones::ZoneManager;
+use crate::scenes::SceneManager;
+use crate::transactions::Change;
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
 use tracing::info;
 
 /// Status of a link rule execution
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RuleStatus {
     /// When this rule was last executed
     pub last_run: Option<std::time::SystemTime>,
@@ -26,18 +43,6 @@ pub struct RuleStatus {
     pub total_runs: usize,
 }
 
-impl Default for RuleStatus {
-    fn default() -> Self {
-        Self {
-            last_run: None,
-            links_created: 0,
-            links_failed: 0,
-            last_error: None,
-            total_runs: 0,
-        }
-    }
-}
-
 /// Global application state (not tied to any specific node)
 pub struct AppState {
     // Link rules to be monitored and relinked
@@ -46,6 +51,32 @@ pub struct AppState {
     pub rule_status: Arc<Mutex<HashMap<usize, RuleStatus>>>,
     // Cache of PipeWire objects (id -> object)
     pub object_cache: Arc<RwLock<Vec<PwObject>>>,
+    // Effective server configuration
+    pub config: Arc<RwLock<ServerConfig>>,
+    // Short-lived cache of listing responses
+    pub response_cache: Arc<ResponseCache>,
+    // Per-client limits for modifying requests
+    pub rate_limiter: Arc<RateLimiter>,
+    // API tokens and roles
+    pub auth: Arc<Authorizer>,
+    // Open PIN pairings and paired clients
+    pub pairing: Arc<PairingManager>,
+    // Suspending of idle sinks
+    pub power: Arc<PowerManager>,
+    // Null sink and processes feeding an external streaming server
+    pub stream_output: Arc<StreamOutputManager>,
+    // Recordings of nodes into files
+    pub recordings: Arc<RecordingManager>,
+    // Volumes lowered while other streams play
+    pub ducking: Arc<DuckingManager>,
+    // DSP modules served by the API
+    pub modules: Arc<RwLock<Vec<Arc<Module>>>>,
+    // Multi-room zones
+    pub zones: Arc<RwLock<Arc<ZoneManager>>>,
+    // Scenes from scenes.conf
+    pub scenes: Arc<RwLock<Arc<SceneManager>>>,
+    // State of the rules applied at startup
+    pub startup: Arc<StartupTracker>,
 }
 
 impl AppState {
@@ -54,6 +85,19 @@ impl AppState {
             link_rules: Arc::new(Mutex::new(Vec::new())),
             rule_status: Arc::new(Mutex::new(HashMap::new())),
             object_cache: Arc::new(RwLock::new(Vec::new())),
+            config: Arc::new(RwLock::new(ServerConfig::default())),
+            response_cache: Arc::new(ResponseCache::default()),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            auth: Arc::new(Authorizer::default()),
+            pairing: Arc::new(PairingManager::default()),
+            power: Arc::new(PowerManager::default()),
+            stream_output: Arc::new(StreamOutputManager::default()),
+            recordings: Arc::new(RecordingManager::default()),
+            ducking: Arc::new(DuckingManager::default()),
+            modules: Arc::new(RwLock::new(Vec::new())),
+            zones: Arc::new(RwLock::new(Arc::new(ZoneManager::default()))),
+            scenes: Arc::new(RwLock::new(Arc::new(SceneManager::default()))),
+            startup: Arc::new(StartupTracker::default()),
         }
     }
 
@@ -62,17 +106,27 @@ impl AppState {
         let objects = crate::pwcli::list_all()?;
         let count = objects.len();
         *self.object_cache.write().unwrap() = objects;
+        self.response_cache.bump();
         info!("Loaded {} PipeWire objects into cache", count);
         Ok(())
     }
 
     /// Get all cached objects
+    ///
+    /// The cache holds the objects of the default instance, it is empty for
+    /// requests to other cores (see [`crate::remote`]).
     pub fn get_cached_objects(&self) -> Vec<PwObject> {
+        if crate::remote::current().is_some() {
+            return Vec::new();
+        }
         self.object_cache.read().unwrap().clone()
     }
 
     /// Get a cached object by ID
     pub fn get_object_by_id(&self, id: u32) -> Option<PwObject> {
+        if crate::remote::current().is_some() {
+            return None;
+        }
         self.object_cache.read().unwrap()
             .iter()
             .find(|o| o.id == id)
@@ -81,6 +135,9 @@ impl AppState {
 
     /// Find a node by name from the cache
     pub fn find_node_by_name(&self, name: &str) -> Option<PwObject> {
+        if crate::remote::current().is_some() {
+            return None;
+        }
         self.object_cache.read().unwrap()
             .iter()
             .find(|o| {
@@ -94,16 +151,15 @@ impl AppState {
 
     /// Get objects by type
     pub fn get_objects_by_type(&self, obj_type: &str) -> Vec<PwObject> {
-        self.object_cache.read().unwrap()
-            .iter()
+        self.get_cached_objects()
+            .into_iter()
             .filter(|o| crate::pwcli::simplify_type(&o.object_type) == obj_type)
-            .cloned()
             .collect()
     }
 
     /// Find object ID by name (searches node.name, device.name, etc.)
     pub fn find_id_by_name(&self, name: &str) -> Option<u32> {
-        self.object_cache.read().unwrap()
+        self.get_cached_objects()
             .iter()
             .find(|o| o.name().map(|n| n == name).unwrap_or(false))
             .map(|o| o.id)
@@ -115,6 +171,49 @@ impl AppState {
             .and_then(|o| o.name().map(|s| s.to_string()))
     }
 
+    pub fn set_config(&self, config: ServerConfig) {
+        self.response_cache
+            .set_ttl(std::time::Duration::from_millis(config.timeouts.listing_cache_ms));
+        self.rate_limiter
+            .configure(config.rate_limit.requests_per_sec, config.rate_limit.burst);
+        self.auth.configure(&config.auth);
+        *self.config.write().unwrap() = config;
+    }
+
+    pub fn get_config(&self) -> ServerConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Register a DSP module for GET /api/v1/modules
+    pub fn register_module(&self, module: Arc<Module>) {
+        self.modules.write().unwrap().push(module);
+    }
+
+    pub fn get_modules(&self) -> Vec<Arc<Module>> {
+        self.modules.read().unwrap().clone()
+    }
+
+    pub fn set_zones(&self, zones: Arc<ZoneManager>) {
+        *self.zones.write().unwrap() = zones;
+    }
+
+    pub fn get_zones(&self) -> Arc<ZoneManager> {
+        self.zones.read().unwrap().clone()
+    }
+
+    pub fn set_scenes(&self, scenes: Arc<SceneManager>) {
+        *self.scenes.write().unwrap() = scenes;
+    }
+
+    pub fn get_scenes(&self) -> Arc<SceneManager> {
+        self.scenes.read().unwrap().clone()
+    }
+
+    /// Get the node state of a registered module
+    pub fn module_state(&self, name: &str) -> Option<Arc<NodeState>> {
+        self.get_modules().iter().find(|m| m.name == name).map(|m| m.state.clone())
+    }
+
     pub fn set_link_rules(&self, rules: Vec<LinkRule>) {
         *self.link_rules.lock().unwrap() = rules;
     }
@@ -126,7 +225,7 @@ impl AppState {
     /// Update the status of a rule after execution
     pub fn update_rule_status(&self, rule_idx: usize, links_created: usize, links_failed: usize, error: Option<String>) {
         let mut status_map = self.rule_status.lock().unwrap();
-        let status = status_map.entry(rule_idx).or_insert_with(RuleStatus::default);
+        let status = status_map.entry(rule_idx).or_default();
         
         status.last_run = Some(std::time::SystemTime::now());
         status.links_created = links_created;
@@ -146,6 +245,18 @@ impl AppState {
     }
 }
 
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number of attempts for a node operation before giving up
+const REATTACH_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, doubled for each further retry
+const REATTACH_INITIAL_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
 /// Node-specific state for modules that manage a specific PipeWire node
 /// (e.g., speakereq, riaa)
 pub struct NodeState {
@@ -155,6 +266,14 @@ pub struct NodeState {
     // Cache for parameters to avoid too many PipeWire calls
     // This is especially important for EQ parameters as external tools rarely change them
     pub cache: Arc<Mutex<Option<HashMap<String, ParameterValue>>>>,
+    /// ID of the node at the last successful lookup, to detect reattaches
+    pub node_id: Mutex<Option<u32>>,
+    /// Parameter change notifications (see [`crate::param_monitor`])
+    pub events: broadcast::Sender<ParamChangeEvent>,
+    /// Incremented on every parameter change (see [`crate::revision`])
+    pub revision: AtomicU64,
+    /// Serializes modifying requests (see [`crate::revision`])
+    pub write_lock: tokio::sync::Mutex<()>,
 }
 
 impl NodeState {
@@ -163,6 +282,10 @@ impl NodeState {
             node_name,
             node_pattern: None,
             cache: Arc::new(Mutex::new(None)),
+            node_id: Mutex::new(None),
+            events: broadcast::channel(PARAM_EVENT_CAPACITY).0,
+            revision: AtomicU64::new(0),
+            write_lock: tokio::sync::Mutex::new(()),
         }
     }
 
@@ -172,21 +295,65 @@ impl NodeState {
             node_name,
             node_pattern: Some(pattern),
             cache: Arc::new(Mutex::new(None)),
+            node_id: Mutex::new(None),
+            events: broadcast::channel(PARAM_EVENT_CAPACITY).0,
+            revision: AtomicU64::new(0),
+            write_lock: tokio::sync::Mutex::new(()),
         }
     }
 
-    /// Get the actual node name (resolves pattern like "speakereq[0-9]x[0-9]" to "speakereq2x2")
-    pub fn get_actual_node_name(&self) -> Result<String, ApiError> {
-        let node = if let Some(ref pattern) = self.node_pattern {
+    /// Find the node, by pattern if configured
+    pub fn find_node(&self) -> Result<PwObject, ApiError> {
+        if let Some(ref pattern) = self.node_pattern {
             crate::pwcli::find_node_by_match(pattern)
                 .map_err(|e| ApiError::Internal(format!("Failed to find node: {}", e)))?
-                .ok_or_else(|| ApiError::NotFound(format!("No node matching pattern '{}' found", pattern)))?
+                .ok_or_else(|| ApiError::NotFound(format!("No node matching pattern '{}' found", pattern)))
         } else {
             crate::pwcli::find_node_by_name(&self.node_name)
                 .map_err(|e| ApiError::Internal(format!("Failed to find node: {}", e)))?
-                .ok_or_else(|| ApiError::NotFound(format!("Node '{}' not found", self.node_name)))?
-        };
-        
+                .ok_or_else(|| ApiError::NotFound(format!("Node '{}' not found", self.node_name)))
+        }
+    }
+
+    /// Run an operation on the node, re-resolving it if the operation fails
+    ///
+    /// When the node is recreated (e.g. the filter-chain was reloaded) it gets
+    /// a new ID and the cached one becomes invalid. On failure, or if the node
+    /// is missing, the node caches are reloaded and the operation is retried
+    /// with exponential backoff.
+    fn with_node<T>(&self, op: impl Fn(u32) -> Result<T, String>) -> Result<T, ApiError> {
+        let mut delay = REATTACH_INITIAL_DELAY;
+        let mut attempt = 1;
+        loop {
+            let result = self.find_node().and_then(|node| {
+                let previous = self.node_id.lock().unwrap().replace(node.id);
+                if let Some(previous) = previous.filter(|p| *p != node.id) {
+                    info!("[{}] Node ID changed from {} to {}, reattached", self.node_name, previous, node.id);
+                    *self.cache.lock().unwrap() = None;
+                }
+                op(node.id).map_err(ApiError::Internal)
+            });
+
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt >= REATTACH_ATTEMPTS => return Err(e),
+                Err(e) => {
+                    tracing::debug!("[{}] Attempt {} failed ({:?}), re-resolving node in {:?}",
+                        self.node_name, attempt, e, delay);
+                    std::thread::sleep(delay);
+                    if let Err(e) = crate::pwcli::invalidate_caches() {
+                        tracing::warn!("[{}] Failed to reload node cache: {}", self.node_name, e);
+                    }
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Get the actual node name (resolves pattern like "speakereq[0-9]x[0-9]" to "speakereq2x2")
+    pub fn get_actual_node_name(&self) -> Result<String, ApiError> {
+        let node = self.find_node()?;
         node.properties.get("node.name")
             .cloned()
             .ok_or_else(|| ApiError::Internal("Node has no node.name property".to_string()))
@@ -205,36 +372,16 @@ impl NodeState {
 
         // Cache miss - fetch from PipeWire using pw-cli
         // Use pattern matching if configured (finds speakereq2x2, speakereq4x4, etc.)
-        let node = if let Some(ref pattern) = self.node_pattern {
-            tracing::debug!("[{}] get_params: using pattern matching '{}'", self.node_name, pattern);
-            crate::pwcli::find_node_by_match(pattern)
-                .map_err(|e| {
-                    tracing::error!("[{}] get_params: find_node_by_match failed: {}", self.node_name, e);
-                    ApiError::Internal(format!("Failed to find node: {}", e))
-                })?
-                .ok_or_else(|| {
-                    tracing::error!("[{}] get_params: no node found with pattern '{}'", self.node_name, pattern);
-                    ApiError::NotFound(format!("No node matching pattern '{}' found", pattern))
-                })?
-        } else {
-            crate::pwcli::find_node_by_name(&self.node_name)
-                .map_err(|e| ApiError::Internal(format!("Failed to find node: {}", e)))?
-                .ok_or_else(|| ApiError::NotFound(format!("Node '{}' not found", self.node_name)))?
-        };
-        
-        tracing::debug!("[{}] get_params: found node id={}, name={:?}", 
-            self.node_name, node.id, node.name());
-        
-        // Use pw-cli to enumerate parameters
-        let params = Self::get_params_via_pwcli(node.id)
+        let params = self.with_node(Self::get_params_via_pwcli)
             .map_err(|e| {
-                tracing::error!("[{}] get_params: get_params_via_pwcli failed for node {}: {}", 
-                    self.node_name, node.id, e);
-                ApiError::Internal(format!("Failed to get parameters: {}", e))
+                tracing::error!("[{}] get_params: failed: {:?}", self.node_name, e);
+                match e {
+                    ApiError::Internal(msg) => ApiError::Internal(format!("Failed to get parameters: {}", msg)),
+                    other => other,
+                }
             })?;
         
-        tracing::debug!("[{}] get_params: got {} params from node {}", 
-            self.node_name, params.len(), node.id);
+        tracing::debug!("[{}] get_params: got {} params", self.node_name, params.len());
         
         // Update cache
         *self.cache.lock().unwrap() = Some(params.clone());
@@ -258,141 +405,150 @@ impl NodeState {
         self.set_parameters(params)
     }
 
+    /// Get the current values of parameters
+    ///
+    /// Keys without plugin prefix (e.g. `xbar_0_to_1`) match the prefixed
+    /// parameter. Unknown keys are left out.
+    pub fn current_values(&self, keys: impl IntoIterator<Item = String>) -> Result<HashMap<String, ParameterValue>, ApiError> {
+        let current = self.get_params()?;
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| {
+                let value = current.get(&key).or_else(|| {
+                    current
+                        .iter()
+                        .find(|(k, _)| k.strip_suffix(key.as_str()).is_some_and(|p| p.ends_with(':')))
+                        .map(|(_, v)| v)
+                })?;
+                Some((key, value.clone()))
+            })
+            .collect())
+    }
+
     // Helper to set multiple parameters using pw-cli (batched in single call)
+    // The change can be undone with POST /api/v1/undo (see crate::undo)
     pub fn set_parameters(&self, params: HashMap<String, ParameterValue>) -> Result<(), ApiError> {
-        // Find the node ID using pwcli cache
+        let previous = self.current_values(params.keys().cloned()).unwrap_or_else(|e| {
+            tracing::debug!("[{}] set_parameters: previous values unknown: {:?}", self.node_name, e);
+            HashMap::new()
+        });
+        let count = params.len();
+        self.set_parameters_untracked(params.clone())?;
+
+        if !previous.is_empty() {
+            crate::undo::record(
+                format!("Set {} parameter(s) of {}", count, self.node_name),
+                vec![Change::Params { node: self.node_name.clone(), params }],
+                vec![Change::Params { node: self.node_name.clone(), params: previous }],
+            );
+        }
+        Ok(())
+    }
+
+    /// Set parameters without recording them for undo
+    pub fn set_parameters_untracked(&self, params: HashMap<String, ParameterValue>) -> Result<(), ApiError> {
         // Use pattern matching if configured (finds speakereq2x2, speakereq4x4, etc.)
-        let node = if let Some(ref pattern) = self.node_pattern {
-            crate::pwcli::find_node_by_match(pattern)
-                .map_err(|e| ApiError::Internal(format!("Failed to find node: {}", e)))?
-                .ok_or_else(|| ApiError::NotFound(format!("No node matching pattern '{}' found", pattern)))?
-        } else {
-            crate::pwcli::find_node_by_name(&self.node_name)
-                .map_err(|e| ApiError::Internal(format!("Failed to find node: {}", e)))?
-                .ok_or_else(|| ApiError::NotFound(format!("Node '{}' not found", self.node_name)))?
-        };
-        
-        // Build the JSON for pw-cli set-param
-        Self::set_params_via_pwcli(node.id, params)
-            .map_err(|e| ApiError::Internal(format!("Failed to set parameters: {}", e)))?;
+        self.with_node(|id| Self::set_params_via_pwcli(id, params.clone()))
+            .map_err(|e| match e {
+                ApiError::Internal(msg) => ApiError::Internal(format!("Failed to set parameters: {}", msg)),
+                other => other,
+            })?;
         
         // Invalidate cache
         *self.cache.lock().unwrap() = None;
+        self.revision.fetch_add(1, Ordering::SeqCst);
+
+        self.publish(ParamChangeEvent::new(&self.node_name, ChangeSource::Api, &params));
         
         Ok(())
     }
 
-    // Parse pw-cli enum-params output to extract parameters
-    fn get_params_via_pwcli(node_id: u32) -> Result<HashMap<String, ParameterValue>, String> {
-        use std::process::Command;
-        
-        let output = Command::new("pw-cli")
-            .args(["enum-params", &node_id.to_string(), "Props"])
-            .output()
-            .map_err(|e| format!("Failed to run pw-cli: {}", e))?;
-        
-        if !output.status.success() {
-            return Err(format!("pw-cli failed: {}", String::from_utf8_lossy(&output.stderr)));
+    /// Get the revision of the parameters
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    /// Subscribe to parameter change notifications
+    pub fn subscribe(&self) -> broadcast::Receiver<ParamChangeEvent> {
+        self.events.subscribe()
+    }
+
+    /// Publish a parameter change (no-op without subscribers)
+    pub fn publish(&self, event: ParamChangeEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Read the parameters from PipeWire and update the cache if they changed
+    ///
+    /// Returns the change event (also published to subscribers) if parameters
+    /// differ from the cached values. An empty cache is filled without an event.
+    pub fn check_for_changes(&self) -> Result<Option<ParamChangeEvent>, ApiError> {
+        let current = self.with_node(Self::get_params_via_pwcli)?;
+
+        let mut cache = self.cache.lock().unwrap();
+        let changed = match *cache {
+            Some(ref cached) => crate::param_monitor::diff_params(cached, &current),
+            None => HashMap::new(),
+        };
+        *cache = Some(current);
+        drop(cache);
+
+        if changed.is_empty() {
+            return Ok(None);
         }
-        
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        Self::parse_pw_cli_params(&stdout)
+
+        tracing::debug!("[{}] {} parameter(s) changed externally", self.node_name, changed.len());
+        self.revision.fetch_add(1, Ordering::SeqCst);
+        let event = ParamChangeEvent::new(&self.node_name, ChangeSource::External, &changed);
+        self.publish(event.clone());
+        Ok(Some(event))
     }
 
-    // Parse pw-cli output format
-    fn parse_pw_cli_params(output: &str) -> Result<HashMap<String, ParameterValue>, String> {
+    /// Async variant of [`Self::get_params`] running on the PipeWire worker pool
+    pub async fn get_params_async(self: &Arc<Self>) -> Result<HashMap<String, ParameterValue>, ApiError> {
+        let state = Arc::clone(self);
+        crate::worker::run(move || state.get_params())
+            .await
+            .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    }
+
+    /// Async variant of [`Self::refresh_params_cache`] running on the PipeWire worker pool
+    pub async fn refresh_params_cache_async(self: &Arc<Self>) -> Result<(), ApiError> {
+        let state = Arc::clone(self);
+        crate::worker::run(move || state.refresh_params_cache())
+            .await
+            .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+    }
+
+    /// Async variant of [`Self::set_parameter`] running on the PipeWire worker pool
+    pub async fn set_parameter_async(self: &Arc<Self>, key: &str, value: ParameterValue) -> Result<(), ApiError> {
         let mut params = HashMap::new();
-        let lines: Vec<&str> = output.lines().collect();
-        let mut i = 0;
-        
-        while i < lines.len() {
-            let line = lines[i].trim();
-            
-            // Look for: String "speakereq2x2:parameter_name" or String "parameter_name"
-            if line.starts_with("String ") {
-                if let Some(key) = Self::extract_string_value(line) {
-                    // Next line should have the value
-                    if i + 1 < lines.len() {
-                        let value_line = lines[i + 1].trim();
-                        if let Some(value) = Self::parse_param_value(value_line) {
-                            params.insert(key, value);
-                        }
-                    }
-                }
-            }
-            i += 1;
-        }
-        
-        Ok(params)
+        params.insert(key.to_string(), value);
+        self.set_parameters_async(params).await
     }
 
-    // Extract string value from: String "value"
-    fn extract_string_value(line: &str) -> Option<String> {
-        let start = line.find('"')?;
-        let end = line.rfind('"')?;
-        if start < end {
-            Some(line[start + 1..end].to_string())
-        } else {
-            None
-        }
+    /// Async variant of [`Self::set_parameters`] running on the PipeWire worker pool
+    pub async fn set_parameters_async(self: &Arc<Self>, params: HashMap<String, ParameterValue>) -> Result<(), ApiError> {
+        let state = Arc::clone(self);
+        crate::worker::run(move || state.set_parameters(params))
+            .await
+            .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     }
 
-    // Parse parameter value from pw-cli output line
-    fn parse_param_value(line: &str) -> Option<ParameterValue> {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 2 {
-            match parts[0] {
-                "Bool" => Some(ParameterValue::Bool(parts[1] == "true")),
-                "Int" => parts[1].parse::<i32>().ok().map(ParameterValue::Int),
-                "Float" => parts[1].parse::<f32>().ok().map(ParameterValue::Float),
-                "String" => Self::extract_string_value(line).map(ParameterValue::String),
-                _ => None,
-            }
-        } else {
-            None
-        }
+    // Read the control parameters (the `params` of Props)
+    fn get_params_via_pwcli(node_id: u32) -> Result<HashMap<String, ParameterValue>, String> {
+        let props = crate::pwparams::enum_params(node_id, "Props")?;
+        Ok(props
+            .iter()
+            .filter_map(|p| p.properties.get("params").and_then(|v| v.as_object()))
+            .flat_map(|params| params.iter())
+            .filter_map(|(key, value)| Some((key.clone(), ParameterValue::from_json(value).ok()?)))
+            .collect())
     }
 
     // Set parameters using pw-cli
     fn set_params_via_pwcli(node_id: u32, params: HashMap<String, ParameterValue>) -> Result<(), String> {
-        use std::process::Command;
-        
-        // Build array format for params struct: ["key1", value1, "key2", value2, ...]
-        // This is the correct format for the SPA Struct in the params property
-        let mut params_array = Vec::new();
-        
-        for (key, value) in params {
-            params_array.push(serde_json::Value::String(key));
-            
-            let json_value = match value {
-                ParameterValue::Bool(b) => serde_json::Value::Bool(b),
-                ParameterValue::Int(i) => serde_json::Value::Number(i.into()),
-                ParameterValue::Float(f) => {
-                    serde_json::Number::from_f64(f as f64)
-                        .map(serde_json::Value::Number)
-                        .unwrap_or(serde_json::Value::Null)
-                },
-                ParameterValue::String(s) => serde_json::Value::String(s),
-            };
-            params_array.push(json_value);
-        }
-        
-        // Wrap in params property
-        let json = serde_json::json!({
-            "params": params_array
-        });
-        let json_str = json.to_string();
-        
-        let output = Command::new("pw-cli")
-            .args(["set-param", &node_id.to_string(), "Props", &json_str])
-            .output()
-            .map_err(|e| format!("Failed to run pw-cli: {}", e))?;
-        
-        if !output.status.success() {
-            return Err(format!("pw-cli set-param failed: {}", String::from_utf8_lossy(&output.stderr)));
-        }
-        
-        Ok(())
+        crate::parameters::set_params(node_id, &params)
     }
 }
 
@@ -402,6 +558,10 @@ pub enum ApiError {
     NotFound(String),
     BadRequest(String),
     Internal(String),
+    /// A DSP module's node is not loaded (the module name)
+    ModuleNotPresent(String),
+    /// The resource was changed since the client read it
+    Conflict(String),
 }
 
 impl IntoResponse for ApiError {
@@ -410,6 +570,18 @@ impl IntoResponse for ApiError {
             ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
             ApiError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+            ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            ApiError::ModuleNotPresent(module) => {
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(serde_json::json!({
+                        "error": format!("Module '{}' is not present", module),
+                        "module": module,
+                        "present": false
+                    })),
+                )
+                    .into_response();
+            }
         };
         (status, Json(serde_json::json!({ "error": message }))).into_response()
     }
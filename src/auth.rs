@@ -0,0 +1,341 @@
+//! Access control with API tokens and roles
+//!
+//! Routes are divided into groups: `read` (GET requests except the logs,
+//! capture streams and recordings), `volume`, `dsp` (module parameters,
+//! settings), `links` (links, topology, default devices, combined sinks) and
+//! `admin` (everything else, e.g. configuration, logs, capture streams,
+//! recordings, clients, metadata, and scenes, transactions and undo, which
+//! can change volumes and links as well as DSP parameters).
+//! A role is a set of groups. The built-in roles are `viewer`,
+//! `volume-only` and `admin`; further roles can be defined in the `[auth]`
+//! section of `pipewire-api.toml`.
+//!
+//! Clients send their token as `Authorization: Bearer <token>` (or as
+//! `?access_token=` for EventSource clients, which can't set headers).
+//! Access control is off while no tokens are configured. Requests without
+//! a token get the `anonymous_role` if one is set, otherwise
+//! `401 Unauthorized`; requests outside the role get `403 Forbidden`.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+use crate::api_server::AppState;
+use crate::server_config::{ApiToken, AuthSection};
+
+/// Group of routes a role can be allowed to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteGroup {
+    Read,
+    Volume,
+    Dsp,
+    Links,
+    Admin,
+}
+
+impl RouteGroup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteGroup::Read => "read",
+            RouteGroup::Volume => "volume",
+            RouteGroup::Dsp => "dsp",
+            RouteGroup::Links => "links",
+            RouteGroup::Admin => "admin",
+        }
+    }
+}
+
+/// Get the route group of a request
+pub fn route_group(method: &Method, path: &str) -> RouteGroup {
+    let path = path.trim_end_matches('/');
+    let under = |prefix: &str| path == prefix || path.starts_with(&format!("{}/", prefix));
+    // Reads that reveal more than the state of the audio system: the logs
+    // and the audio of capture streams and recordings
+    if under("/api/v1/logs") || under("/api/v1/capture") || under("/api/v1/record") {
+        return RouteGroup::Admin;
+    }
+    // Read-only queries with a request body
+    if method == Method::GET || method == Method::HEAD || (method == Method::POST && path == "/api/v1/properties/query") {
+        return RouteGroup::Read;
+    }
+
+    if under("/api/v1/volume")
+        || (path.starts_with("/api/v1/zones/") && (path.ends_with("/volume") || path.ends_with("/enabled")))
+        || (path.starts_with("/api/v1/devices/") && path.ends_with("/volume"))
+    {
+        RouteGroup::Volume
+    } else if under("/api/v1/module")
+        || under("/api/v1/nodes")
+        || under("/api/v1/settings") && !under("/api/v1/settings/clock")
+    {
+        RouteGroup::Dsp
+    } else if under("/api/v1/links")
+        || under("/api/v1/topology")
+        || under("/api/v1/defaults")
+        || under("/api/v1/combine-sinks")
+    {
+        RouteGroup::Links
+    } else {
+        RouteGroup::Admin
+    }
+}
+
+/// Built-in roles
+pub fn builtin_roles() -> BTreeMap<String, Vec<RouteGroup>> {
+    use RouteGroup::*;
+    BTreeMap::from([
+        ("viewer".to_string(), vec![Read]),
+        ("volume-only".to_string(), vec![Read, Volume]),
+        ("admin".to_string(), vec![Read, Volume, Dsp, Links, Admin]),
+    ])
+}
+
+/// A token accepted by the server
+#[derive(Debug, Clone)]
+pub struct TokenGrant {
+    pub name: String,
+    pub token: String,
+    pub role: String,
+}
+
+#[derive(Debug, Default)]
+struct AuthState {
+    roles: BTreeMap<String, Vec<RouteGroup>>,
+    tokens: Vec<TokenGrant>,
+    /// Tokens issued by pairing (see [`crate::pairing`])
+    paired: Vec<TokenGrant>,
+    anonymous_role: Option<String>,
+}
+
+/// Outcome of checking a request
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    Allowed,
+    /// No or unknown token
+    Unauthorized,
+    /// The role (name) doesn't include the route group
+    Forbidden(String),
+}
+
+/// Tokens and roles from the configuration
+#[derive(Debug, Default)]
+pub struct Authorizer {
+    state: RwLock<AuthState>,
+}
+
+/// Compare tokens without revealing the position of the first difference
+fn token_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl Authorizer {
+    /// Apply the `[auth]` configuration
+    ///
+    /// Tokens with unknown roles are ignored with a warning.
+    pub fn configure(&self, config: &AuthSection) {
+        let mut roles = builtin_roles();
+        roles.extend(config.roles.clone());
+
+        let tokens = config
+            .tokens
+            .iter()
+            .filter(|t| {
+                let known = roles.contains_key(&t.role);
+                if !known {
+                    warn!("Ignoring API token '{}': unknown role '{}'", t.name, t.role);
+                }
+                known && !t.token.is_empty()
+            })
+            .map(|t: &ApiToken| TokenGrant {
+                name: t.name.clone(),
+                token: t.token.clone(),
+                role: t.role.clone(),
+            })
+            .collect();
+
+        if let Some(role) = config.anonymous_role.as_ref().filter(|r| !roles.contains_key(*r)) {
+            warn!("Unknown anonymous_role '{}', requests without token are rejected", role);
+        }
+
+        let mut state = self.state.write().unwrap();
+        state.roles = roles;
+        state.tokens = tokens;
+        state.anonymous_role = config.anonymous_role.clone();
+    }
+
+    /// Replace the tokens issued by pairing
+    pub fn set_paired(&self, grants: Vec<TokenGrant>) {
+        self.state.write().unwrap().paired = grants;
+    }
+
+    /// Check whether a role is defined
+    pub fn has_role(&self, role: &str) -> bool {
+        self.state.read().unwrap().roles.contains_key(role)
+    }
+
+    /// Check whether access control is on (tokens are configured)
+    ///
+    /// Paired tokens don't count: pairing is only offered while it is on.
+    pub fn enabled(&self) -> bool {
+        !self.state.read().unwrap().tokens.is_empty()
+    }
+
+    /// Check a request with an optional token
+    pub fn check(&self, token: Option<&str>, group: RouteGroup) -> Access {
+        let state = self.state.read().unwrap();
+        if state.tokens.is_empty() {
+            return Access::Allowed;
+        }
+
+        let role = match token {
+            Some(token) => match state.tokens.iter().chain(&state.paired).find(|t| token_eq(&t.token, token)) {
+                Some(grant) => &grant.role,
+                None => return Access::Unauthorized,
+            },
+            None => match &state.anonymous_role {
+                Some(role) => role,
+                None => return Access::Unauthorized,
+            },
+        };
+
+        match state.roles.get(role) {
+            Some(groups) if groups.contains(&group) => Access::Allowed,
+            Some(_) => Access::Forbidden(role.clone()),
+            None => Access::Unauthorized,
+        }
+    }
+}
+
+/// Get the token of a request from the Authorization header or the query
+pub fn request_token<'a>(headers: &'a HeaderMap, query: Option<&'a str>) -> Option<&'a str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .or_else(|| {
+            query?
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("access_token="))
+        })
+        .filter(|t| !t.is_empty())
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Middleware checking the token and role of every request
+pub async fn middleware(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    // CORS preflight requests carry no credentials, pairing clients have none yet
+    if request.method() == Method::OPTIONS
+        || !state.auth.enabled()
+        || (request.method() == Method::POST && request.uri().path().trim_end_matches('/') == "/api/v1/pair")
+    {
+        return next.run(request).await;
+    }
+
+    let group = route_group(request.method(), request.uri().path());
+    let token = request_token(request.headers(), request.uri().query());
+    match state.auth.check(token, group) {
+        Access::Allowed => next.run(request).await,
+        Access::Unauthorized => {
+            let mut response = error_response(StatusCode::UNAUTHORIZED, "Missing or invalid API token".to_string());
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            response
+        }
+        Access::Forbidden(role) => error_response(
+            StatusCode::FORBIDDEN,
+            format!("Role '{}' is not allowed to use {} routes", role, group.as_str()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_groups() {
+        assert_eq!(route_group(&Method::GET, "/api/v1/links"), RouteGroup::Read);
+        assert_eq!(route_group(&Method::PUT, "/api/v1/volume/42"), RouteGroup::Volume);
+        assert_eq!(route_group(&Method::PUT, "/api/v1/zones/kitchen/volume"), RouteGroup::Volume);
+        assert_eq!(route_group(&Method::PUT, "/api/v1/devices/48/routes/2/volume"), RouteGroup::Volume);
+        assert_eq!(route_group(&Method::PUT, "/api/v1/module/speakereq/eq/output_0/1"), RouteGroup::Dsp);
+        assert_eq!(route_group(&Method::POST, "/api/v1/settings/save"), RouteGroup::Dsp);
+        // Scenes, transactions and undo can change volumes and links as well
+        assert_eq!(route_group(&Method::POST, "/api/v1/transactions"), RouteGroup::Admin);
+        assert_eq!(route_group(&Method::POST, "/api/v1/scenes/evening/activate"), RouteGroup::Admin);
+        assert_eq!(route_group(&Method::POST, "/api/v1/undo"), RouteGroup::Admin);
+        assert_eq!(route_group(&Method::PUT, "/api/v1/settings/clock"), RouteGroup::Admin);
+        assert_eq!(route_group(&Method::DELETE, "/api/v1/links/7"), RouteGroup::Links);
+        assert_eq!(route_group(&Method::POST, "/api/v1/topology/apply"), RouteGroup::Links);
+        assert_eq!(route_group(&Method::PUT, "/api/v1/logs/level"), RouteGroup::Admin);
+        // The logs may contain more than the audio state
+        assert_eq!(route_group(&Method::GET, "/api/v1/logs"), RouteGroup::Admin);
+        assert_eq!(route_group(&Method::GET, "/api/v1/capture/42/stream"), RouteGroup::Admin);
+        assert_eq!(route_group(&Method::GET, "/api/v1/record"), RouteGroup::Admin);
+        assert_eq!(route_group(&Method::GET, "/api/v1/record/1/download"), RouteGroup::Admin);
+        assert_eq!(route_group(&Method::POST, "/api/v1/volumes"), RouteGroup::Admin);
+        assert_eq!(route_group(&Method::POST, "/api/v1/properties/query"), RouteGroup::Read);
+        // Applying the headroom trim is a PUT, the GET only analyzes
+        assert_eq!(route_group(&Method::PUT, "/api/v1/module/speakereq/headroom"), RouteGroup::Dsp);
+    }
+
+    fn authorizer(anonymous_role: Option<&str>) -> Authorizer {
+        let auth = Authorizer::default();
+        auth.configure(&AuthSection {
+            anonymous_role: anonymous_role.map(String::from),
+            roles: BTreeMap::from([("dsp".to_string(), vec![RouteGroup::Read, RouteGroup::Dsp])]),
+            tokens: vec![
+                ApiToken { name: "panel".to_string(), token: "panel-token".to_string(), role: "volume-only".to_string() },
+                ApiToken { name: "eq".to_string(), token: "eq-token".to_string(), role: "dsp".to_string() },
+                ApiToken { name: "bad".to_string(), token: "bad-token".to_string(), role: "nope".to_string() },
+            ],
+            ..Default::default()
+        });
+        auth
+    }
+
+    #[test]
+    fn test_roles() {
+        assert_eq!(Authorizer::default().check(None, RouteGroup::Admin), Access::Allowed);
+
+        let auth = authorizer(None);
+        assert!(auth.enabled());
+        assert_eq!(auth.check(Some("panel-token"), RouteGroup::Volume), Access::Allowed);
+        assert_eq!(auth.check(Some("panel-token"), RouteGroup::Links), Access::Forbidden("volume-only".to_string()));
+        assert_eq!(auth.check(Some("eq-token"), RouteGroup::Dsp), Access::Allowed);
+        assert_eq!(auth.check(Some("bad-token"), RouteGroup::Read), Access::Unauthorized);
+        assert_eq!(auth.check(Some("other"), RouteGroup::Read), Access::Unauthorized);
+        assert_eq!(auth.check(None, RouteGroup::Read), Access::Unauthorized);
+
+        auth.set_paired(vec![TokenGrant { name: "phone".to_string(), token: "phone-token".to_string(), role: "viewer".to_string() }]);
+        assert_eq!(auth.check(Some("phone-token"), RouteGroup::Read), Access::Allowed);
+        assert_eq!(auth.check(Some("phone-token"), RouteGroup::Volume), Access::Forbidden("viewer".to_string()));
+
+        let auth = authorizer(Some("viewer"));
+        assert_eq!(auth.check(None, RouteGroup::Read), Access::Allowed);
+        assert_eq!(auth.check(None, RouteGroup::Volume), Access::Forbidden("viewer".to_string()));
+    }
+
+    #[test]
+    fn test_request_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(request_token(&headers, Some("access_token=abc&x=1")), Some("abc"));
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer xyz"));
+        assert_eq!(request_token(&headers, None), Some("xyz"));
+        assert_eq!(request_token(&HeaderMap::new(), Some("access_token=")), None);
+    }
+}
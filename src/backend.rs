@@ -0,0 +1,400 @@
+//! Exchangeable access to PipeWire objects and parameters
+//!
+//! Listing objects, looking one up (bind), reading parameters and writing
+//! them go through the [`PipeWireBackend`] trait: [`pwcli::list_objects`],
+//! [`pwcli::query_object`], [`crate::pwparams::enum_params`] and
+//! [`crate::pwparams::set_param`] call the current backend. [`CliBackend`]
+//! runs pw-cli, [`crate::pwdump::DumpBackend`] reads the JSON of pw-dump
+//! (`[server] pipewire_backend`, see [`open`]); [`FakeBackend`] keeps
+//! objects and parameters in memory, so handlers can be tested through the
+//! HTTP layer without a PipeWire daemon.
+//!
+//! A backend installed with [`set`] is used by the whole process. A backend
+//! given to [`scope`] is only used by that future and the worker jobs it
+//! starts (see [`crate::worker`]), like the remote of a core (see
+//! [`crate::remote`]); the object caches are bypassed meanwhile.
+
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::command;
+use crate::pod_parser::{self, ParamObject};
+use crate::pwcli::{self, PwObject};
+
+/// Access to PipeWire objects and their parameters (blocking)
+pub trait PipeWireBackend: Send + Sync {
+    /// Short name, e.g. "pw-cli"
+    fn name(&self) -> &'static str;
+
+    /// Whether the objects are those of a PipeWire daemon, so that wpctl,
+    /// pw-link and pw-metadata see them as well
+    fn is_live(&self) -> bool {
+        true
+    }
+
+    /// List objects, of one type (e.g. "Node") or with one ID if `filter` is given
+    fn list_objects(&self, filter: Option<&str>) -> Result<Vec<PwObject>, String>;
+
+    /// Get the current state of one object
+    fn bind(&self, id: u32) -> Result<Option<PwObject>, String> {
+        Ok(self.list_objects(Some(&id.to_string()))?.into_iter().find(|o| o.id == id))
+    }
+
+    /// Read all parameter objects of a type, e.g. "Props"
+    fn enum_params(&self, id: u32, param_type: &str) -> Result<Vec<ParamObject>, String>;
+
+    /// Write a parameter object with named keys
+    fn set_param(&self, id: u32, param_type: &str, value: &Value) -> Result<(), String>;
+}
+
+/// The PipeWire daemon, through pw-cli
+#[derive(Debug, Default)]
+pub struct CliBackend;
+
+impl PipeWireBackend for CliBackend {
+    fn name(&self) -> &'static str {
+        "pw-cli"
+    }
+
+    fn list_objects(&self, filter: Option<&str>) -> Result<Vec<PwObject>, String> {
+        let mut cmd = command::pipewire("pw-cli");
+        cmd.arg("ls");
+        if let Some(f) = filter {
+            cmd.arg(f);
+        }
+
+        let output = command::output_with_timeout(&mut cmd, command::discovery_timeout())
+            .map_err(|e| format!("Failed to run pw-cli ls: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("pw-cli ls failed: {}", stderr));
+        }
+        pwcli::parse_pwcli_ls(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn enum_params(&self, id: u32, param_type: &str) -> Result<Vec<ParamObject>, String> {
+        let output = command::output_with_timeout(
+            command::pipewire("pw-cli").args(["enum-params", &id.to_string(), param_type]),
+            command::param_read_timeout(),
+        )
+        .map_err(|e| format!("Failed to run pw-cli enum-params: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("pw-cli enum-params failed: {}", stderr.trim()));
+        }
+        Ok(pod_parser::parse_enum_params(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    fn set_param(&self, id: u32, param_type: &str, value: &Value) -> Result<(), String> {
+        let output = command::pipewire("pw-cli")
+            .args(["set-param", &id.to_string(), param_type, &value.to_string()])
+            .output()
+            .map_err(|e| format!("Failed to run pw-cli set-param: {}", e))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // pw-cli reports errors on stderr but may still exit successfully
+        if !output.status.success() || stderr.contains("error") {
+            return Err(format!("pw-cli set-param failed: {}", stderr.trim()));
+        }
+        Ok(())
+    }
+}
+
+/// Objects and parameters kept in memory
+///
+/// Written parameters are merged into the stored ones like PipeWire does:
+/// keys of a `Props` object replace the stored keys of the object that has
+/// them (the `params` of filter-chain controls are merged by name), a `Route`
+/// replaces the keys of the route with the same `index`.
+#[derive(Debug, Default)]
+pub struct FakeBackend {
+    objects: RwLock<Vec<PwObject>>,
+    params: RwLock<HashMap<(u32, String), Vec<ParamObject>>>,
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an object, e.g. of type "Node", with its properties
+    pub fn add_object(&self, id: u32, object_type: &str, properties: &[(&str, &str)]) {
+        let object = PwObject::new(id, object_type, properties);
+        let mut objects = self.objects.write().unwrap();
+        objects.retain(|o| o.id != id);
+        objects.push(object);
+        objects.sort_by_key(|o| o.id);
+    }
+
+    /// Remove an object and its parameters
+    pub fn remove_object(&self, id: u32) {
+        self.objects.write().unwrap().retain(|o| o.id != id);
+        self.params.write().unwrap().retain(|(object, _), _| *object != id);
+    }
+
+    /// Add a parameter object of a type (e.g. one `EnumRoute` per route)
+    pub fn add_param(&self, id: u32, param_type: &str, properties: Value) {
+        let param = ParamObject {
+            object_type: param_type.trim_start_matches("Enum").to_string(),
+            id: param_type.to_string(),
+            properties: properties.as_object().cloned().unwrap_or_default(),
+        };
+        self.params.write().unwrap().entry((id, param_type.to_string())).or_default().push(param);
+    }
+
+    fn check_object(&self, id: u32) -> Result<(), String> {
+        match self.objects.read().unwrap().iter().any(|o| o.id == id) {
+            true => Ok(()),
+            false => Err(format!("Object {} not found", id)),
+        }
+    }
+}
+
+/// Convert the `params` array of a write (alternating names and values)
+/// into the object form of a read
+///
+/// Like filter-chain, a control name without plugin prefix (`master_gain_db`)
+/// addresses the stored control with a prefix (`speakereq2x2:master_gain_db`).
+fn params_object(value: &Value, stored: Option<&Value>) -> Value {
+    let resolve = |name: &str| {
+        let controls = stored.and_then(|s| s.as_object());
+        match controls {
+            Some(controls) if !name.contains(':') && !controls.contains_key(name) => controls
+                .keys()
+                .find(|k| k.strip_suffix(name).is_some_and(|p| p.ends_with(':')))
+                .cloned()
+                .unwrap_or_else(|| name.to_string()),
+            _ => name.to_string(),
+        }
+    };
+    match value {
+        Value::Array(items) => Value::Object(
+            items
+                .chunks(2)
+                .filter_map(|pair| Some((resolve(pair[0].as_str()?), pair.get(1)?.clone())))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Merge the keys of `update` into `target`, objects recursively
+fn merge(target: &mut Map<String, Value>, update: &Map<String, Value>) {
+    for (key, value) in update {
+        let value = if key == "params" { params_object(value, target.get(key)) } else { value.clone() };
+        match (target.get_mut(key), value) {
+            (Some(Value::Object(existing)), Value::Object(update)) => merge(existing, &update),
+            (_, value) => {
+                target.insert(key.clone(), value);
+            }
+        }
+    }
+}
+
+impl PipeWireBackend for FakeBackend {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn is_live(&self) -> bool {
+        false
+    }
+
+    fn list_objects(&self, filter: Option<&str>) -> Result<Vec<PwObject>, String> {
+        let objects = self.objects.read().unwrap();
+        Ok(objects
+            .iter()
+            .filter(|o| match filter {
+                None => true,
+                Some(f) if f.chars().all(|c| c.is_ascii_digit()) => o.id.to_string() == f,
+                Some(f) => o.is_type(f),
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn enum_params(&self, id: u32, param_type: &str) -> Result<Vec<ParamObject>, String> {
+        self.check_object(id)?;
+        Ok(self.params.read().unwrap().get(&(id, param_type.to_string())).cloned().unwrap_or_default())
+    }
+
+    fn set_param(&self, id: u32, param_type: &str, value: &Value) -> Result<(), String> {
+        self.check_object(id)?;
+        let update = value.as_object().ok_or("Parameter value must be a JSON object")?;
+        let mut params = self.params.write().unwrap();
+        let stored = params.entry((id, param_type.to_string())).or_default();
+        // The route with the same index, or the object holding the keys
+        // (filter-chains report their controls in a separate Props object)
+        let position = match update.get("index") {
+            Some(index) => stored.iter().position(|p| p.properties.get("index") == Some(index)),
+            None => stored
+                .iter()
+                .position(|p| update.keys().any(|k| p.properties.contains_key(k)))
+                .or((!stored.is_empty()).then_some(0)),
+        };
+        let existing = position.map(|i| &mut stored[i]);
+        match existing {
+            Some(param) => merge(&mut param.properties, update),
+            None => {
+                let mut properties = Map::new();
+                merge(&mut properties, update);
+                stored.push(ParamObject { object_type: param_type.to_string(), id: param_type.to_string(), properties });
+            }
+        }
+        Ok(())
+    }
+}
+
+static GLOBAL: RwLock<Option<Arc<dyn PipeWireBackend>>> = RwLock::new(None);
+
+tokio::task_local! {
+    static SCOPED: Arc<dyn PipeWireBackend>;
+}
+
+thread_local! {
+    static THREAD_BACKEND: RefCell<Option<Arc<dyn PipeWireBackend>>> = const { RefCell::new(None) };
+}
+
+/// Open the backend of `pipewire_backend`: "pw-cli", "pw-dump" or "auto"
+/// (pw-dump if it is installed, pw-cli otherwise)
+pub fn open(name: &str) -> Result<Arc<dyn PipeWireBackend>, String> {
+    match name {
+        "pw-cli" => Ok(Arc::new(CliBackend)),
+        "pw-dump" => Ok(Arc::new(crate::pwdump::DumpBackend)),
+        "auto" if crate::pwdump::is_available() => Ok(Arc::new(crate::pwdump::DumpBackend)),
+        "auto" => Ok(Arc::new(CliBackend)),
+        other => Err(format!("Unknown PipeWire backend '{}', use \"auto\", \"pw-dump\" or \"pw-cli\"", other)),
+    }
+}
+
+/// Use a backend in the whole process instead of pw-cli
+pub fn set(backend: Arc<dyn PipeWireBackend>) {
+    *GLOBAL.write().unwrap() = Some(backend);
+}
+
+/// Backend of the current future or worker job, None outside of [`scope`]
+pub fn scoped() -> Option<Arc<dyn PipeWireBackend>> {
+    THREAD_BACKEND
+        .with(|b| b.borrow().clone())
+        .or_else(|| SCOPED.try_with(Arc::clone).ok())
+}
+
+/// The backend to use: scoped, installed with [`set`] or pw-cli
+pub fn current() -> Arc<dyn PipeWireBackend> {
+    static CLI: OnceLock<Arc<dyn PipeWireBackend>> = OnceLock::new();
+    scoped()
+        .or_else(|| GLOBAL.read().unwrap().clone())
+        .unwrap_or_else(|| Arc::clone(CLI.get_or_init(|| Arc::new(CliBackend))))
+}
+
+/// Run a future with a backend (None: the process-wide one)
+pub async fn scope<F: Future>(backend: Option<Arc<dyn PipeWireBackend>>, future: F) -> F::Output {
+    match backend {
+        Some(backend) => SCOPED.scope(backend, future).await,
+        None => future.await,
+    }
+}
+
+/// Restores the backend of the thread when dropped, also after a panic
+struct ThreadBackend(Option<Arc<dyn PipeWireBackend>>);
+
+impl Drop for ThreadBackend {
+    fn drop(&mut self) {
+        THREAD_BACKEND.with(|b| *b.borrow_mut() = self.0.take());
+    }
+}
+
+/// Run a blocking function with a backend on this thread
+pub fn run_with<T>(backend: Option<Arc<dyn PipeWireBackend>>, f: impl FnOnce() -> T) -> T {
+    let _restore = ThreadBackend(THREAD_BACKEND.with(|b| b.replace(backend)));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fake() -> Arc<FakeBackend> {
+        let fake = FakeBackend::new();
+        fake.add_object(40, "Node", &[("node.name", "alsa_output.usb"), ("media.class", "Audio/Sink")]);
+        fake.add_object(41, "Node", &[("node.name", "speakereq2x2"), ("media.class", "Audio/Sink")]);
+        fake.add_object(30, "Device", &[("device.name", "alsa_card.usb")]);
+        fake.add_param(40, "Props", json!({"channelVolumes": [1.0, 1.0], "mute": false}));
+        fake.add_param(41, "Props", json!({"params": {"speakereq2x2:Enable": true, "speakereq2x2:master_gain_db": 0.0}}));
+        fake.add_param(30, "Route", json!({"index": 1, "device": 4, "props": {"channelVolumes": [1.0, 1.0]}}));
+        Arc::new(fake)
+    }
+
+    #[test]
+    fn test_fake_backend() {
+        let fake = fake();
+        assert_eq!(fake.list_objects(Some("Node")).unwrap().len(), 2);
+        assert_eq!(fake.list_objects(None).unwrap().len(), 3);
+        assert_eq!(fake.bind(41).unwrap().unwrap().name(), Some("speakereq2x2"));
+        assert!(fake.bind(99).unwrap().is_none());
+
+        fake.set_param(40, "Props", &json!({"mute": true})).unwrap();
+        let props = fake.enum_params(40, "Props").unwrap();
+        assert_eq!(props[0].properties["mute"], json!(true));
+        assert_eq!(props[0].properties["channelVolumes"], json!([1.0, 1.0]));
+
+        // Filter-chain controls are written as a list and read as an object
+        fake.set_param(41, "Props", &json!({"params": ["speakereq2x2:master_gain_db", -3.0]})).unwrap();
+        let params = &fake.enum_params(41, "Props").unwrap()[0].properties["params"];
+        assert_eq!(params, &json!({"speakereq2x2:Enable": true, "speakereq2x2:master_gain_db": -3.0}));
+        // Names without plugin prefix address the prefixed control
+        fake.set_param(41, "Props", &json!({"params": ["master_gain_db", -6.0]})).unwrap();
+        let params = &fake.enum_params(41, "Props").unwrap()[0].properties["params"];
+        assert_eq!(params, &json!({"speakereq2x2:Enable": true, "speakereq2x2:master_gain_db": -6.0}));
+
+        fake.set_param(30, "Route", &json!({"index": 1, "device": 4, "props": {"mute": true}, "save": true})).unwrap();
+        let route = &fake.enum_params(30, "Route").unwrap()[0].properties;
+        assert_eq!(route["props"], json!({"channelVolumes": [1.0, 1.0], "mute": true}));
+
+        assert!(fake.set_param(99, "Props", &json!({"mute": true})).is_err());
+        fake.remove_object(40);
+        assert!(fake.enum_params(40, "Props").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handlers_with_fake() {
+        use axum::{body::Body, extract::Request, http::StatusCode};
+        use tower::Service;
+
+        let app = crate::api::create_router(Arc::new(crate::api_server::AppState::new()));
+        let fake: Arc<dyn PipeWireBackend> = fake();
+        let call = |method: &str, uri: &str, body: Option<Value>| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(body.map(|b| Body::from(b.to_string())).unwrap_or_default())
+                .unwrap();
+            scope(Some(Arc::clone(&fake)), app.clone().call(request))
+        };
+        let json = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), 1 << 20).await.unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        };
+
+        let response = call("GET", "/api/v1/ls/sinks", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["objects"].as_array().unwrap().len(), 2);
+
+        let response = call("PUT", "/api/v1/nodes/40/params/Props", Some(json!({"mute": true}))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["params"][0]["properties"]["mute"], json!(true));
+        assert_eq!(call("GET", "/api/v1/nodes/99/params/Props", None).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        // Outside of the scope pw-cli is used
+        assert_eq!(current().name(), "pw-cli");
+        assert_eq!(open("pw-dump").unwrap().name(), "pw-dump");
+        assert!(open("pw-top").is_err());
+        assert_eq!(run_with(Some(Arc::clone(&fake)), || current().name()), "fake");
+    }
+}
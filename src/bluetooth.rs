@@ -0,0 +1,217 @@
+//! Bluetooth device support
+//!
+//! Bluetooth devices and nodes are created by the PipeWire bluez5 plugin and
+//! carry `api.bluez5.*` properties (address, codec, profile). Battery levels
+//! are not exposed by PipeWire and are read from BlueZ via bluetoothctl.
+
+use serde::Serialize;
+use std::process::Command;
+
+use crate::command;
+use crate::pod_parser::ParamObject;
+use crate::pwcli::PwObject;
+use crate::pwparams;
+
+/// Bluetooth information of a device or node
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BluetoothInfo {
+    pub id: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Battery level in percent, if reported by the device
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery: Option<u8>,
+    /// IDs of the nodes belonging to this device
+    pub nodes: Vec<u32>,
+}
+
+/// A profile of a Bluetooth device
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BluetoothProfile {
+    pub index: i64,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub available: bool,
+}
+
+/// Check if an object belongs to the bluez5 plugin
+pub fn is_bluetooth(obj: &PwObject) -> bool {
+    obj.get("device.api") == Some("bluez5")
+        || obj.properties.keys().any(|k| k.starts_with("api.bluez5."))
+        || obj.name().map(|n| n.starts_with("bluez_")).unwrap_or(false)
+}
+
+/// Get the codec of a Bluetooth node, e.g. "sbc", "aac", "ldac"
+pub fn codec(obj: &PwObject) -> Option<&str> {
+    obj.get("api.bluez5.codec")
+}
+
+/// Get the profile of a Bluetooth node, e.g. "a2dp-sink", "headset-head-unit"
+pub fn profile(obj: &PwObject) -> Option<&str> {
+    obj.get("api.bluez5.profile")
+}
+
+/// Get the Bluetooth address of a device or node
+pub fn address(obj: &PwObject) -> Option<&str> {
+    obj.get("api.bluez5.address")
+        .or_else(|| obj.get("device.string"))
+}
+
+/// Build Bluetooth info for a device, collecting codec and profile from its nodes
+pub fn device_info(device: &PwObject, nodes: &[PwObject]) -> BluetoothInfo {
+    let id_str = device.id.to_string();
+    let device_nodes: Vec<&PwObject> = nodes
+        .iter()
+        .filter(|n| n.get("device.id") == Some(id_str.as_str()))
+        .collect();
+
+    let address = address(device).map(|s| s.to_string());
+    let battery = address.as_deref().and_then(|a| read_battery(a).ok().flatten());
+
+    BluetoothInfo {
+        id: device.id,
+        name: device.display_name(),
+        codec: device_nodes.iter().find_map(|n| codec(n)).map(|s| s.to_string()),
+        profile: device_nodes
+            .iter()
+            .find_map(|n| profile(n))
+            .or_else(|| device.get("device.profile.name"))
+            .map(|s| s.to_string()),
+        address,
+        battery,
+        nodes: device_nodes.iter().map(|n| n.id).collect(),
+    }
+}
+
+/// Parse the battery percentage from `bluetoothctl info` output
+///
+/// The line looks like `Battery Percentage: 0x5a (90)`.
+pub fn parse_battery(output: &str) -> Option<u8> {
+    output.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix("Battery Percentage:")?;
+        let start = rest.find('(')?;
+        let end = rest.find(')')?;
+        rest[start + 1..end].trim().parse().ok()
+    })
+}
+
+/// Read the battery level of a Bluetooth device from BlueZ
+pub fn read_battery(address: &str) -> Result<Option<u8>, String> {
+    let output = command::output_with_timeout(
+        Command::new("bluetoothctl").args(["info", address]),
+        command::discovery_timeout(),
+    )
+    .map_err(|e| format!("Failed to run bluetoothctl: {}", e))?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    Ok(parse_battery(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Convert EnumProfile parameter objects to profiles
+pub fn profiles_from_params(params: &[ParamObject]) -> Vec<BluetoothProfile> {
+    params
+        .iter()
+        .filter_map(|p| {
+            let index = p.properties.get("index")?.as_i64()?;
+            let name = p.properties.get("name")?.as_str()?.to_string();
+            Some(BluetoothProfile {
+                index,
+                name,
+                description: p
+                    .properties
+                    .get("description")
+                    .and_then(|d| d.as_str())
+                    .map(|s| s.to_string()),
+                available: p
+                    .properties
+                    .get("available")
+                    .and_then(|a| a.as_str())
+                    .map(|a| a != "no")
+                    .unwrap_or(true),
+            })
+        })
+        .collect()
+}
+
+/// List the profiles of a device
+pub fn list_profiles(device_id: u32) -> Result<Vec<BluetoothProfile>, String> {
+    let params = pwparams::enum_params(device_id, "EnumProfile")?;
+    Ok(profiles_from_params(&params))
+}
+
+/// Switch the profile of a device by name or index
+pub fn set_profile(device_id: u32, profile: &str) -> Result<BluetoothProfile, String> {
+    let profiles = list_profiles(device_id)?;
+    let selected = profiles
+        .iter()
+        .find(|p| p.name == profile || p.index.to_string() == profile)
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "Unknown profile '{}'. Available: {}",
+                profile,
+                profiles.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", ")
+            )
+        })?;
+
+    pwparams::set_param(
+        device_id,
+        "Profile",
+        &serde_json::json!({ "index": selected.index, "save": true }),
+    )?;
+
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_bluetooth() {
+        let bt = PwObject::new(50, "Device", &[("device.api", "bluez5"), ("device.name", "bluez_card.AA_BB")]);
+        let alsa = PwObject::new(51, "Device", &[("device.api", "alsa"), ("device.name", "alsa_card.0")]);
+        let bt_node = PwObject::new(60, "Node", &[("node.name", "bluez_output.AA_BB.1"), ("api.bluez5.codec", "aac")]);
+        assert!(is_bluetooth(&bt));
+        assert!(!is_bluetooth(&alsa));
+        assert!(is_bluetooth(&bt_node));
+        assert_eq!(codec(&bt_node), Some("aac"));
+    }
+
+    #[test]
+    fn test_parse_battery() {
+        let output = "Device AA:BB:CC:DD:EE:FF (public)\n\tName: Phone\n\tBattery Percentage: 0x5a (90)\n";
+        assert_eq!(parse_battery(output), Some(90));
+        assert_eq!(parse_battery("Device AA:BB\n\tName: Phone\n"), None);
+    }
+
+    #[test]
+    fn test_profiles_from_params() {
+        let output = r#"
+  Object: size 100, type Spa:Pod:Object:Param:Profile (262152), id Spa:Enum:ParamId:EnumProfile (8)
+    Prop: key Spa:Pod:Object:Param:Profile:index (1), flags 00000000
+      Int 1
+    Prop: key Spa:Pod:Object:Param:Profile:name (2), flags 00000000
+      String "a2dp-sink"
+    Prop: key Spa:Pod:Object:Param:Profile:description (3), flags 00000000
+      String "High Fidelity Playback (A2DP Sink)"
+    Prop: key Spa:Pod:Object:Param:Profile:available (5), flags 00000000
+      Id 2        (Spa:Enum:ParamAvailability:yes)
+"#;
+        let params = crate::pod_parser::parse_enum_params(output);
+        let profiles = profiles_from_params(&params);
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].index, 1);
+        assert_eq!(profiles[0].name, "a2dp-sink");
+        assert!(profiles[0].available);
+    }
+}
@@ -0,0 +1,215 @@
+//! Bluetooth auto-connect rules
+//!
+//! When a Bluetooth audio source appears (e.g. a phone streaming via A2DP),
+//! link it to a configured sink and optionally set its volume. Rules are
+//! loaded from `bluetooth-rules.conf` and match on the device MAC address:
+//!
+//! ```json
+//! [
+//!   {
+//!     "name": "phone to DSP",
+//!     "address": "AA:BB:CC:.*",
+//!     "destination": { "node.name": "^speakereq2x2$" },
+//!     "volume": 0.8
+//!   }
+//! ]
+//! ```
+//!
+//! Nodes are detected by polling; each source node is handled once after it
+//! appears.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::bluetooth;
+use crate::link_manager_cli;
+use crate::linker::{LinkRule, LinkType, LogLevel, NodeIdentifier};
+use crate::pwcli::{self, PwObject};
+use crate::util::regex_match;
+
+/// A Bluetooth auto-connect rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BluetoothRule {
+    /// Name of the rule (used in log messages)
+    pub name: String,
+    /// Regex matching the device MAC address (case-insensitive), all devices if not set
+    #[serde(default)]
+    pub address: Option<String>,
+    /// Sink to link the Bluetooth source to
+    pub destination: NodeIdentifier,
+    /// Volume to set on the Bluetooth source node (0.0 - 2.0)
+    #[serde(default)]
+    pub volume: Option<f32>,
+}
+
+impl BluetoothRule {
+    /// Check whether the rule applies to a device address
+    pub fn matches_address(&self, address: &str) -> bool {
+        match self.address {
+            Some(ref pattern) => regex_match(&format!("(?i){}", pattern), address),
+            None => true,
+        }
+    }
+}
+
+/// Check if a node is an audio source created for a Bluetooth device
+pub fn is_bluetooth_source(node: &PwObject) -> bool {
+    bluetooth::is_bluetooth(node)
+        && matches!(node.media_class(), Some("Audio/Source") | Some("Stream/Output/Audio"))
+}
+
+/// Find the first rule that matches a Bluetooth source node
+pub fn find_rule<'a>(rules: &'a [BluetoothRule], node: &PwObject) -> Option<&'a BluetoothRule> {
+    let address = bluetooth::address(node)?;
+    rules.iter().find(|r| r.matches_address(address))
+}
+
+/// Link a Bluetooth source node and set its volume
+pub fn apply_rule(rule: &BluetoothRule, node: &PwObject) -> Result<(), String> {
+    let name = node.name().ok_or_else(|| format!("Node {} has no name", node.id))?;
+    let link = LinkRule {
+        name: rule.name.clone(),
+        source: NodeIdentifier {
+            node_name: Some(format!("^{}$", regex::escape(name))),
+            node_nick: None,
+            object_path: None,
+        },
+        destination: rule.destination.clone(),
+        link_type: LinkType::Link,
+        link_at_startup: false,
+        relink_every: 0,
+        info_level: LogLevel::Info,
+        error_level: LogLevel::Error,
+        wait_for: None,
+        exclusive: false,
+        channel_map: None,
+        source_ports: None,
+        destination_ports: None,
+        include_monitors: false,
+    };
+
+    let failures: Vec<String> = link_manager_cli::apply_link_rule(&link)?
+        .into_iter()
+        .filter(|r| !r.success)
+        .map(|r| r.message)
+        .collect();
+    if !failures.is_empty() {
+        return Err(failures.join("; "));
+    }
+
+    if let Some(volume) = rule.volume {
+        crate::wpctl::set_volume(node.id, volume)?;
+    }
+    Ok(())
+}
+
+/// Handle Bluetooth source nodes that appeared since the last check
+///
+/// `seen` holds the IDs of the handled nodes; IDs of removed nodes are dropped.
+pub fn check_new_sources(rules: &[BluetoothRule], seen: &mut HashSet<u32>) -> Result<(), String> {
+    let sources: Vec<PwObject> = pwcli::list_nodes()?
+        .into_iter()
+        .filter(is_bluetooth_source)
+        .collect();
+
+    seen.retain(|id| sources.iter().any(|n| n.id == *id));
+
+    for node in &sources {
+        if !seen.insert(node.id) {
+            continue;
+        }
+        let Some(rule) = find_rule(rules, node) else {
+            continue;
+        };
+        match apply_rule(rule, node) {
+            Ok(()) => info!("Bluetooth rule '{}' applied to {}", rule.name, node.display_name()),
+            Err(e) => warn!("Bluetooth rule '{}' failed for {}: {}", rule.name, node.display_name(), e),
+        }
+    }
+    Ok(())
+}
+
+/// Start the task that applies Bluetooth rules to new source nodes
+pub fn start_bluetooth_rules(rules: Vec<BluetoothRule>, interval: Duration) -> tokio::task::JoinHandle<()> {
+    let rules = Arc::new(rules);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut seen = HashSet::new();
+        info!("Bluetooth rules started with {} rule(s)", rules.len());
+
+        loop {
+            ticker.tick().await;
+            let rules = rules.clone();
+            let mut current = std::mem::take(&mut seen);
+            match crate::worker::run(move || {
+                let result = check_new_sources(&rules, &mut current);
+                (current, result)
+            })
+            .await
+            {
+                Ok((current, result)) => {
+                    seen = current;
+                    if let Err(e) = result {
+                        warn!("Failed to check Bluetooth nodes: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to check Bluetooth nodes: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(media_class: &str, address: &str) -> PwObject {
+        PwObject::new(80, "Node", &[
+            ("node.name", "bluez_input.AA_BB_CC_DD_EE_FF.2"),
+            ("media.class", media_class),
+            ("api.bluez5.address", address),
+        ])
+    }
+
+    fn rule(address: Option<&str>) -> BluetoothRule {
+        BluetoothRule {
+            name: "test".to_string(),
+            address: address.map(|a| a.to_string()),
+            destination: NodeIdentifier {
+                node_name: Some("^speakereq2x2$".to_string()),
+                node_nick: None,
+                object_path: None,
+            },
+            volume: None,
+        }
+    }
+
+    #[test]
+    fn test_is_bluetooth_source() {
+        assert!(is_bluetooth_source(&node("Audio/Source", "AA:BB:CC:DD:EE:FF")));
+        assert!(!is_bluetooth_source(&node("Audio/Sink", "AA:BB:CC:DD:EE:FF")));
+    }
+
+    #[test]
+    fn test_find_rule_by_address() {
+        let rules = vec![rule(Some("^11:22:")), rule(Some("aa:bb:cc:.*")), rule(None)];
+        let source = node("Audio/Source", "AA:BB:CC:DD:EE:FF");
+        let found = find_rule(&rules, &source).unwrap();
+        assert_eq!(found.address.as_deref(), Some("aa:bb:cc:.*"));
+
+        let rules = vec![rule(Some("^11:22:"))];
+        assert!(find_rule(&rules, &source).is_none());
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let json = r#"[{"name": "phone", "address": "AA:BB:.*",
+            "destination": {"node.name": "^speakereq2x2$"}, "volume": 0.8}]"#;
+        let rules: Vec<BluetoothRule> = serde_json::from_str(json).unwrap();
+        assert_eq!(rules[0].volume, Some(0.8));
+        assert!(rules[0].matches_address("aa:bb:cc:dd:ee:ff"));
+    }
+}
@@ -0,0 +1,196 @@
+//! Enable/bypass of any DSP node
+//!
+//! Many filter-chain plugins have a control that switches the processing
+//! off: an `Enable` control (off means bypassed) or a `bypass` control (on
+//! means bypassed). The controls are found in the PropInfo of the node (see
+//! [`crate::prop_schema`]), so any DSP node can be A/B compared without
+//! module-specific code. A node with several plugins has a control per
+//! plugin; all of them are switched together.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::parameters::{self, ParameterValue};
+use crate::prop_schema::{self, PropSchema};
+use crate::pwparams;
+
+/// Meaning of a bypass control
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlKind {
+    /// On means processing (e.g. `eq:Enable`)
+    Enable,
+    /// On means bypassed (e.g. `comp:bypass`)
+    Bypass,
+}
+
+/// A control that enables or bypasses a plugin
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BypassControl {
+    pub name: String,
+    pub kind: ControlKind,
+    /// Value type of the control: bool, int or float
+    #[serde(rename = "type")]
+    pub value_type: String,
+    /// Current value, None if not reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+/// Bypass state of a node
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BypassStatus {
+    pub id: u32,
+    /// All controls are in the bypassed state
+    pub bypassed: bool,
+    pub controls: Vec<BypassControl>,
+}
+
+/// Kind of a control from its name, e.g. "eq:Enable" or "comp:Bypass"
+fn control_kind(name: &str) -> Option<ControlKind> {
+    let short = name.rsplit(':').next().unwrap_or(name).trim().to_lowercase();
+    match short.as_str() {
+        "enable" | "enabled" => Some(ControlKind::Enable),
+        "bypass" | "bypassed" => Some(ControlKind::Bypass),
+        _ => None,
+    }
+}
+
+/// Find the enable and bypass controls of a node
+pub fn find_controls(schema: &[PropSchema]) -> Vec<BypassControl> {
+    schema
+        .iter()
+        .filter(|s| s.param && matches!(s.value_type.as_str(), "bool" | "int" | "float"))
+        .filter_map(|s| {
+            Some(BypassControl {
+                name: s.name.clone(),
+                kind: control_kind(&s.name)?,
+                value_type: s.value_type.clone(),
+                value: None,
+            })
+        })
+        .collect()
+}
+
+fn is_on(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::Number(n) => n.as_f64().map(|n| n > 0.5),
+        _ => None,
+    }
+}
+
+/// Whether a control is in the bypassed state
+fn is_bypassed(control: &BypassControl) -> Option<bool> {
+    let on = is_on(control.value.as_ref()?)?;
+    Some(match control.kind {
+        ControlKind::Enable => !on,
+        ControlKind::Bypass => on,
+    })
+}
+
+/// Build the status from the controls and the `params` struct of the Props
+pub fn status(id: u32, mut controls: Vec<BypassControl>, values: &Map<String, Value>) -> BypassStatus {
+    for control in &mut controls {
+        control.value = values.get(&control.name).cloned();
+    }
+    let bypassed = !controls.is_empty() && controls.iter().all(|c| is_bypassed(c) == Some(true));
+    BypassStatus { id, bypassed, controls }
+}
+
+/// Parameter values that bypass (or enable) all controls
+pub fn bypass_params(controls: &[BypassControl], bypassed: bool) -> HashMap<String, ParameterValue> {
+    controls
+        .iter()
+        .map(|c| {
+            let on = match c.kind {
+                ControlKind::Enable => !bypassed,
+                ControlKind::Bypass => bypassed,
+            };
+            let value = match c.value_type.as_str() {
+                "int" => ParameterValue::Int(on as i32),
+                "float" => ParameterValue::Float(if on { 1.0 } else { 0.0 }),
+                _ => ParameterValue::Bool(on),
+            };
+            (c.name.clone(), value)
+        })
+        .collect()
+}
+
+fn read_controls(id: u32) -> Result<Vec<BypassControl>, String> {
+    let controls = find_controls(&prop_schema::read_schema(id)?);
+    if controls.is_empty() {
+        return Err(format!("Node {} has no enable or bypass control", id));
+    }
+    Ok(controls)
+}
+
+/// Read the bypass state of a node with pw-cli
+pub fn read_status(id: u32) -> Result<BypassStatus, String> {
+    let controls = read_controls(id)?;
+    let props = pwparams::enum_params(id, "Props")?;
+    let values = props
+        .iter()
+        .find_map(|p| p.properties.get("params").and_then(|v| v.as_object()).cloned())
+        .unwrap_or_default();
+    Ok(status(id, controls, &values))
+}
+
+/// Bypass or enable a node and return the new state
+pub fn set_bypassed(id: u32, bypassed: bool) -> Result<BypassStatus, String> {
+    let controls = read_controls(id)?;
+    parameters::set_params(id, &bypass_params(&controls, bypassed))?;
+    read_status(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn control(name: &str, value_type: &str) -> PropSchema {
+        PropSchema {
+            name: name.to_string(),
+            value_type: value_type.to_string(),
+            param: true,
+            description: None,
+            default: None,
+            min: None,
+            max: None,
+            step: None,
+            options: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_find_controls() {
+        let schema = vec![
+            control("eq:Enable", "bool"),
+            control("comp:bypass", "float"),
+            control("eq:Gain", "float"),
+            control("riaa:RIAA Enable", "bool"),
+        ];
+        let controls = find_controls(&schema);
+        assert_eq!(controls.len(), 2);
+        assert_eq!(controls[0].kind, ControlKind::Enable);
+        assert_eq!(controls[1].kind, ControlKind::Bypass);
+
+        let params = bypass_params(&controls, true);
+        assert_eq!(params["eq:Enable"], ParameterValue::Bool(false));
+        assert_eq!(params["comp:bypass"], ParameterValue::Float(1.0));
+    }
+
+    #[test]
+    fn test_status() {
+        let controls = find_controls(&[control("eq:Enable", "bool"), control("comp:bypass", "float")]);
+        let values = json!({"eq:Enable": false, "comp:bypass": 1.0});
+        let bypassed = status(7, controls.clone(), values.as_object().unwrap());
+        assert!(bypassed.bypassed);
+        assert_eq!(bypassed.controls[0].value, Some(json!(false)));
+
+        let values = json!({"eq:Enable": true, "comp:bypass": 1.0});
+        assert!(!status(7, controls, values.as_object().unwrap()).bypassed);
+    }
+}
@@ -0,0 +1,358 @@
+//! Audio capture from a node
+//!
+//! Audio is recorded with `pw-record` as raw 16 bit PCM. For a sink the
+//! monitor is recorded (`stream.capture.sink`), for a source its output.
+//! WAV is the raw audio behind a header; FLAC is encoded by piping the raw
+//! audio through the `flac` command line encoder.
+//!
+//! A capture runs until the given duration is reached, it is stopped or the
+//! receiver of the encoded audio goes away, then the processes are killed.
+
+use axum::body::Bytes;
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, warn};
+
+use crate::pwcli::PwObject;
+
+/// Sample rate used if none is given
+pub const DEFAULT_RATE: u32 = 48000;
+
+/// Channel count used if none is given
+pub const DEFAULT_CHANNELS: u16 = 2;
+
+/// Bits per sample of the captured audio
+const BITS: u16 = 16;
+
+/// Size of the chunks read from the processes
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Encoded chunks buffered before the capture waits for the receiver
+const CHANNEL_CAPACITY: usize = 32;
+
+/// Container of the captured audio
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureFormat {
+    #[default]
+    Wav,
+    Flac,
+}
+
+impl CaptureFormat {
+    /// MIME type of the format
+    pub fn content_type(self) -> &'static str {
+        match self {
+            CaptureFormat::Wav => "audio/wav",
+            CaptureFormat::Flac => "audio/flac",
+        }
+    }
+
+    /// File extension of the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            CaptureFormat::Wav => "wav",
+            CaptureFormat::Flac => "flac",
+        }
+    }
+}
+
+/// What to capture and how
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureSpec {
+    /// node.name of the node
+    pub target: String,
+    /// Record the monitor of a sink
+    pub monitor: bool,
+    pub format: CaptureFormat,
+    pub rate: u32,
+    pub channels: u16,
+    /// Stop after this many seconds (None: until the receiver goes away)
+    pub duration_secs: Option<u64>,
+}
+
+impl CaptureSpec {
+    /// Bytes of raw audio per second
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.rate as u64 * self.channels as u64 * (BITS / 8) as u64
+    }
+
+    /// Bytes of raw audio to capture, None without duration
+    pub fn byte_limit(&self) -> Option<u64> {
+        self.duration_secs.map(|secs| secs * self.bytes_per_sec())
+    }
+}
+
+/// Node name and whether the monitor is recorded, for an audio sink or source
+pub fn capture_target(node: &PwObject) -> Result<(String, bool), String> {
+    let name = node.name().ok_or_else(|| format!("Node {} has no name", node.id))?.to_string();
+    match node.media_class() {
+        Some(class) if class.starts_with("Audio/Sink") => Ok((name, true)),
+        Some(class) if class.starts_with("Audio/Source") || class == "Audio/Duplex" => Ok((name, false)),
+        class => Err(format!(
+            "Node {} is not an audio sink or source ({})",
+            node.id,
+            class.unwrap_or("no media class")
+        )),
+    }
+}
+
+/// Check rate, channels and duration
+pub fn validate(rate: u32, channels: u16, duration_secs: Option<u64>, max_duration_secs: u64) -> Result<(), String> {
+    if !(8000..=192_000).contains(&rate) {
+        return Err(format!("Invalid rate {} (8000 - 192000)", rate));
+    }
+    if !(1..=8).contains(&channels) {
+        return Err(format!("Invalid channel count {} (1 - 8)", channels));
+    }
+    if let Some(secs) = duration_secs {
+        if secs == 0 || secs > max_duration_secs {
+            return Err(format!("Duration must be 1 to {} seconds", max_duration_secs));
+        }
+    }
+    Ok(())
+}
+
+/// Arguments of pw-record writing raw audio to stdout
+pub fn record_args(spec: &CaptureSpec) -> Vec<String> {
+    let mut args = vec!["--target".to_string(), spec.target.clone()];
+    if spec.monitor {
+        args.extend(["-P".to_string(), "{ stream.capture.sink = true }".to_string()]);
+    }
+    args.extend([
+        "--rate".to_string(),
+        spec.rate.to_string(),
+        "--channels".to_string(),
+        spec.channels.to_string(),
+        "--format".to_string(),
+        format!("s{}", BITS),
+        "--raw".to_string(),
+        "-".to_string(),
+    ]);
+    args
+}
+
+/// Arguments of flac encoding raw audio from stdin to stdout
+pub fn flac_args(spec: &CaptureSpec) -> Vec<String> {
+    vec![
+        "--silent".to_string(),
+        "--force-raw-format".to_string(),
+        "--endian=little".to_string(),
+        "--sign=signed".to_string(),
+        format!("--channels={}", spec.channels),
+        format!("--bps={}", BITS),
+        format!("--sample-rate={}", spec.rate),
+        "--stdout".to_string(),
+        "-".to_string(),
+    ]
+}
+
+/// WAV header for 16 bit PCM
+///
+/// Without a length (live streams) the sizes are set to the maximum, which
+/// players treat as "until the end of the stream".
+pub fn wav_header(rate: u32, channels: u16, data_len: Option<u32>) -> Vec<u8> {
+    let block_align = channels * (BITS / 8);
+    let data_len = data_len.unwrap_or(u32::MAX - 36);
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&data_len.saturating_add(36).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&rate.to_le_bytes());
+    header.extend_from_slice(&(rate * block_align as u32).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&BITS.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+/// A running capture
+pub struct Capture {
+    /// Encoded audio, the WAV header is the first chunk
+    pub audio: mpsc::Receiver<Bytes>,
+    /// Send `true` to end the capture; the rest of the audio still arrives
+    pub stop: watch::Sender<bool>,
+}
+
+/// Wait until `true` is sent; never returns if the sender is dropped
+async fn stopped(stop: &mut watch::Receiver<bool>) {
+    while stop.changed().await.is_ok() {
+        if *stop.borrow() {
+            return;
+        }
+    }
+    std::future::pending::<()>().await
+}
+
+/// Copy from a reader to the channel until it ends or the receiver goes away
+pub async fn forward(mut reader: impl AsyncRead + Unpin, sender: &mpsc::Sender<Bytes>) {
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buffer).await {
+            Ok(0) => return,
+            Ok(n) => {
+                if sender.send(Bytes::copy_from_slice(&buffer[..n])).await.is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                warn!("Failed to read captured audio: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Pump raw audio from pw-record to the channel (WAV) or the encoder (FLAC)
+async fn pump(
+    mut recorder: Child,
+    mut encoder: Option<Child>,
+    spec: CaptureSpec,
+    sender: mpsc::Sender<Bytes>,
+    mut stop: watch::Receiver<bool>,
+) {
+    let Some(mut raw) = recorder.stdout.take() else {
+        return;
+    };
+    let mut encoder_input = encoder.as_mut().and_then(|e| e.stdin.take());
+    let encoded = encoder.as_mut().and_then(|e| e.stdout.take()).map(|stdout| {
+        let sender = sender.clone();
+        tokio::spawn(async move { forward(stdout, &sender).await })
+    });
+
+    let limit = spec.byte_limit();
+    let mut total: u64 = 0;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        let wanted = limit.map_or(CHUNK_SIZE as u64, |l| (l - total).min(CHUNK_SIZE as u64)) as usize;
+        if wanted == 0 {
+            break;
+        }
+        let n = tokio::select! {
+            read = raw.read(&mut buffer[..wanted]) => match read {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) => {
+                    warn!("Failed to read from pw-record: {}", e);
+                    break;
+                }
+            },
+            _ = stopped(&mut stop) => break,
+        };
+        total += n as u64;
+        let delivered = match encoder_input.as_mut() {
+            Some(input) => input.write_all(&buffer[..n]).await.is_ok(),
+            None => sender.send(Bytes::copy_from_slice(&buffer[..n])).await.is_ok(),
+        };
+        if !delivered || sender.is_closed() {
+            break;
+        }
+    }
+
+    let _ = recorder.kill().await;
+    // Closing the input lets flac write the rest of the stream
+    drop(encoder_input);
+    if let Some(task) = encoded {
+        let _ = task.await;
+    }
+    if let Some(mut encoder) = encoder {
+        let _ = encoder.kill().await;
+    }
+    debug!("Capture of {} ended after {} bytes", spec.target, total);
+}
+
+/// Start a capture
+///
+/// The processes are killed when the duration is reached, the capture is
+/// stopped or the receiver of the audio is dropped.
+pub fn start_capture(spec: CaptureSpec) -> Result<Capture, String> {
+    let mut recorder = Command::from(crate::command::pipewire("pw-record"))
+        .args(record_args(&spec))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start pw-record: {}", e))?;
+
+    let encoder = match spec.format {
+        CaptureFormat::Wav => None,
+        CaptureFormat::Flac => match Command::new("flac")
+            .args(flac_args(&spec))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(encoder) => Some(encoder),
+            Err(e) => {
+                let _ = recorder.start_kill();
+                return Err(format!("Failed to start flac: {}", e));
+            }
+        },
+    };
+
+    let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+    if spec.format == CaptureFormat::Wav {
+        let data_len = spec.byte_limit().map(|l| l.min((u32::MAX - 36) as u64) as u32);
+        // The channel is empty, so the header always fits
+        let _ = sender.try_send(Bytes::from(wav_header(spec.rate, spec.channels, data_len)));
+    }
+    debug!("Capturing {} as {:?}", spec.target, spec.format);
+    let (stop, stop_receiver) = watch::channel(false);
+    tokio::spawn(pump(recorder, encoder, spec, sender, stop_receiver));
+    Ok(Capture { audio: receiver, stop })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(class: &str) -> PwObject {
+        PwObject::new(56, "Node", &[("node.name", "alsa_output.usb"), ("media.class", class)])
+    }
+
+    #[test]
+    fn test_capture_target_and_args() {
+        assert_eq!(capture_target(&node("Audio/Sink")).unwrap(), ("alsa_output.usb".to_string(), true));
+        assert!(!capture_target(&node("Audio/Source")).unwrap().1);
+        assert!(capture_target(&node("Video/Source")).is_err());
+
+        let spec = CaptureSpec {
+            target: "alsa_output.usb".to_string(),
+            monitor: true,
+            format: CaptureFormat::Flac,
+            rate: 44100,
+            channels: 2,
+            duration_secs: Some(10),
+        };
+        let args = record_args(&spec);
+        assert!(args.contains(&"{ stream.capture.sink = true }".to_string()));
+        assert!(args.windows(2).any(|w| w == ["--rate", "44100"]));
+        assert!(flac_args(&spec).contains(&"--sample-rate=44100".to_string()));
+        assert_eq!(spec.byte_limit(), Some(10 * 44100 * 4));
+        assert!(validate(44100, 2, Some(0), 3600).is_err());
+        assert!(validate(44100, 2, None, 3600).is_ok());
+    }
+
+    #[test]
+    fn test_wav_header() {
+        let header = wav_header(48000, 2, Some(192_000));
+        assert_eq!(header.len(), 44);
+        assert_eq!(&header[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(header[4..8].try_into().unwrap()), 192_036);
+        assert_eq!(u32::from_le_bytes(header[28..32].try_into().unwrap()), 192_000);
+        assert_eq!(u16::from_le_bytes(header[32..34].try_into().unwrap()), 4);
+        assert_eq!(u32::from_le_bytes(header[40..44].try_into().unwrap()), 192_000);
+
+        let live = wav_header(48000, 2, None);
+        assert_eq!(u32::from_le_bytes(live[4..8].try_into().unwrap()), u32::MAX);
+    }
+}
@@ -0,0 +1,195 @@
+//! Combined sinks
+//!
+//! A combined sink plays one stream on several sinks. It is a null audio
+//! sink (`support.null-audio-sink`) whose monitor ports are linked to the
+//! inputs of every target sink. The node is created with `object.linger`
+//! so it stays after pw-cli exits; it can be removed with `pw-cli destroy`.
+
+use serde::Serialize;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::link_manager_cli::{LinkData, PortInfo};
+use crate::pwcli;
+use crate::pwlink;
+
+/// Time to wait for the combined sink node to appear
+const NODE_STARTUP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Channel positions used if the target sinks do not report any
+const DEFAULT_POSITIONS: &[&str] = &["FL", "FR"];
+
+/// A created combined sink
+#[derive(Debug, Clone, Serialize)]
+pub struct CombinedSink {
+    pub id: u32,
+    pub name: String,
+    pub sinks: Vec<String>,
+    /// Created links, as "output port -> input port"
+    pub links: Vec<String>,
+}
+
+/// Pair output ports with input ports
+///
+/// Ports are matched by `audio.channel`; if the channels don't match, they
+/// are paired in port ID order.
+pub fn pair_ports<'a>(outputs: &[&'a PortInfo], inputs: &[&'a PortInfo]) -> Vec<(&'a PortInfo, &'a PortInfo)> {
+    let mut outputs = outputs.to_vec();
+    let mut inputs = inputs.to_vec();
+    outputs.sort_by_key(|p| p.id);
+    inputs.sort_by_key(|p| p.id);
+
+    let by_channel: Vec<_> = outputs
+        .iter()
+        .filter_map(|out| {
+            let channel = out.channel.as_ref()?;
+            inputs
+                .iter()
+                .find(|i| i.channel.as_ref() == Some(channel))
+                .map(|i| (*out, *i))
+        })
+        .collect();
+
+    if by_channel.is_empty() {
+        outputs.into_iter().zip(inputs).collect()
+    } else {
+        by_channel
+    }
+}
+
+/// Build the properties for pw-cli create-node
+fn node_properties(name: &str, positions: &[String]) -> String {
+    format!(
+        "{{ factory.name=support.null-audio-sink node.name=\"{}\" node.description=\"{}\" \
+         media.class=Audio/Sink object.linger=true audio.channels={} audio.position=[ {} ] }}",
+        name,
+        name,
+        positions.len(),
+        positions.join(" ")
+    )
+}
+
+/// Create a null sink and wait until its node is registered
+pub fn create_null_sink(name: &str, positions: &[String]) -> Result<u32, String> {
+    let output = crate::command::pipewire("pw-cli")
+        .args(["create-node", "adapter", &node_properties(name, positions)])
+        .output()
+        .map_err(|e| format!("Failed to run pw-cli create-node: {}", e))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || stderr.contains("error") {
+        return Err(format!("pw-cli create-node failed: {}", stderr.trim()));
+    }
+
+    let start = Instant::now();
+    while start.elapsed() < NODE_STARTUP_TIMEOUT {
+        if let Some(node) = pwcli::list_nodes()?.iter().find(|n| n.name() == Some(name)) {
+            return Ok(node.id);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    Err(format!("Null sink '{}' did not appear", name))
+}
+
+/// Create a combined sink that plays to all given sinks (by node.name)
+pub fn create_combined_sink(name: &str, sinks: &[String]) -> Result<CombinedSink, String> {
+    let data = LinkData::load()?;
+    if data.nodes.iter().any(|n| n.node_name.as_deref() == Some(name)) {
+        return Err(format!("A node named '{}' already exists", name));
+    }
+
+    let mut targets = Vec::new();
+    for sink in sinks {
+        let node = data
+            .nodes
+            .iter()
+            .find(|n| n.node_name.as_deref() == Some(sink.as_str()))
+            .ok_or_else(|| format!("Sink '{}' not found", sink))?;
+        targets.push(node.id);
+    }
+
+    // Use the channel layout of the first sink
+    let mut positions: Vec<String> = targets
+        .first()
+        .map(|id| {
+            let mut ports = data.get_input_ports(*id);
+            ports.sort_by_key(|p| p.id);
+            ports.iter().filter_map(|p| p.channel.clone()).collect()
+        })
+        .unwrap_or_default();
+    if positions.is_empty() {
+        positions = DEFAULT_POSITIONS.iter().map(|s| s.to_string()).collect();
+    }
+
+    let id = create_null_sink(name, &positions)?;
+
+    let data = LinkData::load()?;
+    let mut monitors = data.get_monitor_ports(id);
+    if monitors.is_empty() {
+        monitors = data.get_output_ports(id);
+    }
+    let mut links = Vec::new();
+    for target in targets {
+        for (out, input) in pair_ports(&monitors, &data.get_input_ports(target)) {
+            pwlink::create_link(&out.full_name, &input.full_name)?;
+            links.push(format!("{} -> {}", out.full_name, input.full_name));
+        }
+    }
+
+    Ok(CombinedSink {
+        id,
+        name: name.to_string(),
+        sinks: sinks.to_vec(),
+        links,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link_manager_cli::PortDirection;
+
+    fn port(id: u32, name: &str, direction: PortDirection, channel: Option<&str>) -> PortInfo {
+        PortInfo {
+            id,
+            node_id: 1,
+            name: name.to_string(),
+            full_name: format!("node:{}", name),
+            direction,
+            channel: channel.map(|c| c.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_pair_ports_by_channel() {
+        let out_l = port(10, "monitor_FL", PortDirection::Output, Some("FL"));
+        let out_r = port(11, "monitor_FR", PortDirection::Output, Some("FR"));
+        let in_r = port(20, "playback_FR", PortDirection::Input, Some("FR"));
+        let in_l = port(21, "playback_FL", PortDirection::Input, Some("FL"));
+
+        let pairs = pair_ports(&[&out_l, &out_r], &[&in_r, &in_l]);
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].1.name, "playback_FL");
+        assert_eq!(pairs[1].1.name, "playback_FR");
+    }
+
+    #[test]
+    fn test_pair_ports_by_order() {
+        let out_l = port(10, "monitor_FL", PortDirection::Output, Some("FL"));
+        let out_r = port(11, "monitor_FR", PortDirection::Output, Some("FR"));
+        let in_1 = port(20, "playback_1", PortDirection::Input, None);
+        let in_2 = port(21, "playback_2", PortDirection::Input, None);
+
+        let pairs = pair_ports(&[&out_l, &out_r], &[&in_2, &in_1]);
+        assert_eq!(pairs[0].1.name, "playback_1");
+        assert_eq!(pairs[1].1.name, "playback_2");
+    }
+
+    #[test]
+    fn test_node_properties() {
+        let props = node_properties("combined", &["FL".to_string(), "FR".to_string()]);
+        assert!(props.contains("node.name=\"combined\""));
+        assert!(props.contains("object.linger=true"));
+        assert!(props.contains("audio.position=[ FL FR ]"));
+    }
+}
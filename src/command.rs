@@ -0,0 +1,143 @@
+//! Running PipeWire command line tools with timeouts
+//!
+//! There is no PipeWire client in-process: all queries run pw-cli, pw-link
+//! or pw-metadata. These tools finish their enumeration with a core sync
+//! roundtrip and exit once the server's `done` event arrives, so a listing
+//! is complete when the process exits and no fixed sleeps are needed.
+//!
+//! The tools can still hang if PipeWire is unresponsive (e.g. on a heavily
+//! loaded SBC). All discovery and parameter reads go through
+//! [`output_with_timeout`] so a stuck tool fails with a clear error instead
+//! of blocking a request forever.
+
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::sync::RwLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Timeouts for external commands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandTimeouts {
+    /// Object discovery (pw-cli ls, pw-link -l/-i/-o, pw-metadata)
+    pub discovery: Duration,
+    /// Parameter reads (pw-cli enum-params)
+    pub param_read: Duration,
+}
+
+impl Default for CommandTimeouts {
+    fn default() -> Self {
+        Self {
+            discovery: Duration::from_millis(5000),
+            param_read: Duration::from_millis(3000),
+        }
+    }
+}
+
+static TIMEOUTS: RwLock<CommandTimeouts> = RwLock::new(CommandTimeouts {
+    discovery: Duration::from_millis(5000),
+    param_read: Duration::from_millis(3000),
+});
+
+/// Set the timeouts used for all subsequent commands
+pub fn set_timeouts(timeouts: CommandTimeouts) {
+    *TIMEOUTS.write().unwrap() = timeouts;
+}
+
+/// Get the current timeouts
+pub fn timeouts() -> CommandTimeouts {
+    *TIMEOUTS.read().unwrap()
+}
+
+/// Get the timeout for object discovery
+pub fn discovery_timeout() -> Duration {
+    timeouts().discovery
+}
+
+/// Get the timeout for parameter reads
+pub fn param_read_timeout() -> Duration {
+    timeouts().param_read
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut p) = pipe {
+            let _ = p.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+/// Create a command for a PipeWire tool connecting to the remote of the
+/// current request (see [`crate::remote`])
+pub fn pipewire(program: &str) -> Command {
+    let mut cmd = Command::new(program);
+    if let Some(remote) = crate::remote::current() {
+        cmd.env("PIPEWIRE_REMOTE", remote);
+    }
+    cmd
+}
+
+/// Run a command and collect its output, killing it if it exceeds `timeout`
+///
+/// Returns as soon as the command exits; the timeout is only an upper bound.
+pub fn output_with_timeout(cmd: &mut Command, timeout: Duration) -> Result<Output, String> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    // Read the pipes in the background so large outputs can't block the child
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let start = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if start.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{} timed out after {} ms", program, timeout.as_millis()));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(2)),
+            Err(e) => return Err(format!("Failed to wait for {}: {}", program, e)),
+        }
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_completes_before_timeout() {
+        let start = Instant::now();
+        let output = output_with_timeout(Command::new("echo").arg("hello"), Duration::from_secs(5)).unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hello");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_command_is_killed_on_timeout() {
+        let result = output_with_timeout(Command::new("sleep").arg("5"), Duration::from_millis(100));
+        let err = result.unwrap_err();
+        assert!(err.contains("timed out after 100 ms"), "{}", err);
+    }
+
+    #[test]
+    fn test_missing_program() {
+        let result = output_with_timeout(&mut Command::new("does-not-exist-pw-tool"), Duration::from_secs(1));
+        assert!(result.is_err());
+    }
+}
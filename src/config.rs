@@ -1,12 +1,21 @@
 use anyhow::{Context, Result};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::{debug, info, warn};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::link_state::SavedLink;
 use crate::linker::LinkRule;
 use crate::param_rules::ParamRule;
+use crate::bluetooth_rules::BluetoothRule;
+use crate::failover::FailoverRule;
+use crate::jack_rules::JackRule;
+use crate::startup::WaitFor;
+use crate::zones::ZoneConfig;
+use crate::scenes::SceneConfig;
+use crate::webhooks::WebhookConfig;
 
 /// Volume rule for devices and sinks
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +33,10 @@ pub struct VolumeRule {
     /// Use state file instead of config volume if available
     #[serde(default)]
     pub use_state_file: bool,
+
+    /// Node to wait for before applying the rule at startup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for: Option<WaitFor>,
 }
 
 /// Get the path to the user config file
@@ -68,6 +81,18 @@ fn get_system_param_rules_path() -> PathBuf {
     PathBuf::from("/etc/pipewire-api/param-rules.conf")
 }
 
+/// Get all locations of a configuration file (user config first, then system config)
+pub fn config_paths(file_name: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(mut user_path) = dirs::config_dir() {
+        user_path.push("pipewire-api");
+        user_path.push(file_name);
+        paths.push(user_path);
+    }
+    paths.push(PathBuf::from("/etc/pipewire-api").join(file_name));
+    paths
+}
+
 /// Load link rules from a JSON configuration file
 pub fn load_link_rules_from_file(path: &PathBuf) -> Result<Vec<LinkRule>> {
     debug!("Attempting to load link rules from: {}", path.display());
@@ -267,14 +292,14 @@ pub struct VolumeState {
     pub volume: f32,
 }
 
+/// Get the directory for state files (~/.state/pipewire-api)
+pub fn state_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|path| path.join(".state").join("pipewire-api"))
+}
+
 /// Get the path to the volume state file
 fn get_volume_state_path() -> Option<PathBuf> {
-    dirs::home_dir().map(|mut path| {
-        path.push(".state");
-        path.push("pipewire-api");
-        path.push("volume.state");
-        path
-    })
+    state_dir().map(|path| path.join("volume.state"))
 }
 
 /// Load volume state from file
@@ -321,7 +346,7 @@ pub fn save_volume_state(states: Vec<VolumeState>) -> Result<()> {
         let content = serde_json::to_string_pretty(&states)
             .with_context(|| "Failed to serialize volume state")?;
         
-        fs::write(&state_path, content)
+        crate::state_file::write(&state_path, content)
             .with_context(|| format!("Failed to write volume state file: {}", state_path.display()))?;
         
         info!("Saved {} volume state(s) to {}", states.len(), state_path.display());
@@ -354,6 +379,138 @@ pub fn save_single_volume_state(name: String, volume: f32) -> Result<()> {
     save_volume_state(states)
 }
 
+/// Get the path to the link state file
+fn get_link_state_path() -> Option<PathBuf> {
+    state_dir().map(|path| path.join("links.state"))
+}
+
+/// Load saved links from the link state file
+pub fn load_link_state() -> Vec<SavedLink> {
+    let Some(state_path) = get_link_state_path() else {
+        return Vec::new();
+    };
+    if !state_path.exists() {
+        debug!("Link state file does not exist: {}", state_path.display());
+        return Vec::new();
+    }
+
+    match fs::read_to_string(&state_path) {
+        Ok(content) => match serde_json::from_str::<Vec<SavedLink>>(&content) {
+            Ok(links) => {
+                debug!("Loaded {} saved link(s) from {}", links.len(), state_path.display());
+                links
+            }
+            Err(e) => {
+                warn!("Failed to parse link state file: {}", e);
+                Vec::new()
+            }
+        },
+        Err(e) => {
+            warn!("Failed to read link state file: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Save links to the link state file
+pub fn save_link_state(links: &[SavedLink]) -> Result<()> {
+    let state_path = get_link_state_path()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine link state path"))?;
+
+    if let Some(parent) = state_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create state directory: {}", parent.display()))?;
+    }
+
+    let content = serde_json::to_string_pretty(links)
+        .with_context(|| "Failed to serialize link state")?;
+
+    crate::state_file::write(&state_path, content)
+        .with_context(|| format!("Failed to write link state file: {}", state_path.display()))?;
+
+    info!("Saved {} link(s) to {}", links.len(), state_path.display());
+    Ok(())
+}
+
+/// Load the entries of a JSON configuration file
+pub fn load_config_file<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+    let entries: Vec<T> = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+
+    info!("Loaded {} entries from {}", entries.len(), path.display());
+    Ok(entries)
+}
+
+/// Load the entries of a configuration file from the user and the system
+/// config, user entries first
+pub fn load_config_files<T: DeserializeOwned>(file_name: &str) -> Vec<T> {
+    let mut entries = Vec::new();
+
+    for path in config_paths(file_name) {
+        if !path.exists() {
+            debug!("Config file does not exist: {}", path.display());
+            continue;
+        }
+        match load_config_file(&path) {
+            Ok(loaded) => entries.extend(loaded),
+            Err(e) => warn!("Failed to load {}: {:#}", file_name, e),
+        }
+    }
+
+    entries
+}
+
+/// Load all zones from zones.conf
+///
+/// Zones from the user config take precedence over system zones with the same name.
+pub fn load_all_zones() -> Vec<ZoneConfig> {
+    let mut zones: Vec<ZoneConfig> = Vec::new();
+    for zone in load_config_files::<ZoneConfig>("zones.conf") {
+        if !zones.iter().any(|z| z.name == zone.name) {
+            zones.push(zone);
+        }
+    }
+    zones
+}
+
+/// Load all scenes from scenes.conf
+///
+/// Scenes from the user config take precedence over system scenes with the same name.
+pub fn load_all_scenes() -> Vec<SceneConfig> {
+    let mut scenes: Vec<SceneConfig> = Vec::new();
+    for scene in load_config_files::<SceneConfig>("scenes.conf") {
+        if !scenes.iter().any(|s| s.name == scene.name) {
+            scenes.push(scene);
+        }
+    }
+    scenes
+}
+
+/// Load all webhooks from webhooks.conf (user and system config)
+pub fn load_all_webhooks() -> Vec<WebhookConfig> {
+    load_config_files("webhooks.conf")
+}
+
+/// Load all Bluetooth rules from bluetooth-rules.conf
+///
+/// User rules come first, so they are matched before system rules.
+pub fn load_all_bluetooth_rules() -> Vec<BluetoothRule> {
+    load_config_files("bluetooth-rules.conf")
+}
+
+/// Load all failover rules from failover.conf (user and system config)
+pub fn load_all_failover_rules() -> Vec<FailoverRule> {
+    load_config_files("failover.conf")
+}
+
+/// Load all headphone/speaker switching rules from jack-rules.conf (user and system config)
+pub fn load_all_jack_rules() -> Vec<JackRule> {
+    load_config_files("jack-rules.conf")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,475 @@
+//! Validation of configuration files
+//!
+//! Parses link-rules.conf, param-rules.conf and volume.conf and reports
+//! structured issues with file, line and field context. The other JSON
+//! configuration files (zones, scenes, webhooks, Bluetooth, failover and
+//! jack rules) are checked for syntax and structure. Used by the
+//! `/api/v1/config/validate` endpoint and the `--check-config` flag.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bluetooth_rules::BluetoothRule;
+use crate::config::{self, VolumeRule};
+use crate::failover::FailoverRule;
+use crate::jack_rules::JackRule;
+use crate::linker::{LinkRule, NodeIdentifier};
+use crate::param_rules::ParamRule;
+use crate::scenes::SceneConfig;
+use crate::webhooks::WebhookConfig;
+use crate::zones::ZoneConfig;
+
+/// Valid values for log level fields
+const LOG_LEVELS: &[&str] = &["debug", "info", "warn", "error"];
+
+/// Kind of configuration file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfigKind {
+    LinkRules,
+    ParamRules,
+    Volume,
+    Zones,
+    Scenes,
+    Webhooks,
+    BluetoothRules,
+    Failover,
+    JackRules,
+}
+
+impl ConfigKind {
+    /// All kinds of configuration files
+    pub const ALL: [ConfigKind; 9] = [
+        ConfigKind::LinkRules,
+        ConfigKind::ParamRules,
+        ConfigKind::Volume,
+        ConfigKind::Zones,
+        ConfigKind::Scenes,
+        ConfigKind::Webhooks,
+        ConfigKind::BluetoothRules,
+        ConfigKind::Failover,
+        ConfigKind::JackRules,
+    ];
+
+    /// File name of this kind of configuration file
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ConfigKind::LinkRules => "link-rules.conf",
+            ConfigKind::ParamRules => "param-rules.conf",
+            ConfigKind::Volume => "volume.conf",
+            ConfigKind::Zones => "zones.conf",
+            ConfigKind::Scenes => "scenes.conf",
+            ConfigKind::Webhooks => "webhooks.conf",
+            ConfigKind::BluetoothRules => "bluetooth-rules.conf",
+            ConfigKind::Failover => "failover.conf",
+            ConfigKind::JackRules => "jack-rules.conf",
+        }
+    }
+}
+
+/// Severity of a validation issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single validation issue
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ConfigIssue {
+    pub severity: Severity,
+    /// Line number (1-based), if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Column number (1-based), if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    /// Index of the rule in the file, if the issue belongs to a rule
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<usize>,
+    /// Field of the rule, e.g. "source.node.name"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub message: String,
+}
+
+/// Validation result for one file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    pub kind: ConfigKind,
+    pub path: String,
+    pub exists: bool,
+    /// Number of rules parsed
+    pub rules: usize,
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl FileReport {
+    /// True if the file has no errors (warnings are allowed)
+    pub fn is_valid(&self) -> bool {
+        !self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+}
+
+/// Validation result for all files
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub files: Vec<FileReport>,
+}
+
+/// Find the starting line of each element of a top-level JSON array
+fn element_start_lines(content: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut line = 1;
+
+    for c in content.chars() {
+        if c == '\n' {
+            line += 1;
+        }
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => {
+                if depth == 1 {
+                    lines.push(line);
+                }
+                depth += 1;
+            }
+            '}' | ']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    lines
+}
+
+struct IssueCollector {
+    issues: Vec<ConfigIssue>,
+    lines: Vec<usize>,
+}
+
+impl IssueCollector {
+    fn add(&mut self, severity: Severity, rule: usize, field: &str, message: String) {
+        self.issues.push(ConfigIssue {
+            severity,
+            line: self.lines.get(rule).copied(),
+            column: None,
+            rule: Some(rule),
+            field: Some(field.to_string()),
+            message,
+        });
+    }
+
+    fn error(&mut self, rule: usize, field: &str, message: String) {
+        self.add(Severity::Error, rule, field, message);
+    }
+
+    fn warning(&mut self, rule: usize, field: &str, message: String) {
+        self.add(Severity::Warning, rule, field, message);
+    }
+
+    fn check_regex(&mut self, rule: usize, field: &str, pattern: &str) {
+        if let Err(e) = Regex::new(pattern) {
+            self.error(rule, field, format!("Invalid regular expression '{}': {}", pattern, e));
+        }
+    }
+
+    fn check_name(&mut self, rule: usize, name: &str) {
+        if name.trim().is_empty() {
+            self.warning(rule, "name", "Rule has no name".to_string());
+        }
+    }
+
+    fn check_level(&mut self, rule: usize, field: &str, level: &str) {
+        if !LOG_LEVELS.contains(&level) {
+            self.error(rule, field, format!("Invalid log level '{}', expected one of: {}", level, LOG_LEVELS.join(", ")));
+        }
+    }
+
+    fn check_identifier(&mut self, rule: usize, prefix: &str, id: &NodeIdentifier) {
+        let fields = [
+            ("node.name", &id.node_name),
+            ("node.nick", &id.node_nick),
+            ("object.path", &id.object_path),
+        ];
+        if fields.iter().all(|(_, v)| v.is_none()) {
+            self.error(rule, prefix, "At least one of node.name, node.nick or object.path is required".to_string());
+        }
+        for (name, value) in fields {
+            if let Some(pattern) = value {
+                self.check_regex(rule, &format!("{}.{}", prefix, name), pattern);
+            }
+        }
+    }
+}
+
+/// Parse JSON content into rules, converting parse errors into issues
+fn parse_rules<T: for<'de> Deserialize<'de>>(content: &str) -> Result<Vec<T>, ConfigIssue> {
+    serde_json::from_str(content).map_err(|e| ConfigIssue {
+        severity: Severity::Error,
+        line: Some(e.line()),
+        column: Some(e.column()),
+        rule: None,
+        field: None,
+        message: e.to_string(),
+    })
+}
+
+fn validate_link_rules(rules: &[LinkRule], c: &mut IssueCollector) {
+    for (i, rule) in rules.iter().enumerate() {
+        c.check_name(i, &rule.name);
+        c.check_identifier(i, "source", &rule.source);
+        c.check_identifier(i, "destination", &rule.destination);
+        if !rule.link_at_startup && rule.relink_every == 0 {
+            c.warning(i, "link_at_startup", "Rule is never applied (link_at_startup is false and relink_every is 0)".to_string());
+        }
+    }
+}
+
+fn validate_param_rules(rules: &[ParamRule], c: &mut IssueCollector) {
+    for (i, rule) in rules.iter().enumerate() {
+        c.check_name(i, &rule.name);
+        match (&rule.node.node_name, &rule.node.object_path) {
+            (None, None) => c.error(i, "node", "At least one of node.name or object.path is required".to_string()),
+            (name, path) => {
+                if let Some(p) = name {
+                    c.check_regex(i, "node.node.name", p);
+                }
+                if let Some(p) = path {
+                    c.check_regex(i, "node.object.path", p);
+                }
+            }
+        }
+        if rule.parameters.is_empty() {
+            c.warning(i, "parameters", "Rule does not set any parameters".to_string());
+        }
+        for (key, value) in &rule.parameters {
+            if let Err(e) = crate::parameters::ParameterValue::from_json(value) {
+                c.error(i, &format!("parameters.{}", key), e);
+            }
+        }
+        c.check_level(i, "info_level", &rule.info_level);
+        c.check_level(i, "error_level", &rule.error_level);
+    }
+}
+
+fn validate_volume_rules(rules: &[VolumeRule], c: &mut IssueCollector) {
+    for (i, rule) in rules.iter().enumerate() {
+        c.check_name(i, &rule.name);
+        if rule.object.is_empty() {
+            c.error(i, "object", "At least one object property to match is required".to_string());
+        }
+        for (key, pattern) in &rule.object {
+            c.check_regex(i, &format!("object.{}", key), pattern);
+        }
+        if !(0.0..=2.0).contains(&rule.volume) {
+            c.error(i, "volume", format!("Volume {} is out of range (0.0 - 2.0)", rule.volume));
+        }
+    }
+}
+
+/// Validate the content of a configuration file
+///
+/// Returns the number of rules parsed and the issues found.
+pub fn validate_content(kind: ConfigKind, content: &str) -> (usize, Vec<ConfigIssue>) {
+    let mut collector = IssueCollector {
+        issues: Vec::new(),
+        lines: element_start_lines(content),
+    };
+
+    let count = match kind {
+        ConfigKind::LinkRules => parse_rules::<LinkRule>(content).map(|rules| {
+            validate_link_rules(&rules, &mut collector);
+            rules.len()
+        }),
+        ConfigKind::ParamRules => parse_rules::<ParamRule>(content).map(|rules| {
+            validate_param_rules(&rules, &mut collector);
+            rules.len()
+        }),
+        ConfigKind::Volume => parse_rules::<VolumeRule>(content).map(|rules| {
+            validate_volume_rules(&rules, &mut collector);
+            rules.len()
+        }),
+        ConfigKind::Zones => parse_rules::<ZoneConfig>(content).map(|zones| zones.len()),
+        ConfigKind::Scenes => parse_rules::<SceneConfig>(content).map(|scenes| scenes.len()),
+        ConfigKind::Webhooks => parse_rules::<WebhookConfig>(content).map(|hooks| hooks.len()),
+        ConfigKind::BluetoothRules => parse_rules::<BluetoothRule>(content).map(|rules| rules.len()),
+        ConfigKind::Failover => parse_rules::<FailoverRule>(content).map(|rules| rules.len()),
+        ConfigKind::JackRules => parse_rules::<JackRule>(content).map(|rules| rules.len()),
+    };
+
+    match count {
+        Ok(n) => (n, collector.issues),
+        Err(issue) => (0, vec![issue]),
+    }
+}
+
+/// Validate a configuration file on disk
+pub fn validate_file(kind: ConfigKind, path: &Path) -> FileReport {
+    let mut report = FileReport {
+        kind,
+        path: path.display().to_string(),
+        exists: path.exists(),
+        rules: 0,
+        issues: Vec::new(),
+    };
+
+    if !report.exists {
+        return report;
+    }
+
+    match fs::read_to_string(path) {
+        Ok(content) => {
+            let (rules, issues) = validate_content(kind, &content);
+            report.rules = rules;
+            report.issues = issues;
+        }
+        Err(e) => report.issues.push(ConfigIssue {
+            severity: Severity::Error,
+            line: None,
+            column: None,
+            rule: None,
+            field: None,
+            message: format!("Failed to read file: {}", e),
+        }),
+    }
+
+    report
+}
+
+/// All configuration files (user and system) with their kind
+pub fn config_files() -> Vec<(ConfigKind, PathBuf)> {
+    let mut files = Vec::new();
+    for kind in ConfigKind::ALL {
+        for path in config::config_paths(kind.file_name()) {
+            files.push((kind, path));
+        }
+    }
+    files
+}
+
+/// Validate all configuration files
+pub fn validate_all() -> ValidationReport {
+    let files: Vec<FileReport> = config_files()
+        .iter()
+        .map(|(kind, path)| validate_file(*kind, path))
+        .collect();
+
+    ValidationReport {
+        valid: files.iter().all(|f| f.is_valid()),
+        files,
+    }
+}
+
+/// Format a report for the command line
+pub fn format_report(report: &ValidationReport) -> String {
+    let mut out = String::new();
+    for file in &report.files {
+        if !file.exists {
+            out.push_str(&format!("{}: not present\n", file.path));
+            continue;
+        }
+        let status = if file.is_valid() { "OK" } else { "INVALID" };
+        out.push_str(&format!("{}: {} ({} rule(s))\n", file.path, status, file.rules));
+        for issue in &file.issues {
+            let mut location = String::new();
+            if let Some(line) = issue.line {
+                location.push_str(&format!("line {}", line));
+                if let Some(col) = issue.column {
+                    location.push_str(&format!(":{}", col));
+                }
+                location.push_str(", ");
+            }
+            if let Some(rule) = issue.rule {
+                location.push_str(&format!("rule {}, ", rule));
+            }
+            if let Some(ref field) = issue.field {
+                location.push_str(&format!("field '{}', ", field));
+            }
+            let severity = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            out.push_str(&format!("  {}: {}{}\n", severity, location, issue.message));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syntax_error_has_line_and_column() {
+        let content = "[\n  {\n    \"name\": \"x\",\n    \"volume\": \n  }\n]";
+        let (rules, issues) = validate_content(ConfigKind::Volume, content);
+        assert_eq!(rules, 0);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert!(issues[0].line.is_some());
+        assert!(issues[0].column.is_some());
+    }
+
+    #[test]
+    fn test_volume_rule_out_of_range() {
+        let content = r#"[
+  {"name": "ok", "object": {"node.name": "alsa.*"}, "volume": 0.5},
+  {"name": "bad", "object": {"node.name": "(unclosed"}, "volume": 3.0}
+]"#;
+        let (rules, issues) = validate_content(ConfigKind::Volume, content);
+        assert_eq!(rules, 2);
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(|i| i.rule == Some(1) && i.line == Some(3)));
+        assert!(issues.iter().any(|i| i.field.as_deref() == Some("volume")));
+        assert!(issues.iter().any(|i| i.field.as_deref() == Some("object.node.name")));
+    }
+
+    #[test]
+    fn test_link_rule_requires_identifier() {
+        let content = r#"[{"name": "r", "source": {}, "destination": {"node.name": "^sink$"}, "type": "link"}]"#;
+        let (_, issues) = validate_content(ConfigKind::LinkRules, content);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field.as_deref(), Some("source"));
+    }
+
+    #[test]
+    fn test_param_rule_invalid_level() {
+        let content = r#"[{"name": "p", "node": {"node.name": "riaa"}, "parameters": {"riaa:Gain (dB)": 3.0}, "info_level": "loud"}]"#;
+        let (_, issues) = validate_content(ConfigKind::ParamRules, content);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field.as_deref(), Some("info_level"));
+    }
+
+    #[test]
+    fn test_other_config_files() {
+        let zones = r#"[{"name": "kitchen", "source": {"node.name": "^kitchen$"}, "sinks": []}]"#;
+        let (rules, issues) = validate_content(ConfigKind::Zones, zones);
+        assert_eq!((rules, issues.len()), (1, 0));
+        let (rules, issues) = validate_content(ConfigKind::Failover, r#"[{"name": "usb"}]"#);
+        assert_eq!(rules, 0);
+        assert!(issues[0].message.contains("missing field"));
+        assert_eq!(ConfigKind::JackRules.file_name(), "jack-rules.conf");
+        assert_eq!(config_files().len(), ConfigKind::ALL.len() * config::config_paths("x").len());
+    }
+
+    #[test]
+    fn test_element_start_lines() {
+        let content = "[\n  {\"a\": \"[{\"},\n  {\"b\": [1, 2]}\n]";
+        assert_eq!(element_start_lines(content), vec![2, 3]);
+    }
+}
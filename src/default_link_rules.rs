@@ -20,6 +20,12 @@ pub fn get_default_rules() -> Vec<LinkRule> {
             relink_every: 10,
             info_level: LogLevel::Info,
             error_level: LogLevel::Error,
+            wait_for: None,
+            exclusive: false,
+            channel_map: None,
+            source_ports: None,
+            destination_ports: None,
+            include_monitors: false,
         },
     ]
 }
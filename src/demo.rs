@@ -0,0 +1,198 @@
+//! Demo mode with simulated PipeWire objects
+//!
+//! `pipewire-api --demo` serves a HiFiBerry setup without PipeWire or
+//! HiFiBerry hardware: a DAC with its ALSA device, the speakereq2x2 and riaa
+//! filter-chains with their controls, a microphone and a playing stream. The
+//! objects and parameters live in a [`FakeBackend`], so reading and writing
+//! parameters and volumes works like on a real system. Links, metadata and
+//! features driven by pw-link or pw-metadata are not simulated.
+
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+use crate::backend::{self, FakeBackend};
+
+/// Node name of the DAC sink
+pub const DAC_SINK: &str = "alsa_output.platform-soc_sound.stereo-fallback";
+
+/// Node name of the speakereq filter-chain
+pub const SPEAKEREQ_NODE: &str = "speakereq2x2";
+
+/// EQ bands per block of the speakereq plugin
+const EQ_BANDS: u32 = 20;
+
+/// Controls of the speakereq2x2 plugin with a few EQ bands in use
+fn speakereq_params() -> Value {
+    let mut params = Map::new();
+    let mut set = |key: String, value: Value| {
+        params.insert(format!("{}:{}", SPEAKEREQ_NODE, key), value);
+    };
+    set("Enable".to_string(), json!(true));
+    set("Licensed".to_string(), json!(true));
+    set("master_gain_db".to_string(), json!(-3.0));
+    for i in 0..2 {
+        set(format!("input_{}_gain_db", i), json!(0.0));
+        set(format!("output_{}_gain_db", i), json!(0.0));
+        set(format!("delay_{}_ms", i), json!(0.0));
+        for j in 0..2 {
+            set(format!("xbar_{}_to_{}", i, j), json!(if i == j { 1.0 } else { 0.0 }));
+        }
+    }
+    for block in ["input_0", "input_1", "output_0", "output_1"] {
+        for band in 1..=EQ_BANDS {
+            // Low shelf and a room mode notch on the outputs: type, f, q, gain
+            let (eq_type, f, q, gain) = match (block, band) {
+                (b, 1) if b.starts_with("output") => (1, 80.0, 0.7, 3.0),
+                (b, 2) if b.starts_with("output") => (3, 120.0, 4.0, -6.0),
+                _ => (0, 1000.0, 1.0, 0.0),
+            };
+            set(format!("{}_eq_{}_type", block, band), json!(eq_type));
+            set(format!("{}_eq_{}_f", block, band), json!(f));
+            set(format!("{}_eq_{}_q", block, band), json!(q));
+            set(format!("{}_eq_{}_gain", block, band), json!(gain));
+            set(format!("{}_eq_{}_enabled", block, band), json!(true));
+        }
+    }
+    json!({ "params": params })
+}
+
+/// Controls of the riaa phono preamplifier
+fn riaa_params() -> Value {
+    json!({ "params": {
+        "riaa:Gain (dB)": 0.0,
+        "riaa:Subsonic Filter": 1,
+        "riaa:RIAA Enable": true,
+        "riaa:Declick Enable": false,
+        "riaa:Spike Threshold (dB)": 20.0,
+        "riaa:Spike Width (ms)": 1.0,
+        "riaa:Notch Filter Enable": false,
+        "riaa:Notch Frequency (Hz)": 50.0,
+        "riaa:Notch Q Factor": 10.0,
+    }})
+}
+
+fn stereo(volume: f32, mute: bool) -> Value {
+    json!({ "channelVolumes": [volume, volume], "channelMap": ["FL", "FR"], "mute": mute })
+}
+
+/// The simulated objects and parameters
+pub fn backend() -> FakeBackend {
+    let fake = FakeBackend::new();
+
+    fake.add_object(30, "Device", &[
+        ("device.name", "alsa_card.platform-soc_sound"),
+        ("device.description", "HiFiBerry DAC+"),
+        ("media.class", "Audio/Device"),
+        ("device.api", "alsa"),
+    ]);
+    fake.add_param(30, "EnumRoute", json!({"index": 0, "name": "analog-output", "description": "Analog Output",
+        "direction": "Output", "available": "unknown"}));
+    fake.add_param(30, "Route", json!({"index": 0, "device": 0, "props": stereo(1.0, false)}));
+
+    fake.add_object(40, "Node", &[
+        ("node.name", DAC_SINK),
+        ("node.description", "HiFiBerry DAC+ Stereo"),
+        ("media.class", "Audio/Sink"),
+        ("device.id", "30"),
+        ("priority.session", "1000"),
+        ("audio.channels", "2"),
+    ]);
+    fake.add_param(40, "Props", stereo(1.0, false));
+
+    fake.add_object(41, "Node", &[
+        ("node.name", SPEAKEREQ_NODE),
+        ("node.description", "SpeakerEQ 2x2"),
+        ("media.class", "Audio/Sink"),
+        ("node.link-group", "filter-chain-speakereq2x2"),
+        ("priority.session", "2000"),
+        ("audio.channels", "2"),
+    ]);
+    fake.add_param(41, "Props", stereo(0.512, false));
+    fake.add_param(41, "Props", speakereq_params());
+
+    fake.add_object(42, "Node", &[
+        ("node.name", "speakereq2x2_output"),
+        ("node.description", "SpeakerEQ 2x2 Output"),
+        ("media.class", "Stream/Output/Audio"),
+        ("node.link-group", "filter-chain-speakereq2x2"),
+        ("node.passive", "true"),
+    ]);
+    fake.add_param(42, "Props", stereo(1.0, false));
+
+    fake.add_object(43, "Node", &[
+        ("node.name", "riaa"),
+        ("node.description", "RIAA Phono Preamplifier"),
+        ("media.class", "Audio/Source"),
+        ("node.link-group", "filter-chain-riaa"),
+        ("audio.channels", "2"),
+    ]);
+    fake.add_param(43, "Props", stereo(1.0, false));
+    fake.add_param(43, "Props", riaa_params());
+
+    fake.add_object(44, "Node", &[
+        ("node.name", "alsa_input.usb-microphone.mono-fallback"),
+        ("node.description", "USB Microphone Mono"),
+        ("media.class", "Audio/Source"),
+        ("priority.session", "1000"),
+        ("audio.channels", "1"),
+    ]);
+    fake.add_param(44, "Props", json!({ "channelVolumes": [0.729], "channelMap": ["MONO"], "mute": false }));
+
+    fake.add_object(70, "Node", &[
+        ("node.name", "spotify"),
+        ("application.name", "librespot"),
+        ("media.name", "Spotify"),
+        ("media.class", "Stream/Output/Audio"),
+        ("target.object", SPEAKEREQ_NODE),
+    ]);
+    fake.add_param(70, "Props", stereo(0.343, false));
+
+    fake
+}
+
+/// Serve the simulated objects instead of those of PipeWire
+pub fn enable() -> Arc<FakeBackend> {
+    let fake = Arc::new(backend());
+    backend::set(fake.clone());
+    fake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::PipeWireBackend;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_speakereq_params() {
+        let fake = backend();
+        let props = fake.enum_params(41, "Props").unwrap();
+        let params: HashMap<_, _> = props
+            .iter()
+            .filter_map(|p| p.properties.get("params")?.as_object())
+            .flat_map(|p| p.iter())
+            .map(|(k, v)| (k.clone(), crate::parameters::ParameterValue::from_json(v).unwrap()))
+            .collect();
+        assert_eq!(crate::speakereq::get_plugin_prefix(&params), SPEAKEREQ_NODE);
+        // 4 blocks of 20 bands with 5 controls, gains, delays and crossbar
+        assert_eq!(params.len(), 4 * 20 * 5 + 3 + 6 + 4);
+        assert_eq!(fake.list_objects(Some("Node")).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_volumes() {
+        let fake: Arc<dyn PipeWireBackend> = Arc::new(backend());
+        backend::run_with(Some(fake), || {
+            let volume = crate::wpctl::get_volume(41).unwrap();
+            assert_eq!((volume.volume, volume.object_type.as_str()), (0.8, "filter"));
+            crate::wpctl::set_volume(41, 0.5).unwrap();
+            crate::wpctl::set_mute(41, true).unwrap();
+            let state = crate::volume_ops::read_node_volume(41).unwrap();
+            assert_eq!((state.channel_volumes, state.mute), (vec![0.125, 0.125], Some(true)));
+
+            assert_eq!(crate::wpctl::list_volumes().unwrap().len(), 6);
+            assert_eq!(crate::wpctl::get_default_sink().unwrap().name, SPEAKEREQ_NODE);
+            assert!(crate::wpctl::get_volume(99).unwrap_err().contains("not found"));
+        });
+    }
+}
@@ -0,0 +1,164 @@
+//! Self-test of the environment the server depends on
+//!
+//! Checks that the PipeWire command line tools are installed, the PipeWire
+//! server can be reached, the nodes of the DSP modules are loaded and the
+//! state directory is writable. Each check is reported separately so a
+//! failing setup can be narrowed down without shell access.
+
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+
+use crate::api_server::ApiError;
+use crate::command;
+use crate::modules::Module;
+use crate::status;
+
+/// Command line tools used by the server
+pub const REQUIRED_TOOLS: &[&str] = &["pw-cli", "pw-link", "pw-metadata", "wpctl"];
+
+/// Result of a single check
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Check {
+    /// Check group: tools, pipewire, modules or storage
+    pub category: String,
+    pub name: String,
+    pub passed: bool,
+    pub message: String,
+}
+
+/// Result of all checks
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    /// All checks passed
+    pub passed: bool,
+    pub checks: Vec<Check>,
+}
+
+fn check(category: &str, name: &str, result: Result<String, String>) -> Check {
+    let (passed, message) = match result {
+        Ok(message) => (true, message),
+        Err(message) => (false, message),
+    };
+    Check {
+        category: category.to_string(),
+        name: name.to_string(),
+        passed,
+        message,
+    }
+}
+
+/// Check that a command line tool can be executed
+pub fn check_tool(tool: &str) -> Check {
+    // The exit code is not checked, not all tools support --version
+    let result = command::output_with_timeout(Command::new(tool).arg("--version"), command::discovery_timeout())
+        .map(|output| {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            stdout
+                .lines()
+                .map(str::trim)
+                .find(|l| !l.is_empty())
+                .map(|l| l.to_string())
+                .unwrap_or_else(|| "available".to_string())
+        })
+        .map_err(|e| if e.contains("Failed to run") { format!("{} is not installed", tool) } else { e });
+    check("tools", tool, result)
+}
+
+/// Check that the PipeWire server answers
+pub fn check_pipewire() -> Check {
+    let result = command::output_with_timeout(command::pipewire("pw-cli").args(["info", "0"]), command::discovery_timeout())
+        .and_then(|output| {
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("Cannot connect to PipeWire: {}", stderr.trim()));
+            }
+            Ok(match status::parse_core_version(&String::from_utf8_lossy(&output.stdout)) {
+                Some(version) => format!("Connected to PipeWire {}", version),
+                None => "Connected to PipeWire".to_string(),
+            })
+        });
+    check("pipewire", "connection", result)
+}
+
+/// Check that the node of a DSP module is loaded
+pub fn check_module(module: &Module) -> Check {
+    let result = match module.status() {
+        Ok(s) if s.present => Ok(format!(
+            "Node {} (id {})",
+            s.node_name.unwrap_or_default(),
+            s.node_id.map(|id| id.to_string()).unwrap_or_default()
+        )),
+        Ok(s) => Err(format!("No node matching '{}'", s.node_match)),
+        Err(ApiError::NotFound(e) | ApiError::BadRequest(e) | ApiError::Internal(e) | ApiError::Conflict(e)) => Err(e),
+        Err(ApiError::ModuleNotPresent(name)) => Err(format!("Module '{}' is not present", name)),
+    };
+    check("modules", &module.name, result)
+}
+
+/// Check that files can be created in a directory, creating it if needed
+pub fn check_writable(name: &str, dir: &Path) -> Check {
+    let result = std::fs::create_dir_all(dir)
+        .and_then(|_| {
+            let probe = dir.join(".pipewire-api-write-test");
+            std::fs::write(&probe, b"")?;
+            std::fs::remove_file(&probe)
+        })
+        .map(|_| format!("{} is writable", dir.display()))
+        .map_err(|e| format!("{} is not writable: {}", dir.display(), e));
+    check("storage", name, result)
+}
+
+/// Run all checks
+pub fn run(modules: &[Arc<Module>]) -> DiagnosticsReport {
+    let mut checks: Vec<Check> = REQUIRED_TOOLS.iter().map(|tool| check_tool(tool)).collect();
+
+    let pipewire = check_pipewire();
+    let connected = pipewire.passed;
+    checks.push(pipewire);
+
+    // Module lookups need a running PipeWire server
+    for module in modules {
+        if connected {
+            checks.push(check_module(module));
+        } else {
+            checks.push(check("modules", &module.name, Err("Skipped, PipeWire is not reachable".to_string())));
+        }
+    }
+
+    checks.push(match crate::config::state_dir() {
+        Some(dir) => check_writable("state_dir", &dir),
+        None => check("storage", "state_dir", Err("Home directory not found".to_string())),
+    });
+
+    DiagnosticsReport {
+        passed: checks.iter().all(|c| c.passed),
+        checks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_missing_tool() {
+        let result = check_tool("pw-api-no-such-tool");
+        assert!(!result.passed);
+        assert_eq!(result.message, "pw-api-no-such-tool is not installed");
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("state");
+        assert!(check_writable("state_dir", &state).passed);
+        assert!(state.is_dir());
+        assert_eq!(std::fs::read_dir(&state).unwrap().count(), 0);
+
+        let file = dir.path().join("file");
+        std::fs::write(&file, b"").unwrap();
+        assert!(!check_writable("state_dir", &file.join("sub")).passed);
+    }
+}
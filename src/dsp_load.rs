@@ -0,0 +1,154 @@
+//! DSP load of the graph
+//!
+//! Computed from the profiler data read with pw-top (see [`crate::pwtop`]).
+//! Each driver runs a graph cycle of `quantum / rate` seconds; the busy time
+//! of the driver covers the whole cycle, so its ratio to the cycle time is
+//! the load of the graph. A load near 100% leaves no room for another filter
+//! (e.g. a convolver) and ends in xruns. The busy times of the followers show
+//! which nodes take the time.
+
+use serde::Serialize;
+
+use crate::pwtop::TopNode;
+
+/// Processing time of a node in a cycle
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct NodeLoad {
+    pub id: u32,
+    pub name: String,
+    pub busy_us: f64,
+    /// Share of the cycle time in percent
+    pub load_percent: f64,
+}
+
+/// Load of a driver's graph
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DriverLoad {
+    pub id: u32,
+    pub name: String,
+    pub quantum: u32,
+    pub rate: u32,
+    /// Time available per cycle
+    pub cycle_us: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub busy_us: Option<f64>,
+    /// Busy time of the graph in percent of the cycle time
+    pub load_percent: f64,
+    /// Followers by processing time, most expensive first
+    pub nodes: Vec<NodeLoad>,
+}
+
+/// Load of all running drivers
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LoadReport {
+    /// Time of the sample (RFC 3339)
+    pub sampled: String,
+    /// Highest load of the drivers in percent
+    pub max_load_percent: f64,
+    /// Time left in the busiest cycle in percent
+    pub headroom_percent: f64,
+    pub drivers: Vec<DriverLoad>,
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// Compute the load of the running drivers from a pw-top sample
+pub fn load_report(nodes: &[TopNode], sampled: String) -> LoadReport {
+    let drivers: Vec<DriverLoad> = nodes
+        .iter()
+        .filter(|n| n.is_driver() && n.state == "R" && n.quantum > 0 && n.rate > 0)
+        .map(|driver| {
+            let cycle_us = driver.quantum as f64 * 1_000_000.0 / driver.rate as f64;
+            let load = driver
+                .busy_quantum
+                .or_else(|| driver.busy_us.map(|busy| busy / cycle_us))
+                .unwrap_or(0.0);
+            let mut followers: Vec<NodeLoad> = nodes
+                .iter()
+                .filter(|n| n.driver_id == Some(driver.id))
+                .filter_map(|n| {
+                    let busy_us = n.busy_us?;
+                    Some(NodeLoad {
+                        id: n.id,
+                        name: n.name.clone(),
+                        busy_us,
+                        load_percent: round(busy_us * 100.0 / cycle_us),
+                    })
+                })
+                .collect();
+            followers.sort_by(|a, b| b.busy_us.total_cmp(&a.busy_us));
+            DriverLoad {
+                id: driver.id,
+                name: driver.name.clone(),
+                quantum: driver.quantum,
+                rate: driver.rate,
+                cycle_us: round(cycle_us),
+                busy_us: driver.busy_us,
+                load_percent: round(load * 100.0),
+                nodes: followers,
+            }
+        })
+        .collect();
+    let max_load_percent = drivers.iter().map(|d| d.load_percent).fold(0.0, f64::max);
+    LoadReport {
+        sampled,
+        max_load_percent,
+        headroom_percent: round((100.0 - max_load_percent).max(0.0)),
+        drivers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: u32, name: &str, driver_id: Option<u32>, busy_us: Option<f64>, busy_quantum: Option<f64>) -> TopNode {
+        TopNode {
+            id,
+            name: name.to_string(),
+            state: "R".to_string(),
+            driver_id,
+            quantum: if driver_id.is_none() { 1024 } else { 0 },
+            rate: if driver_id.is_none() { 48000 } else { 0 },
+            wait_us: None,
+            busy_us,
+            wait_quantum: None,
+            busy_quantum,
+            errors: 0,
+            format: None,
+        }
+    }
+
+    #[test]
+    fn test_load_report() {
+        let nodes = vec![
+            node(37, "alsa_output.usb", None, Some(8533.0), Some(0.4)),
+            node(55, "spotify", Some(37), Some(200.0), None),
+            node(60, "speakereq2x2", Some(37), Some(4266.7), None),
+            node(61, "convolver", Some(37), None, None),
+        ];
+        let report = load_report(&nodes, "2026-10-16T08:00:00Z".to_string());
+        assert_eq!(report.drivers.len(), 1);
+        let driver = &report.drivers[0];
+        assert_eq!(driver.cycle_us, 21333.3);
+        assert_eq!(driver.load_percent, 40.0);
+        assert_eq!(driver.nodes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![60, 55]);
+        assert_eq!(driver.nodes[0].load_percent, 20.0);
+        assert_eq!((report.max_load_percent, report.headroom_percent), (40.0, 60.0));
+    }
+
+    #[test]
+    fn test_idle_drivers() {
+        let mut suspended = node(37, "alsa_output.usb", None, None, None);
+        suspended.state = "S".to_string();
+        let report = load_report(&[suspended], String::new());
+        assert!(report.drivers.is_empty());
+        assert_eq!(report.headroom_percent, 100.0);
+
+        // Without B/Q the busy time is used
+        let report = load_report(&[node(37, "alsa_output.usb", None, Some(2133.3), None)], String::new());
+        assert_eq!(report.drivers[0].load_percent, 10.0);
+    }
+}
@@ -0,0 +1,467 @@
+//! Ducking rules
+//!
+//! `[[ducking]]` entries in pipewire-api.toml lower the volume of sinks and
+//! playback streams while another stream plays, e.g. the music while an
+//! announcement or a navigation prompt plays. A rule is active from the first
+//! matching stream becoming active until the last one is idle (the
+//! `stream_active` and `stream_idle` events of [`crate::events`]).
+//!
+//! Volumes are ramped down over `attack_ms` and back over `release_ms`. A
+//! volume that was changed while ducked is not restored. Streams that start
+//! while a rule is active are ducked as well; when several rules duck a
+//! node, the strongest reduction applies.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::events::{self, StateEvent, StreamInfo};
+use crate::notify::ducked_volume;
+use crate::pwcli::{self, PwObject};
+use crate::wpctl;
+
+/// Interval between the volume steps of a ramp
+pub const STEP_MS: u64 = 50;
+
+fn default_duck_db() -> f32 {
+    -12.0
+}
+
+fn default_attack_ms() -> u64 {
+    200
+}
+
+fn default_release_ms() -> u64 {
+    800
+}
+
+/// Rule definition from the `[[ducking]]` section
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DuckingRule {
+    /// Regex on the node.name, application.name or media.role of the streams that trigger ducking
+    pub trigger: String,
+    /// Regex on the node.name (or application.name of streams) of the sinks and streams to lower
+    pub target: String,
+    /// Reduction in dB
+    #[serde(default = "default_duck_db")]
+    pub duck_db: f32,
+    /// Time to lower the volume in milliseconds
+    #[serde(default = "default_attack_ms")]
+    pub attack_ms: u64,
+    /// Time to restore the volume in milliseconds
+    #[serde(default = "default_release_ms")]
+    pub release_ms: u64,
+}
+
+struct CompiledRule {
+    rule: DuckingRule,
+    trigger: Regex,
+    target: Regex,
+}
+
+impl CompiledRule {
+    fn new(rule: DuckingRule) -> Result<Self, String> {
+        let trigger =
+            Regex::new(&rule.trigger).map_err(|e| format!("Invalid trigger regex '{}': {}", rule.trigger, e))?;
+        let target = Regex::new(&rule.target).map_err(|e| format!("Invalid target regex '{}': {}", rule.target, e))?;
+        Ok(Self { rule, trigger, target })
+    }
+
+    fn is_trigger(&self, stream: &StreamInfo) -> bool {
+        self.trigger.is_match(&stream.name)
+            || stream.application.as_deref().is_some_and(|a| self.trigger.is_match(a))
+            || stream.role.as_deref().is_some_and(|r| self.trigger.is_match(r))
+    }
+
+    fn is_target(&self, node: &PwObject) -> bool {
+        let class = node.media_class().unwrap_or_default();
+        (class.starts_with("Audio/Sink") || class == "Stream/Output/Audio")
+            && (node.name().is_some_and(|n| self.target.is_match(n))
+                || node.get("application.name").is_some_and(|a| self.target.is_match(a)))
+            && !(class == "Stream/Output/Audio" && self.is_trigger(&StreamInfo::from_node(node)))
+    }
+}
+
+/// A volume change of a ducked node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ramp {
+    pub id: u32,
+    pub to: f32,
+    pub ms: u64,
+    /// Restoring: skipped if the volume was changed since it was ducked
+    pub restore: bool,
+}
+
+/// Status of a rule
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DuckingRuleStatus {
+    #[serde(flatten)]
+    pub rule: DuckingRule,
+    pub active: bool,
+    /// IDs of the active streams that trigger the rule
+    pub streams: Vec<u32>,
+}
+
+/// A node whose volume is lowered
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DuckedNode {
+    pub id: u32,
+    pub name: String,
+    /// Volume restored when ducking ends
+    pub original: f32,
+    pub volume: f32,
+}
+
+/// State of the ducking rules
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DuckingStatus {
+    pub rules: Vec<DuckingRuleStatus>,
+    pub ducked: Vec<DuckedNode>,
+}
+
+struct Target {
+    name: String,
+    original: f32,
+    volume: f32,
+    rules: BTreeSet<usize>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Active trigger streams per rule
+    triggers: Vec<BTreeSet<u32>>,
+    targets: BTreeMap<u32, Target>,
+}
+
+/// Strongest reduction of the rules ducking a node
+fn strongest(rules: &[CompiledRule], active: &BTreeSet<usize>) -> f32 {
+    active.iter().map(|i| rules[*i].rule.duck_db.abs()).fold(0.0, f32::max)
+}
+
+impl State {
+    fn duck(&mut self, rules: &[CompiledRule], rule: usize, node: &PwObject, volume_of: &impl Fn(u32) -> Option<f32>) -> Option<Ramp> {
+        let target = match self.targets.entry(node.id) {
+            std::collections::btree_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::btree_map::Entry::Vacant(entry) => {
+                let original = volume_of(node.id)?;
+                entry.insert(Target {
+                    name: node.name().unwrap_or_default().to_string(),
+                    original,
+                    volume: original,
+                    rules: BTreeSet::new(),
+                })
+            }
+        };
+        target.rules.insert(rule);
+        let volume = ducked_volume(target.original, strongest(rules, &target.rules));
+        if (volume - target.volume).abs() < 0.005 {
+            return None;
+        }
+        target.volume = volume;
+        Some(Ramp { id: node.id, to: volume, ms: rules[rule].rule.attack_ms, restore: false })
+    }
+
+    fn release(&mut self, rules: &[CompiledRule], rule: usize) -> Vec<Ramp> {
+        let mut ramps = Vec::new();
+        let ms = rules[rule].rule.release_ms;
+        self.targets.retain(|id, target| {
+            if !target.rules.remove(&rule) {
+                return true;
+            }
+            let volume = ducked_volume(target.original, strongest(rules, &target.rules));
+            if target.rules.is_empty() {
+                ramps.push(Ramp { id: *id, to: target.original, ms, restore: true });
+                return false;
+            }
+            if (volume - target.volume).abs() >= 0.005 {
+                target.volume = volume;
+                ramps.push(Ramp { id: *id, to: volume, ms, restore: true });
+            }
+            true
+        });
+        ramps
+    }
+}
+
+/// Volumes of a ramp from one volume to another, one per [`STEP_MS`]
+pub fn ramp_steps(from: f32, to: f32, ms: u64) -> Vec<f32> {
+    let steps = (ms / STEP_MS).max(1);
+    let mut volumes: Vec<f32> = (1..=steps)
+        .map(|k| ((from + (to - from) * k as f32 / steps as f32) * 100.0).round() / 100.0)
+        .collect();
+    volumes.dedup();
+    volumes
+}
+
+/// Tracks the ducking rules and the ducked nodes
+#[derive(Default)]
+pub struct DuckingManager {
+    rules: RwLock<Vec<CompiledRule>>,
+    state: Mutex<State>,
+    /// Running ramp (a newer one stops it) and the volume last set per node
+    ramps: Mutex<HashMap<u32, (u64, Option<f32>)>>,
+}
+
+impl DuckingManager {
+    /// Set the rules; invalid ones are logged and ignored
+    pub fn configure(&self, rules: Vec<DuckingRule>) {
+        let compiled: Vec<CompiledRule> = rules
+            .into_iter()
+            .filter_map(|rule| match CompiledRule::new(rule) {
+                Ok(rule) => Some(rule),
+                Err(e) => {
+                    warn!("Ignoring ducking rule: {}", e);
+                    None
+                }
+            })
+            .collect();
+        *self.state.lock().unwrap() = State {
+            triggers: vec![BTreeSet::new(); compiled.len()],
+            targets: BTreeMap::new(),
+        };
+        *self.rules.write().unwrap() = compiled;
+    }
+
+    /// Whether there is at least one valid rule
+    pub fn is_enabled(&self) -> bool {
+        !self.rules.read().unwrap().is_empty()
+    }
+
+    /// Rules and ducked nodes
+    pub fn status(&self) -> DuckingStatus {
+        let rules = self.rules.read().unwrap();
+        let state = self.state.lock().unwrap();
+        DuckingStatus {
+            rules: rules
+                .iter()
+                .zip(&state.triggers)
+                .map(|(rule, streams)| DuckingRuleStatus {
+                    rule: rule.rule.clone(),
+                    active: !streams.is_empty(),
+                    streams: streams.iter().copied().collect(),
+                })
+                .collect(),
+            ducked: state
+                .targets
+                .iter()
+                .map(|(id, target)| DuckedNode {
+                    id: *id,
+                    name: target.name.clone(),
+                    original: target.original,
+                    volume: target.volume,
+                })
+                .collect(),
+        }
+    }
+
+    /// Update the rules for an event, returns the volume changes to make
+    ///
+    /// `nodes` are the current nodes (only needed for `stream_active`),
+    /// `volume_of` reads the volume of a node before it is ducked.
+    pub fn handle(&self, event: &StateEvent, nodes: &[PwObject], volume_of: impl Fn(u32) -> Option<f32>) -> Vec<Ramp> {
+        let rules = self.rules.read().unwrap();
+        let mut state = self.state.lock().unwrap();
+        let mut ramps = Vec::new();
+        match event {
+            StateEvent::StreamActive { stream } => {
+                for (i, rule) in rules.iter().enumerate() {
+                    if rule.is_trigger(stream) {
+                        let started = state.triggers[i].is_empty();
+                        state.triggers[i].insert(stream.id);
+                        if started {
+                            info!("Ducking {} while {} plays", rule.rule.target, stream.name);
+                            for node in nodes.iter().filter(|n| rule.is_target(n)) {
+                                ramps.extend(state.duck(&rules, i, node, &volume_of));
+                            }
+                        }
+                    } else if !state.triggers[i].is_empty() {
+                        if let Some(node) = nodes.iter().find(|n| n.id == stream.id && rule.is_target(n)) {
+                            ramps.extend(state.duck(&rules, i, node, &volume_of));
+                        }
+                    }
+                }
+            }
+            StateEvent::StreamIdle { stream } => {
+                for i in 0..rules.len() {
+                    if state.triggers[i].remove(&stream.id) && state.triggers[i].is_empty() {
+                        info!("Restoring {} after {} ended", rules[i].rule.target, stream.name);
+                        ramps.extend(state.release(&rules, i));
+                    }
+                }
+            }
+            StateEvent::NodeRemoved { node } => {
+                state.targets.remove(&node.id);
+            }
+            _ => {}
+        }
+        ramps
+    }
+
+    /// Change a volume step by step; a newer ramp of the node stops it
+    pub async fn run_ramp(self: Arc<Self>, ramp: Ramp) {
+        let id = ramp.id;
+        let (generation, written) = {
+            let mut ramps = self.ramps.lock().unwrap();
+            let entry = ramps.entry(id).or_insert((0, None));
+            entry.0 += 1;
+            *entry
+        };
+        let current = match crate::worker::run(move || wpctl::get_volume(id)).await {
+            Ok(Ok(info)) => info.volume,
+            _ => return,
+        };
+        if ramp.restore && written.is_some_and(|w| (current - w).abs() > 0.011) {
+            debug!("Volume of {} was changed while ducked, not restoring it", id);
+            return;
+        }
+        for volume in ramp_steps(current, ramp.to, ramp.ms) {
+            if self.ramps.lock().unwrap().get(&id).map(|r| r.0) != Some(generation) {
+                return;
+            }
+            match crate::worker::run(move || wpctl::set_volume(id, volume)).await {
+                Ok(Ok(_)) => {}
+                _ => return,
+            }
+            if let Some(entry) = self.ramps.lock().unwrap().get_mut(&id) {
+                entry.1 = Some(volume);
+            }
+            tokio::time::sleep(Duration::from_millis(STEP_MS)).await;
+        }
+        if ramp.restore {
+            let mut ramps = self.ramps.lock().unwrap();
+            if ramps.get(&id).map(|r| r.0) == Some(generation) {
+                ramps.remove(&id);
+            }
+        }
+    }
+}
+
+/// Start applying the ducking rules to stream events, `None` without rules
+pub fn start_ducking(manager: Arc<DuckingManager>) -> Option<JoinHandle<()>> {
+    if !manager.is_enabled() {
+        return None;
+    }
+    let mut receiver = events::subscribe();
+    Some(tokio::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Ducking: {} event(s) dropped", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if !matches!(
+                event,
+                StateEvent::StreamActive { .. } | StateEvent::StreamIdle { .. } | StateEvent::NodeRemoved { .. }
+            ) {
+                continue;
+            }
+            let handler = manager.clone();
+            let ramps = crate::worker::run(move || {
+                let nodes = match event {
+                    StateEvent::StreamActive { .. } => pwcli::list_nodes()?,
+                    _ => Vec::new(),
+                };
+                Ok::<_, String>(handler.handle(&event, &nodes, |id| wpctl::get_volume(id).ok().map(|v| v.volume)))
+            })
+            .await;
+            match ramps {
+                Ok(Ok(ramps)) => {
+                    for ramp in ramps {
+                        tokio::spawn(manager.clone().run_ramp(ramp));
+                    }
+                }
+                Ok(Err(e)) => warn!("Ducking: {}", e),
+                Err(e) => warn!("Ducking: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: u32, name: &str, class: &str, role: Option<&str>) -> PwObject {
+        let mut properties = vec![("node.name", name), ("media.class", class)];
+        properties.extend(role.map(|role| ("media.role", role)));
+        PwObject::new(id, "Node", &properties)
+    }
+
+    fn rule(trigger: &str, target: &str, duck_db: f32) -> DuckingRule {
+        DuckingRule {
+            trigger: trigger.to_string(),
+            target: target.to_string(),
+            duck_db,
+            attack_ms: 200,
+            release_ms: 800,
+        }
+    }
+
+    #[test]
+    fn test_duck_and_restore() {
+        let manager = DuckingManager::default();
+        manager.configure(vec![rule("^Notification$", "spotify|output_1", -20.0), rule("(", ".*", -6.0)]);
+        assert_eq!(manager.status().rules.len(), 1);
+
+        let announcement = node(80, "announcement", "Stream/Output/Audio", Some("Notification"));
+        let nodes = vec![
+            node(56, "output_1", "Audio/Sink", None),
+            node(70, "spotify", "Stream/Output/Audio", Some("Music")),
+            announcement.clone(),
+        ];
+        let active = StateEvent::StreamActive { stream: StreamInfo::from_node(&announcement) };
+        let ramps = manager.handle(&active, &nodes, |_| Some(0.8));
+        assert_eq!(
+            ramps,
+            vec![
+                Ramp { id: 56, to: 0.08, ms: 200, restore: false },
+                Ramp { id: 70, to: 0.08, ms: 200, restore: false },
+            ]
+        );
+        let status = manager.status();
+        assert!(status.rules[0].active);
+        assert_eq!(status.ducked.len(), 2);
+
+        // A second trigger stream does not duck again
+        let chime = node(81, "chime", "Stream/Output/Audio", Some("Notification"));
+        let second = StateEvent::StreamActive { stream: StreamInfo::from_node(&chime) };
+        assert!(manager.handle(&second, &nodes, |_| Some(0.8)).is_empty());
+
+        let idle = StateEvent::StreamIdle { stream: StreamInfo::from_node(&announcement) };
+        assert!(manager.handle(&idle, &[], |_| None).is_empty());
+        let idle = StateEvent::StreamIdle { stream: StreamInfo::from_node(&chime) };
+        let ramps = manager.handle(&idle, &[], |_| None);
+        assert_eq!(ramps.len(), 2);
+        assert!(ramps.iter().all(|r| r.to == 0.8 && r.ms == 800 && r.restore));
+        assert!(manager.status().ducked.is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_rules_and_ramp() {
+        let manager = DuckingManager::default();
+        manager.configure(vec![rule("Notification", "spotify", -6.0), rule("navigation", "spotify", -20.0)]);
+        let spotify = node(70, "spotify", "Stream/Output/Audio", None);
+        let chime = node(80, "chime", "Stream/Output/Audio", Some("Notification"));
+        let nav = node(81, "navigation", "Stream/Output/Audio", None);
+        let nodes = vec![spotify.clone(), chime.clone(), nav.clone()];
+
+        let active = |n: &PwObject| StateEvent::StreamActive { stream: StreamInfo::from_node(n) };
+        let idle = |n: &PwObject| StateEvent::StreamIdle { stream: StreamInfo::from_node(n) };
+        assert_eq!(manager.handle(&active(&chime), &nodes, |_| Some(1.0))[0].to, 0.5);
+        // The stronger rule lowers further, ending it returns to the weaker one
+        assert_eq!(manager.handle(&active(&nav), &nodes, |_| Some(1.0))[0].to, 0.1);
+        assert_eq!(manager.handle(&idle(&nav), &nodes, |_| None)[0].to, 0.5);
+        assert_eq!(manager.handle(&idle(&chime), &nodes, |_| None)[0].to, 1.0);
+
+        assert_eq!(ramp_steps(1.0, 0.5, 200), vec![0.88, 0.75, 0.63, 0.5]);
+        assert_eq!(ramp_steps(0.5, 1.0, 0), vec![1.0]);
+    }
+}
@@ -0,0 +1,547 @@
+//! State change events for push notifications
+//!
+//! PipeWire has no event subscription via the command line tools, so the
+//! monitor polls the node and device lists and `wpctl status` and publishes
+//! the differences: nodes and devices that appeared or disappeared, volume
+//! changes and playback streams becoming active or idle. A playback stream
+//! (`Stream/Output/Audio`) is active while it is linked to a node.
+//! Module enable toggles are taken from the parameter change events of the
+//! module nodes (see [`crate::param_monitor`]).
+//!
+//! Consumers (webhooks, script hooks) subscribe with [`subscribe`]. The
+//! appear and disappear events are also kept in a timestamped in-memory
+//! history (see [`history`]), e.g. to find out when a USB DAC dropped out.
+//! The monitor only runs if there is at least one consumer configured or the
+//! history is enabled.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+use crate::modules::Module;
+use crate::param_monitor::ParamChangeEvent;
+
+/// Number of events buffered for slow subscribers
+pub const EVENT_CAPACITY: usize = 256;
+
+/// Number of appear/disappear events kept in the history
+pub const HISTORY_CAPACITY: usize = 1000;
+
+/// Event names, as used in event filters
+pub const EVENT_NAMES: &[&str] = &[
+    "node_added",
+    "node_removed",
+    "device_added",
+    "device_removed",
+    "volume_changed",
+    "module_enabled",
+    "failover",
+    "stream_active",
+    "stream_idle",
+    "change_reverted",
+];
+
+/// A node or device as reported in events
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct NodeInfo {
+    pub id: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A playback stream as reported in events
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StreamInfo {
+    pub id: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub application: Option<String>,
+    /// media.role, e.g. "Notification" or "Music"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+}
+
+impl StreamInfo {
+    /// Get the stream info of a node
+    pub fn from_node(node: &crate::pwcli::PwObject) -> Self {
+        StreamInfo {
+            id: node.id,
+            name: node.name().map(|s| s.to_string()).unwrap_or_else(|| node.id.to_string()),
+            application: node.get("application.name").map(|s| s.to_string()),
+            role: node.get("media.role").map(|s| s.to_string()),
+        }
+    }
+}
+
+/// A change of the PipeWire state
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StateEvent {
+    NodeAdded {
+        #[serde(flatten)]
+        node: NodeInfo,
+    },
+    NodeRemoved {
+        #[serde(flatten)]
+        node: NodeInfo,
+    },
+    DeviceAdded {
+        #[serde(flatten)]
+        device: NodeInfo,
+    },
+    DeviceRemoved {
+        #[serde(flatten)]
+        device: NodeInfo,
+    },
+    VolumeChanged {
+        id: u32,
+        name: String,
+        volume: f32,
+        previous: f32,
+    },
+    ModuleEnabled {
+        module: String,
+        node: String,
+        enabled: bool,
+    },
+    /// Nodes moved between a primary and a backup sink (see [`crate::failover`])
+    Failover {
+        rule: String,
+        from: String,
+        to: String,
+        sources: Vec<String>,
+        success: bool,
+    },
+    /// A playback stream was linked
+    StreamActive {
+        #[serde(flatten)]
+        stream: StreamInfo,
+    },
+    /// A playback stream was unlinked or went away
+    StreamIdle {
+        #[serde(flatten)]
+        stream: StreamInfo,
+    },
+    /// The session manager undid a change made through the API (see [`crate::reverts`])
+    ChangeReverted {
+        /// "volume" or "link"
+        kind: String,
+        subject: String,
+        expected: String,
+        actual: String,
+        hint: String,
+    },
+}
+
+impl StateEvent {
+    /// Event name, e.g. "node_added"
+    pub fn name(&self) -> &'static str {
+        match self {
+            StateEvent::NodeAdded { .. } => "node_added",
+            StateEvent::NodeRemoved { .. } => "node_removed",
+            StateEvent::DeviceAdded { .. } => "device_added",
+            StateEvent::DeviceRemoved { .. } => "device_removed",
+            StateEvent::VolumeChanged { .. } => "volume_changed",
+            StateEvent::ModuleEnabled { .. } => "module_enabled",
+            StateEvent::Failover { .. } => "failover",
+            StateEvent::StreamActive { .. } => "stream_active",
+            StateEvent::StreamIdle { .. } => "stream_idle",
+            StateEvent::ChangeReverted { .. } => "change_reverted",
+        }
+    }
+
+    /// Name of the node or module the event is about
+    pub fn subject(&self) -> &str {
+        match self {
+            StateEvent::NodeAdded { node } | StateEvent::NodeRemoved { node } => &node.name,
+            StateEvent::DeviceAdded { device } | StateEvent::DeviceRemoved { device } => &device.name,
+            StateEvent::VolumeChanged { name, .. } => name,
+            StateEvent::ModuleEnabled { node, .. } => node,
+            StateEvent::Failover { to, .. } => to,
+            StateEvent::StreamActive { stream } | StateEvent::StreamIdle { stream } => &stream.name,
+            StateEvent::ChangeReverted { subject, .. } => subject,
+        }
+    }
+
+    /// Whether the event is a node or device appearing or disappearing
+    pub fn is_hotplug(&self) -> bool {
+        matches!(
+            self,
+            StateEvent::NodeAdded { .. }
+                | StateEvent::NodeRemoved { .. }
+                | StateEvent::DeviceAdded { .. }
+                | StateEvent::DeviceRemoved { .. }
+        )
+    }
+}
+
+/// An event in the history
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct HistoryEntry {
+    /// RFC 3339 time
+    pub time: String,
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: StateEvent,
+}
+
+static HISTORY: Mutex<VecDeque<HistoryEntry>> = Mutex::new(VecDeque::new());
+
+fn record(event: &StateEvent) {
+    let now = SystemTime::now();
+    let mut history = HISTORY.lock().unwrap();
+    if history.len() == HISTORY_CAPACITY {
+        history.pop_front();
+    }
+    history.push_back(HistoryEntry {
+        time: humantime::format_rfc3339_seconds(now).to_string(),
+        timestamp_ms: now.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+        event: event.clone(),
+    });
+}
+
+/// Get the appear/disappear events newer than `since_ms`, oldest first
+pub fn history(since_ms: Option<u64>) -> Vec<HistoryEntry> {
+    HISTORY
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| since_ms.map(|s| e.timestamp_ms > s).unwrap_or(true))
+        .cloned()
+        .collect()
+}
+
+fn sender() -> &'static broadcast::Sender<StateEvent> {
+    static SENDER: OnceLock<broadcast::Sender<StateEvent>> = OnceLock::new();
+    SENDER.get_or_init(|| broadcast::channel(EVENT_CAPACITY).0)
+}
+
+/// Publish an event to all subscribers
+pub fn publish(event: StateEvent) {
+    tracing::debug!("Event {}: {}", event.name(), event.subject());
+    if event.is_hotplug() {
+        record(&event);
+    }
+    let _ = sender().send(event);
+}
+
+/// Subscribe to state events
+pub fn subscribe() -> broadcast::Receiver<StateEvent> {
+    sender().subscribe()
+}
+
+/// Get added and removed nodes between two polls
+pub fn diff_nodes(old: &HashMap<u32, NodeInfo>, new: &HashMap<u32, NodeInfo>) -> Vec<StateEvent> {
+    diff_objects(old, new, |node| StateEvent::NodeAdded { node }, |node| StateEvent::NodeRemoved { node })
+}
+
+/// Get added and removed devices between two polls
+pub fn diff_devices(old: &HashMap<u32, NodeInfo>, new: &HashMap<u32, NodeInfo>) -> Vec<StateEvent> {
+    diff_objects(
+        old,
+        new,
+        |device| StateEvent::DeviceAdded { device },
+        |device| StateEvent::DeviceRemoved { device },
+    )
+}
+
+fn diff_objects(
+    old: &HashMap<u32, NodeInfo>,
+    new: &HashMap<u32, NodeInfo>,
+    added: fn(NodeInfo) -> StateEvent,
+    removed: fn(NodeInfo) -> StateEvent,
+) -> Vec<StateEvent> {
+    let mut events: Vec<StateEvent> = old
+        .iter()
+        .filter(|(id, _)| !new.contains_key(id))
+        .map(|(_, info)| removed(info.clone()))
+        .collect();
+    events.extend(
+        new.iter()
+            .filter(|(id, _)| !old.contains_key(id))
+            .map(|(_, info)| added(info.clone())),
+    );
+    events
+}
+
+/// Get volume changes between two polls (objects by id with name and volume)
+pub fn diff_volumes(old: &HashMap<u32, (String, f32)>, new: &HashMap<u32, (String, f32)>) -> Vec<StateEvent> {
+    new.iter()
+        .filter_map(|(id, (name, volume))| {
+            let (_, previous) = old.get(id)?;
+            ((previous - volume).abs() > 0.001).then(|| StateEvent::VolumeChanged {
+                id: *id,
+                name: name.clone(),
+                volume: *volume,
+                previous: *previous,
+            })
+        })
+        .collect()
+}
+
+/// Get playback streams that became active or idle between two polls
+///
+/// Streams that went away while active are reported as idle.
+pub fn diff_streams(old: &HashMap<u32, StreamInfo>, new: &HashMap<u32, StreamInfo>) -> Vec<StateEvent> {
+    let mut events: Vec<StateEvent> = old
+        .iter()
+        .filter(|(id, _)| !new.contains_key(id))
+        .map(|(_, stream)| StateEvent::StreamIdle { stream: stream.clone() })
+        .collect();
+    events.extend(
+        new.iter()
+            .filter(|(id, _)| !old.contains_key(id))
+            .map(|(_, stream)| StateEvent::StreamActive { stream: stream.clone() }),
+    );
+    events
+}
+
+/// Get the playback streams that are linked to a node
+pub fn active_streams(nodes: &[crate::pwcli::PwObject], links: &[crate::pwcli::PwObject]) -> HashMap<u32, StreamInfo> {
+    nodes
+        .iter()
+        .filter(|n| n.media_class() == Some("Stream/Output/Audio"))
+        .filter(|n| {
+            links
+                .iter()
+                .any(|l| l.get("link.output.node").and_then(|v| v.parse::<u32>().ok()) == Some(n.id))
+        })
+        .map(|n| (n.id, StreamInfo::from_node(n)))
+        .collect()
+}
+
+/// Get the new enable state from a parameter change of a module node
+pub fn enable_change(event: &ParamChangeEvent) -> Option<bool> {
+    crate::status::ENABLE_PARAMS.iter().find_map(|name| {
+        event
+            .changed
+            .iter()
+            .find(|(key, _)| key.rsplit(':').next() == Some(*name))
+            .and_then(|(_, value)| value.as_bool())
+    })
+}
+
+/// Nodes and active playback streams by id
+type PolledNodes = (HashMap<u32, NodeInfo>, HashMap<u32, StreamInfo>);
+
+fn poll_nodes() -> Result<PolledNodes, String> {
+    let nodes = crate::pwcli::list_nodes()?;
+    let links = crate::pwcli::list_links()?;
+    let streams = active_streams(&nodes, &links);
+    Ok((to_infos(nodes)?, streams))
+}
+
+fn poll_devices() -> Result<HashMap<u32, NodeInfo>, String> {
+    to_infos(crate::pwcli::list_devices()?)
+}
+
+fn to_infos(objects: Vec<crate::pwcli::PwObject>) -> Result<HashMap<u32, NodeInfo>, String> {
+    Ok(objects
+        .iter()
+        .filter_map(|n| {
+            Some((
+                n.id,
+                NodeInfo {
+                    id: n.id,
+                    name: n.name()?.to_string(),
+                    media_class: n.media_class().map(|s| s.to_string()),
+                    description: n.description().map(|s| s.to_string()),
+                },
+            ))
+        })
+        .collect())
+}
+
+fn poll_volumes() -> Result<HashMap<u32, (String, f32)>, String> {
+    Ok(crate::wpctl::list_volumes()?
+        .into_iter()
+        .map(|v| (v.id, (v.name, v.volume)))
+        .collect())
+}
+
+/// Start publishing node, device, volume, stream and module events
+pub fn start_event_monitor(modules: Vec<Arc<Module>>, poll_interval: Duration) -> JoinHandle<()> {
+    for module in modules {
+        let mut receiver = module.state.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(change) => {
+                        if let Some(enabled) = enable_change(&change) {
+                            publish(StateEvent::ModuleEnabled {
+                                module: module.name.clone(),
+                                node: change.node.clone(),
+                                enabled,
+                            });
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = interval(poll_interval);
+        // The first poll is the baseline, existing nodes are not reported
+        let mut nodes: Option<HashMap<u32, NodeInfo>> = None;
+        let mut devices: Option<HashMap<u32, NodeInfo>> = None;
+        let mut volumes: Option<HashMap<u32, (String, f32)>> = None;
+        let mut streams: Option<HashMap<u32, StreamInfo>> = None;
+        loop {
+            ticker.tick().await;
+            let polled = crate::worker::run(|| (poll_nodes(), poll_devices(), poll_volumes())).await;
+            let (new_nodes, new_devices, new_volumes) = match polled {
+                Ok(result) => result,
+                Err(e) => {
+                    tracing::warn!("Event monitor: {}", e);
+                    continue;
+                }
+            };
+
+            match new_nodes {
+                Ok((new_nodes, new_streams)) => {
+                    if let Some(old) = &nodes {
+                        diff_nodes(old, &new_nodes).into_iter().for_each(publish);
+                    }
+                    if let Some(old) = &streams {
+                        diff_streams(old, &new_streams).into_iter().for_each(publish);
+                    }
+                    nodes = Some(new_nodes);
+                    streams = Some(new_streams);
+                }
+                Err(e) => tracing::debug!("Event monitor: {}", e),
+            }
+            match new_devices {
+                Ok(new_devices) => {
+                    if let Some(old) = &devices {
+                        diff_devices(old, &new_devices).into_iter().for_each(publish);
+                    }
+                    devices = Some(new_devices);
+                }
+                Err(e) => tracing::debug!("Event monitor: {}", e),
+            }
+            match new_volumes {
+                Ok(new_volumes) => {
+                    if let Some(old) = &volumes {
+                        diff_volumes(old, &new_volumes).into_iter().for_each(publish);
+                    }
+                    volumes = Some(new_volumes);
+                }
+                Err(e) => tracing::debug!("Event monitor: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param_monitor::ChangeSource;
+    use crate::parameters::ParameterValue;
+
+    fn node(id: u32, name: &str) -> NodeInfo {
+        NodeInfo {
+            id,
+            name: name.to_string(),
+            media_class: Some("Audio/Sink".to_string()),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_nodes() {
+        let old = HashMap::from([(1, node(1, "a")), (2, node(2, "b"))]);
+        let new = HashMap::from([(2, node(2, "b")), (3, node(3, "c"))]);
+        let events = diff_nodes(&old, &new);
+        assert_eq!(events.len(), 2);
+        assert!(events.contains(&StateEvent::NodeRemoved { node: node(1, "a") }));
+        assert!(events.contains(&StateEvent::NodeAdded { node: node(3, "c") }));
+    }
+
+    #[test]
+    fn test_device_history() {
+        let usb = NodeInfo {
+            id: 60,
+            name: "alsa_card.usb-DAC".to_string(),
+            media_class: Some("Audio/Device".to_string()),
+            description: Some("USB DAC".to_string()),
+        };
+        let events = diff_devices(&HashMap::from([(60, usb.clone())]), &HashMap::new());
+        assert_eq!(events, vec![StateEvent::DeviceRemoved { device: usb }]);
+
+        let start = crate::store::now_ms() - 1;
+        events.into_iter().for_each(publish);
+        publish(StateEvent::VolumeChanged {
+            id: 1,
+            name: "Speakers".to_string(),
+            volume: 0.5,
+            previous: 0.4,
+        });
+        let history = history(Some(start));
+        let entry = history.iter().find(|e| e.event.subject() == "alsa_card.usb-DAC").unwrap();
+        assert_eq!(entry.event.name(), "device_removed");
+        assert!(history.iter().all(|e| e.event.is_hotplug()));
+        assert!(entry.time.ends_with('Z'));
+    }
+
+    #[test]
+    fn test_diff_volumes() {
+        let old = HashMap::from([(1, ("Speakers".to_string(), 0.5)), (2, ("HDMI".to_string(), 1.0))]);
+        let new = HashMap::from([
+            (1, ("Speakers".to_string(), 0.6)),
+            (2, ("HDMI".to_string(), 1.0)),
+            (3, ("USB".to_string(), 0.3)),
+        ]);
+        let events = diff_volumes(&old, &new);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].subject(), "Speakers");
+    }
+
+    #[test]
+    fn test_stream_events() {
+        let object = crate::pwcli::PwObject::new;
+        let nodes = vec![
+            object(70, "Node", &[("node.name", "spotify"), ("media.class", "Stream/Output/Audio"), ("media.role", "Music")]),
+            object(71, "Node", &[("node.name", "chime"), ("media.class", "Stream/Output/Audio")]),
+            object(56, "Node", &[("node.name", "output_1"), ("media.class", "Audio/Sink")]),
+        ];
+        let links = vec![object(90, "Link", &[("link.output.node", "70"), ("link.input.node", "56")])];
+        let playing = active_streams(&nodes, &links);
+        assert_eq!(playing.len(), 1);
+        assert_eq!(playing[&70].role.as_deref(), Some("Music"));
+
+        let events = diff_streams(&HashMap::new(), &playing);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name(), "stream_active");
+        assert!(!events[0].is_hotplug());
+        let events = diff_streams(&playing, &active_streams(&nodes, &[]));
+        assert_eq!(
+            serde_json::to_value(&events[0]).unwrap(),
+            serde_json::json!({"event": "stream_idle", "id": 70, "name": "spotify", "role": "Music"})
+        );
+    }
+
+    #[test]
+    fn test_enable_change_and_serialization() {
+        let changed = HashMap::from([("speakereq2x2:Enable".to_string(), ParameterValue::Bool(false))]);
+        let change = ParamChangeEvent::new("speakereq2x2", ChangeSource::External, &changed);
+        assert_eq!(enable_change(&change), Some(false));
+
+        let gain = HashMap::from([("speakereq2x2:master_gain_db".to_string(), ParameterValue::Float(-3.0))]);
+        assert_eq!(enable_change(&ParamChangeEvent::new("speakereq2x2", ChangeSource::Api, &gain)), None);
+
+        assert_eq!(
+            serde_json::to_value(StateEvent::NodeAdded { node: node(3, "c") }).unwrap(),
+            serde_json::json!({"event": "node_added", "id": 3, "name": "c", "media_class": "Audio/Sink"})
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Automatic failover to a backup sink
+//!
+//! When the primary sink disappears (e.g. a USB DAC is unplugged), the
+//! sources and filter-chains that were linked to it are linked to a backup
+//! sink. With `move_back` they are moved back when the primary returns.
+//! Rules are loaded from `failover.conf`:
+//!
+//! ```json
+//! [
+//!   {
+//!     "name": "USB DAC",
+//!     "primary": { "node.name": "^alsa_output\\.usb-" },
+//!     "backup": { "node.name": "^alsa_output\\.platform-" },
+//!     "move_back": true
+//!   }
+//! ]
+//! ```
+//!
+//! Nodes and links are detected by polling. While the primary is present,
+//! the nodes linked to it are remembered; these are the nodes that are
+//! moved. Each switch is published as a `failover` event (see
+//! [`crate::events`]).
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::events::{self, StateEvent};
+use crate::link_manager_cli;
+use crate::linker::{LinkRule, LinkType, LogLevel, NodeIdentifier};
+use crate::pwcli::{self, PwObject};
+
+/// A failover rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailoverRule {
+    /// Name of the rule (used in log messages and events)
+    pub name: String,
+    /// Sink that is used normally
+    pub primary: NodeIdentifier,
+    /// Sink that is used while the primary is missing
+    pub backup: NodeIdentifier,
+    /// Move the nodes back to the primary when it returns
+    #[serde(default)]
+    pub move_back: bool,
+}
+
+/// State of a failover rule between polls
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FailoverState {
+    /// The nodes are linked to the backup
+    pub on_backup: bool,
+    /// Node name of the primary when it was last seen
+    pub primary: Option<String>,
+    /// Names of the nodes linked to the primary (while on primary) or moved to the backup
+    pub sources: Vec<String>,
+}
+
+/// A switch between the primary and the backup sink
+#[derive(Debug, Clone, PartialEq)]
+pub struct Switch {
+    /// Node name of the sink the nodes are moved away from
+    pub from: String,
+    /// Node name of the sink the nodes are moved to
+    pub to: String,
+    /// Names of the nodes to move
+    pub sources: Vec<String>,
+}
+
+fn find_node<'a>(objects: &'a [PwObject], identifier: &NodeIdentifier) -> Option<&'a PwObject> {
+    objects
+        .iter()
+        .find(|o| o.object_type == "Node" && identifier.matches_properties(&o.properties))
+}
+
+/// Names of the nodes with links into a node
+fn linked_sources(objects: &[PwObject], node_id: u32) -> Vec<String> {
+    let mut sources: Vec<String> = objects
+        .iter()
+        .filter(|o| o.object_type == "Link" && o.get("link.input.node") == Some(node_id.to_string().as_str()))
+        .filter_map(|link| link.get("link.output.node")?.parse::<u32>().ok())
+        .filter_map(|id| objects.iter().find(|o| o.id == id && o.object_type == "Node"))
+        .filter_map(|node| node.name().map(String::from))
+        .collect();
+    sources.sort();
+    sources.dedup();
+    sources
+}
+
+/// Decide whether a rule has to switch, given the current objects
+///
+/// Updates the state: while the primary is present its linked nodes are
+/// remembered, so they are known after it disappeared.
+pub fn check(rule: &FailoverRule, state: &mut FailoverState, objects: &[PwObject]) -> Option<Switch> {
+    let name = |node: &PwObject| node.name().unwrap_or_default().to_string();
+
+    match find_node(objects, &rule.primary) {
+        Some(primary) => {
+            if state.on_backup {
+                state.on_backup = false;
+                let moved = std::mem::take(&mut state.sources);
+                if rule.move_back && !moved.is_empty() {
+                    if let Some(backup) = find_node(objects, &rule.backup) {
+                        state.sources = moved.clone();
+                        return Some(Switch { from: name(backup), to: name(primary), sources: moved });
+                    }
+                }
+            }
+            state.primary = Some(name(primary));
+            state.sources = linked_sources(objects, primary.id);
+            None
+        }
+        None => {
+            if state.on_backup || state.sources.is_empty() {
+                return None;
+            }
+            // Without a backup, retry on the next poll
+            let backup = find_node(objects, &rule.backup)?;
+            state.on_backup = true;
+            Some(Switch {
+                from: state.primary.clone().unwrap_or_default(),
+                to: name(backup),
+                sources: state.sources.clone(),
+            })
+        }
+    }
+}
+
+/// Rule linking or unlinking two nodes by exact name
+fn rule(name: &str, source: &str, destination: &str, link_type: LinkType) -> LinkRule {
+    let exact = |name: &str| NodeIdentifier {
+        node_name: Some(format!("^{}$", regex::escape(name))),
+        node_nick: None,
+        object_path: None,
+    };
+    LinkRule {
+        name: format!("failover {}", name),
+        source: exact(source),
+        destination: exact(destination),
+        link_type,
+        link_at_startup: false,
+        relink_every: 0,
+        info_level: LogLevel::Info,
+        error_level: LogLevel::Error,
+        wait_for: None,
+        exclusive: false,
+        channel_map: None,
+        source_ports: None,
+        destination_ports: None,
+        include_monitors: false,
+    }
+}
+
+fn apply(rule: &LinkRule) -> Result<(), String> {
+    let results = link_manager_cli::apply_link_rule(rule)?;
+    let failures: Vec<String> = results.into_iter().filter(|r| !r.success).map(|r| r.message).collect();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
+/// Link the nodes of a switch to the new sink and unlink them from the old one
+///
+/// Nodes that are gone are skipped. Returns the errors of the other nodes.
+pub fn apply_switch(name: &str, switch: &Switch) -> Vec<String> {
+    let mut errors = Vec::new();
+    for source in &switch.sources {
+        if let Err(e) = apply(&rule(name, source, &switch.to, LinkType::Link)) {
+            errors.push(format!("{}: {}", source, e));
+            continue;
+        }
+        // Links to a primary that disappeared are already gone
+        if let Err(e) = apply(&rule(name, source, &switch.from, LinkType::Unlink)) {
+            tracing::debug!("Failover '{}': not unlinking {}: {}", name, source, e);
+        }
+    }
+    errors
+}
+
+/// Check all rules once and apply the switches
+pub fn check_rules(rules: &[FailoverRule], states: &mut [FailoverState]) -> Result<(), String> {
+    let objects = pwcli::list_all()?;
+    for (rule, state) in rules.iter().zip(states.iter_mut()) {
+        let Some(switch) = check(rule, state, &objects) else {
+            continue;
+        };
+        warn!(
+            "Failover '{}': moving {} node(s) from {} to {}",
+            rule.name,
+            switch.sources.len(),
+            switch.from,
+            switch.to
+        );
+        let errors = apply_switch(&rule.name, &switch);
+        for e in &errors {
+            warn!("Failover '{}': failed to move {}", rule.name, e);
+        }
+        events::publish(StateEvent::Failover {
+            rule: rule.name.clone(),
+            from: switch.from,
+            to: switch.to,
+            sources: switch.sources,
+            success: errors.is_empty(),
+        });
+    }
+    Ok(())
+}
+
+/// Start the task that checks the failover rules
+pub fn start_failover(rules: Vec<FailoverRule>, interval: Duration) -> tokio::task::JoinHandle<()> {
+    let rules = Arc::new(rules);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut states = vec![FailoverState::default(); rules.len()];
+        info!("Failover started with {} rule(s)", rules.len());
+
+        loop {
+            ticker.tick().await;
+            let rules = rules.clone();
+            let mut current = std::mem::take(&mut states);
+            match crate::worker::run(move || {
+                let result = check_rules(&rules, &mut current);
+                (current, result)
+            })
+            .await
+            {
+                Ok((current, result)) => {
+                    states = current;
+                    if let Err(e) = result {
+                        warn!("Failed to check failover rules: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to check failover rules: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failover_rule(move_back: bool) -> FailoverRule {
+        serde_json::from_value(serde_json::json!({
+            "name": "dac",
+            "primary": { "node.name": "^usb_dac$" },
+            "backup": { "node.name": "^onboard$" },
+            "move_back": move_back
+        }))
+        .unwrap()
+    }
+
+    fn objects(primary: bool) -> Vec<PwObject> {
+        let mut objects = vec![
+            PwObject::new(30, "Node", &[("node.name", "speakereq2x2.output")]),
+            PwObject::new(40, "Node", &[("node.name", "onboard")]),
+        ];
+        if primary {
+            objects.push(PwObject::new(50, "Node", &[("node.name", "usb_dac")]));
+            objects.push(PwObject::new(60, "Link", &[("link.output.node", "30"), ("link.input.node", "50")]));
+        }
+        objects
+    }
+
+    #[test]
+    fn test_switch_to_backup_and_back() {
+        let rule = failover_rule(true);
+        let mut state = FailoverState::default();
+        assert_eq!(check(&rule, &mut state, &objects(true)), None);
+        assert_eq!(state.sources, vec!["speakereq2x2.output"]);
+
+        let switch = check(&rule, &mut state, &objects(false)).unwrap();
+        assert_eq!((switch.from.as_str(), switch.to.as_str()), ("usb_dac", "onboard"));
+        assert_eq!(switch.sources, vec!["speakereq2x2.output"]);
+        assert!(state.on_backup);
+        assert_eq!(check(&rule, &mut state, &objects(false)), None);
+
+        let back = check(&rule, &mut state, &objects(true)).unwrap();
+        assert_eq!((back.from.as_str(), back.to.as_str()), ("onboard", "usb_dac"));
+        assert!(!state.on_backup);
+    }
+
+    #[test]
+    fn test_stay_on_backup() {
+        let rule = failover_rule(false);
+        let mut state = FailoverState::default();
+        check(&rule, &mut state, &objects(true));
+        assert!(check(&rule, &mut state, &objects(false)).is_some());
+        assert_eq!(check(&rule, &mut state, &objects(true)), None);
+        assert!(!state.on_backup);
+    }
+}
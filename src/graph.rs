@@ -4,19 +4,157 @@
 //! Filter-chains are combined into single nodes for clarity.
 
 use axum::{
-    extract::State,
+    extract::{Query, State},
     http::{header, StatusCode},
-    response::{IntoResponse, Response},
+    response::{Html, IntoResponse, Response},
     routing::get,
     Router,
 };
-use std::collections::{HashMap, HashSet};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::process::Command;
 use std::sync::Arc;
 use tracing::error;
 
 use crate::api_server::AppState;
+use crate::bluetooth;
+use crate::midi;
+use crate::pod_parser::ParamObject;
 use crate::pwcli;
+use crate::pwparams;
+use crate::status;
+
+/// How to group the nodes of a graph into clusters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClusterBy {
+    /// By the device owning the node (`device.id`)
+    Device,
+    /// By the client that created the node (`client.id`)
+    Client,
+}
+
+/// Live values added to node labels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Annotations {
+    /// Volume and mute state (Props parameter)
+    pub volume: bool,
+    /// Sample rate, channels and sample format (Format parameter)
+    pub format: bool,
+}
+
+impl Annotations {
+    /// Parse a comma-separated list like "volume,format"
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut annotations = Annotations::default();
+        for item in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match item {
+                "volume" => annotations.volume = true,
+                "format" => annotations.format = true,
+                other => return Err(format!("Unknown annotation '{}' (use volume, format)", other)),
+            }
+        }
+        Ok(annotations)
+    }
+
+    fn any(&self) -> bool {
+        self.volume || self.format
+    }
+}
+
+/// Node kinds shown in addition to audio nodes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Include {
+    /// MIDI nodes and their links (see [`crate::midi`])
+    pub midi: bool,
+    /// Video nodes (cameras, screen capture) and their links
+    pub video: bool,
+}
+
+impl Include {
+    /// Parse a comma-separated list like "midi,video"
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut include = Include::default();
+        for item in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match item {
+                "midi" => include.midi = true,
+                "video" => include.video = true,
+                other => return Err(format!("Unknown node kind '{}' (use midi or video)", other)),
+            }
+        }
+        Ok(include)
+    }
+}
+
+fn deserialize_include<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Include, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Include::parse(&value).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_annotations<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Annotations, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Annotations::parse(&value).map_err(serde::de::Error::custom)
+}
+
+/// Options for graph generation, also the query parameters of the graph endpoints
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GraphOptions {
+    /// Group nodes into labeled clusters
+    pub cluster: Option<ClusterBy>,
+    /// Add live values to node labels, e.g. `?annotate=volume,format`
+    #[serde(default, deserialize_with = "deserialize_annotations")]
+    pub annotate: Annotations,
+    /// Show other node kinds too, e.g. `?include=midi,video`
+    #[serde(default, deserialize_with = "deserialize_include")]
+    pub include: Include,
+}
+
+/// Label lines with the volume and format of a node from its parameters
+pub fn annotation_label(props: &[ParamObject], format: &[ParamObject]) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let (volume, mute) = status::props_volume(props);
+    match (volume, mute) {
+        (Some(volume), Some(true)) => lines.push(format!("Volume: {:.0}% (muted)", volume * 100.0)),
+        (Some(volume), _) => lines.push(format!("Volume: {:.0}%", volume * 100.0)),
+        (None, Some(true)) => lines.push("Muted".to_string()),
+        (None, _) => {}
+    }
+
+    if let Some(format) = format.first() {
+        let mut parts = Vec::new();
+        if let Some(rate) = format.properties.get("rate").and_then(|v| v.as_u64()) {
+            parts.push(format!("{} Hz", rate));
+        }
+        if let Some(channels) = format.properties.get("channels").and_then(|v| v.as_u64()) {
+            parts.push(format!("{} ch", channels));
+        }
+        if let Some(sample_format) = format.properties.get("format").and_then(|v| v.as_str()) {
+            parts.push(sample_format.rsplit(':').next().unwrap_or(sample_format).to_string());
+        }
+        if !parts.is_empty() {
+            lines.push(parts.join(", "));
+        }
+    }
+    lines
+}
+
+/// Read the annotations of a node with pw-cli; missing parameters are skipped
+fn read_annotation(node_id: u32, annotations: Annotations) -> String {
+    let read = |enabled: bool, param_type: &str| {
+        if enabled {
+            pwparams::enum_params(node_id, param_type).unwrap_or_default()
+        } else {
+            Vec::new()
+        }
+    };
+    let props = read(annotations.volume, "Props");
+    let format = read(annotations.format, "Format");
+    annotation_label(&props, &format)
+        .iter()
+        .map(|line| format!("\\n{}", line.replace('"', "\\\"")))
+        .collect()
+}
 
 /// Represents a combined filter-chain node (input + output merged)
 struct FilterChain {
@@ -60,6 +198,7 @@ fn is_audio_node(obj: &pwcli::PwObject) -> bool {
         }
         // Include known audio nodes
         if name_lower.contains("alsa")
+            || name_lower.contains("bluez")
             || name_lower.contains("speakereq")
             || name_lower.contains("riaa")
             || name_lower.contains("output")
@@ -139,6 +278,12 @@ fn detect_filter_chains(nodes: &[&pwcli::PwObject]) -> Vec<FilterChain> {
     chains
 }
 
+/// Check if a node is a video node (`media.class` like `Video/Source`)
+fn is_video_node(obj: &pwcli::PwObject) -> bool {
+    obj.object_type == "Node"
+        && pwcli::classify_media_class(obj.media_class()) == pwcli::NodeTypeClassification::Video
+}
+
 /// Check if a port belongs to an audio node
 fn is_audio_port(obj: &pwcli::PwObject, audio_node_ids: &HashSet<u32>) -> bool {
     // Check if port's parent node is an audio node
@@ -151,7 +296,12 @@ fn is_audio_port(obj: &pwcli::PwObject, audio_node_ids: &HashSet<u32>) -> bool {
 }
 
 /// Generate DOT format graph of audio topology
-fn generate_dot_graph(objects: &[pwcli::PwObject]) -> String {
+pub fn generate_dot_graph(objects: &[pwcli::PwObject]) -> String {
+    generate_dot_graph_with(objects, &GraphOptions::default())
+}
+
+/// Generate a DOT graph with options, e.g. clusters by device or client
+pub fn generate_dot_graph_with(objects: &[pwcli::PwObject], options: &GraphOptions) -> String {
     let mut dot = String::new();
 
     dot.push_str("digraph PipeWire {\n");
@@ -164,7 +314,7 @@ fn generate_dot_graph(objects: &[pwcli::PwObject]) -> String {
     // Collect audio nodes and their IDs
     let mut audio_node_ids: HashSet<u32> = HashSet::new();
     let mut nodes: Vec<&pwcli::PwObject> = Vec::new();
-    let mut devices: Vec<&pwcli::PwObject> = Vec::new();
+    let mut devices: HashMap<u32, &pwcli::PwObject> = HashMap::new();
     let mut all_clients: HashMap<u32, &pwcli::PwObject> = HashMap::new();
     let mut node_to_client: HashMap<u32, u32> = HashMap::new();
 
@@ -177,7 +327,9 @@ fn generate_dot_graph(objects: &[pwcli::PwObject]) -> String {
 
     // Second pass: collect nodes and map to clients
     for obj in objects {
-        if obj.object_type == "Node" && is_audio_node(obj) {
+        let included = (options.include.midi && midi::is_midi_node(obj))
+            || (options.include.video && is_video_node(obj));
+        if obj.object_type == "Node" && (is_audio_node(obj) || included) {
             audio_node_ids.insert(obj.id);
             nodes.push(obj);
             // Track client.id for this node
@@ -187,7 +339,7 @@ fn generate_dot_graph(objects: &[pwcli::PwObject]) -> String {
                 }
             }
         } else if obj.object_type == "Device" && is_audio_node(obj) {
-            devices.push(obj);
+            devices.insert(obj.id, obj);
         }
     }
 
@@ -206,7 +358,7 @@ fn generate_dot_graph(objects: &[pwcli::PwObject]) -> String {
 
     // Track which clients are connected to audio nodes
     let mut connected_client_ids: HashSet<u32> = HashSet::new();
-    for (_, client_id) in &node_to_client {
+    for client_id in node_to_client.values() {
         connected_client_ids.insert(*client_id);
     }
 
@@ -279,26 +431,44 @@ fn generate_dot_graph(objects: &[pwcli::PwObject]) -> String {
                 client.id, escaped_name, client.id
             ));
         }
-        dot.push_str("\n");
+        dot.push('\n');
     }
 
-    // 4. Add filter-chains as combined nodes
-    if !filter_chains.is_empty() {
-        dot.push_str("        // Filter Chains (combined input+output)\n");
-        for chain in &filter_chains {
-            let escaped_name = chain.name.replace('"', "\\\"");
-            let node_name = format!("chain_{}", chain.input_id);
-            dot.push_str(&format!(
-                "        {} [label=\"{}\\nID: {}/{}\", fillcolor=lightyellow, style=\"filled,bold\"];\n",
-                node_name, escaped_name, chain.input_id, chain.output_id
-            ));
-            filter_nodes.push(node_name);
+    // Cluster of a node, if clustering is requested
+    let cluster_of = |node_id: u32| -> Option<u32> {
+        let key = match options.cluster? {
+            ClusterBy::Device => "device.id",
+            ClusterBy::Client => "client.id",
+        };
+        let node = nodes.iter().find(|n| n.id == node_id)?;
+        node.properties.get(key)?.parse().ok()
+    };
+    // Live values for the labels (reads parameters with pw-cli)
+    let annotation = |node_id: u32| -> String {
+        if options.annotate.any() {
+            read_annotation(node_id, options.annotate)
+        } else {
+            String::new()
         }
-        dot.push('\n');
+    };
+    // Node statements with their cluster, written after all nodes are known
+    let mut node_lines: Vec<(Option<u32>, String)> = Vec::new();
+
+    // 4. Add filter-chains as combined nodes
+    for chain in &filter_chains {
+        let escaped_name = chain.name.replace('"', "\\\"");
+        let node_name = format!("chain_{}", chain.input_id);
+        node_lines.push((
+            cluster_of(chain.input_id),
+            format!(
+                "{} [label=\"{}\\nID: {}/{}{}\", fillcolor=lightyellow, style=\"filled,bold\"];",
+                node_name, escaped_name, chain.input_id, chain.output_id, annotation(chain.input_id)
+            ),
+        ));
+        filter_nodes.push(node_name);
     }
 
     // Add regular nodes (excluding filter-chain members)
-    dot.push_str("        // Audio Nodes\n");
     for node in &nodes {
         // Skip nodes that are part of a filter-chain
         if filter_chain_input_ids.contains(&node.id) || filter_chain_output_ids.contains(&node.id) {
@@ -322,12 +492,25 @@ fn generate_dot_graph(objects: &[pwcli::PwObject]) -> String {
                 ("paleturquoise", "sink")  // Stream outputs are sinks
             } else if class_lower.contains("stream/input") {
                 ("palegreen", "source")  // Stream inputs are sources
+            } else if class_lower.contains("midi") {
+                ("plum", "filter")
             } else {
                 ("white", "filter")
             }
         } else {
             ("white", "filter")
         };
+        let color = if is_video_node(node) { "wheat" } else { color };
+
+        // Bluetooth nodes keep their category but get their own color and the codec in the label
+        let (color, label) = if bluetooth::is_bluetooth(node) {
+            let codec = bluetooth::codec(node)
+                .map(|c| format!(" ({})", c.to_uppercase()))
+                .unwrap_or_default();
+            ("lightsteelblue", format!("{}\\nBluetooth{}", escaped_name, codec))
+        } else {
+            (color, escaped_name)
+        };
 
         // Track for ranking
         match category {
@@ -336,12 +519,16 @@ fn generate_dot_graph(objects: &[pwcli::PwObject]) -> String {
             _ => filter_nodes.push(node_name.clone()),
         }
 
-        dot.push_str(&format!(
-            "        {} [label=\"{}\\nID: {}\", fillcolor={}];\n",
-            node_name, escaped_name, node.id, color
+        node_lines.push((
+            cluster_of(node.id),
+            format!(
+                "{} [label=\"{}\\nID: {}{}\", fillcolor={}];",
+                node_name, label, node.id, annotation(node.id), color
+            ),
         ));
     }
-    dot.push('\n');
+
+    write_nodes(&mut dot, node_lines, options.cluster, &devices, &all_clients);
 
     // Add links between nodes (aggregate port links to node links)
     // For filter-chains, map input/output node IDs to the chain's input_id
@@ -423,14 +610,115 @@ fn generate_dot_graph(objects: &[pwcli::PwObject]) -> String {
     dot
 }
 
+/// Write node statements, grouped into labeled clusters where they have one
+fn write_nodes(
+    dot: &mut String,
+    node_lines: Vec<(Option<u32>, String)>,
+    cluster: Option<ClusterBy>,
+    devices: &HashMap<u32, &pwcli::PwObject>,
+    clients: &HashMap<u32, &pwcli::PwObject>,
+) {
+    let mut clusters: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    dot.push_str("        // Audio Nodes\n");
+    for (cluster_id, line) in node_lines {
+        match cluster_id {
+            Some(id) => clusters.entry(id).or_default().push(line),
+            None => dot.push_str(&format!("        {}\n", line)),
+        }
+    }
+    dot.push('\n');
+
+    let (prefix, owners) = match cluster {
+        Some(ClusterBy::Device) => ("device", devices),
+        Some(ClusterBy::Client) => ("client", clients),
+        None => return,
+    };
+    for (id, lines) in clusters {
+        let name = owners
+            .get(&id)
+            .map(|owner| owner.get("device.description").map(String::from).unwrap_or_else(|| owner.display_name()))
+            .unwrap_or_else(|| format!("{} {}", prefix, id));
+        dot.push_str(&format!("        subgraph cluster_{}_{} {{\n", prefix, id));
+        dot.push_str(&format!("            label=\"{}\";\n", name.replace('"', "\\\"")));
+        dot.push_str("            style=\"rounded,dashed\";\n");
+        dot.push_str("            color=gray50;\n");
+        for line in lines {
+            dot.push_str(&format!("            {}\n", line));
+        }
+        dot.push_str("        }\n\n");
+    }
+}
+
+/// Check if graphviz (dot) is available
+pub fn graphviz_available() -> bool {
+    matches!(
+        Command::new("which").arg("dot").output(),
+        Ok(output) if output.status.success()
+    )
+}
+
+/// Render a DOT graph with graphviz to an output format like "png" or "svg"
+pub fn render(dot: &str, format: &str) -> Result<Vec<u8>, String> {
+    let mut child = Command::new("dot")
+        .arg(format!("-T{}", format))
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn dot process: {}", e))?;
+
+    // Write DOT to stdin
+    if let Some(mut stdin) = child.stdin.take() {
+        use std::io::Write;
+        stdin.write_all(dot.as_bytes())
+            .map_err(|e| format!("Failed to write to dot stdin: {}", e))?;
+    }
+
+    // Get output
+    let output = child.wait_with_output()
+        .map_err(|e| format!("Failed to wait for dot process: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("dot command failed: {}", stderr));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Render a DOT graph to "png" or "svg", with graphviz if it is installed
+///
+/// Without graphviz the built-in renderer is used (`graph-render` feature).
+pub fn render_image(dot: &str, format: &str) -> Result<Vec<u8>, String> {
+    if graphviz_available() {
+        return render(dot, format);
+    }
+
+    #[cfg(feature = "graph-render")]
+    {
+        match format {
+            "png" => crate::graph_render::render_png(dot),
+            "svg" => crate::graph_render::render_svg(dot).map(String::into_bytes),
+            _ => Err(format!("Unsupported graph format '{}'", format)),
+        }
+    }
+
+    #[cfg(not(feature = "graph-render"))]
+    Err("Graphviz not found".to_string())
+}
+
 /// Handler for GET /api/v1/graph - returns DOT format graph
+///
+/// `?cluster=device` or `?cluster=client` groups the nodes into clusters,
+/// `?annotate=volume,format` adds live values to the node labels.
 pub async fn get_graph_dot(
     State(_state): State<Arc<AppState>>,
+    Query(options): Query<GraphOptions>,
 ) -> Response {
-    let result = tokio::task::spawn_blocking(|| {
+    let result = crate::worker::run(move || {
         let objects = pwcli::list_all()
             .map_err(|e| format!("Failed to list PipeWire objects: {}", e))?;
-        Ok::<_, String>(generate_dot_graph(&objects))
+        Ok::<_, String>(generate_dot_graph_with(&objects, &options))
     })
     .await;
 
@@ -447,61 +735,25 @@ pub async fn get_graph_dot(
             (StatusCode::INTERNAL_SERVER_ERROR, "Failed to get PipeWire objects").into_response()
         }
         Err(e) => {
-            error!("Task join error: {}", e);
+            error!("Worker error: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response()
         }
     }
 }
 
 /// Handler for GET /api/v1/graph/png - returns PNG image
+///
+/// Takes the same query parameters as [`get_graph_dot`].
 pub async fn get_graph_png(
     State(_state): State<Arc<AppState>>,
+    Query(options): Query<GraphOptions>,
 ) -> Response {
-    let result = tokio::task::spawn_blocking(|| {
-        // Check if graphviz (dot) is available
-        let dot_check = Command::new("which")
-            .arg("dot")
-            .output();
-
-        match dot_check {
-            Ok(output) if output.status.success() => {}
-            _ => {
-                return Err("Graphviz not found".to_string());
-            }
-        }
-
+    let result = crate::worker::run(move || {
         // Get all objects
         let objects = pwcli::list_all()
             .map_err(|e| format!("Failed to list PipeWire objects: {}", e))?;
 
-        let dot = generate_dot_graph(&objects);
-
-        // Run dot to generate PNG
-        let mut child = Command::new("dot")
-            .arg("-Tpng")
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-            .map_err(|e| format!("Failed to spawn dot process: {}", e))?;
-
-        // Write DOT to stdin
-        if let Some(mut stdin) = child.stdin.take() {
-            use std::io::Write;
-            stdin.write_all(dot.as_bytes())
-                .map_err(|e| format!("Failed to write to dot stdin: {}", e))?;
-        }
-
-        // Get output
-        let output = child.wait_with_output()
-            .map_err(|e| format!("Failed to wait for dot process: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("dot command failed: {}", stderr));
-        }
-
-        Ok(output.stdout)
+        render_image(&generate_dot_graph_with(&objects, &options), "png")
     })
     .await;
 
@@ -522,15 +774,137 @@ pub async fn get_graph_png(
             }
         }
         Err(e) => {
-            error!("Task join error: {}", e);
+            error!("Worker error: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response()
         }
     }
 }
 
+/// Self-contained topology explorer, see `src/graph.html`
+const GRAPH_PAGE: &str = include_str!("graph.html");
+
+/// Handler for GET /api/v1/graph/html - interactive topology page
+///
+/// The page loads `/api/v1/topology/export` and shows the properties of a
+/// node from `/api/v1/properties/:id` when it is clicked. An
+/// `?access_token=` of the page URL is passed on to these requests.
+pub async fn get_graph_html() -> Html<&'static str> {
+    Html(GRAPH_PAGE)
+}
+
 /// Create router for graph endpoints
 pub fn create_graph_router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/api/v1/graph", get(get_graph_dot))
         .route("/api/v1/graph/png", get(get_graph_png))
+        .route("/api/v1/graph/html", get(get_graph_html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pwcli::PwObject;
+
+    #[test]
+    fn test_cluster_by_device() {
+        let objects = vec![
+            PwObject::new(10, "Device", &[("media.class", "Audio/Device"), ("device.description", "USB DAC")]),
+            PwObject::new(40, "Node", &[
+                ("media.class", "Audio/Sink"),
+                ("node.name", "alsa_output.usb"),
+                ("device.id", "10"),
+            ]),
+            PwObject::new(41, "Node", &[
+                ("media.class", "Audio/Source"),
+                ("node.name", "alsa_input.usb"),
+                ("device.id", "10"),
+            ]),
+            PwObject::new(50, "Node", &[("media.class", "Stream/Output/Audio"), ("node.name", "player")]),
+        ];
+
+        let plain = generate_dot_graph(&objects);
+        assert!(!plain.contains("cluster_device"));
+
+        let options = GraphOptions { cluster: Some(ClusterBy::Device), ..Default::default() };
+        let dot = generate_dot_graph_with(&objects, &options);
+        let cluster = dot.find("subgraph cluster_device_10 {").expect("device cluster");
+        assert!(dot[cluster..].contains("label=\"USB DAC\""));
+        assert!(dot.find("node_40 [").unwrap() > cluster);
+        assert!(dot.find("node_41 [").unwrap() > cluster);
+        assert!(dot.find("node_50 [").unwrap() < cluster);
+    }
+
+    #[test]
+    fn test_bluetooth_label() {
+        let objects = vec![PwObject::new(60, "Node", &[
+            ("media.class", "Audio/Sink"),
+            ("node.name", "bluez_output.headphones"),
+            ("api.bluez5.codec", "aac"),
+        ])];
+        let dot = generate_dot_graph(&objects);
+        // A DOT line break, not a newline inside the quoted label
+        assert!(dot.contains("bluez_output.headphones\\nBluetooth (AAC)"));
+        assert!(dot.contains("lightsteelblue"));
+    }
+
+    fn param(id: &str, properties: serde_json::Value) -> ParamObject {
+        ParamObject {
+            object_type: id.to_string(),
+            id: id.to_string(),
+            properties: properties.as_object().unwrap().clone(),
+        }
+    }
+
+    #[test]
+    fn test_annotations() {
+        assert_eq!(Annotations::parse("volume, format").unwrap(), Annotations { volume: true, format: true });
+        assert_eq!(Annotations::parse("").unwrap(), Annotations::default());
+        assert!(Annotations::parse("volume,latency").is_err());
+
+        let props = [param("Props", serde_json::json!({"channelVolumes": [0.125, 0.125], "mute": true}))];
+        let format = [param("Format", serde_json::json!({"rate": 48000, "channels": 2, "format": "S32LE"}))];
+        assert_eq!(
+            annotation_label(&props, &format),
+            vec!["Volume: 50% (muted)".to_string(), "48000 Hz, 2 ch, S32LE".to_string()]
+        );
+        assert!(annotation_label(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_include_midi() {
+        let objects = vec![
+            PwObject::new(30, "Node", &[("media.class", "Midi/Bridge"), ("node.name", "Midi-Bridge")]),
+            PwObject::new(31, "Port", &[("node.id", "30"), ("port.direction", "out")]),
+            PwObject::new(40, "Node", &[("media.class", "Stream/Input/Audio"), ("node.name", "volume-knob")]),
+            PwObject::new(41, "Port", &[("node.id", "40"), ("port.direction", "in")]),
+            PwObject::new(50, "Link", &[("link.output.port", "31"), ("link.input.port", "41")]),
+        ];
+
+        assert!(!generate_dot_graph(&objects).contains("node_30"));
+
+        let options = GraphOptions { include: Include::parse("midi").unwrap(), ..Default::default() };
+        let dot = generate_dot_graph_with(&objects, &options);
+        assert!(dot.contains("node_30 [label=\"Midi-Bridge\\nID: 30\", fillcolor=plum];"));
+        assert!(dot.contains("node_30 -> node_40;"));
+        assert!(Include::parse("audio").is_err());
+    }
+
+    #[test]
+    fn test_include_video() {
+        let objects = vec![
+            PwObject::new(60, "Node", &[("media.class", "Video/Source"), ("node.name", "v4l2_input.camera")]),
+            PwObject::new(61, "Port", &[("node.id", "60"), ("port.direction", "out")]),
+            PwObject::new(70, "Node", &[("media.class", "Stream/Input/Video"), ("node.name", "recorder")]),
+            PwObject::new(71, "Port", &[("node.id", "70"), ("port.direction", "in")]),
+            PwObject::new(80, "Link", &[("link.output.port", "61"), ("link.input.port", "71")]),
+        ];
+
+        let dot = generate_dot_graph(&objects);
+        assert!(!dot.contains("node_60") && !dot.contains("node_70"));
+
+        let options = GraphOptions { include: Include::parse("video").unwrap(), ..Default::default() };
+        let dot = generate_dot_graph_with(&objects, &options);
+        assert!(dot.contains("node_60 [label=\"v4l2_input.camera\\nID: 60\", fillcolor=wheat];"));
+        assert!(dot.contains("node_60 -> node_70;"));
+    }
 }
@@ -0,0 +1,121 @@
+//! Built-in graph rendering without graphviz
+//!
+//! Lays out DOT graphs with layout-rs and rasterizes the resulting SVG with
+//! resvg, so `/api/v1/graph/png` also works on minimal images without the
+//! `dot` tool. The layout is simpler than graphviz's: clusters and rank
+//! constraints are ignored. Labels are drawn with the system fonts; without
+//! any installed font the boxes are drawn without text.
+//!
+//! Only built with the `graph-render` feature.
+
+use layout::backends::svg::SVGWriter;
+use layout::gv::{DotParser, GraphBuilder};
+use resvg::{tiny_skia, usvg};
+use std::sync::{Arc, OnceLock};
+
+/// Largest width or height of a rendered image in pixels
+const MAX_IMAGE_SIZE: u32 = 8192;
+
+static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+
+/// System fonts, loaded on first use
+fn fonts() -> Arc<usvg::fontdb::Database> {
+    FONTS
+        .get_or_init(|| {
+            let mut db = usvg::fontdb::Database::new();
+            db.load_system_fonts();
+            // layout-rs asks for "Times, serif", map it to an installed font
+            let families: Vec<String> = db
+                .faces()
+                .filter_map(|face| face.families.first().map(|(name, _)| name.clone()))
+                .collect();
+            match families.iter().find(|f| f.contains("Sans")).or(families.first()) {
+                Some(family) => db.set_serif_family(family.clone()),
+                None => tracing::warn!("No system fonts found, graph labels are not rendered"),
+            }
+            Arc::new(db)
+        })
+        .clone()
+}
+
+/// Lay out a DOT graph and render it as SVG
+pub fn render_svg(dot: &str) -> Result<String, String> {
+    let mut parser = DotParser::new(dot);
+    let graph = parser
+        .process()
+        .map_err(|e| format!("Failed to parse DOT graph: {}", e))?;
+
+    let mut builder = GraphBuilder::new();
+    builder.visit_graph(&graph);
+    let mut visual = builder.get();
+
+    let mut writer = SVGWriter::new();
+    visual.do_it(false, false, false, &mut writer);
+    Ok(writer.finalize())
+}
+
+/// Lay out a DOT graph and render it as PNG
+pub fn render_png(dot: &str) -> Result<Vec<u8>, String> {
+    let svg = render_svg(dot)?;
+
+    let options = usvg::Options {
+        fontdb: fonts(),
+        ..usvg::Options::default()
+    };
+    let tree = usvg::Tree::from_str(&svg, &options)
+        .map_err(|e| format!("Failed to parse rendered SVG: {}", e))?;
+
+    let size = tree.size().to_int_size();
+    if size.width() > MAX_IMAGE_SIZE || size.height() > MAX_IMAGE_SIZE {
+        return Err(format!(
+            "Graph is too large to render ({}x{} pixels)",
+            size.width(),
+            size.height()
+        ));
+    }
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| "Failed to allocate image".to_string())?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+    pixmap
+        .encode_png()
+        .map_err(|e| format!("Failed to encode PNG: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOT: &str = "digraph PipeWire {
+    rankdir=TB;
+    node [shape=box, style=filled];
+    newrank=true;
+    compound=true;
+    subgraph cluster_graph {
+        label=\"\";
+        style=invis;
+        client_30 [label=\"Spotify\\nClient ID: 30\", fillcolor=lavender, shape=ellipse];
+        node_40 [label=\"Spotify\\nID: 40\", fillcolor=paleturquoise];
+        node_50 [label=\"Built-in Audio\\nID: 50\", fillcolor=lightblue];
+        node_40 -> node_50;
+        client_30 -> node_40 [style=dashed, color=gray];
+    }
+    { rank=max; node_50 }
+}
+";
+
+    #[test]
+    fn test_render_generated_dot() {
+        let svg = render_svg(DOT).unwrap();
+        assert!(svg.contains("<svg"));
+
+        let png = render_png(DOT).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn test_invalid_dot() {
+        assert!(render_svg("digraph {").is_err());
+    }
+}
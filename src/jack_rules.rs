@@ -0,0 +1,303 @@
+//! Headphone/speaker switching by jack detection
+//!
+//! Sound cards report whether headphones are plugged in as the availability
+//! of their headphone route. When the headphones are plugged in, a rule
+//! mutes the speakers and/or moves a source (e.g. the SpeakerEQ output) from
+//! the speakers to the headphones; when they are unplugged, it switches back.
+//! Rules are loaded from `jack-rules.conf`:
+//!
+//! ```json
+//! [
+//!   {
+//!     "name": "Headphone jack",
+//!     "device": "^alsa_card\\.pci-",
+//!     "route": "headphones",
+//!     "source": { "node.name": "^speakereq2x2\\.output$" },
+//!     "headphones": { "node.name": "^alsa_output\\.pci-.*analog-stereo$" },
+//!     "speakers": { "node.name": "^alsa_output\\.usb-" },
+//!     "mute_speakers": true
+//!   }
+//! ]
+//! ```
+//!
+//! Jacks are detected by polling the `EnumRoute` parameter of the devices.
+//! The state found at startup is applied, later only changes are applied.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::link_manager_cli;
+use crate::linker::{LinkRule, LinkType, LogLevel, NodeIdentifier};
+use crate::pod_parser::ParamObject;
+use crate::pwcli;
+use crate::util::regex_match;
+
+fn default_route() -> String {
+    "headphones".to_string()
+}
+
+/// A headphone/speaker switching rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JackRule {
+    /// Name of the rule (used in log messages)
+    pub name: String,
+    /// Regex on the `device.name` of the sound card with the jack
+    pub device: String,
+    /// Regex on the name of the output route of the jack
+    #[serde(default = "default_route")]
+    pub route: String,
+    /// Node that is moved between speakers and headphones
+    #[serde(default)]
+    pub source: Option<NodeIdentifier>,
+    /// Sink of the headphones, the source is linked to it while plugged in
+    #[serde(default)]
+    pub headphones: Option<NodeIdentifier>,
+    /// Sink of the speakers
+    pub speakers: NodeIdentifier,
+    /// Mute the speakers while the headphones are plugged in
+    #[serde(default)]
+    pub mute_speakers: bool,
+}
+
+/// A step of switching between headphones and speakers
+#[derive(Debug, Clone, PartialEq)]
+pub enum JackAction {
+    Mute { node: NodeIdentifier, mute: bool },
+    Link { source: NodeIdentifier, destination: NodeIdentifier },
+    Unlink { source: NodeIdentifier, destination: NodeIdentifier },
+}
+
+/// Get the jack state from the routes of a device
+///
+/// Returns `None` if the device has no matching output route.
+pub fn is_plugged(routes: &[ParamObject], route_pattern: &str) -> Option<bool> {
+    let matching: Vec<&ParamObject> = routes
+        .iter()
+        .filter(|r| r.properties.get("direction").and_then(|d| d.as_str()) == Some("Output"))
+        .filter(|r| {
+            r.properties
+                .get("name")
+                .and_then(|n| n.as_str())
+                .map(|n| regex_match(route_pattern, n))
+                .unwrap_or(false)
+        })
+        .collect();
+    if matching.is_empty() {
+        return None;
+    }
+    Some(
+        matching
+            .iter()
+            .any(|r| r.properties.get("available").and_then(|a| a.as_str()) == Some("yes")),
+    )
+}
+
+/// Steps to switch to the headphones (plugged) or the speakers (unplugged)
+pub fn plan(rule: &JackRule, plugged: bool) -> Vec<JackAction> {
+    let mut actions = Vec::new();
+    if rule.mute_speakers {
+        actions.push(JackAction::Mute { node: rule.speakers.clone(), mute: plugged });
+    }
+    if let Some(source) = &rule.source {
+        let (to, from) = if plugged {
+            (rule.headphones.as_ref(), Some(&rule.speakers))
+        } else {
+            (Some(&rule.speakers), rule.headphones.as_ref())
+        };
+        if let Some(to) = to {
+            actions.push(JackAction::Link { source: source.clone(), destination: to.clone() });
+        }
+        if let Some(from) = from {
+            actions.push(JackAction::Unlink { source: source.clone(), destination: from.clone() });
+        }
+    }
+    actions
+}
+
+fn link_rule(name: &str, source: &NodeIdentifier, destination: &NodeIdentifier, link_type: LinkType) -> LinkRule {
+    LinkRule {
+        name: format!("jack {}", name),
+        source: source.clone(),
+        destination: destination.clone(),
+        link_type,
+        link_at_startup: false,
+        relink_every: 0,
+        info_level: LogLevel::Info,
+        error_level: LogLevel::Error,
+        wait_for: None,
+        exclusive: false,
+        channel_map: None,
+        source_ports: None,
+        destination_ports: None,
+        include_monitors: false,
+    }
+}
+
+fn apply_link(rule: &LinkRule) -> Result<(), String> {
+    let results = link_manager_cli::apply_link_rule(rule)?;
+    let failures: Vec<String> = results.into_iter().filter(|r| !r.success).map(|r| r.message).collect();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
+/// Run the steps of a switch, returning the errors
+pub fn apply(rule: &JackRule, actions: &[JackAction]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for action in actions {
+        let result = match action {
+            JackAction::Mute { node, mute } => pwcli::list_nodes().and_then(|nodes| {
+                nodes
+                    .iter()
+                    .filter(|n| node.matches_properties(&n.properties))
+                    .try_for_each(|n| crate::wpctl::set_mute(n.id, *mute))
+            }),
+            JackAction::Link { source, destination } => {
+                apply_link(&link_rule(&rule.name, source, destination, LinkType::Link))
+            }
+            JackAction::Unlink { source, destination } => {
+                // There is nothing to unlink if the source was not linked
+                if let Err(e) = apply_link(&link_rule(&rule.name, source, destination, LinkType::Unlink)) {
+                    debug!("Jack rule '{}': not unlinking: {}", rule.name, e);
+                }
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+    errors
+}
+
+/// Read the jack state of a rule from the devices
+fn read_state(rule: &JackRule, devices: &[pwcli::PwObject]) -> Option<bool> {
+    devices
+        .iter()
+        .filter(|d| d.get("device.name").map(|n| regex_match(&rule.device, n)).unwrap_or(false))
+        .filter_map(|d| {
+            let routes = crate::pwparams::enum_params(d.id, "EnumRoute").ok()?;
+            is_plugged(&routes, &rule.route)
+        })
+        .reduce(|a, b| a || b)
+}
+
+/// Check the jacks once and switch the rules whose state changed
+///
+/// `states` holds the last state of each rule (`None` before the first check).
+pub fn check_jacks(rules: &[JackRule], states: &mut [Option<bool>]) -> Result<(), String> {
+    let devices = pwcli::list_devices()?;
+    for (rule, state) in rules.iter().zip(states.iter_mut()) {
+        let Some(plugged) = read_state(rule, &devices) else {
+            continue;
+        };
+        if *state == Some(plugged) {
+            continue;
+        }
+        *state = Some(plugged);
+        info!(
+            "Jack rule '{}': headphones {}, switching to the {}",
+            rule.name,
+            if plugged { "plugged in" } else { "unplugged" },
+            if plugged { "headphones" } else { "speakers" }
+        );
+        for e in apply(rule, &plan(rule, plugged)) {
+            warn!("Jack rule '{}' failed: {}", rule.name, e);
+        }
+    }
+    Ok(())
+}
+
+/// Start the task that applies the jack rules
+pub fn start_jack_rules(rules: Vec<JackRule>, interval: Duration) -> tokio::task::JoinHandle<()> {
+    let rules = Arc::new(rules);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut states = vec![None; rules.len()];
+        info!("Jack rules started with {} rule(s)", rules.len());
+
+        loop {
+            ticker.tick().await;
+            let rules = rules.clone();
+            let mut current = std::mem::take(&mut states);
+            match crate::worker::run(move || {
+                let result = check_jacks(&rules, &mut current);
+                (current, result)
+            })
+            .await
+            {
+                Ok((current, result)) => {
+                    states = current;
+                    if let Err(e) = result {
+                        warn!("Failed to check jacks: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to check jacks: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(name: &str, available: &str) -> ParamObject {
+        ParamObject {
+            object_type: "Route".to_string(),
+            id: "EnumRoute".to_string(),
+            properties: serde_json::json!({ "name": name, "direction": "Output", "available": available })
+                .as_object()
+                .unwrap()
+                .clone(),
+        }
+    }
+
+    fn rule() -> JackRule {
+        serde_json::from_value(serde_json::json!({
+            "name": "jack",
+            "device": "^alsa_card\\.pci-",
+            "source": { "node.name": "^speakereq2x2\\.output$" },
+            "headphones": { "node.name": "^alsa_output\\.pci-" },
+            "speakers": { "node.name": "^alsa_output\\.usb-" },
+            "mute_speakers": true
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_is_plugged() {
+        let routes = vec![route("analog-output-speaker", "unknown"), route("analog-output-headphones", "yes")];
+        assert_eq!(is_plugged(&routes, "headphones"), Some(true));
+        let routes = vec![route("analog-output-headphones", "no")];
+        assert_eq!(is_plugged(&routes, "headphones"), Some(false));
+        assert_eq!(is_plugged(&routes, "hdmi"), None);
+    }
+
+    #[test]
+    fn test_plan() {
+        let rule = rule();
+        let plugged = plan(&rule, true);
+        assert_eq!(plugged[0], JackAction::Mute { node: rule.speakers.clone(), mute: true });
+        assert_eq!(
+            plugged[1],
+            JackAction::Link { source: rule.source.clone().unwrap(), destination: rule.headphones.clone().unwrap() }
+        );
+        assert_eq!(
+            plugged[2],
+            JackAction::Unlink { source: rule.source.clone().unwrap(), destination: rule.speakers.clone() }
+        );
+
+        let unplugged = plan(&rule, false);
+        assert_eq!(unplugged[0], JackAction::Mute { node: rule.speakers.clone(), mute: false });
+        assert_eq!(
+            unplugged[1],
+            JackAction::Link { source: rule.source.clone().unwrap(), destination: rule.speakers.clone() }
+        );
+        assert_eq!(rule.route, "headphones");
+    }
+}
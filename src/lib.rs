@@ -1,22 +1,93 @@
 pub mod util;
+pub mod logging;
+pub mod shutdown;
+pub mod command;
+pub mod remote;
+pub mod backend;
+pub mod demo;
+pub mod worker;
+pub mod response_cache;
+pub mod rate_limit;
+pub mod auth;
+pub mod pairing;
+pub mod revision;
+pub mod watch;
+pub mod param_monitor;
+pub mod modules;
+pub mod status;
+pub mod diagnostics;
+pub mod session_manager;
+pub mod reverts;
+pub mod state_file;
+pub mod store;
+pub mod scenes;
+pub mod transactions;
+pub mod undo;
+pub mod smoothing;
+pub mod events;
+pub mod webhooks;
+pub mod script_hooks;
+#[cfg(feature = "sqlite")]
+pub mod store_sqlite;
+pub mod riaa_input;
+pub mod zones;
+pub mod combine;
+pub mod stream_output;
+pub mod capture;
+pub mod recording;
+pub mod playback;
+pub mod notify;
+pub mod ducking;
+pub mod stream_stats;
+pub mod pwtop;
+pub mod xrun_stats;
+pub mod dsp_load;
+pub mod metrics_history;
 pub mod parameters;
 pub mod api_server;
 pub mod api;
 pub mod speakereq;
+pub mod speakereq_headroom;
+pub mod speakereq_license;
 pub mod riaa;
 pub mod linker;
 pub mod links;
 pub mod default_link_rules;
 pub mod link_manager_cli;
 pub mod link_scheduler;
+pub mod link_state;
+pub mod startup;
 pub mod config;
+pub mod config_validation;
+pub mod server_config;
 pub mod volume;
+pub mod volume_ops;
 pub mod wpctl;
 pub mod pwcli;
+pub mod pwdump;
 pub mod pwlink;
+pub mod fixtures;
+pub mod link_factory;
 pub mod graph;
+pub mod midi;
+#[cfg(feature = "graph-render")]
+pub mod graph_render;
 pub mod param_rules;
 pub mod settings;
+pub mod spa_props;
+pub mod pod_parser;
+pub mod pwparams;
+pub mod prop_schema;
+pub mod bypass;
+pub mod pwmetadata;
+pub mod bluetooth;
+pub mod bluetooth_rules;
+pub mod failover;
+pub mod jack_rules;
+pub mod power;
+pub mod alsa;
+pub mod route_volume;
+pub mod topology;
 
 pub use parameters::ParameterValue;
 pub use api_server::{AppState, NodeState};
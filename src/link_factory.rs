@@ -0,0 +1,171 @@
+//! Link creation with the link factory of a persistent pw-cli session
+//!
+//! pw-link starts a new process for every link. Instead, one `pw-cli`
+//! process is kept running and links are created with
+//! `create-object link-factory`, which calls `core.create_object` on the
+//! server. The session is a PipeWire client of its own, so links created
+//! with `object.linger = false` belong to it and are destroyed when the API
+//! server exits.
+//!
+//! pw-cli reports errors asynchronously, so a new link is confirmed by
+//! looking it up. If the session cannot be used or the link does not
+//! appear, lingering links are created with pw-link instead. The session is
+//! disabled with `native_links = false` in the `[features]` section.
+
+use std::io::Write;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::pwlink::{self, LinkErrorKind, LinkOptions, PortDirection};
+
+/// Number of lookups before a link counts as not created
+const CONFIRM_ATTEMPTS: u32 = 10;
+
+/// Time between the lookups of a new link
+const CONFIRM_INTERVAL: Duration = Duration::from_millis(50);
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+/// A running pw-cli process reading commands from stdin
+struct Session {
+    child: Child,
+    stdin: ChildStdin,
+}
+
+impl Session {
+    fn start() -> Result<Self, String> {
+        let mut child = Command::new("pw-cli")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start pw-cli: {}", e))?;
+        let stdin = child.stdin.take().ok_or("pw-cli has no stdin")?;
+        debug!("Started pw-cli session for link creation");
+        Ok(Self { child, stdin })
+    }
+
+    fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("Failed to write to pw-cli: {}", e))
+    }
+}
+
+/// Enable or disable link creation through the pw-cli session
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Check whether links are created through the pw-cli session
+///
+/// The session is connected to the default instance, links on other cores
+/// are created with pw-link.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed) && crate::remote::current().is_none()
+}
+
+/// Properties of a new link for the link factory, in the format pw-cli reads
+pub fn factory_props(output_port: u32, input_port: u32, options: &LinkOptions) -> String {
+    format!(
+        "{{ link.output.port = {} link.input.port = {} object.linger = {} link.passive = {} }}",
+        output_port, input_port, options.linger, options.passive
+    )
+}
+
+/// Send a command to the session, starting it if it is not running
+fn send_command(command: &str) -> Result<(), String> {
+    let mut session = SESSION.lock().unwrap();
+    if !session.as_mut().is_some_and(Session::is_running) {
+        *session = Some(Session::start()?);
+    }
+    let result = session.as_mut().map_or(Err("No pw-cli session".to_string()), |s| s.send(command));
+    if result.is_err() {
+        *session = None;
+    }
+    result
+}
+
+/// Get the ID of a port given by ID or as "node_name:port_name"
+fn port_id(port: &str, direction: PortDirection) -> Result<u32, String> {
+    if let Ok(id) = port.parse() {
+        return Ok(id);
+    }
+    pwlink::find_port_exact(direction, port)?
+        .map(|p| p.id)
+        .ok_or_else(|| format!("Port {} not found", port))
+}
+
+fn find_link(output: u32, input: u32) -> Option<u32> {
+    let links = pwlink::list_links().unwrap_or_default();
+    links
+        .iter()
+        .find(|l| l.output_port_id == output && l.input_port_id == input)
+        .map(|l| l.id)
+}
+
+/// Wait until a link between two ports exists and get its ID
+fn wait_for_link(output: u32, input: u32) -> Option<u32> {
+    for _ in 0..CONFIRM_ATTEMPTS {
+        if let Some(id) = find_link(output, input) {
+            return Some(id);
+        }
+        thread::sleep(CONFIRM_INTERVAL);
+    }
+    None
+}
+
+/// Create a link with the link factory and return its ID
+pub fn create_native(output: &str, input: &str, options: &LinkOptions) -> Result<u32, String> {
+    let output = port_id(output, PortDirection::Output)?;
+    let input = port_id(input, PortDirection::Input)?;
+    if let Some(id) = find_link(output, input) {
+        return Err(format!("Link {} between {} and {} already exists", id, output, input));
+    }
+    send_command(&format!("create-object link-factory {}", factory_props(output, input, options)))?;
+    wait_for_link(output, input).ok_or_else(|| format!("Link {} -> {} was not created", output, input))
+}
+
+/// Create a link between two ports (names or IDs)
+///
+/// Uses the pw-cli session if enabled, falling back to pw-link for
+/// lingering links unless the ports are missing or already linked.
+/// Returns the link ID if it could be found.
+pub fn create_link(output: &str, input: &str, options: &LinkOptions) -> Result<Option<u32>, String> {
+    if is_enabled() {
+        match create_native(output, input, options) {
+            Ok(id) => return Ok(Some(id)),
+            Err(e) if options.linger && pwlink::error_kind(&e) == LinkErrorKind::Other => {
+                warn!("Native link creation failed, using pw-link: {}", e)
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    pwlink::create_link_with_options(output, input, options)?;
+    Ok(pwlink::find_link(output, input).ok().flatten().map(|l| l.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_factory_props() {
+        assert_eq!(
+            factory_props(67, 89, &LinkOptions::default()),
+            "{ link.output.port = 67 link.input.port = 89 object.linger = true link.passive = false }"
+        );
+        let options = LinkOptions { passive: true, linger: false };
+        assert!(factory_props(67, 89, &options).ends_with("object.linger = false link.passive = true }"));
+    }
+}
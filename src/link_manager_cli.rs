@@ -3,6 +3,8 @@
 //! This module provides link rule management using command-line tools
 //! instead of the native PipeWire API for simplicity and reliability.
 
+use serde::Serialize;
+
 use crate::linker::{LinkRule, LinkType, NodeIdentifier};
 use crate::util::regex_match;
 use crate::pwcli::{self, PwObject};
@@ -87,6 +89,19 @@ impl PortInfo {
             channel: obj.get("audio.channel").map(|s| s.to_string()),
         })
     }
+
+    /// Port of a node in tests, named after the IDs
+    #[cfg(test)]
+    pub fn new(id: u32, node_id: u32, direction: PortDirection) -> Self {
+        PortInfo {
+            id,
+            node_id,
+            name: format!("port{}", id),
+            full_name: format!("node{}:port{}", node_id, id),
+            direction,
+            channel: None,
+        }
+    }
 }
 
 /// Information about an existing link
@@ -100,6 +115,18 @@ pub struct LinkInfo {
 }
 
 impl LinkInfo {
+    /// Link between two ports in tests, named after the port IDs
+    #[cfg(test)]
+    pub fn new(id: u32, output_port_id: u32, input_port_id: u32) -> Self {
+        LinkInfo {
+            id,
+            output_port_id,
+            input_port_id,
+            output_port_name: format!("out{}", output_port_id),
+            input_port_name: format!("in{}", input_port_id),
+        }
+    }
+
     /// Create from a pwlink::PwLink
     pub fn from_pw_link(link: &pwlink::PwLink) -> Self {
         LinkInfo {
@@ -145,6 +172,8 @@ fn matches_identifier(node: &NodeInfo, identifier: &NodeIdentifier) -> bool {
 pub struct LinkData {
     pub nodes: Vec<NodeInfo>,
     pub ports: Vec<PortInfo>,
+    /// Monitor output ports of sinks, only linked by rules with `include_monitors`
+    pub monitor_ports: Vec<PortInfo>,
     pub links: Vec<LinkInfo>,
 }
 
@@ -165,7 +194,7 @@ impl LinkData {
             .filter(|o| o.object_type == "Node")
             .collect();
         
-        let ports: Vec<PortInfo> = all_objects.iter()
+        let (monitor_ports, ports): (Vec<_>, Vec<_>) = all_objects.iter()
             .filter(|o| o.object_type == "Port")
             .filter_map(|o| {
                 let node_id: u32 = o.get("node.id")?.parse().ok()?;
@@ -181,21 +210,21 @@ impl LinkData {
                     _ => return None,
                 };
                 
-                // Skip monitor ports (they're output copies)
-                if o.get("port.monitor").map(|v| v == "true").unwrap_or(false) {
-                    return None;
-                }
+                // Monitor ports (output copies of sinks) are kept apart
+                let monitor = o.get("port.monitor").map(|v| v == "true").unwrap_or(false);
                 
-                Some(PortInfo {
+                Some((monitor, PortInfo {
                     id: o.id,
                     node_id,
                     name: port_name.clone(),
                     full_name: format!("{}:{}", node_name, port_name),
                     direction,
                     channel: o.get("audio.channel").map(|s| s.to_string()),
-                })
+                }))
             })
-            .collect();
+            .partition(|(monitor, _)| *monitor);
+        let monitor_ports = monitor_ports.into_iter().map(|(_, p)| p).collect();
+        let ports = ports.into_iter().map(|(_, p)| p).collect();
         
         // Load existing links
         let pw_links = pwlink::list_links()?;
@@ -203,7 +232,7 @@ impl LinkData {
             .map(LinkInfo::from_pw_link)
             .collect();
         
-        Ok(LinkData { nodes, ports, links })
+        Ok(LinkData { nodes, ports, monitor_ports, links })
     }
     
     /// Find nodes matching an identifier
@@ -220,6 +249,13 @@ impl LinkData {
             .collect()
     }
     
+    /// Get monitor ports for a node
+    pub fn get_monitor_ports(&self, node_id: u32) -> Vec<&PortInfo> {
+        self.monitor_ports.iter()
+            .filter(|p| p.node_id == node_id)
+            .collect()
+    }
+    
     /// Get input ports for a node
     pub fn get_input_ports(&self, node_id: u32) -> Vec<&PortInfo> {
         self.ports.iter()
@@ -241,6 +277,19 @@ impl LinkData {
         )
     }
     
+    /// Links into the input ports of a node that do not come from one of the given nodes
+    pub fn foreign_links_into(&self, node_id: u32, allowed_sources: &[u32]) -> Vec<&LinkInfo> {
+        let inputs: Vec<u32> = self.get_input_ports(node_id).iter().map(|p| p.id).collect();
+        self.links
+            .iter()
+            .filter(|l| inputs.contains(&l.input_port_id))
+            .filter(|l| {
+                let source_node = self.ports.iter().find(|p| p.id == l.output_port_id).map(|p| p.node_id);
+                !source_node.map(|id| allowed_sources.contains(&id)).unwrap_or(false)
+            })
+            .collect()
+    }
+
     /// Find link ID for a connection
     pub fn find_link_id(&self, output_name: &str, input_name: &str) -> Option<u32> {
         self.links.iter()
@@ -249,6 +298,189 @@ impl LinkData {
     }
 }
 
+/// Check whether a port matches a channel map key or value (audio.channel or port name)
+fn port_matches_channel(port: &PortInfo, channel: &str) -> bool {
+    port.channel.as_deref() == Some(channel) || port.name == channel
+}
+
+/// Check whether a port is selected by a port filter (regex on port name or audio.channel)
+pub fn port_selected(port: &PortInfo, filter: Option<&str>) -> bool {
+    match filter {
+        Some(pattern) => {
+            regex_match(pattern, &port.name)
+                || port.channel.as_deref().map(|c| regex_match(pattern, c)).unwrap_or(false)
+        }
+        None => true,
+    }
+}
+
+/// Get the output ports of the source and input ports of the destination selected by a rule
+pub fn rule_ports<'a>(
+    rule: &LinkRule,
+    data: &'a LinkData,
+    source_id: u32,
+    dest_id: u32,
+) -> (Vec<&'a PortInfo>, Vec<&'a PortInfo>) {
+    let mut outputs = data.get_output_ports(source_id);
+    if rule.include_monitors {
+        outputs.extend(data.get_monitor_ports(source_id));
+    }
+    let outputs = outputs
+        .into_iter()
+        .filter(|p| port_selected(p, rule.source_ports.as_deref()))
+        .collect();
+    let inputs = data.get_input_ports(dest_id)
+        .into_iter()
+        .filter(|p| port_selected(p, rule.destination_ports.as_deref()))
+        .collect();
+    (outputs, inputs)
+}
+
+/// Pair ports in ID order
+fn sorted_pairs<'a>(mut outputs: Vec<&'a PortInfo>, mut inputs: Vec<&'a PortInfo>) -> Vec<(&'a PortInfo, &'a PortInfo)> {
+    outputs.sort_by_key(|p| p.id);
+    inputs.sort_by_key(|p| p.id);
+    outputs.into_iter().zip(inputs).collect()
+}
+
+/// Pair the output ports of a source with the input ports of a destination
+///
+/// With a `channel_map`, each entry pairs the source port with that channel
+/// (or port name) with the matching destination port. Otherwise ports are
+/// paired in ID order, which requires equal port counts.
+pub fn pair_ports<'a>(
+    rule: &LinkRule,
+    outputs: Vec<&'a PortInfo>,
+    inputs: Vec<&'a PortInfo>,
+) -> Result<Vec<(&'a PortInfo, &'a PortInfo)>, String> {
+    if let Some(ref channel_map) = rule.channel_map {
+        let mut pairs = Vec::new();
+        for (from, to) in channel_map {
+            let output = outputs.iter().find(|p| port_matches_channel(p, from));
+            let input = inputs.iter().find(|p| port_matches_channel(p, to));
+            match (output, input) {
+                (Some(output), Some(input)) => pairs.push((*output, *input)),
+                (None, _) => return Err(format!("No output port for channel '{}'", from)),
+                (_, None) => return Err(format!("No input port for channel '{}'", to)),
+            }
+        }
+        return Ok(pairs);
+    }
+
+    if outputs.len() != inputs.len() {
+        return Err(format!(
+            "Port count mismatch ({} output ports vs {} input ports)",
+            outputs.len(), inputs.len()
+        ));
+    }
+    if outputs.is_empty() {
+        return Err("No ports found to link".to_string());
+    }
+    Ok(sorted_pairs(outputs, inputs))
+}
+
+/// Port pairs (output, input full names) a link rule connects in the current graph
+pub fn rule_port_pairs(rule: &LinkRule, data: &LinkData) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    for source in data.find_matching_nodes(&rule.source) {
+        for dest in data.find_matching_nodes(&rule.destination) {
+            let (outputs, inputs) = rule_ports(rule, data, source.id, dest.id);
+            if let Ok(ports) = pair_ports(rule, outputs, inputs) {
+                pairs.extend(ports.into_iter().map(|(o, i)| (o.full_name.clone(), i.full_name.clone())));
+            }
+        }
+    }
+    pairs
+}
+
+/// A port pair of a rule with its current link state
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RulePortPair {
+    pub output: String,
+    pub input: String,
+    pub linked: bool,
+}
+
+/// Nodes and ports a rule matches in the current graph
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleMatch {
+    pub rule: String,
+    pub sources: Vec<String>,
+    pub destinations: Vec<String>,
+    pub pairs: Vec<RulePortPair>,
+    /// Source/destination combinations whose ports could not be paired
+    pub errors: Vec<String>,
+}
+
+/// Show which nodes and port pairs a rule matches, without changing anything
+///
+/// Ports are paired the same way [`apply_link_rule`] pairs them.
+pub fn match_link_rule(rule: &LinkRule, data: &LinkData) -> RuleMatch {
+    let sources = data.find_matching_nodes(&rule.source);
+    let destinations = data.find_matching_nodes(&rule.destination);
+
+    let mut pairs = Vec::new();
+    let mut errors = Vec::new();
+    for source in &sources {
+        for dest in &destinations {
+            let (outputs, inputs) = rule_ports(rule, data, source.id, dest.id);
+            // Unlink rules without a channel map pair ports even if counts differ
+            let result = match rule.link_type {
+                LinkType::Unlink if rule.channel_map.is_none() => Ok(sorted_pairs(outputs, inputs)),
+                _ => pair_ports(rule, outputs, inputs),
+            };
+            match result {
+                Ok(ports) => pairs.extend(ports.into_iter().map(|(o, i)| RulePortPair {
+                    output: o.full_name.clone(),
+                    input: i.full_name.clone(),
+                    linked: data.link_exists(&o.full_name, &i.full_name),
+                })),
+                Err(e) => errors.push(format!("{} for {} -> {}", e, source.display_name(), dest.display_name())),
+            }
+        }
+    }
+
+    RuleMatch {
+        rule: rule.name.clone(),
+        sources: sources.iter().map(|n| n.display_name()).collect(),
+        destinations: destinations.iter().map(|n| n.display_name()).collect(),
+        pairs,
+        errors,
+    }
+}
+
+/// Undo a link rule: remove the links a link rule creates, or recreate the
+/// links an unlink rule removes
+///
+/// Links removed because of `exclusive` are not restored.
+pub fn revert_link_rule(rule: &LinkRule) -> Result<Vec<LinkRuleResult>, String> {
+    let data = LinkData::load()?;
+    let matched = match_link_rule(rule, &data);
+
+    let mut results = Vec::new();
+    for pair in matched.pairs {
+        let result = match rule.link_type {
+            LinkType::Link if pair.linked => match data.find_link_id(&pair.output, &pair.input) {
+                Some(link_id) => pwlink::remove_link(link_id)
+                    .map(|_| format!("Removed link: {} -> {}", pair.output, pair.input)),
+                None => continue,
+            },
+            LinkType::Unlink if !pair.linked => pwlink::create_link(&pair.output, &pair.input)
+                .map(|_| format!("Created link: {} -> {}", pair.output, pair.input)),
+            _ => continue,
+        };
+        results.push(match result {
+            Ok(message) => LinkRuleResult { success: true, message },
+            Err(e) => LinkRuleResult {
+                success: false,
+                message: format!("Failed to revert {} -> {}: {}", pair.output, pair.input, e),
+            },
+        });
+    }
+
+    Ok(results)
+}
+
 /// Apply a link rule and return results
 pub fn apply_link_rule(rule: &LinkRule) -> Result<Vec<LinkRuleResult>, String> {
     let mut results = Vec::new();
@@ -272,41 +504,20 @@ pub fn apply_link_rule(rule: &LinkRule) -> Result<Vec<LinkRuleResult>, String> {
         LinkType::Link => {
             for source in &sources {
                 for dest in &destinations {
-                    let source_outputs = data.get_output_ports(source.id);
-                    let dest_inputs = data.get_input_ports(dest.id);
-                    
-                    // Check port counts match
-                    if source_outputs.len() != dest_inputs.len() {
-                        results.push(LinkRuleResult {
-                            success: false,
-                            message: format!(
-                                "Port count mismatch for {} -> {}: {} output ports vs {} input ports",
-                                source.display_name(), dest.display_name(),
-                                source_outputs.len(), dest_inputs.len()
-                            ),
-                        });
-                        continue;
-                    }
-                    
-                    if source_outputs.is_empty() {
-                        results.push(LinkRuleResult {
-                            success: false,
-                            message: format!(
-                                "No ports found to link {} -> {}",
-                                source.display_name(), dest.display_name()
-                            ),
-                        });
-                        continue;
-                    }
-                    
-                    // Sort ports by ID for consistent ordering
-                    let mut source_outputs: Vec<_> = source_outputs;
-                    let mut dest_inputs: Vec<_> = dest_inputs;
-                    source_outputs.sort_by_key(|p| p.id);
-                    dest_inputs.sort_by_key(|p| p.id);
-                    
+                    let (source_outputs, dest_inputs) = rule_ports(rule, &data, source.id, dest.id);
+                    let pairs = match pair_ports(rule, source_outputs, dest_inputs) {
+                        Ok(pairs) => pairs,
+                        Err(e) => {
+                            results.push(LinkRuleResult {
+                                success: false,
+                                message: format!("{} for {} -> {}", e, source.display_name(), dest.display_name()),
+                            });
+                            continue;
+                        }
+                    };
+
                     // Create links for each port pair
-                    for (src_port, dst_port) in source_outputs.iter().zip(dest_inputs.iter()) {
+                    for (src_port, dst_port) in pairs {
                         // Check if link already exists
                         if data.link_exists(&src_port.full_name, &dst_port.full_name) {
                             results.push(LinkRuleResult {
@@ -343,21 +554,60 @@ pub fn apply_link_rule(rule: &LinkRule) -> Result<Vec<LinkRuleResult>, String> {
                     }
                 }
             }
+
+            if rule.exclusive {
+                let source_ids: Vec<u32> = sources.iter().map(|n| n.id).collect();
+                for dest in &destinations {
+                    let selected = |link: &&LinkInfo| {
+                        data.ports.iter()
+                            .find(|p| p.id == link.input_port_id)
+                            .map(|p| port_selected(p, rule.destination_ports.as_deref()))
+                            .unwrap_or(false)
+                    };
+                    for link in data.foreign_links_into(dest.id, &source_ids).into_iter().filter(selected) {
+                        match pwlink::remove_link(link.id) {
+                            Ok(()) => results.push(LinkRuleResult {
+                                success: true,
+                                message: format!(
+                                    "Removed foreign link: {} -> {}",
+                                    link.output_port_name, link.input_port_name
+                                ),
+                            }),
+                            Err(e) => results.push(LinkRuleResult {
+                                success: false,
+                                message: format!(
+                                    "Failed to remove foreign link {} -> {}: {}",
+                                    link.output_port_name, link.input_port_name, e
+                                ),
+                            }),
+                        }
+                    }
+                }
+            }
         }
         LinkType::Unlink => {
             for source in &sources {
                 for dest in &destinations {
-                    let source_outputs = data.get_output_ports(source.id);
-                    let dest_inputs = data.get_input_ports(dest.id);
-                    
-                    // Sort ports for consistent ordering
-                    let mut source_outputs: Vec<_> = source_outputs;
-                    let mut dest_inputs: Vec<_> = dest_inputs;
-                    source_outputs.sort_by_key(|p| p.id);
-                    dest_inputs.sort_by_key(|p| p.id);
-                    
+                    let (source_outputs, dest_inputs) = rule_ports(rule, &data, source.id, dest.id);
+
+                    // Without a channel map, pair ports in ID order even if counts differ
+                    let pairs = if rule.channel_map.is_some() {
+                        match pair_ports(rule, source_outputs, dest_inputs) {
+                            Ok(pairs) => pairs,
+                            Err(e) => {
+                                results.push(LinkRuleResult {
+                                    success: false,
+                                    message: format!("{} for {} -> {}", e, source.display_name(), dest.display_name()),
+                                });
+                                continue;
+                            }
+                        }
+                    } else {
+                        sorted_pairs(source_outputs, dest_inputs)
+                    };
+
                     // Remove links for each port pair
-                    for (src_port, dst_port) in source_outputs.iter().zip(dest_inputs.iter()) {
+                    for (src_port, dst_port) in pairs {
                         // Find the link
                         if let Some(link_id) = data.find_link_id(&src_port.full_name, &dst_port.full_name) {
                             match pwlink::remove_link(link_id) {
@@ -422,6 +672,7 @@ pub fn list_links() -> Result<Vec<LinkInfo>, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BTreeMap;
     
     #[test]
     fn test_matches_identifier() {
@@ -456,4 +707,144 @@ mod tests {
         };
         assert!(!matches_identifier(&node, &id3));
     }
+
+    #[test]
+    fn test_foreign_links_into() {
+        // Node 1 is the wanted source, node 2 another source, node 3 the destination
+        let data = LinkData {
+            nodes: Vec::new(),
+            monitor_ports: Vec::new(),
+            ports: vec![
+                PortInfo::new(10, 1, PortDirection::Output),
+                PortInfo::new(20, 2, PortDirection::Output),
+                PortInfo::new(30, 3, PortDirection::Input),
+                PortInfo::new(31, 3, PortDirection::Input),
+            ],
+            links: vec![LinkInfo::new(100, 10, 30), LinkInfo::new(101, 20, 31), LinkInfo::new(102, 20, 99)],
+        };
+
+        let foreign: Vec<u32> = data.foreign_links_into(3, &[1]).iter().map(|l| l.id).collect();
+        assert_eq!(foreign, vec![101]);
+    }
+
+    fn rule_with_map(map: Option<&[(&str, &str)]>) -> LinkRule {
+        serde_json::from_value(serde_json::json!({
+            "name": "test",
+            "source": {"node.name": "a"},
+            "destination": {"node.name": "b"},
+            "type": "link",
+            "channel_map": map.map(|m| m.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<BTreeMap<_, _>>()),
+        }))
+        .unwrap()
+    }
+
+    fn channel_port(id: u32, name: &str, channel: &str, direction: PortDirection) -> PortInfo {
+        PortInfo {
+            channel: Some(channel.to_string()),
+            name: name.to_string(),
+            ..PortInfo::new(id, 1, direction)
+        }
+    }
+
+    #[test]
+    fn test_pair_ports_channel_map() {
+        let out_l = channel_port(10, "output_FL", "FL", PortDirection::Output);
+        let out_r = channel_port(11, "output_FR", "FR", PortDirection::Output);
+        // Destination ports with reversed ID order
+        let in_r = channel_port(20, "playback_FR", "FR", PortDirection::Input);
+        let in_l = channel_port(21, "playback_FL", "FL", PortDirection::Input);
+
+        // ID order pairs FL with FR
+        let pairs = pair_ports(&rule_with_map(None), vec![&out_l, &out_r], vec![&in_r, &in_l]).unwrap();
+        assert_eq!(pairs[0].1.name, "playback_FR");
+
+        let rule = rule_with_map(Some(&[("FL", "playback_FL"), ("FR", "FR")]));
+        let pairs = pair_ports(&rule, vec![&out_l, &out_r], vec![&in_r, &in_l]).unwrap();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!((pairs[0].0.id, pairs[0].1.id), (10, 21));
+        assert_eq!((pairs[1].0.id, pairs[1].1.id), (11, 20));
+
+        let rule = rule_with_map(Some(&[("RL", "FL")]));
+        assert!(pair_ports(&rule, vec![&out_l], vec![&in_l]).is_err());
+    }
+
+    #[test]
+    fn test_pair_ports_count_mismatch() {
+        let out_l = channel_port(10, "output_FL", "FL", PortDirection::Output);
+        let in_l = channel_port(21, "playback_FL", "FL", PortDirection::Input);
+        let in_r = channel_port(20, "playback_FR", "FR", PortDirection::Input);
+        assert!(pair_ports(&rule_with_map(None), vec![&out_l], vec![&in_l, &in_r]).is_err());
+    }
+
+    #[test]
+    fn test_match_link_rule() {
+        let node = |id: u32, name: &str| NodeInfo {
+            id,
+            node_name: Some(name.to_string()),
+            node_nick: None,
+            object_path: None,
+        };
+        let data = LinkData {
+            nodes: vec![node(1, "a"), node(2, "b")],
+            monitor_ports: Vec::new(),
+            ports: vec![
+                PortInfo::new(10, 1, PortDirection::Output),
+                PortInfo::new(11, 1, PortDirection::Output),
+                PortInfo::new(20, 2, PortDirection::Input),
+                PortInfo::new(21, 2, PortDirection::Input),
+            ],
+            links: vec![LinkInfo {
+                output_port_name: "node1:port10".to_string(),
+                input_port_name: "node2:port20".to_string(),
+                ..LinkInfo::new(100, 10, 20)
+            }],
+        };
+
+        let matched = match_link_rule(&rule_with_map(None), &data);
+        assert_eq!(matched.sources, vec!["a"]);
+        assert_eq!(matched.destinations, vec!["b"]);
+        assert!(matched.errors.is_empty());
+        assert_eq!(
+            matched.pairs,
+            vec![
+                RulePortPair { output: "node1:port10".to_string(), input: "node2:port20".to_string(), linked: true },
+                RulePortPair { output: "node1:port11".to_string(), input: "node2:port21".to_string(), linked: false },
+            ]
+        );
+
+        let data = LinkData { ports: data.ports[..3].to_vec(), ..data };
+        let matched = match_link_rule(&rule_with_map(None), &data);
+        assert!(matched.pairs.is_empty());
+        assert!(matched.errors[0].starts_with("Port count mismatch"));
+    }
+
+    #[test]
+    fn test_rule_ports_include_monitors() {
+        let data = LinkData {
+            nodes: Vec::new(),
+            ports: vec![PortInfo::new(10, 1, PortDirection::Output), PortInfo::new(20, 2, PortDirection::Input)],
+            monitor_ports: vec![PortInfo::new(11, 1, PortDirection::Output)],
+            links: Vec::new(),
+        };
+        let mut rule = rule_with_map(None);
+        let (outputs, _) = rule_ports(&rule, &data, 1, 2);
+        assert_eq!(outputs.iter().map(|p| p.id).collect::<Vec<_>>(), vec![10]);
+
+        rule.include_monitors = true;
+        let (outputs, inputs) = rule_ports(&rule, &data, 1, 2);
+        assert_eq!(outputs.iter().map(|p| p.id).collect::<Vec<_>>(), vec![10, 11]);
+        assert_eq!(inputs.len(), 1);
+    }
+
+    #[test]
+    fn test_port_selected() {
+        let monitor = channel_port(10, "monitor_FL", "FL", PortDirection::Output);
+        let aux = channel_port(11, "playback_AUX2", "AUX2", PortDirection::Input);
+
+        assert!(port_selected(&monitor, None));
+        assert!(port_selected(&monitor, Some("^monitor_")));
+        assert!(!port_selected(&monitor, Some("^output_")));
+        assert!(port_selected(&aux, Some("^AUX[23]$")));
+        assert!(!port_selected(&aux, Some("^AUX[01]$")));
+    }
 }
@@ -5,7 +5,7 @@ use tracing::{debug, error, info, warn};
 
 use crate::api_server::AppState;
 use crate::link_manager_cli;
-use crate::linker::LogLevel;
+use crate::linker::{LinkRule, LogLevel};
 
 /// Log a message at the specified level
 macro_rules! log_at_level {
@@ -130,9 +130,9 @@ pub fn start_link_scheduler(state: Arc<AppState>) -> tokio::task::JoinHandle<()>
 async fn apply_rule_safe(
     rule: &crate::linker::LinkRule,
 ) -> anyhow::Result<Vec<link_manager_cli::LinkRuleResult>> {
-    // Run the blocking operations in a blocking task
+    // Run the blocking operations on the PipeWire worker pool
     let rule = rule.clone();
-    let result = tokio::task::spawn_blocking(move || {
+    let result = crate::worker::run(move || {
         link_manager_cli::apply_link_rule(&rule)
     })
     .await?;
@@ -153,73 +153,82 @@ pub async fn apply_startup_rules(state: Arc<AppState>) {
             continue;
         }
 
-        debug!("Applying startup rule '{}'", rule.name);
-        match apply_rule_safe(rule).await {
-            Ok(results) => {
-                let success_count = results.iter().filter(|r| r.success).count();
-                let failed_count = results.iter().filter(|r| !r.success).count();
-                let total = results.len();
-
-                if total > 0 {
-                    info!(
-                        "Startup rule '{}' applied: {}/{} links successful",
-                        rule.name, success_count, total
-                    );
-                }
+        apply_startup_rule(&state, idx, rule).await;
+    }
+}
 
-                let error_msg = if failed_count > 0 {
-                    let errors: Vec<String> = results.iter()
-                        .filter(|r| !r.success)
-                        .map(|r| r.message.clone())
-                        .collect();
-                    Some(errors.join("; "))
-                } else {
-                    None
-                };
+/// Apply a single startup rule and record its status
+///
+/// Returns the error message if the rule failed.
+pub async fn apply_startup_rule(state: &AppState, idx: usize, rule: &LinkRule) -> Option<String> {
+    debug!("Applying startup rule '{}'", rule.name);
+    match apply_rule_safe(rule).await {
+        Ok(results) => {
+            let success_count = results.iter().filter(|r| r.success).count();
+            let failed_count = results.iter().filter(|r| !r.success).count();
+            let total = results.len();
+
+            if total > 0 {
+                info!(
+                    "Startup rule '{}' applied: {}/{} links successful",
+                    rule.name, success_count, total
+                );
+            }
 
-                // Update rule status
-                state.update_rule_status(idx, success_count, failed_count, error_msg.clone());
-
-                // Log results using appropriate log levels
-                for result in results {
-                    if result.success {
-                        log_at_level!(
-                            &rule.info_level,
-                            "  ✓ {}",
-                            result.message
-                        );
-                    } else {
-                        log_at_level!(
-                            &rule.error_level,
-                            "  ✗ {}",
-                            result.message
-                        );
-                    }
-                }
-                
-                // Also log a summary if there were failures
-                if failed_count > 0 {
-                    if let Some(ref err_msg) = error_msg {
-                        log_at_level!(
-                            &rule.error_level,
-                            "Startup rule '{}' had {} failure(s): {}",
-                            rule.name,
-                            failed_count,
-                            err_msg
-                        );
-                    }
+            let error_msg = if failed_count > 0 {
+                let errors: Vec<String> = results.iter()
+                    .filter(|r| !r.success)
+                    .map(|r| r.message.clone())
+                    .collect();
+                Some(errors.join("; "))
+            } else {
+                None
+            };
+
+            // Update rule status
+            state.update_rule_status(idx, success_count, failed_count, error_msg.clone());
+
+            // Log results using appropriate log levels
+            for result in results {
+                if result.success {
+                    log_at_level!(
+                        &rule.info_level,
+                        "  ✓ {}",
+                        result.message
+                    );
+                } else {
+                    log_at_level!(
+                        &rule.error_level,
+                        "  ✗ {}",
+                        result.message
+                    );
                 }
             }
-            Err(e) => {
-                log_at_level!(
-                    &rule.error_level,
-                    "Failed to apply startup rule '{}': {}",
-                    rule.name,
-                    e
-                );
-                // Update status with error
-                state.update_rule_status(idx, 0, 0, Some(e.to_string()));
+            
+            // Also log a summary if there were failures
+            if failed_count > 0 {
+                if let Some(ref err_msg) = error_msg {
+                    log_at_level!(
+                        &rule.error_level,
+                        "Startup rule '{}' had {} failure(s): {}",
+                        rule.name,
+                        failed_count,
+                        err_msg
+                    );
+                }
             }
+            error_msg
+        }
+        Err(e) => {
+            log_at_level!(
+                &rule.error_level,
+                "Failed to apply startup rule '{}': {}",
+                rule.name,
+                e
+            );
+            // Update status with error
+            state.update_rule_status(idx, 0, 0, Some(e.to_string()));
+            Some(e.to_string())
         }
     }
 }
@@ -0,0 +1,176 @@
+//! Saving and restoring manual links
+//!
+//! Links created by hand (e.g. via `POST /api/v1/links`) are lost when
+//! PipeWire restarts. The current links that are not managed by a link rule
+//! can be saved to `~/.state/pipewire-api/links.state` and are restored at
+//! startup, like volumes in `volume.state`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+use crate::link_manager_cli::{self, LinkData};
+use crate::linker::{LinkRule, LinkType};
+use crate::pwlink;
+
+/// A saved link between two ports ("node_name:port_name")
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct SavedLink {
+    pub output: String,
+    pub input: String,
+}
+
+/// Result of restoring saved links
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreResult {
+    /// Links that were created
+    pub restored: Vec<SavedLink>,
+    /// Links that already existed
+    pub existing: usize,
+    /// Links whose ports do not exist (node not present)
+    pub missing: Vec<SavedLink>,
+    /// Links that could not be created, with the error
+    pub failed: Vec<String>,
+}
+
+/// Get the current links that are not created by one of the link rules
+pub fn manual_links(data: &LinkData, rules: &[LinkRule]) -> Vec<SavedLink> {
+    let rule_links: HashSet<(String, String)> = rules
+        .iter()
+        .filter(|r| matches!(r.link_type, LinkType::Link))
+        .flat_map(|r| link_manager_cli::rule_port_pairs(r, data))
+        .collect();
+
+    let mut links: Vec<SavedLink> = data
+        .links
+        .iter()
+        .filter(|l| !rule_links.contains(&(l.output_port_name.clone(), l.input_port_name.clone())))
+        .map(|l| SavedLink {
+            output: l.output_port_name.clone(),
+            input: l.input_port_name.clone(),
+        })
+        .collect();
+    links.sort_by(|a, b| (&a.output, &a.input).cmp(&(&b.output, &b.input)));
+    links.dedup();
+    links
+}
+
+/// Save the current manual links to the link state file
+pub fn save_links(rules: &[LinkRule]) -> Result<Vec<SavedLink>, String> {
+    let data = LinkData::load()?;
+    let links = manual_links(&data, rules);
+    crate::config::save_link_state(&links).map_err(|e| e.to_string())?;
+    Ok(links)
+}
+
+/// Create the given links if their ports exist
+pub fn restore_links(links: &[SavedLink]) -> Result<RestoreResult, String> {
+    let data = LinkData::load()?;
+    let ports: HashSet<&str> = data.ports.iter().map(|p| p.full_name.as_str()).collect();
+    let mut result = RestoreResult::default();
+
+    for link in links {
+        if data.link_exists(&link.output, &link.input) {
+            result.existing += 1;
+        } else if !ports.contains(link.output.as_str()) || !ports.contains(link.input.as_str()) {
+            result.missing.push(link.clone());
+        } else {
+            match pwlink::create_link(&link.output, &link.input) {
+                Ok(()) => result.restored.push(link.clone()),
+                Err(e) => result.failed.push(format!("{} -> {}: {}", link.output, link.input, e)),
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Restore the links from the link state file
+pub fn restore_saved_links() -> Result<RestoreResult, String> {
+    let links = crate::config::load_link_state();
+    if links.is_empty() {
+        return Ok(RestoreResult::default());
+    }
+
+    let result = restore_links(&links)?;
+    info!(
+        "Restored {} saved link(s), {} already present, {} missing",
+        result.restored.len(),
+        result.existing,
+        result.missing.len()
+    );
+    for failure in &result.failed {
+        warn!("Failed to restore link {}", failure);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link_manager_cli::{LinkInfo, NodeInfo, PortDirection, PortInfo};
+
+    fn node(id: u32, name: &str) -> NodeInfo {
+        NodeInfo {
+            id,
+            node_name: Some(name.to_string()),
+            node_nick: None,
+            object_path: None,
+        }
+    }
+
+    fn port(id: u32, node_id: u32, full_name: &str, direction: PortDirection) -> PortInfo {
+        PortInfo {
+            id,
+            node_id,
+            name: full_name.split(':').nth(1).unwrap().to_string(),
+            full_name: full_name.to_string(),
+            direction,
+            channel: None,
+        }
+    }
+
+    fn link(id: u32, output: (u32, &str), input: (u32, &str)) -> LinkInfo {
+        LinkInfo {
+            id,
+            output_port_id: output.0,
+            input_port_id: input.0,
+            output_port_name: output.1.to_string(),
+            input_port_name: input.1.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_manual_links_exclude_rule_links() {
+        let data = LinkData {
+            nodes: vec![node(1, "eq"), node(2, "dac"), node(3, "player")],
+            monitor_ports: Vec::new(),
+            ports: vec![
+                port(10, 1, "eq:out", PortDirection::Output),
+                port(20, 2, "dac:in", PortDirection::Input),
+                port(30, 3, "player:out", PortDirection::Output),
+                port(11, 1, "eq:in", PortDirection::Input),
+            ],
+            links: vec![
+                link(100, (10, "eq:out"), (20, "dac:in")),
+                link(101, (30, "player:out"), (11, "eq:in")),
+            ],
+        };
+        let rule: LinkRule = serde_json::from_value(serde_json::json!({
+            "name": "eq to dac",
+            "source": {"node.name": "^eq$"},
+            "destination": {"node.name": "^dac$"},
+            "type": "link"
+        }))
+        .unwrap();
+
+        let links = manual_links(&data, &[rule]);
+        assert_eq!(
+            links,
+            vec![SavedLink {
+                output: "player:out".to_string(),
+                input: "eq:in".to_string()
+            }]
+        );
+        assert_eq!(manual_links(&data, &[]).len(), 2);
+    }
+}
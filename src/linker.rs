@@ -1,22 +1,19 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::startup::WaitFor;
 use crate::util::regex_match;
 
 /// Log level for rule execution messages
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
     Debug,
+    #[default]
     Info,
     Warn,
     Error,
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {
-        LogLevel::Info
-    }
-}
-
 /// Default log level for errors
 fn default_error_level() -> LogLevel {
     LogLevel::Error
@@ -31,7 +28,7 @@ pub enum LinkType {
 }
 
 /// Node identifier - can use node.name, node.nick, or object.path with wildcard support
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NodeIdentifier {
     #[serde(rename = "node.name")]
     pub node_name: Option<String>,
@@ -93,6 +90,28 @@ pub struct LinkRule {
     /// Log level for errors (node not found, can't create link, etc.) - default: error
     #[serde(default = "default_error_level")]
     pub error_level: LogLevel,
+    /// Node to wait for before applying the rule at startup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for: Option<WaitFor>,
+    /// Remove all other links into the destination ports (default: false)
+    #[serde(default)]
+    pub exclusive: bool,
+    /// Explicit port pairing: source channel -> destination channel
+    ///
+    /// Keys and values match `audio.channel` or the port name, e.g.
+    /// `{"FL": "playback_FL", "FR": "playback_FR"}`. Without a map, ports are
+    /// paired in ID order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_map: Option<BTreeMap<String, String>>,
+    /// Regex selecting the source output ports to link (port name or audio.channel)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_ports: Option<String>,
+    /// Regex selecting the destination input ports to link (port name or audio.channel)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_ports: Option<String>,
+    /// Also link the monitor ports of a source sink (default: false)
+    #[serde(default)]
+    pub include_monitors: bool,
 }
 
 fn default_link_at_startup() -> bool {
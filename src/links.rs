@@ -11,6 +11,7 @@ use tracing::{debug, error, info};
 use crate::api_server::{ApiError, AppState};
 use crate::linker::LinkRule;
 use crate::link_manager_cli;
+use crate::link_state::{self, RestoreResult, SavedLink};
 
 /// Create the router for link management endpoints
 pub fn create_router(state: Arc<AppState>) -> Router {
@@ -21,6 +22,8 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/api/v1/links/default", get(get_default_rules))
         .route("/api/v1/links/apply-defaults", post(apply_default_rules))
         .route("/api/v1/links/status", get(get_link_rules_status))
+        .route("/api/v1/links/save", post(save_links))
+        .route("/api/v1/links/restore", post(restore_links))
         .with_state(state)
 }
 
@@ -64,11 +67,11 @@ pub async fn apply_link_rule(
 
     // Apply the rule using CLI-based implementation
     let rule_clone = rule.clone();
-    let results = tokio::task::spawn_blocking(move || {
+    let results = crate::worker::run(move || {
         link_manager_cli::apply_link_rule(&rule_clone)
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     .map_err(|e| ApiError::Internal(format!("Failed to apply link rule: {}", e)))?;
 
     let success = results.iter().all(|r| r.success);
@@ -90,11 +93,11 @@ pub async fn list_links(
     debug!("Listing all PipeWire links");
 
     // Use pwlink to list all links
-    let links = tokio::task::spawn_blocking(|| {
+    let links = crate::worker::run(|| {
         crate::pwlink::list_links()
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
     .map_err(|e| ApiError::Internal(format!("Failed to list links: {}", e)))?;
 
     // Convert pwlink::PwLink to our LinkInfo structure
@@ -145,11 +148,11 @@ pub async fn apply_batch_rules(
         debug!("Applying rule {}/{}", idx + 1, total);
         
         let rule_clone = rule.clone();
-        let link_results = tokio::task::spawn_blocking(move || {
+        let link_results = crate::worker::run(move || {
             link_manager_cli::apply_link_rule(&rule_clone)
         })
         .await
-        .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?;
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?;
 
         match link_results {
             Ok(results_vec) => {
@@ -223,11 +226,11 @@ pub async fn apply_default_rules(
         debug!("Applying default rule {}/{}", idx + 1, total);
         
         let rule_clone = rule.clone();
-        let link_results = tokio::task::spawn_blocking(move || {
+        let link_results = crate::worker::run(move || {
             link_manager_cli::apply_link_rule(&rule_clone)
         })
         .await
-        .map_err(|e| ApiError::Internal(format!("Task join error: {}", e)))?;
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?;
 
         match link_results {
             Ok(results_vec) => {
@@ -346,3 +349,38 @@ pub async fn get_link_rules_status(
         rules: rules_with_status,
     }))
 }
+
+/// Response for saving links
+#[derive(Debug, Serialize)]
+pub struct SaveLinksResponse {
+    pub success: bool,
+    pub message: String,
+    pub links: Vec<SavedLink>,
+}
+
+/// Save all links not managed by a link rule to the link state file
+pub async fn save_links(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<SaveLinksResponse>, ApiError> {
+    let rules = state.get_link_rules();
+    let links = crate::worker::run(move || link_state::save_links(&rules))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to save links: {}", e)))?;
+
+    Ok(Json(SaveLinksResponse {
+        success: true,
+        message: format!("Saved {} link(s)", links.len()),
+        links,
+    }))
+}
+
+/// Recreate the links from the link state file
+pub async fn restore_links() -> Result<Json<RestoreResult>, ApiError> {
+    let result = crate::worker::run(link_state::restore_saved_links)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to restore links: {}", e)))?;
+
+    Ok(Json(result))
+}
@@ -0,0 +1,251 @@
+//! Logging setup with an in-memory log buffer
+//!
+//! The tracing subscriber writes to stderr and keeps the most recent events
+//! in a ring buffer, so logs can be read via `GET /api/v1/logs` on headless
+//! devices. The log filter is behind a reload handle and can be changed at
+//! runtime with `PUT /api/v1/logs/level`, either globally (`debug`) or per
+//! module with env-filter directives (`warn,pw_api::speakereq=debug`).
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+/// Number of log events kept in memory
+pub const LOG_BUFFER_CAPACITY: usize = 1000;
+
+/// Log levels accepted on the command line and in filter directives
+pub const LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+/// A log event
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LogEntry {
+    /// Time in RFC 3339 format
+    pub time: String,
+    /// Time in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub level: String,
+    /// Module that logged the event, e.g. "pw_api::speakereq"
+    pub target: String,
+    pub message: String,
+}
+
+/// Ring buffer of recent log events
+pub struct LogBuffer {
+    entries: Mutex<VecDeque<LogEntry>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Add an entry, dropping the oldest one if the buffer is full
+    pub fn push(&self, entry: LogEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Get entries at `min_level` or more severe, newer than `since_ms`, oldest first
+    pub fn query(&self, min_level: Option<Level>, since_ms: Option<u64>) -> Vec<LogEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| since_ms.map(|s| e.timestamp_ms > s).unwrap_or(true))
+            .filter(|e| match min_level {
+                // More severe levels compare as smaller
+                Some(min) => e.level.parse::<Level>().map(|l| l <= min).unwrap_or(true),
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Collects the message and fields of an event
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+}
+
+/// Layer that stores events in a [`LogBuffer`]
+pub struct BufferLayer {
+    buffer: &'static LogBuffer,
+}
+
+impl<S: Subscriber> Layer<S> for BufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let now = SystemTime::now();
+        self.buffer.push(LogEntry {
+            time: humantime::format_rfc3339_millis(now).to_string(),
+            timestamp_ms: now.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            level: event.metadata().level().to_string().to_lowercase(),
+            target: event.metadata().target().to_string(),
+            message: visitor.message + &visitor.fields,
+        });
+    }
+}
+
+static BUFFER: OnceLock<LogBuffer> = OnceLock::new();
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+static CURRENT_FILTER: Mutex<String> = Mutex::new(String::new());
+
+/// Get the global log buffer
+pub fn buffer() -> &'static LogBuffer {
+    BUFFER.get_or_init(|| LogBuffer::new(LOG_BUFFER_CAPACITY))
+}
+
+/// Install the global tracing subscriber with the given filter
+pub fn init(filter: &str) {
+    let (filter_layer, handle) = reload::Layer::new(EnvFilter::new(filter));
+    let _ = FILTER.set(handle);
+    *CURRENT_FILTER.lock().unwrap() = filter.to_string();
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(tracing_subscriber::fmt::layer())
+        .with(BufferLayer { buffer: buffer() })
+        .init();
+}
+
+/// Get the active log filter
+pub fn current_filter() -> String {
+    CURRENT_FILTER.lock().unwrap().clone()
+}
+
+/// Parse a log filter
+///
+/// The filter is a comma separated list of directives, each either a level
+/// (`info`) or `target=level` (`pw_api::speakereq=debug`). Levels are error,
+/// warn, info, debug, trace and off.
+pub fn parse_filter(filter: &str) -> Result<EnvFilter, String> {
+    let filter = filter.trim();
+    if filter.is_empty() {
+        return Err("Invalid log filter: empty".to_string());
+    }
+
+    for directive in filter.split(',').map(str::trim) {
+        let level = match directive.rsplit_once('=') {
+            Some((target, level)) if !target.is_empty() => level,
+            Some(_) => return Err(format!("Invalid log filter directive '{}': missing target", directive)),
+            None => directive,
+        };
+        let level = level.to_lowercase();
+        if level != "off" && !LEVELS.contains(&level.as_str()) {
+            return Err(format!(
+                "Invalid log filter directive '{}', levels are {}, off",
+                directive,
+                LEVELS.join(", ")
+            ));
+        }
+    }
+
+    EnvFilter::try_new(filter).map_err(|e| format!("Invalid log filter '{}': {}", filter, e))
+}
+
+/// Change the log filter at runtime
+///
+/// Accepts a level (`debug`) or env-filter directives
+/// (`warn,pw_api::speakereq=debug`), see [`parse_filter`].
+pub fn set_filter(filter: &str) -> Result<(), String> {
+    let env_filter = parse_filter(filter)?;
+
+    let handle = FILTER.get().ok_or_else(|| "Logging is not initialized".to_string())?;
+    handle
+        .reload(env_filter)
+        .map_err(|e| format!("Failed to change log filter: {}", e))?;
+    *CURRENT_FILTER.lock().unwrap() = filter.trim().to_string();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp_ms: u64, level: &str) -> LogEntry {
+        LogEntry {
+            time: String::new(),
+            timestamp_ms,
+            level: level.to_string(),
+            target: "pw_api".to_string(),
+            message: format!("{} at {}", level, timestamp_ms),
+        }
+    }
+
+    #[test]
+    fn test_buffer_capacity() {
+        let buffer = LogBuffer::new(2);
+        buffer.push(entry(1, "info"));
+        buffer.push(entry(2, "info"));
+        buffer.push(entry(3, "info"));
+        let entries = buffer.query(None, None);
+        assert_eq!(entries.iter().map(|e| e.timestamp_ms).collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_query_level_and_since() {
+        let buffer = LogBuffer::new(10);
+        buffer.push(entry(1, "error"));
+        buffer.push(entry(2, "debug"));
+        buffer.push(entry(3, "warn"));
+        buffer.push(entry(4, "info"));
+
+        let warnings = buffer.query(Some(Level::WARN), None);
+        assert_eq!(warnings.iter().map(|e| e.timestamp_ms).collect::<Vec<_>>(), vec![1, 3]);
+
+        let recent = buffer.query(None, Some(2));
+        assert_eq!(recent.iter().map(|e| e.timestamp_ms).collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert!(parse_filter("debug").is_ok());
+        assert!(parse_filter("warn,pw_api::speakereq=debug").is_ok());
+        assert!(parse_filter("pw_api::link_scheduler=off").is_ok());
+        assert!(parse_filter("loud").is_err());
+        assert!(parse_filter("pw_api::speakereq=loud").is_err());
+        assert!(parse_filter("=debug").is_err());
+        assert!(parse_filter("").is_err());
+    }
+
+    #[test]
+    fn test_set_invalid_filter() {
+        assert!(set_filter("loud").is_err());
+    }
+}
@@ -0,0 +1,292 @@
+//! Long-term metrics history
+//!
+//! With `history = true` in the `[stats]` section, the volume of the default
+//! sink, the xruns (see [`crate::xrun_stats`]) and the playtime of streams
+//! (see [`crate::stream_stats`]) are sampled every minute and aggregated into
+//! buckets of `history_bucket_secs` (default an hour). The buckets of the
+//! last `history_days` are kept in `metrics.json` in the state directory,
+//! written when a bucket is complete and on shutdown, so a dashboard can
+//! chart e.g. the last 24 hours across restarts.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+/// Metrics that can be queried
+pub const METRICS: [&str; 3] = ["volume", "xruns", "playtime"];
+
+/// Interval of the samples
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Aggregated metrics of a time span
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Bucket {
+    pub start_ms: u64,
+    /// Average volume of the default sink
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    /// Volume samples in the average
+    #[serde(default)]
+    pub volume_samples: u32,
+    #[serde(default)]
+    pub xruns: u64,
+    #[serde(default)]
+    pub playtime_secs: u64,
+}
+
+/// A value of a metric
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Point {
+    /// Start of the bucket (RFC 3339)
+    pub time: String,
+    pub timestamp_ms: u64,
+    /// None if the metric was not available
+    pub value: Option<f64>,
+}
+
+/// Content of metrics.json
+#[derive(Debug, Serialize, Deserialize)]
+struct MetricsFile {
+    bucket_secs: u64,
+    buckets: VecDeque<Bucket>,
+}
+
+/// Buckets of the history
+#[derive(Debug)]
+pub struct MetricsHistory {
+    bucket_secs: u64,
+    capacity: usize,
+    buckets: VecDeque<Bucket>,
+}
+
+impl MetricsHistory {
+    /// Create an empty history of `days` with buckets of `bucket_secs`
+    pub fn new(bucket_secs: u64, days: u64) -> Self {
+        let bucket_secs = bucket_secs.max(SAMPLE_INTERVAL.as_secs());
+        Self {
+            bucket_secs,
+            capacity: ((days.max(1) * 86400) / bucket_secs).max(1) as usize,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    pub fn bucket_secs(&self) -> u64 {
+        self.bucket_secs
+    }
+
+    /// Start of the bucket containing `now_ms`
+    pub fn bucket_start(&self, now_ms: u64) -> u64 {
+        let bucket_ms = self.bucket_secs * 1000;
+        now_ms - now_ms % bucket_ms
+    }
+
+    /// Add a sample taken at `now_ms`: the volume, the xruns since the last
+    /// sample and the playtime since the start of the bucket. Returns true if
+    /// a new bucket was started.
+    pub fn add(&mut self, now_ms: u64, volume: Option<f32>, xruns: u64, playtime_secs: u64) -> bool {
+        let start_ms = self.bucket_start(now_ms);
+        let started = self.buckets.back().is_none_or(|b| b.start_ms < start_ms);
+        if started {
+            if self.buckets.len() == self.capacity {
+                self.buckets.pop_front();
+            }
+            self.buckets.push_back(Bucket { start_ms, ..Default::default() });
+        }
+        let Some(bucket) = self.buckets.back_mut() else {
+            return false;
+        };
+        if let Some(volume) = volume {
+            let samples = bucket.volume_samples as f32;
+            let average = bucket.volume.unwrap_or(0.0) * samples;
+            bucket.volume = Some((average + volume) / (samples + 1.0));
+            bucket.volume_samples += 1;
+        }
+        bucket.xruns += xruns;
+        bucket.playtime_secs = bucket.playtime_secs.max(playtime_secs);
+        started && self.buckets.len() > 1
+    }
+
+    /// Values of `metric` in the buckets since `since_ms`, oldest first
+    pub fn query(&self, metric: &str, since_ms: u64) -> Result<Vec<Point>, String> {
+        let value: fn(&Bucket) -> Option<f64> = match metric {
+            "volume" => |b| b.volume.map(|v| (v as f64 * 1000.0).round() / 1000.0),
+            "xruns" => |b| Some(b.xruns as f64),
+            "playtime" => |b| Some(b.playtime_secs as f64),
+            _ => return Err(format!("Unknown metric '{}', use one of: {}", metric, METRICS.join(", "))),
+        };
+        let first = self.bucket_start(since_ms);
+        Ok(self
+            .buckets
+            .iter()
+            .filter(|b| b.start_ms >= first)
+            .map(|b| Point {
+                time: humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_millis(b.start_ms)).to_string(),
+                timestamp_ms: b.start_ms,
+                value: value(b),
+            })
+            .collect())
+    }
+
+    fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(&MetricsFile { bucket_secs: self.bucket_secs, buckets: self.buckets.clone() })
+            .map_err(|e| format!("Failed to serialize metrics: {}", e))
+    }
+
+    /// Take the buckets of a saved history with the same bucket size
+    fn restore(&mut self, json: &str) -> Result<usize, String> {
+        let file: MetricsFile = serde_json::from_str(json).map_err(|e| format!("Invalid metrics file: {}", e))?;
+        if file.bucket_secs != self.bucket_secs {
+            return Err(format!(
+                "Bucket size changed from {}s to {}s, starting a new history",
+                file.bucket_secs, self.bucket_secs
+            ));
+        }
+        self.buckets = file.buckets;
+        while self.buckets.len() > self.capacity {
+            self.buckets.pop_front();
+        }
+        Ok(self.buckets.len())
+    }
+}
+
+struct State {
+    history: MetricsHistory,
+    path: PathBuf,
+}
+
+fn state() -> &'static Mutex<Option<State>> {
+    static STATE: OnceLock<Mutex<Option<State>>> = OnceLock::new();
+    STATE.get_or_init(|| Mutex::new(None))
+}
+
+/// Path of the history file
+pub fn metrics_path() -> Option<PathBuf> {
+    crate::config::state_dir().map(|dir| dir.join("metrics.json"))
+}
+
+/// Bucket size and the values of `metric` since `since_ms`, None if the
+/// history is disabled
+pub fn query(metric: &str, since_ms: u64) -> Option<Result<(u64, Vec<Point>), String>> {
+    let state = state().lock().unwrap();
+    let state = state.as_ref()?;
+    Some(state.history.query(metric, since_ms).map(|points| (state.history.bucket_secs(), points)))
+}
+
+/// Write the history file (blocking), does nothing if the history is disabled
+pub fn save() -> Result<(), String> {
+    let (json, path) = {
+        let state = state().lock().unwrap();
+        let Some(state) = state.as_ref() else {
+            return Ok(());
+        };
+        (state.history.to_json()?, state.path.clone())
+    };
+    crate::state_file::write_with_backups(&path, json.as_bytes(), 0)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Volume of the default sink (blocking)
+fn default_sink_volume() -> Option<f32> {
+    let sink = crate::wpctl::get_default_sink().ok()?;
+    crate::wpctl::get_volume(sink.id).ok().map(|v| v.volume)
+}
+
+/// Load the saved history and start sampling every minute
+pub fn start_metrics_history(bucket_secs: u64, days: u64) -> Option<JoinHandle<()>> {
+    let Some(path) = metrics_path() else {
+        warn!("Metrics history disabled: state directory not found");
+        return None;
+    };
+    let mut history = MetricsHistory::new(bucket_secs, days);
+    match std::fs::read_to_string(&path) {
+        Ok(json) => match history.restore(&json) {
+            Ok(count) => info!("Loaded {} metrics bucket(s) from {}", count, path.display()),
+            Err(e) => warn!("{}: {}", path.display(), e),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Failed to read {}: {}", path.display(), e),
+    }
+    *state().lock().unwrap() = Some(State { history, path });
+
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+        let mut last_xruns = crate::xrun_stats::report().total_xruns;
+        loop {
+            ticker.tick().await;
+            let volume = crate::worker::run(default_sink_volume).await.ok().flatten();
+            let xruns = crate::xrun_stats::report().total_xruns;
+            let now_ms = crate::store::now_ms();
+            let completed = {
+                let mut state = state().lock().unwrap();
+                let Some(state) = state.as_mut() else {
+                    break;
+                };
+                let start_ms = state.history.bucket_start(now_ms);
+                let playtime: u64 = crate::stream_stats::stats(start_ms).iter().map(|a| a.playtime_secs).sum();
+                state.history.add(now_ms, volume, xruns.saturating_sub(last_xruns), playtime)
+            };
+            last_xruns = xruns;
+            if completed {
+                match crate::worker::run(save).await {
+                    Ok(Ok(())) => debug!("Saved metrics history"),
+                    Ok(Err(e)) => error!("{}", e),
+                    Err(e) => error!("Failed to save metrics history: {}", e),
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_MS: u64 = 3_600_000;
+
+    #[test]
+    fn test_aggregation() {
+        let mut history = MetricsHistory::new(3600, 1);
+        assert!(!history.add(HOUR_MS + 60_000, Some(0.5), 0, 60));
+        assert!(!history.add(HOUR_MS + 120_000, Some(0.7), 2, 120));
+        assert!(!history.add(HOUR_MS + 180_000, None, 1, 100));
+        // The next hour completes the first bucket
+        assert!(history.add(2 * HOUR_MS + 60_000, Some(0.2), 0, 0));
+
+        let volume = history.query("volume", 0).unwrap();
+        assert_eq!(volume.len(), 2);
+        assert_eq!(volume[0].value, Some(0.6));
+        assert_eq!(volume[0].time, "1970-01-01T01:00:00Z");
+        assert_eq!(history.query("xruns", 0).unwrap()[0].value, Some(3.0));
+        assert_eq!(history.query("playtime", 0).unwrap()[0].value, Some(120.0));
+        // Only the buckets of the range
+        assert_eq!(history.query("xruns", 2 * HOUR_MS + 1).unwrap().len(), 1);
+        assert!(history.query("cpu", 0).is_err());
+
+        // At most a day of hourly buckets
+        for hour in 3..30 {
+            history.add(hour * HOUR_MS, None, 0, 0);
+        }
+        assert_eq!(history.buckets.len(), 24);
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut history = MetricsHistory::new(3600, 30);
+        history.add(HOUR_MS, Some(0.5), 1, 10);
+        let json = history.to_json().unwrap();
+
+        let mut restored = MetricsHistory::new(3600, 30);
+        assert_eq!(restored.restore(&json), Ok(1));
+        assert_eq!(restored.buckets, history.buckets);
+
+        // Buckets of another size are not mixed
+        let mut other = MetricsHistory::new(300, 30);
+        assert!(other.restore(&json).is_err());
+        assert!(other.buckets.is_empty());
+        assert!(MetricsHistory::new(3600, 1).restore("{").is_err());
+    }
+}
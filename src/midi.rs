@@ -0,0 +1,140 @@
+//! MIDI nodes and ports
+//!
+//! The audio listings and the graph leave MIDI out. Some setups use MIDI
+//! controllers (e.g. a volume knob), so `/api/v1/midi` lists the MIDI nodes
+//! with their ports. The port names have the `node:port` format that
+//! `POST /api/v1/links` takes, so MIDI links are created like audio links.
+
+use serde::Serialize;
+
+use crate::pwcli::{self, NodeTypeClassification, PwObject};
+
+/// A MIDI port
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MidiPort {
+    pub id: u32,
+    /// Full name for links ("node_name:port_name")
+    pub name: String,
+    /// "in" or "out"
+    pub direction: String,
+}
+
+/// A MIDI node with its ports
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MidiNode {
+    pub id: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_class: Option<String>,
+    pub ports: Vec<MidiPort>,
+}
+
+/// Check if a node is a MIDI node (`media.class` like `Midi/Bridge`)
+///
+/// Nodes without `media.class` count as MIDI if their name contains "midi".
+pub fn is_midi_node(obj: &PwObject) -> bool {
+    if obj.object_type != "Node" {
+        return false;
+    }
+    match pwcli::classify_media_class(obj.media_class()) {
+        NodeTypeClassification::Midi => true,
+        NodeTypeClassification::Unknown => obj
+            .get("node.name")
+            .map(|n| n.to_lowercase().contains("midi"))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Check if a port carries MIDI (`format.dsp` like "8 bit raw midi")
+pub fn is_midi_port(obj: &PwObject) -> bool {
+    obj.object_type == "Port" && pwcli::classify_port_format(obj.get("format.dsp")) == NodeTypeClassification::Midi
+}
+
+/// Collect the MIDI nodes and their ports from a list of objects
+///
+/// Nodes that are not MIDI nodes but have MIDI ports (e.g. a synthesizer
+/// with audio outputs) are included with their MIDI ports only.
+pub fn collect(objects: &[PwObject]) -> Vec<MidiNode> {
+    let mut nodes: Vec<MidiNode> = Vec::new();
+    let find_node = |id: u32| objects.iter().find(|o| o.id == id && o.object_type == "Node");
+
+    let midi_ports: Vec<&PwObject> = objects
+        .iter()
+        .filter(|o| {
+            is_midi_port(o)
+                || o.object_type == "Port"
+                    && o.get("node.id")
+                        .and_then(|id| id.parse().ok())
+                        .and_then(find_node)
+                        .map(is_midi_node)
+                        .unwrap_or(false)
+        })
+        .collect();
+
+    for obj in objects.iter().filter(|o| o.object_type == "Node") {
+        let ports: Vec<MidiPort> = midi_ports
+            .iter()
+            .filter(|p| p.get("node.id") == Some(obj.id.to_string().as_str()))
+            .map(|p| MidiPort {
+                id: p.id,
+                name: format!(
+                    "{}:{}",
+                    obj.get("node.name").unwrap_or_default(),
+                    p.get("port.name").unwrap_or_default()
+                ),
+                direction: p.get("port.direction").unwrap_or("unknown").to_string(),
+            })
+            .collect();
+        if !is_midi_node(obj) && ports.is_empty() {
+            continue;
+        }
+        nodes.push(MidiNode {
+            id: obj.id,
+            name: obj.display_name(),
+            description: obj.description().map(String::from),
+            media_class: obj.media_class().map(String::from),
+            ports,
+        });
+    }
+    nodes
+}
+
+/// List MIDI nodes and ports with pw-cli
+pub fn list() -> Result<Vec<MidiNode>, String> {
+    let objects = pwcli::list_all().map_err(|e| format!("Failed to list PipeWire objects: {}", e))?;
+    Ok(collect(&objects))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_midi_nodes() {
+        let objects = vec![
+            PwObject::new(30, "Node", &[("media.class", "Midi/Bridge"), ("node.name", "Midi-Bridge")]),
+            PwObject::new(31, "Port", &[
+                ("node.id", "30"),
+                ("port.name", "Knob:(capture_0) Knob"),
+                ("port.direction", "out"),
+                ("format.dsp", "8 bit raw midi"),
+            ]),
+            PwObject::new(40, "Node", &[("media.class", "Audio/Sink"), ("node.name", "alsa_output")]),
+            PwObject::new(41, "Port", &[
+                ("node.id", "40"),
+                ("port.name", "playback_FL"),
+                ("port.direction", "in"),
+                ("format.dsp", "32 bit float mono audio"),
+            ]),
+        ];
+
+        let nodes = collect(&objects);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].id, 30);
+        assert_eq!(nodes[0].ports[0].name, "Midi-Bridge:Knob:(capture_0) Knob");
+        assert_eq!(nodes[0].ports[0].direction, "out");
+    }
+}
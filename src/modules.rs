@@ -0,0 +1,248 @@
+//! DSP module availability
+//!
+//! Module endpoints (speakereq, riaa) only work if the module's filter node
+//! is loaded. Their routers are guarded by [`require_module`], which answers
+//! `503 Service Unavailable` with a status object while the node is missing.
+//! The node is looked up on every request, so the endpoints start working
+//! as soon as the module appears, without restarting the server.
+//!
+//! If several nodes match a module's pattern (e.g. a main and a sub
+//! speakereq chain), each of them can be addressed by node name as an
+//! instance, see [`ModuleInstances`].
+
+use axum::{
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tower::Service;
+
+use crate::api_server::{ApiError, NodeState};
+
+/// A DSP module served by the API
+pub struct Module {
+    /// Module name as used in the URL, e.g. "speakereq"
+    pub name: String,
+    pub state: Arc<NodeState>,
+}
+
+/// Availability of a module
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModuleStatus {
+    pub name: String,
+    pub present: bool,
+    /// Name of the node currently serving the module
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<u32>,
+    /// Node name or pattern the module is looked up by
+    #[serde(rename = "match")]
+    pub node_match: String,
+    /// Base path of the module's endpoints
+    pub path: String,
+}
+
+impl Module {
+    pub fn new(name: &str, state: Arc<NodeState>) -> Self {
+        Self {
+            name: name.to_string(),
+            state,
+        }
+    }
+
+    /// Look up the module's node
+    pub fn status(&self) -> Result<ModuleStatus, ApiError> {
+        let node = match self.state.find_node() {
+            Ok(node) => Some(node),
+            Err(ApiError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(ModuleStatus {
+            name: self.name.clone(),
+            present: node.is_some(),
+            node_name: node.as_ref().and_then(|n| n.name().map(|s| s.to_string())),
+            node_id: node.as_ref().map(|n| n.id),
+            node_match: self
+                .state
+                .node_pattern
+                .clone()
+                .unwrap_or_else(|| self.state.node_name.clone()),
+            path: format!("/api/v1/module/{}", self.name),
+        })
+    }
+}
+
+/// Middleware rejecting requests to a module whose node is not loaded
+pub async fn require_module(State(module): State<Arc<Module>>, request: Request, next: Next) -> Response {
+    let check = module.clone();
+    let status = match crate::worker::run(move || check.status()).await {
+        Ok(Ok(status)) => status,
+        Ok(Err(e)) => return e.into_response(),
+        Err(e) => return ApiError::Internal(format!("Worker error: {}", e)).into_response(),
+    };
+
+    if !status.present {
+        return ApiError::ModuleNotPresent(module.name.clone()).into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Per-node instances of a module whose pattern matches several nodes
+///
+/// Requests to `/api/v1/module/<module>/<instance>/<path>` are served by the
+/// module's regular router, bound to a [`NodeState`] for the node named
+/// `<instance>`. Routers are created on first use and kept, so each instance
+/// has its own parameter cache.
+pub struct ModuleInstances {
+    pub module: String,
+    pub pattern: String,
+    make_router: fn(Arc<NodeState>) -> Router,
+    routers: Mutex<HashMap<String, Router>>,
+}
+
+/// A node serving a module instance
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModuleInstance {
+    pub name: String,
+    pub node_id: u32,
+    pub path: String,
+}
+
+/// Response for GET /api/v1/module/<module>/instances
+#[derive(Debug, Serialize)]
+pub struct InstancesResponse {
+    pub module: String,
+    pub instances: Vec<ModuleInstance>,
+}
+
+impl ModuleInstances {
+    pub fn new(module: &str, pattern: &str, make_router: fn(Arc<NodeState>) -> Router) -> Self {
+        Self {
+            module: module.to_string(),
+            pattern: pattern.to_string(),
+            make_router,
+            routers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check whether a node name can be an instance of this module
+    pub fn is_instance_name(&self, name: &str) -> bool {
+        Regex::new(&format!("^{}$", self.pattern))
+            .map(|re| re.is_match(name))
+            .unwrap_or(false)
+    }
+
+    /// Get the router for an instance, creating it on first use
+    fn router(&self, name: &str) -> Router {
+        self.routers
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| (self.make_router)(Arc::new(NodeState::new(name.to_string()))))
+            .clone()
+    }
+
+    /// List the nodes currently matching the module pattern
+    pub fn list(&self) -> Result<Vec<ModuleInstance>, String> {
+        let nodes = crate::pwcli::find_nodes_by_match(&self.pattern)?;
+        Ok(nodes
+            .iter()
+            .filter_map(|n| {
+                let name = n.name()?.to_string();
+                Some(ModuleInstance {
+                    path: format!("/api/v1/module/{}/{}", self.module, name),
+                    name,
+                    node_id: n.id,
+                })
+            })
+            .collect())
+    }
+}
+
+/// List the instances of a module
+/// GET /api/v1/module/<module>/instances
+pub async fn list_instances(
+    State(instances): State<Arc<ModuleInstances>>,
+) -> Result<Json<InstancesResponse>, ApiError> {
+    let lookup = instances.clone();
+    let list = crate::worker::run(move || lookup.list())
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to list instances: {}", e)))?;
+
+    Ok(Json(InstancesResponse {
+        module: instances.module.clone(),
+        instances: list,
+    }))
+}
+
+/// Forward a request to the router of a module instance
+/// ANY /api/v1/module/<module>/:instance/*path
+pub async fn dispatch_instance(
+    State(instances): State<Arc<ModuleInstances>>,
+    Path((instance, path)): Path<(String, String)>,
+    mut request: Request,
+) -> Response {
+    if !instances.is_instance_name(&instance) {
+        return ApiError::NotFound(format!("'{}' is not a {} instance", instance, instances.module))
+            .into_response();
+    }
+
+    let query = request.uri().query().map(|q| format!("?{}", q)).unwrap_or_default();
+    let uri = format!("/api/v1/module/{}/{}{}", instances.module, path, query);
+    match uri.parse() {
+        Ok(uri) => *request.uri_mut() = uri,
+        Err(_) => return ApiError::BadRequest(format!("Invalid path '{}'", path)).into_response(),
+    }
+
+    // Routers are always ready, no need to poll_ready before calling
+    let mut router = instances.router(&instance);
+    match router.call(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn test_is_instance_name() {
+        let instances = ModuleInstances::new("speakereq", "speakereq[0-9]+x[0-9]+", |_| Router::new());
+        assert!(instances.is_instance_name("speakereq2x2"));
+        assert!(!instances.is_instance_name("riaa"));
+        assert!(!instances.is_instance_name("speakereq2x2-extra"));
+    }
+
+    #[tokio::test]
+    async fn test_instance_routes_coexist_with_module_routes() {
+        let mut router = crate::speakereq::create_router(Arc::new(NodeState::new("speakereq".to_string())))
+            .merge(crate::speakereq::create_instances_router("speakereq[0-9]+x[0-9]+"));
+        let request = Request::builder()
+            .uri("/api/v1/module/speakereq/riaa/status")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_module_not_present_response() {
+        let response = ApiError::ModuleNotPresent("riaa".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["module"], "riaa");
+        assert_eq!(json["present"], false);
+    }
+}
@@ -0,0 +1,242 @@
+//! Notification sounds
+//!
+//! A handful of named WAV files (doorbell, timer, ...) stored in the notify
+//! directory. Playing one mixes it into a sink with `pw-play` at a gain in dB
+//! while the volume of the other playback streams is lowered. Their volumes
+//! are restored when the sound ended, unless they were changed meanwhile.
+//! Notifications play one after another, so the restored volumes are always
+//! the ones from before the first sound.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::playback::{self, Playback};
+use crate::pwcli::PwObject;
+use crate::wpctl;
+
+/// Most sounds that can be stored
+pub const MAX_SOUNDS: usize = 16;
+
+/// Held while a notification plays
+static PLAYING: Mutex<()> = Mutex::const_new(());
+
+/// A stored notification sound
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Sound {
+    pub name: String,
+    pub rate: u32,
+    pub channels: u16,
+    pub duration_secs: f64,
+}
+
+/// A started notification
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Notification {
+    #[serde(flatten)]
+    pub playback: Playback,
+    pub gain_db: f32,
+    /// IDs of the streams whose volume was lowered
+    pub ducked: Vec<u32>,
+}
+
+/// Volume of a stream that was lowered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ducked {
+    pub id: u32,
+    pub volume: f32,
+    pub ducked: f32,
+}
+
+/// Check a sound name: letters, digits, '-' and '_'
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 64 && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// Linear volume of a gain in dB, at most 1.0
+pub fn gain_to_volume(gain_db: f32) -> f32 {
+    10f32.powf(gain_db / 20.0).clamp(0.0, 1.0)
+}
+
+/// Volume lowered by `duck_db`, rounded like wpctl sets it
+pub fn ducked_volume(volume: f32, duck_db: f32) -> f32 {
+    (volume * 10f32.powf(-duck_db.abs() / 20.0) * 100.0).round() / 100.0
+}
+
+/// Directory of the sounds (None: `notify` in the state directory)
+pub fn notify_dir(configured: Option<&str>) -> Result<PathBuf, String> {
+    match configured {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => crate::config::state_dir()
+            .map(|dir| dir.join("notify"))
+            .ok_or_else(|| "Could not determine state directory".to_string()),
+    }
+}
+
+/// File of a sound
+pub fn sound_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(format!("{}.wav", name))
+}
+
+/// Stored sounds, sorted by name; files that are not WAV are skipped
+pub async fn list_sounds(directory: &Path) -> Result<Vec<Sound>, String> {
+    let mut entries = match tokio::fs::read_dir(directory).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", directory.display(), e)),
+    };
+    let mut sounds = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".wav")) else {
+            continue;
+        };
+        if !valid_name(name) {
+            continue;
+        }
+        match tokio::fs::read(&path).await.map_err(|e| e.to_string()).and_then(|data| playback::wav_info(&data)) {
+            Ok(info) => sounds.push(Sound {
+                name: name.to_string(),
+                rate: info.rate,
+                channels: info.channels,
+                duration_secs: info.duration_secs,
+            }),
+            Err(e) => debug!("Skipping {}: {}", path.display(), e),
+        }
+    }
+    sounds.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(sounds)
+}
+
+/// Store a sound, replacing one with the same name
+pub async fn store_sound(directory: &Path, name: &str, data: &[u8]) -> Result<Sound, String> {
+    let info = playback::wav_info(data)?;
+    let path = sound_path(directory, name);
+    let exists = tokio::fs::try_exists(&path).await.unwrap_or(false);
+    if !exists && list_sounds(directory).await?.len() >= MAX_SOUNDS {
+        return Err(format!("At most {} sounds can be stored", MAX_SOUNDS));
+    }
+    tokio::fs::create_dir_all(directory)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+    tokio::fs::write(&path, data)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(Sound {
+        name: name.to_string(),
+        rate: info.rate,
+        channels: info.channels,
+        duration_secs: info.duration_secs,
+    })
+}
+
+/// Lower the volume of the playback streams by `duck_db`
+///
+/// Blocking (runs wpctl); streams without a volume are skipped.
+pub fn duck_streams(nodes: &[PwObject], duck_db: f32) -> Vec<Ducked> {
+    if duck_db == 0.0 {
+        return Vec::new();
+    }
+    nodes
+        .iter()
+        .filter(|n| n.media_class() == Some("Stream/Output/Audio"))
+        .filter_map(|n| {
+            let volume = wpctl::get_volume(n.id).ok()?.volume;
+            let ducked = ducked_volume(volume, duck_db);
+            match wpctl::set_volume(n.id, ducked) {
+                Ok(_) => Some(Ducked { id: n.id, volume, ducked }),
+                Err(e) => {
+                    debug!("Could not duck stream {}: {}", n.id, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Restore the volumes of ducked streams that were not changed meanwhile
+///
+/// Blocking (runs wpctl); streams that went away are skipped.
+pub fn restore_streams(ducked: &[Ducked]) {
+    for stream in ducked {
+        match wpctl::get_volume(stream.id) {
+            Ok(current) if (current.volume - stream.ducked).abs() < 0.01 => {
+                if let Err(e) = wpctl::set_volume(stream.id, stream.volume) {
+                    warn!("Could not restore the volume of stream {}: {}", stream.id, e);
+                }
+            }
+            Ok(_) => debug!("Volume of stream {} was changed, not restoring it", stream.id),
+            Err(_) => {}
+        }
+    }
+}
+
+/// Play a sound on a sink, ducking the other streams while it plays
+///
+/// Waits for notifications that are still playing. Returns the notification
+/// and a handle that finishes when the volumes are restored.
+pub async fn notify(
+    sink: PwObject,
+    path: PathBuf,
+    gain_db: f32,
+    duck_db: f32,
+) -> Result<(Notification, tokio::task::JoinHandle<()>), String> {
+    let data = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let info = playback::wav_info(&data)?;
+
+    let guard = PLAYING.lock().await;
+    let ducked = crate::worker::run(move || {
+        let nodes = crate::pwcli::list_nodes()?;
+        Ok::<_, String>(duck_streams(&nodes, duck_db))
+    })
+    .await
+    .map_err(|e| format!("Worker error: {}", e))??;
+
+    let (playback, handle) = match playback::play(&sink, path, info, Some(gain_to_volume(gain_db)), false) {
+        Ok(started) => started,
+        Err(e) => {
+            let _ = crate::worker::run(move || restore_streams(&ducked)).await;
+            return Err(e);
+        }
+    };
+    let notification = Notification {
+        playback,
+        gain_db,
+        ducked: ducked.iter().map(|d| d.id).collect(),
+    };
+    let restore = tokio::spawn(async move {
+        let _ = handle.await;
+        let _ = crate::worker::run(move || restore_streams(&ducked)).await;
+        drop(guard);
+    });
+    Ok((notification, restore))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_name() {
+        assert!(valid_name("doorbell"));
+        assert!(valid_name("timer_2-short"));
+        assert!(!valid_name("doorbell.wav"));
+        assert!(!valid_name("../doorbell"));
+        assert!(!valid_name(""));
+        assert_eq!(sound_path(Path::new("/tmp/notify"), "doorbell"), PathBuf::from("/tmp/notify/doorbell.wav"));
+    }
+
+    #[test]
+    fn test_gain() {
+        assert_eq!(gain_to_volume(0.0), 1.0);
+        assert_eq!(gain_to_volume(6.0), 1.0);
+        assert!((gain_to_volume(-6.0) - 0.501).abs() < 0.001);
+        assert_eq!(ducked_volume(0.8, -20.0), 0.08);
+        // The sign of the ducking is ignored
+        assert_eq!(ducked_volume(0.8, 20.0), 0.08);
+        assert_eq!(ducked_volume(0.5, 0.0), 0.5);
+    }
+}
@@ -0,0 +1,353 @@
+//! PIN pairing of clients
+//!
+//! Instead of copying an API token onto a phone, an app can pair with the
+//! server: it requests pairing with a name and a role, the server shows a
+//! six-digit PIN in its log (and optionally runs `pairing_command`, e.g. to
+//! show it on a display), and the app sends the PIN back to receive its own
+//! token. PINs are valid for [`PIN_VALIDITY`] and for [`MAX_ATTEMPTS`]
+//! tries. After [`MAX_FAILURES`] wrong PINs in all requests together,
+//! pairing is locked for [`LOCKOUT`], so the PIN can't be guessed by opening
+//! new requests. Only the roles in `pairing_roles` can be requested, pairing
+//! is off while it is empty (the default). The PIN is printed on stdout and
+//! never logged, the log can be read via the API.
+//!
+//! Paired clients are kept in `~/.state/pipewire-api/paired-clients.json`
+//! and can be revoked with `DELETE /api/v1/pair/clients/:name`. The file
+//! is readable by the user only and has no backups, so revoked tokens are
+//! gone from the disk.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
+
+use crate::auth::TokenGrant;
+
+/// Time a PIN can be used
+pub const PIN_VALIDITY: Duration = Duration::from_secs(120);
+
+/// Number of wrong PINs before a pairing request is discarded
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// Number of wrong PINs in all requests before pairing is locked
+pub const MAX_FAILURES: u32 = 10;
+
+/// Time pairing is locked after [`MAX_FAILURES`] wrong PINs
+pub const LOCKOUT: Duration = Duration::from_secs(300);
+
+/// Maximum number of open pairing requests
+const MAX_PENDING: usize = 8;
+
+/// File name of the paired clients in the state directory
+pub const PAIRED_CLIENTS_FILE: &str = "paired-clients.json";
+
+/// A client that received a token by pairing
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PairedClient {
+    pub name: String,
+    pub role: String,
+    /// Not included in listings
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub token: String,
+    /// Time of pairing (RFC 3339)
+    pub created: String,
+}
+
+impl PairedClient {
+    fn grant(&self) -> TokenGrant {
+        TokenGrant {
+            name: self.name.clone(),
+            token: self.token.clone(),
+            role: self.role.clone(),
+        }
+    }
+}
+
+/// An open pairing request
+#[derive(Debug)]
+struct Pending {
+    name: String,
+    role: String,
+    pin: String,
+    expires: Instant,
+    attempts: u32,
+}
+
+/// Errors completing a pairing
+#[derive(Debug, Clone, PartialEq)]
+pub enum PairingError {
+    /// Unknown or expired pairing ID
+    NotFound,
+    /// Wrong PIN, with the number of attempts left
+    WrongPin(u32),
+    /// Too many open pairing requests
+    Busy,
+    /// Too many wrong PINs, with the seconds until pairing is possible again
+    LockedOut(u64),
+    /// A client with the name is paired already
+    NameTaken(String),
+    /// The client could not be saved
+    Storage(String),
+}
+
+/// Open pairing requests and paired clients
+#[derive(Debug, Default)]
+pub struct PairingManager {
+    pending: Mutex<HashMap<String, Pending>>,
+    /// Wrong PINs since the last lockout and the end of the current lockout
+    failures: Mutex<(u32, Option<Instant>)>,
+    clients: Mutex<Vec<PairedClient>>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+/// Read random bytes from the kernel
+fn random_bytes<const N: usize>() -> Result<[u8; N], String> {
+    let mut bytes = [0u8; N];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut bytes))
+        .map_err(|e| format!("Failed to read random bytes: {}", e))?;
+    Ok(bytes)
+}
+
+fn random_hex<const N: usize>() -> Result<String, String> {
+    Ok(random_bytes::<N>()?.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn random_pin() -> Result<String, String> {
+    let value = u32::from_le_bytes(random_bytes::<4>()?);
+    Ok(format!("{:06}", value % 1_000_000))
+}
+
+impl PairingManager {
+    /// Load the paired clients from a file, which is also used to save them
+    ///
+    /// Returns the tokens of the paired clients.
+    pub fn load(&self, path: PathBuf) -> Vec<TokenGrant> {
+        let clients: Vec<PairedClient> = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Failed to parse {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        let grants = clients.iter().map(PairedClient::grant).collect();
+        *self.clients.lock().unwrap() = clients;
+        *self.path.lock().unwrap() = Some(path);
+        grants
+    }
+
+    fn save(&self, clients: &[PairedClient]) -> Result<(), String> {
+        let Some(path) = self.path.lock().unwrap().clone() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let content = serde_json::to_string_pretty(clients)
+            .map_err(|e| format!("Failed to serialize paired clients: {}", e))?;
+        // The file holds bearer tokens: readable by the user only, and no
+        // backups that would keep revoked tokens
+        crate::state_file::write_private(&path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Fail if pairing is locked after too many wrong PINs
+    fn check_lockout(&self, now: Instant) -> Result<(), PairingError> {
+        match self.failures.lock().unwrap().1 {
+            Some(until) if until > now => Err(PairingError::LockedOut((until - now).as_secs().max(1))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Count a wrong PIN, locking pairing and discarding all open requests
+    /// after [`MAX_FAILURES`]
+    fn record_failure(&self, pending: &mut HashMap<String, Pending>, now: Instant) {
+        let mut failures = self.failures.lock().unwrap();
+        failures.0 += 1;
+        if failures.0 >= MAX_FAILURES {
+            warn!("{} wrong pairing PINs, pairing is locked for {} s", failures.0, LOCKOUT.as_secs());
+            *failures = (0, Some(now + LOCKOUT));
+            pending.clear();
+        }
+    }
+
+    /// Start pairing, returning the pairing ID and the PIN
+    pub fn start(&self, name: &str, role: &str) -> Result<(String, String), PairingError> {
+        let now = Instant::now();
+        self.check_lockout(now)?;
+        if self.clients.lock().unwrap().iter().any(|c| c.name == name) {
+            return Err(PairingError::NameTaken(name.to_string()));
+        }
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, p| p.expires > now);
+        if pending.len() >= MAX_PENDING {
+            return Err(PairingError::Busy);
+        }
+
+        let id = random_hex::<8>().map_err(PairingError::Storage)?;
+        let pin = random_pin().map_err(PairingError::Storage)?;
+        pending.insert(
+            id.clone(),
+            Pending {
+                name: name.to_string(),
+                role: role.to_string(),
+                pin: pin.clone(),
+                expires: now + PIN_VALIDITY,
+                attempts: 0,
+            },
+        );
+        Ok((id, pin))
+    }
+
+    /// Complete pairing with the PIN, returning the new client with its token
+    pub fn complete(&self, id: &str, pin: &str) -> Result<PairedClient, PairingError> {
+        let now = Instant::now();
+        self.check_lockout(now)?;
+        let request = {
+            let mut pending = self.pending.lock().unwrap();
+            let entry = pending.get_mut(id).filter(|p| p.expires > now).ok_or(PairingError::NotFound)?;
+            if entry.pin != pin.trim() {
+                entry.attempts += 1;
+                let left = MAX_ATTEMPTS.saturating_sub(entry.attempts);
+                if left == 0 {
+                    pending.remove(id);
+                }
+                self.record_failure(&mut pending, now);
+                return Err(PairingError::WrongPin(left));
+            }
+            pending.remove(id).ok_or(PairingError::NotFound)?
+        };
+
+        let client = PairedClient {
+            name: request.name,
+            role: request.role,
+            token: random_hex::<16>().map_err(PairingError::Storage)?,
+            created: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        };
+
+        let mut clients = self.clients.lock().unwrap();
+        // Another request with the name may have completed meanwhile; a
+        // paired client is only replaced by revoking it
+        if clients.iter().any(|c| c.name == client.name) {
+            return Err(PairingError::NameTaken(client.name));
+        }
+        clients.push(client.clone());
+        self.save(&clients).map_err(PairingError::Storage)?;
+        info!("Paired client '{}' with role '{}'", client.name, client.role);
+        Ok(client)
+    }
+
+    /// Paired clients without their tokens
+    pub fn clients(&self) -> Vec<PairedClient> {
+        self.clients
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|c| PairedClient { token: String::new(), ..c.clone() })
+            .collect()
+    }
+
+    /// Tokens of all paired clients
+    pub fn grants(&self) -> Vec<TokenGrant> {
+        self.clients.lock().unwrap().iter().map(PairedClient::grant).collect()
+    }
+
+    /// Remove a paired client, returning whether it existed
+    pub fn revoke(&self, name: &str) -> Result<bool, String> {
+        let mut clients = self.clients.lock().unwrap();
+        let count = clients.len();
+        clients.retain(|c| c.name != name);
+        if clients.len() == count {
+            return Ok(false);
+        }
+        self.save(&clients)?;
+        info!("Revoked paired client '{}'", name);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PAIRED_CLIENTS_FILE);
+        let manager = PairingManager::default();
+        assert!(manager.load(path.clone()).is_empty());
+
+        let (id, pin) = manager.start("phone", "volume-only").unwrap();
+        assert_eq!(pin.len(), 6);
+        assert_eq!(manager.complete(&id, "wrong"), Err(PairingError::WrongPin(2)));
+        let client = manager.complete(&id, &pin).unwrap();
+        assert_eq!(client.role, "volume-only");
+        assert_eq!(client.token.len(), 32);
+        // A pairing ID can only be used once
+        assert_eq!(manager.complete(&id, &pin), Err(PairingError::NotFound));
+
+        assert!(manager.clients()[0].token.is_empty());
+        let reloaded = PairingManager::default();
+        assert_eq!(reloaded.load(path)[0].token, client.token);
+
+        // The name of a paired client can't be taken over
+        assert_eq!(manager.start("phone", "viewer"), Err(PairingError::NameTaken("phone".to_string())));
+
+        assert!(manager.revoke("phone").unwrap());
+        assert!(manager.grants().is_empty());
+    }
+
+    #[test]
+    fn test_revoked_token_not_on_disk() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PAIRED_CLIENTS_FILE);
+        // A backup written by an earlier version
+        std::fs::write(crate::state_file::backup_path(&path, 1), "[]").unwrap();
+        let manager = PairingManager::default();
+        manager.load(path.clone());
+
+        let (id, pin) = manager.start("phone", "viewer").unwrap();
+        let client = manager.complete(&id, &pin).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(manager.revoke("phone").unwrap());
+
+        // Only the file itself is left, without the token
+        let files: Vec<_> = std::fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().path()).collect();
+        assert_eq!(files, vec![path.clone()]);
+        assert!(!std::fs::read_to_string(&path).unwrap().contains(&client.token));
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_too_many_wrong_pins() {
+        let manager = PairingManager::default();
+        let (id, pin) = manager.start("tablet", "viewer").unwrap();
+        let wrong = if pin == "000000" { "111111" } else { "000000" };
+        for left in (0..MAX_ATTEMPTS).rev() {
+            assert_eq!(manager.complete(&id, wrong), Err(PairingError::WrongPin(left)));
+        }
+        assert_eq!(manager.complete(&id, &pin), Err(PairingError::NotFound));
+    }
+
+    #[test]
+    fn test_lockout() {
+        let manager = PairingManager::default();
+        let (id, pin) = manager.start("tablet", "viewer").unwrap();
+        // New requests don't give new attempts
+        let mut request = manager.start("laptop", "viewer").unwrap();
+        for failure in 1..=MAX_FAILURES {
+            let wrong = if request.1 == "000000" { "111111" } else { "000000" };
+            assert!(matches!(manager.complete(&request.0, wrong), Err(PairingError::WrongPin(_))));
+            if failure % MAX_ATTEMPTS == 0 && failure < MAX_FAILURES {
+                request = manager.start("laptop", "viewer").unwrap();
+            }
+        }
+        assert!(matches!(manager.start("laptop", "viewer"), Err(PairingError::LockedOut(_))));
+        assert!(matches!(manager.complete(&id, &pin), Err(PairingError::LockedOut(_))));
+    }
+}
@@ -0,0 +1,141 @@
+//! Parameter change notifications for module nodes
+//!
+//! PipeWire parameters of a module node (e.g. speakereq) can be changed by
+//! other tools such as pw-cli. The monitor periodically compares the node's
+//! parameters with the [`NodeState`] cache, updates the cache when they
+//! differ and publishes a [`ParamChangeEvent`]. Changes made through the API
+//! are published directly by [`NodeState::set_parameters`].
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+use crate::api_server::{ApiError, NodeState};
+use crate::parameters::ParameterValue;
+
+/// Number of events buffered for slow subscribers
+pub const PARAM_EVENT_CAPACITY: usize = 64;
+
+/// Origin of a parameter change
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeSource {
+    /// Changed through this API
+    Api,
+    /// Changed by another PipeWire client
+    External,
+}
+
+/// Parameters of a node that changed
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ParamChangeEvent {
+    pub node: String,
+    pub source: ChangeSource,
+    /// Changed parameters with their new values
+    pub changed: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ParamChangeEvent {
+    pub fn new(node: &str, source: ChangeSource, changed: &HashMap<String, ParameterValue>) -> Self {
+        Self {
+            node: node.to_string(),
+            source,
+            changed: changed.iter().map(|(k, v)| (k.clone(), v.to_json())).collect(),
+        }
+    }
+}
+
+/// Get parameters whose value differs from the old state or that are new
+pub fn diff_params(
+    old: &HashMap<String, ParameterValue>,
+    new: &HashMap<String, ParameterValue>,
+) -> HashMap<String, ParameterValue> {
+    new.iter()
+        .filter(|(k, v)| old.get(*k) != Some(*v))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+/// Start polling a node for external parameter changes
+pub fn start_param_monitor(state: Arc<NodeState>, poll_interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(poll_interval);
+        loop {
+            ticker.tick().await;
+            let node_state = state.clone();
+            match crate::worker::run(move || node_state.check_for_changes()).await {
+                Ok(Ok(_)) => {}
+                // The module is not loaded, nothing to watch
+                Ok(Err(ApiError::NotFound(_))) => {}
+                Ok(Err(e)) => tracing::debug!("[{}] Parameter monitor: {:?}", state.node_name, e),
+                Err(e) => tracing::warn!("[{}] Parameter monitor: {}", state.node_name, e),
+            }
+        }
+    })
+}
+
+/// Stream parameter changes of a module node as Server-Sent Events
+/// GET /api/v1/module/<module>/events
+pub async fn events(
+    State(state): State<Arc<NodeState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // Lagging subscribers skip missed events instead of ending the stream
+    let stream = BroadcastStream::new(state.subscribe()).filter_map(|event| {
+        event.ok().map(|e| {
+            Ok(Event::default()
+                .event("params")
+                .data(serde_json::to_string(&e).unwrap_or_default()))
+        })
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_params() {
+        let old = HashMap::from([
+            ("master_gain_db".to_string(), ParameterValue::Float(0.0)),
+            ("Enable".to_string(), ParameterValue::Bool(true)),
+        ]);
+        let new = HashMap::from([
+            ("master_gain_db".to_string(), ParameterValue::Float(-3.0)),
+            ("Enable".to_string(), ParameterValue::Bool(true)),
+        ]);
+        let changed = diff_params(&old, &new);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed.get("master_gain_db"), Some(&ParameterValue::Float(-3.0)));
+        assert!(diff_params(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let changed = HashMap::from([("Enable".to_string(), ParameterValue::Bool(false))]);
+        let event = ParamChangeEvent::new("speakereq2x2", ChangeSource::External, &changed);
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"node": "speakereq2x2", "source": "external", "changed": {"Enable": false}})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_api_changes() {
+        let state = NodeState::new("speakereq2x2".to_string());
+        let mut receiver = state.subscribe();
+        let changed = HashMap::from([("Enable".to_string(), ParameterValue::Bool(true))]);
+        state.publish(ParamChangeEvent::new("speakereq2x2", ChangeSource::Api, &changed));
+        let event = receiver.recv().await.unwrap();
+        assert_eq!(event.source, ChangeSource::Api);
+    }
+}
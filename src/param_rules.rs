@@ -9,6 +9,7 @@ use std::path::Path;
 use tracing::{debug, error, info, warn};
 
 use crate::pwcli;
+use crate::startup::WaitFor;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeMatcher {
@@ -29,6 +30,9 @@ pub struct ParamRule {
     pub info_level: String,
     #[serde(default = "default_error_level")]
     pub error_level: String,
+    /// Node to wait for before applying the rule at startup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for: Option<WaitFor>,
 }
 
 fn default_true() -> bool {
@@ -98,9 +102,8 @@ fn node_matches(node: &pwcli::PwObject, matcher: &NodeMatcher) -> bool {
 
 /// Apply parameter rules to nodes
 pub async fn apply_param_rules(rules: &[ParamRule]) -> Result<(), String> {
-    use std::process::Command;
     use crate::parameters::ParameterValue;
-    
+
     if rules.is_empty() {
         debug!("No parameter rules to apply");
         return Ok(());
@@ -147,58 +150,20 @@ pub async fn apply_param_rules(rules: &[ParamRule]) -> Result<(), String> {
             // Convert parameters to ParameterValue format
             let mut params = HashMap::new();
             for (param_name, param_value) in &rule.parameters {
-                let value = match param_value {
-                    serde_json::Value::Number(n) => {
-                        if let Some(i) = n.as_i64() {
-                            ParameterValue::Int(i as i32)
-                        } else {
-                            ParameterValue::Float(n.as_f64().unwrap_or(0.0) as f32)
-                        }
-                    }
-                    serde_json::Value::Bool(b) => ParameterValue::Bool(*b),
-                    serde_json::Value::String(s) => ParameterValue::String(s.clone()),
-                    _ => {
-                        warn!("Unsupported parameter value type for {}: {:?}", param_name, param_value);
-                        continue;
+                match ParameterValue::from_json(param_value) {
+                    Ok(value) => {
+                        params.insert(param_name.clone(), value);
                     }
-                };
-                params.insert(param_name.clone(), value);
-            }
-
-            // Build array format for params struct
-            let mut params_array = Vec::new();
-            for (key, value) in params {
-                params_array.push(serde_json::Value::String(key.clone()));
-                
-                let json_value = match value {
-                    ParameterValue::Bool(b) => serde_json::Value::Bool(b),
-                    ParameterValue::Int(i) => serde_json::Value::Number(i.into()),
-                    ParameterValue::Float(f) => {
-                        serde_json::Number::from_f64(f as f64)
-                            .map(serde_json::Value::Number)
-                            .unwrap_or(serde_json::Value::Null)
-                    },
-                    ParameterValue::String(s) => serde_json::Value::String(s),
-                };
-                params_array.push(json_value);
+                    Err(e) => warn!("Unsupported parameter value for {}: {}", param_name, e),
+                }
             }
 
-            // Wrap in params property
-            let json = serde_json::json!({ "params": params_array });
-            let json_str = json.to_string();
-
-            // Set parameters via pw-cli
-            let output = Command::new("pw-cli")
-                .args(&["set-param", &node.id.to_string(), "Props", &json_str])
-                .output()
-                .map_err(|e| format!("Failed to execute pw-cli: {}", e))?;
-
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            // Set all parameters with a single pw-cli call
+            if let Err(e) = crate::parameters::set_params(node.id, &params) {
                 match rule.error_level.as_str() {
-                    "error" => error!("Failed to set parameters on {}: {}", node_name, stderr),
-                    "warn" => warn!("Failed to set parameters on {}: {}", node_name, stderr),
-                    _ => debug!("Failed to set parameters on {}: {}", node_name, stderr),
+                    "error" => error!("Failed to set parameters on {}: {}", node_name, e),
+                    "warn" => warn!("Failed to set parameters on {}: {}", node_name, e),
+                    _ => debug!("Failed to set parameters on {}: {}", node_name, e),
                 }
             } else {
                 debug!("Successfully set parameters on {}", node_name);
@@ -260,7 +225,7 @@ mod tests {
         ]"#;
 
         let rules: Vec<ParamRule> = serde_json::from_str(json).unwrap();
-        assert_eq!(rules[0].set_at_startup, true); // default
+        assert!(rules[0].set_at_startup); // default
         assert_eq!(rules[0].info_level, "info"); // default
         assert_eq!(rules[0].error_level, "error"); // default
     }
@@ -396,7 +361,7 @@ mod tests {
         temp_file.write_all(content.as_bytes()).unwrap();
         temp_file.flush().unwrap();
 
-        let result = load_param_rules(&temp_file.path().to_path_buf());
+        let result = load_param_rules(temp_file.path());
         assert!(result.is_ok());
         
         let rules = result.unwrap();
@@ -421,7 +386,7 @@ mod tests {
         temp_file.write_all(b"not valid json").unwrap();
         temp_file.flush().unwrap();
 
-        let result = load_param_rules(&temp_file.path().to_path_buf());
+        let result = load_param_rules(temp_file.path());
         assert!(result.is_err());
     }
 
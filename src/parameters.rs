@@ -1,12 +1,24 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
 use std::fmt;
 
+use crate::prop_schema::{self, PropSchema};
+use crate::spa_props::{self, ParamKind};
+
 /// Enum for parameter values used by the API
+///
+/// In JSON, arrays of numbers map to `Array` (e.g. `channelVolumes`) and
+/// `{"enum": "<label>"}` maps to `Enum`, a value of a selector control given
+/// by its label. Labels are resolved with the node's PropInfo before the
+/// value is set.
 #[derive(Debug, Clone, PartialEq)]
 pub enum ParameterValue {
     Bool(bool),
     Int(i32),
     Float(f32),
     String(String),
+    Array(Vec<f32>),
+    Enum(String),
 }
 
 impl ParameterValue {
@@ -25,6 +37,59 @@ impl ParameterValue {
         }
     }
 
+    /// Convert from a JSON value (bool, integer, number, string, array of
+    /// numbers or `{"enum": "<label>"}`)
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, String> {
+        match value {
+            serde_json::Value::Bool(b) => Ok(ParameterValue::Bool(*b)),
+            serde_json::Value::Number(n) => match n.as_i64().and_then(|i| i32::try_from(i).ok()) {
+                Some(i) => Ok(ParameterValue::Int(i)),
+                None => n
+                    .as_f64()
+                    .map(|f| ParameterValue::Float(f as f32))
+                    .ok_or_else(|| format!("Invalid number {}", n)),
+            },
+            serde_json::Value::String(s) => Ok(ParameterValue::String(s.clone())),
+            serde_json::Value::Array(items) => items
+                .iter()
+                .map(|v| v.as_f64().map(|f| f as f32))
+                .collect::<Option<Vec<f32>>>()
+                .map(ParameterValue::Array)
+                .ok_or_else(|| format!("Invalid array {}, expected numbers", value)),
+            serde_json::Value::Object(map) => match (map.len(), map.get("enum")) {
+                (1, Some(serde_json::Value::String(label))) => Ok(ParameterValue::Enum(label.clone())),
+                _ => Err(format!("Invalid parameter value {}, expected {{\"enum\": \"<label>\"}}", value)),
+            },
+            other => Err(format!(
+                "Invalid parameter value {}, expected bool, number, string, array or enum label",
+                other
+            )),
+        }
+    }
+
+    /// Convert to a JSON value
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            ParameterValue::Bool(b) => serde_json::Value::Bool(*b),
+            ParameterValue::Int(i) => serde_json::Value::Number((*i).into()),
+            ParameterValue::Float(f) => serde_json::Number::from_f64(*f as f64)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            ParameterValue::String(s) => serde_json::Value::String(s.clone()),
+            ParameterValue::Array(values) => serde_json::Value::Array(
+                values
+                    .iter()
+                    .map(|f| {
+                        serde_json::Number::from_f64(*f as f64)
+                            .map(serde_json::Value::Number)
+                            .unwrap_or(serde_json::Value::Null)
+                    })
+                    .collect(),
+            ),
+            ParameterValue::Enum(label) => serde_json::json!({ "enum": label }),
+        }
+    }
+
     /// Extract as f32, coercing Int to f32
     pub fn as_float(&self) -> Option<f32> {
         match self {
@@ -60,6 +125,159 @@ impl fmt::Display for ParameterValue {
             ParameterValue::Int(i) => write!(f, "{}", i),
             ParameterValue::Float(v) => write!(f, "{}", v),
             ParameterValue::String(s) => write!(f, "{}", s),
+            ParameterValue::Array(values) => {
+                let items: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            ParameterValue::Enum(label) => write!(f, "{}", label),
+        }
+    }
+}
+
+impl Serialize for ParameterValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ParameterValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        ParameterValue::from_json(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Replace enum labels by the values they stand for, using the labels from
+/// the controls' PropInfo
+pub fn resolve_enum_labels(
+    params: &mut HashMap<String, ParameterValue>,
+    schema: &[PropSchema],
+) -> Result<(), String> {
+    for (name, value) in params.iter_mut() {
+        let ParameterValue::Enum(label) = value else {
+            continue;
+        };
+        let control = schema
+            .iter()
+            .find(|c| c.name == *name)
+            .ok_or_else(|| format!("Control '{}' not found", name))?;
+        let resolved = control
+            .labels
+            .iter()
+            .find(|l| l.label.eq_ignore_ascii_case(label))
+            .ok_or_else(|| {
+                let labels: Vec<&str> = control.labels.iter().map(|l| l.label.as_str()).collect();
+                format!("Invalid label '{}' for {}, valid labels: {}", label, name, labels.join(", "))
+            })?;
+        *value = ParameterValue::from_json(&resolved.value)?;
+    }
+    Ok(())
+}
+
+/// Build the Props JSON for pw-cli set-param.
+///
+/// Built-in Props keys (`volume`, `mute`, `channelVolumes`, ...) are set
+/// directly. Control parameters of filter-chain plugins live in the `params`
+/// struct as an array of alternating names and values:
+/// `["key1", value1, "key2", value2]`.
+/// Keys are sorted so the generated command is deterministic.
+pub fn props_json(params: &HashMap<String, ParameterValue>) -> serde_json::Value {
+    let mut keys: Vec<&String> = params.keys().collect();
+    keys.sort();
+
+    let mut props = serde_json::Map::new();
+    let mut params_array = Vec::new();
+    for key in keys {
+        if spa_props::name_to_key(ParamKind::Props, key).is_some() {
+            props.insert(key.clone(), params[key].to_json());
+        } else {
+            params_array.push(serde_json::Value::String(key.clone()));
+            params_array.push(params[key].to_json());
         }
     }
+    if !params_array.is_empty() {
+        props.insert("params".to_string(), serde_json::Value::Array(params_array));
+    }
+    serde_json::Value::Object(props)
+}
+
+/// Set several control parameters of a node in a single pw-cli call
+pub fn set_params(node_id: u32, params: &HashMap<String, ParameterValue>) -> Result<(), String> {
+    let props = if params.values().any(|v| matches!(v, ParameterValue::Enum(_))) {
+        let mut params = params.clone();
+        resolve_enum_labels(&mut params, &prop_schema::read_schema(node_id)?)?;
+        props_json(&params)
+    } else {
+        props_json(params)
+    };
+    crate::pwparams::set_param(node_id, "Props", &props)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prop_schema::PropLabel;
+    use serde_json::json;
+
+    #[test]
+    fn test_array_and_enum_json() {
+        let volumes = ParameterValue::from_json(&json!([0.5, 0.25])).unwrap();
+        assert_eq!(volumes, ParameterValue::Array(vec![0.5, 0.25]));
+        assert_eq!(volumes.to_json(), json!([0.5, 0.25]));
+
+        let selector = ParameterValue::from_json(&json!({"enum": "Low shelf"})).unwrap();
+        assert_eq!(selector, ParameterValue::Enum("Low shelf".to_string()));
+        assert_eq!(selector.to_json(), json!({"enum": "Low shelf"}));
+
+        assert!(ParameterValue::from_json(&json!(["a"])).is_err());
+        assert!(ParameterValue::from_json(&json!({"label": "x"})).is_err());
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let params: HashMap<String, ParameterValue> =
+            serde_json::from_value(json!({"channelVolumes": [1.0, 0.5], "eq:Type": {"enum": "Peaking"}, "mute": true}))
+                .unwrap();
+        assert_eq!(params["channelVolumes"], ParameterValue::Array(vec![1.0, 0.5]));
+        assert_eq!(params["mute"], ParameterValue::Bool(true));
+        assert_eq!(serde_json::to_value(&params["eq:Type"]).unwrap(), json!({"enum": "Peaking"}));
+    }
+
+    #[test]
+    fn test_props_json_splits_builtin_props() {
+        let params = HashMap::from([
+            ("channelVolumes".to_string(), ParameterValue::Array(vec![0.5, 0.5])),
+            ("eq:Gain".to_string(), ParameterValue::Float(-3.0)),
+        ]);
+        assert_eq!(
+            props_json(&params),
+            json!({"channelVolumes": [0.5, 0.5], "params": ["eq:Gain", -3.0]})
+        );
+    }
+
+    #[test]
+    fn test_resolve_enum_labels() {
+        let schema = vec![PropSchema {
+            name: "eq:Type".to_string(),
+            value_type: "int".to_string(),
+            param: true,
+            description: None,
+            default: None,
+            min: None,
+            max: None,
+            step: None,
+            options: vec![json!(0), json!(1)],
+            labels: vec![
+                PropLabel { value: json!(0), label: "Peaking".to_string() },
+                PropLabel { value: json!(1), label: "Low shelf".to_string() },
+            ],
+        }];
+
+        let mut params = HashMap::from([("eq:Type".to_string(), ParameterValue::Enum("low shelf".to_string()))]);
+        resolve_enum_labels(&mut params, &schema).unwrap();
+        assert_eq!(params["eq:Type"], ParameterValue::Int(1));
+
+        let mut params = HashMap::from([("eq:Type".to_string(), ParameterValue::Enum("Notch".to_string()))]);
+        assert!(resolve_enum_labels(&mut params, &schema).unwrap_err().contains("Peaking, Low shelf"));
+    }
 }
@@ -8,9 +8,9 @@ use tower_http::cors::CorsLayer;
 #[command(name = "pipewire-api")]
 #[command(about = "REST API server for SpeakerEQ 2x2 PipeWire plugin", long_about = None)]
 struct Args {
-    /// Port to listen on
-    #[arg(short, long, default_value_t = 2716)]
-    port: u16,
+    /// Port to listen on (default: 2716 or server.port from pipewire-api.toml)
+    #[arg(short, long)]
+    port: Option<u16>,
 
     /// Bind to localhost only (127.0.0.1) instead of all interfaces (0.0.0.0)
     #[arg(long)]
@@ -24,61 +24,121 @@ struct Args {
     #[arg(long)]
     no_api: bool,
 
-    /// Log level: error, warn, info, debug, trace
+    /// Log level: error, warn, info, debug, trace, or env-filter directives
+    /// like "warn,pw_api::speakereq=debug"
     #[arg(long, default_value = "warn")]
     log_level: String,
+
+    /// Validate the configuration files and exit (exit code 1 on errors)
+    #[arg(long)]
+    check_config: bool,
+
+    /// Serve simulated PipeWire objects (DAC, speakereq, riaa) instead of
+    /// those of the running PipeWire, for developing clients without hardware
+    #[arg(long)]
+    demo: bool,
+
+    /// Record the pw-cli and pw-link outputs of the running PipeWire into
+    /// DIR/pipewire-<version> as parser test fixtures and exit
+    #[arg(long, value_name = "DIR")]
+    capture_fixtures: Option<std::path::PathBuf>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
-    // Initialize tracing with specified log level
-    let log_level = args.log_level.to_lowercase();
-    let env_filter = match log_level.as_str() {
-        "error" => tracing_subscriber::EnvFilter::new("error"),
-        "warn" => tracing_subscriber::EnvFilter::new("warn"),
-        "info" => tracing_subscriber::EnvFilter::new("info"),
-        "debug" => tracing_subscriber::EnvFilter::new("debug"),
-        "trace" => tracing_subscriber::EnvFilter::new("trace"),
-        _ => {
-            eprintln!("Invalid log level '{}', using 'warn'", args.log_level);
-            tracing_subscriber::EnvFilter::new("warn")
+    if args.check_config {
+        let report = pw_api::config_validation::validate_all();
+        print!("{}", pw_api::config_validation::format_report(&report));
+        std::process::exit(if report.valid { 0 } else { 1 });
+    }
+
+    if let Some(dir) = args.capture_fixtures {
+        match pw_api::fixtures::capture(&dir) {
+            Ok(target) => println!("Captured PipeWire outputs in {}", target.display()),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
         }
-    };
-    
-    tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
-        .init();
+        return Ok(());
+    }
+
+    // Initialize tracing with specified log level (can be changed at runtime)
+    let mut log_level = args.log_level.to_lowercase();
+    if let Err(e) = pw_api::logging::parse_filter(&log_level) {
+        eprintln!("{}, using 'warn'", e);
+        log_level = "warn".to_string();
+    }
+    pw_api::logging::init(&log_level);
+
+    // Load server configuration, command line arguments take precedence
+    let mut server_config = pw_api::server_config::load()?;
+    if let Some(port) = args.port {
+        server_config.server.port = port;
+    }
+    if args.localhost {
+        server_config.server.bind = "127.0.0.1".to_string();
+    }
+    if args.no_auto_link {
+        server_config.features.auto_link = false;
+    }
+    if args.demo {
+        // Features that link nodes or watch devices need a PipeWire daemon
+        pw_api::demo::enable();
+        let features = &mut server_config.features;
+        features.auto_link = false;
+        features.restore_links = false;
+        features.bluetooth_rules = false;
+        features.failover = false;
+        features.jack_rules = false;
+        tracing::info!("Demo mode: serving simulated PipeWire objects");
+    }
 
     // Create global application state (not tied to any specific node)
     let app_state = Arc::new(AppState::new());
+    app_state.set_config(server_config.clone());
+    if let Some(dir) = pw_api::config::state_dir() {
+        let grants = app_state.pairing.load(dir.join(pw_api::pairing::PAIRED_CLIENTS_FILE));
+        app_state.auth.set_paired(grants);
+    }
+    pw_api::command::set_timeouts(server_config.timeouts.command_timeouts());
+    pw_api::worker::init(server_config.server.workers);
+    pw_api::state_file::set_backup_count(server_config.server.state_backups);
+    match pw_api::store::open(&server_config.server.state_backend) {
+        Ok(store) => pw_api::store::set(store),
+        Err(e) => tracing::error!("{}, using state files", e),
+    }
+    if !args.demo {
+        match pw_api::backend::open(&server_config.server.pipewire_backend) {
+            Ok(backend) => {
+                tracing::info!("Reading PipeWire objects with {}", backend.name());
+                pw_api::backend::set(backend);
+            }
+            Err(e) => tracing::error!("{}, using pw-cli", e),
+        }
+    }
 
     // Load PipeWire object cache on startup
     if let Err(e) = app_state.refresh_object_cache() {
         tracing::warn!("Failed to load object cache on startup: {}", e);
     }
 
-    // Load and apply volume rules on startup
-    let volume_rules = pw_api::config::load_all_volume_rules();
-    if !volume_rules.is_empty() {
-        tracing::info!("Applying {} volume rule(s)", volume_rules.len());
-        if let Err(e) = pw_api::volume::apply_volume_rules(volume_rules) {
-            tracing::error!("Failed to apply volume rules: {}", e);
-        }
-    }
-
-    // Load and apply parameter rules on startup
-    let param_rules = pw_api::config::load_all_param_rules();
-    if !param_rules.is_empty() {
-        tracing::info!("Applying {} parameter rule(s)", param_rules.len());
-        if let Err(e) = pw_api::param_rules::apply_param_rules(&param_rules).await {
-            tracing::error!("Failed to apply parameter rules: {}", e);
-        }
-    }
+    // Load volume and parameter rules
+    let volume_rules = if server_config.features.volume_rules {
+        pw_api::config::load_all_volume_rules()
+    } else {
+        Vec::new()
+    };
+    let param_rules = if server_config.features.param_rules {
+        pw_api::config::load_all_param_rules()
+    } else {
+        Vec::new()
+    };
 
     // Load link rules unless disabled
-    if !args.no_auto_link {
+    if server_config.features.auto_link {
         // Load rules from config files (user config takes precedence over system config)
         let mut all_rules = pw_api::config::load_all_link_rules();
         
@@ -92,48 +152,195 @@ async fn main() -> Result<()> {
         
         tracing::info!("Total {} link rule(s) configured", all_rules.len());
         app_state.set_link_rules(all_rules);
+    }
 
-        // Apply startup rules
-        pw_api::link_scheduler::apply_startup_rules(app_state.clone()).await;
+    // Apply startup rules (volume, parameter, link); rules with wait_for
+    // are applied in the background when their node appears. Volume rules on
+    // stream properties also apply to streams appearing later.
+    let stream_volume_rules = volume_rules.clone();
+    let startup = pw_api::startup::run_startup(app_state.clone(), volume_rules, param_rules).await;
 
-        // If --no-api is set, exit now after applying rules
-        if args.no_api {
-            tracing::info!("Initial rules applied, exiting (--no-api mode)");
-            return Ok(());
+    // Restore manually created links saved with POST /api/v1/links/save
+    if server_config.features.restore_links {
+        match pw_api::worker::run(pw_api::link_state::restore_saved_links).await {
+            Ok(Err(e)) => tracing::error!("Failed to restore saved links: {}", e),
+            Err(e) => tracing::error!("Failed to restore saved links: {}", e),
+            Ok(Ok(_)) => {}
         }
+    }
 
-        // Start the link scheduler for periodic relinking
-        let _scheduler_handle = pw_api::link_scheduler::start_link_scheduler(app_state.clone());
-    } else if args.no_api {
-        // --no-api without link rules, just exit
-        tracing::info!("Volume rules applied, exiting (--no-api mode)");
+    // Load zones and enable the ones configured for startup
+    let zones = Arc::new(pw_api::zones::ZoneManager::new(pw_api::config::load_all_zones()));
+    app_state.set_zones(zones.clone());
+    if let Err(e) = pw_api::worker::run(move || zones.enable_startup_zones()).await {
+        tracing::error!("Failed to enable zones: {}", e);
+    }
+
+    app_state.set_scenes(Arc::new(pw_api::scenes::SceneManager::new(pw_api::config::load_all_scenes())));
+
+    // If --no-api is set, exit now after applying rules
+    if args.no_api {
+        let _ = startup.await;
+        tracing::info!("Initial rules applied, exiting (--no-api mode)");
         return Ok(());
     }
 
+    // Start the link scheduler for periodic relinking
+    if server_config.features.auto_link {
+        let _scheduler_handle = pw_api::link_scheduler::start_link_scheduler(app_state.clone());
+    }
+
+    // Link Bluetooth sources when they appear
+    if server_config.features.bluetooth_rules {
+        let bluetooth_rules = pw_api::config::load_all_bluetooth_rules();
+        if !bluetooth_rules.is_empty() {
+            pw_api::bluetooth_rules::start_bluetooth_rules(
+                bluetooth_rules,
+                std::time::Duration::from_millis(server_config.timeouts.bluetooth_poll_ms.max(100)),
+            );
+        }
+    }
+
+    // Move nodes to a backup sink when the primary sink disappears
+    if server_config.features.failover {
+        let failover_rules = pw_api::config::load_all_failover_rules();
+        if !failover_rules.is_empty() {
+            pw_api::failover::start_failover(
+                failover_rules,
+                std::time::Duration::from_millis(server_config.timeouts.failover_poll_ms.max(100)),
+            );
+        }
+    }
+
+    // Switch between headphones and speakers when headphones are plugged in
+    if server_config.features.jack_rules {
+        let jack_rules = pw_api::config::load_all_jack_rules();
+        if !jack_rules.is_empty() {
+            pw_api::jack_rules::start_jack_rules(
+                jack_rules,
+                std::time::Duration::from_millis(server_config.timeouts.jack_poll_ms.max(100)),
+            );
+        }
+    }
+
+    pw_api::link_factory::set_enabled(server_config.features.native_links);
+    pw_api::remote::set_cores(&server_config.cores);
+    pw_api::reverts::set_check_ms(server_config.timeouts.revert_check_ms);
+
+    // Suspend idle sinks
+    app_state.power.configure(server_config.power.clone());
+    if app_state.power.is_enabled() {
+        pw_api::power::start_power_manager(app_state.power.clone());
+    }
+
+    app_state.recordings.configure(server_config.recording.directory.as_ref().map(std::path::PathBuf::from));
+
+    // Feed a null sink to an external streaming server (e.g. snapserver)
+    app_state.stream_output.configure(server_config.stream_output.clone());
+    let stream_output = app_state.stream_output.clone();
+    if stream_output.is_configured() {
+        if stream_output.autostart() {
+            let manager = stream_output.clone();
+            match pw_api::worker::run(move || manager.start()).await {
+                Ok(Err(e)) => tracing::warn!("Failed to start stream output: {}", e),
+                Err(e) => tracing::warn!("Failed to start stream output: {}", e),
+                Ok(Ok(_)) => {}
+            }
+        }
+        pw_api::stream_output::start_stream_output(stream_output.clone());
+    }
+
     // Create node-specific state for modules that manage specific nodes
     // speakereq uses pattern matching to find speakereq2x2, speakereq4x4, etc.
     let speakereq_state = Arc::new(NodeState::with_pattern(
         "speakereq".to_string(),
-        r"speakereq[0-9]+x[0-9]+".to_string()
+        server_config.modules.speakereq_pattern.clone()
     ));
-    let riaa_state = Arc::new(NodeState::new("riaa".to_string()));
+    let riaa_state = Arc::new(NodeState::new(server_config.modules.riaa_node.clone()));
+
+    app_state.register_module(Arc::new(pw_api::modules::Module::new("speakereq", speakereq_state.clone())));
+    app_state.register_module(Arc::new(pw_api::modules::Module::new("riaa", riaa_state.clone())));
+
+    // Send state changes to the webhooks, run node hooks, duck volumes, set the
+    // volume of new streams, keep the event history and the stream statistics
+    let webhooks = pw_api::webhooks::start_webhooks(pw_api::config::load_all_webhooks());
+    let hooks = pw_api::script_hooks::start_script_hooks(server_config.hooks.clone());
+    app_state.ducking.configure(server_config.ducking.clone());
+    let ducking = pw_api::ducking::start_ducking(app_state.ducking.clone());
+    let stream_volumes = pw_api::volume::start_stream_volume_rules(stream_volume_rules);
+    if server_config.features.stream_stats {
+        pw_api::stream_stats::start_stream_stats();
+    }
+    pw_api::xrun_stats::start_xrun_stats(server_config.stats.sample_secs);
+    if server_config.stats.history {
+        pw_api::metrics_history::start_metrics_history(
+            server_config.stats.history_bucket_secs,
+            server_config.stats.history_days,
+        );
+    }
+    if webhooks.is_some()
+        || hooks.is_some()
+        || ducking.is_some()
+        || stream_volumes.is_some()
+        || server_config.features.event_history
+        || server_config.features.stream_stats
+    {
+        pw_api::events::start_event_monitor(
+            app_state.get_modules(),
+            std::time::Duration::from_millis(server_config.timeouts.event_poll_ms.max(100)),
+        );
+    }
+
+    // Keep the speakereq parameter cache in sync with changes made by other tools
+    if server_config.timeouts.param_monitor_ms > 0 {
+        pw_api::param_monitor::start_param_monitor(
+            speakereq_state.clone(),
+            std::time::Duration::from_millis(server_config.timeouts.param_monitor_ms),
+        );
+    }
     
     // Create router with global api and module-specific endpoints
     let app = pw_api::api::create_router(app_state.clone())
         .merge(pw_api::links::create_router(app_state.clone()))
         .merge(pw_api::speakereq::create_router(speakereq_state.clone()))
+        .merge(pw_api::speakereq::create_instances_router(&server_config.modules.speakereq_pattern))
         .merge(pw_api::riaa::create_router(riaa_state.clone()))
-        .merge(pw_api::settings::create_router(speakereq_state, riaa_state, Some(10)))
-        .merge(pw_api::graph::create_graph_router().with_state(app_state))
+        .merge(pw_api::settings::create_router(
+            speakereq_state.clone(),
+            riaa_state.clone(),
+            Some(server_config.timeouts.settings_autosave_secs),
+        ))
+        .merge(pw_api::graph::create_graph_router().with_state(app_state.clone()))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            pw_api::response_cache::middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            pw_api::auth::middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state,
+            pw_api::rate_limit::middleware,
+        ))
         .layer(CorsLayer::permissive());
+    // Serve /api/v1/cores/<name>/... with the routes above
+    let app = pw_api::remote::with_cores(app);
 
-    // Bind to localhost or all interfaces
-    let host = if args.localhost { "127.0.0.1" } else { "0.0.0.0" };
-    let addr = format!("{}:{}", host, args.port);
+    // Bind to the configured address (localhost with --localhost)
+    let addr = format!("{}:{}", server_config.server.bind, server_config.server.port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     tracing::info!("Server listening on http://{}", addr);
     
-    axum::serve(listener, app).await?;
+    // Stop accepting requests on SIGTERM/SIGINT and let running requests finish
+    pw_api::shutdown::serve(listener, app).await?;
+
+    if stream_output.is_configured() {
+        let _ = pw_api::worker::run(move || stream_output.stop()).await;
+    }
+
+    pw_api::shutdown::save_state(server_config.server.save_on_shutdown, &speakereq_state, &riaa_state).await;
+    tracing::info!("Server stopped");
 
     Ok(())
 }
@@ -0,0 +1,246 @@
+//! Playback of WAV files through a sink
+//!
+//! A file is played with `pw-play`, which creates a short-lived playback
+//! stream targeting the sink and ends when the file is played. Files are
+//! either uploaded (written to a temporary file that is removed afterwards)
+//! or taken from the sounds directory, e.g. test signals for identifying
+//! speakers or announcement chimes.
+
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use crate::pwcli::PwObject;
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Format of a WAV file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct WavInfo {
+    pub rate: u32,
+    pub channels: u16,
+    pub bits: u16,
+    pub duration_secs: f64,
+}
+
+/// A started playback
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Playback {
+    pub id: u32,
+    pub sink_id: u32,
+    pub sink: String,
+    /// File name in the sounds directory, None for uploads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub rate: u32,
+    pub channels: u16,
+    pub duration_secs: f64,
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// Read the format and duration from the chunks of a WAV file
+pub fn wav_info(data: &[u8]) -> Result<WavInfo, String> {
+    if data.get(0..4) != Some(b"RIFF") || data.get(8..12) != Some(b"WAVE") {
+        return Err("Not a WAV file".to_string());
+    }
+    let mut format = None;
+    let mut pos = 12;
+    while let (Some(id), Some(size)) = (data.get(pos..pos + 4), u32_at(data, pos + 4)) {
+        let body = pos + 8;
+        match id {
+            b"fmt " => {
+                let channels = u16_at(data, body + 2).ok_or("Truncated fmt chunk")?;
+                let rate = u32_at(data, body + 4).ok_or("Truncated fmt chunk")?;
+                let bits = u16_at(data, body + 14).ok_or("Truncated fmt chunk")?;
+                format = Some((rate, channels, bits));
+            }
+            b"data" => {
+                let (rate, channels, bits) = format.ok_or("WAV file has no fmt chunk before the data")?;
+                if rate == 0 || channels == 0 || bits == 0 {
+                    return Err("Invalid WAV format".to_string());
+                }
+                // Streamed files have no real size, use the rest of the file
+                let len = (size as usize).min(data.len().saturating_sub(body));
+                let frame = channels as usize * bits.div_ceil(8) as usize;
+                return Ok(WavInfo {
+                    rate,
+                    channels,
+                    bits,
+                    duration_secs: (len / frame) as f64 / rate as f64,
+                });
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        pos = body.saturating_add(size as usize + (size as usize & 1));
+    }
+    Err("WAV file has no data chunk".to_string())
+}
+
+/// Find an audio sink by ID or node.name
+pub fn find_sink<'a>(nodes: &'a [PwObject], sink: &str) -> Result<&'a PwObject, String> {
+    let node = nodes
+        .iter()
+        .find(|n| sink.parse::<u32>().ok() == Some(n.id) || n.name() == Some(sink))
+        .ok_or_else(|| format!("Sink {} not found", sink))?;
+    if !node.media_class().is_some_and(|c| c.starts_with("Audio/Sink")) {
+        return Err(format!("Node {} is not an audio sink", sink));
+    }
+    Ok(node)
+}
+
+/// Directory of the sounds (None: `sounds` in the state directory)
+pub fn sounds_dir(configured: Option<&str>) -> Result<PathBuf, String> {
+    match configured {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => crate::config::state_dir()
+            .map(|dir| dir.join("sounds"))
+            .ok_or_else(|| "Could not determine state directory".to_string()),
+    }
+}
+
+/// Arguments of pw-play
+pub fn play_args(sink: &str, volume: Option<f32>, path: &Path) -> Vec<String> {
+    let mut args = vec!["--target".to_string(), sink.to_string()];
+    if let Some(volume) = volume {
+        args.extend(["--volume".to_string(), volume.clamp(0.0, 1.0).to_string()]);
+    }
+    args.push(path.display().to_string());
+    args
+}
+
+/// Start playing a file on a sink; a temporary file is removed afterwards
+///
+/// Returns the playback and a handle that finishes when it ended.
+pub fn play(
+    sink: &PwObject,
+    path: PathBuf,
+    info: WavInfo,
+    volume: Option<f32>,
+    temporary: bool,
+) -> Result<(Playback, tokio::task::JoinHandle<()>), String> {
+    let name = sink.name().ok_or_else(|| format!("Sink {} has no name", sink.id))?.to_string();
+    let spawned = Command::from(crate::command::pipewire("pw-play"))
+        .args(play_args(&name, volume, &path))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            if temporary {
+                let _ = std::fs::remove_file(&path);
+            }
+            return Err(format!("Failed to start pw-play: {}", e));
+        }
+    };
+
+    let playback = Playback {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        sink_id: sink.id,
+        sink: name,
+        file: if temporary { None } else { path.file_name().map(|n| n.to_string_lossy().to_string()) },
+        rate: info.rate,
+        channels: info.channels,
+        duration_secs: info.duration_secs,
+    };
+    let id = playback.id;
+    let handle = tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if !status.success() => warn!("Playback {} ended with {}", id, status),
+            Err(e) => warn!("Playback {} failed: {}", id, e),
+            Ok(_) => debug!("Playback {} finished", id),
+        }
+        if temporary {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+    });
+    Ok((playback, handle))
+}
+
+/// Write an uploaded file to a temporary file for pw-play
+///
+/// The file gets a random name and is created exclusively, so a file or
+/// symlink placed in the temporary directory beforehand is never written to.
+pub async fn write_upload(data: &[u8]) -> Result<PathBuf, String> {
+    let data = data.to_vec();
+    tokio::task::spawn_blocking(move || {
+        let mut file = tempfile::Builder::new()
+            .prefix("pw-api-play-")
+            .suffix(".wav")
+            .tempfile()
+            .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+        file.write_all(&data)
+            .map_err(|e| format!("Failed to write {}: {}", file.path().display(), e))?;
+        // Removed by play() when the playback ended
+        file.into_temp_path()
+            .keep()
+            .map_err(|e| format!("Failed to keep temporary file: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Worker error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(rate: u32, channels: u16, frames: usize) -> Vec<u8> {
+        let len = frames * channels as usize * 2;
+        let mut data = crate::capture::wav_header(rate, channels, Some(len as u32));
+        data.resize(data.len() + len, 0);
+        data
+    }
+
+    #[test]
+    fn test_wav_info() {
+        let info = wav_info(&wav(48000, 2, 24000)).unwrap();
+        assert_eq!((info.rate, info.channels, info.bits), (48000, 2, 16));
+        assert!((info.duration_secs - 0.5).abs() < 1e-9);
+
+        // Streamed WAV with maximum size: the rest of the file counts
+        let mut streamed = crate::capture::wav_header(44100, 1, None);
+        streamed.resize(streamed.len() + 44100 * 2, 0);
+        assert!((wav_info(&streamed).unwrap().duration_secs - 1.0).abs() < 1e-9);
+
+        assert!(wav_info(b"ID3\x03not a wav file").is_err());
+        assert!(wav_info(&wav(48000, 2, 10)[..36]).is_err());
+    }
+
+    #[test]
+    fn test_find_sink() {
+        let node =
+            |id: u32, name: &str, class: &str| PwObject::new(id, "Node", &[("node.name", name), ("media.class", class)]);
+        let nodes = vec![node(56, "output_1", "Audio/Sink"), node(60, "mic", "Audio/Source")];
+        assert_eq!(find_sink(&nodes, "output_1").unwrap().id, 56);
+        assert_eq!(find_sink(&nodes, "56").unwrap().id, 56);
+        assert!(find_sink(&nodes, "mic").is_err());
+        assert!(find_sink(&nodes, "output_2").is_err());
+
+        let args = play_args("output_1", Some(1.5), Path::new("/tmp/chime.wav"));
+        assert_eq!(args, vec!["--target", "output_1", "--volume", "1", "/tmp/chime.wav"]);
+    }
+
+    #[tokio::test]
+    async fn test_write_upload() {
+        let data = wav(48000, 2, 10);
+        let first = write_upload(&data).await.unwrap();
+        let second = write_upload(&data).await.unwrap();
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read(&first).unwrap(), data);
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+}
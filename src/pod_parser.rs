@@ -0,0 +1,339 @@
+//! Parser for SPA POD dumps printed by `pw-cli enum-params`
+//!
+//! pw-cli prints parameter objects as an indented tree:
+//!
+//! ```text
+//!   Object: size 232, type Spa:Pod:Object:Param:Props (262146), id Spa:Enum:ParamId:Props (2)
+//!     Prop: key Spa:Pod:Object:Param:Props:volume (65539), flags 00000000
+//!       Float 1.000000
+//!     Prop: key Spa:Pod:Object:Param:Props:channelVolumes (65544), flags 00000000
+//!       Array: child.size 4, child.type Spa:Float
+//!         Float 1.000000
+//!         Float 1.000000
+//! ```
+//!
+//! This module converts such dumps into JSON. Property keys are resolved to
+//! names using [`crate::spa_props`], so numeric keys never leak into responses.
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+use std::sync::OnceLock;
+
+use crate::spa_props::{self, ParamKind};
+
+/// A parameter object parsed from pw-cli output
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ParamObject {
+    /// Object type, e.g. "Props", "Route", "Format"
+    #[serde(rename = "type")]
+    pub object_type: String,
+    /// Parameter id, e.g. "Props", "EnumRoute"
+    pub id: String,
+    /// Properties with resolved names
+    pub properties: Map<String, Value>,
+}
+
+/// A line of the dump with its indentation level
+struct Line<'a> {
+    indent: usize,
+    text: &'a str,
+}
+
+/// A node in the indentation tree
+struct Node<'a> {
+    text: &'a str,
+    children: Vec<Node<'a>>,
+}
+
+fn object_header_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^Object: size \d+, type (\S+)(?: \((\d+)\))?, id (\S+)(?: \((\d+)\))?").unwrap()
+    })
+}
+
+fn prop_key_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^Prop: key ([^\s,]+)(?: \((\d+)\))?").unwrap())
+}
+
+/// Build a tree from indented lines, starting at `pos`, for all lines
+/// indented deeper than `parent_indent`
+fn build_tree<'a>(lines: &[Line<'a>], pos: &mut usize, parent_indent: Option<usize>) -> Vec<Node<'a>> {
+    let mut nodes = Vec::new();
+    while *pos < lines.len() {
+        let line = &lines[*pos];
+        if let Some(parent) = parent_indent {
+            if line.indent <= parent {
+                break;
+            }
+        }
+        *pos += 1;
+        let children = build_tree(lines, pos, Some(line.indent));
+        nodes.push(Node { text: line.text, children });
+    }
+    nodes
+}
+
+/// Short name of a SPA type, e.g. "Spa:Pod:Object:Param:Props" -> "Props"
+fn short_type_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Resolve the name of a property key from a `Prop:` line
+fn resolve_key_name(text: &str, kind: Option<ParamKind>) -> String {
+    let Some(caps) = prop_key_regex().captures(text) else {
+        return text.to_string();
+    };
+    let key_text = caps.get(1).map(|m| m.as_str()).unwrap_or("");
+    let number = caps.get(2).and_then(|m| m.as_str().parse::<u32>().ok());
+
+    // Key printed as a bare number
+    if let Ok(n) = key_text.parse::<u32>() {
+        return spa_props::display_name(kind, n);
+    }
+
+    let short = short_type_name(key_text);
+    // Type info is present but the last segment is numeric (unknown to pw-cli)
+    if let Ok(n) = short.parse::<u32>() {
+        return spa_props::display_name(kind, n);
+    }
+    if short.is_empty() {
+        if let Some(n) = number {
+            return spa_props::display_name(kind, n);
+        }
+    }
+    short.to_string()
+}
+
+/// Convert a number string to a JSON number
+fn number_value(s: &str) -> Value {
+    if let Ok(i) = s.parse::<i64>() {
+        return Value::Number(i.into());
+    }
+    s.parse::<f64>()
+        .ok()
+        .and_then(Number::from_f64)
+        .map(Value::Number)
+        .unwrap_or_else(|| Value::String(s.to_string()))
+}
+
+/// Convert a value node to JSON
+fn value_from_node(node: &Node) -> Value {
+    let text = node.text;
+    let (head, rest) = match text.split_once(' ') {
+        Some((h, r)) => (h, r.trim()),
+        None => (text, ""),
+    };
+
+    match head {
+        "Bool" => Value::Bool(rest == "true"),
+        "Int" | "Long" | "Float" | "Double" => number_value(rest),
+        "String" => Value::String(rest.trim_matches('"').to_string()),
+        "None" => Value::Null,
+        "Id" => {
+            // "Id 1        (Spa:Enum:Direction:Output)"
+            match rest.find('(') {
+                Some(p) => Value::String(short_type_name(rest[p + 1..].trim_end_matches(')')).to_string()),
+                None => number_value(rest),
+            }
+        }
+        "Array:" | "Struct:" => {
+            Value::Array(node.children.iter().map(value_from_node).collect())
+        }
+        "Choice:" => {
+            // "Choice: type Spa:Enum:Choice:Range, flags 00000000, size 40"
+            let choice_type = rest
+                .strip_prefix("type ")
+                .and_then(|s| s.split(',').next())
+                .map(short_type_name)
+                .unwrap_or("None");
+            let values: Vec<Value> = node.children.iter().map(value_from_node).collect();
+            let mut map = Map::new();
+            map.insert("choice".to_string(), Value::String(choice_type.to_string()));
+            map.insert("values".to_string(), Value::Array(values));
+            Value::Object(map)
+        }
+        "Object:" => match parse_object(node) {
+            Some(obj) => Value::Object(obj.properties),
+            None => Value::Null,
+        },
+        _ => Value::String(text.to_string()),
+    }
+}
+
+/// Convert the "params" struct of filter-chain nodes (alternating name/value)
+/// into a JSON object
+fn params_struct_to_object(value: Value) -> Value {
+    let Value::Array(items) = &value else {
+        return value;
+    };
+    if items.len() % 2 != 0 {
+        return value;
+    }
+    let mut map = Map::new();
+    for pair in items.chunks(2) {
+        match &pair[0] {
+            Value::String(name) => {
+                map.insert(name.clone(), pair[1].clone());
+            }
+            _ => return value,
+        }
+    }
+    Value::Object(map)
+}
+
+/// Parse an `Object:` node
+fn parse_object(node: &Node) -> Option<ParamObject> {
+    let caps = object_header_regex().captures(node.text)?;
+    let type_name = caps.get(1).map(|m| m.as_str()).unwrap_or("");
+    let id_name = caps.get(3).map(|m| m.as_str()).unwrap_or("");
+    let kind = ParamKind::from_type_name(type_name);
+
+    let mut properties = Map::new();
+    for child in &node.children {
+        if !child.text.starts_with("Prop:") {
+            continue;
+        }
+        let name = resolve_key_name(child.text, kind);
+        let mut value = match child.children.first() {
+            Some(v) => value_from_node(v),
+            None => Value::Null,
+        };
+        if name == "params" {
+            value = params_struct_to_object(value);
+        }
+        properties.insert(name, value);
+    }
+
+    Some(ParamObject {
+        object_type: short_type_name(type_name).to_string(),
+        id: short_type_name(id_name).to_string(),
+        properties,
+    })
+}
+
+/// Parse the output of `pw-cli enum-params <id> <type>` into parameter objects
+pub fn parse_enum_params(output: &str) -> Vec<ParamObject> {
+    let lines: Vec<Line> = output
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| Line {
+            indent: l.len() - l.trim_start().len(),
+            text: l.trim(),
+        })
+        .collect();
+
+    let mut pos = 0;
+    let tree = build_tree(&lines, &mut pos, None);
+
+    let mut objects = Vec::new();
+    collect_objects(&tree, &mut objects);
+    objects
+}
+
+/// Find top-level `Object:` nodes anywhere in the tree
+fn collect_objects(nodes: &[Node], objects: &mut Vec<ParamObject>) {
+    for node in nodes {
+        if node.text.starts_with("Object:") {
+            if let Some(obj) = parse_object(node) {
+                objects.push(obj);
+            }
+        } else {
+            collect_objects(&node.children, objects);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROPS_OUTPUT: &str = r#"
+  Object: size 232, type Spa:Pod:Object:Param:Props (262146), id Spa:Enum:ParamId:Props (2)
+    Prop: key Spa:Pod:Object:Param:Props:volume (65539), flags 00000000
+      Float 0.500000
+    Prop: key Spa:Pod:Object:Param:Props:mute (65540), flags 00000000
+      Bool false
+    Prop: key Spa:Pod:Object:Param:Props:channelVolumes (65544), flags 00000000
+      Array: child.size 4, child.type Spa:Float
+        Float 1.000000
+        Float 0.250000
+    Prop: key Spa:Pod:Object:Param:Props:65551 (65551), flags 00000000
+      Bool true
+    Prop: key Spa:Pod:Object:Param:Props:params (524289), flags 00000000
+      Struct: size 64
+        String "riaa:Gain (dB)"
+        Float 3.000000
+        String "riaa:Enable"
+        Bool true
+"#;
+
+    #[test]
+    fn test_parse_props() {
+        let objects = parse_enum_params(PROPS_OUTPUT);
+        assert_eq!(objects.len(), 1);
+        let obj = &objects[0];
+        assert_eq!(obj.object_type, "Props");
+        assert_eq!(obj.id, "Props");
+        assert_eq!(obj.properties["volume"], serde_json::json!(0.5));
+        assert_eq!(obj.properties["mute"], serde_json::json!(false));
+        assert_eq!(obj.properties["channelVolumes"], serde_json::json!([1.0, 0.25]));
+        assert_eq!(obj.properties["params"]["riaa:Gain (dB)"], serde_json::json!(3.0));
+    }
+
+    #[test]
+    fn test_numeric_keys_are_resolved() {
+        let objects = parse_enum_params(PROPS_OUTPUT);
+        // 65551 printed without a name must be resolved via spa_props
+        assert_eq!(objects[0].properties["softMute"], serde_json::json!(true));
+        assert!(objects[0].properties.keys().all(|k| !k.starts_with("prop_")));
+    }
+
+    #[test]
+    fn test_parse_route_with_nested_props() {
+        let output = r#"
+  Object: size 400, type Spa:Pod:Object:Param:Route (262153), id Spa:Enum:ParamId:Route (13)
+    Prop: key Spa:Pod:Object:Param:Route:index (1), flags 00000000
+      Int 2
+    Prop: key Spa:Pod:Object:Param:Route:direction (2), flags 00000000
+      Id 1        (Spa:Enum:Direction:Output)
+    Prop: key Spa:Pod:Object:Param:Route:name (4), flags 00000000
+      String "analog-output-headphones"
+    Prop: key 10 (10), flags 00000000
+      Object: size 80, type Spa:Pod:Object:Param:Props (262146), id Spa:Enum:ParamId:Route (13)
+        Prop: key 65540, flags 00000000
+          Bool true
+"#;
+        let objects = parse_enum_params(output);
+        assert_eq!(objects.len(), 1);
+        let route = &objects[0];
+        assert_eq!(route.object_type, "Route");
+        assert_eq!(route.properties["index"], serde_json::json!(2));
+        assert_eq!(route.properties["direction"], serde_json::json!("Output"));
+        assert_eq!(route.properties["name"], serde_json::json!("analog-output-headphones"));
+        assert_eq!(route.properties["props"]["mute"], serde_json::json!(true));
+    }
+
+    #[test]
+    fn test_parse_choice_and_multiple_objects() {
+        let output = r#"
+  Object: size 100, type Spa:Pod:Object:Param:Format (262147), id Spa:Enum:ParamId:EnumFormat (3)
+    Prop: key Spa:Pod:Object:Param:Format:Audio:rate (65539), flags 00000000
+      Choice: type Spa:Enum:Choice:Range, flags 00000000, size 40
+        Int 48000
+        Int 1
+        Int 384000
+  Object: size 100, type Spa:Pod:Object:Param:Format (262147), id Spa:Enum:ParamId:EnumFormat (3)
+    Prop: key Spa:Pod:Object:Param:Format:Audio:channels (65540), flags 00000000
+      Int 2
+"#;
+        let objects = parse_enum_params(output);
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].id, "EnumFormat");
+        assert_eq!(objects[0].properties["rate"]["choice"], serde_json::json!("Range"));
+        assert_eq!(objects[0].properties["rate"]["values"], serde_json::json!([48000, 1, 384000]));
+        assert_eq!(objects[1].properties["channels"], serde_json::json!(2));
+    }
+}
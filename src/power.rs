@@ -0,0 +1,317 @@
+//! Suspending of idle sinks
+//!
+//! Some DACs and amplifiers only go to standby when their ALSA device is
+//! closed. The power manager suspends the sinks configured in the `[power]`
+//! section after `idle_minutes` without a stream, also when they are still
+//! linked to a filter-chain. A suspended sink is resumed by PipeWire when it
+//! is needed again.
+//!
+//! A sink counts as active while a stream (`Stream/*` node) is linked to it,
+//! directly or through filter-chains. Sinks can be kept awake or suspended
+//! at once with the `/api/v1/power` endpoints.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::pwcli::{self, PwObject};
+use crate::server_config::PowerSection;
+use crate::util::regex_match;
+
+/// Interval for checking the sinks
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// State of a managed sink
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerState {
+    /// A stream is playing to the sink
+    Active,
+    /// No stream, the sink will be suspended
+    Idle,
+    /// Suspended by the power manager
+    Suspended,
+}
+
+/// Status of a managed sink
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PowerNodeStatus {
+    pub id: u32,
+    pub name: String,
+    pub state: PowerState,
+    /// Seconds since a stream was last linked
+    pub idle_secs: u64,
+    /// Never suspended by the power manager
+    pub keep_awake: bool,
+}
+
+#[derive(Debug, Clone)]
+struct PowerNode {
+    id: u32,
+    last_active: Instant,
+    active: bool,
+    suspended: bool,
+}
+
+/// Tracks the managed sinks and decides when to suspend them
+#[derive(Debug, Default)]
+pub struct PowerManager {
+    config: RwLock<PowerSection>,
+    nodes: Mutex<HashMap<String, PowerNode>>,
+    keep_awake: Mutex<HashSet<String>>,
+}
+
+/// Input node of a filter-chain for its output stream
+///
+/// Filter-chains play to their sink with a stream node (`<name>.output` or
+/// `<name>_output.proc`); the chain continues at its input sink.
+fn chain_input(objects: &[PwObject], node: &PwObject) -> Option<u32> {
+    let name = node.name()?;
+    let input = match name.strip_suffix(".output") {
+        Some(prefix) => prefix.to_string(),
+        None => format!("{}_input.proc", name.strip_suffix("_output.proc")?),
+    };
+    objects
+        .iter()
+        .find(|o| o.object_type == "Node" && o.name() == Some(input.as_str()))
+        .map(|o| o.id)
+}
+
+/// Check whether a stream reaches a node through links (e.g. via filter-chains)
+fn is_fed_by_stream(objects: &[PwObject], node_id: u32) -> bool {
+    let mut visited = HashSet::new();
+    let mut pending = vec![node_id];
+    while let Some(id) = pending.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+        for link in objects.iter().filter(|o| o.object_type == "Link") {
+            if link.get("link.input.node").and_then(|v| v.parse::<u32>().ok()) != Some(id) {
+                continue;
+            }
+            let Some(output) = link.get("link.output.node").and_then(|v| v.parse::<u32>().ok()) else {
+                continue;
+            };
+            let Some(node) = objects.iter().find(|o| o.id == output && o.object_type == "Node") else {
+                continue;
+            };
+            if let Some(input) = chain_input(objects, node) {
+                pending.push(input);
+            } else if node.media_class().map(|c| c.starts_with("Stream/")).unwrap_or(false) {
+                return true;
+            } else {
+                pending.push(output);
+            }
+        }
+    }
+    false
+}
+
+impl PowerManager {
+    /// Set the sinks to manage and the idle time
+    pub fn configure(&self, config: PowerSection) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Whether any sinks are configured
+    pub fn is_enabled(&self) -> bool {
+        !self.config.read().unwrap().nodes.is_empty()
+    }
+
+    /// Update the sinks from the current objects and get the ones to suspend
+    pub fn update(&self, objects: &[PwObject], now: Instant) -> Vec<(u32, String)> {
+        let config = self.config.read().unwrap().clone();
+        let idle = Duration::from_secs(config.idle_minutes * 60);
+        let keep_awake = self.keep_awake.lock().unwrap().clone();
+        let mut nodes = self.nodes.lock().unwrap();
+
+        let managed: Vec<&PwObject> = objects
+            .iter()
+            .filter(|o| o.object_type == "Node" && o.media_class() == Some("Audio/Sink"))
+            .filter(|o| {
+                let name = o.name().unwrap_or_default();
+                config.nodes.iter().any(|pattern| regex_match(pattern, name))
+            })
+            .collect();
+        nodes.retain(|name, _| managed.iter().any(|o| o.name() == Some(name.as_str())));
+
+        let mut to_suspend = Vec::new();
+        for obj in managed {
+            let name = obj.name().unwrap_or_default().to_string();
+            let active = is_fed_by_stream(objects, obj.id);
+            let node = nodes.entry(name.clone()).or_insert(PowerNode {
+                id: obj.id,
+                last_active: now,
+                active,
+                suspended: false,
+            });
+            node.id = obj.id;
+            node.active = active;
+            if active {
+                node.last_active = now;
+                if node.suspended {
+                    info!("Power: {} resumed", name);
+                    node.suspended = false;
+                }
+            } else if !node.suspended && !keep_awake.contains(&name) && now.duration_since(node.last_active) >= idle {
+                to_suspend.push((obj.id, name));
+            }
+        }
+        to_suspend
+    }
+
+    /// Record that a sink was suspended
+    pub fn mark_suspended(&self, name: &str) {
+        if let Some(node) = self.nodes.lock().unwrap().get_mut(name) {
+            node.suspended = true;
+        }
+    }
+
+    /// Keep a sink awake or let it be suspended when idle again
+    pub fn set_keep_awake(&self, name: &str, keep_awake: bool) {
+        let mut set = self.keep_awake.lock().unwrap();
+        if keep_awake {
+            set.insert(name.to_string());
+        } else {
+            set.remove(name);
+        }
+    }
+
+    /// Get the ID of a managed sink
+    pub fn node_id(&self, name: &str) -> Option<u32> {
+        self.nodes.lock().unwrap().get(name).map(|n| n.id)
+    }
+
+    /// Status of the managed sinks, sorted by name
+    pub fn status(&self, now: Instant) -> Vec<PowerNodeStatus> {
+        let keep_awake = self.keep_awake.lock().unwrap();
+        let mut status: Vec<PowerNodeStatus> = self
+            .nodes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, node)| PowerNodeStatus {
+                id: node.id,
+                name: name.clone(),
+                state: if node.suspended {
+                    PowerState::Suspended
+                } else if node.active {
+                    PowerState::Active
+                } else {
+                    PowerState::Idle
+                },
+                idle_secs: if node.active { 0 } else { now.duration_since(node.last_active).as_secs() },
+                keep_awake: keep_awake.contains(name),
+            })
+            .collect();
+        status.sort_by(|a, b| a.name.cmp(&b.name));
+        status
+    }
+
+    /// Minutes without links before a sink is suspended
+    pub fn idle_minutes(&self) -> u64 {
+        self.config.read().unwrap().idle_minutes
+    }
+}
+
+/// Suspend a managed sink now
+pub fn suspend(manager: &PowerManager, name: &str) -> Result<(), String> {
+    let id = manager
+        .node_id(name)
+        .ok_or_else(|| format!("Sink '{}' is not managed", name))?;
+    pwcli::suspend_node(id)?;
+    manager.mark_suspended(name);
+    info!("Power: {} suspended", name);
+    Ok(())
+}
+
+/// Check the sinks once and suspend the idle ones
+pub fn check(manager: &PowerManager) -> Result<(), String> {
+    let objects = pwcli::list_all()?;
+    for (id, name) in manager.update(&objects, Instant::now()) {
+        match pwcli::suspend_node(id) {
+            Ok(()) => {
+                manager.mark_suspended(&name);
+                info!("Power: {} suspended after {} minutes without streams", name, manager.idle_minutes());
+            }
+            Err(e) => warn!("Power: failed to suspend {}: {}", name, e),
+        }
+    }
+    Ok(())
+}
+
+/// Start the task that suspends idle sinks
+pub fn start_power_manager(manager: Arc<PowerManager>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+        info!("Power manager started");
+
+        loop {
+            ticker.tick().await;
+            let manager = manager.clone();
+            match crate::worker::run(move || check(&manager)).await {
+                Ok(Err(e)) => warn!("Failed to check sinks for suspend: {}", e),
+                Ok(Ok(())) => {}
+                Err(e) => warn!("Failed to check sinks for suspend: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn objects(playing: bool) -> Vec<PwObject> {
+        let mut objects = vec![
+            PwObject::new(40, "Node", &[("node.name", "alsa_output.dac"), ("media.class", "Audio/Sink")]),
+            PwObject::new(41, "Node", &[("node.name", "speakereq2x2.output"), ("media.class", "Stream/Output/Audio")]),
+            PwObject::new(42, "Node", &[("node.name", "speakereq2x2"), ("media.class", "Audio/Sink")]),
+            PwObject::new(50, "Link", &[("link.output.node", "41"), ("link.input.node", "40")]),
+        ];
+        if playing {
+            let spotify = [("node.name", "spotify"), ("media.class", "Stream/Output/Audio")];
+            objects.push(PwObject::new(60, "Node", &spotify));
+            objects.push(PwObject::new(61, "Link", &[("link.output.node", "60"), ("link.input.node", "42")]));
+        }
+        objects
+    }
+
+    fn manager() -> PowerManager {
+        let manager = PowerManager::default();
+        manager.configure(PowerSection {
+            nodes: vec!["^alsa_output\\.".to_string()],
+            idle_minutes: 10,
+        });
+        manager
+    }
+
+    #[test]
+    fn test_suspend_after_idle_time() {
+        let manager = manager();
+        let start = Instant::now();
+        assert!(manager.update(&objects(false), start).is_empty());
+        let later = start + Duration::from_secs(600);
+        assert_eq!(manager.update(&objects(false), later), vec![(40, "alsa_output.dac".to_string())]);
+
+        manager.mark_suspended("alsa_output.dac");
+        assert!(manager.update(&objects(false), later).is_empty());
+        assert_eq!(manager.status(later)[0].state, PowerState::Suspended);
+
+        // A stream through the filter-chain resumes it
+        assert!(manager.update(&objects(true), later).is_empty());
+        assert_eq!(manager.status(later)[0].state, PowerState::Active);
+    }
+
+    #[test]
+    fn test_keep_awake() {
+        let manager = manager();
+        let start = Instant::now();
+        manager.set_keep_awake("alsa_output.dac", true);
+        manager.update(&objects(false), start);
+        assert!(manager.update(&objects(false), start + Duration::from_secs(3600)).is_empty());
+        assert!(manager.status(start)[0].keep_awake);
+    }
+}
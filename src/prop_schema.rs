@@ -0,0 +1,226 @@
+//! Control discovery from PropInfo parameters
+//!
+//! Every node describes its controls with `PropInfo` parameter objects: the
+//! name, the value type with default and range (a Choice pod) and optional
+//! labels for enumerated values. Built-in controls (volume, mute, ...) are
+//! identified by a Props key, filter-chain plugin controls (LADSPA, LV2,
+//! builtin) by a name like `eq:Gain` and the `params` flag.
+//!
+//! The schema is the basis for generating a UI for any filter-chain.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::pod_parser::ParamObject;
+use crate::pwparams;
+
+/// A label for one value of an enumerated control
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PropLabel {
+    pub value: Value,
+    pub label: String,
+}
+
+/// Description of one control of a node
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PropSchema {
+    /// Control name; Props key for built-in controls, e.g. `volume`,
+    /// control name for filter-chain controls, e.g. `eq:Gain`
+    pub name: String,
+    /// Value type: bool, int, float or string
+    #[serde(rename = "type")]
+    pub value_type: String,
+    /// True for filter-chain controls, which are set in the `params` struct
+    /// (see PUT /api/v1/nodes/:id/props)
+    pub param: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<Value>,
+    /// Allowed values of enumerated controls
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<PropLabel>,
+}
+
+/// Get the type name of a JSON value parsed from a pod
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "int",
+        Value::String(_) => "string",
+        _ => "unknown",
+    }
+}
+
+/// Convert the alternating value/label struct of a PropInfo into labels
+fn parse_labels(value: Option<&Value>) -> Vec<PropLabel> {
+    let Some(Value::Array(items)) = value else {
+        return Vec::new();
+    };
+    items
+        .chunks(2)
+        .filter_map(|pair| match pair {
+            [value, Value::String(label)] => Some(PropLabel {
+                value: value.clone(),
+                label: label.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Build the schema of a control from a PropInfo parameter object
+pub fn from_prop_info(info: &ParamObject) -> Option<PropSchema> {
+    let props = &info.properties;
+    let param = props.get("params").and_then(|v| v.as_bool()).unwrap_or(false);
+    let description = props.get("description").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+    // Filter-chain controls are identified by name, built-in controls by key
+    let name = if param {
+        props.get("name").and_then(|v| v.as_str())
+    } else {
+        props
+            .get("id")
+            .and_then(|v| v.as_str())
+            .or_else(|| props.get("name").and_then(|v| v.as_str()))
+    }?
+    .to_string();
+
+    let mut schema = PropSchema {
+        name,
+        value_type: "unknown".to_string(),
+        param,
+        description,
+        default: None,
+        min: None,
+        max: None,
+        step: None,
+        options: Vec::new(),
+        labels: parse_labels(props.get("labels")),
+    };
+
+    match props.get("type") {
+        // Choice pods are parsed to {"choice": ..., "values": [default, ...]}
+        Some(Value::Object(choice)) => {
+            let values = choice.get("values").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            let kind = choice.get("choice").and_then(|v| v.as_str()).unwrap_or("None");
+            schema.value_type = values.first().map(value_type).unwrap_or("unknown").to_string();
+            schema.default = values.first().cloned();
+            match kind {
+                "Range" | "Step" => {
+                    schema.min = values.get(1).cloned();
+                    schema.max = values.get(2).cloned();
+                    if kind == "Step" {
+                        schema.step = values.get(3).cloned();
+                    }
+                }
+                "Enum" | "Flags" => schema.options = values.into_iter().skip(1).collect(),
+                _ => {}
+            }
+        }
+        Some(value) => {
+            schema.value_type = value_type(value).to_string();
+            schema.default = Some(value.clone());
+        }
+        None => {}
+    }
+
+    Some(schema)
+}
+
+/// Read the control schema of a node with `pw-cli enum-params <id> PropInfo`
+pub fn read_schema(id: u32) -> Result<Vec<PropSchema>, String> {
+    let infos = pwparams::enum_params(id, "PropInfo")?;
+    Ok(infos.iter().filter_map(from_prop_info).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pod_parser::parse_enum_params;
+    use serde_json::json;
+
+    const PROP_INFO_OUTPUT: &str = r#"
+  Object: size 128, type Spa:Pod:Object:Param:PropInfo (262146), id Spa:Enum:ParamId:PropInfo (1)
+    Prop: key Spa:Pod:Object:Param:PropInfo:id (1), flags 00000000
+      Id 65539        (Spa:Pod:Object:Param:Props:volume)
+    Prop: key Spa:Pod:Object:Param:PropInfo:description (7), flags 00000000
+      String "Volume"
+    Prop: key Spa:Pod:Object:Param:PropInfo:type (3), flags 00000000
+      Choice: type Spa:Enum:Choice:Range, flags 00000000, size 40
+        Float 1.000000
+        Float 0.000000
+        Float 10.000000
+  Object: size 160, type Spa:Pod:Object:Param:PropInfo (262146), id Spa:Enum:ParamId:PropInfo (1)
+    Prop: key Spa:Pod:Object:Param:PropInfo:name (2), flags 00000000
+      String "eq:Gain"
+    Prop: key Spa:Pod:Object:Param:PropInfo:type (3), flags 00000000
+      Choice: type Spa:Enum:Choice:Range, flags 00000000, size 40
+        Float 0.000000
+        Float -24.000000
+        Float 24.000000
+    Prop: key Spa:Pod:Object:Param:PropInfo:params (6), flags 00000000
+      Bool true
+  Object: size 200, type Spa:Pod:Object:Param:PropInfo (262146), id Spa:Enum:ParamId:PropInfo (1)
+    Prop: key Spa:Pod:Object:Param:PropInfo:name (2), flags 00000000
+      String "eq:Type"
+    Prop: key Spa:Pod:Object:Param:PropInfo:type (3), flags 00000000
+      Choice: type Spa:Enum:Choice:Enum, flags 00000000, size 60
+        Int 0
+        Int 0
+        Int 1
+    Prop: key Spa:Pod:Object:Param:PropInfo:labels (4), flags 00000000
+      Struct: size 64
+        Int 0
+        String "Peaking"
+        Int 1
+        String "Low shelf"
+    Prop: key Spa:Pod:Object:Param:PropInfo:params (6), flags 00000000
+      Bool true
+  Object: size 96, type Spa:Pod:Object:Param:PropInfo (262146), id Spa:Enum:ParamId:PropInfo (1)
+    Prop: key Spa:Pod:Object:Param:PropInfo:name (2), flags 00000000
+      String "eq:Enable"
+    Prop: key Spa:Pod:Object:Param:PropInfo:type (3), flags 00000000
+      Bool true
+    Prop: key Spa:Pod:Object:Param:PropInfo:params (6), flags 00000000
+      Bool true
+"#;
+
+    #[test]
+    fn test_builtin_and_param_controls() {
+        let schema: Vec<PropSchema> = parse_enum_params(PROP_INFO_OUTPUT)
+            .iter()
+            .filter_map(from_prop_info)
+            .collect();
+        assert_eq!(schema.len(), 4);
+
+        assert_eq!(schema[0].name, "volume");
+        assert!(!schema[0].param);
+        assert_eq!(schema[0].description.as_deref(), Some("Volume"));
+        assert_eq!(schema[0].value_type, "float");
+        assert_eq!(schema[0].max, Some(json!(10.0)));
+
+        assert_eq!(schema[1].name, "eq:Gain");
+        assert!(schema[1].param);
+        assert_eq!(schema[1].default, Some(json!(0.0)));
+        assert_eq!(schema[1].min, Some(json!(-24.0)));
+        assert_eq!(schema[1].max, Some(json!(24.0)));
+
+        assert_eq!(schema[2].value_type, "int");
+        assert_eq!(schema[2].options, vec![json!(0), json!(1)]);
+        assert_eq!(schema[2].labels[1], PropLabel { value: json!(1), label: "Low shelf".to_string() });
+
+        assert_eq!(schema[3].value_type, "bool");
+        assert_eq!(schema[3].default, Some(json!(true)));
+        assert_eq!(schema[3].min, None);
+    }
+}
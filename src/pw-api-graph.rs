@@ -0,0 +1,94 @@
+use clap::Parser;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use pw_api::{graph, pwcli, topology};
+
+#[derive(Parser, Debug)]
+#[command(name = "pw-api-graph")]
+#[command(about = "Render the PipeWire audio topology to files without the API server", long_about = None)]
+struct Args {
+    /// Write the graph in graphviz DOT format ("-" for stdout)
+    #[arg(long, value_name = "FILE")]
+    dot: Option<PathBuf>,
+
+    /// Render the graph to a PNG image (graphviz or the built-in renderer)
+    #[arg(long, value_name = "FILE")]
+    png: Option<PathBuf>,
+
+    /// Render the graph to an SVG image (graphviz or the built-in renderer)
+    #[arg(long, value_name = "FILE")]
+    svg: Option<PathBuf>,
+
+    /// Write the nodes and links as JSON ("-" for stdout)
+    #[arg(long, value_name = "FILE")]
+    json: Option<PathBuf>,
+
+    /// Group the nodes of the graph by their device or client
+    #[arg(long, value_name = "BY", value_parser = ["device", "client"])]
+    cluster: Option<String>,
+
+    /// Add live values to node labels: "volume", "format" or "volume,format"
+    #[arg(long, value_name = "LIST")]
+    annotate: Option<String>,
+
+    /// Show other node kinds too: "midi", "video" or "midi,video"
+    #[arg(long, value_name = "LIST")]
+    include: Option<String>,
+}
+
+fn write_output(path: &Path, data: &[u8]) -> Result<(), String> {
+    if path == Path::new("-") {
+        std::io::stdout()
+            .write_all(data)
+            .map_err(|e| format!("Failed to write to stdout: {}", e))
+    } else {
+        std::fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let objects = pwcli::list_all().map_err(|e| format!("Failed to list PipeWire objects: {}", e))?;
+    let options = graph::GraphOptions {
+        cluster: match args.cluster.as_deref() {
+            Some("device") => Some(graph::ClusterBy::Device),
+            Some("client") => Some(graph::ClusterBy::Client),
+            _ => None,
+        },
+        annotate: graph::Annotations::parse(args.annotate.as_deref().unwrap_or(""))?,
+        include: graph::Include::parse(args.include.as_deref().unwrap_or(""))?,
+    };
+    let dot = graph::generate_dot_graph_with(&objects, &options);
+
+    // Without an output option, print DOT to stdout
+    if args.dot.is_none() && args.png.is_none() && args.svg.is_none() && args.json.is_none() {
+        return write_output(Path::new("-"), dot.as_bytes());
+    }
+
+    if let Some(path) = &args.dot {
+        write_output(path, dot.as_bytes())?;
+    }
+
+    for (path, format) in [(&args.png, "png"), (&args.svg, "svg")] {
+        if let Some(path) = path {
+            write_output(path, &graph::render_image(&dot, format)?)?;
+        }
+    }
+
+    if let Some(path) = &args.json {
+        let topology = topology::export()?;
+        let json = serde_json::to_string_pretty(&topology)
+            .map_err(|e| format!("Failed to serialize topology: {}", e))?;
+        write_output(path, format!("{}\n", json).as_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
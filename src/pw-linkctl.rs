@@ -0,0 +1,145 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use pw_api::link_manager_cli::{self, LinkData, LinkRuleResult};
+use pw_api::{config, LinkRule, LinkType};
+
+#[derive(Parser, Debug)]
+#[command(name = "pw-linkctl")]
+#[command(about = "Test link rules without restarting the API server", long_about = None)]
+struct Args {
+    /// Link rules file (default: link-rules.conf from the user and system
+    /// configuration directories)
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Print JSON instead of text
+    #[arg(short, long)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the rules in the file
+    List,
+    /// Show which nodes and ports a rule matches (all rules if no name is given)
+    Show {
+        /// Rule name
+        rule: Option<String>,
+    },
+    /// Apply a single rule
+    Apply {
+        /// Rule name
+        rule: String,
+    },
+    /// Undo a single rule: remove the links of a link rule, recreate the
+    /// links of an unlink rule
+    Revert {
+        /// Rule name
+        rule: String,
+    },
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+fn load_rules(path: Option<&PathBuf>) -> Vec<LinkRule> {
+    match path {
+        Some(path) => config::load_link_rules_from_file(path)
+            .unwrap_or_else(|e| fail(format!("Failed to load {}: {}", path.display(), e))),
+        None => config::load_all_link_rules(),
+    }
+}
+
+fn find_rule<'a>(rules: &'a [LinkRule], name: &str) -> &'a LinkRule {
+    rules.iter().find(|r| r.name == name).unwrap_or_else(|| {
+        let names: Vec<&str> = rules.iter().map(|r| r.name.as_str()).collect();
+        fail(format!("Rule '{}' not found. Rules: {}", name, names.join(", ")))
+    })
+}
+
+fn type_name(rule: &LinkRule) -> &'static str {
+    match rule.link_type {
+        LinkType::Link => "link",
+        LinkType::Unlink => "unlink",
+    }
+}
+
+fn print_results(results: &[LinkRuleResult], json: bool) -> bool {
+    if json {
+        let results: Vec<_> = results
+            .iter()
+            .map(|r| serde_json::json!({"success": r.success, "message": r.message}))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&results).unwrap_or_default());
+    } else {
+        for result in results {
+            println!("{} {}", if result.success { "ok  " } else { "FAIL" }, result.message);
+        }
+    }
+    results.iter().all(|r| r.success)
+}
+
+fn main() {
+    let args = Args::parse();
+    let rules = load_rules(args.config.as_ref());
+
+    let success = match &args.command {
+        Command::List => {
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&rules).unwrap_or_default());
+            } else {
+                for rule in &rules {
+                    println!("{} ({})", rule.name, type_name(rule));
+                }
+            }
+            true
+        }
+        Command::Show { rule } => {
+            let selected: Vec<&LinkRule> = match rule {
+                Some(name) => vec![find_rule(&rules, name)],
+                None => rules.iter().collect(),
+            };
+            let data = LinkData::load().unwrap_or_else(|e| fail(e));
+            let matches: Vec<_> = selected
+                .iter()
+                .map(|r| link_manager_cli::match_link_rule(r, &data))
+                .collect();
+
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&matches).unwrap_or_default());
+            } else {
+                for (rule, matched) in selected.iter().zip(&matches) {
+                    println!("{} ({})", matched.rule, type_name(rule));
+                    println!("  sources:      {}", matched.sources.join(", "));
+                    println!("  destinations: {}", matched.destinations.join(", "));
+                    for pair in &matched.pairs {
+                        let state = if pair.linked { "linked" } else { "not linked" };
+                        println!("  {} -> {} ({})", pair.output, pair.input, state);
+                    }
+                    for error in &matched.errors {
+                        println!("  error: {}", error);
+                    }
+                }
+            }
+            matches.iter().all(|m| m.errors.is_empty())
+        }
+        Command::Apply { rule } => {
+            let results = link_manager_cli::apply_link_rule(find_rule(&rules, rule)).unwrap_or_else(|e| fail(e));
+            print_results(&results, args.json)
+        }
+        Command::Revert { rule } => {
+            let results = link_manager_cli::revert_link_rule(find_rule(&rules, rule)).unwrap_or_else(|e| fail(e));
+            print_results(&results, args.json)
+        }
+    };
+
+    if !success {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,254 @@
+use clap::Parser;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime};
+
+use pw_api::pod_parser::ParamObject;
+use pw_api::pwcli::{self, PwObject};
+use pw_api::{pwparams, watch};
+
+#[derive(Parser, Debug)]
+#[command(name = "pw-props")]
+#[command(about = "Show the Props of PipeWire nodes or the Route of devices", long_about = None)]
+struct Args {
+    /// Object IDs
+    #[arg(required_unless_present = "all")]
+    ids: Vec<u32>,
+
+    /// Show all objects (nodes and devices unless --type is given)
+    #[arg(short, long, conflicts_with = "ids")]
+    all: bool,
+
+    /// Object type for --all: node, device, port, ...
+    #[arg(short = 't', long = "type", requires = "all")]
+    object_type: Option<String>,
+
+    /// Parameter type (default: Props for nodes, Route for devices)
+    #[arg(short, long)]
+    param: Option<String>,
+
+    /// Print JSON instead of text, one JSON object per object or change
+    #[arg(short, long)]
+    json: bool,
+
+    /// Keep running and print parameter changes as they happen
+    #[arg(short, long)]
+    watch: bool,
+
+    /// Polling interval in milliseconds for --watch
+    #[arg(long, default_value_t = watch::DEFAULT_INTERVAL_MS)]
+    interval_ms: u64,
+}
+
+/// An object whose parameters are shown
+struct Target {
+    id: u32,
+    object_type: String,
+    name: String,
+    param_type: &'static str,
+}
+
+/// JSON output of one object
+#[derive(Serialize)]
+struct PropsOutput<'a> {
+    id: u32,
+    #[serde(rename = "type")]
+    object_type: &'a str,
+    name: &'a str,
+    param_type: &'a str,
+    params: &'a [ParamObject],
+}
+
+/// JSON output of one change in watch mode
+#[derive(Serialize)]
+struct ChangeOutput<'a> {
+    time: String,
+    id: u32,
+    name: &'a str,
+    param_type: &'a str,
+    key: &'a str,
+    old: &'a Option<Value>,
+    new: &'a Option<Value>,
+}
+
+fn format_value(value: &Option<Value>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => "(removed)".to_string(),
+    }
+}
+
+fn timestamp() -> String {
+    humantime::format_rfc3339_millis(SystemTime::now()).to_string()
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+async fn list_objects() -> Result<Vec<PwObject>, String> {
+    pw_api::worker::run(pwcli::list_all).await.map_err(|e| e.to_string())?
+}
+
+async fn read_params(id: u32, param_type: &'static str) -> Result<Vec<ParamObject>, String> {
+    pw_api::worker::run(move || pwparams::enum_params(id, param_type))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Select the objects to show from the command line arguments
+fn select_targets(args: &Args, objects: &[PwObject], param: Option<&'static str>) -> Vec<Target> {
+    let target = |obj: &PwObject| {
+        let param_type = param.or_else(|| watch::watched_param_type(obj))?;
+        Some(Target {
+            id: obj.id,
+            object_type: pwcli::simplify_type(&obj.object_type).to_string(),
+            name: obj.display_name(),
+            param_type,
+        })
+    };
+
+    if args.all {
+        let mut targets: Vec<Target> = objects
+            .iter()
+            .filter(|obj| match &args.object_type {
+                Some(t) => pwcli::simplify_type(&obj.object_type).eq_ignore_ascii_case(t),
+                None => true,
+            })
+            .filter_map(target)
+            .collect();
+        targets.sort_by_key(|t| t.id);
+        return targets;
+    }
+
+    args.ids
+        .iter()
+        .map(|id| {
+            let obj = objects
+                .iter()
+                .find(|o| o.id == *id)
+                .unwrap_or_else(|| fail(format!("Object {} not found", id)));
+            target(obj).unwrap_or_else(|| {
+                fail(format!("Object {} has no default parameter type, use --param", id))
+            })
+        })
+        .collect()
+}
+
+fn print_params(target: &Target, params: &[ParamObject], json: bool) {
+    if json {
+        let output = PropsOutput {
+            id: target.id,
+            object_type: &target.object_type,
+            name: &target.name,
+            param_type: target.param_type,
+            params,
+        };
+        println!("{}", serde_json::to_string(&output).unwrap_or_default());
+        return;
+    }
+
+    println!("{} {} ({}), {}", target.object_type, target.id, target.name, target.param_type);
+    for change in watch::diff_params(&[], params) {
+        println!("  {}: {}", change.key, format_value(&change.new));
+    }
+}
+
+fn print_changes(target: &Target, changes: &[watch::ParamChange], json: bool) {
+    let time = timestamp();
+    for change in changes {
+        if json {
+            let output = ChangeOutput {
+                time: time.clone(),
+                id: target.id,
+                name: &target.name,
+                param_type: target.param_type,
+                key: &change.key,
+                old: &change.old,
+                new: &change.new,
+            };
+            println!("{}", serde_json::to_string(&output).unwrap_or_default());
+        } else {
+            println!(
+                "{} {} {} {}: {} -> {}",
+                time,
+                target.id,
+                target.param_type,
+                change.key,
+                format_value(&change.old),
+                format_value(&change.new)
+            );
+        }
+    }
+}
+
+/// Print changes until all watched objects have disappeared
+async fn watch_params(mut watched: Vec<(Target, Vec<ParamObject>)>, interval: Duration, json: bool) {
+    let mut ticker = tokio::time::interval(interval.max(Duration::from_millis(watch::MIN_INTERVAL_MS)));
+    ticker.tick().await;
+    while !watched.is_empty() {
+        ticker.tick().await;
+
+        let ids: HashSet<u32> = match list_objects().await {
+            Ok(objects) => objects.iter().map(|o| o.id).collect(),
+            Err(e) => {
+                eprintln!("{} error: {}", timestamp(), e);
+                continue;
+            }
+        };
+
+        let mut remaining = Vec::with_capacity(watched.len());
+        for (target, params) in watched {
+            if !ids.contains(&target.id) {
+                if !json {
+                    println!("{} {} removed", timestamp(), target.id);
+                }
+                continue;
+            }
+            match read_params(target.id, target.param_type).await {
+                Ok(new_params) => {
+                    print_changes(&target, &watch::diff_params(&params, &new_params), json);
+                    remaining.push((target, new_params));
+                }
+                Err(e) => {
+                    eprintln!("{} error reading {}: {}", timestamp(), target.id, e);
+                    remaining.push((target, params));
+                }
+            }
+        }
+        watched = remaining;
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    let param = args.param.as_deref().map(|t| {
+        pwparams::normalize_param_type(t).unwrap_or_else(|| {
+            fail(format!("Unknown parameter type '{}'. Valid types: {}", t, pwparams::param_types().join(", ")))
+        })
+    });
+
+    let objects = list_objects().await.unwrap_or_else(|e| fail(e));
+    let targets = select_targets(&args, &objects, param);
+
+    let mut watched = Vec::with_capacity(targets.len());
+    for target in targets {
+        match read_params(target.id, target.param_type).await {
+            Ok(params) => {
+                print_params(&target, &params, args.json);
+                watched.push((target, params));
+            }
+            // With --all, objects without this parameter type are skipped
+            Err(_) if args.all => {}
+            Err(e) => fail(format!("{}: {}", target.id, e)),
+        }
+    }
+
+    if args.watch {
+        watch_params(watched, Duration::from_millis(args.interval_ms), args.json).await;
+    }
+}
@@ -4,7 +4,6 @@
 //! the output of pw-cli commands instead of using the native API.
 
 use std::collections::HashMap;
-use std::process::Command;
 use std::sync::{Mutex, OnceLock};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -41,9 +40,10 @@ pub enum NodeTypeClassification {
 /// Classify a media.class string to determine the node type
 /// 
 /// Returns the detected node type based on media.class patterns:
-/// - `Audio`: Contains "audio" or "stream"
+/// - `Audio`: Contains "audio"
 /// - `Midi`: Contains "midi"
-/// - `Video`: Contains "video"
+/// - `Video`: Contains "video" (e.g. "Video/Source", "Stream/Input/Video")
+/// - `Audio`: Other streams (contains "stream")
 /// - `Link`: Is exactly "link" or contains "link/"
 /// - `Other`: Has a media.class but doesn't match known patterns
 /// - `Unknown`: No media.class provided, caller should use heuristics
@@ -56,6 +56,7 @@ pub enum NodeTypeClassification {
 /// assert_eq!(classify_media_class(Some("Stream/Output/Audio")), NodeTypeClassification::Audio);
 /// assert_eq!(classify_media_class(Some("Midi/Bridge")), NodeTypeClassification::Midi);
 /// assert_eq!(classify_media_class(Some("Video/Source")), NodeTypeClassification::Video);
+/// assert_eq!(classify_media_class(Some("Stream/Input/Video")), NodeTypeClassification::Video);
 /// assert_eq!(classify_media_class(None), NodeTypeClassification::Unknown);
 /// ```
 pub fn classify_media_class(media_class: Option<&str>) -> NodeTypeClassification {
@@ -64,7 +65,7 @@ pub fn classify_media_class(media_class: Option<&str>) -> NodeTypeClassification
             let class_lower = class.to_lowercase();
             
             // Check for audio (includes Stream/Output/Audio patterns)
-            if class_lower.contains("audio") || class_lower.contains("stream") {
+            if class_lower.contains("audio") {
                 return NodeTypeClassification::Audio;
             }
             
@@ -73,11 +74,16 @@ pub fn classify_media_class(media_class: Option<&str>) -> NodeTypeClassification
                 return NodeTypeClassification::Midi;
             }
             
-            // Check for video
+            // Check for video (includes Stream/Input/Video patterns)
             if class_lower.contains("video") {
                 return NodeTypeClassification::Video;
             }
             
+            // Other streams are audio
+            if class_lower.contains("stream") {
+                return NodeTypeClassification::Audio;
+            }
+            
             // Check for link
             if class_lower == "link" || class_lower.starts_with("link/") {
                 return NodeTypeClassification::Link;
@@ -91,6 +97,22 @@ pub fn classify_media_class(media_class: Option<&str>) -> NodeTypeClassification
     }
 }
 
+/// Classify a port by its `format.dsp` property (e.g. "32 bit float mono audio")
+///
+/// Returns `Audio`, `Midi` or `Video`, or `Unknown` without a known format.
+pub fn classify_port_format(format_dsp: Option<&str>) -> NodeTypeClassification {
+    let format = format_dsp.unwrap_or_default().to_lowercase();
+    if format.contains("audio") {
+        NodeTypeClassification::Audio
+    } else if format.contains("midi") {
+        NodeTypeClassification::Midi
+    } else if format.contains("video") {
+        NodeTypeClassification::Video
+    } else {
+        NodeTypeClassification::Unknown
+    }
+}
+
 /// Check if a node is a driver node (Dummy-Driver, Freewheel-Driver, etc.)
 pub fn is_driver_node(obj: &PwObject) -> bool {
     if let Some(name) = obj.properties.get("node.name") {
@@ -102,8 +124,18 @@ pub fn is_driver_node(obj: &PwObject) -> bool {
     false
 }
 
+/// The caches hold the objects of the default instance from the process-wide
+/// backend, they are not used for other cores or a scoped backend
+fn bypass_caches() -> bool {
+    crate::remote::current().is_some() || crate::backend::scoped().is_some()
+}
+
 /// Initialize or refresh the node name cache
 fn refresh_node_cache() -> Result<(), String> {
+    // The caches hold the objects of the default instance
+    if bypass_caches() {
+        return Ok(());
+    }
     let nodes = list_nodes()?;
     let mut cache = HashMap::new();
     
@@ -130,6 +162,15 @@ pub struct PwObject {
 }
 
 impl PwObject {
+    /// Create an object with properties given as key-value pairs
+    pub fn new(id: u32, object_type: &str, properties: &[(&str, &str)]) -> Self {
+        Self {
+            id,
+            object_type: object_type.to_string(),
+            properties: properties.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
     /// Get a property value
     pub fn get(&self, key: &str) -> Option<&str> {
         self.properties.get(key).map(|s| s.as_str())
@@ -227,24 +268,7 @@ pub const TYPE_METADATA: &str = "Metadata";
 /// If `filter` is provided, only objects of that type are returned.
 /// Valid filters: Node, Device, Port, Module, Factory, Client, Link, etc.
 pub fn list_objects(filter: Option<&str>) -> Result<Vec<PwObject>, String> {
-    let mut cmd = Command::new("pw-cli");
-    cmd.arg("ls");
-    
-    if let Some(f) = filter {
-        cmd.arg(f);
-    }
-    
-    let output = cmd
-        .output()
-        .map_err(|e| format!("Failed to run pw-cli ls: {}", e))?;
-    
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("pw-cli ls failed: {}", stderr));
-    }
-    
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    parse_pwcli_ls(&stdout)
+    crate::backend::current().list_objects(filter)
 }
 
 /// List all objects
@@ -287,8 +311,48 @@ pub fn list_links() -> Result<Vec<PwObject>, String> {
     list_objects(Some(TYPE_LINK))
 }
 
+/// Suspend a node (pw-cli send-command), which closes its device
+///
+/// PipeWire resumes the node when it is needed again, e.g. when a stream is
+/// linked to it.
+pub fn suspend_node(id: u32) -> Result<(), String> {
+    let output = crate::command::output_with_timeout(
+        crate::command::pipewire("pw-cli").args(["send-command", &id.to_string()]),
+        crate::command::discovery_timeout(),
+    )
+    .map_err(|e| format!("Failed to run pw-cli send-command: {}", e))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || stderr.contains("error") {
+        return Err(format!("pw-cli send-command failed: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+/// Destroy an object via the registry (pw-cli destroy)
+///
+/// Destroying a client object disconnects the client from the server.
+pub fn destroy_object(id: u32) -> Result<(), String> {
+    let output = crate::command::output_with_timeout(
+        crate::command::pipewire("pw-cli").args(["destroy", &id.to_string()]),
+        crate::command::discovery_timeout(),
+    )
+    .map_err(|e| format!("Failed to run pw-cli destroy: {}", e))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || stderr.contains("error") {
+        return Err(format!("pw-cli destroy failed: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
 /// Get a specific object by ID (uses internal cache, refreshes on miss)
 pub fn get_object(id: u32) -> Result<Option<PwObject>, String> {
+    if bypass_caches() {
+        return query_object(id);
+    }
     let cache_mutex = OBJECT_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
 
     // Check cache first
@@ -306,8 +370,16 @@ pub fn get_object(id: u32) -> Result<Option<PwObject>, String> {
     Ok(cache.get(&id).cloned())
 }
 
+/// Get the current state of an object directly from PipeWire, bypassing the cache
+pub fn query_object(id: u32) -> Result<Option<PwObject>, String> {
+    crate::backend::current().bind(id)
+}
+
 /// Refresh the internal object cache from pw-cli
 pub fn refresh_object_cache() -> Result<(), String> {
+    if bypass_caches() {
+        return Ok(());
+    }
     let objects = list_all()?;
     let cache_mutex = OBJECT_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
     let mut cache = cache_mutex.lock().unwrap();
@@ -319,8 +391,20 @@ pub fn refresh_object_cache() -> Result<(), String> {
     Ok(())
 }
 
+/// Reload the node name and object caches
+///
+/// Needed when a node was recreated (e.g. a filter-chain reload) and the
+/// cached ID of its name is no longer valid.
+pub fn invalidate_caches() -> Result<(), String> {
+    refresh_node_cache()?;
+    refresh_object_cache()
+}
+
 /// Find a node by name using cache (refreshes cache on first call or if not found)
 pub fn find_node_by_name(name: &str) -> Result<Option<PwObject>, String> {
+    if bypass_caches() {
+        return Ok(list_nodes()?.into_iter().find(|n| n.get("node.name") == Some(name)));
+    }
     // Initialize cache on first use
     if NODE_CACHE.get().is_none() {
         refresh_node_cache()?;
@@ -348,6 +432,9 @@ pub fn find_node_by_name(name: &str) -> Result<Option<PwObject>, String> {
 
 /// Find a node name by ID using cache
 pub fn find_name_by_id(id: u32) -> Result<Option<String>, String> {
+    if bypass_caches() {
+        return Ok(query_object(id)?.and_then(|o| o.get("node.name").map(|s| s.to_string())));
+    }
     // Initialize cache on first use
     if NODE_CACHE.get().is_none() {
         refresh_node_cache()?;
@@ -419,6 +506,18 @@ pub fn find_node_by_match(pattern: &str) -> Result<Option<PwObject>, String> {
     Ok(None)
 }
 
+/// Find all nodes whose name matches a regex pattern (anchored like [`find_node_by_match`])
+pub fn find_nodes_by_match(pattern: &str) -> Result<Vec<PwObject>, String> {
+    let re = Regex::new(&format!("^{}$", pattern))
+        .map_err(|e| format!("Invalid regex pattern '{}': {}", pattern, e))?;
+    let mut nodes: Vec<PwObject> = list_nodes()?
+        .into_iter()
+        .filter(|n| n.name().map(|name| re.is_match(name)).unwrap_or(false))
+        .collect();
+    nodes.sort_by_key(|n| n.id);
+    Ok(nodes)
+}
+
 /// Parse pw-cli ls output into objects
 /// 
 /// Format:
@@ -428,7 +527,7 @@ pub fn find_node_by_match(pattern: &str) -> Result<Option<PwObject>, String> {
 ///                 factory.id = "18"
 ///                 node.name = "effect_input.proc"
 /// ```
-fn parse_pwcli_ls(output: &str) -> Result<Vec<PwObject>, String> {
+pub fn parse_pwcli_ls(output: &str) -> Result<Vec<PwObject>, String> {
     let mut objects = Vec::new();
     
     // Regex for object header: "id N, type PipeWire:Interface:Type/Version"
@@ -541,6 +640,16 @@ mod tests {
         assert_eq!(device.name(), Some("alsa_card.0"));
     }
     
+    #[test]
+    fn test_classify_video() {
+        assert_eq!(classify_media_class(Some("Video/Source")), NodeTypeClassification::Video);
+        assert_eq!(classify_media_class(Some("Stream/Output/Video")), NodeTypeClassification::Video);
+        assert_eq!(classify_media_class(Some("Stream/Output/Audio")), NodeTypeClassification::Audio);
+        assert_eq!(classify_port_format(Some("32 bit float RGBA video")), NodeTypeClassification::Video);
+        assert_eq!(classify_port_format(Some("32 bit float mono audio")), NodeTypeClassification::Audio);
+        assert_eq!(classify_port_format(None), NodeTypeClassification::Unknown);
+    }
+
     #[test]
     fn test_simplify_type() {
         assert_eq!(simplify_type("Node"), "node");
@@ -0,0 +1,259 @@
+//! Objects and parameters from the JSON of pw-dump
+//!
+//! `pw-cli ls` and `pw-cli enum-params` print text whose format changes
+//! between PipeWire versions, so parsing it with regular expressions is
+//! fragile. pw-dump prints the same objects as JSON, including the
+//! parameters of nodes, devices and ports. [`DumpBackend`] reads objects and
+//! parameters from it; writing parameters still uses pw-cli, pw-dump cannot
+//! write. With `[server] pipewire_backend = "auto"` it is used whenever
+//! pw-dump is installed (see [`crate::backend::open`]).
+//!
+//! The JSON is converted to the same form as the pw-cli output: property
+//! values become strings, the `params` of filter-chain nodes an object and
+//! choices `{"choice": ..., "values": [default, ...]}`.
+
+use serde_json::{Map, Value};
+
+use crate::backend::{CliBackend, PipeWireBackend};
+use crate::command;
+use crate::pod_parser::ParamObject;
+use crate::pwcli::PwObject;
+
+/// Prefix of the object types in pw-dump
+const INTERFACE_PREFIX: &str = "PipeWire:Interface:";
+
+/// Check whether pw-dump can be run
+pub fn is_available() -> bool {
+    command::output_with_timeout(command::pipewire("pw-dump").arg("--version"), command::discovery_timeout())
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Run pw-dump for all objects or the one with this ID
+fn dump(id: Option<u32>) -> Result<Vec<Value>, String> {
+    let mut cmd = command::pipewire("pw-dump");
+    if let Some(id) = id {
+        cmd.arg(id.to_string());
+    }
+    let output = command::output_with_timeout(&mut cmd, command::discovery_timeout())
+        .map_err(|e| format!("Failed to run pw-dump: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("pw-dump failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    parse_dump(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the JSON array printed by pw-dump
+pub fn parse_dump(output: &str) -> Result<Vec<Value>, String> {
+    if output.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    match serde_json::from_str(output) {
+        Ok(Value::Array(objects)) => Ok(objects),
+        Ok(_) => Err("pw-dump did not print an array".to_string()),
+        Err(e) => Err(format!("Invalid pw-dump output: {}", e)),
+    }
+}
+
+/// Property value as pw-cli prints it
+fn property_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Convert an object of pw-dump, None for entries without ID or type
+pub fn to_object(value: &Value) -> Option<PwObject> {
+    let id = value.get("id")?.as_u64()? as u32;
+    let object_type = value.get("type")?.as_str()?;
+    // Metadata objects have their properties outside of "info"
+    let props = value
+        .pointer("/info/props")
+        .or_else(|| value.get("props"))
+        .and_then(|p| p.as_object());
+    Some(PwObject {
+        id,
+        object_type: object_type.strip_prefix(INTERFACE_PREFIX).unwrap_or(object_type).to_string(),
+        properties: props
+            .into_iter()
+            .flatten()
+            .filter_map(|(k, v)| Some((k.clone(), property_string(v)?)))
+            .collect(),
+    })
+}
+
+/// Convert a choice of pw-dump (`{"default": 1.0, "min": 0.0, "max": 10.0}`,
+/// `{"default": 0, "alt1": 0, "alt2": 1}`) into the form of the pod parser
+fn choice(map: &Map<String, Value>) -> Option<Value> {
+    let default = map.get("default")?;
+    let mut values = vec![default.clone()];
+    let kind = if map.contains_key("min") && map.contains_key("max") {
+        values.extend(["min", "max", "step"].iter().filter_map(|k| map.get(*k)).cloned());
+        if map.contains_key("step") { "Step" } else { "Range" }
+    } else if map.keys().all(|k| k == "default" || k.starts_with("alt")) {
+        values.extend((1..map.len()).filter_map(|i| map.get(&format!("alt{}", i))).cloned());
+        "Enum"
+    } else if let Some(Value::Array(flags)) = map.get("flags") {
+        values.extend(flags.iter().cloned());
+        "Flags"
+    } else {
+        return None;
+    };
+    let mut converted = Map::new();
+    converted.insert("choice".to_string(), Value::String(kind.to_string()));
+    converted.insert("values".to_string(), Value::Array(values));
+    Some(Value::Object(converted))
+}
+
+/// Convert a parameter value of pw-dump, recursively
+fn param_value(key: &str, value: &Value) -> Value {
+    match value {
+        // The controls of filter-chains: alternating names and values
+        Value::Array(items) if key == "params" && items.len() % 2 == 0 && items.iter().step_by(2).all(|n| n.is_string()) => {
+            Value::Object(
+                items
+                    .chunks(2)
+                    .map(|pair| (pair[0].as_str().unwrap_or_default().to_string(), param_value("", &pair[1])))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| param_value("", v)).collect()),
+        Value::Object(map) => choice(map)
+            .unwrap_or_else(|| Value::Object(map.iter().map(|(k, v)| (k.clone(), param_value(k, v))).collect())),
+        other => other.clone(),
+    }
+}
+
+/// Parameters of a type of a pw-dump object
+pub fn to_params(value: &Value, param_type: &str) -> Vec<ParamObject> {
+    let params = value.pointer("/info/params").and_then(|p| p.get(param_type)).and_then(|p| p.as_array());
+    params
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.as_object())
+        .map(|p| ParamObject {
+            object_type: param_type.strip_prefix("Enum").unwrap_or(param_type).to_string(),
+            id: param_type.to_string(),
+            properties: p.iter().map(|(k, v)| (k.clone(), param_value(k, v))).collect(),
+        })
+        .collect()
+}
+
+/// PipeWire objects and parameters read with pw-dump, written with pw-cli
+#[derive(Debug, Default)]
+pub struct DumpBackend;
+
+impl PipeWireBackend for DumpBackend {
+    fn name(&self) -> &'static str {
+        "pw-dump"
+    }
+
+    fn list_objects(&self, filter: Option<&str>) -> Result<Vec<PwObject>, String> {
+        let id = filter.and_then(|f| f.parse::<u32>().ok());
+        let mut objects: Vec<PwObject> = dump(id)?
+            .iter()
+            .filter_map(to_object)
+            .filter(|o| match (id, filter) {
+                (Some(id), _) => o.id == id,
+                (None, Some(f)) => o.is_type(f),
+                (None, None) => true,
+            })
+            .collect();
+        objects.sort_by_key(|o| o.id);
+        Ok(objects)
+    }
+
+    fn enum_params(&self, id: u32, param_type: &str) -> Result<Vec<ParamObject>, String> {
+        let objects = dump(Some(id))?;
+        let object = objects
+            .iter()
+            .find(|o| o.get("id").and_then(|i| i.as_u64()) == Some(id as u64))
+            .ok_or_else(|| format!("Object {} not found", id))?;
+        Ok(to_params(object, param_type))
+    }
+
+    fn set_param(&self, id: u32, param_type: &str, value: &Value) -> Result<(), String> {
+        CliBackend.set_param(id, param_type, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const DUMP: &str = r#"[
+  {
+    "id": 52,
+    "type": "PipeWire:Interface:Node",
+    "version": 3,
+    "permissions": [ "r", "w", "x", "m" ],
+    "info": {
+      "max-input-ports": 2,
+      "state": "suspended",
+      "error": null,
+      "props": {
+        "object.serial": 52,
+        "node.name": "speakereq2x2",
+        "media.class": "Audio/Sink",
+        "node.virtual": true,
+        "node.link-group": "filter-chain-812-11"
+      },
+      "params": {
+        "PropInfo": [
+          { "id": "volume", "name": "Volume", "type": { "default": 1.0, "min": 0.0, "max": 10.0 } },
+          { "id": "speakereq2x2:output_0_eq_1_type", "name": "speakereq2x2:output_0_eq_1_type",
+            "type": { "default": 0, "alt1": 0, "alt2": 1, "alt3": 3 } }
+        ],
+        "Props": [
+          { "volume": 1.0, "mute": false, "channelVolumes": [ 0.125, 0.125 ], "channelMap": [ "FL", "FR" ] },
+          { "params": [ "speakereq2x2:Enable", true, "speakereq2x2:master_gain_db", -3.0 ] }
+        ]
+      }
+    }
+  },
+  {
+    "id": 3,
+    "type": "PipeWire:Interface:Metadata",
+    "version": 3,
+    "permissions": [ "r", "w", "x", "m" ],
+    "props": { "metadata.name": "default", "object.serial": 3 },
+    "metadata": [ ]
+  }
+]"#;
+
+    #[test]
+    fn test_to_object() {
+        let objects: Vec<PwObject> = parse_dump(DUMP).unwrap().iter().filter_map(to_object).collect();
+        assert_eq!(objects.len(), 2);
+        let node = &objects[0];
+        assert_eq!((node.id, node.object_type.as_str()), (52, "Node"));
+        assert!(node.is_type("Node"));
+        assert_eq!(node.name(), Some("speakereq2x2"));
+        // Numbers and booleans as strings, like pw-cli prints them
+        assert_eq!(node.get("object.serial"), Some("52"));
+        assert_eq!(node.get("node.virtual"), Some("true"));
+        assert_eq!(objects[1].get("metadata.name"), Some("default"));
+        assert!(parse_dump("").unwrap().is_empty());
+        assert!(parse_dump("{}").is_err());
+    }
+
+    #[test]
+    fn test_to_params() {
+        let objects = parse_dump(DUMP).unwrap();
+        let props = to_params(&objects[0], "Props");
+        assert_eq!(props.len(), 2);
+        assert_eq!(props[0].properties["channelVolumes"], json!([0.125, 0.125]));
+        // Filter-chain controls as an object, like the pod parser returns them
+        assert_eq!(props[1].properties["params"], json!({"speakereq2x2:Enable": true, "speakereq2x2:master_gain_db": -3.0}));
+        assert_eq!(crate::volume_ops::from_props_params(&props).mute, Some(false));
+
+        let info = to_params(&objects[0], "PropInfo");
+        assert_eq!(info[0].properties["type"], json!({"choice": "Range", "values": [1.0, 0.0, 10.0]}));
+        assert_eq!(info[1].properties["type"], json!({"choice": "Enum", "values": [0, 0, 1, 3]}));
+        assert!(to_params(&objects[0], "Route").is_empty());
+        assert!(to_params(&objects[1], "Props").is_empty());
+    }
+}
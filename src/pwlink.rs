@@ -3,10 +3,11 @@
 //! This module provides a simple interface to PipeWire links by using
 //! the pw-link command line tool.
 
-use std::process::Command;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::command;
+
 /// A PipeWire port as returned by pw-link
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PwPort {
@@ -44,6 +45,38 @@ pub struct PwLink {
     pub input_port_name: String,
 }
 
+/// Cause of a failed pw-link call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LinkErrorKind {
+    /// The link exists already
+    AlreadyExists,
+    /// A port or the link does not exist
+    NotFound,
+    Other,
+}
+
+/// Get the cause of a failed link operation from its error message
+///
+/// pw-link reports errors like "failed to link ports: File exists" on stderr.
+pub fn error_kind(message: &str) -> LinkErrorKind {
+    let message = message.to_lowercase();
+    // pw-link itself is missing, not a port
+    if message.contains("failed to run") {
+        return LinkErrorKind::Other;
+    }
+    if message.contains("file exists") || message.contains("already exists") || message.contains("already linked") {
+        LinkErrorKind::AlreadyExists
+    } else if ["no such", "not found", "unknown port", "can't find", "cannot find"]
+        .iter()
+        .any(|p| message.contains(p))
+    {
+        LinkErrorKind::NotFound
+    } else {
+        LinkErrorKind::Other
+    }
+}
+
 /// Direction for listing ports
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PortDirection {
@@ -63,7 +96,7 @@ pub fn list_input_ports() -> Result<Vec<PwPort>, String> {
 
 /// List ports in a given direction
 pub fn list_ports(direction: PortDirection) -> Result<Vec<PwPort>, String> {
-    let mut cmd = Command::new("pw-link");
+    let mut cmd = crate::command::pipewire("pw-link");
     cmd.arg("-I");
     
     match direction {
@@ -71,8 +104,7 @@ pub fn list_ports(direction: PortDirection) -> Result<Vec<PwPort>, String> {
         PortDirection::Input => cmd.arg("-i"),
     };
     
-    let output = cmd
-        .output()
+    let output = command::output_with_timeout(&mut cmd, command::discovery_timeout())
         .map_err(|e| format!("Failed to run pw-link: {}", e))?;
     
     if !output.status.success() {
@@ -106,10 +138,11 @@ fn parse_ports(output: &str) -> Result<Vec<PwPort>, String> {
 
 /// List all links
 pub fn list_links() -> Result<Vec<PwLink>, String> {
-    let output = Command::new("pw-link")
-        .args(["-l", "-I"])
-        .output()
-        .map_err(|e| format!("Failed to run pw-link: {}", e))?;
+    let output = command::output_with_timeout(
+        crate::command::pipewire("pw-link").args(["-l", "-I"]),
+        command::discovery_timeout(),
+    )
+    .map_err(|e| format!("Failed to run pw-link: {}", e))?;
     
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -126,7 +159,7 @@ pub fn list_links() -> Result<Vec<PwLink>, String> {
 ///   90 effect_output.proc:output_FL
 ///   92   |->   82 speakereq2x2:playback_FL
 /// ```
-fn parse_links(output: &str) -> Result<Vec<PwLink>, String> {
+pub fn parse_links(output: &str) -> Result<Vec<PwLink>, String> {
     let mut links = Vec::new();
     
     // Regex for output port line: "  90 effect_output.proc:output_FL"
@@ -169,13 +202,65 @@ fn parse_links(output: &str) -> Result<Vec<PwLink>, String> {
     Ok(links)
 }
 
+/// Options of a new link
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkOptions {
+    /// The link does not keep the nodes running (`link.passive`)
+    #[serde(default)]
+    pub passive: bool,
+    /// The link stays after its creator has exited (`object.linger`)
+    #[serde(default = "default_linger")]
+    pub linger: bool,
+}
+
+fn default_linger() -> bool {
+    true
+}
+
+impl Default for LinkOptions {
+    fn default() -> Self {
+        Self { passive: false, linger: true }
+    }
+}
+
+/// Build the pw-link arguments for a new link
+///
+/// pw-link exits right after creating the link, so only lingering links
+/// can be created with it.
+pub fn link_args(output: &str, input: &str, options: &LinkOptions) -> Result<Vec<String>, String> {
+    if !options.linger {
+        return Err("pw-link can only create lingering links".to_string());
+    }
+    let mut args = vec!["--linger".to_string()];
+    if options.passive {
+        args.push("--passive".to_string());
+    }
+    args.extend([output.to_string(), input.to_string()]);
+    Ok(args)
+}
+
+/// Create a link between two ports (names or IDs) with options
+pub fn create_link_with_options(output: &str, input: &str, options: &LinkOptions) -> Result<(), String> {
+    let output_cmd = crate::command::pipewire("pw-link")
+        .args(link_args(output, input, options)?)
+        .output()
+        .map_err(|e| format!("Failed to run pw-link: {}", e))?;
+
+    if !output_cmd.status.success() {
+        let stderr = String::from_utf8_lossy(&output_cmd.stderr);
+        return Err(format!("Failed to create link: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
 /// Create a link between two ports by name
 /// 
 /// # Arguments
 /// * `output` - Output port name (e.g., "effect_output.proc:output_FL")
 /// * `input` - Input port name (e.g., "speakereq2x2:playback_FL")
 pub fn create_link(output: &str, input: &str) -> Result<(), String> {
-    let output_cmd = Command::new("pw-link")
+    let output_cmd = crate::command::pipewire("pw-link")
         .args([output, input])
         .output()
         .map_err(|e| format!("Failed to run pw-link: {}", e))?;
@@ -190,7 +275,7 @@ pub fn create_link(output: &str, input: &str) -> Result<(), String> {
 
 /// Create a link between two ports by ID
 pub fn create_link_by_id(output_id: u32, input_id: u32) -> Result<(), String> {
-    let output = Command::new("pw-link")
+    let output = crate::command::pipewire("pw-link")
         .args([&output_id.to_string(), &input_id.to_string()])
         .output()
         .map_err(|e| format!("Failed to run pw-link: {}", e))?;
@@ -205,7 +290,7 @@ pub fn create_link_by_id(output_id: u32, input_id: u32) -> Result<(), String> {
 
 /// Remove a link by its link ID
 pub fn remove_link(link_id: u32) -> Result<(), String> {
-    let output = Command::new("pw-link")
+    let output = crate::command::pipewire("pw-link")
         .args(["-d", &link_id.to_string()])
         .output()
         .map_err(|e| format!("Failed to run pw-link: {}", e))?;
@@ -220,7 +305,7 @@ pub fn remove_link(link_id: u32) -> Result<(), String> {
 
 /// Remove a link between two ports by name
 pub fn remove_link_by_name(output: &str, input: &str) -> Result<(), String> {
-    let output_cmd = Command::new("pw-link")
+    let output_cmd = crate::command::pipewire("pw-link")
         .args(["-d", output, input])
         .output()
         .map_err(|e| format!("Failed to run pw-link: {}", e))?;
@@ -298,6 +383,25 @@ mod tests {
         assert_eq!(links[1].output_port_id, 91);
     }
     
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(error_kind("Failed to create link: failed to link ports: File exists"), LinkErrorKind::AlreadyExists);
+        assert_eq!(error_kind("Failed to remove link: No such file or directory"), LinkErrorKind::NotFound);
+        assert_eq!(error_kind("Port foo:bar not found"), LinkErrorKind::NotFound);
+        assert_eq!(error_kind("Failed to run pw-link: No such file or directory (os error 2)"), LinkErrorKind::Other);
+        assert_eq!(error_kind("failed to link ports: Invalid argument"), LinkErrorKind::Other);
+    }
+
+    #[test]
+    fn test_link_args() {
+        let args = link_args("67", "89", &LinkOptions::default()).unwrap();
+        assert_eq!(args, vec!["--linger", "67", "89"]);
+
+        let passive = LinkOptions { passive: true, ..Default::default() };
+        assert_eq!(link_args("a:out", "b:in", &passive).unwrap(), vec!["--linger", "--passive", "a:out", "b:in"]);
+        assert!(link_args("a:out", "b:in", &LinkOptions { passive: false, linger: false }).is_err());
+    }
+
     #[test]
     fn test_port_from_full_name() {
         let port = PwPort::from_full_name(42, "node:port_name");
@@ -0,0 +1,201 @@
+//! Access to PipeWire metadata objects via pw-metadata
+//!
+//! Metadata objects store key/value pairs per subject (object id). Several
+//! controls only exist as metadata, e.g. the default sink/source
+//! (`default` metadata) or clock settings (`settings` metadata).
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use crate::command;
+use crate::pwcli;
+
+/// Type string used by PipeWire for JSON values
+pub const TYPE_JSON: &str = "Spa:String:JSON";
+
+/// A metadata object as listed by pw-cli
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataObject {
+    pub id: u32,
+    pub name: String,
+}
+
+/// A single metadata entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetadataEntry {
+    /// Object id the entry applies to (0 for global entries)
+    pub subject: u32,
+    pub key: String,
+    /// Value, parsed as JSON if the type is Spa:String:JSON
+    pub value: Value,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<String>,
+}
+
+fn update_line_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^\s*update: id:(\d+) key:'([^']*)' value:'(.*)' type:'([^']*)'\s*$").unwrap()
+    })
+}
+
+/// List all metadata objects
+pub fn list_metadata_objects() -> Result<Vec<MetadataObject>, String> {
+    let objects = pwcli::list_objects(Some(pwcli::TYPE_METADATA))?;
+    Ok(objects
+        .into_iter()
+        .map(|o| MetadataObject {
+            id: o.id,
+            name: o.get("metadata.name").unwrap_or("default").to_string(),
+        })
+        .collect())
+}
+
+/// Parse the output of pw-metadata
+pub fn parse_pw_metadata(output: &str) -> Vec<MetadataEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let caps = update_line_regex().captures(line)?;
+            let subject = caps[1].parse().ok()?;
+            let key = caps[2].to_string();
+            let raw_value = &caps[3];
+            let value_type = match &caps[4] {
+                "" | "(null)" => None,
+                t => Some(t.to_string()),
+            };
+            let value = if value_type.as_deref() == Some(TYPE_JSON) {
+                serde_json::from_str(raw_value).unwrap_or_else(|_| Value::String(raw_value.to_string()))
+            } else {
+                Value::String(raw_value.to_string())
+            };
+            Some(MetadataEntry { subject, key, value, value_type })
+        })
+        .collect()
+}
+
+/// Get all entries of a metadata object
+pub fn get_metadata(name: &str) -> Result<Vec<MetadataEntry>, String> {
+    let output = command::output_with_timeout(
+        crate::command::pipewire("pw-metadata").args(["-n", name]),
+        command::discovery_timeout(),
+    )
+    .map_err(|e| format!("Failed to run pw-metadata: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("pw-metadata failed: {}", stderr.trim()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_pw_metadata(&stdout))
+}
+
+/// Get a single entry of a metadata object
+pub fn get_metadata_key(name: &str, subject: u32, key: &str) -> Result<Option<MetadataEntry>, String> {
+    Ok(get_metadata(name)?
+        .into_iter()
+        .find(|e| e.subject == subject && e.key == key))
+}
+
+/// Convert a JSON value into the string and type passed to pw-metadata
+///
+/// Objects and arrays are stored as JSON, scalars as plain strings.
+pub fn value_to_metadata(value: &Value) -> (String, Option<&'static str>) {
+    match value {
+        Value::String(s) => (s.clone(), None),
+        Value::Object(_) | Value::Array(_) => (value.to_string(), Some(TYPE_JSON)),
+        other => (other.to_string(), None),
+    }
+}
+
+/// Arguments of pw-metadata setting an entry
+///
+/// `--` ends the options, so keys and values starting with `-` (e.g. a
+/// negative number) are not taken as options.
+pub fn set_args(name: &str, subject: u32, key: &str, value: &Value) -> Vec<String> {
+    let (value_str, value_type) = value_to_metadata(value);
+    let mut args: Vec<String> =
+        ["-n", name, "--", &subject.to_string(), key].iter().map(|a| a.to_string()).collect();
+    args.push(value_str);
+    args.extend(value_type.map(|t| t.to_string()));
+    args
+}
+
+/// Arguments of pw-metadata deleting an entry
+pub fn delete_args(name: &str, subject: u32, key: &str) -> Vec<String> {
+    ["-n", name, "-d", "--", &subject.to_string(), key].iter().map(|a| a.to_string()).collect()
+}
+
+/// Set an entry of a metadata object
+pub fn set_metadata(name: &str, subject: u32, key: &str, value: &Value) -> Result<(), String> {
+    let output = crate::command::pipewire("pw-metadata")
+        .args(set_args(name, subject, key, value))
+        .output()
+        .map_err(|e| format!("Failed to run pw-metadata: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("pw-metadata failed: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+/// Delete an entry of a metadata object
+pub fn delete_metadata(name: &str, subject: u32, key: &str) -> Result<(), String> {
+    let output = crate::command::pipewire("pw-metadata")
+        .args(delete_args(name, subject, key))
+        .output()
+        .map_err(|e| format!("Failed to run pw-metadata: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("pw-metadata failed: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pw_metadata() {
+        let output = r#"Found "default" metadata 31
+update: id:0 key:'default.configured.audio.sink' value:'{ "name": "alsa_output.hdmi" }' type:'Spa:String:JSON'
+update: id:0 key:'default.audio.sink' value:'{ "name": "effect_input.proc" }' type:'Spa:String:JSON'
+update: id:57 key:'target.object' value:'alsa_output.hdmi' type:''
+"#;
+        let entries = parse_pw_metadata(output);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].subject, 0);
+        assert_eq!(entries[0].key, "default.configured.audio.sink");
+        assert_eq!(entries[0].value["name"], "alsa_output.hdmi");
+        assert_eq!(entries[0].value_type.as_deref(), Some(TYPE_JSON));
+        assert_eq!(entries[2].subject, 57);
+        assert_eq!(entries[2].value, Value::String("alsa_output.hdmi".to_string()));
+        assert_eq!(entries[2].value_type, None);
+    }
+
+    #[test]
+    fn test_value_to_metadata() {
+        assert_eq!(value_to_metadata(&Value::String("x".into())), ("x".to_string(), None));
+        let (s, t) = value_to_metadata(&serde_json::json!({"name": "sink"}));
+        assert_eq!(s, r#"{"name":"sink"}"#);
+        assert_eq!(t, Some(TYPE_JSON));
+        assert_eq!(value_to_metadata(&serde_json::json!(1024)), ("1024".to_string(), None));
+    }
+
+    #[test]
+    fn test_args_end_options() {
+        let args = set_args("settings", 0, "-clock.rate", &serde_json::json!(-3));
+        assert_eq!(args, vec!["-n", "settings", "--", "0", "-clock.rate", "-3"]);
+        let args = set_args("default", 0, "default.audio.sink", &serde_json::json!({"name": "sink"}));
+        assert_eq!(args[2..], ["--", "0", "default.audio.sink", r#"{"name":"sink"}"#, TYPE_JSON]);
+        assert_eq!(delete_args("default", 71, "--help"), vec!["-n", "default", "-d", "--", "71", "--help"]);
+    }
+}
@@ -0,0 +1,87 @@
+//! Generic parameter access via pw-cli
+//!
+//! Reads parameters with `pw-cli enum-params` and writes them with
+//! `pw-cli set-param`, or with another backend (see [`crate::backend`]).
+//! Parameter objects are represented as JSON with named keys (see
+//! [`crate::spa_props`] and [`crate::pod_parser`]).
+
+use serde_json::Value;
+
+use crate::pod_parser::ParamObject;
+
+/// Parameter types known to PipeWire, with a flag whether they can be set
+const PARAM_TYPES: &[(&str, bool)] = &[
+    ("PropInfo", false),
+    ("Props", true),
+    ("EnumFormat", false),
+    ("Format", true),
+    ("Buffers", false),
+    ("Meta", false),
+    ("IO", false),
+    ("EnumProfile", false),
+    ("Profile", true),
+    ("EnumPortConfig", false),
+    ("PortConfig", true),
+    ("EnumRoute", false),
+    ("Route", true),
+    ("Control", false),
+    ("Latency", true),
+    ("ProcessLatency", true),
+    ("Tag", true),
+];
+
+/// Normalize a parameter type name (case-insensitive), e.g. "props" -> "Props"
+pub fn normalize_param_type(name: &str) -> Option<&'static str> {
+    PARAM_TYPES
+        .iter()
+        .find(|(t, _)| t.eq_ignore_ascii_case(name))
+        .map(|(t, _)| *t)
+}
+
+/// Check whether a parameter type can be written with set-param
+pub fn is_writable(param_type: &str) -> bool {
+    PARAM_TYPES
+        .iter()
+        .any(|(t, writable)| *writable && *t == param_type)
+}
+
+/// List all known parameter type names
+pub fn param_types() -> Vec<&'static str> {
+    PARAM_TYPES.iter().map(|(t, _)| *t).collect()
+}
+
+/// Read all parameter objects of a given type from an object
+pub fn enum_params(id: u32, param_type: &str) -> Result<Vec<ParamObject>, String> {
+    crate::backend::current().enum_params(id, param_type)
+}
+
+/// Write a parameter object to an object
+///
+/// `value` must be a JSON object using named keys, e.g.
+/// `{"mute": true}` for Props or `{"index": 1, "device": 3, "props": {...}}` for Route.
+pub fn set_param(id: u32, param_type: &str, value: &Value) -> Result<(), String> {
+    if !value.is_object() {
+        return Err("Parameter value must be a JSON object".to_string());
+    }
+    crate::backend::current().set_param(id, param_type, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_param_type() {
+        assert_eq!(normalize_param_type("props"), Some("Props"));
+        assert_eq!(normalize_param_type("EnumRoute"), Some("EnumRoute"));
+        assert_eq!(normalize_param_type("bogus"), None);
+    }
+
+    #[test]
+    fn test_is_writable() {
+        assert!(is_writable("Props"));
+        assert!(is_writable("Route"));
+        assert!(!is_writable("EnumFormat"));
+        assert!(!is_writable("PropInfo"));
+    }
+}
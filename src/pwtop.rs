@@ -0,0 +1,186 @@
+//! Driver and profiler statistics via pw-top
+//!
+//! `pw-top` reads the profiler data of the graph: per node the quantum and
+//! rate of its driver, the time it waited for and spent processing a cycle
+//! and the number of errors (xruns). In batch mode it prints one table per
+//! iteration; the first one can be incomplete, so [`sample`] runs two and
+//! uses the last:
+//!
+//! ```text
+//! S   ID  QUANT   RATE    WAIT    BUSY   W/Q   B/Q  ERR FORMAT           NAME
+//! R   37   1024  48000  53.6us  12.4us  0.00  0.00    0   S16LE 2 48000 alsa_output.platform-soc_sound.stereo-fallback
+//! R   55      0      0  20.3us  24.4us  0.00  0.00    3   F32LE 2 44100  + spotify
+//! ```
+//!
+//! Followers (`+` before the name) belong to the driver listed above them.
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// Time pw-top may take for its iterations
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A node in the pw-top table
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TopNode {
+    pub id: u32,
+    pub name: String,
+    /// R (running), I (idle), S (suspended), C (creating) or E (error)
+    pub state: String,
+    /// ID of the driver for followers, None for drivers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver_id: Option<u32>,
+    pub quantum: u32,
+    pub rate: u32,
+    /// Time from the start of the cycle until the node was started, in µs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_us: Option<f64>,
+    /// Processing time of the node in a cycle, in µs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub busy_us: Option<f64>,
+    /// wait_us relative to the cycle time (1.0 = a whole cycle)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_quantum: Option<f64>,
+    /// busy_us relative to the cycle time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub busy_quantum: Option<f64>,
+    /// Errors (xruns) counted by the profiler
+    pub errors: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+impl TopNode {
+    pub fn is_driver(&self) -> bool {
+        self.driver_id.is_none()
+    }
+}
+
+/// Parse a time like "53.6us", "1.2ms" or "---" into microseconds
+fn parse_time(value: &str) -> Option<f64> {
+    let (number, factor) = if let Some(v) = value.strip_suffix("us") {
+        (v, 1.0)
+    } else if let Some(v) = value.strip_suffix("ms") {
+        (v, 1000.0)
+    } else if let Some(v) = value.strip_suffix('s') {
+        (v, 1_000_000.0)
+    } else {
+        (value, 1.0)
+    };
+    number.parse::<f64>().ok().map(|n| n * factor)
+}
+
+fn is_number(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Parse one table row, `driver` is the ID of the last driver row
+fn parse_row(line: &str, driver: Option<u32>) -> Option<TopNode> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 {
+        return None;
+    }
+    let id = fields[1].parse().ok()?;
+    let mut rest = &fields[9..];
+    let mut format = None;
+    // The format is three fields (e.g. "S16LE 2 48000") followed by the name
+    if rest.len() >= 4 && rest[0] != "+" && is_number(rest[1]) && is_number(rest[2]) {
+        format = Some(rest[..3].join(" "));
+        rest = &rest[3..];
+    }
+    let follower = rest.first() == Some(&"+");
+    if follower {
+        rest = &rest[1..];
+    }
+    Some(TopNode {
+        id,
+        name: rest.join(" "),
+        state: fields[0].to_string(),
+        driver_id: if follower { driver } else { None },
+        quantum: fields[2].parse().unwrap_or(0),
+        rate: fields[3].parse().unwrap_or(0),
+        wait_us: parse_time(fields[4]),
+        busy_us: parse_time(fields[5]),
+        wait_quantum: fields[6].parse().ok(),
+        busy_quantum: fields[7].parse().ok(),
+        errors: fields[8].parse().unwrap_or(0),
+        format,
+    })
+}
+
+/// Parse the output of `pw-top -b`, using the last table
+pub fn parse_output(output: &str) -> Vec<TopNode> {
+    let mut tables: Vec<Vec<TopNode>> = Vec::new();
+    let mut driver = None;
+    for line in output.lines() {
+        if line.starts_with('S') && line.contains("QUANT") {
+            tables.push(Vec::new());
+            driver = None;
+            continue;
+        }
+        let Some(table) = tables.last_mut() else {
+            continue;
+        };
+        if let Some(node) = parse_row(line, driver) {
+            if node.is_driver() {
+                driver = Some(node.id);
+            }
+            table.push(node);
+        }
+    }
+    tables.pop().unwrap_or_default()
+}
+
+/// Take a sample of the profiler data (blocking, takes about two seconds)
+pub fn sample() -> Result<Vec<TopNode>, String> {
+    let output = crate::command::output_with_timeout(crate::command::pipewire("pw-top").args(["-b", "-n", "2"]), TIMEOUT)
+        .map_err(|e| format!("Failed to run pw-top: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("pw-top failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(parse_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+S   ID  QUANT   RATE    WAIT    BUSY   W/Q   B/Q  ERR FORMAT           NAME
+R   37   1024  48000    ---     ---   ---   ---     0                  alsa_output.usb
+S   ID  QUANT   RATE    WAIT    BUSY   W/Q   B/Q  ERR FORMAT           NAME
+S   28      0      0    ---     ---   ---   ---     0                  Dummy-Driver
+R   37   1024  48000  53.6us  12.4us  0.00  0.01    2   S16LE 2 48000 alsa_output.usb
+R   55      0      0  20.3us   1.2ms  0.02  0.05    3    F32LE 2 44100  + spotify player
+R   60      0      0   4.1us   8.0us  0.00  0.00    0                   + speakereq2x2
+";
+
+    #[test]
+    fn test_parse_output() {
+        let nodes = parse_output(OUTPUT);
+        assert_eq!(nodes.len(), 4);
+
+        let usb = &nodes[1];
+        assert!(usb.is_driver());
+        assert_eq!((usb.id, usb.quantum, usb.rate, usb.errors), (37, 1024, 48000, 2));
+        assert_eq!(usb.format.as_deref(), Some("S16LE 2 48000"));
+        assert_eq!(usb.wait_us, Some(53.6));
+        assert_eq!(usb.busy_quantum, Some(0.01));
+
+        let spotify = &nodes[2];
+        assert_eq!(spotify.name, "spotify player");
+        assert_eq!(spotify.driver_id, Some(37));
+        assert_eq!(spotify.busy_us, Some(1200.0));
+        assert_eq!(nodes[3].format, None);
+        assert_eq!(nodes[3].driver_id, Some(37));
+        assert_eq!(nodes[0].wait_us, None);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("12us"), Some(12.0));
+        assert_eq!(parse_time("1.5ms"), Some(1500.0));
+        assert_eq!(parse_time("---"), None);
+        assert!(parse_output("").is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+//! Per-client rate limiting of modifying requests
+//!
+//! Every PUT, POST and DELETE runs one or more PipeWire commands. A runaway
+//! automation loop sending hundreds of volume writes per second would keep
+//! PipeWire busy, so modifying requests are limited per client with a token
+//! bucket: a client can send `burst` requests at once and then
+//! `requests_per_sec` on average. Requests over the limit get
+//! `429 Too Many Requests` with a `Retry-After` header. GET requests are not
+//! limited.
+//!
+//! Clients are identified by their IP address. For connections from
+//! localhost (e.g. through the nginx proxy) the address in `X-Forwarded-For`
+//! or `X-Real-IP` is used instead.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::api_server::AppState;
+
+/// Default sustained rate of modifying requests per client
+pub const DEFAULT_REQUESTS_PER_SEC: f64 = 20.0;
+
+/// Default number of modifying requests a client can send at once
+pub const DEFAULT_BURST: u32 = 40;
+
+/// Number of clients above which idle buckets are removed
+const PRUNE_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Limits {
+    requests_per_sec: f64,
+    burst: f64,
+}
+
+/// Token buckets of all clients
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: RwLock<Limits>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_REQUESTS_PER_SEC, DEFAULT_BURST)
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter; a rate of 0 disables limiting
+    pub fn new(requests_per_sec: f64, burst: u32) -> Self {
+        Self {
+            limits: RwLock::new(Limits {
+                requests_per_sec,
+                burst: burst.max(1) as f64,
+            }),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Change the limits; a rate of 0 disables limiting
+    pub fn configure(&self, requests_per_sec: f64, burst: u32) {
+        *self.limits.write().unwrap() = Limits {
+            requests_per_sec,
+            burst: burst.max(1) as f64,
+        };
+        self.buckets.lock().unwrap().clear();
+    }
+
+    /// Check whether limiting is enabled
+    pub fn enabled(&self) -> bool {
+        self.limits.read().unwrap().requests_per_sec > 0.0
+    }
+
+    /// Take a token for a client, or return how long to wait for the next one
+    pub fn check(&self, client: &str) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let limits = *self.limits.read().unwrap();
+        if limits.requests_per_sec <= 0.0 {
+            return Ok(());
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > PRUNE_THRESHOLD {
+            // A bucket that has been idle long enough to be full again carries no state
+            let refill = Duration::from_secs_f64(limits.burst / limits.requests_per_sec);
+            buckets.retain(|_, b| now.duration_since(b.updated) < refill);
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: limits.burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limits.requests_per_sec).min(limits.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / limits.requests_per_sec))
+        }
+    }
+}
+
+/// Identify the client of a request
+///
+/// Proxied requests arrive from localhost; for those the client address set
+/// by the proxy is used.
+pub fn client_key(peer: Option<IpAddr>, headers: &HeaderMap) -> String {
+    let forwarded = || {
+        headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .or_else(|| headers.get("x-real-ip").and_then(|v| v.to_str().ok()))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    match peer {
+        Some(ip) if ip.is_loopback() => forwarded().unwrap_or_else(|| ip.to_string()),
+        Some(ip) => ip.to_string(),
+        None => forwarded().unwrap_or_else(|| "unknown".to_string()),
+    }
+}
+
+/// Middleware rejecting modifying requests over the limit
+pub async fn middleware(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let limiter = &state.rate_limiter;
+    let method = request.method();
+    if !limiter.enabled() || !(method == Method::PUT || method == Method::POST || method == Method::DELETE) {
+        return next.run(request).await;
+    }
+
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip());
+    let client = client_key(peer, request.headers());
+
+    match limiter.check(&client) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            tracing::debug!("Rate limit exceeded for {}", client);
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(serde_json::json!({
+                    "error": "Too many requests, slow down",
+                    "retry_after": retry_after
+                })),
+            )
+                .into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(10.0, 3);
+        let start = Instant::now();
+
+        // The burst is available at once
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", start).is_ok());
+        }
+        let wait = limiter.check_at("a", start).unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_millis(100));
+
+        // Other clients have their own bucket
+        assert!(limiter.check_at("b", start).is_ok());
+
+        // One token is refilled after 100 ms
+        assert!(limiter.check_at("a", start + Duration::from_millis(100)).is_ok());
+        assert!(limiter.check_at("a", start + Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let limiter = RateLimiter::new(0.0, 1);
+        assert!(!limiter.enabled());
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(limiter.check_at("a", now).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_client_key() {
+        let mut headers = HeaderMap::new();
+        let remote: IpAddr = "10.1.2.3".parse().unwrap();
+        let local: IpAddr = "127.0.0.1".parse().unwrap();
+        assert_eq!(client_key(Some(remote), &headers), "10.1.2.3");
+        assert_eq!(client_key(Some(local), &headers), "127.0.0.1");
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("10.9.8.7, 127.0.0.1"));
+        assert_eq!(client_key(Some(local), &headers), "10.9.8.7");
+        // Only proxies on localhost are trusted
+        assert_eq!(client_key(Some(remote), &headers), "10.1.2.3");
+    }
+}
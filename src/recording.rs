@@ -0,0 +1,329 @@
+//! Recording the audio of a node to a file
+//!
+//! A recording is a capture (see [`crate::capture`]) written to a WAV or
+//! FLAC file in the recordings directory, e.g. to rip vinyl through the RIAA
+//! chain. It runs for the requested duration or until it is stopped. The
+//! sizes in the header of a WAV file are set when the recording ends.
+//!
+//! Files stay on the device and can be downloaded; the list of recordings
+//! is kept in memory and holds the last [`MAX_KEPT`] finished ones.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Instant, SystemTime};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::capture::{self, Capture, CaptureFormat, CaptureSpec};
+
+/// Finished recordings kept in the list
+pub const MAX_KEPT: usize = 20;
+
+/// Longest recording, also used when no duration is given
+pub const MAX_RECORD_SECS: u64 = 4 * 3600;
+
+/// Size of the header of the WAV files
+const WAV_HEADER_LEN: u64 = 44;
+
+/// State of a recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingState {
+    Recording,
+    /// The duration was reached or the node went away
+    Finished,
+    /// Stopped through the API
+    Stopped,
+    Failed,
+}
+
+/// Status of a recording
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RecordingStatus {
+    pub id: u32,
+    pub node_id: u32,
+    pub node_name: String,
+    pub format: CaptureFormat,
+    /// File name in the recordings directory
+    pub file: String,
+    pub state: RecordingState,
+    /// Start time (RFC 3339)
+    pub started: String,
+    pub elapsed_secs: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+    /// Bytes written to the file
+    pub bytes: u64,
+    /// URL to download the file
+    pub download: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct Recording {
+    status: RecordingStatus,
+    path: PathBuf,
+    started: Instant,
+    ended: Option<Instant>,
+    stop: watch::Sender<bool>,
+    stopped: bool,
+}
+
+impl Recording {
+    fn status(&self) -> RecordingStatus {
+        let end = self.ended.unwrap_or_else(Instant::now);
+        RecordingStatus {
+            elapsed_secs: end.duration_since(self.started).as_secs(),
+            ..self.status.clone()
+        }
+    }
+}
+
+/// Runs the recordings and keeps their status
+#[derive(Default)]
+pub struct RecordingManager {
+    directory: RwLock<Option<PathBuf>>,
+    recordings: Mutex<BTreeMap<u32, Recording>>,
+    next_id: AtomicU32,
+}
+
+/// Check a file name given by a client: no path, only common characters
+pub fn valid_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 128
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// File name of a recording, with the extension of the format
+pub fn file_name(name: Option<&str>, node_name: &str, format: CaptureFormat, now: SystemTime) -> String {
+    let stem = match name {
+        Some(name) => name.strip_suffix(&format!(".{}", format.extension())).unwrap_or(name).to_string(),
+        None => {
+            let time = humantime::format_rfc3339_seconds(now).to_string().replace(':', "-");
+            let node: String = node_name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+                .collect();
+            format!("{}-{}", node, time.trim_end_matches('Z'))
+        }
+    };
+    format!("{}.{}", stem, format.extension())
+}
+
+/// Create a file that does not exist yet
+async fn create_new(path: &Path) -> std::io::Result<tokio::fs::File> {
+    tokio::fs::OpenOptions::new().write(true).create_new(true).open(path).await
+}
+
+/// Write the audio of a capture to a file, returns the bytes written
+async fn write_file(
+    path: &Path,
+    mut file: tokio::fs::File,
+    mut capture: Capture,
+    spec: &CaptureSpec,
+    progress: impl Fn(u64),
+) -> Result<u64, String> {
+    let mut bytes: u64 = 0;
+    while let Some(chunk) = capture.audio.recv().await {
+        file.write_all(&chunk).await.map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        bytes += chunk.len() as u64;
+        progress(bytes);
+    }
+    if spec.format == CaptureFormat::Wav && bytes >= WAV_HEADER_LEN {
+        let data_len = (bytes - WAV_HEADER_LEN).min(u32::MAX as u64 - 36) as u32;
+        file.seek(std::io::SeekFrom::Start(0)).await.map_err(|e| e.to_string())?;
+        file.write_all(&capture::wav_header(spec.rate, spec.channels, Some(data_len)))
+            .await
+            .map_err(|e| format!("Failed to write WAV header: {}", e))?;
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+impl RecordingManager {
+    /// Set the recordings directory (None: `recordings` in the state directory)
+    pub fn configure(&self, directory: Option<PathBuf>) {
+        *self.directory.write().unwrap() = directory;
+    }
+
+    /// Directory the files are written to
+    pub fn directory(&self) -> Result<PathBuf, String> {
+        if let Some(dir) = self.directory.read().unwrap().clone() {
+            return Ok(dir);
+        }
+        crate::config::state_dir()
+            .map(|dir| dir.join("recordings"))
+            .ok_or_else(|| "Could not determine state directory".to_string())
+    }
+
+    /// All recordings, newest last
+    pub fn list(&self) -> Vec<RecordingStatus> {
+        self.recordings.lock().unwrap().values().map(Recording::status).collect()
+    }
+
+    /// Status of a recording
+    pub fn status(&self, id: u32) -> Option<RecordingStatus> {
+        self.recordings.lock().unwrap().get(&id).map(Recording::status)
+    }
+
+    /// Path and format of a recording's file
+    pub fn file(&self, id: u32) -> Option<(PathBuf, CaptureFormat)> {
+        self.recordings.lock().unwrap().get(&id).map(|r| (r.path.clone(), r.status.format))
+    }
+
+    /// Stop a recording; the rest of the audio is still written
+    pub fn stop(&self, id: u32) -> Option<RecordingStatus> {
+        let mut recordings = self.recordings.lock().unwrap();
+        let recording = recordings.get_mut(&id)?;
+        if recording.status.state == RecordingState::Recording {
+            recording.stopped = true;
+            let _ = recording.stop.send(true);
+        }
+        Some(recording.status())
+    }
+
+    fn update(&self, id: u32, f: impl FnOnce(&mut Recording)) {
+        if let Some(recording) = self.recordings.lock().unwrap().get_mut(&id) {
+            f(recording);
+        }
+    }
+
+    /// Drop the oldest finished recordings from the list
+    fn prune(recordings: &mut BTreeMap<u32, Recording>) {
+        let finished: Vec<u32> = recordings
+            .iter()
+            .filter(|(_, r)| r.status.state != RecordingState::Recording)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in finished.iter().take(finished.len().saturating_sub(MAX_KEPT)) {
+            recordings.remove(id);
+        }
+    }
+
+    /// Start recording a node into `file` in the recordings directory
+    pub async fn start(self: &Arc<Self>, node_id: u32, spec: CaptureSpec, file: String) -> Result<RecordingStatus, String> {
+        let directory = self.directory()?;
+        tokio::fs::create_dir_all(&directory)
+            .await
+            .map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+        let path = directory.join(&file);
+        // Created exclusively: concurrent starts with the same name, or a
+        // file that appeared meanwhile, are never overwritten
+        let output = create_new(&path).await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => format!("File {} already exists", file),
+            _ => format!("Failed to create {}: {}", path.display(), e),
+        })?;
+
+        let capture = match capture::start_capture(spec.clone()) {
+            Ok(capture) => capture,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&path).await;
+                return Err(e);
+            }
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let status = RecordingStatus {
+            id,
+            node_id,
+            node_name: spec.target.clone(),
+            format: spec.format,
+            file: file.clone(),
+            state: RecordingState::Recording,
+            started: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            elapsed_secs: 0,
+            duration_secs: spec.duration_secs,
+            bytes: 0,
+            download: format!("/api/v1/record/{}/download", id),
+            error: None,
+        };
+        {
+            let mut recordings = self.recordings.lock().unwrap();
+            recordings.insert(
+                id,
+                Recording {
+                    status: status.clone(),
+                    path: path.clone(),
+                    started: Instant::now(),
+                    ended: None,
+                    stop: capture.stop.clone(),
+                    stopped: false,
+                },
+            );
+            Self::prune(&mut recordings);
+        }
+        info!("Recording {} to {}", spec.target, path.display());
+
+        let manager = Arc::clone(self);
+        tokio::spawn(async move {
+            let progress = |bytes| manager.update(id, |r| r.status.bytes = bytes);
+            let result = write_file(&path, output, capture, &spec, progress).await;
+            if let Err(ref e) = result {
+                warn!("Recording {} failed: {}", id, e);
+            }
+            manager.update(id, |r| {
+                r.ended = Some(Instant::now());
+                r.status.state = match result {
+                    Ok(_) if r.stopped => RecordingState::Stopped,
+                    Ok(_) => RecordingState::Finished,
+                    Err(e) => {
+                        r.status.error = Some(e);
+                        RecordingState::Failed
+                    }
+                };
+            });
+        });
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_valid_file_name() {
+        assert!(valid_file_name("side-a_1.wav"));
+        assert!(!valid_file_name("../side-a.wav"));
+        assert!(!valid_file_name("dir/side-a.wav"));
+        assert!(!valid_file_name(".hidden"));
+        assert!(!valid_file_name(""));
+    }
+
+    #[test]
+    fn test_file_name() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            file_name(None, "riaa.output", CaptureFormat::Flac, now),
+            "riaa_output-2023-11-14T22-13-20.flac"
+        );
+        assert_eq!(file_name(Some("side-a.wav"), "riaa", CaptureFormat::Wav, now), "side-a.wav");
+        assert_eq!(file_name(Some("side-a"), "riaa", CaptureFormat::Flac, now), "side-a.flac");
+    }
+
+    #[tokio::test]
+    async fn test_existing_file_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = Arc::new(RecordingManager::default());
+        manager.configure(Some(dir.path().to_path_buf()));
+        std::fs::write(dir.path().join("side-a.wav"), "take 1").unwrap();
+
+        let spec = CaptureSpec {
+            target: "riaa".to_string(),
+            monitor: false,
+            format: CaptureFormat::Wav,
+            rate: 48000,
+            channels: 2,
+            duration_secs: None,
+        };
+        let error = manager.start(40, spec, "side-a.wav".to_string()).await.unwrap_err();
+        assert!(error.contains("already exists"));
+        assert_eq!(std::fs::read_to_string(dir.path().join("side-a.wav")).unwrap(), "take 1");
+        assert!(manager.list().is_empty());
+    }
+}
@@ -0,0 +1,299 @@
+//! Multiple PipeWire instances (cores)
+//!
+//! Besides the default instance, further PipeWire instances can be
+//! configured as cores in the `[cores]` section, mapping a name to a
+//! `PIPEWIRE_REMOTE` socket name or path, e.g. the system instance next to
+//! the user instance:
+//!
+//! ```toml
+//! [cores]
+//! system = "/run/pipewire/pipewire-0"
+//! ```
+//!
+//! Requests to `/api/v1/cores/<name>/...` are served by the endpoint without
+//! the prefix with the PipeWire tools connecting to the remote of the core.
+//! The remote is kept in a task-local for the request, handed to the worker
+//! threads by [`crate::worker`] and passed to the tools by
+//! [`crate::command::pipewire`]. Only endpoints that query PipeWire directly
+//! ([`CORE_ROUTES`]) are available per core; DSP modules, caches, rules and
+//! the other background tasks belong to the default instance.
+//!
+//! Clients that cannot change the paths (e.g. in containers) select the core
+//! of a request with the [`REMOTE_HEADER`] header or the `remote` query
+//! parameter instead, giving the core's name or remote. Without either, the
+//! default instance is used.
+
+use axum::{
+    extract::Request,
+    http::{uri::PathAndQuery, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::RwLock;
+use tower::Layer;
+use tracing::warn;
+
+use crate::api_server::ApiError;
+
+/// Name of the default instance
+pub const DEFAULT_CORE: &str = "default";
+
+/// Header selecting the core of a request
+pub const REMOTE_HEADER: &str = "x-pipewire-remote";
+
+/// Prefix of the per-core routes
+pub const CORES_PREFIX: &str = "/api/v1/cores/";
+
+/// Endpoints available per core (paths starting with these)
+pub const CORE_ROUTES: &[&str] = &[
+    "/api/v1/ls",
+    "/api/v1/objects/",
+    "/api/v1/nodes/",
+    "/api/v1/volume",
+    "/api/v1/links",
+    "/api/v1/metadata",
+    "/api/v1/defaults/",
+    "/api/v1/clients",
+    "/api/v1/devices/",
+    "/api/v1/stats/load",
+    "/api/v1/streams/",
+];
+
+/// Endpoints excluded from [`CORE_ROUTES`], they persist state of the default instance
+const EXCLUDED_ROUTES: &[&str] = &["/api/v1/volume/save"];
+
+static CORES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+tokio::task_local! {
+    static REMOTE: String;
+}
+
+thread_local! {
+    static THREAD_REMOTE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Check a core name: letters, digits, '-' and '_'
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Set the configured cores, skipping invalid names
+pub fn set_cores(cores: &BTreeMap<String, String>) {
+    let mut valid = BTreeMap::new();
+    for (name, remote) in cores {
+        if name == DEFAULT_CORE || !valid_name(name) || remote.trim().is_empty() {
+            warn!("Ignoring core '{}': invalid or reserved name or empty remote", name);
+            continue;
+        }
+        valid.insert(name.clone(), remote.clone());
+    }
+    *CORES.write().unwrap() = valid;
+}
+
+/// Configured cores by name, without the default instance
+pub fn cores() -> BTreeMap<String, String> {
+    CORES.read().unwrap().clone()
+}
+
+/// Remote of a core, `Some(None)` for the default instance, None if unknown
+pub fn lookup(name: &str) -> Option<Option<String>> {
+    if name == DEFAULT_CORE {
+        return Some(None);
+    }
+    CORES.read().unwrap().get(name).map(|remote| Some(remote.clone()))
+}
+
+/// Remote of a core given by name or by its remote, `Some(None)` for the
+/// default instance (also for an empty value), None if unknown
+pub fn resolve(value: &str) -> Option<Option<String>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Some(None);
+    }
+    lookup(value).or_else(|| {
+        let cores = CORES.read().unwrap();
+        cores.values().find(|remote| *remote == value).map(|remote| Some(remote.clone()))
+    })
+}
+
+/// Core selected with the header or the `remote` query parameter
+fn requested_core(request: &Request) -> Option<String> {
+    if let Some(value) = request.headers().get(REMOTE_HEADER) {
+        return Some(value.to_str().unwrap_or_default().to_string());
+    }
+    request
+        .uri()
+        .query()?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("remote="))
+        .map(|value| value.to_string())
+}
+
+/// Remote of the current request or worker job, None for the default instance
+pub fn current() -> Option<String> {
+    THREAD_REMOTE
+        .with(|r| r.borrow().clone())
+        .or_else(|| REMOTE.try_with(|r| r.clone()).ok())
+}
+
+/// Run a future with a remote (None: the default instance)
+pub async fn scope<F: Future>(remote: Option<String>, future: F) -> F::Output {
+    match remote {
+        Some(remote) => REMOTE.scope(remote, future).await,
+        None => future.await,
+    }
+}
+
+/// Restores the remote of the thread when dropped, also after a panic
+struct ThreadRemote(Option<String>);
+
+impl Drop for ThreadRemote {
+    fn drop(&mut self) {
+        THREAD_REMOTE.with(|r| *r.borrow_mut() = self.0.take());
+    }
+}
+
+/// Run a blocking function with a remote on this thread
+pub fn run_with<T>(remote: Option<String>, f: impl FnOnce() -> T) -> T {
+    let _restore = ThreadRemote(THREAD_REMOTE.with(|r| r.replace(remote)));
+    f()
+}
+
+/// Check whether an endpoint is available per core
+pub fn is_core_route(path: &str) -> bool {
+    CORE_ROUTES.iter().any(|route| path.starts_with(route)) && !EXCLUDED_ROUTES.iter().any(|route| path.starts_with(route))
+}
+
+/// Split `/api/v1/cores/<name>/<rest>` into the core and `/api/v1/<rest>`
+pub fn split_core_path(path: &str) -> Option<(&str, String)> {
+    let (name, rest) = path.strip_prefix(CORES_PREFIX)?.split_once('/')?;
+    Some((name, format!("/api/v1/{}", rest)))
+}
+
+fn rewrite_uri(uri: &Uri, path: &str) -> Result<Uri, String> {
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(PathAndQuery::try_from(path_and_query).map_err(|e| e.to_string())?);
+    Uri::from_parts(parts).map_err(|e| e.to_string())
+}
+
+/// Serve `/api/v1/cores/<name>/...` with the endpoint without the prefix
+/// and select the core given with the header or the query parameter
+pub async fn middleware(mut request: Request, next: Next) -> Response {
+    let Some((name, path)) = split_core_path(request.uri().path()) else {
+        let Some(value) = requested_core(&request) else {
+            return next.run(request).await;
+        };
+        let remote = match resolve(&value) {
+            Some(None) => return next.run(request).await,
+            Some(remote) => remote,
+            None => return ApiError::BadRequest(format!("Core '{}' not found", value)).into_response(),
+        };
+        // Other endpoints would act on the default instance
+        if !is_core_route(request.uri().path()) {
+            return ApiError::BadRequest(format!("{} is not available per core", request.uri().path()))
+                .into_response();
+        }
+        return scope(remote, next.run(request)).await;
+    };
+    let Some(remote) = lookup(name) else {
+        return ApiError::NotFound(format!("Core '{}' not found", name)).into_response();
+    };
+    if !is_core_route(&path) {
+        return ApiError::NotFound(format!("{} is not available per core", path)).into_response();
+    }
+    match rewrite_uri(request.uri(), &path) {
+        Ok(uri) => *request.uri_mut() = uri,
+        Err(e) => return ApiError::BadRequest(format!("Invalid path: {}", e)).into_response(),
+    }
+    scope(remote, next.run(request)).await
+}
+
+/// Wrap the router so the per-core paths are rewritten before routing
+pub fn with_cores(app: Router) -> Router {
+    Router::new().fallback_service(axum::middleware::from_fn(middleware).layer(app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_core_paths() {
+        assert_eq!(
+            split_core_path("/api/v1/cores/system/volume/42"),
+            Some(("system", "/api/v1/volume/42".to_string()))
+        );
+        assert_eq!(split_core_path("/api/v1/cores"), None);
+        assert_eq!(split_core_path("/api/v1/volume"), None);
+
+        assert!(is_core_route("/api/v1/ls/sinks"));
+        assert!(is_core_route("/api/v1/volume/42"));
+        assert!(!is_core_route("/api/v1/volume/save"));
+        assert!(!is_core_route("/api/v1/modules"));
+
+        let uri: Uri = "/api/v1/cores/system/ls?type=Node".parse().unwrap();
+        assert_eq!(rewrite_uri(&uri, "/api/v1/ls").unwrap().to_string(), "/api/v1/ls?type=Node");
+    }
+
+    #[tokio::test]
+    async fn test_with_cores() {
+        use axum::{body::Body, http::StatusCode, routing::get};
+        use tower::Service;
+
+        set_cores(&BTreeMap::from([("system".to_string(), "pipewire-system".to_string())]));
+        let app = with_cores(Router::new().route(
+            "/api/v1/ls",
+            get(|| async { crate::worker::run(current).await.unwrap().unwrap_or_default() }),
+        ));
+        let get = |uri: &str| {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            app.clone().call(request)
+        };
+
+        let response = get("/api/v1/cores/system/ls?type=Node").await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], b"pipewire-system");
+        let response = get("/api/v1/ls").await.unwrap();
+        assert!(axum::body::to_bytes(response.into_body(), 1024).await.unwrap().is_empty());
+        assert_eq!(get("/api/v1/cores/other/ls").await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(get("/api/v1/cores/system/modules").await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        // Selected with the header or the query parameter
+        let request = Request::builder()
+            .uri("/api/v1/ls")
+            .header(REMOTE_HEADER, "pipewire-system")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().call(request).await.unwrap();
+        assert_eq!(&axum::body::to_bytes(response.into_body(), 1024).await.unwrap()[..], b"pipewire-system");
+        let response = get("/api/v1/ls?type=Node&remote=system").await.unwrap();
+        assert_eq!(&axum::body::to_bytes(response.into_body(), 1024).await.unwrap()[..], b"pipewire-system");
+        let response = get("/api/v1/ls?remote=default").await.unwrap();
+        assert!(axum::body::to_bytes(response.into_body(), 1024).await.unwrap().is_empty());
+        assert_eq!(get("/api/v1/ls?remote=other").await.unwrap().status(), StatusCode::BAD_REQUEST);
+        assert_eq!(get("/api/v1/modules?remote=system").await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_current_remote() {
+        assert_eq!(current(), None);
+        let inner = run_with(Some("pipewire-1".to_string()), || {
+            let nested = run_with(None, current);
+            (current(), nested)
+        });
+        assert_eq!(inner, (Some("pipewire-1".to_string()), None));
+        assert_eq!(current(), None);
+
+        assert!(valid_name("system-1"));
+        assert!(!valid_name("a/b"));
+        assert!(!valid_name(""));
+    }
+}
@@ -0,0 +1,270 @@
+//! Short-lived response cache with ETag support for listing endpoints
+//!
+//! Dashboards tend to poll `/api/v1/ls`, `/api/v1/properties` and
+//! `/api/v1/links` every second. Each of these runs pw-cli or pw-link, so
+//! responses are cached for a short time and keyed on a generation counter.
+//! The generation is bumped by every successful modifying request and by
+//! object cache refreshes, which makes changes made through the API visible
+//! immediately; changes made by other PipeWire clients show up once the
+//! entry expires.
+//!
+//! Responses carry an `ETag` derived from the body. A request with a
+//! matching `If-None-Match` header gets `304 Not Modified` without a body.
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api_server::AppState;
+
+/// Paths whose GET responses are cached
+pub const CACHED_PATHS: &[&str] = &["/api/v1/ls", "/api/v1/properties", "/api/v1/links"];
+
+/// Default lifetime of a cached response in milliseconds
+pub const DEFAULT_TTL_MS: u64 = 1000;
+
+/// Maximum number of cached responses; keys include the query string, so
+/// clients can create any number of them
+pub const MAX_ENTRIES: usize = 64;
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    generation: u64,
+    created: Instant,
+    etag: String,
+    body: Bytes,
+}
+
+/// Cache of serialized listing responses
+#[derive(Debug)]
+pub struct ResponseCache {
+    generation: AtomicU64,
+    ttl_ms: AtomicU64,
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            generation: AtomicU64::new(0),
+            ttl_ms: AtomicU64::new(ttl.as_millis() as u64),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Current registry generation
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Mark all cached responses as outdated
+    pub fn bump(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Set the lifetime of cached responses (0 disables caching)
+    pub fn set_ttl(&self, ttl: Duration) {
+        self.ttl_ms.store(ttl.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    pub fn ttl(&self) -> Duration {
+        Duration::from_millis(self.ttl_ms.load(Ordering::SeqCst))
+    }
+
+    /// Get a cached body and its ETag if it is still valid
+    pub fn get(&self, key: &str) -> Option<(String, Bytes)> {
+        let ttl = self.ttl();
+        let generation = self.generation();
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|e| e.generation == generation && e.created.elapsed() < ttl)
+            .map(|e| (e.etag.clone(), e.body.clone()))
+    }
+
+    /// Store a body, returning its ETag
+    ///
+    /// `generation` must be read before the response was built, so a
+    /// modification that happened meanwhile invalidates the entry.
+    pub fn insert(&self, key: &str, generation: u64, body: Bytes) -> String {
+        let etag = etag_for(&body);
+        let ttl = self.ttl();
+        if !ttl.is_zero() {
+            let current = self.generation();
+            let mut entries = self.entries.lock().unwrap();
+            entries.retain(|_, e| e.generation == current && e.created.elapsed() < ttl);
+            if entries.len() >= MAX_ENTRIES && !entries.contains_key(key) {
+                let oldest = entries.iter().min_by_key(|(_, e)| e.created).map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+            entries.insert(
+                key.to_string(),
+                CachedResponse {
+                    generation,
+                    created: Instant::now(),
+                    etag: etag.clone(),
+                    body,
+                },
+            );
+        }
+        etag
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(DEFAULT_TTL_MS))
+    }
+}
+
+/// Compute a strong ETag for a response body
+pub fn etag_for(body: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// Check whether an If-None-Match header value matches an ETag
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',').any(|candidate| {
+        let candidate = candidate.trim();
+        candidate == "*" || candidate.trim_start_matches("W/") == etag
+    })
+}
+
+fn respond(etag: String, body: Bytes, if_none_match: Option<&str>) -> Response {
+    let etag_value = HeaderValue::from_str(&etag).unwrap_or(HeaderValue::from_static("\"\""));
+    if if_none_match.map(|v| etag_matches(v, &etag)).unwrap_or(false) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag_value)]).into_response();
+    }
+    (
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static("application/json")),
+            (header::ETAG, etag_value),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Middleware serving cached listings and invalidating the cache on modifications
+pub async fn middleware(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let cache = &state.response_cache;
+    let method = request.method().clone();
+
+    if method != Method::GET && method != Method::HEAD {
+        let response = next.run(request).await;
+        if response.status().is_success() {
+            cache.bump();
+        }
+        return response;
+    }
+
+    if !CACHED_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    // Responses of other PipeWire instances have the same paths
+    let key = match crate::remote::current() {
+        Some(remote) => format!("{}|{}", remote, request.uri()),
+        None => request.uri().to_string(),
+    };
+    let if_none_match = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    if let Some((etag, body)) = cache.get(&key) {
+        return respond(etag, body, if_none_match.as_deref());
+    }
+
+    let generation = cache.generation();
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to buffer response for caching: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    let etag = cache.insert(&key, generation, body.clone());
+    respond(etag, body, if_none_match.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_invalidated_by_generation() {
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        let generation = cache.generation();
+        let etag = cache.insert("/api/v1/ls", generation, Bytes::from_static(b"[1]"));
+        assert_eq!(cache.get("/api/v1/ls"), Some((etag, Bytes::from_static(b"[1]"))));
+
+        cache.bump();
+        assert_eq!(cache.get("/api/v1/ls"), None);
+    }
+
+    #[test]
+    fn test_stale_generation_not_served() {
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        let generation = cache.generation();
+        cache.bump();
+        cache.insert("/api/v1/ls", generation, Bytes::from_static(b"[1]"));
+        assert_eq!(cache.get("/api/v1/ls"), None);
+    }
+
+    #[test]
+    fn test_entries_limited() {
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        cache.insert("/api/v1/ls?old", cache.generation(), Bytes::from_static(b"[1]"));
+        cache.bump();
+        for i in 0..MAX_ENTRIES + 10 {
+            cache.insert(&format!("/api/v1/ls?{}", i), cache.generation(), Bytes::from_static(b"[1]"));
+        }
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        // Outdated entries are dropped, then the oldest ones
+        assert!(!entries.contains_key("/api/v1/ls?old"));
+        assert!(!entries.contains_key("/api/v1/ls?0"));
+        assert!(entries.contains_key(&format!("/api/v1/ls?{}", MAX_ENTRIES + 9)));
+    }
+
+    #[test]
+    fn test_zero_ttl_disables_cache() {
+        let cache = ResponseCache::new(Duration::ZERO);
+        cache.insert("/api/v1/ls", cache.generation(), Bytes::from_static(b"[1]"));
+        assert_eq!(cache.get("/api/v1/ls"), None);
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        let etag = etag_for(b"{}");
+        assert_eq!(etag, etag_for(b"{}"));
+        assert_ne!(etag, etag_for(b"[]"));
+        assert!(etag_matches(&etag, &etag));
+        assert!(etag_matches(&format!("W/{}, \"other\"", etag), &etag));
+        assert!(etag_matches("*", &etag));
+        assert!(!etag_matches("\"other\"", &etag));
+    }
+}
@@ -0,0 +1,141 @@
+//! Detection of changes reverted by the session manager
+//!
+//! WirePlumber enforces its own policy: a stream linked by hand is re-linked
+//! to its target, a removed link comes back, a volume is restored from its
+//! stored state. The API call succeeds, but the change is gone a moment
+//! later. After setting a volume or creating or removing a link, the handlers
+//! check the state again once `revert_check_ms` (`[timeouts]`) have passed.
+//! A reverted change is published as a `change_reverted` event (see
+//! [`crate::events`]) and reported in the `warning` field of the response,
+//! with a hint to the metadata the session manager honors.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::events::{self, StateEvent};
+use crate::pwlink::PwLink;
+
+/// Default time until a change is checked in milliseconds
+pub const DEFAULT_CHECK_MS: u64 = 300;
+
+/// Smallest volume difference that counts as a revert
+const VOLUME_TOLERANCE: f32 = 0.01;
+
+const VOLUME_HINT: &str = "The session manager restored the volume from its stored state. \
+     Set the volume of the device route (PUT /api/v1/devices/:id/routes/:index/volume), \
+     which WirePlumber saves, or use a stream rule in volume.conf for streams.";
+
+const LINK_HINT: &str = "The session manager re-linked the stream. Set the target.object of the \
+     stream (PUT /api/v1/streams/:id/target) or default.configured.audio.sink, so WirePlumber \
+     links it itself.";
+
+static CHECK_MS: AtomicU64 = AtomicU64::new(DEFAULT_CHECK_MS);
+
+/// Set the time until changes are checked, 0 disables the checks
+pub fn set_check_ms(ms: u64) {
+    CHECK_MS.store(ms, Ordering::Relaxed);
+}
+
+/// Check whether a volume read back differs from the one set
+pub fn volume_reverted(set: f32, actual: f32) -> bool {
+    (set - actual).abs() > VOLUME_TOLERANCE
+}
+
+/// Publish a reverted change and get the warning for the response
+pub fn report(kind: &str, subject: &str, expected: String, actual: String) -> String {
+    let hint = if kind == "volume" { VOLUME_HINT } else { LINK_HINT };
+    warn!("{} change of {} was reverted: expected {}, found {}", kind, subject, expected, actual);
+    let warning = format!("The {} change of {} was reverted ({} instead of {}). {}", kind, subject, actual, expected, hint);
+    events::publish(StateEvent::ChangeReverted {
+        kind: kind.to_string(),
+        subject: subject.to_string(),
+        expected,
+        actual,
+        hint: hint.to_string(),
+    });
+    warning
+}
+
+/// Wait for the check time and run `check` on a worker, None if disabled
+async fn after_delay<T: Send + 'static>(check: impl FnOnce() -> T + Send + 'static) -> Option<T> {
+    let ms = CHECK_MS.load(Ordering::Relaxed);
+    if ms == 0 {
+        return None;
+    }
+    tokio::time::sleep(Duration::from_millis(ms)).await;
+    crate::worker::run(check).await.ok()
+}
+
+/// Check that the volume of a node is still the one set
+pub async fn check_volume(id: u32, volume: f32) -> Option<String> {
+    let actual = after_delay(move || crate::wpctl::get_volume(id)).await?.ok()?;
+    volume_reverted(volume, actual.volume).then(|| {
+        let subject = if actual.name.is_empty() { id.to_string() } else { actual.name.clone() };
+        report("volume", &subject, format!("{:.2}", volume), format!("{:.2}", actual.volume))
+    })
+}
+
+/// Check whether a link between two ports (names or IDs) is in the list
+pub fn link_present(links: &[PwLink], output: &str, input: &str) -> bool {
+    let matches = |name: &str, id: u32, port: &str| name == port || id.to_string() == port;
+    links
+        .iter()
+        .any(|l| matches(&l.output_port_name, l.output_port_id, output) && matches(&l.input_port_name, l.input_port_id, input))
+}
+
+/// Check that a link between two ports (names or IDs) still exists, or is
+/// still gone if `removed`
+pub async fn check_link(output: String, input: String, removed: bool) -> Option<String> {
+    let (out, inp) = (output.clone(), input.clone());
+    let links = after_delay(crate::pwlink::list_links).await?.ok()?;
+    let exists = link_present(&links, &out, &inp);
+    let subject = format!("{} -> {}", output, input);
+    match (removed, exists) {
+        (false, false) => Some(report("link", &subject, "linked".to_string(), "unlinked".to_string())),
+        (true, true) => Some(report("link", &subject, "unlinked".to_string(), "linked".to_string())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparisons() {
+        assert!(!volume_reverted(0.5, 0.5));
+        assert!(!volume_reverted(0.5, 0.505));
+        assert!(volume_reverted(0.5, 0.4));
+
+        let links = vec![PwLink {
+            id: 90,
+            output_port_id: 61,
+            output_port_name: "spotify:output_FL".to_string(),
+            input_port_id: 48,
+            input_port_name: "hdmi:playback_FL".to_string(),
+        }];
+        assert!(link_present(&links, "spotify:output_FL", "hdmi:playback_FL"));
+        assert!(link_present(&links, "61", "hdmi:playback_FL"));
+        assert!(!link_present(&links, "spotify:output_FL", "usb:playback_FL"));
+    }
+
+    #[tokio::test]
+    async fn test_report() {
+        let mut receiver = events::subscribe();
+        let warning = report("link", "spotify:output_FL -> hdmi:playback_FL", "linked".to_string(), "unlinked".to_string());
+        assert!(warning.contains("target.object"));
+        // Other tests may publish events meanwhile
+        let event = loop {
+            let event = receiver.recv().await.unwrap();
+            if event.name() == "change_reverted" {
+                break event;
+            }
+        };
+        assert_eq!(event.subject(), "spotify:output_FL -> hdmi:playback_FL");
+
+        set_check_ms(0);
+        assert_eq!(check_volume(1, 0.5).await, None);
+        set_check_ms(DEFAULT_CHECK_MS);
+    }
+}
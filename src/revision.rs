@@ -0,0 +1,123 @@
+//! Concurrent-write protection for module parameters
+//!
+//! Every [`NodeState`] has a revision counter that is incremented whenever
+//! its parameters change, through the API or externally (see
+//! [`crate::param_monitor`]). Module responses carry the revision in the
+//! `ETag` header. A client can send it back in `If-Match` on a modifying
+//! request; if the parameters changed in the meantime the request is
+//! rejected with `409 Conflict` instead of overwriting the other change.
+//!
+//! Modifying requests to a module are also serialized, so the parameter
+//! batches of two clients can't interleave.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, NodeState};
+
+/// Parsed `If-Match` header
+#[derive(Debug, Clone, PartialEq)]
+pub enum IfMatch {
+    /// `*`: any revision
+    Any,
+    /// One of the listed revisions
+    Revisions(Vec<u64>),
+}
+
+impl IfMatch {
+    /// Parse an `If-Match` value: `*` or a list of `"<revision>"` tags
+    ///
+    /// Bare numbers are accepted as well. Weak tags (`W/"3"`) are ignored:
+    /// If-Match uses the strong comparison, which a weak tag never passes.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value == "*" {
+            return Ok(IfMatch::Any);
+        }
+        let revisions = value
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.starts_with("W/"))
+            .map(|tag| {
+                let tag = tag.strip_prefix('"').and_then(|t| t.strip_suffix('"')).unwrap_or(tag);
+                tag.parse::<u64>()
+                    .map_err(|_| format!("Invalid If-Match revision '{}'", tag))
+            })
+            .collect::<Result<Vec<u64>, String>>()?;
+        Ok(IfMatch::Revisions(revisions))
+    }
+
+    /// Check whether the condition holds for a revision
+    pub fn matches(&self, revision: u64) -> bool {
+        match self {
+            IfMatch::Any => true,
+            IfMatch::Revisions(revisions) => revisions.contains(&revision),
+        }
+    }
+}
+
+fn with_etag(mut response: Response, revision: u64) -> Response {
+    if let Ok(value) = HeaderValue::from_str(&format!("\"{}\"", revision)) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+/// Middleware adding the `ETag` header and checking `If-Match` on module routes
+pub async fn middleware(State(state): State<Arc<NodeState>>, request: Request, next: Next) -> Response {
+    let method = request.method();
+    if !(method == Method::PUT || method == Method::POST || method == Method::DELETE) {
+        let response = next.run(request).await;
+        return with_etag(response, state.revision());
+    }
+
+    let condition = match request.headers().get(header::IF_MATCH).map(|v| v.to_str()) {
+        None => None,
+        Some(Ok(value)) => match IfMatch::parse(value) {
+            Ok(condition) => Some(condition),
+            Err(e) => return ApiError::BadRequest(e).into_response(),
+        },
+        Some(Err(_)) => return ApiError::BadRequest("Invalid If-Match header".to_string()).into_response(),
+    };
+
+    // Hold the write lock from the check until the change is complete
+    let _guard = state.write_lock.lock().await;
+    let current = state.revision();
+    if let Some(condition) = condition {
+        if !condition.matches(current) {
+            let error = ApiError::Conflict(format!(
+                "Parameters of '{}' were changed (revision {}), reload and retry",
+                state.node_name, current
+            ));
+            return with_etag(error.into_response(), current);
+        }
+    }
+
+    let response = next.run(request).await;
+    with_etag(response, state.revision())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_if_match() {
+        assert_eq!(IfMatch::parse("*").unwrap(), IfMatch::Any);
+        assert_eq!(IfMatch::parse("\"7\"").unwrap(), IfMatch::Revisions(vec![7]));
+        // Weak tags never match
+        assert_eq!(IfMatch::parse("W/\"3\", \"4\"").unwrap(), IfMatch::Revisions(vec![4]));
+        assert!(!IfMatch::parse("W/\"3\"").unwrap().matches(3));
+        assert_eq!(IfMatch::parse("12").unwrap(), IfMatch::Revisions(vec![12]));
+        assert!(IfMatch::parse("\"abc\"").is_err());
+
+        assert!(IfMatch::Any.matches(5));
+        assert!(IfMatch::parse("\"3\", \"4\"").unwrap().matches(4));
+        assert!(!IfMatch::parse("\"3\"").unwrap().matches(4));
+    }
+}
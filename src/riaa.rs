@@ -1,11 +1,13 @@
 use axum::{
     extract::State,
+    middleware,
     routing::{get, post, put},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use crate::api_server::{ApiError, NodeState};
+use crate::modules::{require_module, Module};
 use crate::parameters::ParameterValue;
 
 // API Models
@@ -52,7 +54,7 @@ pub struct NotchConfig {
 
 // Handlers
 pub async fn get_config(State(state): State<Arc<NodeState>>) -> Result<Json<RiaaConfig>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     
     let gain_db = params.get("riaa:Gain (dB)")
         .and_then(|v| match v {
@@ -131,7 +133,7 @@ pub async fn get_config(State(state): State<Arc<NodeState>>) -> Result<Json<Riaa
 }
 
 pub async fn get_gain(State(state): State<Arc<NodeState>>) -> Result<Json<GainValue>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     
     let gain_db = params.get("riaa:Gain (dB)")
         .and_then(|v| match v {
@@ -147,7 +149,7 @@ pub async fn set_gain(
     State(state): State<Arc<NodeState>>,
     Json(gain_value): Json<GainValue>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    state.set_parameter("riaa:Gain (dB)", ParameterValue::Float(gain_value.gain_db))?;
+    state.set_parameter_async("riaa:Gain (dB)", ParameterValue::Float(gain_value.gain_db)).await?;
     
     Ok(Json(serde_json::json!({
         "status": "ok",
@@ -156,7 +158,7 @@ pub async fn set_gain(
 }
 
 pub async fn get_subsonic_filter(State(state): State<Arc<NodeState>>) -> Result<Json<SubsonicFilterValue>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     
     let filter = params.get("riaa:Subsonic Filter")
         .and_then(|v| match v {
@@ -172,7 +174,7 @@ pub async fn set_subsonic_filter(
     State(state): State<Arc<NodeState>>,
     Json(filter_value): Json<SubsonicFilterValue>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    state.set_parameter("riaa:Subsonic Filter", ParameterValue::Int(filter_value.filter))?;
+    state.set_parameter_async("riaa:Subsonic Filter", ParameterValue::Int(filter_value.filter)).await?;
     
     Ok(Json(serde_json::json!({
         "status": "ok",
@@ -181,7 +183,7 @@ pub async fn set_subsonic_filter(
 }
 
 pub async fn get_riaa_enable(State(state): State<Arc<NodeState>>) -> Result<Json<EnableValue>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     
     let enabled = params.get("riaa:RIAA Enable")
         .and_then(|v| match v {
@@ -197,7 +199,7 @@ pub async fn set_riaa_enable(
     State(state): State<Arc<NodeState>>,
     Json(enable_value): Json<EnableValue>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    state.set_parameter("riaa:RIAA Enable", ParameterValue::Bool(enable_value.enabled))?;
+    state.set_parameter_async("riaa:RIAA Enable", ParameterValue::Bool(enable_value.enabled)).await?;
     
     Ok(Json(serde_json::json!({
         "status": "ok",
@@ -206,7 +208,7 @@ pub async fn set_riaa_enable(
 }
 
 pub async fn get_declick_enable(State(state): State<Arc<NodeState>>) -> Result<Json<EnableValue>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     
     let enabled = params.get("riaa:Declick Enable")
         .and_then(|v| match v {
@@ -222,7 +224,7 @@ pub async fn set_declick_enable(
     State(state): State<Arc<NodeState>>,
     Json(enable_value): Json<EnableValue>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    state.set_parameter("riaa:Declick Enable", ParameterValue::Bool(enable_value.enabled))?;
+    state.set_parameter_async("riaa:Declick Enable", ParameterValue::Bool(enable_value.enabled)).await?;
     
     Ok(Json(serde_json::json!({
         "status": "ok",
@@ -231,7 +233,7 @@ pub async fn set_declick_enable(
 }
 
 pub async fn get_spike_config(State(state): State<Arc<NodeState>>) -> Result<Json<SpikeConfig>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     
     let threshold_db = params.get("riaa:Spike Threshold (dB)")
         .and_then(|v| match v {
@@ -263,7 +265,7 @@ pub async fn set_spike_config(
     params.insert("riaa:Spike Threshold (dB)".to_string(), ParameterValue::Float(spike_config.threshold_db));
     params.insert("riaa:Spike Width (ms)".to_string(), ParameterValue::Float(spike_config.width_ms));
     
-    state.set_parameters(params)?;
+    state.set_parameters_async(params).await?;
     
     Ok(Json(serde_json::json!({
         "status": "ok",
@@ -273,7 +275,7 @@ pub async fn set_spike_config(
 }
 
 pub async fn get_notch_config(State(state): State<Arc<NodeState>>) -> Result<Json<NotchConfig>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     
     let enabled = params.get("riaa:Notch Filter Enable")
         .and_then(|v| match v {
@@ -314,7 +316,7 @@ pub async fn set_notch_config(
     params.insert("riaa:Notch Frequency (Hz)".to_string(), ParameterValue::Float(notch_config.frequency_hz));
     params.insert("riaa:Notch Q Factor".to_string(), ParameterValue::Float(notch_config.q_factor));
     
-    state.set_parameters(params)?;
+    state.set_parameters_async(params).await?;
     
     Ok(Json(serde_json::json!({
         "status": "ok",
@@ -334,7 +336,7 @@ pub async fn set_default(State(state): State<Arc<NodeState>>) -> Result<Json<ser
     params.insert("riaa:RIAA Enable".to_string(), ParameterValue::Bool(false));
     params.insert("riaa:Declick Enable".to_string(), ParameterValue::Bool(false));
     
-    state.set_parameters(params)?;
+    state.set_parameters_async(params).await?;
     
     Ok(Json(serde_json::json!({
         "status": "ok",
@@ -352,7 +354,7 @@ pub async fn save_config(State(state): State<Arc<NodeState>>) -> Result<Json<ser
     let mut params = HashMap::new();
     params.insert("riaa:Store settings".to_string(), ParameterValue::Int(1));
     
-    state.set_parameters(params)?;
+    state.set_parameters_async(params).await?;
     
     Ok(Json(serde_json::json!({
         "status": "ok",
@@ -371,6 +373,12 @@ pub fn create_router(state: Arc<NodeState>) -> Router {
         .route("/api/v1/module/riaa/spike", get(get_spike_config).put(set_spike_config))
         .route("/api/v1/module/riaa/notch", get(get_notch_config).put(set_notch_config))
         .route("/api/v1/module/riaa/set-default", put(set_default))
+        .route("/api/v1/module/riaa/input", get(crate::riaa_input::get_input).put(crate::riaa_input::set_input))
         .route("/api/v1/module/riaa/save", post(save_config))
+        .route_layer(middleware::from_fn_with_state(state.clone(), crate::revision::middleware))
+        .route_layer(middleware::from_fn_with_state(
+            Arc::new(Module::new("riaa", state.clone())),
+            require_module,
+        ))
         .with_state(state)
 }
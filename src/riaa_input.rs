@@ -0,0 +1,208 @@
+//! RIAA input selection
+//!
+//! The RIAA filter processes whatever capture source is linked into its
+//! input ports. Switching inputs (e.g. from the phono input to line-in)
+//! removes the links from the current source and links the new one, using
+//! the link manager.
+
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, NodeState};
+use crate::link_manager_cli::{self, LinkData};
+use crate::linker::{LinkRule, LinkType, LogLevel, NodeIdentifier};
+use crate::pwcli::{self, PwObject};
+
+/// A capture source that can feed the RIAA filter
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct InputSource {
+    pub id: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Current and available inputs of the RIAA filter
+#[derive(Debug, Clone, Serialize)]
+pub struct InputStatus {
+    /// Name of the RIAA node
+    pub node: String,
+    /// Sources currently linked into the RIAA filter
+    pub current: Vec<InputSource>,
+    /// Capture sources that can be selected
+    pub available: Vec<InputSource>,
+}
+
+/// Request body for PUT /api/v1/module/riaa/input
+#[derive(Debug, Deserialize)]
+pub struct SelectInputRequest {
+    /// node.name of the source to use, null to disconnect all inputs
+    pub source: Option<String>,
+}
+
+fn to_input_source(obj: &PwObject) -> Option<InputSource> {
+    Some(InputSource {
+        id: obj.id,
+        name: obj.name()?.to_string(),
+        description: obj.get("node.description").map(|s| s.to_string()),
+    })
+}
+
+/// Node IDs of the sources linked into a node's input ports
+pub fn linked_source_ids(data: &LinkData, node_id: u32) -> Vec<u32> {
+    let inputs: Vec<u32> = data.get_input_ports(node_id).iter().map(|p| p.id).collect();
+    let mut ids: Vec<u32> = data
+        .links
+        .iter()
+        .filter(|l| inputs.contains(&l.input_port_id))
+        .filter_map(|l| data.ports.iter().find(|p| p.id == l.output_port_id))
+        .map(|p| p.node_id)
+        .collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// Check whether a node is a capture source (e.g. an ALSA input)
+pub fn is_capture_source(obj: &PwObject) -> bool {
+    obj.media_class() == Some("Audio/Source")
+}
+
+/// Rule linking or unlinking two nodes by exact name
+fn rule(source: &str, destination: &str, link_type: LinkType) -> LinkRule {
+    let exact = |name: &str| NodeIdentifier {
+        node_name: Some(format!("^{}$", regex::escape(name))),
+        node_nick: None,
+        object_path: None,
+    };
+    LinkRule {
+        name: format!("riaa-input {}", source),
+        source: exact(source),
+        destination: exact(destination),
+        link_type,
+        link_at_startup: false,
+        relink_every: 0,
+        info_level: LogLevel::Info,
+        error_level: LogLevel::Error,
+        wait_for: None,
+        exclusive: false,
+        channel_map: None,
+        source_ports: None,
+        destination_ports: None,
+        include_monitors: false,
+    }
+}
+
+fn apply(rule: &LinkRule) -> Result<(), String> {
+    let results = link_manager_cli::apply_link_rule(rule)?;
+    let failures: Vec<String> = results.into_iter().filter(|r| !r.success).map(|r| r.message).collect();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
+/// Get the current and available inputs of the RIAA node
+pub fn input_status(riaa: &PwObject) -> Result<InputStatus, String> {
+    let data = LinkData::load()?;
+    let nodes = pwcli::list_nodes()?;
+    let current_ids = linked_source_ids(&data, riaa.id);
+
+    Ok(InputStatus {
+        node: riaa.name().unwrap_or_default().to_string(),
+        current: nodes
+            .iter()
+            .filter(|n| current_ids.contains(&n.id))
+            .filter_map(to_input_source)
+            .collect(),
+        available: nodes
+            .iter()
+            .filter(|n| n.id != riaa.id && is_capture_source(n))
+            .filter_map(to_input_source)
+            .collect(),
+    })
+}
+
+/// Link a source into the RIAA node, disconnecting all other sources
+pub fn select_input(riaa: &PwObject, source: Option<&str>) -> Result<InputStatus, String> {
+    let riaa_name = riaa.name().ok_or("RIAA node has no name")?.to_string();
+    let status = input_status(riaa)?;
+
+    if let Some(source) = source {
+        if !status.available.iter().chain(status.current.iter()).any(|s| s.name == source) {
+            return Err(format!("Source '{}' not found", source));
+        }
+    }
+
+    for current in status.current.iter().filter(|c| Some(c.name.as_str()) != source) {
+        apply(&rule(&current.name, &riaa_name, LinkType::Unlink))?;
+    }
+    if let Some(source) = source {
+        apply(&rule(source, &riaa_name, LinkType::Link))?;
+    }
+
+    input_status(riaa)
+}
+
+/// Get the selected and available inputs
+/// GET /api/v1/module/riaa/input
+pub async fn get_input(State(state): State<Arc<NodeState>>) -> Result<Json<InputStatus>, ApiError> {
+    let status = crate::worker::run(move || {
+        let riaa = state.find_node()?;
+        input_status(&riaa).map_err(|e| ApiError::Internal(format!("Failed to read inputs: {}", e)))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))??;
+
+    Ok(Json(status))
+}
+
+/// Switch the input of the RIAA filter
+/// PUT /api/v1/module/riaa/input
+pub async fn set_input(
+    State(state): State<Arc<NodeState>>,
+    Json(request): Json<SelectInputRequest>,
+) -> Result<Json<InputStatus>, ApiError> {
+    let status = crate::worker::run(move || {
+        let riaa = state.find_node()?;
+        select_input(&riaa, request.source.as_deref()).map_err(|e| {
+            if e.ends_with("not found") {
+                ApiError::NotFound(e)
+            } else {
+                ApiError::Internal(format!("Failed to switch input: {}", e))
+            }
+        })
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))??;
+
+    Ok(Json(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link_manager_cli::{LinkInfo, PortDirection, PortInfo};
+
+    #[test]
+    fn test_linked_source_ids() {
+        let data = LinkData {
+            nodes: Vec::new(),
+            monitor_ports: Vec::new(),
+            ports: vec![
+                PortInfo::new(1, 10, PortDirection::Output),
+                PortInfo::new(2, 10, PortDirection::Output),
+                PortInfo::new(3, 20, PortDirection::Output),
+                PortInfo::new(4, 30, PortDirection::Input),
+                PortInfo::new(5, 30, PortDirection::Input),
+                PortInfo::new(6, 40, PortDirection::Input),
+            ],
+            links: vec![LinkInfo::new(100, 1, 4), LinkInfo::new(101, 2, 5), LinkInfo::new(102, 3, 6)],
+        };
+        assert_eq!(linked_source_ids(&data, 30), vec![10]);
+        assert_eq!(linked_source_ids(&data, 40), vec![20]);
+        assert!(linked_source_ids(&data, 10).is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+//! Volume of the individual routes of a device
+//!
+//! A device such as an onboard codec has several routes (speaker,
+//! headphones, line out). wpctl only reports the volume of the device as a
+//! whole, while each route keeps its own `channelVolumes` and `mute` in the
+//! `Route` parameter. The routes are listed by `EnumRoute`; the volumes of
+//! the active routes are in `Route` and are changed by writing it.
+//!
+//! Volumes use the same cubic scale as wpctl (the channel volumes are the
+//! cube of the volume). The routes are read and written with
+//! [`crate::volume_ops`].
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::pod_parser::ParamObject;
+use crate::volume_ops::{self, VolumeState};
+
+/// Volume state of a route
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RouteVolume {
+    pub index: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// "Output" or "Input"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<String>,
+    /// Jack state: "yes", "no" or "unknown"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available: Option<String>,
+    /// The route is in use, only active routes report and accept a volume
+    pub active: bool,
+    /// Profile device of the active route
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<u32>,
+    /// Volume on the wpctl scale (highest channel)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    /// Linear volume per channel
+    pub channel_volumes: Vec<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+}
+
+/// Request body for PUT /api/v1/devices/:id/routes/:index/volume
+#[derive(Debug, Deserialize)]
+pub struct SetRouteVolumeRequest {
+    /// Volume on the wpctl scale (0.0 - 2.0) for all channels
+    pub volume: Option<f32>,
+    /// Volumes per channel on the wpctl scale, instead of `volume`
+    pub channels: Option<Vec<f32>>,
+    pub mute: Option<bool>,
+}
+
+impl SetRouteVolumeRequest {
+    /// Volumes to set: per channel, one for all channels or none
+    pub fn volumes(&self) -> Vec<f32> {
+        self.channels.clone().unwrap_or_else(|| self.volume.into_iter().collect())
+    }
+}
+
+fn index_of(param: &ParamObject) -> Option<u32> {
+    param.properties.get("index")?.as_u64().map(|i| i as u32)
+}
+
+fn string_of(param: &ParamObject, key: &str) -> Option<String> {
+    param.properties.get(key)?.as_str().map(|s| s.to_string())
+}
+
+/// Find a route by index in the `EnumRoute` and `Route` parameters of a device
+pub fn find_route(enum_routes: &[ParamObject], routes: &[ParamObject], index: u32) -> Option<RouteVolume> {
+    let info = enum_routes.iter().find(|r| index_of(r) == Some(index))?;
+    let active = routes.iter().find(|r| index_of(r) == Some(index));
+    let state = active
+        .and_then(|r| r.properties.get("props"))
+        .and_then(|p| p.as_object())
+        .map(volume_ops::from_props)
+        .unwrap_or_default();
+
+    Some(RouteVolume {
+        index,
+        name: string_of(info, "name").unwrap_or_default(),
+        description: string_of(info, "description"),
+        direction: string_of(info, "direction"),
+        available: string_of(info, "available"),
+        active: active.is_some(),
+        device: active
+            .and_then(|r| r.properties.get("device"))
+            .and_then(|d| d.as_u64())
+            .map(|d| d as u32),
+        volume: state.volume,
+        channel_volumes: state.channel_volumes,
+        mute: state.mute,
+    })
+}
+
+/// Build the `Route` parameter that sets the volumes of an active route
+///
+/// One volume is set on all channels, several are set per channel (see
+/// [`volume_ops::props_value`]).
+pub fn route_param(route: &RouteVolume, volumes: &[f32], mute: Option<bool>) -> Result<Value, String> {
+    let device = route
+        .device
+        .filter(|_| route.active)
+        .ok_or_else(|| format!("Route {} is not active", route.index))?;
+    let current = VolumeState { volume: route.volume, channel_volumes: route.channel_volumes.clone(), mute: route.mute };
+    let props = volume_ops::props_value(&current, volumes, mute)?;
+    Ok(json!({ "index": route.index, "device": device, "props": props, "save": true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(id: &str, value: Value) -> ParamObject {
+        ParamObject {
+            object_type: "Route".to_string(),
+            id: id.to_string(),
+            properties: value.as_object().unwrap().clone(),
+        }
+    }
+
+    fn device_params() -> (Vec<ParamObject>, Vec<ParamObject>) {
+        let enum_routes = vec![
+            param("EnumRoute", json!({"index": 0, "name": "analog-output-speaker", "direction": "Output", "available": "unknown"})),
+            param("EnumRoute", json!({"index": 1, "name": "analog-output-headphones", "direction": "Output", "available": "no"})),
+        ];
+        let routes = vec![param(
+            "Route",
+            json!({"index": 0, "device": 4, "props": {"channelVolumes": [0.125, 0.064], "mute": false}}),
+        )];
+        (enum_routes, routes)
+    }
+
+    #[test]
+    fn test_find_route() {
+        let (enum_routes, routes) = device_params();
+        let speaker = find_route(&enum_routes, &routes, 0).unwrap();
+        assert!(speaker.active);
+        assert_eq!(speaker.device, Some(4));
+        assert!((speaker.volume.unwrap() - 0.5).abs() < 1e-4);
+        assert_eq!(speaker.mute, Some(false));
+
+        let headphones = find_route(&enum_routes, &routes, 1).unwrap();
+        assert!(!headphones.active);
+        assert_eq!(headphones.volume, None);
+        assert_eq!(headphones.available.as_deref(), Some("no"));
+        assert!(find_route(&enum_routes, &routes, 2).is_none());
+    }
+
+    #[test]
+    fn test_route_param() {
+        let (enum_routes, routes) = device_params();
+        let speaker = find_route(&enum_routes, &routes, 0).unwrap();
+        let value = route_param(&speaker, &[0.5], Some(true)).unwrap();
+        assert_eq!(value["device"], json!(4));
+        assert_eq!(value["props"]["channelVolumes"], json!([0.125, 0.125]));
+        assert_eq!(value["props"]["mute"], json!(true));
+
+        let headphones = find_route(&enum_routes, &routes, 1).unwrap();
+        assert!(route_param(&headphones, &[0.5], None).is_err());
+    }
+}
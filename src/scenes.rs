@@ -0,0 +1,347 @@
+//! Scenes for one-tap switching between setups
+//!
+//! A scene bundles a settings preset of the DSP modules (saved with
+//! `POST /api/v1/settings/save?preset=<name>`), the links to create and the
+//! volumes of sinks, e.g. "TV", "Vinyl" or "Party". Activating a scene
+//! applies settings, then links, then volumes. If a step fails, the steps
+//! already applied are rolled back so the system is left as it was.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+use crate::api_server::{ApiError, NodeState};
+use crate::link_manager_cli::LinkData;
+use crate::linker::NodeIdentifier;
+use crate::settings::Settings;
+use crate::topology::{self, Topology, TopologyLink};
+
+/// Volume of a node in a scene
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SceneVolume {
+    /// Node name (supports regex like link rules)
+    #[serde(rename = "node.name")]
+    pub node_name: String,
+    pub volume: f32,
+}
+
+/// Scene definition from scenes.conf
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SceneConfig {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Settings preset to restore
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<String>,
+    /// Links to create ("node_name:port_name")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<TopologyLink>,
+    /// Remove other links of the nodes referenced by `links`
+    #[serde(default)]
+    pub prune: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<SceneVolume>,
+}
+
+/// A scene and whether it is the active one
+#[derive(Debug, Clone, Serialize)]
+pub struct SceneStatus {
+    #[serde(flatten)]
+    pub config: SceneConfig,
+    pub active: bool,
+}
+
+/// Outcome of a step of a scene activation
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StepResult {
+    /// "settings", "links" or "volumes"
+    pub step: String,
+    pub success: bool,
+    pub message: String,
+}
+
+/// Result of activating a scene
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivationResult {
+    pub scene: String,
+    pub success: bool,
+    /// Steps were undone because a later step failed
+    pub rolled_back: bool,
+    pub steps: Vec<StepResult>,
+    /// Errors while rolling back
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rollback_errors: Vec<String>,
+}
+
+/// State before a step, to roll it back
+enum Undo {
+    Settings(Box<Settings>),
+    Links(Topology),
+    Volumes(Vec<(u32, f32)>),
+}
+
+/// Configured scenes and the active one
+#[derive(Default)]
+pub struct SceneManager {
+    scenes: Vec<SceneConfig>,
+    active: Mutex<Option<String>>,
+}
+
+impl SceneManager {
+    pub fn new(scenes: Vec<SceneConfig>) -> Self {
+        Self {
+            scenes,
+            active: Mutex::new(None),
+        }
+    }
+
+    /// Get a scene by name
+    pub fn get(&self, name: &str) -> Option<SceneConfig> {
+        self.scenes.iter().find(|s| s.name == name).cloned()
+    }
+
+    /// List all scenes
+    pub fn list(&self) -> Vec<SceneStatus> {
+        let active = self.active.lock().unwrap().clone();
+        self.scenes
+            .iter()
+            .map(|s| SceneStatus {
+                config: s.clone(),
+                active: active.as_deref() == Some(s.name.as_str()),
+            })
+            .collect()
+    }
+
+    /// Name of the last successfully activated scene
+    pub fn active(&self) -> Option<String> {
+        self.active.lock().unwrap().clone()
+    }
+
+    fn set_active(&self, name: Option<String>) {
+        *self.active.lock().unwrap() = name;
+    }
+}
+
+async fn run<T: Send + 'static>(f: impl FnOnce() -> Result<T, String> + Send + 'static) -> Result<T, String> {
+    crate::worker::run(f).await.map_err(|e| format!("Worker error: {}", e))?
+}
+
+/// Get the message of an API error, for step results
+pub(crate) fn api_error_message(e: ApiError) -> String {
+    match e {
+        ApiError::NotFound(e) | ApiError::BadRequest(e) | ApiError::Internal(e) | ApiError::Conflict(e) => e,
+        ApiError::ModuleNotPresent(name) => format!("Module '{}' is not present", name),
+    }
+}
+
+/// Set scene volumes, returning the previous volumes of the nodes changed so far
+fn apply_volumes(volumes: &[SceneVolume], undo: &mut Vec<(u32, f32)>) -> Result<String, String> {
+    let data = LinkData::load()?;
+    for v in volumes {
+        let identifier = NodeIdentifier {
+            node_name: Some(v.node_name.clone()),
+            node_nick: None,
+            object_path: None,
+        };
+        let id = data
+            .find_matching_nodes(&identifier)
+            .first()
+            .map(|n| n.id)
+            .ok_or_else(|| format!("Node '{}' not found", v.node_name))?;
+        let previous = crate::wpctl::get_volume(id)?.volume;
+        crate::wpctl::set_volume(id, v.volume)?;
+        undo.push((id, previous));
+    }
+    Ok(format!("Set {} volume(s)", volumes.len()))
+}
+
+fn restore_volumes(volumes: &[(u32, f32)]) -> Vec<String> {
+    volumes
+        .iter()
+        .rev()
+        .filter_map(|(id, volume)| crate::wpctl::set_volume(*id, *volume).err())
+        .map(|e| format!("volumes: {}", e))
+        .collect()
+}
+
+/// Activate a scene: settings, then links, then volumes, rolling back on failure
+pub async fn activate(
+    manager: &SceneManager,
+    scene: &SceneConfig,
+    speakereq: Arc<NodeState>,
+    riaa: Arc<NodeState>,
+) -> ActivationResult {
+    let mut steps = Vec::new();
+    let mut undo = Vec::new();
+    let mut failed = false;
+
+    if let Some(preset) = scene.settings.clone() {
+        let previous = crate::settings::current_settings(&speakereq, &riaa).await;
+        let (s, r) = (speakereq.clone(), riaa.clone());
+        let result = run(move || {
+            let settings = crate::settings::load_preset(&preset).map_err(api_error_message)?;
+            crate::settings::apply_settings(&s, &r, settings).map_err(api_error_message)
+        })
+        .await;
+        match result {
+            Ok(modules) => {
+                undo.push(Undo::Settings(Box::new(previous)));
+                steps.push(step("settings", Ok(format!("Restored {}", modules.join(", ")))));
+            }
+            Err(e) => {
+                failed = true;
+                steps.push(step("settings", Err(e)));
+            }
+        }
+    }
+
+    if !failed && !scene.links.is_empty() {
+        let target = Topology {
+            nodes: Vec::new(),
+            links: scene.links.clone(),
+        };
+        let prune = scene.prune;
+        let result = run(move || {
+            let previous = topology::export()?;
+            let result = topology::apply(&target, prune)?;
+            Ok((previous, result))
+        })
+        .await;
+        match result {
+            Ok((previous, result)) => {
+                let changed = !result.created.is_empty() || !result.removed.is_empty();
+                if changed {
+                    undo.push(Undo::Links(previous));
+                }
+                if result.errors.is_empty() {
+                    steps.push(step(
+                        "links",
+                        Ok(format!(
+                            "Created {}, removed {}, unchanged {}",
+                            result.created.len(),
+                            result.removed.len(),
+                            result.unchanged
+                        )),
+                    ));
+                } else {
+                    failed = true;
+                    steps.push(step("links", Err(result.errors.join("; "))));
+                }
+            }
+            Err(e) => {
+                failed = true;
+                steps.push(step("links", Err(e)));
+            }
+        }
+    }
+
+    if !failed && !scene.volumes.is_empty() {
+        let volumes = scene.volumes.clone();
+        let (previous, result) = crate::worker::run(move || {
+            let mut previous = Vec::new();
+            let result = apply_volumes(&volumes, &mut previous);
+            (previous, result)
+        })
+        .await
+        .unwrap_or_else(|e| (Vec::new(), Err(format!("Worker error: {}", e))));
+        if !previous.is_empty() {
+            undo.push(Undo::Volumes(previous));
+        }
+        failed = result.is_err();
+        steps.push(step("volumes", result));
+    }
+
+    let mut rollback_errors = Vec::new();
+    if failed {
+        warn!("Scene '{}' failed, rolling back {} step(s)", scene.name, undo.len());
+        while let Some(u) = undo.pop() {
+            rollback_errors.extend(rollback(u, &speakereq, &riaa).await);
+        }
+    } else {
+        info!("Scene '{}' activated", scene.name);
+        manager.set_active(Some(scene.name.clone()));
+        let _ = crate::store::get().record_event(&crate::store::AuditEvent::new("scene.activate", &scene.name, ""));
+    }
+
+    ActivationResult {
+        scene: scene.name.clone(),
+        success: !failed,
+        rolled_back: failed && steps.iter().any(|s| s.success),
+        steps,
+        rollback_errors,
+    }
+}
+
+async fn rollback(undo: Undo, speakereq: &Arc<NodeState>, riaa: &Arc<NodeState>) -> Vec<String> {
+    let (s, r) = (speakereq.clone(), riaa.clone());
+    let result = crate::worker::run(move || match undo {
+        Undo::Settings(settings) => crate::settings::apply_settings(&s, &r, *settings)
+            .map(|_| Vec::new())
+            .unwrap_or_else(|e| vec![format!("settings: {}", api_error_message(e))]),
+        Undo::Links(previous) => match topology::apply(&previous, true) {
+            Ok(result) => result.errors.into_iter().map(|e| format!("links: {}", e)).collect(),
+            Err(e) => vec![format!("links: {}", e)],
+        },
+        Undo::Volumes(volumes) => restore_volumes(&volumes),
+    })
+    .await;
+    result.unwrap_or_else(|e| vec![format!("Worker error: {}", e)])
+}
+
+fn step(name: &str, result: Result<String, String>) -> StepResult {
+    let (success, message) = match result {
+        Ok(message) => (true, message),
+        Err(message) => (false, message),
+    };
+    StepResult {
+        step: name.to_string(),
+        success,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scene() {
+        let json = r#"[{
+            "name": "Vinyl",
+            "settings": "vinyl",
+            "links": [{ "output": "riaa:output_FL", "input": "speakereq2x2:input_FL" }],
+            "prune": true,
+            "volumes": [{ "node.name": "alsa_output.*", "volume": 0.6 }]
+        }]"#;
+        let scenes: Vec<SceneConfig> = serde_json::from_str(json).unwrap();
+        assert_eq!(scenes[0].settings.as_deref(), Some("vinyl"));
+        assert_eq!(scenes[0].links.len(), 1);
+        assert!(scenes[0].prune);
+        assert_eq!(scenes[0].volumes[0].node_name, "alsa_output.*");
+
+        let minimal: SceneConfig = serde_json::from_str(r#"{ "name": "TV" }"#).unwrap();
+        assert!(minimal.links.is_empty() && minimal.volumes.is_empty() && minimal.settings.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_activate_empty_scene() {
+        let manager = SceneManager::new(vec![SceneConfig {
+            name: "TV".to_string(),
+            description: None,
+            settings: None,
+            links: Vec::new(),
+            prune: false,
+            volumes: Vec::new(),
+        }]);
+        assert_eq!(manager.active(), None);
+
+        let scene = manager.get("TV").unwrap();
+        let node = Arc::new(NodeState::new("none".to_string()));
+        let result = activate(&manager, &scene, node.clone(), node).await;
+        assert!(result.success);
+        assert!(result.steps.is_empty());
+        assert_eq!(manager.active().as_deref(), Some("TV"));
+        assert!(manager.list()[0].active);
+    }
+}
@@ -0,0 +1,177 @@
+//! Shell commands run when nodes appear or disappear
+//!
+//! A lightweight alternative to WirePlumber scripts: `[[hooks]]` entries in
+//! pipewire-api.toml map a node name pattern to commands that are run with
+//! `sh -c` when a matching node is added or removed. The node is passed in
+//! environment variables:
+//!
+//! - `PW_EVENT`: `node_added` or `node_removed`
+//! - `PW_NODE_ID`, `PW_NODE_NAME`
+//! - `PW_MEDIA_CLASS`, `PW_NODE_DESCRIPTION` (empty if not set)
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::events::{self, NodeInfo, StateEvent};
+
+/// Hook definition from the `[[hooks]]` section
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScriptHook {
+    /// Regex on the node name
+    pub node: String,
+    /// Regex on the media class, e.g. "Audio/Sink"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_class: Option<String>,
+    /// Command run when a matching node appears
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_added: Option<String>,
+    /// Command run when a matching node disappears
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_removed: Option<String>,
+}
+
+struct CompiledHook {
+    hook: ScriptHook,
+    node: Regex,
+    media_class: Option<Regex>,
+}
+
+impl CompiledHook {
+    fn new(hook: ScriptHook) -> Result<Self, String> {
+        let node = Regex::new(&hook.node).map_err(|e| format!("Invalid node regex '{}': {}", hook.node, e))?;
+        let media_class = match &hook.media_class {
+            Some(pattern) => {
+                Some(Regex::new(pattern).map_err(|e| format!("Invalid media_class regex '{}': {}", pattern, e))?)
+            }
+            None => None,
+        };
+        Ok(Self { hook, node, media_class })
+    }
+
+    fn matches(&self, node: &NodeInfo) -> bool {
+        self.node.is_match(&node.name)
+            && match &self.media_class {
+                Some(re) => node.media_class.as_deref().map(|c| re.is_match(c)).unwrap_or(false),
+                None => true,
+            }
+    }
+
+    /// Command to run for an event, if any
+    fn command_for<'a>(&'a self, event: &'a StateEvent) -> Option<(&'a str, &'a NodeInfo)> {
+        let (command, node) = match event {
+            StateEvent::NodeAdded { node } => (self.hook.on_added.as_deref()?, node),
+            StateEvent::NodeRemoved { node } => (self.hook.on_removed.as_deref()?, node),
+            _ => return None,
+        };
+        self.matches(node).then_some((command, node))
+    }
+}
+
+/// Environment variables passed to a hook command
+pub fn hook_env(event: &str, node: &NodeInfo) -> Vec<(&'static str, String)> {
+    vec![
+        ("PW_EVENT", event.to_string()),
+        ("PW_NODE_ID", node.id.to_string()),
+        ("PW_NODE_NAME", node.name.clone()),
+        ("PW_MEDIA_CLASS", node.media_class.clone().unwrap_or_default()),
+        ("PW_NODE_DESCRIPTION", node.description.clone().unwrap_or_default()),
+    ]
+}
+
+async fn run_command(command: String, env: Vec<(&'static str, String)>) {
+    let node = env.iter().find(|(k, _)| *k == "PW_NODE_NAME").map(|(_, v)| v.clone()).unwrap_or_default();
+    info!("Hook for {}: running '{}'", node, command);
+    match Command::new("sh").arg("-c").arg(&command).envs(env).output().await {
+        Ok(output) if output.status.success() => debug!("Hook '{}' finished", command),
+        Ok(output) => warn!(
+            "Hook '{}' failed ({}): {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => warn!("Hook '{}' could not be started: {}", command, e),
+    }
+}
+
+/// Start running hooks for node events, `None` if no valid hook is configured
+pub fn start_script_hooks(hooks: Vec<ScriptHook>) -> Option<JoinHandle<()>> {
+    let hooks: Vec<CompiledHook> = hooks
+        .into_iter()
+        .filter_map(|hook| match CompiledHook::new(hook) {
+            Ok(hook) => Some(hook),
+            Err(e) => {
+                warn!("Ignoring hook: {}", e);
+                None
+            }
+        })
+        .collect();
+    if hooks.is_empty() {
+        return None;
+    }
+
+    let mut receiver = events::subscribe();
+    Some(tokio::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Hooks: {} event(s) dropped", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            for (command, node) in hooks.iter().filter_map(|h| h.command_for(&event)) {
+                tokio::spawn(run_command(command.to_string(), hook_env(event.name(), node)));
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, media_class: &str) -> NodeInfo {
+        NodeInfo {
+            id: 42,
+            name: name.to_string(),
+            media_class: Some(media_class.to_string()),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_hook_matching() {
+        let hook = CompiledHook::new(ScriptHook {
+            node: "^bluez_".to_string(),
+            media_class: Some("Audio/Source".to_string()),
+            on_added: Some("echo added".to_string()),
+            on_removed: None,
+        })
+        .unwrap();
+
+        let added = StateEvent::NodeAdded { node: node("bluez_input.phone", "Audio/Source") };
+        assert_eq!(hook.command_for(&added).map(|(c, _)| c), Some("echo added"));
+
+        let removed = StateEvent::NodeRemoved { node: node("bluez_input.phone", "Audio/Source") };
+        assert!(hook.command_for(&removed).is_none());
+
+        let sink = StateEvent::NodeAdded { node: node("bluez_output.phone", "Audio/Sink") };
+        assert!(hook.command_for(&sink).is_none());
+
+        assert!(CompiledHook::new(ScriptHook { node: "(".to_string(), media_class: None, on_added: None, on_removed: None }).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hook_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let command = format!("echo \"$PW_EVENT $PW_NODE_ID $PW_NODE_NAME\" > {}", out.display());
+        run_command(command, hook_env("node_added", &node("alsa_output.usb", "Audio/Sink"))).await;
+        assert_eq!(std::fs::read_to_string(out).unwrap().trim(), "node_added 42 alsa_output.usb");
+    }
+}
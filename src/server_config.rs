@@ -0,0 +1,484 @@
+//! Server configuration file (pipewire-api.toml)
+//!
+//! Settings are read from `/etc/pipewire-api/pipewire-api.toml` and overridden
+//! by `~/.config/pipewire-api/pipewire-api.toml`. Missing values use the
+//! built-in defaults. Command line arguments take precedence over both files.
+//!
+//! ```toml
+//! [server]
+//! bind = "127.0.0.1"
+//! port = 2716
+//!
+//! [modules]
+//! speakereq_pattern = "speakereq[0-9]+x[0-9]+"
+//!
+//! [features]
+//! auto_link = false
+//!
+//! [[hooks]]
+//! node = "^bluez_input\\."
+//! on_added = "logger bluetooth source $PW_NODE_NAME connected"
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::command::CommandTimeouts;
+
+/// File name of the server configuration
+pub const CONFIG_FILE_NAME: &str = "pipewire-api.toml";
+
+/// HTTP server settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ServerSection {
+    /// Address to bind to
+    pub bind: String,
+    /// Port to listen on
+    pub port: u16,
+    /// Number of worker threads running PipeWire commands
+    pub workers: usize,
+    /// Number of backups kept of each state file
+    pub state_backups: usize,
+    /// Storage of volumes, presets and history: "files" or "sqlite"
+    pub state_backend: String,
+    /// Source of PipeWire objects and parameters: "auto", "pw-dump" or "pw-cli"
+    pub pipewire_backend: String,
+    /// Save volumes and DSP settings when the server is stopped
+    pub save_on_shutdown: bool,
+}
+
+impl Default for ServerSection {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0".to_string(),
+            port: 2716,
+            workers: crate::worker::DEFAULT_WORKERS,
+            state_backups: crate::state_file::DEFAULT_BACKUPS,
+            state_backend: "files".to_string(),
+            pipewire_backend: "auto".to_string(),
+            save_on_shutdown: false,
+        }
+    }
+}
+
+/// Timer and timeout settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TimeoutsSection {
+    /// Interval of the settings auto-save task in seconds
+    pub settings_autosave_secs: u64,
+    /// Maximum time for object discovery (pw-cli ls, pw-link, pw-metadata) in milliseconds
+    pub discovery_ms: u64,
+    /// Maximum time for parameter reads (pw-cli enum-params) in milliseconds
+    pub param_read_ms: u64,
+    /// Lifetime of cached listing responses in milliseconds (0 disables caching)
+    pub listing_cache_ms: u64,
+    /// Interval for checking speakereq parameters for external changes in milliseconds (0 disables)
+    pub param_monitor_ms: u64,
+    /// Interval for detecting new Bluetooth source nodes in milliseconds
+    pub bluetooth_poll_ms: u64,
+    /// Interval for detecting a missing or returning primary sink of the failover rules in milliseconds
+    pub failover_poll_ms: u64,
+    /// Interval for detecting plugged or unplugged headphones of the jack rules in milliseconds
+    pub jack_poll_ms: u64,
+    /// Interval for detecting node, device and volume changes for webhooks and the event history in milliseconds
+    pub event_poll_ms: u64,
+    /// Time until a volume or link change is checked for a revert by the session manager in milliseconds (0 disables)
+    pub revert_check_ms: u64,
+}
+
+impl Default for TimeoutsSection {
+    fn default() -> Self {
+        let commands = CommandTimeouts::default();
+        Self {
+            settings_autosave_secs: 10,
+            discovery_ms: commands.discovery.as_millis() as u64,
+            param_read_ms: commands.param_read.as_millis() as u64,
+            listing_cache_ms: crate::response_cache::DEFAULT_TTL_MS,
+            param_monitor_ms: 2000,
+            bluetooth_poll_ms: 2000,
+            failover_poll_ms: 1000,
+            jack_poll_ms: 1000,
+            event_poll_ms: 2000,
+            revert_check_ms: crate::reverts::DEFAULT_CHECK_MS,
+        }
+    }
+}
+
+impl TimeoutsSection {
+    /// Timeouts for external commands
+    pub fn command_timeouts(&self) -> CommandTimeouts {
+        CommandTimeouts {
+            discovery: Duration::from_millis(self.discovery_ms),
+            param_read: Duration::from_millis(self.param_read_ms),
+        }
+    }
+}
+
+/// Node names/patterns of the filter modules
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ModulesSection {
+    /// Regex matching the SpeakerEQ node name
+    pub speakereq_pattern: String,
+    /// Name of the RIAA node
+    pub riaa_node: String,
+}
+
+impl Default for ModulesSection {
+    fn default() -> Self {
+        Self {
+            speakereq_pattern: r"speakereq[0-9]+x[0-9]+".to_string(),
+            riaa_node: "riaa".to_string(),
+        }
+    }
+}
+
+/// Feature toggles
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FeaturesSection {
+    /// Automatic link management from link rules
+    pub auto_link: bool,
+    /// Apply volume rules at startup
+    pub volume_rules: bool,
+    /// Apply parameter rules at startup
+    pub param_rules: bool,
+    /// Link Bluetooth sources to sinks from bluetooth-rules.conf
+    pub bluetooth_rules: bool,
+    /// Restore links saved with POST /api/v1/links/save at startup
+    pub restore_links: bool,
+    /// Keep a history of devices and nodes appearing and disappearing
+    pub event_history: bool,
+    /// Record stream activity for GET /api/v1/stats/streams
+    pub stream_stats: bool,
+    /// Move nodes to a backup sink from failover.conf when the primary disappears
+    pub failover: bool,
+    /// Switch between headphones and speakers from jack-rules.conf
+    pub jack_rules: bool,
+    /// Create links with the link factory of a pw-cli session instead of pw-link
+    pub native_links: bool,
+}
+
+impl Default for FeaturesSection {
+    fn default() -> Self {
+        Self {
+            auto_link: true,
+            volume_rules: true,
+            param_rules: true,
+            bluetooth_rules: true,
+            restore_links: true,
+            event_history: true,
+            stream_stats: true,
+            failover: true,
+            jack_rules: true,
+            native_links: true,
+        }
+    }
+}
+
+/// Limits for modifying requests (PUT, POST, DELETE) per client
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RateLimitSection {
+    /// Sustained requests per second (0 disables limiting)
+    pub requests_per_sec: f64,
+    /// Requests that can be sent at once
+    pub burst: u32,
+}
+
+impl Default for RateLimitSection {
+    fn default() -> Self {
+        Self {
+            requests_per_sec: crate::rate_limit::DEFAULT_REQUESTS_PER_SEC,
+            burst: crate::rate_limit::DEFAULT_BURST,
+        }
+    }
+}
+
+/// An API token and its role
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiToken {
+    /// Name of the client, for logs
+    pub name: String,
+    /// Not shown in GET /api/v1/config
+    #[serde(serialize_with = "redact")]
+    pub token: String,
+    pub role: String,
+}
+
+fn redact<S: serde::Serializer>(_: &str, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str("********")
+}
+
+/// Access control (see [`crate::auth`])
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AuthSection {
+    /// Role for requests without token; without one they are rejected
+    pub anonymous_role: Option<String>,
+    /// Custom roles: name -> route groups
+    pub roles: std::collections::BTreeMap<String, Vec<crate::auth::RouteGroup>>,
+    /// Accepted tokens; access control is off while there are none
+    pub tokens: Vec<ApiToken>,
+    /// Roles clients can request by PIN pairing (see [`crate::pairing`]), none disables pairing
+    pub pairing_roles: Vec<String>,
+    /// Command run with `PW_PAIRING_PIN` and `PW_PAIRING_CLIENT` set, e.g. to show the PIN
+    pub pairing_command: Option<String>,
+}
+
+/// Suspending of idle sinks (see [`crate::power`])
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PowerSection {
+    /// Regexes on the node name of the sinks to suspend when idle; none by default
+    pub nodes: Vec<String>,
+    /// Minutes without links before a sink is suspended
+    pub idle_minutes: u64,
+}
+
+impl Default for PowerSection {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            idle_minutes: 10,
+        }
+    }
+}
+
+/// Stream output to an external process (see [`crate::stream_output`])
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StreamOutputSection {
+    /// Command run with `sh -c` that reads raw audio on stdin; none disables the stream output
+    pub command: Option<String>,
+    /// node.name of the null sink players are linked to
+    pub sink_name: String,
+    /// Sample format written to the command, e.g. s16 or f32
+    pub format: String,
+    pub rate: u32,
+    pub channels: u32,
+    /// Start the stream output with the server
+    pub autostart: bool,
+}
+
+impl Default for StreamOutputSection {
+    fn default() -> Self {
+        Self {
+            command: None,
+            sink_name: "stream_output".to_string(),
+            format: "s16".to_string(),
+            rate: 48000,
+            channels: 2,
+            autostart: false,
+        }
+    }
+}
+
+/// Recording of nodes into files (see [`crate::recording`])
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RecordingSection {
+    /// Directory of the recorded files (default: `recordings` in the state directory)
+    pub directory: Option<String>,
+}
+
+/// Playback of sound files (see [`crate::playback`])
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PlaybackSection {
+    /// Directory of the sounds played by name (default: `sounds` in the state directory)
+    pub directory: Option<String>,
+}
+
+/// Notification sounds (see [`crate::notify`])
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct NotifySection {
+    /// Directory of the sounds (default: `notify` in the state directory)
+    pub directory: Option<String>,
+    /// Sink ID or node.name the sounds play on (default: the default sink)
+    pub sink: Option<String>,
+    /// Gain of the sounds in dB, at most 0
+    pub gain_db: f32,
+    /// dB the other playback streams are lowered by while a sound plays, 0 disables ducking
+    pub duck_db: f32,
+}
+
+impl Default for NotifySection {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            sink: None,
+            gain_db: -6.0,
+            duck_db: -15.0,
+        }
+    }
+}
+
+/// Collection of statistics
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StatsSection {
+    /// Interval for sampling xruns and quanta with pw-top in seconds, 0 disables it
+    pub sample_secs: u64,
+    /// Keep aggregated metrics on disk for GET /api/v1/stats/history
+    pub history: bool,
+    /// Time span aggregated into one value of the history in seconds
+    pub history_bucket_secs: u64,
+    /// Days the history is kept
+    pub history_days: u64,
+}
+
+impl Default for StatsSection {
+    fn default() -> Self {
+        Self {
+            sample_secs: 10,
+            history: false,
+            history_bucket_secs: 3600,
+            history_days: 30,
+        }
+    }
+}
+
+/// Effective server configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub server: ServerSection,
+    pub timeouts: TimeoutsSection,
+    pub modules: ModulesSection,
+    pub features: FeaturesSection,
+    pub rate_limit: RateLimitSection,
+    pub auth: AuthSection,
+    pub power: PowerSection,
+    pub stream_output: StreamOutputSection,
+    pub recording: RecordingSection,
+    pub playback: PlaybackSection,
+    pub notify: NotifySection,
+    pub stats: StatsSection,
+    /// Further PipeWire instances by name (PIPEWIRE_REMOTE socket name or path)
+    pub cores: std::collections::BTreeMap<String, String>,
+    /// Commands run when nodes appear or disappear
+    pub hooks: Vec<crate::script_hooks::ScriptHook>,
+    /// Volumes lowered while other streams play
+    pub ducking: Vec<crate::ducking::DuckingRule>,
+    /// Configuration files that were loaded, in order of application
+    #[serde(skip_deserializing)]
+    pub sources: Vec<String>,
+}
+
+/// Get the locations of the configuration file (system first, then user override)
+fn config_file_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from("/etc/pipewire-api").join(CONFIG_FILE_NAME)];
+    if let Some(mut user_path) = dirs::config_dir() {
+        user_path.push("pipewire-api");
+        user_path.push(CONFIG_FILE_NAME);
+        paths.push(user_path);
+    }
+    paths
+}
+
+/// Recursively merge `overlay` into `base`; tables are merged, other values replaced
+fn merge_values(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base_table), toml::Value::Table(overlay_table)) => {
+            for (key, value) in overlay_table {
+                match base_table.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base_table.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn read_toml(path: &Path) -> Result<toml::Value> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))
+}
+
+/// Load the configuration from the given files; later files override earlier ones
+pub fn load_from_files(paths: &[PathBuf]) -> Result<ServerConfig> {
+    let mut merged = toml::Value::Table(toml::map::Map::new());
+    let mut sources = Vec::new();
+
+    for path in paths {
+        if !path.exists() {
+            debug!("Server config file does not exist: {}", path.display());
+            continue;
+        }
+        merge_values(&mut merged, read_toml(path)?);
+        sources.push(path.display().to_string());
+        info!("Loaded server configuration from {}", path.display());
+    }
+
+    let mut config: ServerConfig = merged
+        .try_into()
+        .context("Invalid server configuration")?;
+    config.sources = sources;
+    Ok(config)
+}
+
+/// Load the configuration from the system and user locations
+pub fn load() -> Result<ServerConfig> {
+    load_from_files(&config_file_paths())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn write_config(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_defaults_without_files() {
+        let config = load_from_files(&[PathBuf::from("/nonexistent/pipewire-api.toml")]).unwrap();
+        assert_eq!(config, ServerConfig::default());
+        assert_eq!(config.server.port, 2716);
+        assert!(config.features.auto_link);
+    }
+
+    #[test]
+    fn test_user_overrides_system() {
+        let system = write_config("[server]\nbind = \"127.0.0.1\"\nport = 8080\n\n[features]\nauto_link = false\n");
+        let user = write_config("[server]\nport = 9090\n");
+        let config = load_from_files(&[system.path().to_path_buf(), user.path().to_path_buf()]).unwrap();
+        assert_eq!(config.server.bind, "127.0.0.1");
+        assert_eq!(config.server.port, 9090);
+        assert!(!config.features.auto_link);
+        assert!(config.features.volume_rules);
+        assert_eq!(config.sources.len(), 2);
+    }
+
+    #[test]
+    fn test_auth_tokens_are_redacted() {
+        let file = write_config("[auth]\nanonymous_role = \"viewer\"\n\n[[auth.tokens]]\nname = \"panel\"\ntoken = \"secret\"\nrole = \"volume-only\"\n");
+        let config = load_from_files(&[file.path().to_path_buf()]).unwrap();
+        assert_eq!(config.auth.tokens[0].token, "secret");
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(!json.contains("secret"));
+    }
+
+    #[test]
+    fn test_invalid_value_is_rejected() {
+        let file = write_config("[server]\nport = \"not a number\"\n");
+        assert!(load_from_files(&[file.path().to_path_buf()]).is_err());
+    }
+}
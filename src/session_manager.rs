@@ -0,0 +1,231 @@
+//! Inspection of the session manager (WirePlumber)
+//!
+//! The session manager decides the default nodes, links new streams and
+//! restores their volumes and targets. Its decisions often override changes
+//! made through the API, e.g. a link made with pw-link is replaced when the
+//! stream is re-linked. This module collects what it decided: the client of
+//! the session manager, the default nodes in the `default` metadata (chosen
+//! vs. configured), stream targets in the metadata and the stream state
+//! WirePlumber restores from its `restore-stream` state file.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+
+use crate::pwcli::PwObject;
+use crate::pwmetadata::MetadataEntry;
+use crate::status::default_node_name;
+
+/// Binaries of known session managers
+const SESSION_MANAGERS: [&str; 2] = ["wireplumber", "pipewire-media-session"];
+
+/// Default node kinds in the `default` metadata
+const DEFAULT_KINDS: [&str; 4] = ["audio.sink", "audio.source", "video.source", "video.sink"];
+
+/// Metadata keys selecting the target of a stream
+pub const TARGET_KEYS: [&str; 2] = ["target.object", "target.node"];
+
+/// A running session manager
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SessionManager {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// IDs of its clients (WirePlumber connects more than once)
+    pub client_ids: Vec<u32>,
+}
+
+/// Default node chosen by the session manager
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DefaultDecision {
+    /// e.g. "audio.sink"
+    pub kind: String,
+    /// Node in use (`default.<kind>`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+    /// Node chosen by the user (`default.configured.<kind>`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub configured: Option<String>,
+    /// The configured node is not the one in use, e.g. because it is missing
+    pub overridden: bool,
+}
+
+/// Target of a stream set in the `default` metadata
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StreamTarget {
+    pub id: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub key: String,
+    pub target: Value,
+}
+
+/// Stream state restored by WirePlumber
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RestoredStream {
+    /// e.g. "Output/Audio:application.name:Firefox"
+    pub key: String,
+    /// Volume, mute, channel volumes and target as stored
+    pub state: Value,
+}
+
+/// Find the session manager among the clients
+pub fn detect(clients: &[PwObject]) -> Option<SessionManager> {
+    let binary_of = |client: &PwObject| {
+        client
+            .get("application.process.binary")
+            .map(|b| b.rsplit('/').next().unwrap_or(b).to_string())
+    };
+    let is_manager = |client: &PwObject| {
+        binary_of(client).is_some_and(|b| SESSION_MANAGERS.contains(&b.as_str()))
+            || client.get("application.name").is_some_and(|n| n.eq_ignore_ascii_case("wireplumber"))
+    };
+    let managers: Vec<&PwObject> = clients.iter().filter(|c| is_manager(c)).collect();
+    let first = managers.first()?;
+    Some(SessionManager {
+        name: first
+            .get("application.name")
+            .map(|n| n.to_string())
+            .or_else(|| binary_of(first))
+            .unwrap_or_default(),
+        binary: binary_of(first),
+        pid: first.get("application.process.id").and_then(|p| p.parse().ok()),
+        client_ids: managers.iter().map(|c| c.id).collect(),
+    })
+}
+
+/// Default nodes in use and configured, for the kinds present in the metadata
+pub fn default_decisions(entries: &[MetadataEntry]) -> Vec<DefaultDecision> {
+    DEFAULT_KINDS
+        .iter()
+        .filter_map(|kind| {
+            let current = default_node_name(entries, &format!("default.{}", kind));
+            let configured = default_node_name(entries, &format!("default.configured.{}", kind));
+            if current.is_none() && configured.is_none() {
+                return None;
+            }
+            Some(DefaultDecision {
+                kind: kind.to_string(),
+                overridden: configured.is_some() && configured != current,
+                current,
+                configured,
+            })
+        })
+        .collect()
+}
+
+/// Stream targets in the `default` metadata
+pub fn stream_targets(entries: &[MetadataEntry], nodes: &[PwObject]) -> Vec<StreamTarget> {
+    entries
+        .iter()
+        .filter(|e| e.subject != 0 && TARGET_KEYS.contains(&e.key.as_str()))
+        .map(|e| StreamTarget {
+            id: e.subject,
+            name: nodes.iter().find(|n| n.id == e.subject).map(|n| n.display_name()),
+            key: e.key.clone(),
+            target: e.value.clone(),
+        })
+        .collect()
+}
+
+/// Location of WirePlumber's restore-stream state file
+pub fn restore_stream_path() -> Option<PathBuf> {
+    dirs::state_dir().map(|dir| dir.join("wireplumber").join("restore-stream"))
+}
+
+/// Parse the restore-stream state file (key file with JSON values)
+pub fn parse_restore_stream(content: &str) -> Vec<RestoredStream> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('[') && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| RestoredStream {
+            // Key files escape leading spaces and backslashes
+            key: key.trim().replace("\\s", " ").replace("\\\\", "\\"),
+            state: serde_json::from_str(value.trim()).unwrap_or_else(|_| Value::String(value.trim().to_string())),
+        })
+        .collect()
+}
+
+/// Explanations of the decisions for API users
+pub fn notes(manager: Option<&SessionManager>, defaults: &[DefaultDecision]) -> Vec<String> {
+    let Some(manager) = manager else {
+        return vec![
+            "No session manager is running: new streams are not linked and defaults are not restored".to_string(),
+        ];
+    };
+    let mut notes = vec![format!(
+        "{} links new streams to their target.object or the default node; links made with pw-link or \
+         /api/v1/links to a stream are replaced when it is re-linked. Set its target.object instead \
+         (PUT /api/v1/streams/:id/target).",
+        manager.name
+    )];
+    for default in defaults.iter().filter(|d| d.overridden) {
+        notes.push(format!(
+            "The configured default {} '{}' is not in use, {} chose '{}' (the configured node may be missing)",
+            default.kind,
+            default.configured.as_deref().unwrap_or_default(),
+            manager.name,
+            default.current.as_deref().unwrap_or("none"),
+        ));
+    }
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pwmetadata::parse_pw_metadata;
+
+    fn client(id: u32, props: &[(&str, &str)]) -> PwObject {
+        PwObject::new(id, "PipeWire:Interface:Client", props)
+    }
+
+    #[test]
+    fn test_detect_and_decisions() {
+        let clients = vec![
+            client(32, &[("application.name", "pw-cli")]),
+            client(45, &[("application.name", "WirePlumber"), ("application.process.binary", "wireplumber"), ("application.process.id", "812")]),
+            client(52, &[("application.name", "WirePlumber [export]"), ("application.process.binary", "/usr/bin/wireplumber")]),
+        ];
+        let manager = detect(&clients).unwrap();
+        assert_eq!((manager.name.as_str(), manager.pid), ("WirePlumber", Some(812)));
+        assert_eq!(manager.client_ids, vec![45, 52]);
+        assert_eq!(detect(&clients[..1]), None);
+
+        let entries = parse_pw_metadata(
+            "update: id:0 key:'default.configured.audio.sink' value:'{ \"name\": \"alsa_output.usb\" }' type:'Spa:String:JSON'\n\
+             update: id:0 key:'default.audio.sink' value:'{ \"name\": \"alsa_output.hdmi\" }' type:'Spa:String:JSON'\n\
+             update: id:0 key:'default.audio.source' value:'{ \"name\": \"alsa_input.usb\" }' type:'Spa:String:JSON'\n\
+             update: id:71 key:'target.object' value:'alsa_output.hdmi' type:''\n",
+        );
+        let defaults = default_decisions(&entries);
+        assert_eq!(defaults.len(), 2);
+        assert!(defaults[0].overridden);
+        assert_eq!(defaults[1].configured, None);
+        assert!(!defaults[1].overridden);
+
+        let targets = stream_targets(&entries, &[]);
+        assert_eq!((targets[0].id, targets[0].key.as_str()), (71, "target.object"));
+
+        let notes = notes(Some(&manager), &defaults);
+        assert_eq!(notes.len(), 2);
+        assert!(notes[1].contains("'alsa_output.usb' is not in use"));
+    }
+
+    #[test]
+    fn test_parse_restore_stream() {
+        let streams = parse_restore_stream(
+            "[restore-stream]\n\
+             Output/Audio:application.name:Firefox={\"volume\":0.56, \"mute\":false, \"target\":\"alsa_output.usb\"}\n\
+             Output/Audio:media.role:Notification=not json\n",
+        );
+        assert_eq!(streams.len(), 2);
+        assert_eq!(streams[0].key, "Output/Audio:application.name:Firefox");
+        assert_eq!(streams[0].state["target"], "alsa_output.usb");
+        assert_eq!(streams[1].state, Value::String("not json".to_string()));
+    }
+}
@@ -1,6 +1,6 @@
 use axum::{
-    extract::State,
-    routing::post,
+    extract::{Query, State},
+    routing::{get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
@@ -84,29 +84,51 @@ pub fn get_settings_path() -> Result<PathBuf, ApiError> {
     Ok(state_dir.join("settings.json"))
 }
 
+/// Query parameters for POST /api/v1/settings/save and /restore
+#[derive(Debug, Deserialize)]
+pub struct PresetQuery {
+    /// Named settings profile, e.g. "vinyl" (default: the auto-saved settings)
+    pub preset: Option<String>,
+}
+
+impl PresetQuery {
+    fn preset(&self) -> Result<&str, ApiError> {
+        let preset = self.preset.as_deref().unwrap_or(crate::store::SETTINGS_PRESET);
+        crate::store::validate_preset_name(preset).map_err(ApiError::BadRequest)?;
+        Ok(preset)
+    }
+}
+
 /// Save current settings to disk
 pub async fn save_settings(
     State(state): State<SettingsState>,
+    Query(query): Query<PresetQuery>,
 ) -> Result<Json<SaveResponse>, ApiError> {
-    let path = get_settings_path()?;
+    let preset = query.preset()?;
+    let store = crate::store::get();
+    let location = store.preset_location(preset);
     
     // Get current settings as JSON
     let json = get_current_settings_json(&state).await?;
     
-    // Write to file
-    fs::write(&path, &json)
+    // Write to the state store
+    store
+        .save_preset(preset, &json)
         .map_err(|e| ApiError::Internal(format!("Failed to write settings file: {}", e)))?;
+    let _ = store.record_event(&crate::store::AuditEvent::new("settings.save", preset, ""));
     
     // Log to console for systemd journal
-    info!("Settings saved to {}", path.display());
+    info!("Settings saved to {}", location);
     
-    // Update last_saved state
-    let mut last_saved = state.auto_save.last_saved.write().await;
-    *last_saved = Some(json);
+    // Update last_saved state (named presets are not auto-saved)
+    if preset == crate::store::SETTINGS_PRESET {
+        let mut last_saved = state.auto_save.last_saved.write().await;
+        *last_saved = Some(json);
+    }
     
     Ok(Json(SaveResponse {
         success: true,
-        path: path.to_string_lossy().to_string(),
+        path: location,
         message: "Settings saved successfully".to_string(),
     }))
 }
@@ -114,148 +136,292 @@ pub async fn save_settings(
 /// Restore settings from disk by applying saved parameters
 pub async fn restore_settings(
     State(state): State<SettingsState>,
+    Query(query): Query<PresetQuery>,
+    Query(smooth): Query<crate::smoothing::SmoothQuery>,
 ) -> Result<Json<RestoreResponse>, ApiError> {
-    let path = get_settings_path()?;
+    let preset = query.preset()?;
+    let smooth_ms = smooth.smooth_ms()?;
+    let settings = load_preset(preset)?;
     
-    if !path.exists() {
-        return Err(ApiError::NotFound("No saved settings found".to_string()));
-    }
+    let modules_restored = if smooth_ms == 0 {
+        apply_settings(&state.speakereq, &state.riaa, settings)?
+    } else {
+        apply_settings_smoothed(&state.speakereq, &state.riaa, settings, smooth_ms).await?
+    };
     
-    // Read settings file
-    let json = fs::read_to_string(&path)
-        .map_err(|e| ApiError::Internal(format!("Failed to read settings file: {}", e)))?;
+    let _ = crate::store::get().record_event(&crate::store::AuditEvent::new(
+        "settings.restore",
+        preset,
+        modules_restored.join(","),
+    ));
     
-    // Deserialize
-    let settings: Settings = serde_json::from_str(&json)
-        .map_err(|e| ApiError::Internal(format!("Failed to deserialize settings: {}", e)))?;
+    Ok(Json(RestoreResponse {
+        success: true,
+        message: format!("Restored {} modules", modules_restored.len()),
+        modules_restored,
+    }))
+}
+
+/// Load saved settings from the state store
+pub fn load_preset(preset: &str) -> Result<Settings, ApiError> {
+    let json = crate::store::get()
+        .load_preset(preset)
+        .map_err(|e| ApiError::Internal(format!("Failed to read settings file: {}", e)))?
+        .ok_or_else(|| {
+            if preset == crate::store::SETTINGS_PRESET {
+                ApiError::NotFound("No saved settings found".to_string())
+            } else {
+                ApiError::NotFound(format!("Settings preset '{}' not found", preset))
+            }
+        })?;
     
-    let mut modules_restored = Vec::new();
+    serde_json::from_str(&json)
+        .map_err(|e| ApiError::Internal(format!("Failed to deserialize settings: {}", e)))
+}
+
+/// Parameters of the SpeakerEQ module for saved settings
+pub fn speakereq_params(prefix: &str, settings: &crate::speakereq::StatusResponse) -> Result<HashMap<String, ParameterValue>, ApiError> {
+    let mut restore_params = HashMap::new();
     
-    // Restore speakereq settings if present
-    if let Some(speakereq_settings) = settings.speakereq {
-        // Get prefix from cached params
-        let params = state.speakereq.get_params()?;
-        let prefix = crate::speakereq::get_plugin_prefix(&params);
-        
-        let mut restore_params = HashMap::new();
-        
-        // Restore enable and master gain
-        restore_params.insert(
-            format!("{}:Enable", prefix),
-            ParameterValue::Bool(speakereq_settings.enabled)
-        );
-        restore_params.insert(
-            format!("{}:master_gain_db", prefix),
-            ParameterValue::Float(speakereq_settings.master_gain_db)
-        );
-        
-        // Restore crossbar matrix
-        restore_params.insert(
-            format!("{}:xbar_0_to_0", prefix),
-            ParameterValue::Float(speakereq_settings.crossbar.input_0_to_output_0)
-        );
-        restore_params.insert(
-            format!("{}:xbar_0_to_1", prefix),
-            ParameterValue::Float(speakereq_settings.crossbar.input_0_to_output_1)
-        );
-        restore_params.insert(
-            format!("{}:xbar_1_to_0", prefix),
-            ParameterValue::Float(speakereq_settings.crossbar.input_1_to_output_0)
-        );
-        restore_params.insert(
-            format!("{}:xbar_1_to_1", prefix),
-            ParameterValue::Float(speakereq_settings.crossbar.input_1_to_output_1)
-        );
-        
-        // Restore input blocks
-        for input in &speakereq_settings.inputs {
-            let gain_key = format!("{}:{}_gain_db", prefix, input.id);
-            restore_params.insert(gain_key, ParameterValue::Float(input.gain_db));
+    // Restore enable and master gain
+    restore_params.insert(
+        format!("{}:Enable", prefix),
+        ParameterValue::Bool(settings.enabled)
+    );
+    restore_params.insert(
+        format!("{}:master_gain_db", prefix),
+        ParameterValue::Float(settings.master_gain_db)
+    );
+    
+    // Restore crossbar matrix
+    restore_params.insert(
+        format!("{}:xbar_0_to_0", prefix),
+        ParameterValue::Float(settings.crossbar.input_0_to_output_0)
+    );
+    restore_params.insert(
+        format!("{}:xbar_0_to_1", prefix),
+        ParameterValue::Float(settings.crossbar.input_0_to_output_1)
+    );
+    restore_params.insert(
+        format!("{}:xbar_1_to_0", prefix),
+        ParameterValue::Float(settings.crossbar.input_1_to_output_0)
+    );
+    restore_params.insert(
+        format!("{}:xbar_1_to_1", prefix),
+        ParameterValue::Float(settings.crossbar.input_1_to_output_1)
+    );
+    
+    // Restore input blocks
+    for input in &settings.inputs {
+        let gain_key = format!("{}:{}_gain_db", prefix, input.id);
+        restore_params.insert(gain_key, ParameterValue::Float(input.gain_db));
+        
+        // Restore EQ bands
+        for band in &input.eq_bands {
+            let type_id = crate::speakereq::eq_type_from_string(&band.eq_type)?;
+            let type_key = format!("{}:{}_eq_{}_type", prefix, input.id, band.band);
+            let freq_key = format!("{}:{}_eq_{}_f", prefix, input.id, band.band);
+            let q_key = format!("{}:{}_eq_{}_q", prefix, input.id, band.band);
+            let gain_key = format!("{}:{}_eq_{}_gain", prefix, input.id, band.band);
+            let enabled_key = format!("{}:{}_eq_{}_enabled", prefix, input.id, band.band);
             
-            // Restore EQ bands
-            for band in &input.eq_bands {
-                let type_id = crate::speakereq::eq_type_from_string(&band.eq_type)?;
-                let type_key = format!("{}:{}_eq_{}_type", prefix, input.id, band.band);
-                let freq_key = format!("{}:{}_eq_{}_f", prefix, input.id, band.band);
-                let q_key = format!("{}:{}_eq_{}_q", prefix, input.id, band.band);
-                let gain_key = format!("{}:{}_eq_{}_gain", prefix, input.id, band.band);
-                let enabled_key = format!("{}:{}_eq_{}_enabled", prefix, input.id, band.band);
-                
-                restore_params.insert(type_key, ParameterValue::Int(type_id));
-                restore_params.insert(freq_key, ParameterValue::Float(band.frequency));
-                restore_params.insert(q_key, ParameterValue::Float(band.q));
-                restore_params.insert(gain_key, ParameterValue::Float(band.gain));
-                restore_params.insert(enabled_key, ParameterValue::Bool(band.enabled));
-            }
+            restore_params.insert(type_key, ParameterValue::Int(type_id));
+            restore_params.insert(freq_key, ParameterValue::Float(band.frequency));
+            restore_params.insert(q_key, ParameterValue::Float(band.q));
+            restore_params.insert(gain_key, ParameterValue::Float(band.gain));
+            restore_params.insert(enabled_key, ParameterValue::Bool(band.enabled));
+        }
+    }
+    
+    // Restore output blocks
+    for output in &settings.outputs {
+        let gain_key = format!("{}:{}_gain_db", prefix, output.id);
+        restore_params.insert(gain_key, ParameterValue::Float(output.gain_db));
+        
+        if let Some(delay_ms) = output.delay_ms {
+            let delay_key = format!("{}:{}_delay_ms", prefix, output.id);
+            restore_params.insert(delay_key, ParameterValue::Float(delay_ms));
         }
         
-        // Restore output blocks
-        for output in &speakereq_settings.outputs {
-            let gain_key = format!("{}:{}_gain_db", prefix, output.id);
-            restore_params.insert(gain_key, ParameterValue::Float(output.gain_db));
-            
-            if let Some(delay_ms) = output.delay_ms {
-                let delay_key = format!("{}:{}_delay_ms", prefix, output.id);
-                restore_params.insert(delay_key, ParameterValue::Float(delay_ms));
-            }
+        // Restore EQ bands
+        for band in &output.eq_bands {
+            let type_id = crate::speakereq::eq_type_from_string(&band.eq_type)?;
+            let type_key = format!("{}:{}_eq_{}_type", prefix, output.id, band.band);
+            let freq_key = format!("{}:{}_eq_{}_f", prefix, output.id, band.band);
+            let q_key = format!("{}:{}_eq_{}_q", prefix, output.id, band.band);
+            let gain_key = format!("{}:{}_eq_{}_gain", prefix, output.id, band.band);
+            let enabled_key = format!("{}:{}_eq_{}_enabled", prefix, output.id, band.band);
             
-            // Restore EQ bands
-            for band in &output.eq_bands {
-                let type_id = crate::speakereq::eq_type_from_string(&band.eq_type)?;
-                let type_key = format!("{}:{}_eq_{}_type", prefix, output.id, band.band);
-                let freq_key = format!("{}:{}_eq_{}_f", prefix, output.id, band.band);
-                let q_key = format!("{}:{}_eq_{}_q", prefix, output.id, band.band);
-                let gain_key = format!("{}:{}_eq_{}_gain", prefix, output.id, band.band);
-                let enabled_key = format!("{}:{}_eq_{}_enabled", prefix, output.id, band.band);
-                
-                restore_params.insert(type_key, ParameterValue::Int(type_id));
-                restore_params.insert(freq_key, ParameterValue::Float(band.frequency));
-                restore_params.insert(q_key, ParameterValue::Float(band.q));
-                restore_params.insert(gain_key, ParameterValue::Float(band.gain));
-                restore_params.insert(enabled_key, ParameterValue::Bool(band.enabled));
-            }
+            restore_params.insert(type_key, ParameterValue::Int(type_id));
+            restore_params.insert(freq_key, ParameterValue::Float(band.frequency));
+            restore_params.insert(q_key, ParameterValue::Float(band.q));
+            restore_params.insert(gain_key, ParameterValue::Float(band.gain));
+            restore_params.insert(enabled_key, ParameterValue::Bool(band.enabled));
         }
+    }
+
+    Ok(restore_params)
+}
+
+/// Parameters of the RIAA module for saved settings
+pub fn riaa_params(config: &crate::riaa::RiaaConfig) -> HashMap<String, ParameterValue> {
+    let mut riaa_params = HashMap::new();
+    
+    riaa_params.insert("riaa:Gain (dB)".to_string(), ParameterValue::Float(config.gain_db));
+    riaa_params.insert("riaa:Subsonic Filter".to_string(), ParameterValue::Int(config.subsonic_filter));
+    riaa_params.insert("riaa:RIAA Enable".to_string(), ParameterValue::Bool(config.riaa_enable));
+    riaa_params.insert("riaa:Declick Enable".to_string(), ParameterValue::Bool(config.declick_enable));
+    riaa_params.insert("riaa:Spike Threshold (dB)".to_string(), ParameterValue::Float(config.spike_threshold_db));
+    riaa_params.insert("riaa:Spike Width (ms)".to_string(), ParameterValue::Float(config.spike_width_ms));
+    riaa_params.insert("riaa:Notch Filter Enable".to_string(), ParameterValue::Bool(config.notch_filter_enable));
+    riaa_params.insert("riaa:Notch Frequency (Hz)".to_string(), ParameterValue::Float(config.notch_frequency_hz));
+    riaa_params.insert("riaa:Notch Q Factor".to_string(), ParameterValue::Float(config.notch_q_factor));
+
+    riaa_params
+}
+
+/// Apply settings to the DSP modules, returns the names of the restored modules
+pub fn apply_settings(speakereq: &NodeState, riaa: &NodeState, settings: Settings) -> Result<Vec<String>, ApiError> {
+    let mut modules_restored = Vec::new();
+    
+    // Restore speakereq settings if present
+    if let Some(speakereq_settings) = settings.speakereq {
+        // Get prefix from cached params
+        let params = speakereq.get_params()?;
+        let prefix = crate::speakereq::get_plugin_prefix(&params);
+        let restore_params = speakereq_params(&prefix, &speakereq_settings)?;
         
         // Apply all speakereq parameters in one batch
         if !restore_params.is_empty() {
-            state.speakereq.set_parameters(restore_params)?;
+            speakereq.set_parameters(restore_params)?;
             modules_restored.push("speakereq".to_string());
         }
     }
     
     // Restore RIAA settings if present
     if let Some(riaa_config) = settings.riaa {
-        let mut riaa_params = HashMap::new();
-        
-        riaa_params.insert("riaa:Gain (dB)".to_string(), ParameterValue::Float(riaa_config.gain_db));
-        riaa_params.insert("riaa:Subsonic Filter".to_string(), ParameterValue::Int(riaa_config.subsonic_filter));
-        riaa_params.insert("riaa:RIAA Enable".to_string(), ParameterValue::Bool(riaa_config.riaa_enable));
-        riaa_params.insert("riaa:Declick Enable".to_string(), ParameterValue::Bool(riaa_config.declick_enable));
-        riaa_params.insert("riaa:Spike Threshold (dB)".to_string(), ParameterValue::Float(riaa_config.spike_threshold_db));
-        riaa_params.insert("riaa:Spike Width (ms)".to_string(), ParameterValue::Float(riaa_config.spike_width_ms));
-        riaa_params.insert("riaa:Notch Filter Enable".to_string(), ParameterValue::Bool(riaa_config.notch_filter_enable));
-        riaa_params.insert("riaa:Notch Frequency (Hz)".to_string(), ParameterValue::Float(riaa_config.notch_frequency_hz));
-        riaa_params.insert("riaa:Notch Q Factor".to_string(), ParameterValue::Float(riaa_config.notch_q_factor));
-        
+        let riaa_params = riaa_params(&riaa_config);
         if !riaa_params.is_empty() {
-            state.riaa.set_parameters(riaa_params)?;
+            riaa.set_parameters(riaa_params)?;
             modules_restored.push("riaa".to_string());
         }
     }
     
-    Ok(Json(RestoreResponse {
+    Ok(modules_restored)
+}
+
+/// Apply settings to the DSP modules, ramping the gains over `smooth_ms`
+///
+/// See [`crate::smoothing`]. Returns the names of the restored modules.
+pub async fn apply_settings_smoothed(
+    speakereq: &Arc<NodeState>,
+    riaa: &Arc<NodeState>,
+    settings: Settings,
+    smooth_ms: u64,
+) -> Result<Vec<String>, ApiError> {
+    let mut modules_restored = Vec::new();
+    if let Some(speakereq_settings) = settings.speakereq {
+        let params = speakereq.get_params_async().await?;
+        let prefix = crate::speakereq::get_plugin_prefix(&params);
+        crate::smoothing::set_parameters(speakereq, speakereq_params(&prefix, &speakereq_settings)?, smooth_ms).await?;
+        modules_restored.push("speakereq".to_string());
+    }
+    if let Some(riaa_config) = settings.riaa {
+        crate::smoothing::set_parameters(riaa, riaa_params(&riaa_config), smooth_ms).await?;
+        modules_restored.push("riaa".to_string());
+    }
+    Ok(modules_restored)
+}
+
+/// Response for GET /api/v1/settings/backups
+#[derive(Debug, Serialize)]
+pub struct BackupsResponse {
+    /// Number of backups kept per file
+    pub keep: usize,
+    pub backups: Vec<crate::state_file::BackupInfo>,
+}
+
+/// Request body for POST /api/v1/settings/backups/restore
+#[derive(Debug, Deserialize)]
+pub struct RestoreBackupRequest {
+    /// State file name, e.g. "settings.json"
+    pub file: String,
+    /// Backup index, 1 is the newest
+    pub index: usize,
+}
+
+fn state_file_path(file: &str) -> Result<PathBuf, ApiError> {
+    if !crate::state_file::STATE_FILES.contains(&file) {
+        return Err(ApiError::BadRequest(format!(
+            "Unknown state file '{}', use one of {}",
+            file,
+            crate::state_file::STATE_FILES.join(", ")
+        )));
+    }
+    let dir = crate::config::state_dir()
+        .ok_or_else(|| ApiError::Internal("Could not determine state directory".to_string()))?;
+    Ok(dir.join(file))
+}
+
+/// List backups of the state files
+/// GET /api/v1/settings/backups
+pub async fn list_backups() -> Result<Json<BackupsResponse>, ApiError> {
+    let mut backups = Vec::new();
+    for file in crate::state_file::STATE_FILES {
+        backups.extend(crate::state_file::list_backups(&state_file_path(file)?));
+    }
+
+    Ok(Json(BackupsResponse {
+        keep: crate::state_file::backup_count(),
+        backups,
+    }))
+}
+
+/// Replace a state file with one of its backups
+/// POST /api/v1/settings/backups/restore
+pub async fn restore_backup(Json(request): Json<RestoreBackupRequest>) -> Result<Json<SaveResponse>, ApiError> {
+    let path = state_file_path(&request.file)?;
+    crate::state_file::restore_backup(&path, request.index).map_err(|e| {
+        if e.contains("not found") {
+            ApiError::NotFound(e)
+        } else {
+            ApiError::Internal(e)
+        }
+    })?;
+    info!("Restored {} from backup {}", path.display(), request.index);
+
+    Ok(Json(SaveResponse {
         success: true,
-        message: format!("Restored {} modules", modules_restored.len()),
-        modules_restored,
+        path: path.to_string_lossy().to_string(),
+        message: format!("Restored {} from backup {}", request.file, request.index),
     }))
 }
 
 /// Get current settings as JSON string (for comparison)
 async fn get_current_settings_json(state: &SettingsState) -> Result<String, ApiError> {
+    let settings = current_settings(&state.speakereq, &state.riaa).await;
+    
+    serde_json::to_string_pretty(&settings)
+        .map_err(|e| ApiError::Internal(format!("Failed to serialize settings: {}", e)))
+}
+
+/// Save the current settings of the DSP modules as the default preset and
+/// return where they were stored
+pub async fn save_current_settings(speakereq: &Arc<NodeState>, riaa: &Arc<NodeState>) -> Result<String, String> {
+    let json = serde_json::to_string_pretty(&current_settings(speakereq, riaa).await)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let store = crate::store::get();
+    store.save_preset(crate::store::SETTINGS_PRESET, &json)?;
+    let _ = store.record_event(&crate::store::AuditEvent::new("settings.save", crate::store::SETTINGS_PRESET, ""));
+    Ok(store.preset_location(crate::store::SETTINGS_PRESET))
+}
+
+/// Get the current settings of the DSP modules (modules that are not loaded are left out)
+pub async fn current_settings(speakereq: &Arc<NodeState>, riaa: &Arc<NodeState>) -> Settings {
     // Get cached parameters from each module state
-    let speakereq_status = match state.speakereq.get_params() {
+    let speakereq_status = match speakereq.get_params() {
         Ok(_params) => {
-            match crate::speakereq::get_status(State(state.speakereq.clone())).await {
+            match crate::speakereq::get_status(State(speakereq.clone())).await {
                 Ok(Json(status)) => Some(status),
                 Err(_) => None,
             }
@@ -263,9 +429,9 @@ async fn get_current_settings_json(state: &SettingsState) -> Result<String, ApiE
         Err(_) => None,
     };
     
-    let riaa_config = match state.riaa.get_params() {
+    let riaa_config = match riaa.get_params() {
         Ok(_params) => {
-            match crate::riaa::get_config(State(state.riaa.clone())).await {
+            match crate::riaa::get_config(State(riaa.clone())).await {
                 Ok(Json(config)) => Some(config),
                 Err(_) => None,
             }
@@ -273,14 +439,11 @@ async fn get_current_settings_json(state: &SettingsState) -> Result<String, ApiE
         Err(_) => None,
     };
     
-    let settings = Settings {
+    Settings {
         version: env!("CARGO_PKG_VERSION").to_string(),
         speakereq: speakereq_status,
         riaa: riaa_config,
-    };
-    
-    serde_json::to_string_pretty(&settings)
-        .map_err(|e| ApiError::Internal(format!("Failed to serialize settings: {}", e)))
+    }
 }
 
 /// Background task that auto-saves settings when they change
@@ -308,18 +471,12 @@ pub async fn auto_save_task(state: SettingsState) {
         
         if has_changed {
             // Save settings
-            match get_settings_path() {
-                Ok(path) => {
-                    if let Err(e) = fs::write(&path, &current_json) {
-                        eprintln!("Auto-save: Failed to write settings: {}", e);
-                    } else {
-                        *last_saved = Some(current_json);
-                        info!("Auto-save: Settings saved to {}", path.display());
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Auto-save: Failed to get settings path: {:?}", e);
-                }
+            let store = crate::store::get();
+            if let Err(e) = store.save_preset(crate::store::SETTINGS_PRESET, &current_json) {
+                eprintln!("Auto-save: Failed to write settings: {}", e);
+            } else {
+                *last_saved = Some(current_json);
+                info!("Auto-save: Settings saved to {}", store.preset_location(crate::store::SETTINGS_PRESET));
             }
         }
     }
@@ -332,12 +489,10 @@ pub fn create_router(
     auto_save_interval_secs: Option<u64>,
 ) -> Router {
     // Initialize auto-save state with existing file content if available
-    let auto_save = match get_settings_path() {
-        Ok(path) if path.exists() => {
-            Arc::new(AutoSaveState::new_with_file(auto_save_interval_secs.unwrap_or(10), &path))
-        }
-        _ => Arc::new(AutoSaveState::new(auto_save_interval_secs.unwrap_or(10))),
-    };
+    let auto_save = Arc::new(AutoSaveState {
+        last_saved: RwLock::new(crate::store::get().load_preset(crate::store::SETTINGS_PRESET).ok().flatten()),
+        interval_secs: auto_save_interval_secs.unwrap_or(10),
+    });
     
     let settings_state = SettingsState {
         speakereq: speakereq_state,
@@ -354,6 +509,8 @@ pub fn create_router(
     Router::new()
         .route("/api/v1/settings/save", post(save_settings))
         .route("/api/v1/settings/restore", post(restore_settings))
+        .route("/api/v1/settings/backups", get(list_backups))
+        .route("/api/v1/settings/backups/restore", post(restore_backup))
         .with_state(settings_state)
 }
 
@@ -447,7 +604,7 @@ mod tests {
         assert!(deserialized.speakereq.is_some());
         
         let speakereq = deserialized.speakereq.unwrap();
-        assert_eq!(speakereq.enabled, true);
+        assert!(speakereq.enabled);
         assert_eq!(speakereq.master_gain_db, 0.0);
         assert_eq!(speakereq.inputs.len(), 1);
         assert_eq!(speakereq.inputs[0].id, "input_0");
@@ -569,9 +726,9 @@ mod tests {
         let riaa = deserialized.riaa.unwrap();
         assert_eq!(riaa.gain_db, 6.0);
         assert_eq!(riaa.subsonic_filter, 1);
-        assert_eq!(riaa.riaa_enable, true);
-        assert_eq!(riaa.declick_enable, true);
-        assert_eq!(riaa.notch_filter_enable, true);
+        assert!(riaa.riaa_enable);
+        assert!(riaa.declick_enable);
+        assert!(riaa.notch_filter_enable);
         assert_eq!(riaa.notch_frequency_hz, 60.0);
     }
 
@@ -0,0 +1,97 @@
+//! Graceful shutdown
+//!
+//! On SIGTERM or SIGINT the HTTP server stops accepting connections and
+//! finishes the requests in flight. Streaming responses (SSE) never finish
+//! on their own, so they are dropped after [`GRACE_PERIOD`]. Afterwards volumes and DSP settings are
+//! optionally saved (`save_on_shutdown`), the metrics history is written and
+//! the state store is flushed.
+//! PipeWire is only accessed through command line tools, so there are no
+//! connections to close.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+use crate::api_server::NodeState;
+
+/// Time open connections get to finish after a shutdown signal
+pub const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Serve the API until SIGTERM or SIGINT is received
+pub async fn serve(listener: tokio::net::TcpListener, app: axum::Router) -> std::io::Result<()> {
+    let stopping = Arc::new(Notify::new());
+    let trigger = stopping.clone();
+    // The peer address identifies clients for rate limiting
+    let app = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        signal().await;
+        trigger.notify_one();
+    });
+
+    tokio::select! {
+        result = server => result,
+        _ = async {
+            stopping.notified().await;
+            tokio::time::sleep(GRACE_PERIOD).await;
+        } => {
+            warn!("Connections still open after {:?}, closing them", GRACE_PERIOD);
+            Ok(())
+        }
+    }
+}
+
+/// Wait for SIGTERM or SIGINT (Ctrl-C)
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received SIGINT, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+    }
+}
+
+/// Save state after the server has stopped
+pub async fn save_state(save: bool, speakereq: &Arc<NodeState>, riaa: &Arc<NodeState>) {
+    if save {
+        match crate::worker::run(crate::volume::save_all_volumes).await {
+            Ok(Ok(count)) => info!("Saved {} volume(s) on shutdown", count),
+            Ok(Err(e)) => error!("Failed to save volumes on shutdown: {}", e),
+            Err(e) => error!("Failed to save volumes on shutdown: {}", e),
+        }
+        match crate::settings::save_current_settings(speakereq, riaa).await {
+            Ok(location) => info!("Saved settings to {} on shutdown", location),
+            Err(e) => error!("Failed to save settings on shutdown: {}", e),
+        }
+    }
+
+    match crate::worker::run(crate::metrics_history::save).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => error!("Failed to save metrics history on shutdown: {}", e),
+        Err(e) => error!("Failed to save metrics history on shutdown: {}", e),
+    }
+
+    if let Err(e) = crate::store::get().flush() {
+        error!("Failed to flush state store: {}", e);
+    }
+}
@@ -0,0 +1,247 @@
+//! Smooth transitions of gain parameters
+//!
+//! Applying a settings preset or clearing an EQ bank changes many gains at
+//! once, which can be audible as a click. With `?smooth_ms=` the gain-type
+//! parameters are ramped from their current value to the new one by a
+//! background task that writes intermediate values every [`STEP_MS`]. Other
+//! parameters (EQ types, frequencies, enable switches) are written at once.
+//!
+//! A new transition of a node stops the one still running for it. The whole
+//! change is recorded as one undo step (see [`crate::undo`]).
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::api_server::{ApiError, NodeState};
+use crate::parameters::ParameterValue;
+use crate::transactions::Change;
+
+/// Interval between intermediate values
+pub const STEP_MS: u64 = 20;
+
+/// Longest transition accepted
+pub const MAX_SMOOTH_MS: u64 = 10_000;
+
+/// Running transition per node, a newer one stops the older one
+static GENERATIONS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+/// Query parameter of the endpoints that support smooth transitions
+#[derive(Debug, Default, Deserialize)]
+pub struct SmoothQuery {
+    /// Ramp gains over this many milliseconds (0 or missing: jump)
+    pub smooth_ms: Option<u64>,
+}
+
+impl SmoothQuery {
+    /// Get the validated transition time
+    pub fn smooth_ms(&self) -> Result<u64, ApiError> {
+        let smooth_ms = self.smooth_ms.unwrap_or(0);
+        if smooth_ms > MAX_SMOOTH_MS {
+            return Err(ApiError::BadRequest(format!("smooth_ms must be at most {}", MAX_SMOOTH_MS)));
+        }
+        Ok(smooth_ms)
+    }
+}
+
+/// A change split into the parameters written at once and the ramped gains
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Transition {
+    /// Written before the ramp starts
+    pub immediate: HashMap<String, ParameterValue>,
+    /// Current values of the ramped gains
+    pub from: HashMap<String, f32>,
+    /// Final values of the ramped gains
+    pub to: HashMap<String, f32>,
+    /// Written after the ramp ended
+    pub finally: HashMap<String, ParameterValue>,
+}
+
+/// Check whether a parameter is a gain in dB (e.g. `master_gain_db`, `out_1_eq_3_gain`)
+pub fn is_gain_param(key: &str) -> bool {
+    let name = key.rsplit(':').next().unwrap_or(key);
+    name.ends_with("gain_db") || name.ends_with("_gain") || name.starts_with("Gain (dB)")
+}
+
+/// Split new parameter values into a transition from the current values
+///
+/// Gains without a known current value are written at once.
+pub fn plan(current: &HashMap<String, ParameterValue>, params: HashMap<String, ParameterValue>) -> Transition {
+    let mut transition = Transition::default();
+    for (key, value) in params {
+        let from = current.get(&key).and_then(ParameterValue::as_float);
+        match (is_gain_param(&key), from, value.as_float()) {
+            (true, Some(from), Some(to)) if from != to => {
+                transition.from.insert(key.clone(), from);
+                transition.to.insert(key, to);
+            }
+            _ => {
+                transition.immediate.insert(key, value);
+            }
+        }
+    }
+    transition
+}
+
+/// Transition that fades gains to 0 dB, writes `params` and restores the gains
+///
+/// Used when switching EQ bands off: the band is silent before its type
+/// changes and keeps its gain for when it is switched on again.
+pub fn plan_fade(
+    current: &HashMap<String, ParameterValue>,
+    gain_keys: &[String],
+    params: HashMap<String, ParameterValue>,
+) -> Transition {
+    let mut transition = Transition::default();
+    for key in gain_keys {
+        if let Some(gain) = current.get(key).and_then(ParameterValue::as_float).filter(|g| *g != 0.0) {
+            transition.from.insert(key.clone(), gain);
+            transition.to.insert(key.clone(), 0.0);
+            transition.finally.insert(key.clone(), ParameterValue::Float(gain));
+        }
+    }
+    if transition.to.is_empty() {
+        transition.immediate = params;
+    } else {
+        transition.finally.extend(params);
+    }
+    transition
+}
+
+/// Intermediate values of a ramp, the last step has the final values
+pub fn ramp_steps(from: &HashMap<String, f32>, to: &HashMap<String, f32>, smooth_ms: u64) -> Vec<HashMap<String, ParameterValue>> {
+    let steps = (smooth_ms / STEP_MS).max(1);
+    (1..=steps)
+        .map(|step| {
+            let position = step as f32 / steps as f32;
+            to.iter()
+                .map(|(key, target)| {
+                    let start = from.get(key).copied().unwrap_or(*target);
+                    let value = if step == steps { *target } else { start + (target - start) * position };
+                    (key.clone(), ParameterValue::Float(value))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn next_generation(node: &str) -> u64 {
+    let mut generations = GENERATIONS.lock().unwrap();
+    let generation = generations.get(node).map(|g| g + 1).unwrap_or(1);
+    generations.insert(node.to_string(), generation);
+    generation
+}
+
+fn is_current(node: &str, generation: u64) -> bool {
+    GENERATIONS.lock().unwrap().get(node) == Some(&generation)
+}
+
+async fn write(state: &Arc<NodeState>, params: HashMap<String, ParameterValue>) -> Result<(), ApiError> {
+    let state = Arc::clone(state);
+    crate::worker::run(move || state.set_parameters_untracked(params))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))?
+}
+
+/// Start a transition: write the immediate parameters and ramp the gains in the background
+///
+/// `forward` and `inverse` are the parameters recorded for undo.
+pub async fn start(
+    state: &Arc<NodeState>,
+    transition: Transition,
+    smooth_ms: u64,
+    forward: HashMap<String, ParameterValue>,
+    inverse: HashMap<String, ParameterValue>,
+) -> Result<(), ApiError> {
+    let node = state.node_name.clone();
+    let generation = next_generation(&node);
+    if !transition.immediate.is_empty() {
+        write(state, transition.immediate).await?;
+    }
+    crate::undo::record(
+        format!("Set {} parameter(s) of {}", forward.len(), node),
+        vec![Change::Params { node: node.clone(), params: forward }],
+        vec![Change::Params { node: node.clone(), params: inverse }],
+    );
+
+    let steps = ramp_steps(&transition.from, &transition.to, smooth_ms);
+    let finally = transition.finally;
+    let state = Arc::clone(state);
+    debug!("[{}] Ramping {} gain(s) over {} ms", node, transition.to.len(), smooth_ms);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_millis(STEP_MS));
+        for step in steps.into_iter().filter(|s| !s.is_empty()) {
+            ticker.tick().await;
+            if !is_current(&node, generation) {
+                debug!("[{}] Transition replaced by a newer one", node);
+                return;
+            }
+            if let Err(e) = write(&state, step).await {
+                warn!("[{}] Failed to ramp gains: {:?}", node, e);
+                return;
+            }
+        }
+        if !finally.is_empty() && is_current(&node, generation) {
+            if let Err(e) = write(&state, finally).await {
+                warn!("[{}] Failed to finish transition: {:?}", node, e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Set parameters, ramping the gains over `smooth_ms` (0: set at once)
+pub async fn set_parameters(
+    state: &Arc<NodeState>,
+    params: HashMap<String, ParameterValue>,
+    smooth_ms: u64,
+) -> Result<(), ApiError> {
+    if smooth_ms == 0 {
+        return state.set_parameters_async(params).await;
+    }
+    let current = state.get_params_async().await?;
+    let inverse: HashMap<String, ParameterValue> = params
+        .keys()
+        .filter_map(|k| Some((k.clone(), current.get(k)?.clone())))
+        .collect();
+    let transition = plan(&current, params.clone());
+    start(state, transition, smooth_ms, params, inverse).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_gain_param() {
+        assert!(is_gain_param("speakereq2x2:master_gain_db"));
+        assert!(is_gain_param("speakereq2x2:out_1_eq_3_gain"));
+        assert!(is_gain_param("riaa:Gain (dB)"));
+        assert!(!is_gain_param("speakereq2x2:out_1_eq_3_f"));
+        assert!(!is_gain_param("speakereq2x2:out_1_eq_3_type"));
+    }
+
+    #[test]
+    fn test_plan_and_ramp() {
+        let current = HashMap::from([
+            ("eq:master_gain_db".to_string(), ParameterValue::Float(0.0)),
+            ("eq:out_1_eq_1_type".to_string(), ParameterValue::Int(0)),
+        ]);
+        let params = HashMap::from([
+            ("eq:master_gain_db".to_string(), ParameterValue::Float(-10.0)),
+            ("eq:out_1_eq_1_type".to_string(), ParameterValue::Int(3)),
+            ("eq:out_1_eq_1_gain".to_string(), ParameterValue::Float(6.0)),
+        ]);
+        let transition = plan(&current, params);
+        assert_eq!(transition.to, HashMap::from([("eq:master_gain_db".to_string(), -10.0)]));
+        assert_eq!(transition.immediate.len(), 2);
+
+        let steps = ramp_steps(&transition.from, &transition.to, 100);
+        assert_eq!(steps.len(), 5);
+        assert_eq!(steps[0]["eq:master_gain_db"], ParameterValue::Float(-2.0));
+        assert_eq!(steps[4]["eq:master_gain_db"], ParameterValue::Float(-10.0));
+        assert_eq!(ramp_steps(&transition.from, &transition.to, 5).len(), 1);
+    }
+}
@@ -0,0 +1,202 @@
+//! Named SPA property dictionary
+//!
+//! PipeWire identifies the fields of parameter objects (Props, Route, Format, ...)
+//! by numeric keys. Depending on the version and available type information,
+//! pw-cli prints either the full type name (`Spa:Pod:Object:Param:Props:volume`)
+//! or only the number (`65539`). This module maps between both forms so that
+//! API responses always use readable names.
+
+/// Keys of `Spa:Pod:Object:Param:Props` (spa/param/props.h)
+const PROPS_KEYS: &[(u32, &str)] = &[
+    (1, "unknown"),
+    (0x101, "device"),
+    (0x102, "deviceName"),
+    (0x103, "deviceFd"),
+    (0x104, "card"),
+    (0x105, "cardName"),
+    (0x106, "minLatency"),
+    (0x107, "maxLatency"),
+    (0x108, "periods"),
+    (0x109, "periodSize"),
+    (0x10a, "periodEvent"),
+    (0x10b, "live"),
+    (0x10c, "rate"),
+    (0x10d, "quality"),
+    (0x10e, "bluetoothAudioCodec"),
+    (0x10f, "bluetoothOffloadActive"),
+    (65537, "waveType"),
+    (65538, "frequency"),
+    (65539, "volume"),
+    (65540, "mute"),
+    (65541, "patternType"),
+    (65542, "ditherType"),
+    (65543, "truncate"),
+    (65544, "channelVolumes"),
+    (65545, "volumeBase"),
+    (65546, "volumeStep"),
+    (65547, "channelMap"),
+    (65548, "monitorMute"),
+    (65549, "monitorVolumes"),
+    (65550, "latencyOffsetNsec"),
+    (65551, "softMute"),
+    (65552, "softVolumes"),
+    (65553, "iec958Codecs"),
+    (65554, "volumeRampSamples"),
+    (65555, "volumeRampStepSamples"),
+    (65556, "volumeRampTime"),
+    (65557, "volumeRampStepTime"),
+    (65558, "volumeRampScale"),
+    (131073, "brightness"),
+    (131074, "contrast"),
+    (131075, "saturation"),
+    (131076, "hue"),
+    (131077, "gamma"),
+    (131078, "exposure"),
+    (131079, "gain"),
+    (131080, "sharpness"),
+    (524289, "params"),
+];
+
+/// Keys of `Spa:Pod:Object:Param:PropInfo` (spa/param/props.h)
+const PROP_INFO_KEYS: &[(u32, &str)] = &[
+    (1, "id"),
+    (2, "name"),
+    (3, "type"),
+    (4, "labels"),
+    (5, "container"),
+    (6, "params"),
+    (7, "description"),
+];
+
+/// Keys of `Spa:Pod:Object:Param:Route` (spa/param/route.h)
+const ROUTE_KEYS: &[(u32, &str)] = &[
+    (1, "index"),
+    (2, "direction"),
+    (3, "device"),
+    (4, "name"),
+    (5, "description"),
+    (6, "priority"),
+    (7, "available"),
+    (8, "info"),
+    (9, "profiles"),
+    (10, "props"),
+    (11, "devices"),
+    (12, "profile"),
+    (13, "save"),
+];
+
+/// Keys of `Spa:Pod:Object:Param:Profile` (spa/param/profile.h)
+const PROFILE_KEYS: &[(u32, &str)] = &[
+    (1, "index"),
+    (2, "name"),
+    (3, "description"),
+    (4, "priority"),
+    (5, "available"),
+    (6, "info"),
+    (7, "classes"),
+    (8, "save"),
+];
+
+/// Keys of `Spa:Pod:Object:Param:Format` for audio (spa/param/format.h)
+const FORMAT_KEYS: &[(u32, &str)] = &[
+    (1, "mediaType"),
+    (2, "mediaSubtype"),
+    (65537, "format"),
+    (65538, "flags"),
+    (65539, "rate"),
+    (65540, "channels"),
+    (65541, "position"),
+    (65542, "iec958Codec"),
+    (65543, "bitorder"),
+    (65544, "interleave"),
+    (65545, "bitrate"),
+    (65546, "blockAlign"),
+];
+
+/// Parameter object types that have a key dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Props,
+    PropInfo,
+    Route,
+    Profile,
+    Format,
+}
+
+impl ParamKind {
+    /// Determine the kind from a pod object type name like
+    /// `Spa:Pod:Object:Param:Props` or a short name like `Route`
+    pub fn from_type_name(type_name: &str) -> Option<Self> {
+        let short = type_name.rsplit(':').next().unwrap_or(type_name);
+        match short {
+            "Props" => Some(ParamKind::Props),
+            "PropInfo" => Some(ParamKind::PropInfo),
+            "Route" | "EnumRoute" => Some(ParamKind::Route),
+            "Profile" | "EnumProfile" => Some(ParamKind::Profile),
+            "Format" | "EnumFormat" => Some(ParamKind::Format),
+            _ => None,
+        }
+    }
+
+    fn table(self) -> &'static [(u32, &'static str)] {
+        match self {
+            ParamKind::Props => PROPS_KEYS,
+            ParamKind::PropInfo => PROP_INFO_KEYS,
+            ParamKind::Route => ROUTE_KEYS,
+            ParamKind::Profile => PROFILE_KEYS,
+            ParamKind::Format => FORMAT_KEYS,
+        }
+    }
+}
+
+/// Look up the name of a numeric key
+pub fn key_to_name(kind: ParamKind, key: u32) -> Option<&'static str> {
+    kind.table().iter().find(|(k, _)| *k == key).map(|(_, n)| *n)
+}
+
+/// Look up the numeric key for a name
+pub fn name_to_key(kind: ParamKind, name: &str) -> Option<u32> {
+    kind.table().iter().find(|(_, n)| *n == name).map(|(k, _)| *k)
+}
+
+/// Get a display name for a key, falling back to `prop_<key>` for unknown keys
+pub fn display_name(kind: Option<ParamKind>, key: u32) -> String {
+    kind.and_then(|k| key_to_name(k, key))
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| format!("prop_{}", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_props_lookup_both_directions() {
+        assert_eq!(key_to_name(ParamKind::Props, 65539), Some("volume"));
+        assert_eq!(key_to_name(ParamKind::Props, 65540), Some("mute"));
+        assert_eq!(key_to_name(ParamKind::Props, 65544), Some("channelVolumes"));
+        assert_eq!(name_to_key(ParamKind::Props, "channelVolumes"), Some(65544));
+        assert_eq!(name_to_key(ParamKind::Props, "params"), Some(524289));
+    }
+
+    #[test]
+    fn test_route_lookup() {
+        assert_eq!(key_to_name(ParamKind::Route, 10), Some("props"));
+        assert_eq!(name_to_key(ParamKind::Route, "available"), Some(7));
+    }
+
+    #[test]
+    fn test_kind_from_type_name() {
+        assert_eq!(ParamKind::from_type_name("Spa:Pod:Object:Param:Props"), Some(ParamKind::Props));
+        assert_eq!(ParamKind::from_type_name("Spa:Pod:Object:Param:Route"), Some(ParamKind::Route));
+        assert_eq!(ParamKind::from_type_name("EnumFormat"), Some(ParamKind::Format));
+        assert_eq!(ParamKind::from_type_name("Spa:Pod:Object:Param:Latency"), None);
+    }
+
+    #[test]
+    fn test_display_name_fallback() {
+        assert_eq!(display_name(Some(ParamKind::Props), 65539), "volume");
+        assert_eq!(display_name(Some(ParamKind::Props), 999999), "prop_999999");
+        assert_eq!(display_name(None, 3), "prop_3");
+    }
+}
@@ -1,13 +1,16 @@
 use axum::{
-    extract::{Path, State},
-    routing::{get, post, put},
+    extract::{Path, Query, State},
+    middleware,
+    routing::{any, get, post, put},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::collections::HashMap;
 use crate::api_server::{ApiError, NodeState};
+use crate::modules::{dispatch_instance, list_instances, require_module, Module, ModuleInstances};
 use crate::parameters::ParameterValue;
+use crate::smoothing::{self, SmoothQuery};
 
 // EQ type constants
 const EQ_TYPE_OFF: i32 = 0;
@@ -37,7 +40,7 @@ pub fn get_plugin_prefix(params: &HashMap<String, ParameterValue>) -> String {
 }
 
 /// Helper to create a prefixed parameter key
-fn pkey(prefix: &str, param: &str) -> String {
+pub fn pkey(prefix: &str, param: &str) -> String {
     format!("{}:{}", prefix, param)
 }
 
@@ -81,7 +84,7 @@ pub struct IoResponse {
     pub outputs: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EqBand {
     #[serde(rename = "type")]
     pub eq_type: String,
@@ -212,7 +215,7 @@ pub fn eq_type_from_string(type_str: &str) -> Result<i32, ApiError> {
 
 // Handlers
 pub async fn get_structure(State(state): State<Arc<NodeState>>) -> Result<Json<StructureResponse>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     let prefix = get_plugin_prefix(&params);
     
     let enabled = params.get(&pkey(&prefix, "Enable"))
@@ -261,8 +264,8 @@ pub async fn get_config(State(state): State<Arc<NodeState>>) -> Result<Json<serd
     tracing::debug!("speakereq::get_config: starting");
     
     // Force refresh to ensure we have all parameters
-    state.refresh_params_cache()?;
-    let params = state.get_params()?;
+    state.refresh_params_cache_async().await?;
+    let params = state.get_params_async().await?;
     
     tracing::debug!("speakereq::get_config: got {} params", params.len());
     
@@ -326,50 +329,26 @@ pub async fn get_config(State(state): State<Arc<NodeState>>) -> Result<Json<serd
     })))
 }
 
-pub async fn get_eq_band(
-    State(state): State<Arc<NodeState>>,
-    Path((block, band)): Path<(String, u32)>,
-) -> Result<Json<EqBand>, ApiError> {
-    let params = state.get_params()?;
-    let prefix = get_plugin_prefix(&params);
-    
-    let type_key = pkey(&prefix, &format!("{}_eq_{}_type", block, band));
-    let freq_key = pkey(&prefix, &format!("{}_eq_{}_f", block, band));
-    let q_key = pkey(&prefix, &format!("{}_eq_{}_q", block, band));
-    let gain_key = pkey(&prefix, &format!("{}_eq_{}_gain", block, band));
-    let enabled_key = pkey(&prefix, &format!("{}_eq_{}_enabled", block, band));
-    
-    let eq_type = params.get(&type_key)
+/// Read an EQ band from the node parameters
+pub fn read_eq_band(params: &HashMap<String, ParameterValue>, block: &str, band: u32) -> Option<EqBand> {
+    let prefix = get_plugin_prefix(params);
+    let float = |name: &str, default: f32| {
+        params.get(&pkey(&prefix, &format!("{}_eq_{}_{}", block, band, name)))
+            .and_then(|v| match v {
+                ParameterValue::Float(f) => Some(*f),
+                ParameterValue::Int(i) => Some(*i as f32),
+                _ => None,
+            })
+            .unwrap_or(default)
+    };
+
+    let eq_type = params.get(&pkey(&prefix, &format!("{}_eq_{}_type", block, band)))
         .and_then(|v| match v {
             ParameterValue::Int(i) => Some(*i),
             _ => None,
-        })
-        .ok_or_else(|| ApiError::NotFound(format!("EQ band {}/{} not found", block, band)))?;
-    
-    let frequency = params.get(&freq_key)
-        .and_then(|v| match v {
-            ParameterValue::Float(f) => Some(*f),
-            _ => None,
-        })
-        .unwrap_or(1000.0);
-    
-    let q = params.get(&q_key)
-        .and_then(|v| match v {
-            ParameterValue::Float(f) => Some(*f),
-            ParameterValue::Int(i) => Some(*i as f32),
-            _ => None,
-        })
-        .unwrap_or(1.0);
-    
-    let gain = params.get(&gain_key)
-        .and_then(|v| match v {
-            ParameterValue::Float(f) => Some(*f),
-            ParameterValue::Int(i) => Some(*i as f32),
-            _ => None,
-        })
-        .unwrap_or(0.0);
-    
-    let enabled = params.get(&enabled_key)
+        })?;
+
+    let enabled = params.get(&pkey(&prefix, &format!("{}_eq_{}_enabled", block, band)))
         .and_then(|v| match v {
             ParameterValue::Bool(b) => Some(*b),
             ParameterValue::Float(f) => Some(*f > 0.5),
@@ -377,21 +356,18 @@ pub async fn get_eq_band(
             _ => None,
         })
         .unwrap_or(true);
-    
-    Ok(Json(EqBand {
+
+    Some(EqBand {
         eq_type: eq_type_to_string(eq_type),
-        frequency,
-        q,
-        gain,
+        frequency: float("f", 1000.0),
+        q: float("q", 1.0),
+        gain: float("gain", 0.0),
         enabled: Some(enabled),
-    }))
+    })
 }
 
-pub async fn set_eq_band(
-    State(state): State<Arc<NodeState>>,
-    Path((block, band)): Path<(String, u32)>,
-    Json(eq_band): Json<EqBand>,
-) -> Result<Json<EqBand>, ApiError> {
+/// Validate an EQ band and build the parameters to set it
+pub fn eq_band_params(prefix: &str, block: &str, band: u32, eq_band: &EqBand) -> Result<HashMap<String, ParameterValue>, ApiError> {
     // Validate input ranges
     if eq_band.frequency < 20.0 || eq_band.frequency > 20000.0 {
         return Err(ApiError::BadRequest("Frequency must be between 20 and 20000 Hz".to_string()));
@@ -402,43 +378,55 @@ pub async fn set_eq_band(
     if eq_band.gain < -24.0 || eq_band.gain > 24.0 {
         return Err(ApiError::BadRequest("Gain must be between -24 and +24 dB".to_string()));
     }
-    
+
     let type_id = eq_type_from_string(&eq_band.eq_type)?;
-    
-    // Get prefix from existing params
-    let existing_params = state.get_params()?;
-    let prefix = get_plugin_prefix(&existing_params);
-    
-    // Build parameter keys with dynamic prefix
-    let type_key = pkey(&prefix, &format!("{}_eq_{}_type", block, band));
-    let freq_key = pkey(&prefix, &format!("{}_eq_{}_f", block, band));
-    let q_key = pkey(&prefix, &format!("{}_eq_{}_q", block, band));
-    let gain_key = pkey(&prefix, &format!("{}_eq_{}_gain", block, band));
-    let enabled_key = pkey(&prefix, &format!("{}_eq_{}_enabled", block, band));
-    
-    // Batch all parameters into a single pw-cli call
-    let mut params = std::collections::HashMap::new();
-    params.insert(type_key, crate::parameters::ParameterValue::Int(type_id));
-    params.insert(freq_key, crate::parameters::ParameterValue::Float(eq_band.frequency));
-    params.insert(q_key, crate::parameters::ParameterValue::Float(eq_band.q));
-    params.insert(gain_key, crate::parameters::ParameterValue::Float(eq_band.gain));
-    
+
     // Set enabled parameter, default to true if not provided
     let enabled = eq_band.enabled.unwrap_or(true);
-    params.insert(enabled_key, crate::parameters::ParameterValue::Bool(enabled));
-    
-    // Set all parameters at once
-    state.set_parameters(params)?;
-    
+
+    let mut params = HashMap::new();
+    params.insert(pkey(prefix, &format!("{}_eq_{}_type", block, band)), ParameterValue::Int(type_id));
+    params.insert(pkey(prefix, &format!("{}_eq_{}_f", block, band)), ParameterValue::Float(eq_band.frequency));
+    params.insert(pkey(prefix, &format!("{}_eq_{}_q", block, band)), ParameterValue::Float(eq_band.q));
+    params.insert(pkey(prefix, &format!("{}_eq_{}_gain", block, band)), ParameterValue::Float(eq_band.gain));
+    params.insert(pkey(prefix, &format!("{}_eq_{}_enabled", block, band)), ParameterValue::Bool(enabled));
+    Ok(params)
+}
+
+pub async fn get_eq_band(
+    State(state): State<Arc<NodeState>>,
+    Path((block, band)): Path<(String, u32)>,
+) -> Result<Json<EqBand>, ApiError> {
+    let params = state.get_params_async().await?;
+    read_eq_band(&params, &block, band)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("EQ band {}/{} not found", block, band)))
+}
+
+pub async fn set_eq_band(
+    State(state): State<Arc<NodeState>>,
+    Path((block, band)): Path<(String, u32)>,
+    Json(eq_band): Json<EqBand>,
+) -> Result<Json<EqBand>, ApiError> {
+    // Get prefix from existing params
+    let existing_params = state.get_params_async().await?;
+    let prefix = get_plugin_prefix(&existing_params);
+
+    // Set all parameters in a single pw-cli call
+    let params = eq_band_params(&prefix, &block, band, &eq_band)?;
+    state.set_parameters_async(params).await?;
+
     Ok(Json(eq_band))
 }
 
 pub async fn clear_eq_bank(
     State(state): State<Arc<NodeState>>,
     Path(block): Path<String>,
+    Query(query): Query<SmoothQuery>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
+    let smooth_ms = query.smooth_ms()?;
     // Get prefix from existing params
-    let existing_params = state.get_params()?;
+    let existing_params = state.get_params_async().await?;
     let prefix = get_plugin_prefix(&existing_params);
     
     // Dynamically count EQ slots for this block
@@ -454,7 +442,20 @@ pub async fn clear_eq_bank(
         params.insert(type_key, crate::parameters::ParameterValue::Int(0));
     }
     
-    state.set_parameters(params)?;
+    if smooth_ms == 0 {
+        state.set_parameters_async(params).await?;
+    } else {
+        // Fade the band gains out before switching the bands off
+        let gain_keys: Vec<String> = (1..=slots)
+            .map(|band| pkey(&prefix, &format!("{}_eq_{}_gain", block, band)))
+            .collect();
+        let inverse = params
+            .keys()
+            .filter_map(|k| Some((k.clone(), existing_params.get(k)?.clone())))
+            .collect();
+        let transition = smoothing::plan_fade(&existing_params, &gain_keys, params.clone());
+        smoothing::start(&state, transition, smooth_ms, params, inverse).await?;
+    }
     
     Ok(Json(serde_json::json!({
         "block": block,
@@ -464,7 +465,7 @@ pub async fn clear_eq_bank(
 }
 
 pub async fn get_master_gain(State(state): State<Arc<NodeState>>) -> Result<Json<GainValue>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     let prefix = get_plugin_prefix(&params);
     
     let gain = params.get(&pkey(&prefix, "master_gain_db"))
@@ -486,13 +487,13 @@ pub async fn set_master_gain(
         return Err(ApiError::BadRequest("Master gain must be between -60 and +12 dB".to_string()));
     }
     
-    state.set_parameter("master_gain_db", ParameterValue::Float(gain_value.gain))?;
+    state.set_parameter_async("master_gain_db", ParameterValue::Float(gain_value.gain)).await?;
     
     Ok(Json(gain_value))
 }
 
 pub async fn get_enable(State(state): State<Arc<NodeState>>) -> Result<Json<EnableValue>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     let prefix = get_plugin_prefix(&params);
     
     let enabled = params.get(&pkey(&prefix, "Enable"))
@@ -509,7 +510,7 @@ pub async fn set_enable(
     State(state): State<Arc<NodeState>>,
     Json(enable_value): Json<EnableValue>,
 ) -> Result<Json<EnableValue>, ApiError> {
-    state.set_parameter("Enable", ParameterValue::Bool(enable_value.enabled))?;
+    state.set_parameter_async("Enable", ParameterValue::Bool(enable_value.enabled)).await?;
     
     Ok(Json(enable_value))
 }
@@ -519,18 +520,23 @@ pub async fn set_eq_band_enabled(
     Path((block, band)): Path<(String, u32)>,
     Json(enable_value): Json<EnableValue>,
 ) -> Result<Json<EnableValue>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     let prefix = get_plugin_prefix(&params);
     
     let enabled_key = pkey(&prefix, &format!("{}_eq_{}_enabled", block, band));
-    state.set_parameter(&enabled_key, ParameterValue::Bool(enable_value.enabled))?;
+    state.set_parameter_async(&enabled_key, ParameterValue::Bool(enable_value.enabled)).await?;
     
     Ok(Json(enable_value))
 }
 
 pub async fn get_status(State(state): State<Arc<NodeState>>) -> Result<Json<StatusResponse>, ApiError> {
-    let params = state.get_params()?;
-    let prefix = get_plugin_prefix(&params);
+    let params = state.get_params_async().await?;
+    Ok(Json(status_from_params(&params)?))
+}
+
+/// Build the status of all blocks from the plugin parameters
+pub fn status_from_params(params: &HashMap<String, ParameterValue>) -> Result<StatusResponse, ApiError> {
+    let prefix = get_plugin_prefix(params);
     
     // Get enable status
     let enabled = params.get(&pkey(&prefix, "Enable"))
@@ -601,7 +607,7 @@ pub async fn get_status(State(state): State<Arc<NodeState>>) -> Result<Json<Stat
         
         // Get delay (only for output blocks)
         let delay_ms = if has_delay {
-            let delay_key = pkey(prefix, &format!("delay_{}_ms", block_id.split('_').last().unwrap_or("0")));
+            let delay_key = pkey(prefix, &format!("delay_{}_ms", block_id.split('_').next_back().unwrap_or("0")));
             params.get(&delay_key)
                 .and_then(|v| match v {
                     ParameterValue::Float(f) => Some(*f),
@@ -688,20 +694,20 @@ pub async fn get_status(State(state): State<Arc<NodeState>>) -> Result<Json<Stat
         get_block_status("output_1", "output", true, &prefix)?,
     ];
     
-    Ok(Json(StatusResponse {
+    Ok(StatusResponse {
         enabled,
         master_gain_db,
         crossbar,
         inputs,
         outputs,
-    }))
+    })
 }
 
 /// Get crossbar matrix in 2D array format
 pub async fn get_crossbar(
     State(state): State<Arc<NodeState>>,
 ) -> Result<Json<CrossbarMatrixResponse>, ApiError> {
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     let prefix = get_plugin_prefix(&params);
     
     // Read all crossbar values
@@ -768,7 +774,7 @@ pub async fn set_crossbar_value(
     
     // Set the parameter
     let param_name = format!("xbar_{}_to_{}", input, output);
-    state.set_parameter(&param_name, ParameterValue::Float(request.value))?;
+    state.set_parameter_async(&param_name, ParameterValue::Float(request.value)).await?;
     
     Ok(Json(CrossbarValueResponse {
         success: true,
@@ -799,7 +805,7 @@ pub async fn set_crossbar_matrix(
         
         // Validate value ranges
         for (j, &value) in row.iter().enumerate() {
-            if value < 0.0 || value > 2.0 {
+            if !(0.0..=2.0).contains(&value) {
                 return Err(ApiError::BadRequest(
                     format!("Crossbar value at [{},{}] = {} is out of range (0.0-2.0)", i, j, value)
                 ));
@@ -814,7 +820,7 @@ pub async fn set_crossbar_matrix(
     params.insert("xbar_1_to_0".to_string(), ParameterValue::Float(request.matrix[1][0]));
     params.insert("xbar_1_to_1".to_string(), ParameterValue::Float(request.matrix[1][1]));
     
-    state.set_parameters(params)?;
+    state.set_parameters_async(params).await?;
     
     Ok(Json(SetCrossbarMatrixResponse {
         success: true,
@@ -830,6 +836,14 @@ pub fn create_router(state: Arc<NodeState>) -> Router {
         .route("/api/v1/module/speakereq/io", get(get_io))
         .route("/api/v1/module/speakereq/status", get(get_status))
         .route("/api/v1/module/speakereq/capabilities", get(get_capabilities))
+        .route(
+            "/api/v1/module/speakereq/headroom",
+            get(crate::speakereq_headroom::get_headroom).put(crate::speakereq_headroom::apply_headroom),
+        )
+        .route(
+            "/api/v1/module/speakereq/license",
+            get(crate::speakereq_license::get_license).put(crate::speakereq_license::set_license_key),
+        )
         .route("/api/v1/module/speakereq/eq/:block/:band", get(get_eq_band).put(set_eq_band))
         .route("/api/v1/module/speakereq/eq/:block/:band/enabled", put(set_eq_band_enabled))
         .route("/api/v1/module/speakereq/eq/:block/clear", put(clear_eq_bank))
@@ -838,16 +852,31 @@ pub fn create_router(state: Arc<NodeState>) -> Router {
         .route("/api/v1/module/speakereq/crossbar", get(get_crossbar).put(set_crossbar_matrix))
         .route("/api/v1/module/speakereq/crossbar/:input/:output", put(set_crossbar_value))
         .route("/api/v1/module/speakereq/refresh", post(refresh_cache))
+        .route("/api/v1/module/speakereq/events", get(crate::param_monitor::events))
         .route("/api/v1/module/speakereq/default", post(set_default))
         .route("/api/v1/module/speakereq/save", post(save_config))
+        .route_layer(middleware::from_fn_with_state(state.clone(), crate::revision::middleware))
+        .route_layer(middleware::from_fn_with_state(
+            Arc::new(Module::new("speakereq", state.clone())),
+            require_module,
+        ))
         .with_state(state)
 }
 
+/// Create router for per-instance access when several speakereq nodes are loaded,
+/// e.g. /api/v1/module/speakereq/speakereq2x2_sub/status
+pub fn create_instances_router(pattern: &str) -> Router {
+    Router::new()
+        .route("/api/v1/module/speakereq/instances", get(list_instances))
+        .route("/api/v1/module/speakereq/:instance/*path", any(dispatch_instance))
+        .with_state(Arc::new(ModuleInstances::new("speakereq", pattern, create_router)))
+}
+
 /// Refresh parameter cache (use if external tools modified parameters)
 pub async fn refresh_cache(
     State(state): State<Arc<NodeState>>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    state.refresh_params_cache()?;
+    state.refresh_params_cache_async().await?;
     Ok(Json(serde_json::json!({
         "message": "Parameter cache refreshed"
     })))
@@ -914,7 +943,7 @@ pub async fn set_default(
     params.insert("Enable".to_string(), ParameterValue::Bool(true));
     
     // Apply all parameters in batch
-    state.set_parameters(params)?;
+    state.set_parameters_async(params).await?;
     
     Ok(Json(serde_json::json!({
         "status": "ok",
@@ -931,14 +960,14 @@ pub async fn save_config(
     use std::collections::HashMap;
     
     // Get the plugin prefix first
-    let params = state.get_params()?;
+    let params = state.get_params_async().await?;
     let prefix = get_plugin_prefix(&params);
     
     // Set "save_settings" to 1 to trigger the plugin to save
     let mut set_params = HashMap::new();
     set_params.insert(pkey(&prefix, "save_settings"), ParameterValue::Int(1));
     
-    state.set_parameters(set_params)?;
+    state.set_parameters_async(set_params).await?;
     
     Ok(Json(serde_json::json!({
         "status": "ok",
@@ -0,0 +1,267 @@
+//! Headroom analysis of the SpeakerEQ
+//!
+//! Boosting EQ bands, block gains or the crossbar can push a full-scale
+//! signal above 0 dBFS, which clips in the DAC. The analysis sums the
+//! worst-case gain along every path to an output: input block gain and EQ
+//! boost, crossbar (added as amplitudes), output block gain and EQ boost and
+//! the master gain. EQ boosts are summed as if all bands overlapped, so the
+//! result is an upper bound.
+//!
+//! Outputs above 0 dB are reported with a suggested master gain trim that
+//! is applied with a PUT to the same path.
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api_server::{ApiError, NodeState};
+use crate::parameters::ParameterValue;
+use crate::speakereq::{self, BlockStatus, EqBandStatus, StatusResponse};
+
+/// Lowest master gain the trim may set
+const MIN_MASTER_GAIN_DB: f32 = -60.0;
+
+/// Worst-case gain of an output
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct OutputHeadroom {
+    pub id: String,
+    /// Highest gain from any input to this output, None if no input reaches it
+    pub peak_gain_db: Option<f32>,
+    /// Sum of the boosts of the enabled EQ bands of the output block
+    pub eq_boost_db: f32,
+    /// A full-scale signal can clip
+    pub clips: bool,
+}
+
+/// Response for GET /api/v1/module/speakereq/headroom
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct HeadroomResponse {
+    pub enabled: bool,
+    pub master_gain_db: f32,
+    pub outputs: Vec<OutputHeadroom>,
+    /// Change of the master gain that avoids clipping (0 if nothing clips)
+    pub suggested_trim_db: f32,
+    pub warnings: Vec<String>,
+    /// The trim was applied to the master gain
+    pub applied: bool,
+}
+
+/// Query parameters for GET /api/v1/module/speakereq/headroom
+#[derive(Debug, Default, Deserialize)]
+pub struct HeadroomQuery {
+    /// Former way of applying the trim, rejected: applying is a PUT
+    #[serde(default)]
+    pub apply: bool,
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+fn linear_to_db(linear: f32) -> f32 {
+    20.0 * linear.log10()
+}
+
+/// Peak of a resonant low/high pass filter, 0 dB up to Q = 0.707
+fn resonance_db(q: f32) -> f32 {
+    if q <= std::f32::consts::FRAC_1_SQRT_2 {
+        return 0.0;
+    }
+    linear_to_db(q / (1.0 - 1.0 / (4.0 * q * q)).sqrt())
+}
+
+/// Highest gain of a band in dB, 0 for bands that only cut
+pub fn band_boost_db(band: &EqBandStatus) -> f32 {
+    if !band.enabled {
+        return 0.0;
+    }
+    match band.eq_type.as_str() {
+        "peaking" | "low_shelf" | "high_shelf" => band.gain.max(0.0),
+        "low_pass" | "high_pass" => resonance_db(band.q),
+        _ => 0.0,
+    }
+}
+
+fn eq_boost_db(block: &BlockStatus) -> f32 {
+    block.eq_bands.iter().map(band_boost_db).sum()
+}
+
+fn crossbar_gain(status: &StatusResponse, input: usize, output: usize) -> f32 {
+    let xbar = &status.crossbar;
+    match (input, output) {
+        (0, 0) => xbar.input_0_to_output_0,
+        (0, 1) => xbar.input_0_to_output_1,
+        (1, 0) => xbar.input_1_to_output_0,
+        (1, 1) => xbar.input_1_to_output_1,
+        _ => 0.0,
+    }
+}
+
+/// Round a positive gain up to 0.1 dB and negate it
+fn trim_for(peak_db: f32) -> f32 {
+    -(peak_db * 10.0).ceil() / 10.0
+}
+
+/// Analyze the worst-case gain of every output
+pub fn analyze(status: &StatusResponse) -> HeadroomResponse {
+    let mut warnings = Vec::new();
+    if !status.enabled {
+        warnings.push("SpeakerEQ is disabled, the analysis applies when it is enabled".to_string());
+    }
+
+    let outputs: Vec<OutputHeadroom> = status
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(o, output)| {
+            let amplitude: f32 = status
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(i, input)| crossbar_gain(status, i, o).abs() * db_to_linear(input.gain_db + eq_boost_db(input)))
+                .sum();
+            let eq_boost = eq_boost_db(output);
+            let peak_gain_db = (amplitude > 0.0)
+                .then(|| linear_to_db(amplitude) + output.gain_db + eq_boost + status.master_gain_db);
+            OutputHeadroom {
+                id: output.id.clone(),
+                peak_gain_db,
+                eq_boost_db: eq_boost,
+                clips: peak_gain_db.is_some_and(|g| g > 0.0),
+            }
+        })
+        .collect();
+
+    let worst = outputs
+        .iter()
+        .filter_map(|o| o.peak_gain_db)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let suggested_trim_db = if worst > 0.0 { trim_for(worst) } else { 0.0 };
+    for output in outputs.iter().filter(|o| o.clips) {
+        warnings.push(format!(
+            "{} can clip: up to {:+.1} dB",
+            output.id,
+            output.peak_gain_db.unwrap_or_default()
+        ));
+    }
+
+    HeadroomResponse {
+        enabled: status.enabled,
+        master_gain_db: status.master_gain_db,
+        outputs,
+        suggested_trim_db,
+        warnings,
+        applied: false,
+    }
+}
+
+/// Analyze the headroom
+/// GET /api/v1/module/speakereq/headroom
+pub async fn get_headroom(
+    State(state): State<Arc<NodeState>>,
+    Query(query): Query<HeadroomQuery>,
+) -> Result<Json<HeadroomResponse>, ApiError> {
+    // A GET must not change parameters: it bypasses the dsp access group,
+    // the response cache invalidation and the revision check
+    if query.apply {
+        return Err(ApiError::BadRequest(
+            "Use PUT /api/v1/module/speakereq/headroom to apply the trim".to_string(),
+        ));
+    }
+    let params = state.get_params_async().await?;
+    let status = speakereq::status_from_params(&params)?;
+    Ok(Json(analyze(&status)))
+}
+
+/// Apply the suggested trim to the master gain
+/// PUT /api/v1/module/speakereq/headroom
+pub async fn apply_headroom(State(state): State<Arc<NodeState>>) -> Result<Json<HeadroomResponse>, ApiError> {
+    let params = state.get_params_async().await?;
+    let mut status = speakereq::status_from_params(&params)?;
+    let analysis = analyze(&status);
+    if analysis.suggested_trim_db == 0.0 {
+        return Ok(Json(analysis));
+    }
+
+    let master_gain_db = status.master_gain_db + analysis.suggested_trim_db;
+    if master_gain_db < MIN_MASTER_GAIN_DB {
+        return Err(ApiError::BadRequest(format!(
+            "Trim of {:.1} dB would set the master gain below {} dB",
+            analysis.suggested_trim_db, MIN_MASTER_GAIN_DB
+        )));
+    }
+    state
+        .set_parameter_async("master_gain_db", ParameterValue::Float(master_gain_db))
+        .await?;
+    tracing::info!("SpeakerEQ headroom: master gain trimmed to {:.1} dB", master_gain_db);
+
+    status.master_gain_db = master_gain_db;
+    let mut trimmed = analyze(&status);
+    trimmed.applied = true;
+    Ok(Json(trimmed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::speakereq::CrossbarMatrix;
+
+    fn band(eq_type: &str, gain: f32, q: f32) -> EqBandStatus {
+        EqBandStatus { band: 1, eq_type: eq_type.to_string(), frequency: 1000.0, q, gain, enabled: true }
+    }
+
+    fn block(id: &str, gain_db: f32, eq_bands: Vec<EqBandStatus>) -> BlockStatus {
+        BlockStatus { id: id.to_string(), block_type: "output".to_string(), gain_db, delay_ms: None, eq_bands }
+    }
+
+    fn status(output_bands: Vec<EqBandStatus>, master_gain_db: f32) -> StatusResponse {
+        StatusResponse {
+            enabled: true,
+            master_gain_db,
+            crossbar: CrossbarMatrix {
+                input_0_to_output_0: 1.0,
+                input_0_to_output_1: 0.0,
+                input_1_to_output_0: 0.0,
+                input_1_to_output_1: 1.0,
+            },
+            inputs: vec![block("input_0", 0.0, vec![]), block("input_1", 0.0, vec![])],
+            outputs: vec![block("output_0", 0.0, output_bands), block("output_1", 0.0, vec![])],
+        }
+    }
+
+    #[test]
+    fn test_band_boost() {
+        assert_eq!(band_boost_db(&band("peaking", 6.0, 1.0)), 6.0);
+        assert_eq!(band_boost_db(&band("peaking", -6.0, 1.0)), 0.0);
+        assert_eq!(band_boost_db(&band("low_pass", 0.0, 0.707)), 0.0);
+        assert!(band_boost_db(&band("high_pass", 0.0, 2.0)) > 6.0);
+        assert_eq!(band_boost_db(&band("notch", 0.0, 2.0)), 0.0);
+    }
+
+    #[test]
+    fn test_analyze_clipping() {
+        let analysis = analyze(&status(vec![band("peaking", 4.0, 1.0), band("low_shelf", 2.0, 0.7)], -1.0));
+        assert!(analysis.outputs[0].clips);
+        assert!(!analysis.outputs[1].clips);
+        assert_eq!(analysis.suggested_trim_db, -5.0);
+        assert_eq!(analysis.warnings.len(), 1);
+
+        let trimmed = analyze(&status(vec![band("peaking", 4.0, 1.0)], -4.0));
+        assert!(trimmed.outputs.iter().all(|o| !o.clips));
+        assert_eq!(trimmed.suggested_trim_db, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_get_does_not_apply() {
+        let state = Arc::new(NodeState::new("none".to_string()));
+        // Encoded keys and values are decoded like by any other client
+        for query in ["apply=true", "apply=%74rue", "appl%79=true"] {
+            let uri: axum::http::Uri = format!("/api/v1/module/speakereq/headroom?{}", query).parse().unwrap();
+            let query = Query::<HeadroomQuery>::try_from_uri(&uri).unwrap();
+            assert!(matches!(get_headroom(State(state.clone()), query).await, Err(ApiError::BadRequest(_))));
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! License status of the SpeakerEQ plugin
+//!
+//! The plugin reports its license with control parameters: `Licensed`, an
+//! optional expiry (`License Expiry`, a date or Unix time) and feature flags
+//! (`Feature <name>`). Names are matched without case and with spaces or
+//! underscores. A license key is submitted by writing the `License Key`
+//! parameter.
+
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api_server::{ApiError, NodeState};
+use crate::parameters::ParameterValue;
+
+/// Longest license key accepted
+const MAX_KEY_LENGTH: usize = 256;
+
+/// License status of the plugin
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LicenseStatus {
+    pub licensed: bool,
+    /// Expiry as reported by the plugin (RFC 3339 if it is a Unix time)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<String>,
+    /// Whether the expiry has passed, None if unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expired: Option<bool>,
+    /// Feature flags, e.g. {"crossbar": true}
+    pub features: BTreeMap<String, bool>,
+    /// The plugin accepts a license key
+    pub accepts_key: bool,
+}
+
+/// Request body for PUT /api/v1/module/speakereq/license
+#[derive(Debug, Deserialize)]
+pub struct LicenseKeyRequest {
+    pub key: String,
+}
+
+/// Normalized parameter name without plugin prefix: "License Expiry" -> "license_expiry"
+fn normalize(key: &str) -> String {
+    key.rsplit(':')
+        .next()
+        .unwrap_or(key)
+        .trim()
+        .to_lowercase()
+        .replace([' ', '-'], "_")
+}
+
+/// Expiry and whether it passed, from a Unix time or a string
+fn parse_expiry(value: &ParameterValue, now: SystemTime) -> Option<(String, Option<bool>)> {
+    let unix = |secs: u64| {
+        let time = UNIX_EPOCH + Duration::from_secs(secs);
+        (humantime::format_rfc3339_seconds(time).to_string(), Some(time < now))
+    };
+    match value {
+        ParameterValue::Int(i) if *i > 0 => Some(unix(*i as u64)),
+        ParameterValue::Float(f) if *f > 0.0 => Some(unix(*f as u64)),
+        ParameterValue::String(s) | ParameterValue::Enum(s) if !s.trim().is_empty() => {
+            let expired = humantime::parse_rfc3339_weak(s.trim())
+                .or_else(|_| humantime::parse_rfc3339_weak(&format!("{} 00:00:00", s.trim())))
+                .ok()
+                .map(|time| time < now);
+            Some((s.trim().to_string(), expired))
+        }
+        _ => None,
+    }
+}
+
+/// Name of the license key parameter, if the plugin has one
+pub fn license_key_param(params: &HashMap<String, ParameterValue>) -> Option<String> {
+    params.keys().find(|k| normalize(k) == "license_key").cloned()
+}
+
+/// Read the license status from the plugin parameters
+pub fn license_from_params(params: &HashMap<String, ParameterValue>, now: SystemTime) -> LicenseStatus {
+    let mut status = LicenseStatus {
+        // Plugins without license parameters are not restricted
+        licensed: true,
+        expiry: None,
+        expired: None,
+        features: BTreeMap::new(),
+        accepts_key: license_key_param(params).is_some(),
+    };
+    for (key, value) in params {
+        let name = normalize(key);
+        if name == "licensed" {
+            status.licensed = value.as_bool().unwrap_or(true);
+        } else if matches!(name.as_str(), "license_expiry" | "license_expires" | "expiry") {
+            if let Some((expiry, expired)) = parse_expiry(value, now) {
+                status.expiry = Some(expiry);
+                status.expired = expired;
+            }
+        } else if let Some(feature) = name.strip_prefix("feature_") {
+            if let Some(enabled) = value.as_bool() {
+                status.features.insert(feature.to_string(), enabled);
+            }
+        }
+    }
+    status
+}
+
+/// Get the license status and feature flags
+/// GET /api/v1/module/speakereq/license
+pub async fn get_license(State(state): State<Arc<NodeState>>) -> Result<Json<LicenseStatus>, ApiError> {
+    let params = state.get_params_async().await?;
+    Ok(Json(license_from_params(&params, SystemTime::now())))
+}
+
+/// Submit a license key
+/// PUT /api/v1/module/speakereq/license
+///
+/// The key is not recorded for undo. Returns the license status after the
+/// plugin read the key.
+pub async fn set_license_key(
+    State(state): State<Arc<NodeState>>,
+    Json(request): Json<LicenseKeyRequest>,
+) -> Result<Json<LicenseStatus>, ApiError> {
+    let key = request.key.trim().to_string();
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH || key.chars().any(|c| c.is_control()) {
+        return Err(ApiError::BadRequest(format!(
+            "License key must be 1 to {} printable characters",
+            MAX_KEY_LENGTH
+        )));
+    }
+    let params = state.get_params_async().await?;
+    let param = license_key_param(&params)
+        .ok_or_else(|| ApiError::NotFound("The plugin has no license key parameter".to_string()))?;
+
+    let node = Arc::clone(&state);
+    crate::worker::run(move || node.set_parameters_untracked(HashMap::from([(param, ParameterValue::String(key))])))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Worker error: {}", e)))??;
+    tracing::info!("SpeakerEQ license key submitted");
+
+    let params = state.get_params_async().await?;
+    Ok(Json(license_from_params(&params, SystemTime::now())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_license_from_params() {
+        let params = HashMap::from([
+            ("speakereq2x2:Licensed".to_string(), ParameterValue::Bool(false)),
+            ("speakereq2x2:License Expiry".to_string(), ParameterValue::Int(1_700_000_000)),
+            ("speakereq2x2:Feature Crossbar".to_string(), ParameterValue::Bool(true)),
+            ("speakereq2x2:feature_delay".to_string(), ParameterValue::Int(0)),
+            ("speakereq2x2:License Key".to_string(), ParameterValue::String(String::new())),
+            ("speakereq2x2:master_gain_db".to_string(), ParameterValue::Float(0.0)),
+        ]);
+        let now = UNIX_EPOCH + Duration::from_secs(1_800_000_000);
+        let status = license_from_params(&params, now);
+        assert!(!status.licensed);
+        assert_eq!(status.expiry.as_deref(), Some("2023-11-14T22:13:20Z"));
+        assert_eq!(status.expired, Some(true));
+        assert_eq!(status.features, BTreeMap::from([("crossbar".to_string(), true), ("delay".to_string(), false)]));
+        assert!(status.accepts_key);
+    }
+
+    #[test]
+    fn test_license_without_params() {
+        let status = license_from_params(&HashMap::new(), SystemTime::now());
+        assert!(status.licensed);
+        assert!(status.features.is_empty());
+        assert!(!status.accepts_key);
+
+        let params = HashMap::from([("p:Expiry".to_string(), ParameterValue::String("2099-01-01".to_string()))]);
+        assert_eq!(license_from_params(&params, SystemTime::now()).expired, Some(false));
+    }
+}
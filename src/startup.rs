@@ -0,0 +1,332 @@
+//! Startup sequencing of rules
+//!
+//! At boot, volume, parameter and link rules can run before the nodes they
+//! target exist. A rule can declare a node to wait for:
+//!
+//! ```json
+//! "wait_for": { "node": "^speakereq.x.$", "timeout_secs": 30 }
+//! ```
+//!
+//! Rules are applied in phases (volume rules, parameter rules, link rules).
+//! Rules without `wait_for` are applied immediately in configuration order;
+//! rules with `wait_for` are applied in the background as soon as their
+//! node appears. The state of every rule is recorded in a [`StartupTracker`]
+//! and reported at `GET /api/v1/startup/status`.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::api_server::AppState;
+use crate::config::VolumeRule;
+use crate::param_rules::ParamRule;
+use crate::util::regex_match;
+
+/// Interval for checking whether a node has appeared
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+fn default_wait_timeout() -> u64 {
+    30
+}
+
+/// Node a rule waits for before it is applied
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WaitFor {
+    /// Regex matching the node.name of the node
+    pub node: String,
+    /// Maximum time to wait in seconds (default: 30)
+    #[serde(default = "default_wait_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// Type of a startup rule
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleKind {
+    Volume,
+    Param,
+    Link,
+}
+
+/// State of a startup rule
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupState {
+    /// Not applied yet
+    Pending,
+    /// Waiting for the `wait_for` node
+    Waiting,
+    /// Applied successfully
+    Applied,
+    /// Applied with errors
+    Failed,
+    /// The `wait_for` node did not appear in time, the rule was not applied
+    TimedOut,
+}
+
+/// Startup status of a rule
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupEntry {
+    pub kind: RuleKind,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_for: Option<WaitFor>,
+    pub state: StartupState,
+    /// Time spent waiting for the node in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub waited_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Records the startup state of all rules
+#[derive(Debug, Default)]
+pub struct StartupTracker {
+    entries: Mutex<Vec<StartupEntry>>,
+    complete: AtomicBool,
+}
+
+impl StartupTracker {
+    /// Register a rule, returns its index
+    pub fn add(&self, kind: RuleKind, name: &str, wait_for: Option<WaitFor>) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(StartupEntry {
+            kind,
+            name: name.to_string(),
+            wait_for,
+            state: StartupState::Pending,
+            waited_ms: None,
+            message: None,
+        });
+        entries.len() - 1
+    }
+
+    /// Update the state of a rule
+    pub fn set_state(&self, idx: usize, state: StartupState, message: Option<String>) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(idx) {
+            entry.state = state;
+            entry.message = message;
+        }
+    }
+
+    fn set_waited(&self, idx: usize, waited: Duration) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(idx) {
+            entry.waited_ms = Some(waited.as_millis() as u64);
+        }
+    }
+
+    /// Get the state of all rules
+    pub fn entries(&self) -> Vec<StartupEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    /// Mark startup as finished (all rules applied or timed out)
+    pub fn mark_complete(&self) {
+        self.complete.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether all rules have been handled
+    pub fn is_complete(&self) -> bool {
+        self.complete.load(Ordering::SeqCst)
+    }
+}
+
+/// Check whether a node with a matching node.name exists
+pub fn node_present(pattern: &str) -> Result<bool, String> {
+    Ok(crate::pwcli::list_nodes()?
+        .iter()
+        .any(|n| n.name().map(|name| regex_match(pattern, name)).unwrap_or(false)))
+}
+
+/// Wait until a node matching `wait.node` exists
+///
+/// Returns the time waited, or an error if the node did not appear in time.
+pub async fn wait_for_node(wait: &WaitFor) -> Result<Duration, String> {
+    let start = Instant::now();
+    let timeout = Duration::from_secs(wait.timeout_secs);
+
+    loop {
+        let pattern = wait.node.clone();
+        match crate::worker::run(move || node_present(&pattern)).await {
+            Ok(Ok(true)) => return Ok(start.elapsed()),
+            Ok(Ok(false)) => {}
+            Ok(Err(e)) => warn!("Failed to check for node '{}': {}", wait.node, e),
+            Err(e) => warn!("Failed to check for node '{}': {}", wait.node, e),
+        }
+        if start.elapsed() >= timeout {
+            return Err(format!("Node '{}' did not appear within {}s", wait.node, wait.timeout_secs));
+        }
+        tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+    }
+}
+
+/// A rule to apply at startup
+#[derive(Debug, Clone)]
+enum StartupRule {
+    Volume(VolumeRule),
+    Param(ParamRule),
+    /// Link rule with its index in the link rule list
+    Link(usize, crate::linker::LinkRule),
+}
+
+impl StartupRule {
+    fn kind(&self) -> RuleKind {
+        match self {
+            StartupRule::Volume(_) => RuleKind::Volume,
+            StartupRule::Param(_) => RuleKind::Param,
+            StartupRule::Link(..) => RuleKind::Link,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            StartupRule::Volume(r) => &r.name,
+            StartupRule::Param(r) => &r.name,
+            StartupRule::Link(_, r) => &r.name,
+        }
+    }
+
+    fn wait_for(&self) -> Option<&WaitFor> {
+        match self {
+            StartupRule::Volume(r) => r.wait_for.as_ref(),
+            StartupRule::Param(r) => r.wait_for.as_ref(),
+            StartupRule::Link(_, r) => r.wait_for.as_ref(),
+        }
+    }
+
+    /// Apply the rule, returns an error message on failure
+    async fn apply(&self, state: &AppState) -> Option<String> {
+        match self {
+            StartupRule::Volume(rule) => {
+                let rules = vec![rule.clone()];
+                match crate::worker::run(move || crate::volume::apply_volume_rules(rules)).await {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => Some(e.to_string()),
+                    Err(e) => Some(e.to_string()),
+                }
+            }
+            StartupRule::Param(rule) => crate::param_rules::apply_param_rules(std::slice::from_ref(rule))
+                .await
+                .err(),
+            StartupRule::Link(idx, rule) => crate::link_scheduler::apply_startup_rule(state, *idx, rule).await,
+        }
+    }
+}
+
+/// Apply a rule and record the result
+async fn apply_tracked(state: &AppState, idx: usize, rule: &StartupRule) {
+    let tracker = state.startup.clone();
+    match rule.apply(state).await {
+        None => tracker.set_state(idx, StartupState::Applied, None),
+        Some(e) => tracker.set_state(idx, StartupState::Failed, Some(e)),
+    }
+}
+
+/// Wait for the rule's node, then apply it
+async fn wait_and_apply(state: Arc<AppState>, idx: usize, rule: StartupRule) {
+    let tracker = state.startup.clone();
+    let Some(wait) = rule.wait_for().cloned() else {
+        return;
+    };
+
+    tracker.set_state(idx, StartupState::Waiting, None);
+    match wait_for_node(&wait).await {
+        Ok(waited) => {
+            tracker.set_waited(idx, waited);
+            info!("Node '{}' found after {} ms, applying rule '{}'", wait.node, waited.as_millis(), rule.name());
+            apply_tracked(&state, idx, &rule).await;
+        }
+        Err(e) => {
+            warn!("Startup rule '{}' not applied: {}", rule.name(), e);
+            tracker.set_waited(idx, Duration::from_secs(wait.timeout_secs));
+            tracker.set_state(idx, StartupState::TimedOut, Some(e));
+        }
+    }
+}
+
+/// Apply startup rules in order: volume rules, parameter rules, link rules
+///
+/// Link rules are taken from the application state (rules with
+/// `link_at_startup`). Rules without `wait_for` are applied before this
+/// function returns; the returned task applies the rules that wait for a
+/// node and marks startup as complete when all are done.
+pub async fn run_startup(
+    state: Arc<AppState>,
+    volume_rules: Vec<VolumeRule>,
+    param_rules: Vec<ParamRule>,
+) -> tokio::task::JoinHandle<()> {
+    let rules: Vec<StartupRule> = volume_rules
+        .into_iter()
+        .map(StartupRule::Volume)
+        .chain(param_rules.into_iter().filter(|r| r.set_at_startup).map(StartupRule::Param))
+        .chain(
+            state
+                .get_link_rules()
+                .into_iter()
+                .enumerate()
+                .filter(|(_, r)| r.link_at_startup)
+                .map(|(idx, r)| StartupRule::Link(idx, r)),
+        )
+        .collect();
+
+    let indexed: Vec<(usize, StartupRule)> = rules
+        .into_iter()
+        .map(|rule| (state.startup.add(rule.kind(), rule.name(), rule.wait_for().cloned()), rule))
+        .collect();
+
+    let (waiting, immediate): (Vec<_>, Vec<_>) = indexed.into_iter().partition(|(_, r)| r.wait_for().is_some());
+
+    if !immediate.is_empty() {
+        info!("Applying {} startup rule(s)", immediate.len());
+    }
+    for (idx, rule) in &immediate {
+        apply_tracked(&state, *idx, rule).await;
+    }
+
+    if !waiting.is_empty() {
+        info!("{} startup rule(s) waiting for nodes", waiting.len());
+    }
+    let handles: Vec<_> = waiting
+        .into_iter()
+        .map(|(idx, rule)| tokio::spawn(wait_and_apply(state.clone(), idx, rule)))
+        .collect();
+
+    tokio::spawn(async move {
+        for handle in handles {
+            let _ = handle.await;
+        }
+        state.startup.mark_complete();
+        info!("Startup rules complete");
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wait_for() {
+        let wait: WaitFor = serde_json::from_str(r#"{"node": "^speakereq.x.$"}"#).unwrap();
+        assert_eq!(wait.timeout_secs, 30);
+
+        let json = r#"{"name": "eq", "object": {"node.name": "x"}, "volume": 0.5,
+            "wait_for": {"node": "x", "timeout_secs": 5}}"#;
+        let rule: VolumeRule = serde_json::from_str(json).unwrap();
+        assert_eq!(rule.wait_for.map(|w| w.timeout_secs), Some(5));
+    }
+
+    #[test]
+    fn test_tracker() {
+        let tracker = StartupTracker::default();
+        let idx = tracker.add(RuleKind::Link, "eq to dac", None);
+        assert_eq!(tracker.entries()[idx].state, StartupState::Pending);
+
+        tracker.set_state(idx, StartupState::Failed, Some("no ports".to_string()));
+        let entry = &tracker.entries()[idx];
+        assert_eq!(entry.state, StartupState::Failed);
+        assert_eq!(entry.message.as_deref(), Some("no ports"));
+        assert!(!tracker.is_complete());
+    }
+}
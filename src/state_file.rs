@@ -0,0 +1,242 @@
+//! Crash-safe writing of state files with rotated backups
+//!
+//! State files (`settings.json`, `volume.state`, `links.state` in
+//! `~/.state/pipewire-api`) are written to a temporary file first and then
+//! renamed over the old file, so a crash or power loss mid-write leaves
+//! either the old or the new content, never a truncated file.
+//!
+//! Before a file is replaced, its previous content is kept as a backup
+//! (`settings.json.1` is the newest, `settings.json.N` the oldest). The
+//! number of backups is set with `[server] state_backups`.
+
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+/// Default number of backups kept per state file
+pub const DEFAULT_BACKUPS: usize = 3;
+
+/// State files that have backups
+pub const STATE_FILES: &[&str] = &["settings.json", "volume.state", "links.state"];
+
+static BACKUPS: AtomicUsize = AtomicUsize::new(DEFAULT_BACKUPS);
+
+/// Makes the names of temporary files unique within the process
+static NEXT_TMP: AtomicUsize = AtomicUsize::new(0);
+
+/// Serializes rotating the backups and replacing the file
+static REPLACE: Mutex<()> = Mutex::new(());
+
+/// Set the number of backups kept per state file (0 disables backups)
+pub fn set_backup_count(count: usize) {
+    BACKUPS.store(count, Ordering::Relaxed);
+}
+
+/// Get the number of backups kept per state file
+pub fn backup_count() -> usize {
+    BACKUPS.load(Ordering::Relaxed)
+}
+
+/// A backup of a state file
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BackupInfo {
+    /// State file name, e.g. "settings.json"
+    pub file: String,
+    /// 1 is the newest backup
+    pub index: usize,
+    pub size: u64,
+    /// Modification time in RFC 3339 format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+}
+
+/// Get the path of backup `index` of a file
+pub fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// Shift the backups of a file by one and keep the current content as backup 1
+fn rotate_backups(path: &Path, count: usize) -> io::Result<()> {
+    if count == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    for index in (1..count).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, index + 1))?;
+        }
+    }
+    // Copy instead of rename, the file must exist until it is replaced
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+/// Write a file atomically with `count` rotated backups of the old content
+pub fn write_with_backups(path: &Path, content: &[u8], count: usize) -> io::Result<()> {
+    replace(path, content, count, 0o666)
+}
+
+/// Write a file only the user can read, e.g. with tokens, without backups
+///
+/// Backups left by earlier versions are removed, so replaced content (e.g. a
+/// revoked token) does not stay on disk.
+pub fn write_private(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    replace(path, content.as_ref(), 0, 0o600)?;
+    for backup in list_backups(path) {
+        fs::remove_file(backup_path(path, backup.index))?;
+    }
+    Ok(())
+}
+
+/// Replace a file by a temporary file created with `mode` (before the umask)
+fn replace(path: &Path, content: &[u8], count: usize, mode: u32) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Concurrent writers of the same file each use their own temporary file
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.{}.tmp", std::process::id(), NEXT_TMP.fetch_add(1, Ordering::Relaxed)));
+    let tmp = PathBuf::from(tmp);
+
+    let result = write_tmp(&tmp, content, mode).and_then(|()| {
+        let _guard = REPLACE.lock().unwrap_or_else(|e| e.into_inner());
+        // Unchanged content does not push out older backups
+        if fs::read(path).map(|old| old != content).unwrap_or(false) {
+            rotate_backups(path, count)?;
+        }
+        fs::rename(&tmp, path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result?;
+
+    // Make the rename itself survive a power loss
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+fn write_tmp(tmp: &Path, content: &[u8], mode: u32) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).mode(mode).open(tmp)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+/// Write a state file atomically, keeping the configured number of backups
+pub fn write(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    write_with_backups(path, content.as_ref(), backup_count())
+}
+
+/// List the backups of a file, newest first
+pub fn list_backups(path: &Path) -> Vec<BackupInfo> {
+    let file = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // Also list backups beyond the configured count, e.g. after lowering it
+    (1..)
+        .map(|index| (index, backup_path(path, index)))
+        .map_while(|(index, backup)| fs::metadata(&backup).ok().map(|m| (index, m)))
+        .map(|(index, metadata)| BackupInfo {
+            file: file.clone(),
+            index,
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .filter(|t| *t > UNIX_EPOCH)
+                .map(|t| humantime::format_rfc3339_seconds(t).to_string()),
+        })
+        .collect()
+}
+
+/// Replace a file with one of its backups
+///
+/// The replaced content becomes backup 1, so a restore can be undone.
+pub fn restore_backup(path: &Path, index: usize) -> Result<(), String> {
+    let backup = backup_path(path, index);
+    let content = fs::read(&backup).map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            format!("Backup {} of {} not found", index, path.display())
+        } else {
+            format!("Failed to read {}: {}", backup.display(), e)
+        }
+    })?;
+
+    // Keep at least one backup so the restored-over content is not lost
+    write_with_backups(path, &content, backup_count().max(index).max(1))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_rotates_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("volume.state");
+
+        for content in ["a", "b", "c", "d"] {
+            write_with_backups(&path, content.as_bytes(), 2).unwrap();
+        }
+        // Unchanged content is not backed up
+        write_with_backups(&path, b"d", 2).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "d");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "c");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "b");
+        assert!(!backup_path(&path, 3).exists());
+        // No temporary files are left
+        assert_eq!(fs::read_dir(dir.path().join("state")).unwrap().count(), 3);
+
+        let backups = list_backups(&path);
+        assert_eq!(backups.iter().map(|b| b.index).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(backups[0].file, "volume.state");
+        assert_eq!(backups[0].size, 1);
+    }
+
+    #[test]
+    fn test_concurrent_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.state");
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    for j in 0..20 {
+                        write_with_backups(path, format!("{}-{}", i, j).as_bytes(), 2).unwrap();
+                    }
+                });
+            }
+        });
+        assert!(fs::read_to_string(&path).unwrap().ends_with("-19"));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_restore_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        write_with_backups(&path, b"old", 2).unwrap();
+        write_with_backups(&path, b"new", 2).unwrap();
+
+        restore_backup(&path, 1).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "new");
+
+        assert!(restore_backup(&path, 5).unwrap_err().contains("not found"));
+    }
+}
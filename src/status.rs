@@ -0,0 +1,218 @@
+//! System status summary
+//!
+//! Collects what a control panel home screen needs in one call: default
+//! sink and source with volume and mute, active streams, DSP module state
+//! and the PipeWire version. Everything is read with pw-cli and
+//! pw-metadata; parts that cannot be read are left out.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::command;
+use crate::modules::Module;
+use crate::parameters::ParameterValue;
+use crate::pod_parser::ParamObject;
+use crate::pwcli::{self, PwObject};
+use crate::pwmetadata::{self, MetadataEntry};
+use crate::pwparams;
+
+/// Names of the parameters that enable a DSP module, in order of preference
+pub const ENABLE_PARAMS: &[&str] = &["Enable", "RIAA Enable"];
+
+/// Default sink or source
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DefaultNodeSummary {
+    pub id: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Volume on the same (cubic) scale as wpctl
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+}
+
+/// An active audio stream
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StreamSummary {
+    pub id: u32,
+    pub name: String,
+    /// "playback" or "capture"
+    pub direction: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub application: Option<String>,
+}
+
+/// State of a DSP module
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModuleSummary {
+    pub name: String,
+    pub present: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+}
+
+/// Response for GET /api/v1/status
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusSummary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipewire_version: Option<String>,
+    pub default_sink: Option<DefaultNodeSummary>,
+    pub default_source: Option<DefaultNodeSummary>,
+    pub streams: Vec<StreamSummary>,
+    pub modules: Vec<ModuleSummary>,
+}
+
+/// Get the node name of a default node from the "default" metadata
+pub fn default_node_name(entries: &[MetadataEntry], key: &str) -> Option<String> {
+    entries
+        .iter()
+        .find(|e| e.subject == 0 && e.key == key)
+        .and_then(|e| match e.value {
+            Value::Object(ref map) => map.get("name").and_then(|n| n.as_str()).map(|s| s.to_string()),
+            Value::String(ref s) => Some(s.clone()),
+            _ => None,
+        })
+}
+
+/// Get volume (cubic scale, rounded to 0.01) and mute from Props parameters
+pub fn props_volume(params: &[ParamObject]) -> (Option<f32>, Option<bool>) {
+    let state = crate::volume_ops::from_props_params(params);
+    (state.volume.map(|v| (v * 100.0).round() / 100.0), state.mute)
+}
+
+/// Convert a node to a stream summary if it is an audio stream
+pub fn stream_summary(node: &PwObject) -> Option<StreamSummary> {
+    let direction = match node.media_class()? {
+        "Stream/Output/Audio" => "playback",
+        "Stream/Input/Audio" => "capture",
+        _ => return None,
+    };
+    Some(StreamSummary {
+        id: node.id,
+        name: node.display_name(),
+        direction: direction.to_string(),
+        application: node.get("application.name").map(|s| s.to_string()),
+    })
+}
+
+/// Check whether a DSP module is enabled from its parameters
+pub fn module_enabled(params: &std::collections::HashMap<String, ParameterValue>) -> Option<bool> {
+    ENABLE_PARAMS.iter().find_map(|name| {
+        params.iter().find_map(|(key, value)| match value {
+            ParameterValue::Bool(b) if key.rsplit(':').next() == Some(*name) => Some(*b),
+            _ => None,
+        })
+    })
+}
+
+/// Parse the version from `pw-cli info 0`
+pub fn parse_core_version(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("version:")
+            .map(|v| v.trim().trim_matches('"').to_string())
+    })
+}
+
+/// Get the version of the PipeWire server
+pub fn pipewire_version() -> Option<String> {
+    let output = command::output_with_timeout(
+        crate::command::pipewire("pw-cli").args(["info", "0"]),
+        command::discovery_timeout(),
+    )
+    .ok()?;
+    parse_core_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn default_node(nodes: &[PwObject], name: Option<String>) -> Option<DefaultNodeSummary> {
+    let node = nodes.iter().find(|n| n.name() == name.as_deref())?;
+    let (volume, mute) = pwparams::enum_params(node.id, "Props")
+        .map(|p| props_volume(&p))
+        .unwrap_or((None, None));
+    Some(DefaultNodeSummary {
+        id: node.id,
+        name: node.display_name(),
+        description: node.description().map(|s| s.to_string()),
+        volume,
+        mute,
+    })
+}
+
+/// Collect the status summary
+pub fn collect(modules: &[std::sync::Arc<Module>]) -> Result<StatusSummary, String> {
+    let nodes = pwcli::list_nodes()?;
+    let defaults = pwmetadata::get_metadata("default").unwrap_or_default();
+
+    let modules = modules
+        .iter()
+        .map(|m| {
+            let present = m.state.find_node().is_ok();
+            ModuleSummary {
+                name: m.name.clone(),
+                present,
+                enabled: if present {
+                    m.state.get_params().ok().and_then(|p| module_enabled(&p))
+                } else {
+                    None
+                },
+            }
+        })
+        .collect();
+
+    Ok(StatusSummary {
+        pipewire_version: pipewire_version(),
+        default_sink: default_node(&nodes, default_node_name(&defaults, "default.audio.sink")),
+        default_source: default_node(&nodes, default_node_name(&defaults, "default.audio.source")),
+        streams: nodes.iter().filter_map(stream_summary).collect(),
+        modules,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_default_node_name() {
+        let entries = pwmetadata::parse_pw_metadata(
+            "update: id:0 key:'default.audio.sink' value:'{ \"name\": \"speakereq2x2\" }' type:'Spa:String:JSON'\n",
+        );
+        assert_eq!(default_node_name(&entries, "default.audio.sink").as_deref(), Some("speakereq2x2"));
+        assert_eq!(default_node_name(&entries, "default.audio.source"), None);
+    }
+
+    #[test]
+    fn test_props_volume() {
+        let mut properties = serde_json::Map::new();
+        properties.insert("channelVolumes".to_string(), serde_json::json!([0.125, 0.125]));
+        properties.insert("mute".to_string(), serde_json::json!(true));
+        let params = vec![ParamObject {
+            object_type: "Props".to_string(),
+            id: "Props".to_string(),
+            properties,
+        }];
+        assert_eq!(props_volume(&params), (Some(0.5), Some(true)));
+        assert_eq!(props_volume(&[]), (None, None));
+    }
+
+    #[test]
+    fn test_module_enabled() {
+        let params = HashMap::from([
+            ("riaa:Declick Enable".to_string(), ParameterValue::Bool(true)),
+            ("riaa:RIAA Enable".to_string(), ParameterValue::Bool(false)),
+        ]);
+        assert_eq!(module_enabled(&params), Some(false));
+
+        let params = HashMap::from([("speakereq2x2:Enable".to_string(), ParameterValue::Bool(true))]);
+        assert_eq!(module_enabled(&params), Some(true));
+    }
+
+    #[test]
+    fn test_parse_core_version() {
+        let output = "\tid: 0\n\tpermissions: rwxm\n\ttype: PipeWire:Interface:Core/4\n\tversion: \"1.2.7\"\n\tname: \"pipewire-0\"\n";
+        assert_eq!(parse_core_version(output).as_deref(), Some("1.2.7"));
+    }
+}
@@ -0,0 +1,242 @@
+//! Persistence of volume state, presets and history
+//!
+//! State is stored through the [`StateStore`] trait. The default
+//! [`FileStore`] keeps the JSON files in `~/.state/pipewire-api`
+//! (`volume.state`, `<preset>.json`). With the `sqlite` cargo feature,
+//! `[server] state_backend = "sqlite"` keeps everything in a single
+//! database, `state.db`, which also records volume history and an audit log
+//! of state changes that can be queried via `/api/v1/history`.
+//!
+//! Settings saved with `POST /api/v1/settings/save` are the preset
+//! [`SETTINGS_PRESET`].
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::VolumeState;
+
+/// Preset holding the settings of the DSP modules
+pub const SETTINGS_PRESET: &str = "settings";
+
+/// A saved volume at a point in time
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct VolumeRecord {
+    pub name: String,
+    pub volume: f32,
+    pub timestamp_ms: u64,
+}
+
+/// A change of persisted state
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AuditEvent {
+    pub timestamp_ms: u64,
+    /// What was done, e.g. "volume.save" or "settings.restore"
+    pub action: String,
+    /// Object of the action, e.g. a node or preset name
+    pub subject: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+}
+
+impl AuditEvent {
+    pub fn new(action: &str, subject: &str, detail: impl Into<String>) -> Self {
+        Self {
+            timestamp_ms: now_ms(),
+            action: action.to_string(),
+            subject: subject.to_string(),
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Current time in milliseconds since the Unix epoch
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Storage backend for persisted state
+pub trait StateStore: Send + Sync {
+    /// Backend name as used in `state_backend`
+    fn backend(&self) -> &'static str;
+
+    /// Load the saved volumes by node name
+    fn load_volumes(&self) -> Result<HashMap<String, f32>, String>;
+
+    /// Replace all saved volumes
+    fn save_volumes(&self, volumes: &[VolumeState]) -> Result<(), String>;
+
+    /// Save or update a single volume
+    fn save_volume(&self, name: &str, volume: f32) -> Result<(), String>;
+
+    /// Load a preset, `None` if it has not been saved
+    fn load_preset(&self, name: &str) -> Result<Option<String>, String>;
+
+    /// Save a preset
+    fn save_preset(&self, name: &str, content: &str) -> Result<(), String>;
+
+    /// Where a preset is stored, for messages
+    fn preset_location(&self, name: &str) -> String;
+
+    /// Record a state change in the audit log
+    fn record_event(&self, event: &AuditEvent) -> Result<(), String>;
+
+    /// Saved volumes of a node, newest first
+    fn volume_history(&self, name: &str, limit: usize) -> Result<Vec<VolumeRecord>, String>;
+
+    /// Audit log, newest first
+    fn events(&self, limit: usize) -> Result<Vec<AuditEvent>, String>;
+
+    /// Write pending changes to disk before the server exits
+    fn flush(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Check that a preset name can be used as a file name
+pub fn validate_preset_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid preset name '{}', use letters, digits, '-' and '_'", name));
+    }
+    Ok(())
+}
+
+/// Error for history queries on backends without history
+pub fn history_unsupported(backend: &str) -> String {
+    format!(
+        "History is not available with the '{}' state backend, set state_backend = \"sqlite\"",
+        backend
+    )
+}
+
+/// JSON files in the state directory
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn preset_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+impl StateStore for FileStore {
+    fn backend(&self) -> &'static str {
+        "files"
+    }
+
+    fn load_volumes(&self) -> Result<HashMap<String, f32>, String> {
+        Ok(crate::config::load_volume_state())
+    }
+
+    fn save_volumes(&self, volumes: &[VolumeState]) -> Result<(), String> {
+        crate::config::save_volume_state(volumes.to_vec()).map_err(|e| format!("{:#}", e))
+    }
+
+    fn save_volume(&self, name: &str, volume: f32) -> Result<(), String> {
+        crate::config::save_single_volume_state(name.to_string(), volume).map_err(|e| format!("{:#}", e))
+    }
+
+    fn load_preset(&self, name: &str) -> Result<Option<String>, String> {
+        validate_preset_name(name)?;
+        let path = self.preset_path(name);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    fn save_preset(&self, name: &str, content: &str) -> Result<(), String> {
+        validate_preset_name(name)?;
+        let path = self.preset_path(name);
+        crate::state_file::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    fn preset_location(&self, name: &str) -> String {
+        self.preset_path(name).to_string_lossy().to_string()
+    }
+
+    fn record_event(&self, event: &AuditEvent) -> Result<(), String> {
+        // No audit log in files, changes are only logged
+        tracing::debug!("{} {} {}", event.action, event.subject, event.detail);
+        Ok(())
+    }
+
+    fn volume_history(&self, _name: &str, _limit: usize) -> Result<Vec<VolumeRecord>, String> {
+        Err(history_unsupported(self.backend()))
+    }
+
+    fn events(&self, _limit: usize) -> Result<Vec<AuditEvent>, String> {
+        Err(history_unsupported(self.backend()))
+    }
+}
+
+static STORE: RwLock<Option<Arc<dyn StateStore>>> = RwLock::new(None);
+
+fn default_store() -> Arc<dyn StateStore> {
+    let dir = crate::config::state_dir().unwrap_or_else(|| PathBuf::from("."));
+    Arc::new(FileStore::new(dir))
+}
+
+/// Open the store for a `state_backend` setting ("files" or "sqlite")
+pub fn open(backend: &str) -> Result<Arc<dyn StateStore>, String> {
+    match backend {
+        "files" => Ok(default_store()),
+        #[cfg(feature = "sqlite")]
+        "sqlite" => {
+            let dir = crate::config::state_dir().ok_or_else(|| "Could not determine state directory".to_string())?;
+            Ok(Arc::new(crate::store_sqlite::SqliteStore::open(&dir.join("state.db"))?))
+        }
+        #[cfg(not(feature = "sqlite"))]
+        "sqlite" => Err("The sqlite state backend is not compiled in (cargo feature \"sqlite\")".to_string()),
+        other => Err(format!("Unknown state backend '{}', use \"files\" or \"sqlite\"", other)),
+    }
+}
+
+/// Set the store used for all persisted state
+pub fn set(store: Arc<dyn StateStore>) {
+    *STORE.write().unwrap() = Some(store);
+}
+
+/// Get the store, the file store unless another one was set
+pub fn get() -> Arc<dyn StateStore> {
+    if let Some(store) = STORE.read().unwrap().as_ref() {
+        return store.clone();
+    }
+    STORE.write().unwrap().get_or_insert_with(default_store).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_store_presets() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new(dir.path().to_path_buf());
+
+        assert_eq!(store.load_preset("vinyl").unwrap(), None);
+        store.save_preset("vinyl", "{}").unwrap();
+        assert_eq!(store.load_preset("vinyl").unwrap().as_deref(), Some("{}"));
+        assert!(dir.path().join("vinyl.json").exists());
+
+        assert!(store.save_preset("../etc/passwd", "").is_err());
+        assert!(store.volume_history("sink", 10).is_err());
+    }
+
+    #[test]
+    fn test_open_unknown_backend() {
+        assert!(open("redis").is_err());
+        assert_eq!(open("files").unwrap().backend(), "files");
+    }
+}
@@ -0,0 +1,236 @@
+//! SQLite state backend (cargo feature `sqlite`)
+//!
+//! Keeps volumes, presets, volume history and the audit log in one
+//! database instead of many small JSON files. Writes are transactions, so
+//! the database stays consistent on power loss.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::VolumeState;
+use crate::store::{now_ms, validate_preset_name, AuditEvent, StateStore, VolumeRecord};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS volumes (
+    name TEXT PRIMARY KEY,
+    volume REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS volume_history (
+    name TEXT NOT NULL,
+    volume REAL NOT NULL,
+    timestamp_ms INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS volume_history_name ON volume_history (name, timestamp_ms);
+CREATE TABLE IF NOT EXISTS presets (
+    name TEXT PRIMARY KEY,
+    content TEXT NOT NULL,
+    timestamp_ms INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS events (
+    timestamp_ms INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    detail TEXT NOT NULL
+);
+";
+
+/// State in a SQLite database
+pub struct SqliteStore {
+    path: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+fn db_err(e: rusqlite::Error) -> String {
+    format!("Database error: {}", e)
+}
+
+impl SqliteStore {
+    /// Open or create the database
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create state directory {}: {}", parent.display(), e))?;
+        }
+        let conn = Connection::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        conn.execute_batch(SCHEMA).map_err(db_err)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Open an in-memory database (for tests)
+    pub fn open_in_memory() -> Result<Self, String> {
+        let conn = Connection::open_in_memory().map_err(db_err)?;
+        conn.execute_batch(SCHEMA).map_err(db_err)?;
+        Ok(Self {
+            path: PathBuf::from(":memory:"),
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+fn insert_volume(conn: &Connection, name: &str, volume: f32, timestamp_ms: u64) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO volumes (name, volume) VALUES (?1, ?2)
+         ON CONFLICT(name) DO UPDATE SET volume = excluded.volume",
+        params![name, volume as f64],
+    )?;
+    conn.execute(
+        "INSERT INTO volume_history (name, volume, timestamp_ms) VALUES (?1, ?2, ?3)",
+        params![name, volume as f64, timestamp_ms as i64],
+    )?;
+    Ok(())
+}
+
+impl StateStore for SqliteStore {
+    fn backend(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn load_volumes(&self) -> Result<HashMap<String, f32>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT name, volume FROM volumes").map_err(db_err)?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)? as f32)))
+            .map_err(db_err)?;
+        rows.collect::<Result<HashMap<_, _>, _>>().map_err(db_err)
+    }
+
+    fn save_volumes(&self, volumes: &[VolumeState]) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(db_err)?;
+        tx.execute("DELETE FROM volumes", []).map_err(db_err)?;
+        let now = now_ms();
+        for v in volumes {
+            insert_volume(&tx, &v.name, v.volume, now).map_err(db_err)?;
+        }
+        tx.commit().map_err(db_err)
+    }
+
+    fn save_volume(&self, name: &str, volume: f32) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        insert_volume(&conn, name, volume, now_ms()).map_err(db_err)
+    }
+
+    fn load_preset(&self, name: &str) -> Result<Option<String>, String> {
+        validate_preset_name(name)?;
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT content FROM presets WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()
+            .map_err(db_err)
+    }
+
+    fn save_preset(&self, name: &str, content: &str) -> Result<(), String> {
+        validate_preset_name(name)?;
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO presets (name, content, timestamp_ms) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET content = excluded.content, timestamp_ms = excluded.timestamp_ms",
+            params![name, content, now_ms() as i64],
+        )
+        .map(|_| ())
+        .map_err(db_err)
+    }
+
+    fn preset_location(&self, name: &str) -> String {
+        format!("{}#{}", self.path.display(), name)
+    }
+
+    fn record_event(&self, event: &AuditEvent) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO events (timestamp_ms, action, subject, detail) VALUES (?1, ?2, ?3, ?4)",
+            params![event.timestamp_ms as i64, event.action, event.subject, event.detail],
+        )
+        .map(|_| ())
+        .map_err(db_err)
+    }
+
+    fn volume_history(&self, name: &str, limit: usize) -> Result<Vec<VolumeRecord>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT name, volume, timestamp_ms FROM volume_history WHERE name = ?1
+                 ORDER BY timestamp_ms DESC, rowid DESC LIMIT ?2",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map(params![name, limit as i64], |row| {
+                Ok(VolumeRecord {
+                    name: row.get(0)?,
+                    volume: row.get::<_, f64>(1)? as f32,
+                    timestamp_ms: row.get::<_, i64>(2)? as u64,
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)
+    }
+
+    fn events(&self, limit: usize) -> Result<Vec<AuditEvent>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT timestamp_ms, action, subject, detail FROM events
+                 ORDER BY timestamp_ms DESC, rowid DESC LIMIT ?1",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(AuditEvent {
+                    timestamp_ms: row.get::<_, i64>(0)? as u64,
+                    action: row.get(1)?,
+                    subject: row.get(2)?,
+                    detail: row.get(3)?,
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        self.conn.lock().unwrap().cache_flush().map_err(db_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volumes_and_history() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        store.save_volume("alsa_output.dac", 0.5).unwrap();
+        store.save_volume("alsa_output.dac", 0.7).unwrap();
+        store.save_volume("bluez_output", 0.3).unwrap();
+
+        let volumes = store.load_volumes().unwrap();
+        assert_eq!(volumes.get("alsa_output.dac"), Some(&0.7));
+        assert_eq!(volumes.len(), 2);
+
+        let history = store.volume_history("alsa_output.dac", 10).unwrap();
+        assert_eq!(history.iter().map(|r| r.volume).collect::<Vec<_>>(), vec![0.7, 0.5]);
+
+        store
+            .save_volumes(&[VolumeState { name: "bluez_output".to_string(), volume: 0.4 }])
+            .unwrap();
+        assert_eq!(store.load_volumes().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_presets_and_events() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        assert_eq!(store.load_preset("settings").unwrap(), None);
+        store.save_preset("settings", "{\"a\":1}").unwrap();
+        store.save_preset("settings", "{\"a\":2}").unwrap();
+        assert_eq!(store.load_preset("settings").unwrap().as_deref(), Some("{\"a\":2}"));
+
+        store.record_event(&AuditEvent::new("settings.save", "settings", "")).unwrap();
+        store.record_event(&AuditEvent::new("volume.save", "sink", "0.50")).unwrap();
+        let events = store.events(1).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].action, "volume.save");
+    }
+}
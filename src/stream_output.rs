@@ -0,0 +1,386 @@
+//! Stream output to an external process
+//!
+//! Multiroom systems such as Snapcast read raw audio from a pipe. The stream
+//! output creates a null sink (see [`crate::combine`]) and records what is
+//! played to it with `pw-record`, which writes raw PCM to the stdin of the
+//! command from the `[stream_output]` section, e.g.
+//! `cat > /tmp/snapfifo`. Players are linked to the sink like to any other
+//! sink.
+//!
+//! Enabling the output creates the sink and starts both processes;
+//! disabling it stops them and removes the sink. While the output is
+//! enabled, processes that exited are started again every
+//! [`POLL_INTERVAL`].
+
+use serde::{Deserialize, Serialize};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::combine;
+use crate::pwcli;
+use crate::server_config::StreamOutputSection;
+
+/// Interval for checking the processes
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Sample formats pw-record can write
+const FORMATS: &[&str] = &["u8", "s8", "s16", "s24", "s32", "f32", "f64"];
+
+/// Status of the stream output
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StreamOutputStatus {
+    /// A command is configured in the `[stream_output]` section
+    pub configured: bool,
+    /// The output was enabled and is kept running
+    pub enabled: bool,
+    /// pw-record and the command are running
+    pub running: bool,
+    pub sink_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sink_id: Option<u32>,
+    pub format: String,
+    pub rate: u32,
+    pub channels: u32,
+    /// Seconds since the processes were started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime_secs: Option<u64>,
+    /// Why the output stopped or could not be started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Request body for PUT /api/v1/stream-output
+#[derive(Debug, Deserialize)]
+pub struct SetStreamOutputRequest {
+    pub enabled: bool,
+}
+
+struct Running {
+    sink_id: u32,
+    /// The sink was created by the stream output and is removed with it
+    owns_sink: bool,
+    recorder: Child,
+    command: Child,
+    started: Instant,
+}
+
+impl Running {
+    /// Description of the first process that exited, if any
+    fn exited(&mut self) -> Option<String> {
+        if let Ok(Some(status)) = self.recorder.try_wait() {
+            return Some(format!("pw-record exited ({})", status));
+        }
+        if let Ok(Some(status)) = self.command.try_wait() {
+            return Some(format!("Command exited ({})", status));
+        }
+        None
+    }
+
+    fn kill(&mut self) {
+        // The command runs in its own process group: stop the processes sh
+        // started as well, not only sh
+        let _ = Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", self.command.id())])
+            .stderr(Stdio::null())
+            .status();
+        for child in [&mut self.recorder, &mut self.command] {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Runs the null sink and the processes of the stream output
+#[derive(Default)]
+pub struct StreamOutputManager {
+    config: RwLock<StreamOutputSection>,
+    enabled: Mutex<bool>,
+    running: Mutex<Option<Running>>,
+    last_error: Mutex<Option<String>>,
+}
+
+/// Check the `[stream_output]` section
+pub fn validate(config: &StreamOutputSection) -> Result<(), String> {
+    if config.command.as_deref().is_none_or(|c| c.trim().is_empty()) {
+        return Err("No stream output command configured ([stream_output] command)".to_string());
+    }
+    if config.sink_name.is_empty() || config.sink_name.contains(['"', ' ', '}']) {
+        return Err(format!("Invalid sink name '{}'", config.sink_name));
+    }
+    if !FORMATS.contains(&config.format.as_str()) {
+        return Err(format!("Invalid format '{}' (use one of {})", config.format, FORMATS.join(", ")));
+    }
+    if !(1..=32).contains(&config.channels) {
+        return Err(format!("Invalid channel count {}", config.channels));
+    }
+    if !(8000..=768_000).contains(&config.rate) {
+        return Err(format!("Invalid rate {}", config.rate));
+    }
+    Ok(())
+}
+
+/// Channel positions of the null sink
+pub fn positions(channels: u32) -> Vec<String> {
+    match channels {
+        1 => vec!["MONO".to_string()],
+        2 => vec!["FL".to_string(), "FR".to_string()],
+        n => (0..n).map(|i| format!("AUX{}", i)).collect(),
+    }
+}
+
+/// Arguments of pw-record capturing the sink and writing raw audio to stdout
+pub fn record_args(config: &StreamOutputSection) -> Vec<String> {
+    vec![
+        "--target".to_string(),
+        config.sink_name.clone(),
+        "-P".to_string(),
+        "{ stream.capture.sink = true }".to_string(),
+        "--rate".to_string(),
+        config.rate.to_string(),
+        "--channels".to_string(),
+        config.channels.to_string(),
+        "--format".to_string(),
+        config.format.clone(),
+        "--raw".to_string(),
+        "-".to_string(),
+    ]
+}
+
+/// Find the sink by name or create it
+fn sink(config: &StreamOutputSection) -> Result<(u32, bool), String> {
+    if let Some(node) = pwcli::list_nodes()?.iter().find(|n| n.name() == Some(config.sink_name.as_str())) {
+        return Ok((node.id, false));
+    }
+    let id = combine::create_null_sink(&config.sink_name, &positions(config.channels))?;
+    Ok((id, true))
+}
+
+fn remove_sink(id: u32) {
+    if let Err(e) = pwcli::destroy_object(id) {
+        warn!("Failed to remove stream output sink {}: {}", id, e);
+    }
+}
+
+/// Start pw-record and the command reading its output
+fn spawn(config: &StreamOutputSection, command: &str) -> Result<(Child, Child), String> {
+    let mut recorder = Command::new("pw-record")
+        .args(record_args(config))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start pw-record: {}", e))?;
+    let audio = recorder.stdout.take().ok_or("pw-record has no stdout")?;
+    match Command::new("sh").args(["-c", command]).stdin(audio).process_group(0).spawn() {
+        Ok(child) => Ok((recorder, child)),
+        Err(e) => {
+            let _ = recorder.kill();
+            let _ = recorder.wait();
+            Err(format!("Failed to start command: {}", e))
+        }
+    }
+}
+
+impl StreamOutputManager {
+    /// Set the configuration from the `[stream_output]` section
+    pub fn configure(&self, config: StreamOutputSection) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Whether a command is configured
+    pub fn is_configured(&self) -> bool {
+        self.config.read().unwrap().command.is_some()
+    }
+
+    /// Whether the output should be started with the server
+    pub fn autostart(&self) -> bool {
+        self.config.read().unwrap().autostart
+    }
+
+    /// Get the status, checking whether the processes are still running
+    pub fn status(&self) -> StreamOutputStatus {
+        self.reap();
+        let config = self.config.read().unwrap().clone();
+        let enabled = *self.enabled.lock().unwrap();
+        let last_error = self.last_error.lock().unwrap().clone();
+        let running = self.running.lock().unwrap();
+        StreamOutputStatus {
+            configured: config.command.is_some(),
+            enabled,
+            running: running.is_some(),
+            sink_name: config.sink_name,
+            sink_id: running.as_ref().map(|r| r.sink_id),
+            format: config.format,
+            rate: config.rate,
+            channels: config.channels,
+            uptime_secs: running.as_ref().map(|r| r.started.elapsed().as_secs()),
+            last_error,
+        }
+    }
+
+    /// Stop the processes if one of them exited
+    fn reap(&self) {
+        let mut running = self.running.lock().unwrap();
+        let Some(error) = running.as_mut().and_then(Running::exited) else {
+            return;
+        };
+        warn!("Stream output stopped: {}", error);
+        if let Some(mut r) = running.take() {
+            r.kill();
+            if r.owns_sink {
+                remove_sink(r.sink_id);
+            }
+        }
+        *self.last_error.lock().unwrap() = Some(error);
+    }
+
+    /// Create the sink and start the processes, with the lock of `running` held
+    fn launch(&self, running: &mut Option<Running>) -> Result<(), String> {
+        let config = self.config.read().unwrap().clone();
+        validate(&config)?;
+        let command = config.command.clone().unwrap_or_default();
+        let (sink_id, owns_sink) = sink(&config)?;
+        match spawn(&config, &command) {
+            Ok((recorder, command)) => {
+                *running = Some(Running {
+                    sink_id,
+                    owns_sink,
+                    recorder,
+                    command,
+                    started: Instant::now(),
+                });
+                *self.last_error.lock().unwrap() = None;
+                info!("Stream output started on sink {} ({})", config.sink_name, sink_id);
+                Ok(())
+            }
+            Err(e) => {
+                if owns_sink {
+                    remove_sink(sink_id);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Create the sink and start the processes; nothing happens if they are running
+    pub fn start(&self) -> Result<StreamOutputStatus, String> {
+        self.reap();
+        {
+            // Held while launching, so concurrent starts cannot launch twice
+            let mut running = self.running.lock().unwrap();
+            if running.is_none() {
+                if let Err(e) = self.launch(&mut running) {
+                    *self.last_error.lock().unwrap() = Some(e.clone());
+                    return Err(e);
+                }
+            }
+        }
+        *self.enabled.lock().unwrap() = true;
+        Ok(self.status())
+    }
+
+    /// Stop the processes and remove the sink
+    pub fn stop(&self) -> StreamOutputStatus {
+        *self.enabled.lock().unwrap() = false;
+        if let Some(mut running) = self.running.lock().unwrap().take() {
+            running.kill();
+            if running.owns_sink {
+                remove_sink(running.sink_id);
+            }
+            info!("Stream output stopped");
+        }
+        self.status()
+    }
+
+    /// Start the processes again if the output is enabled but not running
+    pub fn check(&self) {
+        self.reap();
+        // Read after locking, so a concurrent stop either sees the launched
+        // processes or is seen here
+        let mut running = self.running.lock().unwrap();
+        let enabled = *self.enabled.lock().unwrap();
+        if enabled && running.is_none() {
+            if let Err(e) = self.launch(&mut running) {
+                warn!("Failed to restart stream output: {}", e);
+                *self.last_error.lock().unwrap() = Some(e);
+            }
+        }
+    }
+}
+
+/// Start the task that keeps the stream output running while it is enabled
+pub fn start_stream_output(manager: Arc<StreamOutputManager>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let manager = manager.clone();
+            if let Err(e) = crate::worker::run(move || manager.check()).await {
+                warn!("Failed to check stream output: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> StreamOutputSection {
+        StreamOutputSection {
+            command: Some("cat > /tmp/snapfifo".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&config()).is_ok());
+        assert!(validate(&StreamOutputSection::default()).is_err());
+        assert!(validate(&StreamOutputSection { format: "mp3".to_string(), ..config() }).is_err());
+        assert!(validate(&StreamOutputSection { sink_name: "snap cast".to_string(), ..config() }).is_err());
+        assert!(validate(&StreamOutputSection { channels: 0, ..config() }).is_err());
+    }
+
+    #[test]
+    fn test_record_args() {
+        let args = record_args(&config());
+        assert_eq!(args[..2], ["--target".to_string(), "stream_output".to_string()]);
+        assert!(args.windows(2).any(|w| w == ["--rate", "48000"]));
+        assert!(args.windows(2).any(|w| w == ["--format", "s16"]));
+        assert_eq!(args.last().map(String::as_str), Some("-"));
+
+        assert_eq!(positions(2), vec!["FL", "FR"]);
+        assert_eq!(positions(3), vec!["AUX0", "AUX1", "AUX2"]);
+    }
+
+    #[test]
+    fn test_kill_process_group() {
+        use std::io::BufRead;
+        let mut command = Command::new("sh")
+            .args(["-c", "sleep 30 & echo $!; wait"])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        std::io::BufReader::new(command.stdout.take().unwrap()).read_line(&mut line).unwrap();
+        let sleep = line.trim().to_string();
+        let recorder = Command::new("sleep").arg("30").spawn().unwrap();
+        let mut running = Running { sink_id: 0, owns_sink: false, recorder, command, started: Instant::now() };
+        running.kill();
+
+        // The process started by sh is gone or a zombie
+        let gone = (0..50).any(|_| {
+            let state = std::fs::read_to_string(format!("/proc/{}/stat", sleep)).unwrap_or_default();
+            let gone = state.is_empty() || state.rsplit(')').next().is_some_and(|s| s.trim_start().starts_with('Z'));
+            if !gone {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            gone
+        });
+        assert!(gone);
+    }
+}
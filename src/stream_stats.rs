@@ -0,0 +1,205 @@
+//! Statistics of stream activity
+//!
+//! Playback streams becoming active and idle (the `stream_active` and
+//! `stream_idle` events of [`crate::events`]) are recorded as sessions in
+//! memory for [`MAX_WINDOW`]. The statistics sum up the playtime and the
+//! number of sessions per application over a rolling window, e.g. to see
+//! what actually plays on the device.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::events::{self, StateEvent};
+
+/// Longest window, older sessions are dropped
+pub const MAX_WINDOW: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// Most sessions kept
+pub const MAX_SESSIONS: usize = 10_000;
+
+/// A stream being active
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub stream_id: u32,
+    /// application.name, or the node name of streams without one
+    pub application: String,
+    pub started_ms: u64,
+    /// None while the stream is active
+    pub ended_ms: Option<u64>,
+}
+
+/// Activity of an application in the window
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AppStats {
+    pub application: String,
+    /// Seconds its streams were active
+    pub playtime_secs: u64,
+    /// Sessions that started in the window
+    pub sessions: usize,
+    /// Streams that are active now
+    pub active: usize,
+    /// Last time a stream was active (RFC 3339)
+    pub last_active: String,
+}
+
+static SESSIONS: Mutex<VecDeque<Session>> = Mutex::new(VecDeque::new());
+
+/// Time the recording started
+static TRACKING_SINCE: OnceLock<u64> = OnceLock::new();
+
+/// Record a stream event into the sessions
+pub fn record(sessions: &mut VecDeque<Session>, event: &StateEvent, now_ms: u64) {
+    match event {
+        StateEvent::StreamActive { stream } => {
+            if sessions.iter().any(|s| s.stream_id == stream.id && s.ended_ms.is_none()) {
+                return;
+            }
+            sessions.push_back(Session {
+                stream_id: stream.id,
+                application: stream.application.clone().unwrap_or_else(|| stream.name.clone()),
+                started_ms: now_ms,
+                ended_ms: None,
+            });
+        }
+        StateEvent::StreamIdle { stream } => {
+            if let Some(session) = sessions
+                .iter_mut()
+                .rev()
+                .find(|s| s.stream_id == stream.id && s.ended_ms.is_none())
+            {
+                session.ended_ms = Some(now_ms);
+            }
+        }
+        _ => return,
+    }
+    let oldest = now_ms.saturating_sub(MAX_WINDOW.as_millis() as u64);
+    sessions.retain(|s| s.ended_ms.is_none_or(|end| end >= oldest));
+    while sessions.len() > MAX_SESSIONS {
+        sessions.pop_front();
+    }
+}
+
+/// Time the recording of sessions started (RFC 3339), None if not started
+pub fn tracking_since() -> Option<String> {
+    TRACKING_SINCE.get().map(|ms| rfc3339(*ms))
+}
+
+fn rfc3339(ms: u64) -> String {
+    humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_millis(ms)).to_string()
+}
+
+/// Sum up the sessions since `since_ms` per application, most played first
+pub fn summarize<'a>(sessions: impl IntoIterator<Item = &'a Session>, since_ms: u64, now_ms: u64) -> Vec<AppStats> {
+    // playtime in ms, sessions, active, last active
+    let mut apps: BTreeMap<&str, (u64, usize, usize, u64)> = BTreeMap::new();
+    for session in sessions {
+        let end = session.ended_ms.unwrap_or(now_ms);
+        if end < since_ms {
+            continue;
+        }
+        let entry = apps.entry(&session.application).or_default();
+        entry.0 += end.saturating_sub(session.started_ms.max(since_ms));
+        if session.started_ms >= since_ms {
+            entry.1 += 1;
+        }
+        if session.ended_ms.is_none() {
+            entry.2 += 1;
+        }
+        entry.3 = entry.3.max(end);
+    }
+    let mut stats: Vec<AppStats> = apps
+        .into_iter()
+        .map(|(application, (playtime_ms, sessions, active, last))| AppStats {
+            application: application.to_string(),
+            playtime_secs: playtime_ms / 1000,
+            sessions,
+            active,
+            last_active: rfc3339(last),
+        })
+        .collect();
+    stats.sort_by(|a, b| b.playtime_secs.cmp(&a.playtime_secs).then_with(|| a.application.cmp(&b.application)));
+    stats
+}
+
+/// Statistics of the recorded sessions since `since_ms`
+pub fn stats(since_ms: u64) -> Vec<AppStats> {
+    summarize(SESSIONS.lock().unwrap().iter(), since_ms, crate::store::now_ms())
+}
+
+/// Start recording stream sessions from the events
+pub fn start_stream_stats() -> JoinHandle<()> {
+    let _ = TRACKING_SINCE.set(crate::store::now_ms());
+    let mut receiver = events::subscribe();
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => record(&mut SESSIONS.lock().unwrap(), &event, crate::store::now_ms()),
+                Err(broadcast::error::RecvError::Lagged(n)) => warn!("Stream statistics: {} event(s) dropped", n),
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::StreamInfo;
+
+    fn stream(id: u32, application: Option<&str>) -> StreamInfo {
+        StreamInfo {
+            id,
+            name: format!("stream{}", id),
+            application: application.map(|s| s.to_string()),
+            role: None,
+        }
+    }
+
+    #[test]
+    fn test_record_sessions() {
+        let mut sessions = VecDeque::new();
+        let active = StateEvent::StreamActive { stream: stream(70, Some("spotify")) };
+        record(&mut sessions, &active, 1_000);
+        // Repeated events do not start a second session
+        record(&mut sessions, &active, 2_000);
+        record(&mut sessions, &StateEvent::StreamIdle { stream: stream(70, Some("spotify")) }, 61_000);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].ended_ms, Some(61_000));
+
+        record(&mut sessions, &StateEvent::StreamActive { stream: stream(71, None) }, 100_000);
+        assert_eq!(sessions[1].application, "stream71");
+
+        // Sessions older than the longest window are dropped
+        let later = 61_000 + MAX_WINDOW.as_millis() as u64 + 1;
+        record(&mut sessions, &StateEvent::StreamActive { stream: stream(72, None) }, later);
+        assert_eq!(sessions.iter().map(|s| s.stream_id).collect::<Vec<_>>(), vec![71, 72]);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let session = |application: &str, started_ms, ended_ms| Session {
+            stream_id: 1,
+            application: application.to_string(),
+            started_ms,
+            ended_ms,
+        };
+        let sessions = vec![
+            // Started before the window: only the part in the window counts
+            session("spotify", 0, Some(120_000)),
+            session("spotify", 200_000, Some(260_000)),
+            session("firefox", 250_000, None),
+            session("mpd", 0, Some(50_000)),
+        ];
+        let stats = summarize(&sessions, 60_000, 400_000);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].application, "firefox");
+        assert_eq!((stats[0].playtime_secs, stats[0].sessions, stats[0].active), (150, 1, 1));
+        assert_eq!((stats[1].playtime_secs, stats[1].sessions, stats[1].active), (120, 1, 0));
+        assert_eq!(stats[1].last_active, "1970-01-01T00:04:20Z");
+    }
+}
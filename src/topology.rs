@@ -0,0 +1,212 @@
+//! Topology snapshots
+//!
+//! A topology describes nodes and the links between their ports by name, so
+//! it stays valid when object IDs change (e.g. after a restart). Applying a
+//! topology creates missing links and optionally removes links that are not
+//! part of it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::pwcli;
+use crate::pwlink::{self, PwLink};
+
+/// A node in a topology snapshot
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TopologyNode {
+    pub id: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A link between two ports, identified by "node_name:port_name"
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct TopologyLink {
+    pub output: String,
+    pub input: String,
+}
+
+/// A topology snapshot
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct Topology {
+    /// Nodes at export time; when applying with prune, their links are
+    /// managed as well, even if no link of the topology uses them
+    #[serde(default)]
+    pub nodes: Vec<TopologyNode>,
+    pub links: Vec<TopologyLink>,
+}
+
+/// Changes needed to reach a topology
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct TopologyDiff {
+    /// Links to create
+    pub create: Vec<TopologyLink>,
+    /// Existing links to remove (only when pruning)
+    pub remove: Vec<TopologyLink>,
+    /// Links that already exist
+    pub unchanged: usize,
+}
+
+/// Result of applying a topology
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ApplyResult {
+    pub created: Vec<TopologyLink>,
+    pub removed: Vec<TopologyLink>,
+    pub unchanged: usize,
+    pub errors: Vec<String>,
+}
+
+fn node_of(port_name: &str) -> &str {
+    port_name.split(':').next().unwrap_or(port_name)
+}
+
+/// Export the current audio topology
+pub fn export() -> Result<Topology, String> {
+    let nodes = pwcli::list_nodes()?
+        .iter()
+        .filter_map(|n| {
+            Some(TopologyNode {
+                id: n.id,
+                name: n.name()?.to_string(),
+                media_class: n.media_class().map(|s| s.to_string()),
+                description: n.description().map(|s| s.to_string()),
+            })
+        })
+        .collect();
+
+    let links = pwlink::list_links()?
+        .iter()
+        .map(|l| TopologyLink {
+            output: l.output_port_name.clone(),
+            input: l.input_port_name.clone(),
+        })
+        .collect();
+
+    Ok(Topology { nodes, links })
+}
+
+/// Compute the changes needed to turn `current` into `target`
+///
+/// With `prune`, existing links are removed if they connect to a node that is
+/// referenced by the target topology but are not part of it. Links between
+/// other nodes are never touched.
+pub fn diff(current: &[PwLink], target: &Topology, prune: bool) -> TopologyDiff {
+    let existing: HashSet<TopologyLink> = current
+        .iter()
+        .map(|l| TopologyLink {
+            output: l.output_port_name.clone(),
+            input: l.input_port_name.clone(),
+        })
+        .collect();
+    let wanted: HashSet<&TopologyLink> = target.links.iter().collect();
+
+    let mut result = TopologyDiff::default();
+    for link in &target.links {
+        if existing.contains(link) {
+            result.unchanged += 1;
+        } else if !result.create.contains(link) {
+            result.create.push(link.clone());
+        }
+    }
+
+    if prune {
+        let managed_nodes: HashSet<&str> = target
+            .links
+            .iter()
+            .flat_map(|l| [node_of(&l.output), node_of(&l.input)])
+            .chain(target.nodes.iter().map(|n| n.name.as_str()))
+            .collect();
+
+        for link in current {
+            let candidate = TopologyLink {
+                output: link.output_port_name.clone(),
+                input: link.input_port_name.clone(),
+            };
+            let touches_managed = managed_nodes.contains(node_of(&candidate.output))
+                || managed_nodes.contains(node_of(&candidate.input));
+            if touches_managed && !wanted.contains(&candidate) {
+                result.remove.push(candidate);
+            }
+        }
+    }
+
+    result
+}
+
+/// Apply a topology: create missing links and optionally prune extra ones
+pub fn apply(target: &Topology, prune: bool) -> Result<ApplyResult, String> {
+    let current = pwlink::list_links()?;
+    let changes = diff(&current, target, prune);
+
+    let mut result = ApplyResult {
+        unchanged: changes.unchanged,
+        ..Default::default()
+    };
+
+    for link in changes.remove {
+        match pwlink::remove_link_by_name(&link.output, &link.input) {
+            Ok(()) => result.removed.push(link),
+            Err(e) => result.errors.push(format!("{} -> {}: {}", link.output, link.input, e)),
+        }
+    }
+
+    for link in changes.create {
+        match pwlink::create_link(&link.output, &link.input) {
+            Ok(()) => result.created.push(link),
+            Err(e) => result.errors.push(format!("{} -> {}: {}", link.output, link.input, e)),
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pw_link(id: u32, output: &str, input: &str) -> PwLink {
+        PwLink {
+            id,
+            output_port_id: 0,
+            output_port_name: output.to_string(),
+            input_port_id: 0,
+            input_port_name: input.to_string(),
+        }
+    }
+
+    fn link(output: &str, input: &str) -> TopologyLink {
+        TopologyLink { output: output.to_string(), input: input.to_string() }
+    }
+
+    #[test]
+    fn test_diff_creates_missing_links() {
+        let current = vec![pw_link(1, "a:out_FL", "b:in_FL")];
+        let target = Topology {
+            nodes: vec![],
+            links: vec![link("a:out_FL", "b:in_FL"), link("a:out_FR", "b:in_FR")],
+        };
+        let d = diff(&current, &target, false);
+        assert_eq!(d.unchanged, 1);
+        assert_eq!(d.create, vec![link("a:out_FR", "b:in_FR")]);
+        assert!(d.remove.is_empty());
+    }
+
+    #[test]
+    fn test_diff_prunes_only_managed_nodes() {
+        let current = vec![
+            pw_link(1, "a:out_FL", "b:in_FL"),
+            pw_link(2, "a:out_FL", "c:in_FL"),
+            pw_link(3, "x:out_FL", "y:in_FL"),
+        ];
+        let target = Topology {
+            nodes: vec![],
+            links: vec![link("a:out_FL", "b:in_FL")],
+        };
+        let d = diff(&current, &target, true);
+        assert!(d.create.is_empty());
+        assert_eq!(d.remove, vec![link("a:out_FL", "c:in_FL")]);
+    }
+}
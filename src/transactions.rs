@@ -0,0 +1,356 @@
+//! Transactions: several changes applied together
+//!
+//! A transaction is a list of operations (volumes, SpeakerEQ bands and
+//! crossbar values, links). All operations are validated first: value
+//! ranges, nodes, ports and EQ bands must exist. Only then are they applied
+//! in order. Every applied step records its inverse change; if a step fails,
+//! the inverses of the steps already applied are run in reverse order, so
+//! the system is left as it was as far as possible.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::api_server::{ApiError, NodeState};
+use crate::parameters::ParameterValue;
+use crate::scenes::api_error_message;
+use crate::pwlink::{self, PortDirection};
+use crate::speakereq::{self, EqBand};
+
+/// A node given by ID or by node.name
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum NodeRef {
+    Id(u32),
+    Name(String),
+}
+
+/// An operation of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    /// Set the volume of a node (0.0 - 2.0)
+    Volume { node: NodeRef, volume: f32 },
+    /// Set a SpeakerEQ band
+    Eq {
+        block: String,
+        band: u32,
+        #[serde(flatten)]
+        settings: EqBand,
+    },
+    /// Set a SpeakerEQ crossbar value (0.0 - 2.0)
+    Crossbar { input: usize, output: usize, value: f32 },
+    /// Link two ports ("node:port")
+    Link { output: String, input: String },
+    /// Remove the link between two ports
+    Unlink { output: String, input: String },
+}
+
+impl Operation {
+    /// Name of the operation type
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Volume { .. } => "volume",
+            Operation::Eq { .. } => "eq",
+            Operation::Crossbar { .. } => "crossbar",
+            Operation::Link { .. } => "link",
+            Operation::Unlink { .. } => "unlink",
+        }
+    }
+
+    /// Check whether the operation changes SpeakerEQ parameters
+    pub fn uses_speakereq(&self) -> bool {
+        matches!(self, Operation::Eq { .. } | Operation::Crossbar { .. })
+    }
+}
+
+/// A validated change, ready to be applied
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum Change {
+    Volume { id: u32, volume: f32 },
+    /// Parameters of a module node (by node name of its state)
+    Params { node: String, params: HashMap<String, ParameterValue> },
+    Link { output: String, input: String },
+    Unlink { output: String, input: String },
+}
+
+/// Outcome of one operation
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TransactionStep {
+    pub index: usize,
+    pub op: String,
+    pub success: bool,
+    pub message: String,
+}
+
+/// Result of a transaction
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionResult {
+    pub success: bool,
+    /// Applied steps were undone because a later step failed
+    pub rolled_back: bool,
+    pub steps: Vec<TransactionStep>,
+    /// Errors while rolling back
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rollback_errors: Vec<String>,
+}
+
+fn check_range(name: &str, value: f32) -> Result<(), String> {
+    if (0.0..=2.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(format!("{} must be between 0.0 and 2.0", name))
+    }
+}
+
+/// Validates operations, reading SpeakerEQ parameters once
+struct Validator<'a> {
+    speakereq: &'a NodeState,
+    params: Option<HashMap<String, ParameterValue>>,
+}
+
+impl Validator<'_> {
+    fn speakereq_params(&mut self) -> Result<&HashMap<String, ParameterValue>, String> {
+        if self.params.is_none() {
+            self.params = Some(self.speakereq.get_params().map_err(api_error_message)?);
+        }
+        Ok(self.params.as_ref().unwrap())
+    }
+
+    fn validate(&mut self, op: &Operation) -> Result<Change, String> {
+        match op {
+            Operation::Volume { node, volume } => {
+                check_range("Volume", *volume)?;
+                let id = match node {
+                    NodeRef::Id(id) => {
+                        crate::wpctl::get_volume(*id)?;
+                        *id
+                    }
+                    NodeRef::Name(name) => crate::pwcli::find_node_by_name(name)?
+                        .map(|n| n.id)
+                        .ok_or_else(|| format!("Node '{}' not found", name))?,
+                };
+                Ok(Change::Volume { id, volume: *volume })
+            }
+            Operation::Eq { block, band, settings } => {
+                let params = self.speakereq_params()?;
+                if speakereq::read_eq_band(params, block, *band).is_none() {
+                    return Err(format!("EQ band {}/{} not found", block, band));
+                }
+                let prefix = speakereq::get_plugin_prefix(params);
+                let params = speakereq::eq_band_params(&prefix, block, *band, settings).map_err(api_error_message)?;
+                Ok(Change::Params { node: self.speakereq.node_name.clone(), params })
+            }
+            Operation::Crossbar { input, output, value } => {
+                check_range("Crossbar value", *value)?;
+                let params = self.speakereq_params()?;
+                let key = speakereq::pkey(
+                    &speakereq::get_plugin_prefix(params),
+                    &format!("xbar_{}_to_{}", input, output),
+                );
+                if !params.contains_key(&key) {
+                    return Err(format!("Crossbar {} -> {} not found", input, output));
+                }
+                Ok(Change::Params {
+                    node: self.speakereq.node_name.clone(),
+                    params: HashMap::from([(key, ParameterValue::Float(*value))]),
+                })
+            }
+            Operation::Link { output, input } => {
+                if pwlink::find_port_exact(PortDirection::Output, output)?.is_none() {
+                    return Err(format!("Output port '{}' not found", output));
+                }
+                if pwlink::find_port_exact(PortDirection::Input, input)?.is_none() {
+                    return Err(format!("Input port '{}' not found", input));
+                }
+                Ok(Change::Link { output: output.clone(), input: input.clone() })
+            }
+            Operation::Unlink { output, input } => {
+                if !pwlink::link_exists(output, input)? {
+                    return Err(format!("No link from '{}' to '{}'", output, input));
+                }
+                Ok(Change::Unlink { output: output.clone(), input: input.clone() })
+            }
+        }
+    }
+}
+
+/// Validate all operations, returning the changes or the errors of all invalid operations
+pub fn validate(ops: &[Operation], speakereq: &NodeState) -> Result<Vec<Change>, Vec<String>> {
+    let mut validator = Validator { speakereq, params: None };
+    let mut changes = Vec::with_capacity(ops.len());
+    let mut errors = Vec::new();
+    for (index, op) in ops.iter().enumerate() {
+        match validator.validate(op) {
+            Ok(change) => changes.push(change),
+            Err(e) => errors.push(format!("Operation {} ({}): {}", index, op.name(), e)),
+        }
+    }
+    if errors.is_empty() {
+        Ok(changes)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Apply a change, returning a description and the change that reverts it
+///
+/// Parameter changes are applied to the node of `nodes` with the node name
+/// of the change. No inverse is returned if nothing had to be changed.
+/// Changes are not recorded for undo.
+pub fn apply_change(change: &Change, nodes: &[Arc<NodeState>]) -> Result<(String, Option<Change>), String> {
+    match change {
+        Change::Volume { id, volume } => {
+            let previous = crate::wpctl::get_volume(*id)?.volume;
+            let volume = crate::wpctl::set_volume(*id, *volume)?;
+            Ok((
+                format!("Set volume of {} to {:.2}", id, volume),
+                Some(Change::Volume { id: *id, volume: previous }),
+            ))
+        }
+        Change::Params { node, params } => {
+            let state = nodes
+                .iter()
+                .find(|n| n.node_name == *node)
+                .ok_or_else(|| format!("Node '{}' is not available", node))?;
+            let previous = state.current_values(params.keys().cloned()).map_err(api_error_message)?;
+            state.set_parameters_untracked(params.clone()).map_err(api_error_message)?;
+            Ok((
+                format!("Set {} parameter(s)", params.len()),
+                Some(Change::Params { node: node.clone(), params: previous }),
+            ))
+        }
+        Change::Link { output, input } => {
+            if pwlink::link_exists(output, input)? {
+                return Ok((format!("{} -> {} already linked", output, input), None));
+            }
+            pwlink::create_link(output, input)?;
+            Ok((
+                format!("Linked {} -> {}", output, input),
+                Some(Change::Unlink { output: output.clone(), input: input.clone() }),
+            ))
+        }
+        Change::Unlink { output, input } => {
+            if !pwlink::link_exists(output, input)? {
+                return Ok((format!("{} -> {} not linked", output, input), None));
+            }
+            pwlink::remove_link_by_name(output, input)?;
+            Ok((
+                format!("Unlinked {} -> {}", output, input),
+                Some(Change::Link { output: output.clone(), input: input.clone() }),
+            ))
+        }
+    }
+}
+
+/// Validate and apply a transaction, rolling back on failure
+///
+/// Runs PipeWire commands, call on the worker pool. Returns
+/// `ApiError::BadRequest` if an operation is invalid; nothing is changed then.
+/// A successful transaction is recorded for undo as a whole.
+pub fn execute(ops: &[Operation], speakereq: &Arc<NodeState>) -> Result<TransactionResult, ApiError> {
+    if ops.is_empty() {
+        return Err(ApiError::BadRequest("Transaction has no operations".to_string()));
+    }
+    let changes = validate(ops, speakereq).map_err(|errors| ApiError::BadRequest(errors.join("; ")))?;
+
+    let mut steps = Vec::with_capacity(changes.len());
+    let mut undo = Vec::new();
+    let mut failed = false;
+    for (index, (op, change)) in ops.iter().zip(&changes).enumerate() {
+        let (success, message) = match apply_change(change, std::slice::from_ref(speakereq)) {
+            Ok((message, inverse)) => {
+                undo.extend(inverse);
+                (true, message)
+            }
+            Err(e) => {
+                failed = true;
+                (false, e)
+            }
+        };
+        steps.push(TransactionStep {
+            index,
+            op: op.name().to_string(),
+            success,
+            message,
+        });
+        if failed {
+            break;
+        }
+    }
+
+    let rolled_back = failed && !undo.is_empty();
+    let mut rollback_errors = Vec::new();
+    if failed {
+        warn!("Transaction failed, rolling back {} change(s)", undo.len());
+        for change in undo.iter().rev() {
+            if let Err(e) = apply_change(change, std::slice::from_ref(speakereq)) {
+                rollback_errors.push(e);
+            }
+        }
+    } else {
+        info!("Transaction with {} operation(s) applied", ops.len());
+        crate::undo::record(
+            format!("Transaction with {} operation(s)", ops.len()),
+            changes,
+            undo.into_iter().rev().collect(),
+        );
+    }
+
+    Ok(TransactionResult {
+        success: !failed,
+        rolled_back,
+        steps,
+        rollback_errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_operations() {
+        let json = r#"[
+            { "op": "volume", "node": 42, "volume": 0.5 },
+            { "op": "volume", "node": "alsa_output.hifiberry", "volume": 0.8 },
+            { "op": "eq", "block": "output_0", "band": 1, "type": "peaking", "frequency": 100.0, "q": 1.0, "gain": -3.0 },
+            { "op": "crossbar", "input": 0, "output": 1, "value": 0.5 },
+            { "op": "link", "output": "riaa:output_FL", "input": "speakereq2x2:input_FL" },
+            { "op": "unlink", "output": "riaa:output_FR", "input": "speakereq2x2:input_FR" }
+        ]"#;
+        let ops: Vec<Operation> = serde_json::from_str(json).unwrap();
+        let names: Vec<&str> = ops.iter().map(|op| op.name()).collect();
+        assert_eq!(names, vec!["volume", "volume", "eq", "crossbar", "link", "unlink"]);
+        assert!(matches!(&ops[0], Operation::Volume { node: NodeRef::Id(42), .. }));
+        assert!(matches!(&ops[1], Operation::Volume { node: NodeRef::Name(n), .. } if n == "alsa_output.hifiberry"));
+        match &ops[2] {
+            Operation::Eq { block, band, settings } => {
+                assert_eq!((block.as_str(), *band), ("output_0", 1));
+                assert_eq!(settings.gain, -3.0);
+                assert_eq!(settings.enabled, Some(true));
+            }
+            other => panic!("unexpected operation {:?}", other),
+        }
+        assert!(ops[3].uses_speakereq() && !ops[4].uses_speakereq());
+
+        assert!(serde_json::from_str::<Operation>(r#"{ "op": "mute", "node": 1 }"#).is_err());
+    }
+
+    #[test]
+    fn test_invalid_operations_are_rejected() {
+        let ops = vec![
+            Operation::Volume { node: NodeRef::Id(1), volume: 3.0 },
+            Operation::Crossbar { input: 0, output: 0, value: -1.0 },
+        ];
+        let node = Arc::new(NodeState::new("none".to_string()));
+        let errors = validate(&ops, &node).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("Operation 0 (volume): Volume must be"));
+        assert!(errors[1].starts_with("Operation 1 (crossbar): Crossbar value must be"));
+
+        assert!(matches!(execute(&[], &node), Err(ApiError::BadRequest(_))));
+    }
+}
@@ -0,0 +1,172 @@
+//! Undo and redo of DSP and volume changes
+//!
+//! Parameter changes of the DSP modules, volume changes and transactions
+//! made through the API are recorded with the changes that revert them.
+//! `POST /api/v1/undo` reverts the last change, `POST /api/v1/redo` applies
+//! it again, e.g. to take back a bad EQ tweak on a touchscreen. A new change
+//! clears the redo stack. Only the last [`HISTORY_SIZE`] changes are kept;
+//! the history is not persisted.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::info;
+
+use crate::api_server::NodeState;
+use crate::transactions::{self, Change};
+
+/// Number of changes that can be undone
+pub const HISTORY_SIZE: usize = 50;
+
+/// A recorded change
+#[derive(Debug, Clone, Serialize)]
+pub struct UndoEntry {
+    pub id: u64,
+    /// Time of the change (RFC 3339)
+    pub time: String,
+    pub description: String,
+    #[serde(skip)]
+    forward: Vec<Change>,
+    /// Applied in order to revert the change
+    #[serde(skip)]
+    inverse: Vec<Change>,
+}
+
+/// Undo and redo stacks
+#[derive(Debug)]
+pub struct UndoHistory {
+    next_id: u64,
+    undo: VecDeque<UndoEntry>,
+    redo: Vec<UndoEntry>,
+}
+
+/// Entries of the history, most recent first
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryStatus {
+    pub undo: Vec<UndoEntry>,
+    pub redo: Vec<UndoEntry>,
+}
+
+impl UndoHistory {
+    pub const fn new() -> Self {
+        Self {
+            next_id: 1,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Record a change and clear the redo stack
+    pub fn record(&mut self, description: String, forward: Vec<Change>, inverse: Vec<Change>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.undo.push_back(UndoEntry {
+            id,
+            time: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            description,
+            forward,
+            inverse,
+        });
+        while self.undo.len() > HISTORY_SIZE {
+            self.undo.pop_front();
+        }
+        self.redo.clear();
+        id
+    }
+
+    pub fn status(&self) -> HistoryStatus {
+        HistoryStatus {
+            undo: self.undo.iter().rev().cloned().collect(),
+            redo: self.redo.iter().rev().cloned().collect(),
+        }
+    }
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static HISTORY: Mutex<UndoHistory> = Mutex::new(UndoHistory::new());
+
+/// Record a change with the changes that revert it
+pub fn record(description: String, forward: Vec<Change>, inverse: Vec<Change>) {
+    if forward.is_empty() {
+        return;
+    }
+    HISTORY.lock().unwrap().record(description, forward, inverse);
+}
+
+/// Get the undo and redo stacks
+pub fn status() -> HistoryStatus {
+    HISTORY.lock().unwrap().status()
+}
+
+fn apply(changes: &[Change], nodes: &[Arc<NodeState>]) -> Result<(), String> {
+    for change in changes {
+        transactions::apply_change(change, nodes)?;
+    }
+    Ok(())
+}
+
+/// Revert the last change
+///
+/// Runs PipeWire commands, call on the worker pool. If reverting fails, the
+/// change stays on the undo stack.
+pub fn undo(nodes: &[Arc<NodeState>]) -> Result<UndoEntry, String> {
+    let entry = HISTORY.lock().unwrap().undo.pop_back().ok_or_else(|| "Nothing to undo".to_string())?;
+    if let Err(e) = apply(&entry.inverse, nodes) {
+        HISTORY.lock().unwrap().undo.push_back(entry);
+        return Err(format!("Failed to undo: {}", e));
+    }
+    info!("Undone: {}", entry.description);
+    HISTORY.lock().unwrap().redo.push(entry.clone());
+    Ok(entry)
+}
+
+/// Apply the last undone change again
+///
+/// Runs PipeWire commands, call on the worker pool. If applying fails, the
+/// change stays on the redo stack.
+pub fn redo(nodes: &[Arc<NodeState>]) -> Result<UndoEntry, String> {
+    let entry = HISTORY.lock().unwrap().redo.pop().ok_or_else(|| "Nothing to redo".to_string())?;
+    if let Err(e) = apply(&entry.forward, nodes) {
+        HISTORY.lock().unwrap().redo.push(entry);
+        return Err(format!("Failed to redo: {}", e));
+    }
+    info!("Redone: {}", entry.description);
+    HISTORY.lock().unwrap().undo.push_back(entry.clone());
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(volume: f32) -> Vec<Change> {
+        vec![Change::Volume { id: 1, volume }]
+    }
+
+    #[test]
+    fn test_history_is_bounded_and_clears_redo() {
+        let mut history = UndoHistory::new();
+        for i in 0..HISTORY_SIZE + 5 {
+            history.record(format!("change {}", i), volume(0.5), volume(0.4));
+        }
+        let status = history.status();
+        assert_eq!(status.undo.len(), HISTORY_SIZE);
+        assert_eq!(status.undo[0].description, format!("change {}", HISTORY_SIZE + 4));
+
+        let entry = history.undo.pop_back().unwrap();
+        history.redo.push(entry);
+        assert_eq!(history.status().redo.len(), 1);
+
+        history.record("new change".to_string(), volume(0.7), volume(0.5));
+        assert!(history.status().redo.is_empty());
+
+        // IDs start at 1 however the history is created
+        assert_eq!(UndoHistory::default().record("first".to_string(), volume(0.5), volume(0.4)), 1);
+    }
+}
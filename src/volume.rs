@@ -1,9 +1,50 @@
 use anyhow::Result;
 use regex::Regex;
 use std::collections::HashMap;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 use tracing::{debug, info, warn, error};
 
 use crate::config::VolumeRule;
+use crate::events::{self, StateEvent};
+use crate::pwcli::PwObject;
+
+/// Properties that make a volume rule a stream rule
+///
+/// Stream rules are also applied to streams that appear while the server
+/// runs, e.g. to start Spotify at 70% and browsers at 50%.
+pub const STREAM_RULE_KEYS: &[&str] = &["application.name", "media.role"];
+
+/// Save the current volumes of all nodes to the state store
+///
+/// Returns the number of saved volumes.
+pub fn save_all_volumes() -> Result<usize, String> {
+    // Get all current volumes
+    let volumes = crate::wpctl::list_volumes()
+        .map_err(|e| format!("Failed to list volumes: {}", e))?;
+
+    // Convert to state format
+    let states: Vec<crate::config::VolumeState> = volumes
+        .into_iter()
+        .map(|v| crate::config::VolumeState {
+            name: v.name,
+            volume: v.volume,
+        })
+        .collect();
+
+    // Save to the state store
+    let store = crate::store::get();
+    store
+        .save_volumes(&states)
+        .map_err(|e| format!("Failed to save volume state: {}", e))?;
+    let _ = store.record_event(&crate::store::AuditEvent::new(
+        "volume.save_all",
+        "",
+        format!("{} volume(s)", states.len()),
+    ));
+
+    Ok(states.len())
+}
 
 /// Apply volume rules on startup for both devices and sinks
 pub fn apply_volume_rules(rules: Vec<VolumeRule>) -> Result<()> {
@@ -18,10 +59,13 @@ pub fn apply_volume_rules(rules: Vec<VolumeRule>) -> Result<()> {
     let objects = crate::pwcli::list_objects(None)
         .map_err(|e| anyhow::anyhow!("Failed to list objects: {}", e))?;
     
-    // Load volume state file
-    let volume_state = crate::config::load_volume_state();
+    // Load saved volumes
+    let volume_state = crate::store::get().load_volumes().unwrap_or_else(|e| {
+        warn!("Failed to load saved volumes: {}", e);
+        HashMap::new()
+    });
     if !volume_state.is_empty() {
-        info!("Loaded {} volume(s) from state file", volume_state.len());
+        info!("Loaded {} saved volume(s)", volume_state.len());
     }
     
     // Apply rules to matching objects
@@ -99,13 +143,11 @@ pub fn apply_volume_rules(rules: Vec<VolumeRule>) -> Result<()> {
 
 /// Set volume using wpctl command
 fn set_volume_wpctl(id: u32, volume: f32) -> Result<()> {
-    use std::process::Command;
-    
     // wpctl expects volume as a percentage (0.0 to 1.0)
     // The volume value is already in this format
     let volume_str = format!("{:.4}", volume);
     
-    let output = Command::new("wpctl")
+    let output = crate::command::pipewire("wpctl")
         .args(["set-volume", &id.to_string(), &volume_str])
         .output()?;
     
@@ -116,3 +158,132 @@ fn set_volume_wpctl(id: u32, volume: f32) -> Result<()> {
     
     Ok(())
 }
+
+/// Whether a rule matches on stream properties (see [`STREAM_RULE_KEYS`])
+pub fn is_stream_rule(rule: &VolumeRule) -> bool {
+    rule.object.keys().any(|key| STREAM_RULE_KEYS.contains(&key.as_str()))
+}
+
+/// Whether all properties of a rule match; invalid patterns never match
+pub fn rule_matches(rule: &VolumeRule, properties: &HashMap<String, String>) -> bool {
+    rule.object
+        .iter()
+        .all(|(key, pattern)| properties.get(key).is_some_and(|value| crate::util::regex_match(pattern, value)))
+}
+
+/// First stream rule matching a stream node
+pub fn stream_rule<'a>(rules: &'a [VolumeRule], node: &PwObject) -> Option<&'a VolumeRule> {
+    if !node.media_class().is_some_and(|c| c.starts_with("Stream/")) {
+        return None;
+    }
+    rules
+        .iter()
+        .find(|rule| is_stream_rule(rule) && rule_matches(rule, &node.properties))
+}
+
+/// Apply the stream rules to streams when they appear
+///
+/// Driven by the `node_added` events of the event monitor. Returns `None`
+/// if there are no stream rules.
+pub fn start_stream_volume_rules(rules: Vec<VolumeRule>) -> Option<JoinHandle<()>> {
+    let rules: Vec<VolumeRule> = rules.into_iter().filter(is_stream_rule).collect();
+    if rules.is_empty() {
+        return None;
+    }
+    info!("Applying {} stream volume rule(s) to new streams", rules.len());
+
+    let rules = std::sync::Arc::new(rules);
+    let mut receiver = events::subscribe();
+    Some(tokio::spawn(async move {
+        loop {
+            let node = match receiver.recv().await {
+                Ok(StateEvent::NodeAdded { node }) => node,
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Stream volume rules: {} event(s) dropped", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if !node.media_class.as_deref().is_some_and(|c| c.starts_with("Stream/")) {
+                continue;
+            }
+            let rules = rules.clone();
+            let result = crate::worker::run(move || {
+                // The event has no properties, query the stream for them
+                let Some(stream) = crate::pwcli::query_object(node.id)? else {
+                    return Ok(None);
+                };
+                let Some(rule) = stream_rule(&rules, &stream) else {
+                    return Ok(None);
+                };
+                crate::wpctl::set_volume(stream.id, rule.volume)?;
+                Ok::<_, String>(Some((rule.name.clone(), node.name, rule.volume)))
+            })
+            .await;
+            match result {
+                Ok(Ok(Some((rule, stream, volume)))) => {
+                    info!("Volume rule '{}': set {} to {:.2}", rule, stream, volume)
+                }
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => warn!("Failed to apply stream volume rule: {}", e),
+                Err(e) => warn!("Failed to apply stream volume rule: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, object: &[(&str, &str)], volume: f32) -> VolumeRule {
+        VolumeRule {
+            name: name.to_string(),
+            object: object.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            volume,
+            use_state_file: false,
+            wait_for: None,
+        }
+    }
+
+    fn stream(properties: &[(&str, &str)]) -> PwObject {
+        PwObject::new(92, "Node", properties)
+    }
+
+    #[test]
+    fn test_stream_rules() {
+        let rules = vec![
+            rule("DAC", &[("device.name", "alsa_card")], 1.0),
+            rule("Spotify", &[("application.name", "^[Ss]potify$")], 0.7),
+            rule("Browser", &[("application.name", "Firefox|Chromium"), ("media.role", "Movie")], 0.5),
+            rule("Notifications", &[("media.role", "^Notification$")], 0.4),
+        ];
+        assert!(!is_stream_rule(&rules[0]));
+        assert!(is_stream_rule(&rules[1]));
+
+        let spotify = stream(&[("media.class", "Stream/Output/Audio"), ("application.name", "spotify")]);
+        assert_eq!(stream_rule(&rules, &spotify).map(|r| r.volume), Some(0.7));
+
+        // All properties of a rule must match
+        let firefox = stream(&[("media.class", "Stream/Output/Audio"), ("application.name", "Firefox")]);
+        assert!(stream_rule(&rules, &firefox).is_none());
+        let movie = stream(&[
+            ("media.class", "Stream/Output/Audio"),
+            ("application.name", "Firefox"),
+            ("media.role", "Movie"),
+        ]);
+        assert_eq!(stream_rule(&rules, &movie).map(|r| r.name.as_str()), Some("Browser"));
+
+        // Only streams
+        let sink = stream(&[("media.class", "Audio/Sink"), ("media.role", "Notification")]);
+        assert!(stream_rule(&rules, &sink).is_none());
+    }
+
+    #[test]
+    fn test_invalid_pattern_never_matches() {
+        let rules = vec![rule("Broken", &[("application.name", "(")], 0.5)];
+        let node = stream(&[("media.class", "Stream/Output/Audio"), ("application.name", "(")]);
+        assert!(stream_rule(&rules, &node).is_none());
+    }
+}
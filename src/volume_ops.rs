@@ -0,0 +1,179 @@
+//! Reading and writing volumes of nodes and device routes
+//!
+//! Nodes keep their volume in the `channelVolumes` and `mute` keys of the
+//! `Props` parameter, device routes in the `props` of their `Route`
+//! parameter (see [`crate::route_volume`]). The channel volumes are linear;
+//! the API uses the cubic scale of wpctl, so a volume of 0.5 is a channel
+//! volume of 0.125. The parsing and the parameter building are pure
+//! functions on the parameter JSON, the `read_*` and `write_*` functions
+//! run pw-cli (blocking).
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::pod_parser::ParamObject;
+use crate::pwparams;
+use crate::route_volume::{self, RouteVolume};
+
+/// Channels written when the current channel volumes are unknown
+pub const DEFAULT_CHANNELS: usize = 2;
+
+/// Highest volume on the wpctl scale
+pub const MAX_VOLUME: f32 = 2.0;
+
+/// Volume and mute of a node or route
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct VolumeState {
+    /// Volume on the wpctl scale (highest channel)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    /// Linear volume per channel
+    pub channel_volumes: Vec<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+}
+
+/// Convert a volume on the wpctl scale into a linear channel volume
+pub fn to_linear(volume: f32) -> f32 {
+    volume.powi(3)
+}
+
+/// Convert a linear channel volume to the wpctl scale
+pub fn to_cubic(channel_volume: f32) -> f32 {
+    channel_volume.cbrt()
+}
+
+/// Volume state in the properties of a `Props` parameter or of a route
+pub fn from_props(props: &Map<String, Value>) -> VolumeState {
+    let channel_volumes: Vec<f32> = props
+        .get("channelVolumes")
+        .and_then(|v| v.as_array())
+        .map(|v| v.iter().filter_map(|c| c.as_f64()).map(|c| c as f32).collect())
+        .unwrap_or_default();
+    VolumeState {
+        volume: channel_volumes.iter().copied().reduce(f32::max).map(to_cubic),
+        channel_volumes,
+        mute: props.get("mute").and_then(|m| m.as_bool()),
+    }
+}
+
+/// Volume state in the `Props` parameters of a node (a node may report
+/// several objects, the channel volumes and mute are taken from any)
+pub fn from_props_params(params: &[ParamObject]) -> VolumeState {
+    let mut state = params
+        .iter()
+        .find(|p| p.properties.contains_key("channelVolumes"))
+        .map(|p| from_props(&p.properties))
+        .unwrap_or_default();
+    if state.mute.is_none() {
+        state.mute = params.iter().find_map(|p| p.properties.get("mute")).and_then(|m| m.as_bool());
+    }
+    state
+}
+
+/// Build the props that set the volumes (wpctl scale) and mute
+///
+/// One volume is set on all channels, several are set per channel and must
+/// match the current number of channels. Volumes are clamped to 0.0 - 2.0.
+pub fn props_value(current: &VolumeState, volumes: &[f32], mute: Option<bool>) -> Result<Value, String> {
+    let channels = if current.channel_volumes.is_empty() {
+        volumes.len().max(DEFAULT_CHANNELS)
+    } else {
+        current.channel_volumes.len()
+    };
+    let linear = |v: f32| to_linear(v.clamp(0.0, MAX_VOLUME));
+    let mut props = Map::new();
+    match volumes {
+        [] => {}
+        [volume] => {
+            props.insert("channelVolumes".to_string(), json!(vec![linear(*volume); channels]));
+        }
+        volumes if volumes.len() == channels => {
+            props.insert("channelVolumes".to_string(), json!(volumes.iter().map(|v| linear(*v)).collect::<Vec<_>>()));
+        }
+        volumes => return Err(format!("Got {} channel volumes, but there are {} channels", volumes.len(), channels)),
+    }
+    if let Some(mute) = mute {
+        props.insert("mute".to_string(), json!(mute));
+    }
+    if props.is_empty() {
+        return Err("Neither a volume nor mute given".to_string());
+    }
+    Ok(Value::Object(props))
+}
+
+/// Read the volume of a node from its `Props`
+pub fn read_node_volume(id: u32) -> Result<VolumeState, String> {
+    pwparams::enum_params(id, "Props").map(|params| from_props_params(&params))
+}
+
+/// Set the volumes (wpctl scale, one or per channel) and mute of a node and
+/// return the new state
+pub fn write_node_volume(id: u32, volumes: &[f32], mute: Option<bool>) -> Result<VolumeState, String> {
+    let current = read_node_volume(id)?;
+    pwparams::set_param(id, "Props", &props_value(&current, volumes, mute)?)?;
+    read_node_volume(id)
+}
+
+/// Read a route of a device, None if the device has no such route
+pub fn read_device_volume(id: u32, index: u32) -> Result<Option<RouteVolume>, String> {
+    let enum_routes = pwparams::enum_params(id, "EnumRoute")?;
+    let routes = pwparams::enum_params(id, "Route")?;
+    Ok(route_volume::find_route(&enum_routes, &routes, index))
+}
+
+/// Set the volumes (wpctl scale, one or per channel) and mute of an active
+/// route and return the new state
+pub fn write_device_volume(id: u32, route: &RouteVolume, volumes: &[f32], mute: Option<bool>) -> Result<RouteVolume, String> {
+    pwparams::set_param(id, "Route", &route_volume::route_param(route, volumes, mute)?)?;
+    read_device_volume(id, route.index)?.ok_or_else(|| format!("Route {} disappeared", route.index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn props(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_from_props() {
+        let state = from_props(&props(json!({"channelVolumes": [0.125, 0.064], "mute": false})));
+        assert!((state.volume.unwrap() - 0.5).abs() < 1e-4);
+        assert_eq!(state.channel_volumes, vec![0.125, 0.064]);
+        assert_eq!(state.mute, Some(false));
+        assert_eq!(from_props(&Map::new()), VolumeState::default());
+
+        // Mute reported in another object than the channel volumes
+        let params = vec![
+            ParamObject { object_type: "Props".to_string(), id: "Props".to_string(), properties: props(json!({"mute": true})) },
+            ParamObject {
+                object_type: "Props".to_string(),
+                id: "Props".to_string(),
+                properties: props(json!({"channelVolumes": [1.0, 1.0, 1.0, 1.0]})),
+            },
+        ];
+        let state = from_props_params(&params);
+        assert_eq!((state.volume, state.channel_volumes.len(), state.mute), (Some(1.0), 4, Some(true)));
+    }
+
+    #[test]
+    fn test_props_value() {
+        let stereo = VolumeState { volume: Some(1.0), channel_volumes: vec![1.0, 1.0], mute: Some(false) };
+        assert_eq!(props_value(&stereo, &[0.5], None).unwrap(), json!({"channelVolumes": [0.125, 0.125]}));
+        assert_eq!(
+            props_value(&stereo, &[0.5, 1.0], Some(true)).unwrap(),
+            json!({"channelVolumes": [0.125, 1.0], "mute": true})
+        );
+        assert_eq!(props_value(&stereo, &[], Some(true)).unwrap(), json!({"mute": true}));
+        // Clamped to the range of wpctl
+        assert_eq!(props_value(&stereo, &[3.0], None).unwrap(), json!({"channelVolumes": [8.0, 8.0]}));
+        assert!(props_value(&stereo, &[0.5, 0.5, 0.5], None).is_err());
+        assert!(props_value(&stereo, &[], None).is_err());
+        // Unknown channels: one volume on two channels, or as given
+        let unknown = VolumeState::default();
+        assert_eq!(props_value(&unknown, &[1.0], None).unwrap(), json!({"channelVolumes": [1.0, 1.0]}));
+        assert_eq!(props_value(&unknown, &[1.0, 1.0, 1.0], None).unwrap()["channelVolumes"], json!([1.0, 1.0, 1.0]));
+    }
+}
@@ -0,0 +1,306 @@
+//! Watching a single PipeWire object for changes
+//!
+//! pw-cli has no way to subscribe to object events, so the object is polled:
+//! its properties (and its main parameters for nodes and devices) are read
+//! periodically and compared with the previous state. Only differences are
+//! reported as [`WatchEvent`]s.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::pod_parser::ParamObject;
+use crate::pwcli::{self, PwObject};
+use crate::pwparams;
+
+/// Default polling interval
+pub const DEFAULT_INTERVAL_MS: u64 = 1000;
+
+/// Shortest allowed polling interval
+pub const MIN_INTERVAL_MS: u64 = 100;
+
+/// Properties that changed between two polls
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct PropertyChanges {
+    /// Added or modified properties with their new values
+    pub changed: HashMap<String, String>,
+    /// Keys of removed properties
+    pub removed: Vec<String>,
+}
+
+impl PropertyChanges {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// An event of a watched object
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WatchEvent {
+    /// Initial state of the object
+    Snapshot {
+        id: u32,
+        properties: HashMap<String, String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        params: Option<WatchedParams>,
+    },
+    /// Properties changed
+    Properties { id: u32, changes: PropertyChanges },
+    /// Parameters changed
+    Params { id: u32, params: WatchedParams },
+    /// The object was removed
+    Removed { id: u32 },
+    /// Reading the object failed
+    Error { id: u32, message: String },
+}
+
+impl WatchEvent {
+    /// Event name used for SSE
+    pub fn name(&self) -> &'static str {
+        match self {
+            WatchEvent::Snapshot { .. } => "snapshot",
+            WatchEvent::Properties { .. } => "properties",
+            WatchEvent::Params { .. } => "params",
+            WatchEvent::Removed { .. } => "removed",
+            WatchEvent::Error { .. } => "error",
+        }
+    }
+}
+
+/// Parameters of a watched object
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct WatchedParams {
+    #[serde(rename = "type")]
+    pub param_type: String,
+    pub values: Vec<ParamObject>,
+}
+
+/// Compare two property maps
+pub fn diff_properties(old: &HashMap<String, String>, new: &HashMap<String, String>) -> PropertyChanges {
+    let changed = new
+        .iter()
+        .filter(|(k, v)| old.get(*k) != Some(*v))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let mut removed: Vec<String> = old.keys().filter(|k| !new.contains_key(*k)).cloned().collect();
+    removed.sort();
+    PropertyChanges { changed, removed }
+}
+
+/// A parameter value that changed between two polls
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamChange {
+    /// Dotted key, prefixed with the object index if there are several
+    /// parameter objects, e.g. `volume` or `1.props.mute`
+    pub key: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+fn flatten_value(prefix: String, value: &Value, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                flatten_value(format!("{}.{}", prefix, k), v, out);
+            }
+        }
+        _ => out.push((prefix, value.clone())),
+    }
+}
+
+fn flatten_params(params: &[ParamObject]) -> Vec<(String, Value)> {
+    let mut out = Vec::new();
+    for (i, obj) in params.iter().enumerate() {
+        for (k, v) in &obj.properties {
+            let key = if params.len() > 1 { format!("{}.{}", i, k) } else { k.clone() };
+            flatten_value(key, v, &mut out);
+        }
+    }
+    out
+}
+
+/// Compare two sets of parameter objects value by value
+///
+/// Nested objects (e.g. the `props` of a Route) are compared per field.
+pub fn diff_params(old: &[ParamObject], new: &[ParamObject]) -> Vec<ParamChange> {
+    let old: HashMap<String, Value> = flatten_params(old).into_iter().collect();
+    let new = flatten_params(new);
+
+    let mut changes: Vec<ParamChange> = new
+        .iter()
+        .filter(|(k, v)| old.get(k) != Some(v))
+        .map(|(k, v)| ParamChange {
+            key: k.clone(),
+            old: old.get(k).cloned(),
+            new: Some(v.clone()),
+        })
+        .collect();
+    let new_keys: std::collections::HashSet<&String> = new.iter().map(|(k, _)| k).collect();
+    changes.extend(old.iter().filter(|(k, _)| !new_keys.contains(k)).map(|(k, v)| ParamChange {
+        key: k.clone(),
+        old: Some(v.clone()),
+        new: None,
+    }));
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+    changes
+}
+
+/// Parameter type that is watched for an object type
+pub fn watched_param_type(obj: &PwObject) -> Option<&'static str> {
+    match pwcli::simplify_type(&obj.object_type) {
+        "node" => Some("Props"),
+        "device" => Some("Route"),
+        _ => None,
+    }
+}
+
+fn read_params(obj: &PwObject) -> Option<WatchedParams> {
+    let param_type = watched_param_type(obj)?;
+    // Not all objects support enumerating parameters, treat failures as no params
+    let values = pwparams::enum_params(obj.id, param_type).ok()?;
+    Some(WatchedParams {
+        param_type: param_type.to_string(),
+        values,
+    })
+}
+
+/// Read the current state of an object
+fn poll(id: u32) -> Result<Option<(PwObject, Option<WatchedParams>)>, String> {
+    let obj = match pwcli::query_object(id)? {
+        Some(obj) => obj,
+        None => return Ok(None),
+    };
+    let params = read_params(&obj);
+    Ok(Some((obj, params)))
+}
+
+/// Start watching an object
+///
+/// Events are sent to the returned channel until the object disappears or
+/// the receiver is dropped. Returns an error if the object does not exist.
+pub async fn watch_object(id: u32, interval: Duration) -> Result<mpsc::Receiver<WatchEvent>, String> {
+    let interval = interval.max(Duration::from_millis(MIN_INTERVAL_MS));
+    let (obj, params) = crate::worker::run(move || poll(id))
+        .await
+        .map_err(|e| e.to_string())??
+        .ok_or_else(|| format!("Object {} not found", id))?;
+
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        let mut properties = obj.properties;
+        let mut params = params;
+        let snapshot = WatchEvent::Snapshot {
+            id,
+            properties: properties.clone(),
+            params: params.clone(),
+        };
+        if tx.send(snapshot).await.is_err() {
+            return;
+        }
+
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = tx.closed() => return,
+            }
+
+            let events = match crate::worker::run(move || poll(id)).await {
+                Ok(Ok(Some((obj, new_params)))) => {
+                    let mut events = Vec::new();
+                    let changes = diff_properties(&properties, &obj.properties);
+                    if !changes.is_empty() {
+                        events.push(WatchEvent::Properties { id, changes });
+                    }
+                    if new_params != params {
+                        if let Some(ref p) = new_params {
+                            events.push(WatchEvent::Params { id, params: p.clone() });
+                        }
+                    }
+                    properties = obj.properties;
+                    params = new_params;
+                    events
+                }
+                Ok(Ok(None)) => {
+                    let _ = tx.send(WatchEvent::Removed { id }).await;
+                    return;
+                }
+                Ok(Err(message)) => vec![WatchEvent::Error { id, message }],
+                Err(e) => vec![WatchEvent::Error { id, message: e.to_string() }],
+            };
+
+            for event in events {
+                if tx.send(event).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    Ok(rx)
+}
+
+/// Serialize an event to JSON, without the `event` tag
+pub fn event_data(event: &WatchEvent) -> Value {
+    let mut value = serde_json::to_value(event).unwrap_or(Value::Null);
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("event");
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn props(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_diff_properties() {
+        let old = props(&[("node.name", "sink"), ("node.latency", "256/48000"), ("node.nick", "DAC")]);
+        let new = props(&[("node.name", "sink"), ("node.latency", "1024/48000"), ("media.role", "Music")]);
+        let changes = diff_properties(&old, &new);
+        assert_eq!(changes.changed, props(&[("node.latency", "1024/48000"), ("media.role", "Music")]));
+        assert_eq!(changes.removed, vec!["node.nick".to_string()]);
+        assert!(diff_properties(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_diff_params() {
+        let route = |volume: f64, mute: bool| ParamObject {
+            object_type: "Route".to_string(),
+            id: "Route".to_string(),
+            properties: serde_json::json!({"index": 1, "props": {"volume": volume, "mute": mute}})
+                .as_object()
+                .unwrap()
+                .clone(),
+        };
+        let changes = diff_params(&[route(0.5, false)], &[route(0.25, false)]);
+        assert_eq!(
+            changes,
+            vec![ParamChange {
+                key: "props.volume".to_string(),
+                old: Some(serde_json::json!(0.5)),
+                new: Some(serde_json::json!(0.25)),
+            }]
+        );
+
+        let changes = diff_params(&[route(0.5, false)], &[route(0.5, false), route(0.5, true)]);
+        assert!(changes.iter().any(|c| c.key == "1.props.mute" && c.old.is_none()));
+        assert!(changes.iter().any(|c| c.key == "index" && c.new.is_none()));
+        assert!(diff_params(&[route(0.5, false)], &[route(0.5, false)]).is_empty());
+    }
+
+    #[test]
+    fn test_event_data() {
+        let event = WatchEvent::Removed { id: 42 };
+        assert_eq!(event.name(), "removed");
+        assert_eq!(event_data(&event), serde_json::json!({"id": 42}));
+    }
+}
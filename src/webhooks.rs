@@ -0,0 +1,174 @@
+//! Webhooks for state changes
+//!
+//! Each webhook from `webhooks.conf` is a URL that receives an HTTP POST with
+//! the event as JSON whenever a matching [`StateEvent`] is published, so
+//! external systems are notified without keeping a connection open.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::events::{self, StateEvent};
+
+/// Timeout of a webhook request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Webhook definition from webhooks.conf
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookConfig {
+    /// URL that receives the events (http or https)
+    pub url: String,
+    /// Events to send, e.g. ["node_added", "volume_changed"] (default: all)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+    /// Regex on the node or module name the event is about
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+}
+
+/// A webhook with its compiled filter
+struct Webhook {
+    config: WebhookConfig,
+    node: Option<Regex>,
+}
+
+impl Webhook {
+    fn new(config: WebhookConfig) -> Result<Self, String> {
+        for event in &config.events {
+            if !events::EVENT_NAMES.contains(&event.as_str()) {
+                return Err(format!(
+                    "Unknown event '{}', use one of {}",
+                    event,
+                    events::EVENT_NAMES.join(", ")
+                ));
+            }
+        }
+        let node = match &config.node {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| format!("Invalid node regex '{}': {}", pattern, e))?),
+            None => None,
+        };
+        Ok(Self { config, node })
+    }
+
+    fn matches(&self, event: &StateEvent) -> bool {
+        (self.config.events.is_empty() || self.config.events.iter().any(|e| e == event.name()))
+            && self.node.as_ref().map(|re| re.is_match(event.subject())).unwrap_or(true)
+    }
+}
+
+/// JSON body sent for an event
+pub fn payload(event: &StateEvent) -> serde_json::Value {
+    let mut value = serde_json::to_value(event).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        object.insert("timestamp_ms".to_string(), crate::store::now_ms().into());
+    }
+    value
+}
+
+/// Check whether a webhook would receive an event
+pub fn matches(config: &WebhookConfig, event: &StateEvent) -> Result<bool, String> {
+    Ok(Webhook::new(config.clone())?.matches(event))
+}
+
+/// Start sending events to the webhooks
+pub fn start_webhooks(configs: Vec<WebhookConfig>) -> Option<JoinHandle<()>> {
+    let hooks: Vec<Webhook> = configs
+        .into_iter()
+        .filter_map(|config| match Webhook::new(config.clone()) {
+            Ok(hook) => Some(hook),
+            Err(e) => {
+                warn!("Ignoring webhook {}: {}", config.url, e);
+                None
+            }
+        })
+        .collect();
+    if hooks.is_empty() {
+        return None;
+    }
+
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Failed to create webhook client: {}", e);
+            return None;
+        }
+    };
+
+    let mut receiver = events::subscribe();
+    Some(tokio::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Webhooks: {} event(s) dropped", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let body = payload(&event);
+            for hook in hooks.iter().filter(|h| h.matches(&event)) {
+                let request = client.post(&hook.config.url).json(&body);
+                let url = hook.config.url.clone();
+                let name = event.name();
+                // Deliver in the background so a slow receiver does not delay others
+                tokio::spawn(async move {
+                    match request.send().await {
+                        Ok(response) if response.status().is_success() => debug!("Webhook {}: sent {}", url, name),
+                        Ok(response) => warn!("Webhook {}: {} returned {}", url, name, response.status()),
+                        Err(e) => warn!("Webhook {}: {}", url, e),
+                    }
+                });
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume_event(name: &str) -> StateEvent {
+        StateEvent::VolumeChanged {
+            id: 5,
+            name: name.to_string(),
+            volume: 0.5,
+            previous: 0.4,
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let all = WebhookConfig {
+            url: "http://localhost/hook".to_string(),
+            events: vec![],
+            node: None,
+        };
+        assert!(matches(&all, &volume_event("Speakers")).unwrap());
+
+        let filtered = WebhookConfig {
+            events: vec!["volume_changed".to_string()],
+            node: Some("^HDMI".to_string()),
+            ..all.clone()
+        };
+        assert!(matches(&filtered, &volume_event("HDMI 1")).unwrap());
+        assert!(!matches(&filtered, &volume_event("Speakers")).unwrap());
+
+        let unknown = WebhookConfig {
+            events: vec!["volume".to_string()],
+            ..all
+        };
+        assert!(matches(&unknown, &volume_event("Speakers")).is_err());
+    }
+
+    #[test]
+    fn test_payload() {
+        let body = payload(&volume_event("Speakers"));
+        assert_eq!(body["event"], "volume_changed");
+        assert_eq!(body["name"], "Speakers");
+        assert!(body["timestamp_ms"].as_u64().unwrap() > 0);
+    }
+}
@@ -0,0 +1,161 @@
+//! Dedicated worker threads for PipeWire calls
+//!
+//! All PipeWire access runs external tools and waits for them, which blocks
+//! the calling thread. Handlers hand these calls to a small pool of worker
+//! threads via [`run`] and await the result, so the tokio runtime threads
+//! stay free to serve other requests. Jobs run with the PipeWire remote and
+//! the backend of the caller (see [`crate::remote`] and [`crate::backend`]).
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use tokio::sync::oneshot;
+use tracing::{debug, error};
+
+/// Default number of worker threads
+pub const DEFAULT_WORKERS: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Error returned when a job could not be completed by the pool
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkerError {
+    /// The job panicked
+    Panicked,
+    /// The pool has shut down
+    Closed,
+}
+
+impl fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkerError::Panicked => write!(f, "worker job panicked"),
+            WorkerError::Closed => write!(f, "worker pool closed"),
+        }
+    }
+}
+
+impl std::error::Error for WorkerError {}
+
+/// A fixed-size pool of threads executing blocking jobs
+pub struct WorkerPool {
+    sender: mpsc::Sender<Job>,
+    size: usize,
+}
+
+impl WorkerPool {
+    /// Start a pool with the given number of threads (at least one)
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..size {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("pw-worker-{}", i))
+                .spawn(move || loop {
+                    // Only hold the lock while waiting for the next job
+                    let job = receiver.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                })
+                .expect("Failed to spawn PipeWire worker thread");
+        }
+
+        debug!("Started PipeWire worker pool with {} threads", size);
+        Self { sender, size }
+    }
+
+    /// Number of worker threads
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Run a blocking function on the pool and await its result
+    pub async fn run<F, T>(&self, f: F) -> Result<T, WorkerError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        // The job connects to the PipeWire instance of the caller, with its backend
+        let remote = crate::remote::current();
+        let backend = crate::backend::scoped();
+        let job: Job = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                crate::backend::run_with(backend, || crate::remote::run_with(remote, f))
+            }));
+            if result.is_err() {
+                error!("PipeWire worker job panicked");
+            }
+            let _ = tx.send(result);
+        });
+
+        self.sender.send(job).map_err(|_| WorkerError::Closed)?;
+
+        match rx.await {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(_)) => Err(WorkerError::Panicked),
+            Err(_) => Err(WorkerError::Closed),
+        }
+    }
+}
+
+static POOL: OnceLock<WorkerPool> = OnceLock::new();
+
+/// Initialize the global pool with a number of threads
+///
+/// Has no effect if the pool is already running.
+pub fn init(size: usize) {
+    let _ = POOL.get_or_init(|| WorkerPool::new(size));
+}
+
+/// Get the global pool, starting it with [`DEFAULT_WORKERS`] threads if needed
+pub fn pool() -> &'static WorkerPool {
+    POOL.get_or_init(|| WorkerPool::new(DEFAULT_WORKERS))
+}
+
+/// Run a blocking PipeWire call on the global worker pool
+pub async fn run<F, T>(f: F) -> Result<T, WorkerError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    pool().run(f).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_run_returns_result() {
+        let pool = WorkerPool::new(2);
+        assert_eq!(pool.run(|| 21 * 2).await, Ok(42));
+    }
+
+    #[tokio::test]
+    async fn test_jobs_run_concurrently() {
+        let pool = WorkerPool::new(2);
+        let start = Instant::now();
+        let slow = || thread::sleep(Duration::from_millis(200));
+        let (a, b) = tokio::join!(pool.run(slow), pool.run(slow));
+        assert!(a.is_ok() && b.is_ok());
+        assert!(start.elapsed() < Duration::from_millis(390));
+    }
+
+    #[tokio::test]
+    async fn test_panic_is_reported() {
+        let pool = WorkerPool::new(1);
+        let result: Result<(), _> = pool.run(|| panic!("boom")).await;
+        assert_eq!(result, Err(WorkerError::Panicked));
+        // The thread survives the panic
+        assert_eq!(pool.run(|| 1).await, Ok(1));
+    }
+}
@@ -2,8 +2,11 @@
 //! 
 //! This module provides volume control by wrapping wpctl commands,
 //! which is simpler and more reliable than direct PipeWire API calls.
+//!
+//! Without a PipeWire daemon (e.g. the in-memory backend of `--demo`, see
+//! [`crate::backend`]) the volumes are read from and written to the `Props`
+//! of the nodes of the backend instead.
 
-use std::process::Command;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
@@ -22,7 +25,10 @@ pub struct VolumeInfo {
 ///   81. Built-in Audio Stereo               [vol: 0.50]
 ///   38. effect_input.proc                   [vol: 1.00]
 pub fn list_volumes() -> Result<Vec<VolumeInfo>, String> {
-    let output = Command::new("wpctl")
+    if !crate::backend::current().is_live() {
+        return offline::list_volumes();
+    }
+    let output = crate::command::pipewire("wpctl")
         .arg("status")
         .output()
         .map_err(|e| format!("Failed to run wpctl status: {}", e))?;
@@ -91,8 +97,11 @@ fn parse_wpctl_status(status: &str) -> Result<Vec<VolumeInfo>, String> {
 
 /// Get volume for a specific object by ID
 pub fn get_volume(id: u32) -> Result<VolumeInfo, String> {
+    if !crate::backend::current().is_live() {
+        return offline::get_volume(id);
+    }
     // First get the volume value
-    let output = Command::new("wpctl")
+    let output = crate::command::pipewire("wpctl")
         .args(["get-volume", &id.to_string()])
         .output()
         .map_err(|e| format!("Failed to run wpctl get-volume: {}", e))?;
@@ -140,7 +149,7 @@ fn parse_volume_output(output: &str) -> Result<f32, String> {
 
 /// Get object name and type from wpctl status
 fn get_object_info(id: u32) -> Result<(String, String), String> {
-    let output = Command::new("wpctl")
+    let output = crate::command::pipewire("wpctl")
         .arg("status")
         .output()
         .map_err(|e| format!("Failed to run wpctl status: {}", e))?;
@@ -179,9 +188,12 @@ fn get_object_info(id: u32) -> Result<(String, String), String> {
 /// Set volume for a specific object by ID
 pub fn set_volume(id: u32, volume: f32) -> Result<f32, String> {
     // Clamp volume to reasonable range (0.0 to 2.0)
-    let volume = volume.max(0.0).min(2.0);
+    let volume = volume.clamp(0.0, 2.0);
+    if !crate::backend::current().is_live() {
+        return offline::set_volume(id, Some(volume), None).map(|_| volume);
+    }
     
-    let output = Command::new("wpctl")
+    let output = crate::command::pipewire("wpctl")
         .args(["set-volume", &id.to_string(), &format!("{:.2}", volume)])
         .output()
         .map_err(|e| format!("Failed to run wpctl set-volume: {}", e))?;
@@ -201,6 +213,30 @@ pub fn set_volume(id: u32, volume: f32) -> Result<f32, String> {
     Ok(volume)
 }
 
+/// Mute or unmute a specific object by ID
+pub fn set_mute(id: u32, mute: bool) -> Result<(), String> {
+    if !crate::backend::current().is_live() {
+        return offline::set_volume(id, None, Some(mute));
+    }
+    let output = crate::command::pipewire("wpctl")
+        .args(["set-mute", &id.to_string(), if mute { "1" } else { "0" }])
+        .output()
+        .map_err(|e| format!("Failed to run wpctl set-mute: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    if stdout.contains("not found") || stderr.contains("not found") {
+        return Err(format!("Object {} not found", id));
+    }
+
+    if !output.status.success() {
+        return Err(format!("wpctl set-mute failed: {}", stderr));
+    }
+
+    Ok(())
+}
+
 /// Information about a default audio node
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefaultNodeInfo {
@@ -222,7 +258,11 @@ pub fn get_default_source() -> Result<DefaultNodeInfo, String> {
 
 /// Get information about a default node using wpctl inspect
 fn get_default_node(selector: &str) -> Result<DefaultNodeInfo, String> {
-    let output = Command::new("wpctl")
+    if !crate::backend::current().is_live() {
+        let class = if selector.contains("SINK") { "Audio/Sink" } else { "Audio/Source" };
+        return offline::default_node(class);
+    }
+    let output = crate::command::pipewire("wpctl")
         .args(["inspect", selector])
         .output()
         .map_err(|e| format!("Failed to run wpctl inspect: {}", e))?;
@@ -277,6 +317,74 @@ fn parse_wpctl_inspect(output: &str) -> Result<DefaultNodeInfo, String> {
     })
 }
 
+/// Volumes of the nodes of a backend without PipeWire daemon
+mod offline {
+    use super::{DefaultNodeInfo, VolumeInfo};
+    use crate::pwcli::{self, PwObject};
+    use crate::volume_ops;
+
+    /// Section of wpctl status the node is listed in
+    fn section(node: &PwObject) -> &'static str {
+        let class = node.media_class().unwrap_or_default();
+        if class.starts_with("Stream/") {
+            "stream"
+        } else if node.get("node.link-group").is_some() {
+            "filter"
+        } else if class.ends_with("/Sink") {
+            "sink"
+        } else if class.ends_with("/Source") {
+            "source"
+        } else {
+            "unknown"
+        }
+    }
+
+    fn info(node: &PwObject) -> Result<Option<VolumeInfo>, String> {
+        let state = volume_ops::read_node_volume(node.id)?;
+        Ok(state.volume.map(|volume| VolumeInfo {
+            id: node.id,
+            name: node.get("node.description").or(node.name()).unwrap_or_default().to_string(),
+            object_type: section(node).to_string(),
+            volume: (volume * 100.0).round() / 100.0,
+        }))
+    }
+
+    pub fn list_volumes() -> Result<Vec<VolumeInfo>, String> {
+        let mut volumes = Vec::new();
+        for node in pwcli::list_nodes()? {
+            volumes.extend(info(&node)?);
+        }
+        Ok(volumes)
+    }
+
+    pub fn get_volume(id: u32) -> Result<VolumeInfo, String> {
+        let node = pwcli::get_object(id)?.ok_or_else(|| format!("Object {} not found", id))?;
+        info(&node)?.ok_or_else(|| format!("Object {} has no volume", id))
+    }
+
+    pub fn set_volume(id: u32, volume: Option<f32>, mute: Option<bool>) -> Result<(), String> {
+        pwcli::get_object(id)?.ok_or_else(|| format!("Object {} not found", id))?;
+        let volumes: Vec<f32> = volume.into_iter().collect();
+        volume_ops::write_node_volume(id, &volumes, mute).map(|_| ())
+    }
+
+    /// The node of a class with the highest priority.session, like WirePlumber chooses it
+    pub fn default_node(class: &str) -> Result<DefaultNodeInfo, String> {
+        let priority = |n: &PwObject| n.get("priority.session").and_then(|p| p.parse::<i64>().ok()).unwrap_or(0);
+        let node = pwcli::list_nodes()?
+            .into_iter()
+            .filter(|n| n.media_class() == Some(class))
+            .max_by_key(priority)
+            .ok_or_else(|| format!("No {} node", class))?;
+        Ok(DefaultNodeInfo {
+            id: node.id,
+            name: node.name().unwrap_or_default().to_string(),
+            description: node.get("node.description").map(|s| s.to_string()),
+            media_class: node.media_class().map(|s| s.to_string()),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
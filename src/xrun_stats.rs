@@ -0,0 +1,264 @@
+//! xrun and quantum statistics
+//!
+//! Samples the profiler data with pw-top (see [`crate::pwtop`]) every
+//! `sample_secs` of the `[stats]` section and keeps, per node name, the xruns
+//! counted since the server started and, per driver, the changes of its
+//! quantum and rate. Crackling on an underpowered Pi shows up as xruns of the
+//! sink's driver, often together with a small quantum.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::pwtop::{self, TopNode};
+
+/// Quantum and rate changes kept
+pub const HISTORY_CAPACITY: usize = 500;
+
+/// xruns of a node
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct NodeXruns {
+    pub id: u32,
+    pub name: String,
+    pub state: String,
+    /// ID of the driver for followers, None for drivers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver_id: Option<u32>,
+    pub quantum: u32,
+    pub rate: u32,
+    /// Errors counted by the profiler since the node was created
+    pub errors: u64,
+    /// xruns since the server started
+    pub xruns: u64,
+    /// Time of the last xrun (RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_xrun: Option<String>,
+}
+
+/// A driver running with a new quantum or rate
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct QuantumChange {
+    /// RFC 3339 time
+    pub time: String,
+    pub timestamp_ms: u64,
+    pub driver: String,
+    pub quantum: u32,
+    pub rate: u32,
+}
+
+/// Collected statistics
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct XrunReport {
+    pub enabled: bool,
+    pub sample_secs: u64,
+    /// Time of the last sample (RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampled: Option<String>,
+    /// Error of the last sample
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// xruns of all nodes since the server started
+    pub total_xruns: u64,
+    pub nodes: Vec<NodeXruns>,
+    /// Quantum and rate changes of the drivers, oldest first
+    pub history: Vec<QuantumChange>,
+}
+
+#[derive(Debug, Default)]
+struct Counter {
+    errors: u64,
+    xruns: u64,
+    last_xrun_ms: Option<u64>,
+}
+
+/// State of the collector
+#[derive(Debug, Default)]
+pub struct XrunState {
+    sample_secs: u64,
+    nodes: Vec<TopNode>,
+    counters: HashMap<String, Counter>,
+    current: HashMap<String, (u32, u32)>,
+    history: VecDeque<QuantumChange>,
+    sampled_ms: Option<u64>,
+    error: Option<String>,
+}
+
+fn rfc3339(ms: u64) -> String {
+    humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_millis(ms)).to_string()
+}
+
+impl XrunState {
+    /// Add a sample taken at `now_ms`
+    pub fn update(&mut self, nodes: Vec<TopNode>, now_ms: u64) {
+        for node in &nodes {
+            match self.counters.get_mut(&node.name) {
+                // Errors before the server started are not counted
+                None => {
+                    self.counters.insert(node.name.clone(), Counter { errors: node.errors, ..Default::default() });
+                }
+                Some(counter) => {
+                    // A lower count means the node was recreated
+                    let new = if node.errors >= counter.errors { node.errors - counter.errors } else { node.errors };
+                    if new > 0 {
+                        counter.xruns += new;
+                        counter.last_xrun_ms = Some(now_ms);
+                    }
+                    counter.errors = node.errors;
+                }
+            }
+            if node.is_driver() && node.state == "R" && node.quantum > 0 {
+                let setting = (node.quantum, node.rate);
+                if self.current.insert(node.name.clone(), setting) != Some(setting) {
+                    if self.history.len() == HISTORY_CAPACITY {
+                        self.history.pop_front();
+                    }
+                    self.history.push_back(QuantumChange {
+                        time: rfc3339(now_ms),
+                        timestamp_ms: now_ms,
+                        driver: node.name.clone(),
+                        quantum: node.quantum,
+                        rate: node.rate,
+                    });
+                }
+            }
+        }
+        self.nodes = nodes;
+        self.sampled_ms = Some(now_ms);
+        self.error = None;
+    }
+
+    /// The statistics of the last sample
+    pub fn report(&self) -> XrunReport {
+        let nodes: Vec<NodeXruns> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let counter = self.counters.get(&node.name);
+                NodeXruns {
+                    id: node.id,
+                    name: node.name.clone(),
+                    state: node.state.clone(),
+                    driver_id: node.driver_id,
+                    quantum: node.quantum,
+                    rate: node.rate,
+                    errors: node.errors,
+                    xruns: counter.map(|c| c.xruns).unwrap_or(0),
+                    last_xrun: counter.and_then(|c| c.last_xrun_ms).map(rfc3339),
+                }
+            })
+            .collect();
+        XrunReport {
+            enabled: self.sample_secs > 0,
+            sample_secs: self.sample_secs,
+            sampled: self.sampled_ms.map(rfc3339),
+            error: self.error.clone(),
+            total_xruns: self.counters.values().map(|c| c.xruns).sum(),
+            nodes,
+            history: self.history.iter().cloned().collect(),
+        }
+    }
+}
+
+fn state() -> &'static Mutex<XrunState> {
+    static STATE: OnceLock<Mutex<XrunState>> = OnceLock::new();
+    STATE.get_or_init(Mutex::default)
+}
+
+/// The collected statistics
+pub fn report() -> XrunReport {
+    state().lock().unwrap().report()
+}
+
+/// Start sampling every `sample_secs`, `None` if 0
+pub fn start_xrun_stats(sample_secs: u64) -> Option<JoinHandle<()>> {
+    if sample_secs == 0 {
+        return None;
+    }
+    state().lock().unwrap().sample_secs = sample_secs;
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(sample_secs));
+        let mut failing = false;
+        loop {
+            ticker.tick().await;
+            let result = crate::worker::run(pwtop::sample)
+                .await
+                .map_err(|e| format!("Worker error: {}", e))
+                .and_then(|r| r);
+            let mut state = state().lock().unwrap();
+            match result {
+                Ok(nodes) => {
+                    state.update(nodes, crate::store::now_ms());
+                    failing = false;
+                }
+                Err(e) => {
+                    // Report the first failure, pw-top may not be installed
+                    if failing {
+                        debug!("xrun statistics: {}", e);
+                    } else {
+                        warn!("xrun statistics: {}", e);
+                    }
+                    state.error = Some(e);
+                    failing = true;
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: u32, name: &str, quantum: u32, errors: u64) -> TopNode {
+        TopNode {
+            id,
+            name: name.to_string(),
+            state: "R".to_string(),
+            driver_id: None,
+            quantum,
+            rate: 48000,
+            wait_us: None,
+            busy_us: None,
+            wait_quantum: None,
+            busy_quantum: None,
+            errors,
+            format: None,
+        }
+    }
+
+    #[test]
+    fn test_xrun_counting() {
+        let mut state = XrunState { sample_secs: 10, ..Default::default() };
+        // Errors from before the first sample are not counted
+        state.update(vec![node(37, "alsa_output.usb", 1024, 5)], 1_000);
+        assert_eq!(state.report().nodes[0].xruns, 0);
+
+        state.update(vec![node(37, "alsa_output.usb", 1024, 8)], 11_000);
+        let report = state.report();
+        assert_eq!((report.nodes[0].xruns, report.total_xruns), (3, 3));
+        assert_eq!(report.nodes[0].last_xrun.as_deref(), Some("1970-01-01T00:00:11Z"));
+
+        // Recreated node with a new ID and count
+        state.update(vec![node(90, "alsa_output.usb", 1024, 1)], 21_000);
+        assert_eq!(state.report().nodes[0].xruns, 4);
+    }
+
+    #[test]
+    fn test_quantum_history() {
+        let mut state = XrunState::default();
+        state.update(vec![node(37, "alsa_output.usb", 1024, 0)], 1_000);
+        state.update(vec![node(37, "alsa_output.usb", 1024, 0)], 2_000);
+        state.update(vec![node(37, "alsa_output.usb", 256, 0)], 3_000);
+        let mut idle = node(37, "alsa_output.usb", 128, 0);
+        idle.state = "S".to_string();
+        state.update(vec![idle], 4_000);
+
+        let report = state.report();
+        assert!(!report.enabled);
+        let quanta: Vec<u32> = report.history.iter().map(|c| c.quantum).collect();
+        assert_eq!(quanta, vec![1024, 256]);
+    }
+}
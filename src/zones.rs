@@ -0,0 +1,400 @@
+//! Zones for multi-room audio
+//!
+//! A zone is a named set of sinks that play the same source, e.g. all
+//! speakers on the ground floor. Each sink can have a trim (level offset in
+//! dB) and a delay to align rooms. Enabling a zone links its source to every
+//! sink; sinks with a delay are fed through a `pw-loopback` with the delay
+//! applied. The zone volume is applied to every sink, adjusted by its trim.
+
+use serde::{Deserialize, Serialize};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::link_manager_cli::{self, LinkData};
+use crate::linker::{LinkRule, LinkType, LogLevel, NodeIdentifier};
+
+/// Time to wait for a delay loopback node to appear
+const LOOPBACK_STARTUP_TIMEOUT: Duration = Duration::from_secs(3);
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+/// A sink that is part of a zone
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ZoneSink {
+    /// Sink node name (supports regex like link rules)
+    #[serde(rename = "node.name")]
+    pub node_name: String,
+    /// Level offset of this sink relative to the zone volume in dB
+    #[serde(default)]
+    pub trim_db: f32,
+    /// Delay of this sink in milliseconds
+    #[serde(default)]
+    pub delay_ms: u32,
+}
+
+/// Zone definition from zones.conf
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZoneConfig {
+    pub name: String,
+    /// Node that provides the audio for the zone
+    pub source: NodeIdentifier,
+    pub sinks: Vec<ZoneSink>,
+    /// Initial zone volume (0.0 - 1.5)
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    /// Enable the zone at startup
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// State of a sink in a zone
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneSinkStatus {
+    #[serde(rename = "node.name")]
+    pub node_name: String,
+    pub trim_db: f32,
+    pub delay_ms: u32,
+    /// ID of the sink node, if present
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<u32>,
+}
+
+/// State of a zone
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneStatus {
+    pub name: String,
+    pub enabled: bool,
+    pub volume: f32,
+    pub source: NodeIdentifier,
+    pub sinks: Vec<ZoneSinkStatus>,
+}
+
+struct Zone {
+    config: ZoneConfig,
+    enabled: bool,
+    volume: f32,
+    /// Running delay loopbacks
+    loopbacks: Vec<Child>,
+}
+
+/// Manages all configured zones
+#[derive(Default)]
+pub struct ZoneManager {
+    zones: Mutex<Vec<Zone>>,
+}
+
+/// Volume to set on a sink for a zone volume and trim
+pub fn sink_volume(zone_volume: f32, trim_db: f32) -> f32 {
+    (zone_volume * 10f32.powf(trim_db / 20.0)).clamp(0.0, 2.0)
+}
+
+/// Name of the loopback node delaying a sink of a zone
+pub fn loopback_name(zone: &str, index: usize) -> String {
+    format!("zone.{}.delay{}", zone, index)
+}
+
+fn exact(name: &str) -> NodeIdentifier {
+    NodeIdentifier {
+        node_name: Some(format!("^{}$", regex::escape(name))),
+        node_nick: None,
+        object_path: None,
+    }
+}
+
+fn pattern(name: &str) -> NodeIdentifier {
+    NodeIdentifier {
+        node_name: Some(name.to_string()),
+        node_nick: None,
+        object_path: None,
+    }
+}
+
+fn apply_rule(zone: &str, source: &NodeIdentifier, destination: NodeIdentifier, link_type: LinkType) -> Result<(), String> {
+    let rule = LinkRule {
+        name: format!("zone {}", zone),
+        source: source.clone(),
+        destination,
+        link_type,
+        link_at_startup: false,
+        relink_every: 0,
+        info_level: LogLevel::Info,
+        error_level: LogLevel::Error,
+        wait_for: None,
+        exclusive: false,
+        channel_map: None,
+        source_ports: None,
+        destination_ports: None,
+        include_monitors: false,
+    };
+    let failures: Vec<String> = link_manager_cli::apply_link_rule(&rule)?
+        .into_iter()
+        .filter(|r| !r.success)
+        .map(|r| r.message)
+        .collect();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
+/// Find the first node matching a sink name pattern
+fn find_sink(data: &LinkData, sink: &ZoneSink) -> Option<(u32, String)> {
+    data.find_matching_nodes(&pattern(&sink.node_name))
+        .first()
+        .map(|n| (n.id, n.display_name()))
+}
+
+/// Start a pw-loopback that delays audio into a sink and wait for its node
+fn start_loopback(name: &str, sink_name: &str, delay_ms: u32) -> Result<Child, String> {
+    let mut child = Command::new("pw-loopback")
+        .arg(format!("--delay={}", delay_ms as f32 / 1000.0))
+        .arg(format!("--capture-props=node.name={} media.class=Audio/Sink", name))
+        .arg(format!("--playback-props=node.name={}.out target.object={}", name, sink_name))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run pw-loopback: {}", e))?;
+
+    let start = Instant::now();
+    while start.elapsed() < LOOPBACK_STARTUP_TIMEOUT {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(format!("pw-loopback exited with {}", status));
+        }
+        if crate::pwcli::list_nodes()?.iter().any(|n| n.name() == Some(name)) {
+            return Ok(child);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    let _ = child.kill();
+    let _ = child.wait();
+    Err(format!("Loopback node '{}' did not appear", name))
+}
+
+impl Zone {
+    fn status(&self, data: Option<&LinkData>) -> ZoneStatus {
+        ZoneStatus {
+            name: self.config.name.clone(),
+            enabled: self.enabled,
+            volume: self.volume,
+            source: self.config.source.clone(),
+            sinks: self
+                .config
+                .sinks
+                .iter()
+                .map(|s| ZoneSinkStatus {
+                    node_name: s.node_name.clone(),
+                    trim_db: s.trim_db,
+                    delay_ms: s.delay_ms,
+                    node_id: data.and_then(|d| find_sink(d, s)).map(|(id, _)| id),
+                })
+                .collect(),
+        }
+    }
+
+    fn stop_loopbacks(&mut self) {
+        for mut child in self.loopbacks.drain(..) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    fn apply_volume(&self, data: &LinkData) -> Result<(), String> {
+        for sink in &self.config.sinks {
+            if let Some((id, _)) = find_sink(data, sink) {
+                crate::wpctl::set_volume(id, sink_volume(self.volume, sink.trim_db))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn enable(&mut self) -> Result<(), String> {
+        let data = LinkData::load()?;
+        let name = self.config.name.clone();
+        let mut errors = Vec::new();
+
+        for (index, sink) in self.config.sinks.iter().enumerate() {
+            let Some((_, sink_name)) = find_sink(&data, sink) else {
+                errors.push(format!("Sink '{}' not found", sink.node_name));
+                continue;
+            };
+
+            let result = if sink.delay_ms == 0 {
+                apply_rule(&name, &self.config.source, exact(&sink_name), LinkType::Link)
+            } else {
+                let loopback = loopback_name(&name, index);
+                start_loopback(&loopback, &sink_name, sink.delay_ms).and_then(|child| {
+                    self.loopbacks.push(child);
+                    apply_rule(&name, &self.config.source, exact(&loopback), LinkType::Link)
+                })
+            };
+            if let Err(e) = result {
+                errors.push(e);
+            }
+        }
+
+        self.enabled = true;
+        if let Err(e) = self.apply_volume(&data) {
+            errors.push(e);
+        }
+
+        if errors.is_empty() {
+            info!("Zone '{}' enabled", name);
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    fn disable(&mut self) -> Result<(), String> {
+        let data = LinkData::load()?;
+        let mut errors = Vec::new();
+
+        for sink in self.config.sinks.iter().filter(|s| s.delay_ms == 0) {
+            if let Some((_, sink_name)) = find_sink(&data, sink) {
+                if let Err(e) = apply_rule(&self.config.name, &self.config.source, exact(&sink_name), LinkType::Unlink) {
+                    errors.push(e);
+                }
+            }
+        }
+        // Links to loopbacks disappear with the loopback nodes
+        self.stop_loopbacks();
+        self.enabled = false;
+
+        if errors.is_empty() {
+            info!("Zone '{}' disabled", self.config.name);
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
+
+impl Drop for Zone {
+    fn drop(&mut self) {
+        self.stop_loopbacks();
+    }
+}
+
+impl ZoneManager {
+    pub fn new(configs: Vec<ZoneConfig>) -> Self {
+        let zones = configs
+            .into_iter()
+            .map(|config| Zone {
+                volume: config.volume,
+                enabled: false,
+                config,
+                loopbacks: Vec::new(),
+            })
+            .collect();
+        Self {
+            zones: Mutex::new(zones),
+        }
+    }
+
+    /// Enable all zones configured with `enabled: true`
+    pub fn enable_startup_zones(&self) {
+        let mut zones = self.zones.lock().unwrap();
+        for zone in zones.iter_mut().filter(|z| z.config.enabled) {
+            if let Err(e) = zone.enable() {
+                warn!("Failed to enable zone '{}': {}", zone.config.name, e);
+            }
+        }
+    }
+
+    /// Get the status of all zones
+    pub fn list(&self) -> Vec<ZoneStatus> {
+        let data = LinkData::load().ok();
+        self.zones.lock().unwrap().iter().map(|z| z.status(data.as_ref())).collect()
+    }
+
+    /// Get the status of a zone
+    pub fn get(&self, name: &str) -> Option<ZoneStatus> {
+        let data = LinkData::load().ok();
+        self.zones
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|z| z.config.name == name)
+            .map(|z| z.status(data.as_ref()))
+    }
+
+    fn with_zone<T>(&self, name: &str, f: impl FnOnce(&mut Zone) -> Result<T, String>) -> Result<Option<T>, String> {
+        let mut zones = self.zones.lock().unwrap();
+        match zones.iter_mut().find(|z| z.config.name == name) {
+            Some(zone) => f(zone).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Enable or disable a zone, returns None if the zone does not exist
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Result<Option<ZoneStatus>, String> {
+        self.with_zone(name, |zone| {
+            if enabled {
+                zone.disable().ok();
+                zone.enable()?;
+            } else {
+                zone.disable()?;
+            }
+            Ok(zone.status(LinkData::load().ok().as_ref()))
+        })
+    }
+
+    /// Set the volume of a zone, returns None if the zone does not exist
+    pub fn set_volume(&self, name: &str, volume: f32) -> Result<Option<ZoneStatus>, String> {
+        if !(0.0..=1.5).contains(&volume) {
+            return Err(format!("Volume {} out of range (0.0 - 1.5)", volume));
+        }
+        self.with_zone(name, |zone| {
+            zone.volume = volume;
+            let data = LinkData::load()?;
+            if zone.enabled {
+                zone.apply_volume(&data)?;
+            }
+            Ok(zone.status(Some(&data)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_zone_config() {
+        let json = r#"[{
+            "name": "kitchen",
+            "source": {"node.name": "^speakereq2x2\\.output$"},
+            "sinks": [
+                {"node.name": "alsa_output.usb-kitchen", "trim_db": -3.0},
+                {"node.name": "alsa_output.hdmi", "delay_ms": 25}
+            ]
+        }]"#;
+        let zones: Vec<ZoneConfig> = serde_json::from_str(json).unwrap();
+        assert_eq!(zones[0].name, "kitchen");
+        assert_eq!(zones[0].volume, 1.0);
+        assert!(!zones[0].enabled);
+        assert_eq!(zones[0].sinks[0].delay_ms, 0);
+        assert_eq!(zones[0].sinks[1].delay_ms, 25);
+    }
+
+    #[test]
+    fn test_sink_volume() {
+        assert_eq!(sink_volume(0.5, 0.0), 0.5);
+        assert!((sink_volume(1.0, -6.0) - 0.501).abs() < 0.001);
+        assert_eq!(sink_volume(1.5, 12.0), 2.0);
+    }
+
+    #[test]
+    fn test_unknown_zone() {
+        let manager = ZoneManager::new(Vec::new());
+        assert_eq!(manager.set_volume("attic", 0.5).unwrap().map(|s| s.name), None);
+        assert!(manager.set_volume("attic", 3.0).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+"""
+Tests for the raw parameter endpoints (/api/v1/nodes/:id/params/:type)
+"""
+
+import pytest
+import requests
+
+
+# Note: test_env fixture is provided by conftest.py (session-scoped)
+
+
+def _find_sink_id(base_url):
+    """Find the ID of any audio sink node"""
+    response = requests.get(f"{base_url}/api/v1/properties")
+    for obj in response.json()["objects"]:
+        if obj["type"] == "node" and obj["properties"].get("media.class") == "Audio/Sink":
+            return obj["id"]
+    return None
+
+
+class TestRawParams:
+    """Tests for GET/PUT /api/v1/nodes/:id/params/:type"""
+
+    def test_get_props_returns_named_keys(self, test_env):
+        """Props of a sink use named keys, not numeric ones"""
+        sink_id = _find_sink_id(test_env.base_url)
+        if sink_id is None:
+            pytest.skip("No audio sink available")
+
+        response = requests.get(f"{test_env.base_url}/api/v1/nodes/{sink_id}/params/props")
+        assert response.status_code == 200
+        data = response.json()
+        assert data["id"] == sink_id
+        assert data["type"] == "Props"
+        for param in data["params"]:
+            assert not any(k.startswith("prop_") for k in param["properties"])
+
+    def test_unknown_param_type_returns_400(self, test_env):
+        """Unknown parameter types are rejected"""
+        sink_id = _find_sink_id(test_env.base_url)
+        if sink_id is None:
+            pytest.skip("No audio sink available")
+
+        response = requests.get(f"{test_env.base_url}/api/v1/nodes/{sink_id}/params/bogus")
+        assert response.status_code == 400
+
+    def test_unknown_object_returns_404(self, test_env):
+        """Non-existent object IDs return 404"""
+        response = requests.get(f"{test_env.base_url}/api/v1/nodes/999999/params/Props")
+        assert response.status_code == 404
+
+    def test_put_read_only_type_returns_400(self, test_env):
+        """Read-only parameter types cannot be set"""
+        sink_id = _find_sink_id(test_env.base_url)
+        if sink_id is None:
+            pytest.skip("No audio sink available")
+
+        response = requests.put(
+            f"{test_env.base_url}/api/v1/nodes/{sink_id}/params/EnumFormat",
+            json={"rate": 48000}
+        )
+        assert response.status_code == 400